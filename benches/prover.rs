@@ -0,0 +1,180 @@
+//! Benchmarks of the stages of a Groth16 proof and of whole proofs, on the
+//! standard circuit of the `bench_prover` example. The sizes are kept small
+//! enough for criterion's repetitions, the example covers the large ones.
+
+#[path = "../examples/bench_prover/circuit.rs"]
+mod circuit;
+
+use std::sync::Arc;
+
+use bellperson::bls::{Bls12, Fr, G1Affine};
+use bellperson::domain::{EvaluationDomain, Scalar};
+use bellperson::groth16::{
+    create_random_proof_with_opts, generate_random_parameters, ProverOptions,
+};
+use bellperson::multicore::Worker;
+use bellperson::multiexp::{multiexp, FullDensity, SourceBuilder};
+use bellperson::SynthesisError;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ff::{Field, PrimeField};
+use groupy::CurveProjective;
+
+use circuit::Squarings;
+
+fn bench_fft(c: &mut Criterion) {
+    let worker = Worker::new();
+    let rng = &mut rand::thread_rng();
+
+    let mut group = c.benchmark_group("fft");
+    for &log_size in &[16, 18, 20] {
+        let coeffs = (0..1 << log_size)
+            .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+            .collect::<Vec<_>>();
+        let mut domain = EvaluationDomain::from_coeffs(coeffs).unwrap();
+
+        group.bench_function(BenchmarkId::from_parameter(log_size), |b| {
+            b.iter(|| domain.fft(&worker, &mut None).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_multiexp(c: &mut Criterion) {
+    let worker = Worker::new();
+    let rng = &mut rand::thread_rng();
+
+    let mut group = c.benchmark_group("multiexp");
+    for &log_size in &[16, 18, 20] {
+        let bases = Arc::new(
+            (0..1 << log_size)
+                .map(|_| <Bls12 as bellperson::bls::Engine>::G1::random(rng).into_affine())
+                .collect::<Vec<_>>(),
+        );
+        let exponents = Arc::new(
+            (0..1 << log_size)
+                .map(|_| Fr::random(rng).into_repr())
+                .collect::<Vec<_>>(),
+        );
+
+        group.bench_function(BenchmarkId::from_parameter(log_size), |b| {
+            b.iter(|| {
+                multiexp(
+                    &worker,
+                    (bases.clone(), 0),
+                    FullDensity,
+                    exponents.clone(),
+                    &mut None,
+                )
+                .wait()
+                .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// The bases in memory, but summed up in projective buckets as if they were
+/// read from a source, to compare the affine buckets against.
+#[derive(Clone)]
+struct ProjectiveBuckets(Arc<Vec<G1Affine>>);
+
+impl SourceBuilder<G1Affine> for ProjectiveBuckets {
+    type Source = (Arc<Vec<G1Affine>>, usize);
+
+    fn new(self) -> Self::Source {
+        (self.0, 0)
+    }
+
+    fn get(self) -> Result<(Arc<Vec<G1Affine>>, usize), SynthesisError> {
+        Ok((self.0, 0))
+    }
+
+    fn cheap_negation(&self) -> bool {
+        true
+    }
+}
+
+fn bench_multiexp_buckets(c: &mut Criterion) {
+    let worker = Worker::new();
+    let rng = &mut rand::thread_rng();
+
+    let mut group = c.benchmark_group("multiexp_buckets");
+    for &log_size in &[16, 18, 20] {
+        let bases = Arc::new(
+            (0..1 << log_size)
+                .map(|_| <Bls12 as bellperson::bls::Engine>::G1::random(rng).into_affine())
+                .collect::<Vec<_>>(),
+        );
+        let exponents = Arc::new(
+            (0..1 << log_size)
+                .map(|_| Fr::random(rng).into_repr())
+                .collect::<Vec<_>>(),
+        );
+
+        group.bench_function(BenchmarkId::new("affine", log_size), |b| {
+            b.iter(|| {
+                multiexp(
+                    &worker,
+                    (bases.clone(), 0),
+                    FullDensity,
+                    exponents.clone(),
+                    &mut None,
+                )
+                .wait()
+                .unwrap()
+            })
+        });
+        group.bench_function(BenchmarkId::new("projective", log_size), |b| {
+            b.iter(|| {
+                multiexp(
+                    &worker,
+                    ProjectiveBuckets(bases.clone()),
+                    FullDensity,
+                    exponents.clone(),
+                    &mut None,
+                )
+                .wait()
+                .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_proof(c: &mut Criterion) {
+    let rng = &mut rand::thread_rng();
+
+    let mut group = c.benchmark_group("proof");
+    group.sample_size(10);
+    for &log_size in &[10, 12, 14, 16] {
+        let params =
+            generate_random_parameters::<Bls12, _, _>(Squarings::of_log_size(log_size, None), rng)
+                .unwrap();
+
+        for &(device, ref opts) in &[
+            ("cpu", ProverOptions::cpu()),
+            ("gpu", ProverOptions::default()),
+        ] {
+            if device == "gpu" && !cfg!(feature = "gpu") {
+                continue;
+            }
+
+            group.bench_function(BenchmarkId::new(device, log_size), |b| {
+                b.iter(|| {
+                    let circuit = Squarings::of_log_size(log_size, Some(Fr::one()));
+                    create_random_proof_with_opts(circuit, &params, rng, opts).unwrap()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_fft,
+    bench_multiexp,
+    bench_multiexp_buckets,
+    bench_proof
+);
+criterion_main!(benches);
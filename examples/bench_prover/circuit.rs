@@ -0,0 +1,52 @@
+use bellperson::bls::Engine;
+use bellperson::{Circuit, ConstraintSystem, SynthesisError};
+use ff::Field;
+
+/// A chain of squarings of a secret, the last square is the public input.
+/// Together with the constraints the prover adds for the two inputs, it fills
+/// a domain of 2<sup>k</sup> elements exactly.
+pub struct Squarings<E: Engine> {
+    pub x: Option<E::Fr>,
+    pub num_squarings: usize,
+}
+
+impl<E: Engine> Squarings<E> {
+    /// A circuit with an evaluation domain of 2<sup>`log_size`</sup> elements.
+    pub fn of_log_size(log_size: u32, x: Option<E::Fr>) -> Self {
+        Squarings {
+            x,
+            num_squarings: (1 << log_size) - 2,
+        }
+    }
+}
+
+impl<E: Engine> Circuit<E> for Squarings<E> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mut value = self.x;
+        let mut var = cs.alloc(|| "x", || value.ok_or(SynthesisError::AssignmentMissing))?;
+
+        for i in 0..self.num_squarings {
+            let square = value.map(|mut v| {
+                v.square();
+                v
+            });
+            let assignment = || square.ok_or(SynthesisError::AssignmentMissing);
+            let square_var = if i + 1 == self.num_squarings {
+                cs.alloc_input(|| "result", assignment)?
+            } else {
+                cs.alloc(|| format!("square {}", i), assignment)?
+            };
+            cs.enforce(
+                || format!("squaring {}", i),
+                |lc| lc + var,
+                |lc| lc + var,
+                |lc| lc + square_var,
+            );
+
+            value = square;
+            var = square_var;
+        }
+
+        Ok(())
+    }
+}
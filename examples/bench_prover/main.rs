@@ -0,0 +1,116 @@
+//! Times the stages of proofs of the standard circuit sizes, on the CPU and,
+//! when built with the `gpu` feature, on the GPU:
+//!
+//! ```text
+//! cargo run --release --example bench_prover --features gpu -- 16 20
+//! ```
+//!
+//! The arguments are the base-2 logarithms of the circuit sizes, all of
+//! 2<sup>16</sup> to 2<sup>26</sup> by default. Generating the parameters of
+//! the larger sizes takes much longer than proving.
+//!
+//! The time of a stage is the time since the previous one finished. The
+//! multiexps run concurrently, so a multiexp stage is the time spent waiting
+//! for it after the previous ones.
+
+mod circuit;
+
+use std::cell::{Cell, RefCell};
+use std::env;
+use std::time::{Duration, Instant};
+
+use bellperson::bls::{Bls12, Fr};
+use bellperson::groth16::{
+    create_random_proof_with_opts, generate_random_parameters, prepare_verifying_key, verify_proof,
+    Parameters, ProverOptions, ProvingStage,
+};
+use ff::Field;
+
+use circuit::Squarings;
+
+const STANDARD_SIZES: [u32; 6] = [16, 18, 20, 22, 24, 26];
+
+fn main() {
+    let sizes = env::args()
+        .skip(1)
+        .map(|arg| arg.parse().expect("sizes are base-2 logarithms, e.g. 20"))
+        .collect::<Vec<u32>>();
+    let sizes = if sizes.is_empty() {
+        STANDARD_SIZES.to_vec()
+    } else {
+        sizes
+    };
+
+    for log_size in sizes {
+        println!("2^{} constraints", log_size);
+
+        let started = Instant::now();
+        let params = generate_random_parameters::<Bls12, _, _>(
+            Squarings::of_log_size(log_size, None),
+            &mut rand::thread_rng(),
+        )
+        .unwrap();
+        println!("  parameters: {} ms", started.elapsed().as_millis());
+
+        let cpu = time_stages(&params, log_size, ProverOptions::cpu());
+        let gpu = if cfg!(feature = "gpu") {
+            Some(time_stages(&params, log_size, ProverOptions::default()))
+        } else {
+            None
+        };
+
+        println!("  {:<20} {:>10} {:>10}", "stage", "cpu ms", "gpu ms");
+        for (i, (stage, elapsed)) in cpu.iter().enumerate() {
+            let gpu = match gpu {
+                Some(ref gpu) => gpu[i].1.as_millis().to_string(),
+                None => "-".to_string(),
+            };
+            println!("  {:<20} {:>10} {:>10}", stage, elapsed.as_millis(), gpu);
+        }
+    }
+}
+
+/// Proves once, returns the time of every stage and the total.
+fn time_stages(
+    params: &Parameters<Bls12>,
+    log_size: u32,
+    opts: ProverOptions,
+) -> Vec<(String, Duration)> {
+    let stages = RefCell::new(vec![]);
+    let last = Cell::new(Instant::now());
+    let sink = |stage: ProvingStage, _: f64| {
+        let now = Instant::now();
+        stages
+            .borrow_mut()
+            .push((stage.to_string(), now.duration_since(last.get())));
+        last.set(now);
+    };
+    let opts = ProverOptions {
+        progress: Some(&sink),
+        ..opts
+    };
+
+    let rng = &mut rand::thread_rng();
+    let x = Fr::random(rng);
+    let started = Instant::now();
+    last.set(started);
+    let proof = create_random_proof_with_opts(
+        Squarings::of_log_size(log_size, Some(x)),
+        params,
+        rng,
+        &opts,
+    )
+    .unwrap();
+    let total = started.elapsed();
+
+    let mut result = x;
+    for _ in 0..(1 << log_size) - 2 {
+        result.square();
+    }
+    let pvk = prepare_verifying_key(&params.vk);
+    assert!(verify_proof(&pvk, &proof, &[result]).unwrap());
+
+    let mut stages = stages.into_inner();
+    stages.push(("total".to_string(), total));
+    stages
+}
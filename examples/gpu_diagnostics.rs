@@ -0,0 +1,63 @@
+//! Checks the GPUs the provers use and tells which ones fail which check:
+//!
+//! ```text
+//! cargo run --release --example gpu_diagnostics --features gpu
+//! ```
+//!
+//! It exits with an error if a GPU fails, or if there is none to check.
+
+use std::process;
+
+use bellperson::bls::Bls12;
+use bellperson::gpu::{self, Check};
+
+fn main() {
+    let diagnostics = gpu::diagnostics::<Bls12>();
+    if diagnostics.is_empty() {
+        println!("No GPU is used by the provers.");
+        let devices = gpu::devices();
+        if devices.is_empty() {
+            println!("There are no OpenCL devices, or BELLMAN_NO_GPU is set.");
+        } else {
+            println!("Only GPUs on the NVIDIA CUDA platform are used, the others are:");
+            for device in devices {
+                println!("  {} ({}, {})", device.name, device.vendor, device.backend);
+            }
+        }
+        process::exit(1);
+    }
+
+    let mut failed = false;
+    for device in diagnostics {
+        match device.info {
+            Some(ref info) => println!(
+                "GPU {}: {} ({} MiB, {} compute units)",
+                device.index,
+                info.name,
+                info.memory >> 20,
+                info.compute_units
+            ),
+            None => println!("GPU {}: cannot be queried", device.index),
+        }
+        print_check("build", &device.build);
+        print_check("fft", &device.fft);
+        print_check("multiexp", &device.multiexp);
+        failed |= !device.passed();
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn print_check(name: &str, check: &Check) {
+    match check.error {
+        None => println!("  {}: ok ({} ms)", name, check.duration.as_millis()),
+        Some(ref error) => println!(
+            "  {}: FAILED ({} ms): {}",
+            name,
+            check.duration.as_millis(),
+            error
+        ),
+    }
+}
@@ -0,0 +1,73 @@
+//! Writes the test vectors of the reference circuits into a directory, or
+//! checks them against the files in it, e.g. ones written by an older version:
+//!
+//! ```text
+//! cargo run --release --example test_vectors --features testing -- write vectors/
+//! cargo run --release --example test_vectors --features testing -- check vectors/
+//! ```
+//!
+//! `compat` checks that this build creates the exact proofs of the
+//! compatibility gate, on the GPU with the `gpu` feature:
+//!
+//! ```text
+//! cargo run --release --example test_vectors --features gpu,testing -- compat
+//! ```
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+use bellperson::circuits::{compat, vectors};
+use bellperson::groth16::ProverOptions;
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let (command, dir) = match &args[..] {
+        [command] if command == "compat" => check_compat(),
+        [command, dir] => (command.as_str(), Path::new(dir)),
+        _ => usage(),
+    };
+
+    let mut failed = false;
+    for vector in vectors::all() {
+        match command {
+            "write" => {
+                vector.write_to(dir).expect("writing the vectors failed");
+                println!("{}: written", vector.name);
+            }
+            "check" => match vector.check_against(dir) {
+                Ok(ref differing) if differing.is_empty() => println!("{}: ok", vector.name),
+                Ok(differing) => {
+                    println!("{}: {} differ", vector.name, differing.join(", "));
+                    failed = true;
+                }
+                Err(e) => {
+                    println!("{}: {}", vector.name, e);
+                    failed = true;
+                }
+            },
+            _ => usage(),
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn check_compat() -> ! {
+    match compat::check(&ProverOptions::default()) {
+        Ok(ref failed) if failed.is_empty() => {
+            println!("compatible");
+            process::exit(0);
+        }
+        Ok(failed) => println!("{} differ", failed.join(", ")),
+        Err(e) => println!("proving failed: {}", e),
+    }
+    process::exit(1);
+}
+
+fn usage() -> ! {
+    eprintln!("usage: test_vectors ((write|check) <directory> | compat)");
+    process::exit(2);
+}
@@ -0,0 +1,19 @@
+#![no_main]
+use bellperson::bls::Bls12;
+use bellperson::groth16::Parameters;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // The first byte picks whether the points are checked to be on the curve
+    // and in the subgroup.
+    let (checked, data) = match data.split_first() {
+        Some((first, rest)) => (first & 1 == 1, rest),
+        None => return,
+    };
+
+    if let Ok(params) = Parameters::<Bls12>::read(data, checked) {
+        let mut encoded = vec![];
+        params.write(&mut encoded).unwrap();
+        assert!(Parameters::<Bls12>::read(&encoded[..], checked).unwrap() == params);
+    }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+use bellperson::bls::Bls12;
+use bellperson::groth16::Proof;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(proof) = Proof::<Bls12>::read(data) {
+        // Encodings are canonical, the proof is the bytes it was read from.
+        let mut encoded = vec![];
+        proof.write(&mut encoded).unwrap();
+        assert_eq!(&encoded[..], &data[..encoded.len()]);
+    }
+});
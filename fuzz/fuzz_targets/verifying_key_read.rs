@@ -0,0 +1,15 @@
+#![no_main]
+use bellperson::bls::Bls12;
+use bellperson::groth16::{prepare_verifying_key, VerifyingKey};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(vk) = VerifyingKey::<Bls12>::read(data) {
+        let mut encoded = vec![];
+        vk.write(&mut encoded).unwrap();
+        assert_eq!(&encoded[..], &data[..encoded.len()]);
+
+        // Whatever is read can be prepared for verification.
+        prepare_verifying_key(&vk);
+    }
+});
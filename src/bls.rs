@@ -0,0 +1,35 @@
+//! The pairing engine traits and the BLS12-381 types of the selected backend:
+//! [`paired`] with the `pairing` feature, which is the default, or [`blstrs`],
+//! which is backed by [blst], with the `blst` feature. The rest of the crate
+//! uses them only through this module.
+//!
+//! The GPU kernels rely on the memory layout of `paired`'s points, so the
+//! `gpu` feature requires the `pairing` backend.
+//!
+//! [`paired`]: https://docs.rs/paired
+//! [`blstrs`]: https://docs.rs/blstrs
+//! [blst]: https://github.com/supranational/blst
+
+#[cfg(not(any(feature = "pairing", feature = "blst")))]
+compile_error!("either the `pairing` or the `blst` feature is required");
+
+#[cfg(all(feature = "pairing", feature = "blst"))]
+compile_error!("the `pairing` and `blst` features are mutually exclusive");
+
+#[cfg(all(feature = "gpu", feature = "blst"))]
+compile_error!("the `gpu` feature requires the `pairing` backend");
+
+#[cfg(feature = "pairing")]
+pub use paired::bls12_381::{
+    Bls12, Fq, Fq12, Fq2, FqRepr, Fr, FrRepr, G1Affine, G1Compressed, G1Uncompressed, G2Affine,
+    G2Compressed, G2Uncompressed, G1, G2,
+};
+#[cfg(feature = "pairing")]
+pub use paired::{Engine, PairingCurveAffine};
+
+#[cfg(feature = "blst")]
+pub use blstrs::{
+    Bls12, Engine, Fp as Fq, Fp12 as Fq12, Fp2 as Fq2, FpRepr as FqRepr, G1Affine, G1Compressed,
+    G1Projective as G1, G1Uncompressed, G2Affine, G2Compressed, G2Projective as G2, G2Uncompressed,
+    PairingCurveAffine, Scalar as Fr, ScalarRepr as FrRepr,
+};
@@ -0,0 +1,17 @@
+//! Reference circuits, written to show how circuits are built from the
+//! gadgets. Each comes with the computation of its public inputs outside of
+//! the circuit.
+//!
+//! With the `groth16` and `testing` features, the `vectors` module proves them
+//! with fixed parameters, which makes their serializations test vectors that stay the
+//! same across versions of the crate, see the `test_vectors` example. The
+//! `compat` module checks that a build creates proofs with the exact bytes
+//! they must have.
+
+#[cfg(all(feature = "groth16", any(test, feature = "testing")))]
+pub mod compat;
+pub mod merkle;
+pub mod multiplier;
+pub mod sha256;
+#[cfg(all(feature = "groth16", any(test, feature = "testing")))]
+pub mod vectors;
@@ -0,0 +1,144 @@
+//! A compatibility gate for builds of the prover: fixed circuits proven with
+//! the parameters and blinding factors of
+//! [`fixtures`](crate::testing::fixtures), together with the exact bytes
+//! their proofs must have. The bytes were computed from the toxic waste
+//! independently of the prover, so a build that proves differently, e.g. on a
+//! GPU that computes wrong results or with another curve backend, fails
+//! [`check`] instead of creating proofs nobody else reproduces.
+
+use ff::{Field, PrimeField};
+
+use super::multiplier::Multiplier;
+use crate::bls::{Bls12, Fr};
+use crate::groth16::{prepare_verifying_key, verify_proof, ProverOptions};
+use crate::testing::fixtures;
+use crate::{Circuit, ConstraintSystem, SynthesisError};
+
+/// The proof of the product of 3 and 11 with [`Multiplier`].
+pub const MULTIPLIER_PROOF: &str = "\
+    8d2e12e4a104ad3175704c6df16aee2b755a5748aefe4423a0f66130279d46580823720f3cf8f1ffb5de058434492943\
+    b008d00b79c023eb72b87826b409bd18b8ac36d337d343bed42ab653b57f84a44b94028243d41105404f856dbb29bd7f\
+    17ac4ea268b28d8e6d2449e609334e49d28d4e9a422fb7a1cb40515ac3bede4d0b8ffe07792beb766437ded089ed7b53\
+    9043c71101c9d3ac18a0170c3d8e898c1a50116388a5a93675e21c067fc67584f7ad78dcc872fd76f034ab68a0926e2a";
+
+/// The number of squarings of the squarings vector, which fill an evaluation
+/// domain of 1024 together with the constraint of the output and the ones of
+/// the two inputs.
+pub const SQUARINGS: usize = 1021;
+
+/// The proof of 3 squared [`SQUARINGS`] times.
+pub const SQUARINGS_PROOF: &str = "\
+    a270ed0c74562b4f1ae5dcf2474218221c86c6a3bf9d3b413cab15adbebb88cbd06f02924f5719d3b3c8214c51c97400\
+    82486f7e886ddc7359b3bacf1d79296af235475c95b8921c148824ab1ee3af6fecfc6421d4e1c96d52eb47af44aa3a73\
+    15ab9f6f813304a846d6f492c0629498ef094e9b683c93a2cdd204d4150766d1e3070574cc461d4aee130a2fcc5e80bd\
+    a95c4ede239801652c6167e9bd7e08b8fd9b678d32f5e06617c89fa01b855a500d5b77e6b24f008a5923a810a5819228";
+
+/// Squares `x` again and again, with a constraint per squaring, and exposes
+/// the result as the only public input.
+struct Squarings {
+    x: Option<Fr>,
+    n: usize,
+}
+
+impl Circuit<Bls12> for Squarings {
+    fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mut value = self.x;
+        let mut var = cs.alloc(|| "x", || value.ok_or(SynthesisError::AssignmentMissing))?;
+        for i in 0..self.n {
+            value = value.map(|mut value| {
+                value.square();
+                value
+            });
+            let next = cs.alloc(
+                || format!("x^(2^{})", i + 1),
+                || value.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            cs.enforce(
+                || format!("squaring {}", i),
+                |lc| lc + var,
+                |lc| lc + var,
+                |lc| lc + next,
+            );
+            var = next;
+        }
+
+        let out = cs.alloc_input(|| "out", || value.ok_or(SynthesisError::AssignmentMissing))?;
+        cs.enforce(|| "out", |lc| lc + var, |lc| lc + CS::one(), |lc| lc + out);
+
+        Ok(())
+    }
+}
+
+/// Proves the vectors with `opts` and returns the names of the ones whose
+/// proofs differ from the expected bytes or don't verify. An empty list means
+/// the build is compatible.
+pub fn check(opts: &ProverOptions) -> Result<Vec<&'static str>, SynthesisError> {
+    let mut failed = vec![];
+
+    let (a, b) = (fr("3"), fr("11"));
+    if !reproduces(
+        MULTIPLIER_PROOF,
+        Multiplier { a: None, b: None },
+        Multiplier {
+            a: Some(a),
+            b: Some(b),
+        },
+        &Multiplier::<Bls12>::public_inputs(a, b),
+        opts,
+    )? {
+        failed.push("multiplier");
+    }
+
+    let mut out = fr("3");
+    for _ in 0..SQUARINGS {
+        out.square();
+    }
+    if !reproduces(
+        SQUARINGS_PROOF,
+        Squarings {
+            x: None,
+            n: SQUARINGS,
+        },
+        Squarings {
+            x: Some(fr("3")),
+            n: SQUARINGS,
+        },
+        &[out],
+        opts,
+    )? {
+        failed.push("squarings");
+    }
+
+    Ok(failed)
+}
+
+/// Whether the proof of `circuit` has the `expected` bytes and verifies.
+fn reproduces<C: Circuit<Bls12>>(
+    expected: &str,
+    blank: C,
+    circuit: C,
+    public_inputs: &[Fr],
+    opts: &ProverOptions,
+) -> Result<bool, SynthesisError> {
+    let params = fixtures::parameters(blank)?;
+    let proof = fixtures::proof_with_opts(circuit, &params, opts)?;
+    let pvk = prepare_verifying_key(&params.vk);
+
+    Ok(proof.to_hex() == expected && verify_proof(&pvk, &proof, public_inputs)?)
+}
+
+fn fr(s: &str) -> Fr {
+    Fr::from_str(s).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compat() {
+        assert!(check(&ProverOptions::cpu()).unwrap().is_empty());
+        // On the GPU with the `gpu` feature.
+        assert!(check(&ProverOptions::default()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+//! Membership of a secret leaf in a Merkle tree with a public root.
+
+use ff::ScalarEngine;
+
+use crate::gadgets::boolean::{AllocatedBit, Boolean};
+use crate::gadgets::merkle::{compute_root, compute_root_circuit};
+use crate::gadgets::num::AllocatedNum;
+use crate::gadgets::poseidon::PoseidonConstants;
+use crate::{Circuit, ConstraintSystem, SynthesisError};
+
+/// Proves that the prover knows a leaf and a path of `depth` steps from it to
+/// the public root of a tree hashed with Poseidon of width 3. A step is a
+/// sibling and whether the current node is the right child, see
+/// [`merkle`](crate::gadgets::merkle).
+pub struct MerkleMembership<'a, E: ScalarEngine> {
+    pub constants: &'a PoseidonConstants<E>,
+    pub leaf: Option<E::Fr>,
+    pub path: Option<Vec<(E::Fr, bool)>>,
+    pub depth: usize,
+}
+
+impl<'a, E: ScalarEngine> MerkleMembership<'a, E> {
+    /// The public inputs of a proof for the leaf and path.
+    pub fn public_inputs(
+        constants: &PoseidonConstants<E>,
+        leaf: &E::Fr,
+        path: &[(E::Fr, bool)],
+    ) -> Vec<E::Fr> {
+        vec![compute_root(constants, leaf, path)]
+    }
+}
+
+impl<'a, E: ScalarEngine> Circuit<E> for MerkleMembership<'a, E> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let steps = match self.path {
+            Some(path) => {
+                assert_eq!(path.len(), self.depth, "the path has the wrong length");
+                path.into_iter()
+                    .map(|(sibling, is_right)| (Some(sibling), Some(is_right)))
+                    .collect()
+            }
+            None => vec![(None, None); self.depth],
+        };
+
+        let leaf = AllocatedNum::alloc(cs.namespace(|| "leaf"), || {
+            self.leaf.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let path = steps
+            .into_iter()
+            .enumerate()
+            .map(|(i, (sibling, is_right))| {
+                let sibling =
+                    AllocatedNum::alloc(cs.namespace(|| format!("sibling {}", i)), || {
+                        sibling.ok_or(SynthesisError::AssignmentMissing)
+                    })?;
+                let is_right =
+                    AllocatedBit::alloc(cs.namespace(|| format!("is right {}", i)), is_right)?;
+                Ok((sibling, Boolean::from(is_right)))
+            })
+            .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        let root = compute_root_circuit(cs.namespace(|| "root"), self.constants, &leaf, &path)?;
+        root.inputize(cs.namespace(|| "root input"))
+    }
+}
@@ -0,0 +1,36 @@
+//! Knowledge of two factors of a public number.
+
+use ff::{Field, ScalarEngine};
+
+use crate::gadgets::num::AllocatedNum;
+use crate::{Circuit, ConstraintSystem, SynthesisError};
+
+/// Proves that the prover knows `a` and `b` with `a * b = c` for the public
+/// input `c`.
+pub struct Multiplier<E: ScalarEngine> {
+    pub a: Option<E::Fr>,
+    pub b: Option<E::Fr>,
+}
+
+impl<E: ScalarEngine> Multiplier<E> {
+    /// The public inputs of a proof for the factors `a` and `b`.
+    pub fn public_inputs(a: E::Fr, b: E::Fr) -> Vec<E::Fr> {
+        let mut c = a;
+        c.mul_assign(&b);
+        vec![c]
+    }
+}
+
+impl<E: ScalarEngine> Circuit<E> for Multiplier<E> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || {
+            self.a.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || {
+            self.b.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let c = a.mul(cs.namespace(|| "a * b"), &b)?;
+        c.inputize(cs.namespace(|| "c"))
+    }
+}
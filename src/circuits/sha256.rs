@@ -0,0 +1,54 @@
+//! Knowledge of the preimage of a SHA-256 hash.
+
+use ff::ScalarEngine;
+
+use crate::gadgets::boolean::{AllocatedBit, Boolean};
+use crate::gadgets::multipack;
+use crate::gadgets::sha256::sha256;
+use crate::{Circuit, ConstraintSystem, SynthesisError};
+
+/// Proves that the prover knows a preimage of `len` bytes of the public hash.
+/// The hash is packed into public inputs in the order of its bits, most
+/// significant bit of each byte first.
+pub struct Sha256Preimage {
+    pub preimage: Option<Vec<u8>>,
+    pub len: usize,
+}
+
+impl Sha256Preimage {
+    /// The public inputs of a proof for a preimage of `hash`.
+    pub fn public_inputs<E: ScalarEngine>(hash: &[u8; 32]) -> Vec<E::Fr> {
+        multipack::compute_multipacking::<E>(&multipack::bytes_to_bits(hash))
+    }
+}
+
+impl<E: ScalarEngine> Circuit<E> for Sha256Preimage {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let bits = match self.preimage {
+            Some(preimage) => {
+                assert_eq!(
+                    preimage.len(),
+                    self.len,
+                    "the preimage has the wrong length"
+                );
+                multipack::bytes_to_bits(&preimage)
+                    .into_iter()
+                    .map(Some)
+                    .collect()
+            }
+            None => vec![None; self.len * 8],
+        };
+
+        let preimage = bits
+            .into_iter()
+            .enumerate()
+            .map(|(i, bit)| {
+                AllocatedBit::alloc(cs.namespace(|| format!("preimage bit {}", i)), bit)
+                    .map(Boolean::from)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let hash = sha256(cs.namespace(|| "sha256"), &preimage)?;
+        multipack::pack_into_inputs(cs.namespace(|| "hash"), &hash)
+    }
+}
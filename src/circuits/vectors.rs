@@ -0,0 +1,210 @@
+//! Groth16 proofs of the reference circuits, with fixed parameters and
+//! blinding factors. Their serializations only change if the serialization
+//! formats, the circuits or the prover change, so versions of the crate can be
+//! checked against the files of each other, see the `test_vectors` example.
+//!
+//! The parameters come from the toxic waste of
+//! [`fixtures`](crate::testing::fixtures), they are good for testing only.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use ff::{PrimeField, PrimeFieldRepr};
+
+use super::merkle::MerkleMembership;
+use super::multiplier::Multiplier;
+use super::sha256::Sha256Preimage;
+use crate::bls::{Bls12, Fr};
+use crate::gadgets::poseidon::PoseidonConstants;
+use crate::groth16::{Parameters, Proof};
+use crate::testing::fixtures;
+use crate::Circuit;
+
+/// The preimage of the SHA-256 vector, with its hash from FIPS 180-2.
+pub const SHA256_PREIMAGE: &[u8] = b"abc";
+pub const SHA256_HASH: [u8; 32] = [
+    0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae, 0x22, 0x23,
+    0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00, 0x15, 0xad,
+];
+
+/// The depth of the tree of the Merkle vector.
+pub const MERKLE_DEPTH: usize = 4;
+
+/// A reference circuit proven with fixed parameters.
+pub struct TestVector {
+    pub name: &'static str,
+    pub params: Parameters<Bls12>,
+    pub proof: Proof<Bls12>,
+    pub public_inputs: Vec<Fr>,
+}
+
+impl TestVector {
+    fn new<C: Circuit<Bls12>>(
+        name: &'static str,
+        blank: C,
+        circuit: C,
+        public_inputs: Vec<Fr>,
+    ) -> TestVector {
+        let params = fixtures::parameters(blank).expect("the reference circuits are valid");
+        let proof =
+            fixtures::proof(circuit, &params).expect("the reference circuits are satisfied");
+
+        TestVector {
+            name,
+            params,
+            proof,
+            public_inputs,
+        }
+    }
+
+    /// The files of the vector with their contents: the parameters, the proof
+    /// and the public inputs as big-endian scalars.
+    pub fn files(&self) -> io::Result<Vec<(String, Vec<u8>)>> {
+        let mut params = vec![];
+        self.params.write(&mut params)?;
+        let mut proof = vec![];
+        self.proof.write(&mut proof)?;
+        let mut inputs = vec![];
+        for input in &self.public_inputs {
+            input.into_repr().write_be(&mut inputs)?;
+        }
+
+        Ok(vec![
+            (format!("{}.params", self.name), params),
+            (format!("{}.proof", self.name), proof),
+            (format!("{}.inputs", self.name), inputs),
+        ])
+    }
+
+    pub fn write_to(&self, dir: &Path) -> io::Result<()> {
+        for (name, contents) in self.files()? {
+            fs::write(dir.join(name), contents)?;
+        }
+
+        Ok(())
+    }
+
+    /// Compares the vector with the files in `dir`, returns the names of the
+    /// files that differ.
+    pub fn check_against(&self, dir: &Path) -> io::Result<Vec<String>> {
+        let mut differing = vec![];
+        for (name, contents) in self.files()? {
+            if fs::read(dir.join(&name))? != contents {
+                differing.push(name);
+            }
+        }
+
+        Ok(differing)
+    }
+}
+
+/// The product of 3 and 11.
+pub fn multiplier() -> TestVector {
+    let (a, b) = (fr("3"), fr("11"));
+
+    TestVector::new(
+        "multiplier",
+        Multiplier { a: None, b: None },
+        Multiplier {
+            a: Some(a),
+            b: Some(b),
+        },
+        Multiplier::<Bls12>::public_inputs(a, b),
+    )
+}
+
+/// The hash of [`SHA256_PREIMAGE`].
+pub fn sha256() -> TestVector {
+    TestVector::new(
+        "sha256",
+        Sha256Preimage {
+            preimage: None,
+            len: SHA256_PREIMAGE.len(),
+        },
+        Sha256Preimage {
+            preimage: Some(SHA256_PREIMAGE.to_vec()),
+            len: SHA256_PREIMAGE.len(),
+        },
+        Sha256Preimage::public_inputs::<Bls12>(&SHA256_HASH),
+    )
+}
+
+/// The leaf 1 with the siblings 2, 3, 4 and 5 on the way up, alternating
+/// between left and right child.
+pub fn merkle() -> TestVector {
+    let constants = PoseidonConstants::<Bls12>::new(3);
+    let leaf = fr("1");
+    let path = (0..MERKLE_DEPTH)
+        .map(|i| (fr(&(i + 2).to_string()), i % 2 == 1))
+        .collect::<Vec<_>>();
+    let public_inputs = MerkleMembership::public_inputs(&constants, &leaf, &path);
+
+    TestVector::new(
+        "merkle",
+        MerkleMembership {
+            constants: &constants,
+            leaf: None,
+            path: None,
+            depth: MERKLE_DEPTH,
+        },
+        MerkleMembership {
+            constants: &constants,
+            leaf: Some(leaf),
+            path: Some(path),
+            depth: MERKLE_DEPTH,
+        },
+        public_inputs,
+    )
+}
+
+pub fn all() -> Vec<TestVector> {
+    vec![multiplier(), sha256(), merkle()]
+}
+
+fn fr(s: &str) -> Fr {
+    Fr::from_str(s).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::{prepare_verifying_key, verify_proof};
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_sha256_hash() {
+        assert_eq!(Sha256::digest(SHA256_PREIMAGE).as_slice(), &SHA256_HASH[..]);
+    }
+
+    #[test]
+    fn test_vectors_verify() {
+        for vector in all() {
+            let pvk = prepare_verifying_key(&vector.params.vk);
+            assert!(
+                verify_proof(&pvk, &vector.proof, &vector.public_inputs).unwrap(),
+                "{}",
+                vector.name
+            );
+
+            let files = vector.files().unwrap();
+            let proof = Proof::<Bls12>::read(&files[1].1[..]).unwrap();
+            assert!(proof == vector.proof);
+        }
+    }
+
+    #[test]
+    fn test_vectors_reproducible() {
+        let dir = std::env::temp_dir().join(format!("bellperson-vectors-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        multiplier().write_to(&dir).unwrap();
+        assert!(multiplier().check_against(&dir).unwrap().is_empty());
+        assert_eq!(
+            merkle().check_against(&dir).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
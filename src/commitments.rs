@@ -0,0 +1,4 @@
+//! Commitment schemes and the arguments about committed values.
+
+pub mod ipa;
+pub mod kzg;
@@ -0,0 +1,393 @@
+//! A [Bulletproofs] inner-product argument, which proves that a commitment
+//!
+//! ```text
+//! P = <a, G> + <b, H> + <a, b>·U
+//! ```
+//!
+//! is to vectors `a` and `b` of length `n`, a power of two, with `2·log2(n)`
+//! points and two scalars. The generators `G`, `H` and `U` must have no known
+//! discrete logarithms relative to each other, e.g. from [`generators`].
+//!
+//! The argument works over any group of the crate's curves, so it can be
+//! used on its own or as the inner argument of larger protocols, like the
+//! aggregation of proofs. Its challenges are drawn from a [`Transcript`],
+//! which the caller may have bound to the statement before already.
+//!
+//! Proving folds the vectors and generators in half `log2(n)` times, while
+//! verifying folds the generators implicitly into a single multiexp of
+//! length `2·n`.
+//!
+//! [Bulletproofs]: https://eprint.iacr.org/2017/1066.pdf
+//! [`Transcript`]: crate::transcript::Transcript
+
+use std::io::{self, Read, Write};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use ff::{Field, PrimeField, PrimeFieldRepr};
+use groupy::{CurveAffine, CurveProjective, EncodedPoint};
+
+use crate::transcript::Transcript;
+
+/// The most rounds of a proof that is read, i.e. vectors of up to `2^32`
+/// elements.
+const MAX_ROUNDS: usize = 32;
+
+/// The cross terms of every round and the folded vectors of length one.
+#[derive(Clone)]
+pub struct Proof<G: CurveAffine> {
+    pub l: Vec<G>,
+    pub r: Vec<G>,
+    pub a: G::Scalar,
+    pub b: G::Scalar,
+}
+
+impl<G: CurveAffine> PartialEq for Proof<G> {
+    fn eq(&self, other: &Self) -> bool {
+        self.l == other.l && self.r == other.r && self.a == other.a && self.b == other.b
+    }
+}
+
+impl<G: CurveAffine> Proof<G> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<BigEndian>(self.l.len() as u32)?;
+        for (l, r) in self.l.iter().zip(self.r.iter()) {
+            writer.write_all(l.into_compressed().as_ref())?;
+            writer.write_all(r.into_compressed().as_ref())?;
+        }
+        self.a.into_repr().write_be(&mut writer)?;
+        self.b.into_repr().write_be(&mut writer)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let rounds = reader.read_u32::<BigEndian>()? as usize;
+        if rounds > MAX_ROUNDS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "too many rounds",
+            ));
+        }
+
+        let mut l = Vec::with_capacity(rounds);
+        let mut r = Vec::with_capacity(rounds);
+        for _ in 0..rounds {
+            l.push(read_point::<G, _>(&mut reader)?);
+            r.push(read_point::<G, _>(&mut reader)?);
+        }
+        let a = read_scalar(&mut reader)?;
+        let b = read_scalar(&mut reader)?;
+
+        Ok(Proof { l, r, a, b })
+    }
+}
+
+fn read_point<G: CurveAffine, R: Read>(reader: &mut R) -> io::Result<G> {
+    let mut repr = G::Compressed::empty();
+    reader.read_exact(repr.as_mut())?;
+
+    repr.into_affine()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn read_scalar<F: PrimeField, R: Read>(reader: &mut R) -> io::Result<F> {
+    let mut repr = F::Repr::default();
+    repr.read_be(reader)?;
+
+    F::from_repr(repr).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Derives `n` generators by hashing `label` with their index to the curve,
+/// so that nobody knows their discrete logarithms.
+pub fn generators<G: CurveAffine>(label: &[u8], n: usize) -> Vec<G> {
+    (0..n as u64)
+        .map(|i| {
+            let mut input = label.to_vec();
+            input.extend_from_slice(&i.to_be_bytes());
+            G::Projective::hash(&input).into_affine()
+        })
+        .collect()
+}
+
+/// `<a, b>`
+pub fn inner_product<F: Field>(a: &[F], b: &[F]) -> F {
+    let mut acc = F::zero();
+    for (a, b) in a.iter().zip(b.iter()) {
+        let mut ab = *a;
+        ab.mul_assign(b);
+        acc.add_assign(&ab);
+    }
+    acc
+}
+
+/// The commitment `<a, G> + <b, H> + <a, b>·U` to `a` and `b`.
+pub fn commit<G: CurveAffine>(g: &[G], h: &[G], u: &G, a: &[G::Scalar], b: &[G::Scalar]) -> G {
+    let mut commitment = msm(g, a);
+    commitment.add_assign(&msm(h, b));
+    commitment.add_assign(&u.mul(inner_product(a, b).into_repr()));
+    commitment.into_affine()
+}
+
+/// Proves that `commitment`, see [`commit`], is to `a` and `b`.
+///
+/// # Panics
+///
+/// If the vectors and generators don't all have the same length, or if it
+/// isn't a power of two.
+pub fn prove<G: CurveAffine>(
+    transcript: &mut Transcript,
+    g: &[G],
+    h: &[G],
+    u: &G,
+    commitment: &G,
+    a: &[G::Scalar],
+    b: &[G::Scalar],
+) -> Proof<G> {
+    let n = a.len();
+    assert!(n.is_power_of_two(), "the length must be a power of two");
+    assert!(
+        b.len() == n && g.len() == n && h.len() == n,
+        "the vectors and generators must have the same length"
+    );
+
+    transcript.append_point(b"ipa-commitment", commitment);
+    let mut g = g.to_vec();
+    let mut h = h.to_vec();
+    let mut a = a.to_vec();
+    let mut b = b.to_vec();
+    let mut ls = vec![];
+    let mut rs = vec![];
+    while a.len() > 1 {
+        let half = a.len() / 2;
+        let (a_lo, a_hi) = a.split_at(half);
+        let (b_lo, b_hi) = b.split_at(half);
+        let (g_lo, g_hi) = g.split_at(half);
+        let (h_lo, h_hi) = h.split_at(half);
+
+        let mut l = msm(g_hi, a_lo);
+        l.add_assign(&msm(h_lo, b_hi));
+        l.add_assign(&u.mul(inner_product(a_lo, b_hi).into_repr()));
+        let l = l.into_affine();
+        let mut r = msm(g_lo, a_hi);
+        r.add_assign(&msm(h_hi, b_lo));
+        r.add_assign(&u.mul(inner_product(a_hi, b_lo).into_repr()));
+        let r = r.into_affine();
+
+        let (x, x_inv) = challenge(transcript, &l, &r);
+        let a_next = fold_scalars(a_lo, a_hi, &x, &x_inv);
+        let b_next = fold_scalars(b_lo, b_hi, &x_inv, &x);
+        let g_next = fold_points(g_lo, g_hi, &x_inv, &x);
+        let h_next = fold_points(h_lo, h_hi, &x, &x_inv);
+        a = a_next;
+        b = b_next;
+        g = g_next;
+        h = h_next;
+
+        ls.push(l);
+        rs.push(r);
+    }
+
+    Proof {
+        l: ls,
+        r: rs,
+        a: a[0],
+        b: b[0],
+    }
+}
+
+/// Checks that `commitment` is to vectors of the length of the generators,
+/// which is a power of two, whose proof is `proof`.
+pub fn verify<G: CurveAffine>(
+    transcript: &mut Transcript,
+    g: &[G],
+    h: &[G],
+    u: &G,
+    commitment: &G,
+    proof: &Proof<G>,
+) -> bool {
+    let n = g.len();
+    if !n.is_power_of_two()
+        || h.len() != n
+        || proof.l.len() != proof.r.len()
+        || n.trailing_zeros() as usize != proof.l.len()
+    {
+        return false;
+    }
+
+    transcript.append_point(b"ipa-commitment", commitment);
+    let mut challenges = Vec::with_capacity(proof.l.len());
+    for (l, r) in proof.l.iter().zip(proof.r.iter()) {
+        challenges.push(challenge(transcript, l, r));
+    }
+
+    // The commitment folded by the challenges, P + Σ x_j²·L_j + x_j⁻²·R_j.
+    let mut lhs = commitment.into_projective();
+    for ((x, x_inv), (l, r)) in challenges.iter().zip(proof.l.iter().zip(proof.r.iter())) {
+        let mut x2 = *x;
+        x2.square();
+        let mut x_inv2 = *x_inv;
+        x_inv2.square();
+        lhs.add_assign(&l.mul(x2.into_repr()));
+        lhs.add_assign(&r.mul(x_inv2.into_repr()));
+    }
+
+    // The generators fold to <s, G> and <s⁻¹, H>, where s_i is the product
+    // of x_j for the rounds in which the i-th generator was in the upper
+    // half, and of x_j⁻¹ for the others. The first round splits by the
+    // highest bit of i.
+    let mut s = vec![G::Scalar::one(); n];
+    for (j, (x, x_inv)) in challenges.iter().enumerate() {
+        let bit = n >> (j + 1);
+        for (i, s) in s.iter_mut().enumerate() {
+            s.mul_assign(if i & bit == 0 { x_inv } else { x });
+        }
+    }
+    let s_inv = s.iter().rev().cloned().collect::<Vec<_>>();
+
+    let mut ab = proof.a;
+    ab.mul_assign(&proof.b);
+    let mut a_s = s;
+    for s in a_s.iter_mut() {
+        s.mul_assign(&proof.a);
+    }
+    let mut b_s = s_inv;
+    for s in b_s.iter_mut() {
+        s.mul_assign(&proof.b);
+    }
+    let mut rhs = msm(g, &a_s);
+    rhs.add_assign(&msm(h, &b_s));
+    rhs.add_assign(&u.mul(ab.into_repr()));
+
+    lhs == rhs
+}
+
+/// Appends the cross terms of a round and derives its challenge with its
+/// inverse.
+fn challenge<G: CurveAffine>(transcript: &mut Transcript, l: &G, r: &G) -> (G::Scalar, G::Scalar) {
+    transcript.append_point(b"ipa-l", l);
+    transcript.append_point(b"ipa-r", r);
+    let x: G::Scalar = transcript.challenge_scalar(b"ipa-x");
+    // A zero challenge happens with negligible probability only.
+    let x_inv = x.inverse().expect("challenge is not zero");
+    (x, x_inv)
+}
+
+/// `lo·x_lo + hi·x_hi`
+fn fold_scalars<F: Field>(lo: &[F], hi: &[F], x_lo: &F, x_hi: &F) -> Vec<F> {
+    lo.iter()
+        .zip(hi.iter())
+        .map(|(lo, hi)| {
+            let mut lo = *lo;
+            lo.mul_assign(x_lo);
+            let mut hi = *hi;
+            hi.mul_assign(x_hi);
+            lo.add_assign(&hi);
+            lo
+        })
+        .collect()
+}
+
+/// `lo·x_lo + hi·x_hi`
+fn fold_points<G: CurveAffine>(lo: &[G], hi: &[G], x_lo: &G::Scalar, x_hi: &G::Scalar) -> Vec<G> {
+    let mut folded = lo
+        .iter()
+        .zip(hi.iter())
+        .map(|(lo, hi)| {
+            let mut p = lo.mul(x_lo.into_repr());
+            p.add_assign(&hi.mul(x_hi.into_repr()));
+            p
+        })
+        .collect::<Vec<_>>();
+    G::Projective::batch_normalization(&mut folded);
+    folded.iter().map(|p| p.into_affine()).collect()
+}
+
+/// `<scalars, bases>`
+fn msm<G: CurveAffine>(bases: &[G], scalars: &[G::Scalar]) -> G::Projective {
+    let mut acc = G::Projective::zero();
+    for (base, scalar) in bases.iter().zip(scalars.iter()) {
+        acc.add_assign(&base.mul(scalar.into_repr()));
+    }
+    acc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::{Bls12, Engine, Fr};
+
+    type G1Affine = <Bls12 as Engine>::G1Affine;
+
+    #[test]
+    fn test_ipa() {
+        let rng = &mut crate::testing::test_rng();
+        let n = 8;
+        let g = generators::<G1Affine>(b"g", n);
+        let h = generators::<G1Affine>(b"h", n);
+        let u = generators::<G1Affine>(b"u", 1)[0];
+
+        let a = (0..n).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+        let b = (0..n).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+        let commitment = commit(&g, &h, &u, &a, &b);
+        let proof = prove(
+            &mut Transcript::new(b"test"),
+            &g,
+            &h,
+            &u,
+            &commitment,
+            &a,
+            &b,
+        );
+        assert_eq!(proof.l.len(), 3);
+        assert!(verify(
+            &mut Transcript::new(b"test"),
+            &g,
+            &h,
+            &u,
+            &commitment,
+            &proof
+        ));
+
+        let mut bytes = vec![];
+        proof.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 4 + 6 * 48 + 2 * 32);
+        assert!(Proof::<G1Affine>::read(&bytes[..]).unwrap() == proof);
+
+        // Another transcript, commitment or proof fails.
+        assert!(!verify(
+            &mut Transcript::new(b"other"),
+            &g,
+            &h,
+            &u,
+            &commitment,
+            &proof
+        ));
+        let mut b_other = b.clone();
+        b_other[0].add_assign(&Fr::one());
+        let other = commit(&g, &h, &u, &a, &b_other);
+        assert!(!verify(
+            &mut Transcript::new(b"test"),
+            &g,
+            &h,
+            &u,
+            &other,
+            &proof
+        ));
+        let mut wrong = proof.clone();
+        wrong.a.add_assign(&Fr::one());
+        assert!(!verify(
+            &mut Transcript::new(b"test"),
+            &g,
+            &h,
+            &u,
+            &commitment,
+            &wrong
+        ));
+        assert!(!verify(
+            &mut Transcript::new(b"test"),
+            &g[..4],
+            &h[..4],
+            &u,
+            &commitment,
+            &proof
+        ));
+    }
+}
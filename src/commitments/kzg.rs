@@ -0,0 +1,211 @@
+//! [KZG] commitments to polynomials with a [`UniversalSrs`], their openings
+//! at single points and the verification of many openings at once.
+//!
+//! A commitment is `p(x)` in G1 for the secret `x` of the SRS, an opening at
+//! `z` is the evaluation `p(z)` with the quotient `(p(x) - p(z)) / (x - z)` in
+//! G1, which is checked with `e(C - p(z)·G + z·π, H) = e(π, x·H)`. The
+//! commitments and openings are computed with multiexps, on the GPU if a
+//! kernel is passed in.
+//!
+//! Commitments and the proofs of openings are serialized as compressed
+//! points, the values of openings as canonical big-endian scalars, like the
+//! proofs of the other backends.
+//!
+//! [KZG]: https://www.iacr.org/archive/asiacrypt2010/6477178/6477178.pdf
+//! [`UniversalSrs`]: crate::srs::UniversalSrs
+
+use std::io::{self, Read, Write};
+
+use ff::{Field, PrimeField, PrimeFieldRepr};
+use groupy::{CurveAffine, CurveProjective, EncodedPoint};
+use rand_core::RngCore;
+
+use crate::bls::{Engine, PairingCurveAffine};
+use crate::gpu;
+use crate::multicore::Worker;
+use crate::poly;
+use crate::srs::UniversalSrs;
+use crate::SynthesisError;
+
+/// The evaluation of a committed polynomial at a point, with its proof.
+#[derive(Clone)]
+pub struct Opening<E: Engine> {
+    pub value: E::Fr,
+    pub proof: E::G1Affine,
+}
+
+impl<E: Engine> PartialEq for Opening<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value && self.proof == other.proof
+    }
+}
+
+impl<E: Engine> Opening<E> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.value.into_repr().write_be(&mut writer)?;
+        write_commitment::<E, _>(&self.proof, writer)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut repr = <E::Fr as PrimeField>::Repr::default();
+        repr.read_be(&mut reader)?;
+        let value =
+            E::Fr::from_repr(repr).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let proof = read_commitment::<E, _>(reader)?;
+
+        Ok(Opening { value, proof })
+    }
+}
+
+pub fn write_commitment<E: Engine, W: Write>(
+    commitment: &E::G1Affine,
+    mut writer: W,
+) -> io::Result<()> {
+    writer.write_all(commitment.into_compressed().as_ref())
+}
+
+pub fn read_commitment<E: Engine, R: Read>(mut reader: R) -> io::Result<E::G1Affine> {
+    let mut repr = <E::G1Affine as CurveAffine>::Compressed::empty();
+    reader.read_exact(repr.as_mut())?;
+    repr.into_affine()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Commits to the polynomial with the given coefficients, whose degree is
+/// at most the maximal degree of the SRS.
+pub fn commit<E: Engine>(
+    worker: &Worker,
+    srs: &UniversalSrs<E>,
+    coeffs: &[E::Fr],
+    kern: &mut Option<gpu::MultiexpKernel<E>>,
+) -> Result<E::G1Affine, SynthesisError> {
+    poly::commit(worker, &srs.g1_powers, coeffs, kern)
+}
+
+/// Opens the polynomial with the given coefficients at `point`.
+pub fn open<E: Engine>(
+    worker: &Worker,
+    srs: &UniversalSrs<E>,
+    coeffs: &[E::Fr],
+    point: &E::Fr,
+    kern: &mut Option<gpu::MultiexpKernel<E>>,
+) -> Result<Opening<E>, SynthesisError> {
+    let quotient = poly::divide_by_linear(coeffs, point);
+
+    Ok(Opening {
+        value: poly::evaluate(coeffs, point),
+        proof: poly::commit(worker, &srs.g1_powers, &quotient, kern)?,
+    })
+}
+
+/// Checks that the committed polynomial evaluates to the value of the
+/// opening at `point`.
+pub fn verify<E: Engine>(
+    srs: &UniversalSrs<E>,
+    commitment: &E::G1Affine,
+    point: &E::Fr,
+    opening: &Opening<E>,
+) -> bool {
+    let (lhs, proof) = opening_terms(srs, commitment, point, opening, &E::Fr::one());
+    check(srs, lhs, proof)
+}
+
+/// Checks many openings at once, each of a commitment at a point, which
+/// costs two pairings regardless of their number. The openings are combined
+/// with random scalars from `rng`, so an invalid one slips through with
+/// negligible probability only.
+pub fn batch_verify<E: Engine, R: RngCore>(
+    srs: &UniversalSrs<E>,
+    openings: &[(E::G1Affine, E::Fr, Opening<E>)],
+    rng: &mut R,
+) -> bool {
+    let mut lhs = E::G1::zero();
+    let mut proofs = E::G1::zero();
+    for (commitment, point, opening) in openings {
+        let r = E::Fr::random(rng);
+        let (l, p) = opening_terms(srs, commitment, point, opening, &r);
+        lhs.add_assign(&l);
+        proofs.add_assign(&p);
+    }
+
+    check(srs, lhs, proofs)
+}
+
+/// `r·(C - p(z)·G + z·π)` and `r·π` of an opening.
+fn opening_terms<E: Engine>(
+    srs: &UniversalSrs<E>,
+    commitment: &E::G1Affine,
+    point: &E::Fr,
+    opening: &Opening<E>,
+    r: &E::Fr,
+) -> (E::G1, E::G1) {
+    let mut value = opening.value;
+    value.mul_assign(r);
+    let mut point = *point;
+    point.mul_assign(r);
+
+    let mut lhs = commitment.mul(r.into_repr());
+    lhs.sub_assign(&srs.g1_powers[0].mul(value.into_repr()));
+    lhs.add_assign(&opening.proof.mul(point.into_repr()));
+
+    (lhs, opening.proof.mul(r.into_repr()))
+}
+
+/// `e(lhs, H) = e(proof, x·H)`.
+fn check<E: Engine>(srs: &UniversalSrs<E>, lhs: E::G1, proof: E::G1) -> bool {
+    let mut proof = proof;
+    proof.negate();
+
+    E::final_exponentiation(&E::miller_loop(
+        [
+            (&lhs.into_affine().prepare(), &srs.g2.prepare()),
+            (&proof.into_affine().prepare(), &srs.g2_x.prepare()),
+        ]
+        .iter(),
+    ))
+    .unwrap()
+        == E::Fqk::one()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::{Bls12, Fr};
+
+    #[test]
+    fn test_kzg() {
+        let rng = &mut crate::testing::test_rng();
+        let worker = Worker::new();
+        let srs = UniversalSrs::<Bls12>::generate_random(16, rng);
+
+        let polys = (0..3)
+            .map(|_| (0..17).map(|_| Fr::random(rng)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut openings = vec![];
+        for coeffs in &polys {
+            let commitment = commit(&worker, &srs, coeffs, &mut None).unwrap();
+            let point = Fr::random(rng);
+            let opening = open(&worker, &srs, coeffs, &point, &mut None).unwrap();
+            assert_eq!(opening.value, poly::evaluate(coeffs, &point));
+            assert!(verify(&srs, &commitment, &point, &opening));
+
+            let mut bytes = vec![];
+            opening.write(&mut bytes).unwrap();
+            assert_eq!(bytes.len(), 32 + 48);
+            assert!(Opening::<Bls12>::read(&bytes[..]).unwrap() == opening);
+
+            openings.push((commitment, point, opening));
+        }
+        assert!(batch_verify(&srs, &openings, rng));
+
+        // A wrong value fails on its own and in a batch.
+        openings[1].2.value.add_assign(&Fr::one());
+        let (commitment, point, opening) = &openings[1];
+        assert!(!verify(&srs, commitment, point, opening));
+        assert!(!batch_verify(&srs, &openings, rng));
+
+        // Polynomials of too high a degree can't be committed to.
+        let coeffs = vec![Fr::one(); 18];
+        assert!(commit(&worker, &srs, &coeffs, &mut None).is_err());
+    }
+}
@@ -8,27 +8,73 @@
 //! field. This allows us to perform polynomial operations in O(n) by performing
 //! an O(n log n) FFT over such a domain.
 //!
+//! Where the multiplicative group of the field also has a subgroup of order
+//! 3<sup>k</sup>, [`EvaluationDomain::from_coeffs_mixed_radix`] additionally
+//! considers domains of size 2<sup>n</sup>·3<sup>k</sup>, which can be much
+//! smaller than the next power of two.
+//!
+//! With the `disk` feature, `disk::DiskEvaluationDomain` keeps the elements
+//! in memory-mapped files instead, for domains that don't fit into memory.
+//!
 //! [`EvaluationDomain`]: crate::domain::EvaluationDomain
+//! [`EvaluationDomain::from_coeffs_mixed_radix`]: crate::domain::EvaluationDomain::from_coeffs_mixed_radix
 //! [Groth16]: https://eprint.iacr.org/2016/260
 
-use ff::{Field, PrimeField, ScalarEngine};
+use crate::bls::Engine;
+use ff::{Field, PrimeField, PrimeFieldRepr, ScalarEngine};
 use groupy::CurveProjective;
-use paired::Engine;
 
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 use super::multicore::Worker;
 use super::SynthesisError;
 
 use crate::gpu;
 
+#[cfg(feature = "disk")]
+pub mod disk;
+
 pub struct EvaluationDomain<E: ScalarEngine, G: Group<E>> {
     coeffs: Vec<G>,
     exp: u32,
+    exp3: u32,
     omega: E::Fr,
     omegainv: E::Fr,
     geninv: E::Fr,
     minv: E::Fr,
+    precomputed: Option<Arc<DomainPrecomputed<E>>>,
+}
+
+/// The tables of the FFTs of one domain size, so that they are computed once
+/// instead of by every FFT: the twiddle factors, the bit-reversal permutation
+/// and the inverse of the target polynomial on the coset.
+///
+/// It can be shared between all domains of the same size, e.g. across the
+/// FFTs of a proof and across proofs, at the cost of memory of about the size
+/// of the coefficients of one domain. Only the CPU FFTs use the tables, the
+/// GPU kernel computes its own.
+pub struct DomainPrecomputed<E: ScalarEngine> {
+    size: usize,
+    // Powers of the root of unity of the radix-2 sub-FFTs and of its inverse,
+    // up to half their size
+    twiddles: Vec<E::Fr>,
+    inv_twiddles: Vec<E::Fr>,
+    bit_reverse: Vec<u32>,
+    z_inv_on_coset: E::Fr,
+}
+
+impl<E: ScalarEngine> DomainPrecomputed<E> {
+    /// The size of the domains the tables are for.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// The twiddle factors and the bit-reversal permutation of a radix-2 FFT.
+#[derive(Clone, Copy)]
+struct FftTables<'a, F> {
+    twiddles: &'a [F],
+    bit_reverse: &'a [u32],
 }
 
 impl<E: ScalarEngine, G: Group<E>> AsRef<[G]> for EvaluationDomain<E, G> {
@@ -48,7 +94,34 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
         self.coeffs
     }
 
-    pub fn from_coeffs(mut coeffs: Vec<G>) -> Result<EvaluationDomain<E, G>, SynthesisError> {
+    /// The elements of the domain, coefficients or evaluations depending on
+    /// the last transform.
+    pub fn coeffs(&self) -> &[G] {
+        &self.coeffs
+    }
+
+    pub fn coeffs_mut(&mut self) -> &mut [G] {
+        &mut self.coeffs
+    }
+
+    /// The number of elements, which is the size of the domain.
+    pub fn size(&self) -> usize {
+        self.coeffs.len()
+    }
+
+    /// The generator of the domain, a primitive root of unity of its size.
+    pub fn omega(&self) -> E::Fr {
+        self.omega
+    }
+
+    /// The inverse of [`omega`].
+    ///
+    /// [`omega`]: EvaluationDomain::omega
+    pub fn omega_inv(&self) -> E::Fr {
+        self.omegainv
+    }
+
+    pub fn from_coeffs(coeffs: Vec<G>) -> Result<EvaluationDomain<E, G>, SynthesisError> {
         // Compute the size of our evaluation domain
         let mut m = 1;
         let mut exp = 0;
@@ -62,18 +135,67 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
                 return Err(SynthesisError::PolynomialDegreeTooLarge);
             }
         }
-        // Compute omega, the 2^exp primitive root of unity
+
+        Ok(Self::new(coeffs, exp, 0))
+    }
+
+    /// Like [`from_coeffs`], but the size of the domain may also be a power of
+    /// two times a power of three, if the field has the roots of unity for it,
+    /// whichever is the smallest size fitting the coefficients.
+    ///
+    /// The size differs from the one of [`from_coeffs`], so parties have to
+    /// agree on using it, e.g. Groth16 parameters are generated for the
+    /// radix-2 domains.
+    ///
+    /// [`from_coeffs`]: EvaluationDomain::from_coeffs
+    pub fn from_coeffs_mixed_radix(
+        coeffs: Vec<G>,
+    ) -> Result<EvaluationDomain<E, G>, SynthesisError> {
+        let mut best = None;
+        let mut power_of_three = 1usize;
+        let mut exp3 = 0;
+        while three_adic_root_of_unity::<E::Fr>(exp3).is_some() {
+            let mut m = power_of_three;
+            let mut exp = 0;
+            while m < coeffs.len() && exp < E::Fr::S {
+                m *= 2;
+                exp += 1;
+            }
+            if m >= coeffs.len() && exp < E::Fr::S {
+                match best {
+                    Some((best_m, _, _)) if best_m <= m => {}
+                    _ => best = Some((m, exp, exp3)),
+                }
+            }
+            if power_of_three >= coeffs.len() {
+                break;
+            }
+            power_of_three *= 3;
+            exp3 += 1;
+        }
+
+        let (_, exp, exp3) = best.ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        Ok(Self::new(coeffs, exp, exp3))
+    }
+
+    fn new(mut coeffs: Vec<G>, exp: u32, exp3: u32) -> EvaluationDomain<E, G> {
+        let m = (1 << exp) * 3usize.pow(exp3);
+
+        // Compute omega, the 2^exp primitive root of unity, times a 3^exp3
+        // primitive root of unity, which is an m-th primitive root of unity.
         let mut omega = E::Fr::root_of_unity();
         for _ in exp..E::Fr::S {
             omega.square();
         }
+        omega.mul_assign(&three_adic_root_of_unity::<E::Fr>(exp3).unwrap());
 
         // Extend the coeffs vector with zeroes if necessary
         coeffs.resize(m, G::group_zero());
 
-        Ok(EvaluationDomain {
+        EvaluationDomain {
             coeffs,
             exp,
+            exp3,
             omega,
             omegainv: omega.inverse().unwrap(),
             geninv: E::Fr::multiplicative_generator().inverse().unwrap(),
@@ -81,7 +203,47 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
                 .unwrap()
                 .inverse()
                 .unwrap(),
-        })
+            precomputed: None,
+        }
+    }
+
+    /// Computes the tables of the FFTs of domains of this size.
+    pub fn precompute(&self, worker: &Worker) -> DomainPrecomputed<E> {
+        let n = 1usize << self.exp;
+        let power_of_three = [3u64.pow(self.exp3)];
+        let omega = self.omega.pow(&power_of_three);
+        let omegainv = self.omegainv.pow(&power_of_three);
+
+        let mut bit_reverse = vec![0u32; n];
+        worker.scope(n, |scope, chunk| {
+            let exp = self.exp;
+
+            for (i, bit_reverse) in bit_reverse.chunks_mut(chunk).enumerate() {
+                scope.spawn(move |_| {
+                    for (j, r) in bit_reverse.iter_mut().enumerate() {
+                        *r = bitreverse((i * chunk + j) as u32, exp);
+                    }
+                });
+            }
+        });
+
+        DomainPrecomputed {
+            size: self.coeffs.len(),
+            twiddles: powers(worker, omega, (n / 2).max(1)),
+            inv_twiddles: powers(worker, omegainv, (n / 2).max(1)),
+            bit_reverse,
+            z_inv_on_coset: self
+                .z(&E::Fr::multiplicative_generator())
+                .inverse()
+                .unwrap(),
+        }
+    }
+
+    /// Uses the given tables for the FFTs of this domain.
+    pub fn set_precomputed(&mut self, precomputed: Arc<DomainPrecomputed<E>>) {
+        assert_eq!(self.coeffs.len(), precomputed.size);
+
+        self.precomputed = Some(precomputed);
     }
 
     pub fn fft(
@@ -89,7 +251,19 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
         worker: &Worker,
         kern: &mut Option<gpu::FFTKernel<E>>,
     ) -> gpu::GPUResult<()> {
-        best_fft(kern, &mut self.coeffs, worker, &self.omega, self.exp)?;
+        let tables = self.precomputed.as_ref().map(|p| FftTables {
+            twiddles: &p.twiddles,
+            bit_reverse: &p.bit_reverse,
+        });
+        mixed_radix_fft(
+            kern,
+            &mut self.coeffs,
+            worker,
+            &self.omega,
+            self.exp,
+            self.exp3,
+            tables,
+        )?;
         Ok(())
     }
 
@@ -98,18 +272,40 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
         worker: &Worker,
         kern: &mut Option<gpu::FFTKernel<E>>,
     ) -> gpu::GPUResult<()> {
-        best_fft(kern, &mut self.coeffs, worker, &self.omegainv, self.exp)?;
+        let tables = self.precomputed.as_ref().map(|p| FftTables {
+            twiddles: &p.inv_twiddles,
+            bit_reverse: &p.bit_reverse,
+        });
+        mixed_radix_fft(
+            kern,
+            &mut self.coeffs,
+            worker,
+            &self.omegainv,
+            self.exp,
+            self.exp3,
+            tables,
+        )?;
+
+        let minv = self.minv;
+        self.scale(worker, minv, kern)
+    }
 
+    pub fn distribute_powers(
+        &mut self,
+        worker: &Worker,
+        g: E::Fr,
+        kern: &mut Option<gpu::FFTKernel<E>>,
+    ) -> gpu::GPUResult<()> {
         if let Some(ref mut k) = kern {
-            gpu_mul_by_field(k, &mut self.coeffs, &self.minv, self.exp)?;
+            gpu_distribute_powers(k, &mut self.coeffs, &g)?;
         } else {
             worker.scope(self.coeffs.len(), |scope, chunk| {
-                let minv = self.minv;
-
-                for v in self.coeffs.chunks_mut(chunk) {
+                for (i, v) in self.coeffs.chunks_mut(chunk).enumerate() {
                     scope.spawn(move |_| {
-                        for v in v {
-                            v.group_mul_assign(&minv);
+                        let mut u = g.pow(&[(i * chunk) as u64]);
+                        for v in v.iter_mut() {
+                            v.group_mul_assign(&u);
+                            u.mul_assign(&g);
                         }
                     });
                 }
@@ -118,26 +314,23 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
         Ok(())
     }
 
-    pub fn distribute_powers(&mut self, worker: &Worker, g: E::Fr) {
-        worker.scope(self.coeffs.len(), |scope, chunk| {
-            for (i, v) in self.coeffs.chunks_mut(chunk).enumerate() {
-                scope.spawn(move |_| {
-                    let mut u = g.pow(&[(i * chunk) as u64]);
-                    for v in v.iter_mut() {
-                        v.group_mul_assign(&u);
-                        u.mul_assign(&g);
-                    }
-                });
-            }
-        });
+    pub fn coset_fft(
+        &mut self,
+        worker: &Worker,
+        kern: &mut Option<gpu::FFTKernel<E>>,
+    ) -> gpu::GPUResult<()> {
+        self.coset_fft_with_shift(worker, E::Fr::multiplicative_generator(), kern)
     }
 
-    pub fn coset_fft(
+    /// Evaluates over the coset of the domain shifted by `shift` instead of
+    /// the multiplicative generator.
+    pub fn coset_fft_with_shift(
         &mut self,
         worker: &Worker,
+        shift: E::Fr,
         kern: &mut Option<gpu::FFTKernel<E>>,
     ) -> gpu::GPUResult<()> {
-        self.distribute_powers(worker, E::Fr::multiplicative_generator());
+        self.distribute_powers(worker, shift, kern)?;
         self.fft(worker, kern)?;
         Ok(())
     }
@@ -149,12 +342,88 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
     ) -> gpu::GPUResult<()> {
         let geninv = self.geninv;
         self.ifft(worker, kern)?;
-        self.distribute_powers(worker, geninv);
+        self.distribute_powers(worker, geninv, kern)?;
+        Ok(())
+    }
+
+    /// The inverse of [`coset_fft_with_shift`]. Panics if `shift` is zero.
+    ///
+    /// [`coset_fft_with_shift`]: EvaluationDomain::coset_fft_with_shift
+    pub fn icoset_fft_with_shift(
+        &mut self,
+        worker: &Worker,
+        shift: E::Fr,
+        kern: &mut Option<gpu::FFTKernel<E>>,
+    ) -> gpu::GPUResult<()> {
+        let shift_inv = shift.inverse().expect("the coset shift must not be zero");
+        self.ifft(worker, kern)?;
+        self.distribute_powers(worker, shift_inv, kern)?;
+        Ok(())
+    }
+
+    /// Like [`ifft`] on each of `domains`. With a kernel and radix-2 domains
+    /// of the same size, their FFTs run on the GPU as a batch.
+    ///
+    /// [`ifft`]: EvaluationDomain::ifft
+    pub fn ifft_many(
+        domains: &mut [&mut Self],
+        worker: &Worker,
+        kern: &mut Option<gpu::FFTKernel<E>>,
+    ) -> gpu::GPUResult<()> {
+        if !batchable(domains, kern) {
+            for domain in domains.iter_mut() {
+                domain.ifft(worker, kern)?;
+            }
+            return Ok(());
+        }
+
+        if let Some(ref mut k) = kern {
+            let (omegainv, exp) = (domains[0].omegainv, domains[0].exp);
+            let mut coeffs = domains
+                .iter_mut()
+                .map(|d| &mut d.coeffs[..])
+                .collect::<Vec<_>>();
+            gpu_fft_many(k, &mut coeffs, &omegainv, exp)?;
+        }
+        for domain in domains.iter_mut() {
+            let minv = domain.minv;
+            domain.scale(worker, minv, kern)?;
+        }
+        Ok(())
+    }
+
+    /// Like [`coset_fft`] on each of `domains`, batched like [`ifft_many`].
+    ///
+    /// [`coset_fft`]: EvaluationDomain::coset_fft
+    /// [`ifft_many`]: EvaluationDomain::ifft_many
+    pub fn coset_fft_many(
+        domains: &mut [&mut Self],
+        worker: &Worker,
+        kern: &mut Option<gpu::FFTKernel<E>>,
+    ) -> gpu::GPUResult<()> {
+        if !batchable(domains, kern) {
+            for domain in domains.iter_mut() {
+                domain.coset_fft(worker, kern)?;
+            }
+            return Ok(());
+        }
+
+        for domain in domains.iter_mut() {
+            domain.distribute_powers(worker, E::Fr::multiplicative_generator(), kern)?;
+        }
+        if let Some(ref mut k) = kern {
+            let (omega, exp) = (domains[0].omega, domains[0].exp);
+            let mut coeffs = domains
+                .iter_mut()
+                .map(|d| &mut d.coeffs[..])
+                .collect::<Vec<_>>();
+            gpu_fft_many(k, &mut coeffs, &omega, exp)?;
+        }
         Ok(())
     }
 
     /// This evaluates t(tau) for this domain, which is
-    /// tau^m - 1 for these radix-2 domains.
+    /// tau^m - 1 for these multiplicative subgroups.
     pub fn z(&self, tau: &E::Fr) -> E::Fr {
         let mut tmp = tau.pow(&[self.coeffs.len() as u64]);
         tmp.sub_assign(&E::Fr::one());
@@ -169,65 +438,122 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
         &mut self,
         worker: &Worker,
         kern: &mut Option<gpu::FFTKernel<E>>,
+    ) -> gpu::GPUResult<()> {
+        let i = match self.precomputed {
+            Some(ref precomputed) => precomputed.z_inv_on_coset,
+            None => self
+                .z(&E::Fr::multiplicative_generator())
+                .inverse()
+                .unwrap(),
+        };
+
+        self.scale(worker, i, kern)
+    }
+
+    /// Like [`divide_by_z_on_coset`], for the coset of
+    /// [`coset_fft_with_shift`]. Panics if the shifted coset is the domain
+    /// itself, i.e. `shift` is in the domain.
+    ///
+    /// [`divide_by_z_on_coset`]: EvaluationDomain::divide_by_z_on_coset
+    /// [`coset_fft_with_shift`]: EvaluationDomain::coset_fft_with_shift
+    pub fn divide_by_z_on_coset_with_shift(
+        &mut self,
+        worker: &Worker,
+        shift: E::Fr,
+        kern: &mut Option<gpu::FFTKernel<E>>,
     ) -> gpu::GPUResult<()> {
         let i = self
-            .z(&E::Fr::multiplicative_generator())
+            .z(&shift)
             .inverse()
-            .unwrap();
+            .expect("the coset shift must not be in the domain");
+
+        self.scale(worker, i, kern)
+    }
+
+    /// Multiplies all elements by `scalar`.
+    fn scale(
+        &mut self,
+        worker: &Worker,
+        scalar: E::Fr,
+        kern: &mut Option<gpu::FFTKernel<E>>,
+    ) -> gpu::GPUResult<()> {
+        match kern {
+            // The kernel only handles radix-2 domains.
+            Some(ref mut k) if self.exp3 == 0 => {
+                gpu_mul_by_field(k, &mut self.coeffs, &scalar, self.exp)?;
+            }
+            _ => {
+                worker.scope(self.coeffs.len(), |scope, chunk| {
+                    for v in self.coeffs.chunks_mut(chunk) {
+                        scope.spawn(move |_| {
+                            for v in v {
+                                v.group_mul_assign(&scalar);
+                            }
+                        });
+                    }
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Perform O(n) multiplication of two polynomials in the domain.
+    pub fn mul_assign(
+        &mut self,
+        worker: &Worker,
+        other: &EvaluationDomain<E, Scalar<E>>,
+        kern: &mut Option<gpu::FFTKernel<E>>,
+    ) -> gpu::GPUResult<()> {
+        assert_eq!(self.coeffs.len(), other.coeffs.len());
 
         if let Some(ref mut k) = kern {
-            gpu_mul_by_field(k, &mut self.coeffs, &i, self.exp)?;
+            gpu_mul_assign(k, &mut self.coeffs, &other.coeffs)?;
         } else {
             worker.scope(self.coeffs.len(), |scope, chunk| {
-                for v in self.coeffs.chunks_mut(chunk) {
+                for (a, b) in self
+                    .coeffs
+                    .chunks_mut(chunk)
+                    .zip(other.coeffs.chunks(chunk))
+                {
                     scope.spawn(move |_| {
-                        for v in v {
-                            v.group_mul_assign(&i);
+                        for (a, b) in a.iter_mut().zip(b.iter()) {
+                            a.group_mul_assign(&b.0);
                         }
                     });
                 }
             });
         }
-
         Ok(())
     }
 
-    /// Perform O(n) multiplication of two polynomials in the domain.
-    pub fn mul_assign(&mut self, worker: &Worker, other: &EvaluationDomain<E, Scalar<E>>) {
-        assert_eq!(self.coeffs.len(), other.coeffs.len());
-
-        worker.scope(self.coeffs.len(), |scope, chunk| {
-            for (a, b) in self
-                .coeffs
-                .chunks_mut(chunk)
-                .zip(other.coeffs.chunks(chunk))
-            {
-                scope.spawn(move |_| {
-                    for (a, b) in a.iter_mut().zip(b.iter()) {
-                        a.group_mul_assign(&b.0);
-                    }
-                });
-            }
-        });
-    }
-
     /// Perform O(n) subtraction of one polynomial from another in the domain.
-    pub fn sub_assign(&mut self, worker: &Worker, other: &EvaluationDomain<E, G>) {
+    pub fn sub_assign(
+        &mut self,
+        worker: &Worker,
+        other: &EvaluationDomain<E, G>,
+        kern: &mut Option<gpu::FFTKernel<E>>,
+    ) -> gpu::GPUResult<()> {
         assert_eq!(self.coeffs.len(), other.coeffs.len());
 
-        worker.scope(self.coeffs.len(), |scope, chunk| {
-            for (a, b) in self
-                .coeffs
-                .chunks_mut(chunk)
-                .zip(other.coeffs.chunks(chunk))
-            {
-                scope.spawn(move |_| {
-                    for (a, b) in a.iter_mut().zip(b.iter()) {
-                        a.group_sub_assign(&b);
-                    }
-                });
-            }
-        });
+        if let Some(ref mut k) = kern {
+            gpu_sub_assign(k, &mut self.coeffs, &other.coeffs)?;
+        } else {
+            worker.scope(self.coeffs.len(), |scope, chunk| {
+                for (a, b) in self
+                    .coeffs
+                    .chunks_mut(chunk)
+                    .zip(other.coeffs.chunks(chunk))
+                {
+                    scope.spawn(move |_| {
+                        for (a, b) in a.iter_mut().zip(b.iter()) {
+                            a.group_sub_assign(&b);
+                        }
+                    });
+                }
+            });
+        }
+        Ok(())
     }
 }
 
@@ -300,21 +626,138 @@ impl<E: ScalarEngine> Group<E> for Scalar<E> {
     }
 }
 
+/// Returns a primitive 3^exp-th root of unity, if the multiplicative group of
+/// the field has a subgroup of that order.
+fn three_adic_root_of_unity<F: PrimeField>(exp: u32) -> Option<F> {
+    // The subgroup is generated by g^((r - 1) / 3^exp) for a generator g of
+    // the multiplicative group, if 3^exp divides r - 1.
+    let mut e = F::char();
+    e.sub_noborrow(&F::Repr::from(1));
+    for _ in 0..exp {
+        let mut remainder = 0u128;
+        for limb in e.as_mut().iter_mut().rev() {
+            let current = (remainder << 64) | u128::from(*limb);
+            *limb = (current / 3) as u64;
+            remainder = current % 3;
+        }
+        if remainder != 0 {
+            return None;
+        }
+    }
+
+    Some(F::multiplicative_generator().pow(e.as_ref()))
+}
+
+/// Performs a decimation-in-time FFT of size 2^log_n·3^exp3: each radix-3
+/// step splits the input into three interleaved parts, whose FFTs are
+/// combined afterwards. The remaining radix-2 FFTs run on the GPU if there
+/// is a kernel.
+fn mixed_radix_fft<E: Engine, T: Group<E>>(
+    kern: &mut Option<gpu::FFTKernel<E>>,
+    a: &mut [T],
+    worker: &Worker,
+    omega: &E::Fr,
+    log_n: u32,
+    exp3: u32,
+    tables: Option<FftTables<E::Fr>>,
+) -> gpu::GPUResult<()> {
+    if exp3 == 0 {
+        return best_fft(kern, a, worker, omega, log_n, tables);
+    }
+
+    let m = a.len() / 3;
+    let mut parts = (0..3).map(|_| Vec::with_capacity(m)).collect::<Vec<_>>();
+    for (i, v) in a.iter().enumerate() {
+        parts[i % 3].push(*v);
+    }
+    let omega_cubed = omega.pow(&[3]);
+    for part in parts.iter_mut() {
+        mixed_radix_fft(kern, part, worker, &omega_cubed, log_n, exp3 - 1, tables)?;
+    }
+
+    // With the primitive cube root of unity w = omega^m, the result at
+    // q·m + s is the sum of (omega^s·w^q)^r times the r-th part at s.
+    let w = omega.pow(&[m as u64]);
+    let mut w_squared = w;
+    w_squared.square();
+    let (a0, rest) = a.split_at_mut(m);
+    let (a1, a2) = rest.split_at_mut(m);
+    worker.scope(m, |scope, chunk| {
+        let parts = &parts;
+
+        for (i, ((a0, a1), a2)) in a0
+            .chunks_mut(chunk)
+            .zip(a1.chunks_mut(chunk))
+            .zip(a2.chunks_mut(chunk))
+            .enumerate()
+        {
+            scope.spawn(move |_scope| {
+                let start = i * chunk;
+                let mut omega_s = omega.pow(&[start as u64]);
+                for (j, ((a0, a1), a2)) in a0
+                    .iter_mut()
+                    .zip(a1.iter_mut())
+                    .zip(a2.iter_mut())
+                    .enumerate()
+                {
+                    let s = start + j;
+                    let mut t1 = parts[1][s];
+                    t1.group_mul_assign(&omega_s);
+                    let mut t2 = parts[2][s];
+                    let mut omega_2s = omega_s;
+                    omega_2s.square();
+                    t2.group_mul_assign(&omega_2s);
+
+                    // w^4 = w, so the factors of the last part are w^0, w^2
+                    // and w.
+                    *a0 = parts[0][s];
+                    a0.group_add_assign(&t1);
+                    a0.group_add_assign(&t2);
+                    let combine = |a: &mut T, w1: &E::Fr, w2: &E::Fr| {
+                        let mut tmp = t1;
+                        tmp.group_mul_assign(w1);
+                        *a = parts[0][s];
+                        a.group_add_assign(&tmp);
+                        let mut tmp = t2;
+                        tmp.group_mul_assign(w2);
+                        a.group_add_assign(&tmp);
+                    };
+                    combine(a1, &w, &w_squared);
+                    combine(a2, &w_squared, &w);
+                    omega_s.mul_assign(omega);
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+/// From 2^FOUR_STEP_MIN_LOG_N elements on, the input doesn't fit into the
+/// caches anymore and the CPU FFT uses the four-step algorithm.
+const FOUR_STEP_MIN_LOG_N: u32 = 16;
+
 fn best_fft<E: Engine, T: Group<E>>(
     kern: &mut Option<gpu::FFTKernel<E>>,
     a: &mut [T],
     worker: &Worker,
     omega: &E::Fr,
     log_n: u32,
+    tables: Option<FftTables<E::Fr>>,
 ) -> gpu::GPUResult<()> {
     if let Some(ref mut k) = kern {
         gpu_fft(k, a, omega, log_n)?;
     } else {
         let log_cpus = worker.log_num_cpus();
         if log_n <= log_cpus {
-            serial_fft(a, omega, log_n);
+            match tables {
+                Some(tables) => serial_fft_with_tables(a, log_n, tables, 0),
+                None => serial_fft(a, omega, log_n),
+            }
+        } else if log_n < FOUR_STEP_MIN_LOG_N {
+            parallel_fft(a, worker, omega, log_n, log_cpus, tables);
         } else {
-            parallel_fft(a, worker, omega, log_n, log_cpus);
+            four_step_fft(a, worker, omega, log_n, tables);
         }
     }
     Ok(())
@@ -338,6 +781,31 @@ pub fn gpu_fft<E: Engine, T: Group<E>>(
     Ok(())
 }
 
+pub fn gpu_fft_many<E: Engine, T: Group<E>>(
+    kern: &mut gpu::FFTKernel<E>,
+    polys: &mut [&mut [T]],
+    omega: &E::Fr,
+    log_n: u32,
+) -> gpu::GPUResult<()> {
+    // The reason of unsafety is same as above.
+    let polys = unsafe { std::mem::transmute::<&mut [&mut [T]], &mut [&mut [E::Fr]]>(polys) };
+    kern.radix_fft_many(polys, omega, log_n)?;
+    Ok(())
+}
+
+/// Whether the FFTs of `domains` can run as a batch on the GPU, which
+/// handles radix-2 domains of one size.
+fn batchable<E: Engine, T: Group<E>>(
+    domains: &[&mut EvaluationDomain<E, T>],
+    kern: &Option<gpu::FFTKernel<E>>,
+) -> bool {
+    kern.is_some()
+        && !domains.is_empty()
+        && domains
+            .iter()
+            .all(|d| d.exp3 == 0 && d.coeffs.len() == domains[0].coeffs.len())
+}
+
 pub fn gpu_mul_by_field<E: Engine, T: Group<E>>(
     kern: &mut gpu::FFTKernel<E>,
     a: &mut [T],
@@ -350,16 +818,69 @@ pub fn gpu_mul_by_field<E: Engine, T: Group<E>>(
     Ok(())
 }
 
-pub fn serial_fft<E: ScalarEngine, T: Group<E>>(a: &mut [T], omega: &E::Fr, log_n: u32) {
-    fn bitreverse(mut n: u32, l: u32) -> u32 {
-        let mut r = 0;
-        for _ in 0..l {
-            r = (r << 1) | (n & 1);
-            n >>= 1;
-        }
-        r
+pub fn gpu_mul_assign<E: Engine, T: Group<E>>(
+    kern: &mut gpu::FFTKernel<E>,
+    a: &mut [T],
+    b: &[Scalar<E>],
+) -> gpu::GPUResult<()> {
+    // The reason of unsafety is same as above.
+    let a = unsafe { std::mem::transmute::<&mut [T], &mut [E::Fr]>(a) };
+    let b = unsafe { std::mem::transmute::<&[Scalar<E>], &[E::Fr]>(b) };
+    kern.mul_assign(a, b)?;
+    Ok(())
+}
+
+pub fn gpu_sub_assign<E: Engine, T: Group<E>>(
+    kern: &mut gpu::FFTKernel<E>,
+    a: &mut [T],
+    b: &[T],
+) -> gpu::GPUResult<()> {
+    // The reason of unsafety is same as above.
+    let a = unsafe { std::mem::transmute::<&mut [T], &mut [E::Fr]>(a) };
+    let b = unsafe { std::mem::transmute::<&[T], &[E::Fr]>(b) };
+    kern.sub_assign(a, b)?;
+    Ok(())
+}
+
+pub fn gpu_distribute_powers<E: Engine, T: Group<E>>(
+    kern: &mut gpu::FFTKernel<E>,
+    a: &mut [T],
+    g: &E::Fr,
+) -> gpu::GPUResult<()> {
+    // The reason of unsafety is same as above.
+    let a = unsafe { std::mem::transmute::<&mut [T], &mut [E::Fr]>(a) };
+    kern.distribute_powers(a, g)?;
+    Ok(())
+}
+
+fn bitreverse(mut n: u32, l: u32) -> u32 {
+    let mut r = 0;
+    for _ in 0..l {
+        r = (r << 1) | (n & 1);
+        n >>= 1;
     }
+    r
+}
+
+/// Returns the first `n` powers of `base`.
+fn powers<F: Field>(worker: &Worker, base: F, n: usize) -> Vec<F> {
+    let mut powers = vec![F::zero(); n];
+    worker.scope(n, |scope, chunk| {
+        for (i, powers) in powers.chunks_mut(chunk).enumerate() {
+            scope.spawn(move |_| {
+                let mut current = base.pow(&[(i * chunk) as u64]);
+                for p in powers.iter_mut() {
+                    *p = current;
+                    current.mul_assign(&base);
+                }
+            });
+        }
+    });
+
+    powers
+}
 
+pub fn serial_fft<E: ScalarEngine, T: Group<E>>(a: &mut [T], omega: &E::Fr, log_n: u32) {
     let n = a.len() as u32;
     assert_eq!(n, 1 << log_n);
 
@@ -394,18 +915,61 @@ pub fn serial_fft<E: ScalarEngine, T: Group<E>>(a: &mut [T], omega: &E::Fr, log_
     }
 }
 
+/// Like `serial_fft`, but with the twiddle factors and the bit-reversal
+/// permutation looked up in the tables of an FFT that is `2^shift` times
+/// larger, of which this is a sub-FFT.
+fn serial_fft_with_tables<E: ScalarEngine, T: Group<E>>(
+    a: &mut [T],
+    log_n: u32,
+    tables: FftTables<E::Fr>,
+    shift: u32,
+) {
+    let n = a.len();
+    assert_eq!(n, 1 << log_n);
+
+    for k in 0..n {
+        let rk = (tables.bit_reverse[k] >> shift) as usize;
+        if k < rk {
+            a.swap(rk, k);
+        }
+    }
+
+    let mut m = 1;
+    for _ in 0..log_n {
+        // The j-th power of w_m is at j * step in the twiddle factors.
+        let step = (n / (2 * m)) << shift;
+
+        let mut k = 0;
+        while k < n {
+            for j in 0..m {
+                let mut t = a[k + j + m];
+                t.group_mul_assign(&tables.twiddles[j * step]);
+                let mut tmp = a[k + j];
+                tmp.group_sub_assign(&t);
+                a[k + j + m] = tmp;
+                a[k + j].group_add_assign(&t);
+            }
+
+            k += 2 * m;
+        }
+
+        m *= 2;
+    }
+}
+
 fn parallel_fft<E: ScalarEngine, T: Group<E>>(
     a: &mut [T],
     worker: &Worker,
     omega: &E::Fr,
     log_n: u32,
     log_cpus: u32,
+    tables: Option<FftTables<E::Fr>>,
 ) {
     assert!(log_n >= log_cpus);
 
     let num_cpus = 1 << log_cpus;
     let log_new_n = log_n - log_cpus;
-    let mut tmp = vec![vec![T::group_zero(); 1 << log_new_n]; num_cpus];
+    let mut tmp = vec![vec![]; num_cpus];
     let new_omega = omega.pow(&[num_cpus as u64]);
 
     worker.scope(0, |scope, _| {
@@ -413,6 +977,10 @@ fn parallel_fft<E: ScalarEngine, T: Group<E>>(
 
         for (j, tmp) in tmp.iter_mut().enumerate() {
             scope.spawn(move |_scope| {
+                // Allocated by the thread that uses it, which keeps it on the
+                // same NUMA node.
+                *tmp = vec![T::group_zero(); 1 << log_new_n];
+
                 // Shuffle into a sub-FFT
                 let omega_j = omega.pow(&[j as u64]);
                 let omega_step = omega.pow(&[(j as u64) << log_new_n]);
@@ -430,7 +998,10 @@ fn parallel_fft<E: ScalarEngine, T: Group<E>>(
                 }
 
                 // Perform sub-FFT
-                serial_fft(tmp, &new_omega, log_new_n);
+                match tables {
+                    Some(tables) => serial_fft_with_tables(tmp, log_new_n, tables, log_cpus),
+                    None => serial_fft(tmp, &new_omega, log_new_n),
+                }
             });
         }
     });
@@ -452,15 +1023,103 @@ fn parallel_fft<E: ScalarEngine, T: Group<E>>(
     });
 }
 
+/// Transposes `src`, a matrix with `rows` rows, into `dst`. Each thread writes
+/// a block of rows of `dst`, reading the corresponding block of columns of
+/// `src` row by row.
+fn transpose<T: Copy + Send + Sync>(worker: &Worker, src: &[T], dst: &mut [T], rows: usize) {
+    let cols = src.len() / rows;
+
+    worker.scope(cols, |scope, chunk| {
+        for (i, dst) in dst.chunks_mut(chunk * rows).enumerate() {
+            scope.spawn(move |_scope| {
+                let first = i * chunk;
+                let count = dst.len() / rows;
+                for (row, src) in src.chunks(cols).enumerate() {
+                    for (col, v) in src[first..first + count].iter().enumerate() {
+                        dst[col * rows + row] = *v;
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// The four-step FFT: the input is viewed as a matrix of `n2` rows and `n1`
+/// columns, which are transformed one after the other, both as contiguous
+/// rows of a transposed copy. The sub-FFTs are small enough to run in the
+/// caches and are spread over the threads, unlike the large strided FFT
+/// stages of `serial_fft`.
+fn four_step_fft<E: ScalarEngine, T: Group<E>>(
+    a: &mut [T],
+    worker: &Worker,
+    omega: &E::Fr,
+    log_n: u32,
+    tables: Option<FftTables<E::Fr>>,
+) {
+    let n = a.len();
+    assert_eq!(n, 1 << log_n);
+
+    let log_n1 = log_n / 2;
+    let log_n2 = log_n - log_n1;
+    let n1 = 1 << log_n1;
+    let n2 = 1 << log_n2;
+    let sub_fft = |row: &mut [T], omega: &E::Fr, log_row: u32| match tables {
+        Some(tables) => serial_fft_with_tables(row, log_row, tables, log_n - log_row),
+        None => serial_fft(row, omega, log_row),
+    };
+    let sub_fft = &sub_fft;
+
+    // For j = j1 + n1·j2 and k = n2·k1 + k2, omega^(jk) is
+    // omega_n2^(j2·k2)·omega^(j1·k2)·omega_n1^(j1·k1).
+    let mut b = worker.scratch(n, T::group_zero());
+
+    // Transform the columns j1 over j2, then multiply by omega^(j1·k2).
+    transpose(worker, a, &mut b, n2);
+    let omega_n2 = omega.pow(&[n1 as u64]);
+    worker.scope(n1, |scope, chunk| {
+        for (i, rows) in b.chunks_mut(chunk * n2).enumerate() {
+            scope.spawn(move |_scope| {
+                for (j, row) in rows.chunks_mut(n2).enumerate() {
+                    sub_fft(row, &omega_n2, log_n2);
+
+                    let w = omega.pow(&[(i * chunk + j) as u64]);
+                    let mut twiddle = E::Fr::one();
+                    for v in row.iter_mut() {
+                        v.group_mul_assign(&twiddle);
+                        twiddle.mul_assign(&w);
+                    }
+                }
+            });
+        }
+    });
+
+    // Transform the rows k2 over j1.
+    transpose(worker, &b, a, n1);
+    let omega_n1 = omega.pow(&[n2 as u64]);
+    worker.scope(n2, |scope, chunk| {
+        for rows in a.chunks_mut(chunk * n1) {
+            scope.spawn(move |_scope| {
+                for row in rows.chunks_mut(n1) {
+                    sub_fft(row, &omega_n1, log_n1);
+                }
+            });
+        }
+    });
+
+    // The result at n2·k1 + k2 is in row k2 and column k1.
+    transpose(worker, a, &mut b, n2);
+    a.copy_from_slice(&b);
+}
+
 // Test multiplying various (low degree) polynomials together and
 // comparing with naive evaluations.
 #[cfg(feature = "pairing")]
 #[test]
 fn polynomial_arith() {
-    use paired::bls12_381::Bls12;
+    use crate::bls::Bls12;
     use rand_core::RngCore;
 
-    fn test_mul<E: ScalarEngine, R: RngCore>(rng: &mut R) {
+    fn test_mul<E: Engine, R: RngCore>(rng: &mut R) {
         let worker = Worker::new();
 
         for coeffs_a in 0..70 {
@@ -488,10 +1147,10 @@ fn polynomial_arith() {
                 let mut a = EvaluationDomain::from_coeffs(a).unwrap();
                 let mut b = EvaluationDomain::from_coeffs(b).unwrap();
 
-                a.fft(&worker, &mut None);
-                b.fft(&worker, &mut None);
-                a.mul_assign(&worker, &b);
-                a.ifft(&worker, &mut None);
+                a.fft(&worker, &mut None).unwrap();
+                b.fft(&worker, &mut None).unwrap();
+                a.mul_assign(&worker, &b, &mut None).unwrap();
+                a.ifft(&worker, &mut None).unwrap();
 
                 for (naive, fft) in naive.iter().zip(a.coeffs.iter()) {
                     assert!(naive == fft);
@@ -500,7 +1159,7 @@ fn polynomial_arith() {
         }
     }
 
-    let rng = &mut rand::thread_rng();
+    let rng = &mut crate::testing::test_rng();
 
     test_mul::<Bls12, _>(rng);
 }
@@ -508,10 +1167,10 @@ fn polynomial_arith() {
 #[cfg(feature = "pairing")]
 #[test]
 fn fft_composition() {
-    use paired::bls12_381::Bls12;
+    use crate::bls::Bls12;
     use rand_core::RngCore;
 
-    fn test_comp<E: ScalarEngine, R: RngCore>(rng: &mut R) {
+    fn test_comp<E: Engine, R: RngCore>(rng: &mut R) {
         let worker = Worker::new();
 
         for coeffs in 0..10 {
@@ -523,22 +1182,22 @@ fn fft_composition() {
             }
 
             let mut domain = EvaluationDomain::from_coeffs(v.clone()).unwrap();
-            domain.ifft(&worker, &mut None);
-            domain.fft(&worker, &mut None);
+            domain.ifft(&worker, &mut None).unwrap();
+            domain.fft(&worker, &mut None).unwrap();
             assert!(v == domain.coeffs);
-            domain.fft(&worker, &mut None);
-            domain.ifft(&worker, &mut None);
+            domain.fft(&worker, &mut None).unwrap();
+            domain.ifft(&worker, &mut None).unwrap();
             assert!(v == domain.coeffs);
-            domain.icoset_fft(&worker, &mut None);
-            domain.coset_fft(&worker, &mut None);
+            domain.icoset_fft(&worker, &mut None).unwrap();
+            domain.coset_fft(&worker, &mut None).unwrap();
             assert!(v == domain.coeffs);
-            domain.coset_fft(&worker, &mut None);
-            domain.icoset_fft(&worker, &mut None);
+            domain.coset_fft(&worker, &mut None).unwrap();
+            domain.icoset_fft(&worker, &mut None).unwrap();
             assert!(v == domain.coeffs);
         }
     }
 
-    let rng = &mut rand::thread_rng();
+    let rng = &mut crate::testing::test_rng();
 
     test_comp::<Bls12, _>(rng);
 }
@@ -546,7 +1205,7 @@ fn fft_composition() {
 #[cfg(feature = "pairing")]
 #[test]
 fn parallel_fft_consistency() {
-    use paired::bls12_381::Bls12;
+    use crate::bls::Bls12;
     use rand_core::RngCore;
     use std::cmp::min;
 
@@ -564,7 +1223,7 @@ fn parallel_fft_consistency() {
                 let mut v2 = EvaluationDomain::from_coeffs(v1.coeffs.clone()).unwrap();
 
                 for log_cpus in log_d..min(log_d + 1, 3) {
-                    parallel_fft(&mut v1.coeffs, &worker, &v1.omega, log_d, log_cpus);
+                    parallel_fft(&mut v1.coeffs, &worker, &v1.omega, log_d, log_cpus, None);
                     serial_fft(&mut v2.coeffs, &v2.omega, log_d);
 
                     assert!(v1.coeffs == v2.coeffs);
@@ -573,11 +1232,138 @@ fn parallel_fft_consistency() {
         }
     }
 
-    let rng = &mut rand::thread_rng();
+    let rng = &mut crate::testing::test_rng();
 
     test_consistency::<Bls12, _>(rng);
 }
 
+#[test]
+fn coset_fft_with_shift() {
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut crate::testing::test_rng();
+    let worker = Worker::new();
+
+    let coeffs = (0..16)
+        .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+        .collect::<Vec<_>>();
+    let shift = Fr::random(rng);
+    let mut domain = EvaluationDomain::from_coeffs(coeffs.clone()).unwrap();
+    domain
+        .coset_fft_with_shift(&worker, shift, &mut None)
+        .unwrap();
+
+    // The evaluations are at shift times the powers of omega.
+    let mut point = shift;
+    for evaluation in domain.coeffs.iter() {
+        let mut expected = Fr::zero();
+        for coeff in coeffs.iter().rev() {
+            expected.mul_assign(&point);
+            expected.add_assign(&coeff.0);
+        }
+        assert!(evaluation.0 == expected);
+        point.mul_assign(&domain.omega);
+    }
+
+    domain
+        .icoset_fft_with_shift(&worker, shift, &mut None)
+        .unwrap();
+    assert!(domain.coeffs == coeffs);
+}
+
+#[test]
+fn four_step_fft_consistency() {
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut crate::testing::test_rng();
+    let worker = Worker::new();
+
+    for log_d in 0..12 {
+        let d = 1 << log_d;
+
+        let v1 = (0..d)
+            .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+            .collect::<Vec<_>>();
+        let mut v1 = EvaluationDomain::from_coeffs(v1).unwrap();
+        let mut v2 = EvaluationDomain::from_coeffs(v1.coeffs.clone()).unwrap();
+        let mut v3 = EvaluationDomain::from_coeffs(v1.coeffs.clone()).unwrap();
+        let precomputed = v3.precompute(&worker);
+        let tables = FftTables {
+            twiddles: &precomputed.twiddles,
+            bit_reverse: &precomputed.bit_reverse,
+        };
+
+        four_step_fft(&mut v1.coeffs, &worker, &v1.omega, log_d, None);
+        four_step_fft(&mut v3.coeffs, &worker, &v3.omega, log_d, Some(tables));
+        serial_fft(&mut v2.coeffs, &v2.omega, log_d);
+
+        assert!(v1.coeffs == v2.coeffs);
+        assert!(v3.coeffs == v2.coeffs);
+    }
+}
+
+#[test]
+fn mixed_radix_fft_consistency() {
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut crate::testing::test_rng();
+    let worker = Worker::new();
+
+    for &(len, size) in [(5, 6), (9, 12), (13, 16), (17, 24), (40, 48)].iter() {
+        let coeffs = (0..len)
+            .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+            .collect::<Vec<_>>();
+        let mut domain = EvaluationDomain::from_coeffs_mixed_radix(coeffs.clone()).unwrap();
+        assert_eq!(domain.coeffs.len(), size);
+        assert_eq!(domain.omega.pow(&[size as u64]), Fr::one());
+
+        domain.fft(&worker, &mut None).unwrap();
+
+        // Compare with evaluating the polynomial at the powers of omega.
+        let mut point = Fr::one();
+        for evaluation in domain.coeffs.iter() {
+            let mut expected = Fr::zero();
+            for coeff in coeffs.iter().rev() {
+                expected.mul_assign(&point);
+                expected.add_assign(&coeff.0);
+            }
+            assert!(evaluation.0 == expected);
+            point.mul_assign(&domain.omega);
+        }
+
+        domain.ifft(&worker, &mut None).unwrap();
+        assert!(domain.coeffs[..len] == coeffs[..]);
+    }
+}
+
+#[test]
+fn precomputed_fft_consistency() {
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut crate::testing::test_rng();
+    let worker = Worker::new();
+
+    for &len in [1, 2, 5, 12, 100, 1 << 10].iter() {
+        let coeffs = (0..len)
+            .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+            .collect::<Vec<_>>();
+        let mut v1 = EvaluationDomain::from_coeffs_mixed_radix(coeffs.clone()).unwrap();
+        let mut v2 = EvaluationDomain::from_coeffs_mixed_radix(coeffs).unwrap();
+        v2.set_precomputed(Arc::new(v2.precompute(&worker)));
+
+        v1.ifft(&worker, &mut None).unwrap();
+        v1.coset_fft(&worker, &mut None).unwrap();
+        v1.divide_by_z_on_coset(&worker, &mut None).unwrap();
+        v1.icoset_fft(&worker, &mut None).unwrap();
+        v2.ifft(&worker, &mut None).unwrap();
+        v2.coset_fft(&worker, &mut None).unwrap();
+        v2.divide_by_z_on_coset(&worker, &mut None).unwrap();
+        v2.icoset_fft(&worker, &mut None).unwrap();
+
+        assert!(v1.coeffs == v2.coeffs);
+    }
+}
+
 lazy_static::lazy_static! {
     static ref GPU_FFT_SUPPORTED: Mutex<Option<bool>> = { Mutex::new(None) };
 }
@@ -587,30 +1373,35 @@ pub fn gpu_fft_supported<E>(log_d: u32) -> gpu::GPUResult<gpu::FFTKernel<E>>
 where
     E: Engine,
 {
-    let log_test_size: u32 = std::cmp::min(E::Fr::S - 1, 10);
-    let test_size: u32 = 1 << log_test_size;
-    let rng = &mut rand::thread_rng();
-    let mut kern = gpu::FFTKernel::create(1 << log_d)?;
-
     // Checking the correctness of GPU results can be time consuming. User can disable this
     // feature using BELLMAN_GPU_NO_CHECK flag.
-    if env::var("BELLMAN_GPU_NO_CHECK").is_ok() {
+    gpu_fft_kernel(log_d, env::var("BELLMAN_GPU_NO_CHECK").is_err())
+}
+
+/// Creates an FFT kernel for domains of up to 2<sup>`log_d`</sup> elements.
+/// With `self_test`, the kernel is compared against the CPU the first time
+/// on the machine, and isn't used if it computes something else. The verdict
+/// is cached on disk until the devices, their drivers or the crate change.
+pub fn gpu_fft_kernel<E>(log_d: u32, self_test: bool) -> gpu::GPUResult<gpu::FFTKernel<E>>
+where
+    E: Engine,
+{
+    let mut kern = gpu::FFTKernel::create(1 << log_d)?;
+    if !self_test {
         return Ok(kern);
     }
 
     let res = {
         let mut supported = GPU_FFT_SUPPORTED.lock().unwrap();
+        let kernel = format!("fft {}", std::any::type_name::<E>());
         if let Some(res) = *supported {
             res
+        } else if let Some(res) = gpu::cached_verdict(&kernel) {
+            *supported = Some(res);
+            res
         } else {
-            let elems = (0..test_size)
-                .map(|_| Scalar::<E>(E::Fr::random(rng)))
-                .collect::<Vec<_>>();
-            let mut v1 = EvaluationDomain::from_coeffs(elems.clone()).unwrap();
-            let mut v2 = EvaluationDomain::from_coeffs(elems.clone()).unwrap();
-            gpu_fft(&mut kern, &mut v1.coeffs, &v1.omega, log_test_size)?;
-            serial_fft(&mut v2.coeffs, &v2.omega, log_test_size);
-            let res = v1.coeffs == v2.coeffs;
+            let res = gpu_fft_self_test(&mut kern)?;
+            gpu::cache_verdict(&kernel, res);
             *supported = Some(res);
             res
         }
@@ -619,18 +1410,37 @@ where
     if res {
         Ok(kern)
     } else {
-        Err(gpu::GPUError {
-            msg: "GPU FFT not supported!".to_string(),
-        })
+        Err(gpu::GPUError::WrongResults(
+            "GPU FFT results differ from the CPU, see gpu::diagnostics()".to_string(),
+        ))
     }
 }
 
+/// Compares an FFT of random elements on the GPU against the CPU.
+pub(crate) fn gpu_fft_self_test<E>(kern: &mut gpu::FFTKernel<E>) -> gpu::GPUResult<bool>
+where
+    E: Engine,
+{
+    let log_test_size: u32 = std::cmp::min(E::Fr::S - 1, 10);
+    let test_size: u32 = 1 << log_test_size;
+    let rng = &mut rand::thread_rng();
+    let elems = (0..test_size)
+        .map(|_| Scalar::<E>(E::Fr::random(rng)))
+        .collect::<Vec<_>>();
+    let mut v1 = EvaluationDomain::from_coeffs(elems.clone()).unwrap();
+    let mut v2 = EvaluationDomain::from_coeffs(elems).unwrap();
+    gpu_fft(kern, &mut v1.coeffs, &v1.omega, log_test_size)?;
+    serial_fft(&mut v2.coeffs, &v2.omega, log_test_size);
+
+    Ok(v1.coeffs == v2.coeffs)
+}
+
 #[cfg(feature = "gpu-test")]
 #[test]
 pub fn gpu_fft_consistency() {
-    use paired::bls12_381::{Bls12, Fr};
+    use crate::bls::{Bls12, Fr};
     use std::time::Instant;
-    let rng = &mut rand::thread_rng();
+    let rng = &mut crate::testing::test_rng();
 
     let worker = Worker::new();
     let log_cpus = worker.log_num_cpus();
@@ -656,7 +1466,7 @@ pub fn gpu_fft_consistency() {
         if log_d <= log_cpus {
             serial_fft(&mut v2.coeffs, &v2.omega, log_d);
         } else {
-            parallel_fft(&mut v2.coeffs, &worker, &v2.omega, log_d, log_cpus);
+            parallel_fft(&mut v2.coeffs, &worker, &v2.omega, log_d, log_cpus, None);
         }
         let cpu_dur = now.elapsed().as_secs() * 1000 as u64 + now.elapsed().subsec_millis() as u64;
         println!("CPU ({} cores) took {}ms.", 1 << log_cpus, cpu_dur);
@@ -667,3 +1477,80 @@ pub fn gpu_fft_consistency() {
         println!("============================");
     }
 }
+
+#[cfg(feature = "gpu-test")]
+#[test]
+pub fn gpu_elementwise_consistency() {
+    use crate::bls::{Bls12, Fr};
+    let rng = &mut crate::testing::test_rng();
+
+    let worker = Worker::new();
+    let mut kern = Some(gpu::FFTKernel::create(1 << 16).expect("Cannot initialize kernel!"));
+
+    for log_d in 1..17 {
+        let d = 1 << log_d;
+        let random = |rng: &mut rand::rngs::ThreadRng| {
+            let elems = (0..d)
+                .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+                .collect::<Vec<_>>();
+            EvaluationDomain::from_coeffs(elems).unwrap()
+        };
+        let (a, b, c) = (random(rng), random(rng), random(rng));
+        let g = Fr::random(rng);
+
+        let mut v1 = EvaluationDomain::from_coeffs(a.coeffs.clone()).unwrap();
+        let mut v2 = EvaluationDomain::from_coeffs(a.coeffs).unwrap();
+        for (v, kern) in vec![(&mut v1, &mut kern), (&mut v2, &mut None)] {
+            v.mul_assign(&worker, &b, kern)
+                .expect("Multiplication failed!");
+            v.sub_assign(&worker, &c, kern)
+                .expect("Subtraction failed!");
+            v.distribute_powers(&worker, g, kern)
+                .expect("Distributing powers failed!");
+        }
+
+        assert!(v1.coeffs == v2.coeffs);
+    }
+}
+
+#[cfg(feature = "gpu-test")]
+#[test]
+pub fn gpu_fft_many_consistency() {
+    use crate::bls::{Bls12, Fr};
+    let rng = &mut crate::testing::test_rng();
+
+    let worker = Worker::new();
+    let mut kern = Some(gpu::FFTKernel::create(1 << 16).expect("Cannot initialize kernel!"));
+
+    for log_d in 1..17 {
+        let d = 1 << log_d;
+
+        let polys = (0..3)
+            .map(|_| {
+                (0..d)
+                    .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let domains = || {
+            polys
+                .iter()
+                .map(|elems| EvaluationDomain::from_coeffs(elems.clone()).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let mut batched = domains();
+        let mut single = domains();
+
+        let mut refs = batched.iter_mut().collect::<Vec<_>>();
+        EvaluationDomain::ifft_many(&mut refs, &worker, &mut kern).expect("GPU FFT failed!");
+        EvaluationDomain::coset_fft_many(&mut refs, &worker, &mut kern).expect("GPU FFT failed!");
+        for domain in single.iter_mut() {
+            domain.ifft(&worker, &mut None).unwrap();
+            domain.coset_fft(&worker, &mut None).unwrap();
+        }
+
+        for (batched, single) in batched.iter().zip(single.iter()) {
+            assert!(batched.coeffs == single.coeffs);
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! An [`EvaluationDomain`](super::EvaluationDomain) of scalars that lives in
+//! memory-mapped temporary files instead of memory, for circuits whose
+//! polynomials don't fit into RAM, e.g. of 2<sup>28</sup> constraints and
+//! more. Only a bounded buffer is kept in memory: the FFTs are four-step FFTs
+//! that transform bands of columns and of rows in two passes over the file,
+//! trading time for feasibility.
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::marker::PhantomData;
+use std::mem;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ff::{Field, PrimeField};
+use memmap::MmapMut;
+
+use super::{serial_fft, Group, Scalar};
+use crate::bls::Engine;
+use crate::multicore::Worker;
+use crate::SynthesisError;
+
+/// A vector of field elements in a memory-mapped file, which is deleted on
+/// drop.
+struct MappedVec<F> {
+    map: MmapMut,
+    path: PathBuf,
+    len: usize,
+    _marker: PhantomData<F>,
+}
+
+impl<F: Field> MappedVec<F> {
+    /// A vector of `len` zeros in a new file in `dir`.
+    fn zeroed(dir: &Path, len: usize) -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let path = dir.join(format!(
+            "bellperson-domain-{}-{}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let mut options = OpenOptions::new();
+        options.read(true).write(true).create_new(true);
+        // Only the owner may read the polynomials, which depend on the witness.
+        #[cfg(unix)]
+        options.mode(0o600);
+        let file: File = options.open(&path)?;
+        // A new file reads as zeros, which is the encoding of zero.
+        file.set_len((len * mem::size_of::<F>()) as u64)?;
+        let map = unsafe { MmapMut::map_mut(&file) };
+
+        match map {
+            Ok(map) => Ok(MappedVec {
+                map,
+                path,
+                len,
+                _marker: PhantomData,
+            }),
+            Err(e) => {
+                let _ = fs::remove_file(&path);
+                Err(e)
+            }
+        }
+    }
+
+    // The field elements are plain arrays of limbs, so any bytes of their
+    // size are a valid element, and the map is page-aligned.
+    fn as_slice(&self) -> &[F] {
+        unsafe { slice::from_raw_parts(self.map.as_ptr() as *const F, self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [F] {
+        unsafe { slice::from_raw_parts_mut(self.map.as_mut_ptr() as *mut F, self.len) }
+    }
+}
+
+impl<F> Drop for MappedVec<F> {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// A radix-2 evaluation domain of scalars in memory-mapped files.
+pub struct DiskEvaluationDomain<E: Engine> {
+    coeffs: MappedVec<E::Fr>,
+    dir: PathBuf,
+    /// The number of elements buffered in memory at once.
+    band: usize,
+    exp: u32,
+    omega: E::Fr,
+    omegainv: E::Fr,
+    geninv: E::Fr,
+    minv: E::Fr,
+}
+
+impl<E: Engine> DiskEvaluationDomain<E> {
+    /// Creates a domain of the next power of two fitting the coefficients,
+    /// padded with zeros, in temporary files in `dir`. The FFTs buffer up to
+    /// `memory` bytes of elements in memory, at least a row of the FFTs'
+    /// matrix, which is about the square root of the size of the domain.
+    pub fn from_coeffs<I>(
+        coeffs: I,
+        dir: &Path,
+        memory: usize,
+    ) -> Result<DiskEvaluationDomain<E>, SynthesisError>
+    where
+        I: IntoIterator<Item = E::Fr>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let coeffs = coeffs.into_iter();
+
+        let mut m = 1;
+        let mut exp = 0;
+        while m < coeffs.len() {
+            m *= 2;
+            exp += 1;
+
+            if exp >= E::Fr::S {
+                return Err(SynthesisError::PolynomialDegreeTooLarge);
+            }
+        }
+
+        let mut omega = E::Fr::root_of_unity();
+        for _ in exp..E::Fr::S {
+            omega.square();
+        }
+
+        let mut mapped = MappedVec::zeroed(dir, m)?;
+        for (v, c) in mapped.as_mut_slice().iter_mut().zip(coeffs) {
+            *v = c;
+        }
+
+        Ok(DiskEvaluationDomain {
+            coeffs: mapped,
+            dir: dir.to_path_buf(),
+            band: (memory / mem::size_of::<E::Fr>()).max(1),
+            exp,
+            omega,
+            omegainv: omega.inverse().unwrap(),
+            geninv: E::Fr::multiplicative_generator().inverse().unwrap(),
+            minv: E::Fr::from_str(&format!("{}", m))
+                .unwrap()
+                .inverse()
+                .unwrap(),
+        })
+    }
+
+    /// The elements of the domain, coefficients or evaluations depending on
+    /// the last transform.
+    pub fn coeffs(&self) -> &[E::Fr] {
+        self.coeffs.as_slice()
+    }
+
+    pub fn coeffs_mut(&mut self) -> &mut [E::Fr] {
+        self.coeffs.as_mut_slice()
+    }
+
+    /// The number of elements, which is the size of the domain.
+    pub fn size(&self) -> usize {
+        self.coeffs.len
+    }
+
+    pub fn fft(&mut self, worker: &Worker) -> Result<(), SynthesisError> {
+        let omega = self.omega;
+        self.transform(worker, omega)
+    }
+
+    pub fn ifft(&mut self, worker: &Worker) -> Result<(), SynthesisError> {
+        let omegainv = self.omegainv;
+        self.transform(worker, omegainv)?;
+
+        let minv = self.minv;
+        self.scale(worker, minv);
+        Ok(())
+    }
+
+    pub fn distribute_powers(&mut self, worker: &Worker, g: E::Fr) {
+        let coeffs = self.coeffs.as_mut_slice();
+        worker.scope(coeffs.len(), |scope, chunk| {
+            for (i, v) in coeffs.chunks_mut(chunk).enumerate() {
+                scope.spawn(move |_| {
+                    let mut u = g.pow(&[(i * chunk) as u64]);
+                    for v in v.iter_mut() {
+                        v.mul_assign(&u);
+                        u.mul_assign(&g);
+                    }
+                });
+            }
+        });
+    }
+
+    pub fn coset_fft(&mut self, worker: &Worker) -> Result<(), SynthesisError> {
+        self.distribute_powers(worker, E::Fr::multiplicative_generator());
+        self.fft(worker)
+    }
+
+    pub fn icoset_fft(&mut self, worker: &Worker) -> Result<(), SynthesisError> {
+        let geninv = self.geninv;
+        self.ifft(worker)?;
+        self.distribute_powers(worker, geninv);
+        Ok(())
+    }
+
+    /// Divides by the target polynomial, which is `g^m - 1` everywhere on the
+    /// coset.
+    pub fn divide_by_z_on_coset(&mut self, worker: &Worker) {
+        let mut z = E::Fr::multiplicative_generator().pow(&[self.size() as u64]);
+        z.sub_assign(&E::Fr::one());
+
+        self.scale(worker, z.inverse().unwrap());
+    }
+
+    pub fn mul_assign(&mut self, worker: &Worker, other: &DiskEvaluationDomain<E>) {
+        self.zip_with(worker, other, |a, b| a.mul_assign(b));
+    }
+
+    pub fn sub_assign(&mut self, worker: &Worker, other: &DiskEvaluationDomain<E>) {
+        self.zip_with(worker, other, |a, b| a.sub_assign(b));
+    }
+
+    fn scale(&mut self, worker: &Worker, scalar: E::Fr) {
+        let coeffs = self.coeffs.as_mut_slice();
+        worker.scope(coeffs.len(), |scope, chunk| {
+            for v in coeffs.chunks_mut(chunk) {
+                scope.spawn(move |_| {
+                    for v in v {
+                        v.mul_assign(&scalar);
+                    }
+                });
+            }
+        });
+    }
+
+    fn zip_with<F>(&mut self, worker: &Worker, other: &DiskEvaluationDomain<E>, f: F)
+    where
+        F: Fn(&mut E::Fr, &E::Fr) + Sync,
+    {
+        assert_eq!(self.size(), other.size());
+
+        let f = &f;
+        let coeffs = self.coeffs.as_mut_slice();
+        worker.scope(coeffs.len(), |scope, chunk| {
+            for (a, b) in coeffs
+                .chunks_mut(chunk)
+                .zip(other.coeffs.as_slice().chunks(chunk))
+            {
+                scope.spawn(move |_| {
+                    for (a, b) in a.iter_mut().zip(b.iter()) {
+                        f(a, b);
+                    }
+                });
+            }
+        });
+    }
+
+    /// An FFT with the root of unity `omega`. Domains that fit into the
+    /// buffer are transformed in memory at once.
+    fn transform(&mut self, worker: &Worker, omega: E::Fr) -> Result<(), SynthesisError> {
+        let n = self.size();
+        if n <= self.band {
+            let coeffs = self.coeffs.as_mut_slice();
+            let mut buf = coeffs.iter().map(|&v| Scalar::<E>(v)).collect::<Vec<_>>();
+            super::best_fft(&mut None, &mut buf, worker, &omega, self.exp, None)?;
+            for (v, s) in coeffs.iter_mut().zip(buf) {
+                *v = s.0;
+            }
+            return Ok(());
+        }
+
+        // Like the in-memory four-step FFT: for j = j1 + n1·j2 and
+        // k = n2·k1 + k2, the input is a matrix of n2 rows and n1 columns.
+        let log_n1 = self.exp / 2;
+        let log_n2 = self.exp - log_n1;
+        let n1 = 1 << log_n1;
+        let n2 = 1 << log_n2;
+
+        // First pass: transform bands of columns j1 over j2 and multiply by
+        // omega^(j1·k2), in place.
+        let omega_n2 = omega.pow(&[n1 as u64]);
+        let cols = (self.band / n2).max(1).min(n1);
+        let mut buf = vec![Scalar::<E>(E::Fr::zero()); cols * n2];
+        let coeffs = self.coeffs.as_mut_slice();
+        for c0 in (0..n1).step_by(cols) {
+            let cols = cols.min(n1 - c0);
+            let buf = &mut buf[..cols * n2];
+            for (j2, row) in coeffs.chunks(n1).enumerate() {
+                for (j, v) in row[c0..c0 + cols].iter().enumerate() {
+                    buf[j * n2 + j2] = Scalar(*v);
+                }
+            }
+
+            sub_ffts(worker, buf, n2, |j, col| {
+                serial_fft::<E, _>(col, &omega_n2, log_n2);
+
+                let w = omega.pow(&[(c0 + j) as u64]);
+                let mut twiddle = E::Fr::one();
+                for v in col.iter_mut() {
+                    v.group_mul_assign(&twiddle);
+                    twiddle.mul_assign(&w);
+                }
+            });
+
+            for (k2, row) in coeffs.chunks_mut(n1).enumerate() {
+                for (j, v) in row[c0..c0 + cols].iter_mut().enumerate() {
+                    *v = buf[j * n2 + k2].0;
+                }
+            }
+        }
+
+        // Second pass: transform bands of rows k2 over j1, the result at
+        // n2·k1 + k2 goes into a new file.
+        let omega_n1 = omega.pow(&[n2 as u64]);
+        let rows = (self.band / n1).max(1).min(n2);
+        let mut buf = vec![Scalar::<E>(E::Fr::zero()); rows * n1];
+        let mut result = MappedVec::<E::Fr>::zeroed(&self.dir, n)?;
+        let out = result.as_mut_slice();
+        for (band, src) in coeffs.chunks(rows * n1).enumerate() {
+            let r0 = band * rows;
+            let rows = src.len() / n1;
+            let buf = &mut buf[..src.len()];
+            for (b, v) in buf.iter_mut().zip(src.iter()) {
+                *b = Scalar(*v);
+            }
+
+            sub_ffts(worker, buf, n1, |_, row| {
+                serial_fft::<E, _>(row, &omega_n1, log_n1)
+            });
+
+            for (k1, out) in out.chunks_mut(n2).enumerate() {
+                for (k2, v) in out[r0..r0 + rows].iter_mut().enumerate() {
+                    *v = buf[k2 * n1 + k1].0;
+                }
+            }
+        }
+        self.coeffs = result;
+
+        Ok(())
+    }
+}
+
+/// Runs `f` on every `len` elements of `buf` with their index, spread over
+/// the threads.
+fn sub_ffts<E, F>(worker: &Worker, buf: &mut [Scalar<E>], len: usize, f: F)
+where
+    E: Engine,
+    F: Fn(usize, &mut [Scalar<E>]) + Sync,
+{
+    let f = &f;
+    worker.scope(buf.len() / len, |scope, chunk| {
+        for (i, rows) in buf.chunks_mut(chunk * len).enumerate() {
+            scope.spawn(move |_| {
+                for (j, row) in rows.chunks_mut(len).enumerate() {
+                    f(i * chunk + j, row);
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::{Bls12, Fr};
+    use crate::domain::EvaluationDomain;
+
+    #[test]
+    fn test_disk_domain() {
+        let rng = &mut crate::testing::test_rng();
+        let worker = Worker::new();
+        let dir = std::env::temp_dir();
+
+        for &log_d in &[0, 1, 5, 10] {
+            let d = 1 << log_d;
+            let a = (0..d).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+            let b = (0..d).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+
+            let mut expected =
+                EvaluationDomain::<Bls12, _>::from_coeffs(a.iter().map(|&v| Scalar(v)).collect())
+                    .unwrap();
+            let mut other =
+                EvaluationDomain::<Bls12, _>::from_coeffs(b.iter().map(|&v| Scalar(v)).collect())
+                    .unwrap();
+            expected.coset_fft(&worker, &mut None).unwrap();
+            other.coset_fft(&worker, &mut None).unwrap();
+            expected.mul_assign(&worker, &other, &mut None).unwrap();
+            expected.divide_by_z_on_coset(&worker, &mut None).unwrap();
+            expected.icoset_fft(&worker, &mut None).unwrap();
+
+            // A buffer of 16 elements, so that the larger domains take both
+            // passes.
+            let memory = 16 * mem::size_of::<Fr>();
+            let mut a = DiskEvaluationDomain::<Bls12>::from_coeffs(a, &dir, memory).unwrap();
+            let mut b = DiskEvaluationDomain::<Bls12>::from_coeffs(b, &dir, memory).unwrap();
+            a.coset_fft(&worker).unwrap();
+            b.coset_fft(&worker).unwrap();
+            a.mul_assign(&worker, &b);
+            a.divide_by_z_on_coset(&worker);
+            a.icoset_fft(&worker).unwrap();
+
+            assert_eq!(a.size(), d);
+            assert!(a
+                .coeffs()
+                .iter()
+                .zip(expected.coeffs())
+                .all(|(a, e)| *a == e.0));
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+
+                let mode = fs::metadata(&a.coeffs.path).unwrap().permissions().mode();
+                assert_eq!(mode & 0o777, 0o600);
+            }
+
+            let path = a.coeffs.path.clone();
+            drop(a);
+            assert!(!path.exists());
+        }
+    }
+}
@@ -0,0 +1,337 @@
+//! A C interface for verifying Groth16 proofs over BLS12-381, so that services
+//! written in other languages can verify proofs by linking against a `cdylib`
+//! that re-exports this module.
+//!
+//! Verifying keys and proofs use the encodings of [`VerifyingKey::write`] and
+//! [`Proof::write`], public inputs are concatenated big-endian scalars of 32
+//! bytes. The functions return [`BELLPERSON_VALID`] or [`BELLPERSON_INVALID`]
+//! for well-formed arguments and one of the negative error codes otherwise.
+//! They never unwind into the caller.
+//!
+//! [`VerifyingKey::write`]: crate::groth16::VerifyingKey::write
+//! [`Proof::write`]: crate::groth16::Proof::write
+
+use std::io;
+use std::os::raw::c_int;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use crate::bls::{Bls12, Fr, FrRepr};
+use ff::{PrimeField, PrimeFieldRepr};
+
+use crate::groth16::{
+    prepare_verifying_key, verify_proof, PreparedVerifyingKey, Proof, VerifyingKey,
+};
+
+/// The proof is valid.
+pub const BELLPERSON_VALID: c_int = 1;
+/// The proof is well-formed, but invalid.
+pub const BELLPERSON_INVALID: c_int = 0;
+/// The verifying key can't be decoded.
+pub const BELLPERSON_MALFORMED_VERIFYING_KEY: c_int = -1;
+/// The proof can't be decoded.
+pub const BELLPERSON_MALFORMED_PROOF: c_int = -2;
+/// The public inputs can't be decoded or don't match the verifying key.
+pub const BELLPERSON_MALFORMED_INPUTS: c_int = -3;
+/// A null pointer was passed or verification panicked.
+pub const BELLPERSON_INTERNAL_ERROR: c_int = -4;
+
+/// An opaque verifying key, prepared for verification.
+pub struct BellpersonVerifyingKey(PreparedVerifyingKey<Bls12>);
+
+/// An opaque proof.
+pub struct BellpersonProof(Proof<Bls12>);
+
+unsafe fn bytes<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    if len == 0 {
+        Some(&[])
+    } else if ptr.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(ptr, len))
+    }
+}
+
+/// Reads a value that has to span all of `bytes`.
+fn read_exact<T>(mut bytes: &[u8], read: impl FnOnce(&mut &[u8]) -> io::Result<T>) -> Option<T> {
+    let value = read(&mut bytes).ok()?;
+    if bytes.is_empty() {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+fn read_inputs(bytes: &[u8]) -> Option<Vec<Fr>> {
+    if bytes.len() % 32 != 0 {
+        return None;
+    }
+
+    bytes
+        .chunks(32)
+        .map(|chunk| {
+            let mut repr = FrRepr::default();
+            repr.read_be(chunk).ok()?;
+            Fr::from_repr(repr).ok()
+        })
+        .collect()
+}
+
+fn verify(pvk: &PreparedVerifyingKey<Bls12>, proof: &Proof<Bls12>, inputs: &[u8]) -> c_int {
+    let inputs = match read_inputs(inputs) {
+        Some(inputs) => inputs,
+        None => return BELLPERSON_MALFORMED_INPUTS,
+    };
+
+    match verify_proof(pvk, proof, &inputs) {
+        Ok(true) => BELLPERSON_VALID,
+        Ok(false) => BELLPERSON_INVALID,
+        Err(_) => BELLPERSON_MALFORMED_INPUTS,
+    }
+}
+
+fn catch(f: impl FnOnce() -> c_int) -> c_int {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(BELLPERSON_INTERNAL_ERROR)
+}
+
+/// Verifies an encoded proof against an encoded verifying key and public
+/// inputs.
+///
+/// # Safety
+///
+/// Every pointer has to be valid for reads of its length, it may only be null
+/// if the length is zero.
+#[no_mangle]
+pub unsafe extern "C" fn bellperson_verify_proof(
+    vk: *const u8,
+    vk_len: usize,
+    proof: *const u8,
+    proof_len: usize,
+    inputs: *const u8,
+    inputs_len: usize,
+) -> c_int {
+    catch(|| {
+        let (vk, proof, inputs) = match (
+            bytes(vk, vk_len),
+            bytes(proof, proof_len),
+            bytes(inputs, inputs_len),
+        ) {
+            (Some(vk), Some(proof), Some(inputs)) => (vk, proof, inputs),
+            _ => return BELLPERSON_INTERNAL_ERROR,
+        };
+
+        let vk = match read_exact(vk, |r| VerifyingKey::<Bls12>::read(r)) {
+            Some(vk) => vk,
+            None => return BELLPERSON_MALFORMED_VERIFYING_KEY,
+        };
+        let proof = match read_exact(proof, |r| Proof::<Bls12>::read(r)) {
+            Some(proof) => proof,
+            None => return BELLPERSON_MALFORMED_PROOF,
+        };
+
+        verify(&prepare_verifying_key(&vk), &proof, inputs)
+    })
+}
+
+/// Decodes and prepares a verifying key, which pays off when verifying many
+/// proofs against it. Returns null if it can't be decoded. The key has to be
+/// freed with [`bellperson_verifying_key_free`].
+///
+/// # Safety
+///
+/// `vk` has to be valid for reads of `vk_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bellperson_verifying_key_read(
+    vk: *const u8,
+    vk_len: usize,
+) -> *mut BellpersonVerifyingKey {
+    panic::catch_unwind(|| {
+        bytes(vk, vk_len)
+            .and_then(|vk| read_exact(vk, |r| VerifyingKey::<Bls12>::read(r)))
+            .map(|vk| BellpersonVerifyingKey(prepare_verifying_key(&vk)))
+            .map(|vk| Box::into_raw(Box::new(vk)))
+            .unwrap_or(ptr::null_mut())
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Frees a key returned by [`bellperson_verifying_key_read`], null is ignored.
+///
+/// # Safety
+///
+/// `vk` has to be null or returned by [`bellperson_verifying_key_read`] and
+/// not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn bellperson_verifying_key_free(vk: *mut BellpersonVerifyingKey) {
+    if !vk.is_null() {
+        drop(Box::from_raw(vk));
+    }
+}
+
+/// Decodes a proof, checking that its points are in the subgroup. Returns null
+/// if it can't be decoded. The proof has to be freed with
+/// [`bellperson_proof_free`].
+///
+/// # Safety
+///
+/// `proof` has to be valid for reads of `proof_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bellperson_proof_read(
+    proof: *const u8,
+    proof_len: usize,
+) -> *mut BellpersonProof {
+    panic::catch_unwind(|| {
+        bytes(proof, proof_len)
+            .and_then(|proof| read_exact(proof, |r| Proof::<Bls12>::read(r)))
+            .map(|proof| Box::into_raw(Box::new(BellpersonProof(proof))))
+            .unwrap_or(ptr::null_mut())
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Frees a proof returned by [`bellperson_proof_read`], null is ignored.
+///
+/// # Safety
+///
+/// `proof` has to be null or returned by [`bellperson_proof_read`] and not be
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn bellperson_proof_free(proof: *mut BellpersonProof) {
+    if !proof.is_null() {
+        drop(Box::from_raw(proof));
+    }
+}
+
+/// Verifies a decoded proof against a decoded verifying key and encoded
+/// public inputs.
+///
+/// # Safety
+///
+/// `vk` and `proof` have to be returned by [`bellperson_verifying_key_read`]
+/// and [`bellperson_proof_read`] and not be freed yet. `inputs` has to be
+/// valid for reads of `inputs_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bellperson_verify_prepared(
+    vk: *const BellpersonVerifyingKey,
+    proof: *const BellpersonProof,
+    inputs: *const u8,
+    inputs_len: usize,
+) -> c_int {
+    catch(
+        || match (vk.as_ref(), proof.as_ref(), bytes(inputs, inputs_len)) {
+            (Some(vk), Some(proof), Some(inputs)) => verify(&vk.0, &proof.0, inputs),
+            _ => BELLPERSON_INTERNAL_ERROR,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ff::Field;
+
+    use crate::groth16::{create_random_proof, generate_random_parameters};
+    use crate::{Circuit, ConstraintSystem, SynthesisError};
+
+    /// Proves knowledge of a square root of the public input.
+    struct Square(Option<Fr>);
+
+    impl Circuit<Bls12> for Square {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let root = cs.alloc(
+                || "root",
+                || self.0.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            let square = cs.alloc_input(
+                || "square",
+                || {
+                    let mut square = self.0.ok_or(SynthesisError::AssignmentMissing)?;
+                    square.square();
+                    Ok(square)
+                },
+            )?;
+            cs.enforce(
+                || "square",
+                |lc| lc + root,
+                |lc| lc + root,
+                |lc| lc + square,
+            );
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_ffi_verify() {
+        let rng = &mut crate::testing::test_rng();
+        let params = generate_random_parameters::<Bls12, _, _>(Square(None), rng).unwrap();
+        let root = Fr::from_str("3").unwrap();
+        let proof = create_random_proof(Square(Some(root)), &params, rng).unwrap();
+
+        let mut vk = vec![];
+        params.vk.write(&mut vk).unwrap();
+        let mut encoded = vec![];
+        proof.write(&mut encoded).unwrap();
+        let mut inputs = vec![];
+        Fr::from_str("9")
+            .unwrap()
+            .into_repr()
+            .write_be(&mut inputs)
+            .unwrap();
+        let mut wrong = vec![];
+        Fr::from_str("10")
+            .unwrap()
+            .into_repr()
+            .write_be(&mut wrong)
+            .unwrap();
+
+        unsafe {
+            let verify = |vk: &[u8], proof: &[u8], inputs: &[u8]| {
+                bellperson_verify_proof(
+                    vk.as_ptr(),
+                    vk.len(),
+                    proof.as_ptr(),
+                    proof.len(),
+                    inputs.as_ptr(),
+                    inputs.len(),
+                )
+            };
+            assert_eq!(verify(&vk, &encoded, &inputs), BELLPERSON_VALID);
+            assert_eq!(verify(&vk, &encoded, &wrong), BELLPERSON_INVALID);
+            assert_eq!(
+                verify(&vk[1..], &encoded, &inputs),
+                BELLPERSON_MALFORMED_VERIFYING_KEY
+            );
+            assert_eq!(
+                verify(&vk, &encoded[..encoded.len() - 1], &inputs),
+                BELLPERSON_MALFORMED_PROOF
+            );
+            assert_eq!(
+                verify(&vk, &encoded, &inputs[1..]),
+                BELLPERSON_MALFORMED_INPUTS
+            );
+            assert_eq!(verify(&vk, &encoded, &[]), BELLPERSON_MALFORMED_INPUTS);
+
+            let pvk = bellperson_verifying_key_read(vk.as_ptr(), vk.len());
+            let read = bellperson_proof_read(encoded.as_ptr(), encoded.len());
+            assert!(!pvk.is_null() && !read.is_null());
+            assert_eq!(
+                bellperson_verify_prepared(pvk, read, inputs.as_ptr(), inputs.len()),
+                BELLPERSON_VALID
+            );
+            assert_eq!(
+                bellperson_verify_prepared(pvk, ptr::null(), inputs.as_ptr(), inputs.len()),
+                BELLPERSON_INTERNAL_ERROR
+            );
+            bellperson_proof_free(read);
+            bellperson_verifying_key_free(pvk);
+
+            assert!(bellperson_proof_read(encoded.as_ptr(), 1).is_null());
+        }
+    }
+}
@@ -4,11 +4,22 @@ pub mod test;
 
 pub mod blake2s;
 pub mod boolean;
+pub mod circom;
+pub mod ecc;
+#[cfg(feature = "groth16")]
+pub mod groth16;
 pub mod lookup;
+pub mod merkle;
 pub mod multieq;
 pub mod multipack;
 pub mod num;
+pub mod pedersen_hash;
+pub mod poseidon;
+pub mod range;
+pub mod recording;
+pub mod schnorr;
 pub mod sha256;
+pub mod shape;
 pub mod uint32;
 
 use crate::SynthesisError;
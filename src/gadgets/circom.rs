@@ -0,0 +1,496 @@
+//! Support for circuits compiled by [circom].
+//!
+//! An [`R1csFile`] is loaded from circom's binary `.r1cs` format, described in
+//! [`super::recording`]. Together with a witness it becomes a [`CircomCircuit`]
+//! that replays the constraints into any constraint system, e.g. for generating
+//! parameters or creating Groth16 proofs. Witnesses computed by circom can be
+//! loaded from `.wtns` files with [`read_witness`].
+//!
+//! [circom]: https://github.com/iden3/circom
+
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use ff::{PrimeField, PrimeFieldRepr, ScalarEngine};
+
+use super::recording::{
+    field_size, WireConstraint, R1CS_CONSTRAINTS_SECTION, R1CS_HEADER_SECTION, R1CS_MAGIC,
+    R1CS_VERSION,
+};
+use crate::{Circuit, ConstraintSystem, LinearCombination, SynthesisError};
+
+const WTNS_MAGIC: &[u8; 4] = b"wtns";
+const WTNS_VERSION: u32 = 2;
+const WTNS_HEADER_SECTION: u32 = 1;
+const WTNS_WITNESS_SECTION: u32 = 2;
+
+fn invalid_data<T>(msg: &str) -> io::Result<T> {
+    Err(io::Error::new(io::ErrorKind::InvalidData, msg))
+}
+
+/// Reads a field element in little-endian byte order.
+pub(crate) fn read_field_element<F: PrimeField, R: Read>(mut reader: R) -> io::Result<F> {
+    let mut repr = F::Repr::default();
+    repr.read_le(&mut reader)?;
+
+    match F::from_repr(repr) {
+        Ok(value) => Ok(value),
+        Err(_) => invalid_data("field element is not in the field"),
+    }
+}
+
+/// Checks that a serialized field definition matches `F`.
+pub(crate) fn read_field_definition<F: PrimeField, R: Read>(mut reader: R) -> io::Result<()> {
+    let n8 = reader.read_u32::<LittleEndian>()? as usize;
+    if n8 != field_size::<F>() {
+        return invalid_data("field element size does not match the scalar field");
+    }
+
+    let mut prime = F::Repr::default();
+    prime.read_le(&mut reader)?;
+    if prime != F::char() {
+        return invalid_data("prime does not match the scalar field");
+    }
+
+    Ok(())
+}
+
+/// Reads a file in the section based format shared by `.r1cs` and `.wtns`
+/// files and returns the sections in the order they appear.
+fn read_sections<R: Read>(
+    mut reader: R,
+    magic: &[u8; 4],
+    version: u32,
+) -> io::Result<Vec<(u32, Vec<u8>)>> {
+    let mut actual_magic = [0u8; 4];
+    reader.read_exact(&mut actual_magic)?;
+    if &actual_magic != magic {
+        return invalid_data("unexpected file type");
+    }
+    if reader.read_u32::<LittleEndian>()? != version {
+        return invalid_data("unsupported version");
+    }
+    let num_sections = reader.read_u32::<LittleEndian>()?;
+
+    let mut sections = vec![];
+    for _ in 0..num_sections {
+        let section_type = reader.read_u32::<LittleEndian>()?;
+        let size = reader.read_u64::<LittleEndian>()?;
+        let mut content = vec![];
+        (&mut reader).take(size).read_to_end(&mut content)?;
+        if content.len() as u64 != size {
+            return invalid_data("truncated section");
+        }
+        sections.push((section_type, content));
+    }
+
+    Ok(sections)
+}
+
+/// The constraints of a circuit, loaded from an `.r1cs` file.
+pub struct R1csFile<E: ScalarEngine> {
+    num_wires: usize,
+    num_pub_out: usize,
+    num_pub_in: usize,
+    num_prv_in: usize,
+    constraints: Vec<WireConstraint<E::Fr>>,
+}
+
+impl<E: ScalarEngine> R1csFile<E> {
+    pub fn read<R: Read>(reader: R) -> io::Result<Self> {
+        // The sections may come in any order, but the constraints can only be
+        // parsed once the header is known.
+        let mut header = None;
+        let mut constraints_section = None;
+        for (section_type, content) in read_sections(reader, R1CS_MAGIC, R1CS_VERSION)? {
+            match section_type {
+                R1CS_HEADER_SECTION => header = Some(content),
+                R1CS_CONSTRAINTS_SECTION => constraints_section = Some(content),
+                // Other sections, like the wire to label map, aren't needed.
+                _ => {}
+            }
+        }
+
+        let header = match header {
+            Some(header) => header,
+            None => return invalid_data("missing header section"),
+        };
+        let mut header = &header[..];
+        read_field_definition::<E::Fr, _>(&mut header)?;
+        let num_wires = header.read_u32::<LittleEndian>()? as usize;
+        let num_pub_out = header.read_u32::<LittleEndian>()? as usize;
+        let num_pub_in = header.read_u32::<LittleEndian>()? as usize;
+        let num_prv_in = header.read_u32::<LittleEndian>()? as usize;
+        let _num_labels = header.read_u64::<LittleEndian>()?;
+        let num_constraints = header.read_u32::<LittleEndian>()? as usize;
+
+        if num_wires == 0 || 1 + num_pub_out + num_pub_in + num_prv_in > num_wires {
+            return invalid_data("inconsistent number of wires");
+        }
+
+        let constraints_section = match constraints_section {
+            Some(constraints) => constraints,
+            None => return invalid_data("missing constraints section"),
+        };
+        let mut content = &constraints_section[..];
+        let mut read_lc = || -> io::Result<Vec<(usize, E::Fr)>> {
+            let num_terms = content.read_u32::<LittleEndian>()?;
+            (0..num_terms)
+                .map(|_| -> io::Result<(usize, E::Fr)> {
+                    let wire = content.read_u32::<LittleEndian>()? as usize;
+                    if wire >= num_wires {
+                        return invalid_data("wire out of range");
+                    }
+                    let coeff = read_field_element(&mut content)?;
+                    Ok((wire, coeff))
+                })
+                .collect()
+        };
+        let constraints = (0..num_constraints)
+            .map(|_| -> io::Result<WireConstraint<E::Fr>> {
+                Ok((read_lc()?, read_lc()?, read_lc()?))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(R1csFile {
+            num_wires,
+            num_pub_out,
+            num_pub_in,
+            num_prv_in,
+            constraints,
+        })
+    }
+
+    /// The number of wires, including the constant `ONE` on wire `0`.
+    pub fn num_wires(&self) -> usize {
+        self.num_wires
+    }
+
+    /// The number of public inputs of the Groth16 circuit, i.e. the public
+    /// outputs and the public inputs of the circom circuit, excluding `ONE`.
+    pub fn num_public(&self) -> usize {
+        self.num_pub_out + self.num_pub_in
+    }
+
+    pub fn num_private_inputs(&self) -> usize {
+        self.num_prv_in
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    pub fn constraints(&self) -> &[WireConstraint<E::Fr>] {
+        &self.constraints
+    }
+}
+
+/// A circom circuit that can be synthesized. Without a witness it's only
+/// good for generating parameters.
+pub struct CircomCircuit<'a, E: ScalarEngine> {
+    pub r1cs: &'a R1csFile<E>,
+    /// The assignment of every wire, starting with `ONE`.
+    pub witness: Option<Vec<E::Fr>>,
+}
+
+impl<'a, E: ScalarEngine> Circuit<E> for CircomCircuit<'a, E> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let r1cs = self.r1cs;
+        let witness = self.witness;
+        if let Some(ref witness) = witness {
+            if witness.len() != r1cs.num_wires {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+        }
+
+        let value = |wire: usize| match witness {
+            Some(ref witness) => Ok(witness[wire]),
+            None => Err(SynthesisError::AssignmentMissing),
+        };
+
+        let mut vars = Vec::with_capacity(r1cs.num_wires);
+        vars.push(CS::one());
+        for wire in 1..r1cs.num_wires {
+            let var = if wire <= r1cs.num_public() {
+                cs.alloc_input(|| format!("public {}", wire), || value(wire))?
+            } else {
+                cs.alloc(|| format!("private {}", wire), || value(wire))?
+            };
+            vars.push(var);
+        }
+
+        let lc = |terms: &[(usize, E::Fr)]| -> LinearCombination<E> {
+            terms
+                .iter()
+                .fold(LinearCombination::zero(), |lc, &(wire, coeff)| {
+                    lc + (coeff, vars[wire])
+                })
+        };
+        for (i, (a, b, c)) in r1cs.constraints.iter().enumerate() {
+            cs.enforce(
+                || format!("constraint {}", i),
+                |_| lc(a),
+                |_| lc(b),
+                |_| lc(c),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the public inputs of a witness, as expected by the verifier.
+pub fn public_inputs<E: ScalarEngine>(r1cs: &R1csFile<E>, witness: &[E::Fr]) -> Vec<E::Fr> {
+    witness[1..=r1cs.num_public()].to_vec()
+}
+
+/// Reads a witness from circom's binary `.wtns` format. All integers are
+/// little-endian:
+///
+/// ```text
+/// "wtns" | version: u32 = 2 | number of sections: u32
+/// every section: type: u32 | size in bytes: u64 | content
+///
+/// header (type 1):
+///     field element size n8: u32 | prime: n8 bytes | number of values: u32
+/// witness (type 2):
+///     every value: n8 bytes
+/// ```
+///
+/// The witness assigns every wire, starting with `ONE`, so it can be used as
+/// the witness of a [`CircomCircuit`] directly.
+pub fn read_witness<F: PrimeField, R: Read>(reader: R) -> io::Result<Vec<F>> {
+    let mut header = None;
+    let mut values = None;
+    for (section_type, content) in read_sections(reader, WTNS_MAGIC, WTNS_VERSION)? {
+        match section_type {
+            WTNS_HEADER_SECTION => header = Some(content),
+            WTNS_WITNESS_SECTION => values = Some(content),
+            _ => {}
+        }
+    }
+
+    let header = match header {
+        Some(header) => header,
+        None => return invalid_data("missing header section"),
+    };
+    let mut header = &header[..];
+    read_field_definition::<F, _>(&mut header)?;
+    let num_values = header.read_u32::<LittleEndian>()? as usize;
+
+    let values = match values {
+        Some(values) => values,
+        None => return invalid_data("missing witness section"),
+    };
+    if values.len() != num_values * field_size::<F>() {
+        return invalid_data("inconsistent number of values");
+    }
+    let mut values = &values[..];
+    let witness = (0..num_values)
+        .map(|_| read_field_element(&mut values))
+        .collect::<io::Result<Vec<F>>>()?;
+
+    if witness.first() != Some(&F::one()) {
+        return invalid_data("the first value must be one");
+    }
+
+    Ok(witness)
+}
+
+/// Writes a witness, e.g. the one of a [`RecordingCS`], in the `.wtns` format
+/// described in [`read_witness`].
+///
+/// [`RecordingCS`]: super::recording::RecordingCS
+pub fn write_witness<F: PrimeField, W: Write>(mut writer: W, witness: &[F]) -> io::Result<()> {
+    let mut header = vec![];
+    header.write_u32::<LittleEndian>(field_size::<F>() as u32)?;
+    F::char().write_le(&mut header)?;
+    header.write_u32::<LittleEndian>(witness.len() as u32)?;
+
+    let mut values = vec![];
+    for value in witness {
+        value.into_repr().write_le(&mut values)?;
+    }
+
+    writer.write_all(WTNS_MAGIC)?;
+    writer.write_u32::<LittleEndian>(WTNS_VERSION)?;
+    writer.write_u32::<LittleEndian>(2)?;
+    for (section_type, content) in &[
+        (WTNS_HEADER_SECTION, header),
+        (WTNS_WITNESS_SECTION, values),
+    ] {
+        writer.write_u32::<LittleEndian>(*section_type)?;
+        writer.write_u64::<LittleEndian>(content.len() as u64)?;
+        writer.write_all(content)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bls::{Bls12, Fr};
+    use ff::Field;
+
+    use super::*;
+    use crate::gadgets::num::AllocatedNum;
+    use crate::gadgets::recording::RecordingCS;
+    use crate::gadgets::test::TestConstraintSystem;
+
+    /// Records `x^3 + x + 5 = out` with a public output.
+    fn record_cubic(x: &str) -> RecordingCS<Bls12> {
+        let mut cs = RecordingCS::<Bls12>::new();
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(Fr::from_str(x).unwrap())).unwrap();
+        let x2 = x.square(cs.namespace(|| "x^2")).unwrap();
+        let x3 = x2.mul(cs.namespace(|| "x^3"), &x).unwrap();
+        let out = cs
+            .alloc_input(
+                || "out",
+                || {
+                    let mut tmp = x3.get_value().unwrap();
+                    tmp.add_assign(&x.get_value().unwrap());
+                    tmp.add_assign(&Fr::from_str("5").unwrap());
+                    Ok(tmp)
+                },
+            )
+            .unwrap();
+        cs.enforce(
+            || "out",
+            |lc| {
+                lc + x3.get_variable()
+                    + x.get_variable()
+                    + (Fr::from_str("5").unwrap(), RecordingCS::<Bls12>::one())
+            },
+            |lc| lc + RecordingCS::<Bls12>::one(),
+            |lc| lc + out,
+        );
+
+        cs
+    }
+
+    #[test]
+    fn test_r1cs_roundtrip() {
+        let recorded = record_cubic("3");
+        let mut buf = vec![];
+        recorded.write_r1cs(&mut buf).unwrap();
+
+        let r1cs = R1csFile::<Bls12>::read(&buf[..]).unwrap();
+        assert_eq!(r1cs.num_wires(), recorded.num_wires());
+        assert_eq!(r1cs.num_public(), 1);
+        assert_eq!(r1cs.num_constraints(), 3);
+        assert_eq!(r1cs.constraints(), &recorded.wire_constraints()[..]);
+
+        let witness = recorded.witness().unwrap();
+        assert_eq!(
+            public_inputs(&r1cs, &witness),
+            vec![Fr::from_str("35").unwrap()]
+        );
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        CircomCircuit {
+            r1cs: &r1cs,
+            witness: Some(witness.clone()),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 3);
+        assert!(cs.verify(&[Fr::from_str("35").unwrap()]));
+
+        // A wrong witness doesn't satisfy the circuit.
+        let mut wrong = witness;
+        wrong[1] = Fr::from_str("36").unwrap();
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        CircomCircuit {
+            r1cs: &r1cs,
+            witness: Some(wrong),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        assert!(!cs.is_satisfied());
+
+        // Replaying the circuit leads to the same constraints again.
+        let mut replayed = RecordingCS::<Bls12>::new();
+        CircomCircuit {
+            r1cs: &r1cs,
+            witness: None,
+        }
+        .synthesize(&mut replayed)
+        .unwrap();
+        assert_eq!(replayed.wire_constraints(), recorded.wire_constraints());
+    }
+
+    #[test]
+    fn test_r1cs_invalid() {
+        let mut buf = vec![];
+        record_cubic("3").write_r1cs(&mut buf).unwrap();
+
+        let mut wrong_magic = buf.clone();
+        wrong_magic[0] = b'x';
+        assert!(R1csFile::<Bls12>::read(&wrong_magic[..]).is_err());
+
+        let truncated = &buf[..buf.len() - 1];
+        assert!(R1csFile::<Bls12>::read(truncated).is_err());
+
+        // A different prime.
+        let mut wrong_prime = buf.clone();
+        wrong_prime[28] ^= 1;
+        assert!(R1csFile::<Bls12>::read(&wrong_prime[..]).is_err());
+
+        let mut no_sections = vec![];
+        no_sections.extend_from_slice(b"r1cs");
+        no_sections.write_u32::<LittleEndian>(1).unwrap();
+        no_sections.write_u32::<LittleEndian>(0).unwrap();
+        assert!(R1csFile::<Bls12>::read(&no_sections[..]).is_err());
+    }
+
+    #[test]
+    fn test_wtns_roundtrip() {
+        let recorded = record_cubic("3");
+        let mut buf = vec![];
+        recorded.write_r1cs(&mut buf).unwrap();
+        let r1cs = R1csFile::<Bls12>::read(&buf[..]).unwrap();
+
+        let witness = recorded.witness().unwrap();
+        let mut buf = vec![];
+        write_witness(&mut buf, &witness).unwrap();
+        assert_eq!(&buf[..4], b"wtns");
+        assert_eq!(buf.len(), 12 + 2 * 12 + (4 + 32 + 4) + 5 * 32);
+
+        let read = read_witness::<Fr, _>(&buf[..]).unwrap();
+        assert_eq!(read, witness);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        CircomCircuit {
+            r1cs: &r1cs,
+            witness: Some(read),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_wtns_invalid() {
+        let witness = record_cubic("3").witness().unwrap();
+        let mut buf = vec![];
+        write_witness(&mut buf, &witness).unwrap();
+
+        // An `.r1cs` file isn't a witness.
+        let mut r1cs = vec![];
+        record_cubic("3").write_r1cs(&mut r1cs).unwrap();
+        assert!(read_witness::<Fr, _>(&r1cs[..]).is_err());
+
+        let mut wrong_version = buf.clone();
+        wrong_version[4] = 1;
+        assert!(read_witness::<Fr, _>(&wrong_version[..]).is_err());
+
+        let truncated = &buf[..buf.len() - 1];
+        assert!(read_witness::<Fr, _>(truncated).is_err());
+
+        // The witness must start with `ONE`.
+        let mut without_one = vec![];
+        write_witness(&mut without_one, &witness[1..]).unwrap();
+        assert!(read_witness::<Fr, _>(&without_one[..]).is_err());
+    }
+}
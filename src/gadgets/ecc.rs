@@ -0,0 +1,814 @@
+//! Arithmetic on a twisted Edwards curve `-x^2 + y^2 = 1 + d x^2 y^2` that is
+//! defined over the scalar field, both natively and as circuits.
+//!
+//! With [`EdwardsParams::jubjub`] and the scalar field of BLS12-381 this is the
+//! [Jubjub] curve. The addition formulas are complete as long as `d` is not a
+//! square, which is the case for Jubjub.
+//!
+//! [Jubjub]: https://z.cash/technology/jubjub/
+
+use blake2s_simd::Params as Blake2sParams;
+use byteorder::{ByteOrder, LittleEndian};
+use ff::{Field, PrimeField, PrimeFieldRepr, ScalarEngine, SqrtField};
+
+use super::boolean::Boolean;
+use super::lookup::lookup3_xy;
+use super::num::AllocatedNum;
+use super::Assignment;
+use crate::{ConstraintSystem, SynthesisError};
+
+/// The parameters of the embedded curve.
+pub struct EdwardsParams<E: ScalarEngine> {
+    d: E::Fr,
+    cofactor_log2: usize,
+    order: E::Fr,
+}
+
+impl<E: ScalarEngine> Clone for EdwardsParams<E> {
+    fn clone(&self) -> Self {
+        EdwardsParams {
+            d: self.d,
+            cofactor_log2: self.cofactor_log2,
+            order: self.order,
+        }
+    }
+}
+
+impl<E: ScalarEngine> EdwardsParams<E> {
+    /// Creates the parameters of a curve with the given `d`, a cofactor of
+    /// `2^cofactor_log2` and a prime-order subgroup of the given `order`, which
+    /// has to be smaller than the modulus of the scalar field.
+    pub fn new(d: E::Fr, cofactor_log2: usize, order: E::Fr) -> Self {
+        EdwardsParams {
+            d,
+            cofactor_log2,
+            order,
+        }
+    }
+
+    /// The parameters of Jubjub, `d = -(10240/10241)` with a cofactor of 8.
+    pub fn jubjub() -> Self {
+        let mut d = E::Fr::from_str("10240").unwrap();
+        d.mul_assign(&E::Fr::from_str("10241").unwrap().inverse().unwrap());
+        d.negate();
+
+        let order = E::Fr::from_str(
+            "6554484396890773809930967563523245729705921265872317281365359162392183254199",
+        )
+        .unwrap();
+
+        EdwardsParams::new(d, 3, order)
+    }
+
+    pub fn d(&self) -> &E::Fr {
+        &self.d
+    }
+
+    /// The order of the prime-order subgroup.
+    pub fn order(&self) -> &E::Fr {
+        &self.order
+    }
+}
+
+/// Hashes to a point in the prime-order subgroup with try-and-increment. The
+/// `index` allows deriving several independent points from the same
+/// personalization.
+pub fn group_hash<E: ScalarEngine>(
+    params: &EdwardsParams<E>,
+    personalization: &[u8; 8],
+    index: usize,
+) -> EdwardsPoint<E> {
+    let mut counter = 0u32;
+    loop {
+        let mut buf = [0u8; 8];
+        LittleEndian::write_u32(&mut buf[0..4], index as u32);
+        LittleEndian::write_u32(&mut buf[4..8], counter);
+        counter += 1;
+
+        let hash = Blake2sParams::new()
+            .hash_length(32)
+            .personal(personalization)
+            .hash(&buf);
+
+        let mut repr = <E::Fr as PrimeField>::Repr::default();
+        repr.read_le(hash.as_bytes())
+            .expect("the hash has the size of the representation");
+        // The top bit is the sign of x.
+        let last = repr.as_ref().len() - 1;
+        let sign = repr.as_ref()[last] >> 63 == 1;
+        repr.as_mut()[last] &= 0x7fff_ffff_ffff_ffff;
+
+        let y = match E::Fr::from_repr(repr) {
+            Ok(y) => y,
+            Err(_) => continue,
+        };
+        if let Some(point) = EdwardsPoint::from_y(y, sign, params) {
+            let point = point.mul_by_cofactor(params);
+            if !point.is_identity() {
+                return point;
+            }
+        }
+    }
+}
+
+/// A point on the embedded curve in affine coordinates.
+pub struct EdwardsPoint<E: ScalarEngine> {
+    x: E::Fr,
+    y: E::Fr,
+}
+
+impl<E: ScalarEngine> Clone for EdwardsPoint<E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E: ScalarEngine> Copy for EdwardsPoint<E> {}
+
+impl<E: ScalarEngine> PartialEq for EdwardsPoint<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.x == other.x && self.y == other.y
+    }
+}
+
+impl<E: ScalarEngine> Eq for EdwardsPoint<E> {}
+
+impl<E: ScalarEngine> ::std::fmt::Debug for EdwardsPoint<E> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("EdwardsPoint")
+            .field("x", &self.x)
+            .field("y", &self.y)
+            .finish()
+    }
+}
+
+impl<E: ScalarEngine> EdwardsPoint<E> {
+    /// The neutral element `(0, 1)`.
+    pub fn identity() -> Self {
+        EdwardsPoint {
+            x: E::Fr::zero(),
+            y: E::Fr::one(),
+        }
+    }
+
+    /// Returns the point with the given coordinates, if it is on the curve.
+    pub fn from_coordinates(x: E::Fr, y: E::Fr, params: &EdwardsParams<E>) -> Option<Self> {
+        let point = EdwardsPoint { x, y };
+        if point.is_on_curve(params) {
+            Some(point)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the point with the given `y` coordinate and the sign of `x`,
+    /// if there is one.
+    pub fn from_y(y: E::Fr, sign: bool, params: &EdwardsParams<E>) -> Option<Self> {
+        // x^2 = (y^2 - 1) / (d y^2 + 1)
+        let mut y2 = y;
+        y2.square();
+
+        let mut num = y2;
+        num.sub_assign(&E::Fr::one());
+
+        let mut den = y2;
+        den.mul_assign(&params.d);
+        den.add_assign(&E::Fr::one());
+
+        let mut x2 = num;
+        x2.mul_assign(&den.inverse()?);
+
+        let mut x = x2.sqrt()?;
+        if x.into_repr().as_ref()[0] & 1 == 1 {
+            if !sign {
+                x.negate();
+            }
+        } else if sign {
+            x.negate();
+        }
+
+        Some(EdwardsPoint { x, y })
+    }
+
+    pub fn get_x(&self) -> E::Fr {
+        self.x
+    }
+
+    pub fn get_y(&self) -> E::Fr {
+        self.y
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::identity()
+    }
+
+    pub fn is_on_curve(&self, params: &EdwardsParams<E>) -> bool {
+        let mut x2 = self.x;
+        x2.square();
+        let mut y2 = self.y;
+        y2.square();
+
+        // -x^2 + y^2
+        let mut lhs = y2;
+        lhs.sub_assign(&x2);
+
+        // 1 + d x^2 y^2
+        let mut rhs = x2;
+        rhs.mul_assign(&y2);
+        rhs.mul_assign(&params.d);
+        rhs.add_assign(&E::Fr::one());
+
+        lhs == rhs
+    }
+
+    pub fn negate(&self) -> Self {
+        let mut x = self.x;
+        x.negate();
+
+        EdwardsPoint { x, y: self.y }
+    }
+
+    pub fn add(&self, other: &Self, params: &EdwardsParams<E>) -> Self {
+        // A = x1 * y2, B = y1 * x2, C = d * A * B
+        let mut a = self.x;
+        a.mul_assign(&other.y);
+        let mut b = self.y;
+        b.mul_assign(&other.x);
+        let mut c = a;
+        c.mul_assign(&b);
+        c.mul_assign(&params.d);
+
+        // x3 = (A + B) / (1 + C)
+        let mut x = a;
+        x.add_assign(&b);
+        let mut den = E::Fr::one();
+        den.add_assign(&c);
+        x.mul_assign(&den.inverse().expect("the addition law is complete"));
+
+        // y3 = (y1 * y2 + x1 * x2) / (1 - C)
+        let mut y = self.y;
+        y.mul_assign(&other.y);
+        let mut tmp = self.x;
+        tmp.mul_assign(&other.x);
+        y.add_assign(&tmp);
+        let mut den = E::Fr::one();
+        den.sub_assign(&c);
+        y.mul_assign(&den.inverse().expect("the addition law is complete"));
+
+        EdwardsPoint { x, y }
+    }
+
+    pub fn double(&self, params: &EdwardsParams<E>) -> Self {
+        self.add(self, params)
+    }
+
+    pub fn mul_by_cofactor(&self, params: &EdwardsParams<E>) -> Self {
+        (0..params.cofactor_log2).fold(*self, |acc, _| acc.double(params))
+    }
+
+    /// Multiplies the point by a scalar given as little-endian bits.
+    pub fn mul<I>(&self, bits: I, params: &EdwardsParams<E>) -> Self
+    where
+        I: IntoIterator<Item = bool>,
+    {
+        let mut acc = Self::identity();
+        let mut base = *self;
+        for bit in bits {
+            if bit {
+                acc = acc.add(&base, params);
+            }
+            base = base.double(params);
+        }
+
+        acc
+    }
+}
+
+/// A window table for fixed-base scalar multiplication with 3-bit windows:
+/// entry `k` of window `i` is `k * 8^i * base`.
+pub fn fixed_base_table<E: ScalarEngine>(
+    base: &EdwardsPoint<E>,
+    num_windows: usize,
+    params: &EdwardsParams<E>,
+) -> Vec<Vec<(E::Fr, E::Fr)>> {
+    let mut window_base = *base;
+    (0..num_windows)
+        .map(|_| {
+            let mut window = Vec::with_capacity(8);
+            let mut cur = EdwardsPoint::identity();
+            for _ in 0..8 {
+                window.push((cur.x, cur.y));
+                cur = cur.add(&window_base, params);
+            }
+            window_base = cur;
+            window
+        })
+        .collect()
+}
+
+/// A point on the embedded curve within the circuit.
+pub struct AllocatedEdwardsPoint<E: ScalarEngine> {
+    x: AllocatedNum<E>,
+    y: AllocatedNum<E>,
+}
+
+impl<E: ScalarEngine> Clone for AllocatedEdwardsPoint<E> {
+    fn clone(&self) -> Self {
+        AllocatedEdwardsPoint {
+            x: self.x.clone(),
+            y: self.y.clone(),
+        }
+    }
+}
+
+impl<E: ScalarEngine> AllocatedEdwardsPoint<E> {
+    /// Allocates a point and enforces that it is on the curve.
+    pub fn alloc<CS>(
+        mut cs: CS,
+        value: Option<EdwardsPoint<E>>,
+        params: &EdwardsParams<E>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(value.get()?.x))?;
+        let y = AllocatedNum::alloc(cs.namespace(|| "y"), || Ok(value.get()?.y))?;
+
+        Self::interpret(cs, &x, &y, params)
+    }
+
+    /// Interprets the coordinates as a point, enforcing that it is on the curve.
+    pub fn interpret<CS>(
+        mut cs: CS,
+        x: &AllocatedNum<E>,
+        y: &AllocatedNum<E>,
+        params: &EdwardsParams<E>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let x2 = x.square(cs.namespace(|| "x^2"))?;
+        let y2 = y.square(cs.namespace(|| "y^2"))?;
+
+        // Constrain: (d * x^2) * y^2 = y^2 - x^2 - 1
+        cs.enforce(
+            || "on curve check",
+            |lc| lc + (params.d, x2.get_variable()),
+            |lc| lc + y2.get_variable(),
+            |lc| lc + y2.get_variable() - x2.get_variable() - CS::one(),
+        );
+
+        Ok(AllocatedEdwardsPoint {
+            x: x.clone(),
+            y: y.clone(),
+        })
+    }
+
+    pub fn get_x(&self) -> &AllocatedNum<E> {
+        &self.x
+    }
+
+    pub fn get_y(&self) -> &AllocatedNum<E> {
+        &self.y
+    }
+
+    pub fn get_value(&self) -> Option<EdwardsPoint<E>> {
+        match (self.x.get_value(), self.y.get_value()) {
+            (Some(x), Some(y)) => Some(EdwardsPoint { x, y }),
+            _ => None,
+        }
+    }
+
+    /// Exposes both coordinates as public inputs.
+    pub fn inputize<CS>(&self, mut cs: CS) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        self.x.inputize(cs.namespace(|| "x"))?;
+        self.y.inputize(cs.namespace(|| "y"))?;
+
+        Ok(())
+    }
+
+    /// Adds two points with six constraints.
+    pub fn add<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+        params: &EdwardsParams<E>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        // U = (x1 + y1) * (x2 + y2)
+        let u = AllocatedNum::alloc(cs.namespace(|| "U"), || {
+            let mut t0 = *self.x.get_value().get()?;
+            t0.add_assign(self.y.get_value().get()?);
+
+            let mut t1 = *other.x.get_value().get()?;
+            t1.add_assign(other.y.get_value().get()?);
+
+            t0.mul_assign(&t1);
+            Ok(t0)
+        })?;
+
+        cs.enforce(
+            || "U computation",
+            |lc| lc + self.x.get_variable() + self.y.get_variable(),
+            |lc| lc + other.x.get_variable() + other.y.get_variable(),
+            |lc| lc + u.get_variable(),
+        );
+
+        // A = x1 * y2
+        let a = self.x.mul(cs.namespace(|| "A computation"), &other.y)?;
+
+        // B = y1 * x2
+        let b = self.y.mul(cs.namespace(|| "B computation"), &other.x)?;
+
+        // C = d * A * B
+        let c = AllocatedNum::alloc(cs.namespace(|| "C"), || {
+            let mut t0 = *a.get_value().get()?;
+            t0.mul_assign(b.get_value().get()?);
+            t0.mul_assign(&params.d);
+            Ok(t0)
+        })?;
+
+        cs.enforce(
+            || "C computation",
+            |lc| lc + (params.d, a.get_variable()),
+            |lc| lc + b.get_variable(),
+            |lc| lc + c.get_variable(),
+        );
+
+        // x3 = (A + B) / (1 + C)
+        let x3 = AllocatedNum::alloc(cs.namespace(|| "x3"), || {
+            let mut t0 = *a.get_value().get()?;
+            t0.add_assign(b.get_value().get()?);
+
+            let mut t1 = E::Fr::one();
+            t1.add_assign(c.get_value().get()?);
+
+            match t1.inverse() {
+                Some(t1) => {
+                    t0.mul_assign(&t1);
+                    Ok(t0)
+                }
+                None => Err(SynthesisError::DivisionByZero),
+            }
+        })?;
+
+        cs.enforce(
+            || "x3 computation",
+            |lc| lc + CS::one() + c.get_variable(),
+            |lc| lc + x3.get_variable(),
+            |lc| lc + a.get_variable() + b.get_variable(),
+        );
+
+        // y3 = (U - A - B) / (1 - C)
+        let y3 = AllocatedNum::alloc(cs.namespace(|| "y3"), || {
+            let mut t0 = *u.get_value().get()?;
+            t0.sub_assign(a.get_value().get()?);
+            t0.sub_assign(b.get_value().get()?);
+
+            let mut t1 = E::Fr::one();
+            t1.sub_assign(c.get_value().get()?);
+
+            match t1.inverse() {
+                Some(t1) => {
+                    t0.mul_assign(&t1);
+                    Ok(t0)
+                }
+                None => Err(SynthesisError::DivisionByZero),
+            }
+        })?;
+
+        cs.enforce(
+            || "y3 computation",
+            |lc| lc + CS::one() - c.get_variable(),
+            |lc| lc + y3.get_variable(),
+            |lc| lc + u.get_variable() - a.get_variable() - b.get_variable(),
+        );
+
+        Ok(AllocatedEdwardsPoint { x: x3, y: y3 })
+    }
+
+    pub fn double<CS>(&self, cs: CS, params: &EdwardsParams<E>) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        self.add(cs, self, params)
+    }
+
+    pub fn mul_by_cofactor<CS>(
+        &self,
+        mut cs: CS,
+        params: &EdwardsParams<E>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let mut result = self.clone();
+        for i in 0..params.cofactor_log2 {
+            result = result.double(cs.namespace(|| format!("doubling {}", i)), params)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Returns the point if the condition is true, and the identity otherwise.
+    pub fn conditionally_select_identity<CS>(
+        &self,
+        mut cs: CS,
+        condition: &Boolean,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || {
+            if *condition.get_value().get()? {
+                Ok(*self.x.get_value().get()?)
+            } else {
+                Ok(E::Fr::zero())
+            }
+        })?;
+
+        // Constrain: x * condition = x'
+        cs.enforce(
+            || "x computation",
+            |lc| lc + self.x.get_variable(),
+            |_| condition.lc(CS::one(), E::Fr::one()),
+            |lc| lc + x.get_variable(),
+        );
+
+        let y = AllocatedNum::alloc(cs.namespace(|| "y"), || {
+            if *condition.get_value().get()? {
+                Ok(*self.y.get_value().get()?)
+            } else {
+                Ok(E::Fr::one())
+            }
+        })?;
+
+        // Constrain: (y - 1) * condition = y' - 1
+        cs.enforce(
+            || "y computation",
+            |lc| lc + self.y.get_variable() - CS::one(),
+            |_| condition.lc(CS::one(), E::Fr::one()),
+            |lc| lc + y.get_variable() - CS::one(),
+        );
+
+        Ok(AllocatedEdwardsPoint { x, y })
+    }
+
+    /// Multiplies the point by a scalar given as little-endian bits.
+    pub fn mul<CS>(
+        &self,
+        mut cs: CS,
+        bits: &[Boolean],
+        params: &EdwardsParams<E>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        assert!(!bits.is_empty());
+
+        let mut base = self.clone();
+        let mut result: Option<Self> = None;
+        for (i, bit) in bits.iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("bit {}", i));
+
+            let selected = base.conditionally_select_identity(cs.namespace(|| "selection"), bit)?;
+            result = Some(match result {
+                None => selected,
+                Some(acc) => acc.add(cs.namespace(|| "addition"), &selected, params)?,
+            });
+
+            if i + 1 < bits.len() {
+                base = base.double(cs.namespace(|| "doubling"), params)?;
+            }
+        }
+
+        Ok(result.unwrap())
+    }
+
+    /// Enforces that both points are equal.
+    pub fn enforce_equal<CS>(mut cs: CS, a: &Self, b: &Self) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        cs.enforce(
+            || "x equality",
+            |lc| lc + a.x.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + b.x.get_variable(),
+        );
+        cs.enforce(
+            || "y equality",
+            |lc| lc + a.y.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + b.y.get_variable(),
+        );
+
+        Ok(())
+    }
+
+    /// Multiplies a fixed base by a scalar given as little-endian bits, using a
+    /// table from [`fixed_base_table`] with at least `bits.len() / 3` windows.
+    pub fn fixed_base_mul<CS>(
+        mut cs: CS,
+        table: &[Vec<(E::Fr, E::Fr)>],
+        bits: &[Boolean],
+        params: &EdwardsParams<E>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        assert!(!bits.is_empty());
+        assert!(bits.len() <= table.len() * 3);
+
+        let mut result: Option<Self> = None;
+        for (i, (chunk, window)) in bits.chunks(3).zip(table.iter()).enumerate() {
+            let mut cs = cs.namespace(|| format!("window {}", i));
+
+            let mut chunk = chunk.to_vec();
+            chunk.resize(3, Boolean::constant(false));
+
+            let (x, y) = lookup3_xy(cs.namespace(|| "lookup"), &chunk, window)?;
+            let point = AllocatedEdwardsPoint { x, y };
+
+            result = Some(match result {
+                None => point,
+                Some(acc) => acc.add(cs.namespace(|| "addition"), &point, params)?,
+            });
+        }
+
+        Ok(result.unwrap())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bls::{Bls12, Fr};
+    use ff::Field;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use super::*;
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::gadgets::test::TestConstraintSystem;
+
+    fn random_point(rng: &mut XorShiftRng, params: &EdwardsParams<Bls12>) -> EdwardsPoint<Bls12> {
+        loop {
+            let sign = rng.next_u32() & 1 == 1;
+            if let Some(p) = EdwardsPoint::from_y(Fr::random(rng), sign, params) {
+                return p.mul_by_cofactor(params);
+            }
+        }
+    }
+
+    #[test]
+    fn test_edwards_native() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = EdwardsParams::<Bls12>::jubjub();
+
+        for _ in 0..10 {
+            let p = random_point(&mut rng, &params);
+            let q = random_point(&mut rng, &params);
+            assert!(p.is_on_curve(&params));
+
+            let sum = p.add(&q, &params);
+            assert!(sum.is_on_curve(&params));
+            assert_eq!(sum, q.add(&p, &params));
+            assert_eq!(p.add(&EdwardsPoint::identity(), &params), p);
+            assert!(p.add(&p.negate(), &params).is_identity());
+
+            // 5 * p = 2 * (2 * p) + p
+            let five = p.mul(vec![true, false, true], &params);
+            assert_eq!(five, p.double(&params).double(&params).add(&p, &params));
+        }
+    }
+
+    #[test]
+    fn test_edwards_addition() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = EdwardsParams::<Bls12>::jubjub();
+
+        for _ in 0..10 {
+            let p = random_point(&mut rng, &params);
+            let q = random_point(&mut rng, &params);
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let a = AllocatedEdwardsPoint::alloc(cs.namespace(|| "a"), Some(p), &params).unwrap();
+            let b = AllocatedEdwardsPoint::alloc(cs.namespace(|| "b"), Some(q), &params).unwrap();
+            let c = a.add(cs.namespace(|| "a + b"), &b, &params).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 3 + 3 + 6);
+            assert_eq!(c.get_value().unwrap(), p.add(&q, &params));
+
+            cs.set("a + b/x3/num", Fr::random(&mut rng));
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_edwards_not_on_curve() {
+        let params = EdwardsParams::<Bls12>::jubjub();
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let p = EdwardsPoint {
+            x: Fr::one(),
+            y: Fr::one(),
+        };
+        AllocatedEdwardsPoint::alloc(&mut cs, Some(p), &params).unwrap();
+
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_edwards_mul() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = EdwardsParams::<Bls12>::jubjub();
+
+        for num_bits in &[1, 2, 10, 64] {
+            let p = random_point(&mut rng, &params);
+            let bits = (0..*num_bits)
+                .map(|_| rng.next_u32() & 1 == 1)
+                .collect::<Vec<_>>();
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let allocated = bits
+                .iter()
+                .enumerate()
+                .map(|(i, b)| {
+                    Boolean::from(
+                        AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(*b))
+                            .unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            let point =
+                AllocatedEdwardsPoint::alloc(cs.namespace(|| "p"), Some(p), &params).unwrap();
+
+            let result = point
+                .mul(cs.namespace(|| "mul"), &allocated, &params)
+                .unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(
+                result.get_value().unwrap(),
+                p.mul(bits.iter().cloned(), &params)
+            );
+        }
+    }
+
+    #[test]
+    fn test_fixed_base_mul() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = EdwardsParams::<Bls12>::jubjub();
+        let base = random_point(&mut rng, &params);
+        let table = fixed_base_table(&base, 10, &params);
+
+        for num_bits in &[1, 3, 20, 30] {
+            let bits = (0..*num_bits)
+                .map(|_| rng.next_u32() & 1 == 1)
+                .collect::<Vec<_>>();
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let allocated = bits
+                .iter()
+                .enumerate()
+                .map(|(i, b)| {
+                    Boolean::from(
+                        AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(*b))
+                            .unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let result = AllocatedEdwardsPoint::fixed_base_mul(
+                cs.namespace(|| "mul"),
+                &table,
+                &allocated,
+                &params,
+            )
+            .unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(
+                result.get_value().unwrap(),
+                base.mul(bits.iter().cloned(), &params)
+            );
+        }
+    }
+}
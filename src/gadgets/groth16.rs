@@ -0,0 +1,340 @@
+//! Verification of Groth16 proofs inside a circuit, for recursion and
+//! aggregation.
+//!
+//! Verifying the proofs of an engine takes a circuit over a field whose
+//! elements are the coordinates of the engine's points, e.g. a BW6 curve for
+//! BLS12-381 proofs. None of these curves is available to this crate yet, so
+//! the verifier is generic over a [`PairingGadget`], which implements the
+//! arithmetic of the engine's groups and its pairing in such a circuit.
+
+use ff::{PrimeField, ScalarEngine};
+use groupy::CurveAffine;
+
+use super::boolean::{self, Boolean};
+use crate::bls::Engine;
+use crate::groth16::{Proof, VerifyingKey};
+use crate::{ConstraintSystem, SynthesisError};
+
+/// The groups of a pairing engine and its pairing, as circuits over the field
+/// of `F`.
+pub trait PairingGadget<F: ScalarEngine> {
+    /// The engine whose points the circuits compute with.
+    type Engine: Engine;
+    type G1: Clone;
+    type G2: Clone;
+
+    fn alloc_g1<CS: ConstraintSystem<F>>(
+        &self,
+        cs: CS,
+        value: Option<<Self::Engine as Engine>::G1Affine>,
+    ) -> Result<Self::G1, SynthesisError>;
+
+    fn alloc_g2<CS: ConstraintSystem<F>>(
+        &self,
+        cs: CS,
+        value: Option<<Self::Engine as Engine>::G2Affine>,
+    ) -> Result<Self::G2, SynthesisError>;
+
+    fn constant_g1<CS: ConstraintSystem<F>>(
+        &self,
+        cs: CS,
+        value: <Self::Engine as Engine>::G1Affine,
+    ) -> Result<Self::G1, SynthesisError>;
+
+    fn constant_g2<CS: ConstraintSystem<F>>(
+        &self,
+        cs: CS,
+        value: <Self::Engine as Engine>::G2Affine,
+    ) -> Result<Self::G2, SynthesisError>;
+
+    fn add_g1<CS: ConstraintSystem<F>>(
+        &self,
+        cs: CS,
+        a: &Self::G1,
+        b: &Self::G1,
+    ) -> Result<Self::G1, SynthesisError>;
+
+    /// Multiplies `base` by the scalar of the little-endian `bits`.
+    fn mul_bits_g1<CS: ConstraintSystem<F>>(
+        &self,
+        cs: CS,
+        base: &Self::G1,
+        bits: &[Boolean],
+    ) -> Result<Self::G1, SynthesisError>;
+
+    /// Enforces that the product of the pairings of `pairs` is `target`.
+    fn enforce_pairing_product<CS: ConstraintSystem<F>>(
+        &self,
+        cs: CS,
+        pairs: &[(Self::G1, Self::G2)],
+        target: &<Self::Engine as Engine>::Fqk,
+    ) -> Result<(), SynthesisError>;
+}
+
+/// A Groth16 proof allocated in a circuit.
+pub struct AllocatedProof<F: ScalarEngine, P: PairingGadget<F>> {
+    a: P::G1,
+    b: P::G2,
+    c: P::G1,
+}
+
+impl<F: ScalarEngine, P: PairingGadget<F>> AllocatedProof<F, P> {
+    pub fn alloc<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        pairing: &P,
+        proof: Option<&Proof<P::Engine>>,
+    ) -> Result<Self, SynthesisError> {
+        Ok(AllocatedProof {
+            a: pairing.alloc_g1(cs.namespace(|| "a"), proof.map(|proof| proof.a))?,
+            b: pairing.alloc_g2(cs.namespace(|| "b"), proof.map(|proof| proof.b))?,
+            c: pairing.alloc_g1(cs.namespace(|| "c"), proof.map(|proof| proof.c))?,
+        })
+    }
+}
+
+/// Allocates the bits of a public input of the proofs, little-endian.
+pub fn alloc_input<F, CS, S>(cs: CS, value: Option<S>) -> Result<Vec<Boolean>, SynthesisError>
+where
+    F: ScalarEngine,
+    CS: ConstraintSystem<F>,
+    S: PrimeField,
+{
+    Ok(boolean::field_into_allocated_bits_le(cs, value)?
+        .into_iter()
+        .map(Boolean::from)
+        .collect())
+}
+
+/// Enforces that `proof` is valid for `vk` and the public inputs, given by
+/// their little-endian bits, like [`verify_proof`](crate::groth16::verify_proof)
+/// checks it natively.
+///
+/// Fails with [`SynthesisError::MalformedVerifyingKey`] if the number of
+/// inputs doesn't match the key.
+pub fn enforce_verification<F, P, CS>(
+    mut cs: CS,
+    pairing: &P,
+    vk: &VerifyingKey<P::Engine>,
+    proof: &AllocatedProof<F, P>,
+    inputs: &[Vec<Boolean>],
+) -> Result<(), SynthesisError>
+where
+    F: ScalarEngine,
+    P: PairingGadget<F>,
+    CS: ConstraintSystem<F>,
+{
+    if inputs.len() + 1 != vk.ic.len() {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+
+    let mut acc = pairing.constant_g1(cs.namespace(|| "ic 0"), vk.ic[0])?;
+    for (i, (bits, ic)) in inputs.iter().zip(vk.ic.iter().skip(1)).enumerate() {
+        let ic = pairing.constant_g1(cs.namespace(|| format!("ic {}", i + 1)), *ic)?;
+        let term = pairing.mul_bits_g1(cs.namespace(|| format!("input {}", i)), &ic, bits)?;
+        acc = pairing.add_g1(cs.namespace(|| format!("acc {}", i)), &acc, &term)?;
+    }
+
+    // A * B + inputs * (-gamma) + C * (-delta) = alpha * beta, as in the
+    // native verifier.
+    let mut neg_gamma = vk.gamma_g2;
+    neg_gamma.negate();
+    let mut neg_delta = vk.delta_g2;
+    neg_delta.negate();
+    let neg_gamma = pairing.constant_g2(cs.namespace(|| "-gamma"), neg_gamma)?;
+    let neg_delta = pairing.constant_g2(cs.namespace(|| "-delta"), neg_delta)?;
+
+    let target = <P::Engine as Engine>::pairing(vk.alpha_g1, vk.beta_g2);
+    pairing.enforce_pairing_product(
+        cs.namespace(|| "pairing check"),
+        &[
+            (proof.a.clone(), proof.b.clone()),
+            (acc, neg_gamma),
+            (proof.c.clone(), neg_delta),
+        ],
+        &target,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bls::{Bls12, Fr, PairingCurveAffine};
+    use ff::Field;
+    use groupy::CurveProjective;
+    use std::marker::PhantomData;
+
+    use super::*;
+    use crate::gadgets::test::TestConstraintSystem;
+    use crate::groth16::{create_random_proof, generate_random_parameters};
+    use crate::Circuit;
+
+    /// Computes with the values of the points instead of constraining them,
+    /// and enforces an unsatisfiable constraint if the pairing check fails.
+    struct NativePairing<E>(PhantomData<E>);
+
+    impl<F: ScalarEngine, E: Engine> PairingGadget<F> for NativePairing<E> {
+        type Engine = E;
+        type G1 = Option<E::G1Affine>;
+        type G2 = Option<E::G2Affine>;
+
+        fn alloc_g1<CS: ConstraintSystem<F>>(
+            &self,
+            _: CS,
+            value: Option<E::G1Affine>,
+        ) -> Result<Self::G1, SynthesisError> {
+            Ok(value)
+        }
+
+        fn alloc_g2<CS: ConstraintSystem<F>>(
+            &self,
+            _: CS,
+            value: Option<E::G2Affine>,
+        ) -> Result<Self::G2, SynthesisError> {
+            Ok(value)
+        }
+
+        fn constant_g1<CS: ConstraintSystem<F>>(
+            &self,
+            _: CS,
+            value: E::G1Affine,
+        ) -> Result<Self::G1, SynthesisError> {
+            Ok(Some(value))
+        }
+
+        fn constant_g2<CS: ConstraintSystem<F>>(
+            &self,
+            _: CS,
+            value: E::G2Affine,
+        ) -> Result<Self::G2, SynthesisError> {
+            Ok(Some(value))
+        }
+
+        fn add_g1<CS: ConstraintSystem<F>>(
+            &self,
+            _: CS,
+            a: &Self::G1,
+            b: &Self::G1,
+        ) -> Result<Self::G1, SynthesisError> {
+            Ok(a.and_then(|a| {
+                b.map(|b| {
+                    let mut sum = a.into_projective();
+                    sum.add_assign_mixed(&b);
+                    sum.into_affine()
+                })
+            }))
+        }
+
+        fn mul_bits_g1<CS: ConstraintSystem<F>>(
+            &self,
+            _: CS,
+            base: &Self::G1,
+            bits: &[Boolean],
+        ) -> Result<Self::G1, SynthesisError> {
+            let base = match base {
+                Some(base) => base,
+                None => return Ok(None),
+            };
+            let mut acc = E::G1::zero();
+            for bit in bits.iter().rev() {
+                acc.double();
+                match bit.get_value() {
+                    Some(true) => acc.add_assign_mixed(base),
+                    Some(false) => {}
+                    None => return Ok(None),
+                }
+            }
+
+            Ok(Some(acc.into_affine()))
+        }
+
+        fn enforce_pairing_product<CS: ConstraintSystem<F>>(
+            &self,
+            mut cs: CS,
+            pairs: &[(Self::G1, Self::G2)],
+            target: &E::Fqk,
+        ) -> Result<(), SynthesisError> {
+            let prepared = pairs
+                .iter()
+                .map(|(g1, g2)| Some((g1.as_ref()?.prepare(), g2.as_ref()?.prepare())))
+                .collect::<Option<Vec<_>>>();
+            if let Some(prepared) = prepared {
+                let pairs = prepared.iter().map(|(g1, g2)| (g1, g2)).collect::<Vec<_>>();
+                let product = E::final_exponentiation(&E::miller_loop(pairs.iter()));
+                if product.as_ref() != Some(target) {
+                    cs.enforce(|| "fails", |lc| lc, |lc| lc, |lc| lc + CS::one());
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Knows a square root of its public input.
+    struct SquareRoot(Option<Fr>);
+
+    impl Circuit<Bls12> for SquareRoot {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let root = cs.alloc(
+                || "root",
+                || self.0.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            let square = cs.alloc_input(
+                || "square",
+                || {
+                    let mut square = self.0.ok_or(SynthesisError::AssignmentMissing)?;
+                    square.square();
+                    Ok(square)
+                },
+            )?;
+            cs.enforce(
+                || "square",
+                |lc| lc + root,
+                |lc| lc + root,
+                |lc| lc + square,
+            );
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_enforce_verification() {
+        let rng = &mut crate::testing::test_rng();
+        let params = generate_random_parameters(SquareRoot(None), rng).unwrap();
+        let root = Fr::random(rng);
+        let proof = create_random_proof(SquareRoot(Some(root)), &params, rng).unwrap();
+        let mut square = root;
+        square.square();
+
+        let pairing = NativePairing::<Bls12>(PhantomData);
+        let verifies = |input: Fr| {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let proof =
+                AllocatedProof::alloc(cs.namespace(|| "proof"), &pairing, Some(&proof)).unwrap();
+            let input = alloc_input(cs.namespace(|| "input"), Some(input)).unwrap();
+            enforce_verification(
+                cs.namespace(|| "verify"),
+                &pairing,
+                &params.vk,
+                &proof,
+                &[input],
+            )
+            .unwrap();
+
+            cs.is_satisfied()
+        };
+
+        assert!(verifies(square));
+        assert!(!verifies(root));
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let proof =
+            AllocatedProof::alloc(cs.namespace(|| "proof"), &pairing, Some(&proof)).unwrap();
+        match enforce_verification(cs.namespace(|| "verify"), &pairing, &params.vk, &proof, &[]) {
+            Err(SynthesisError::MalformedVerifyingKey) => (),
+            _ => panic!("expected a malformed verifying key error"),
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Merkle membership proofs over a binary tree of field elements.
+//!
+//! A path is given from the leaf up to the root. Every step consists of the
+//! sibling node and a bit that is `true` if the current node is the *right*
+//! child, i.e. if the sibling is hashed first.
+
+use ff::{BitIterator, PrimeField, ScalarEngine};
+
+use super::boolean::Boolean;
+use super::num::AllocatedNum;
+use super::pedersen_hash::{pedersen_hash, pedersen_hash_circuit, PedersenParams};
+use super::poseidon::{poseidon_hash, poseidon_hash_circuit, PoseidonConstants};
+use crate::{ConstraintSystem, SynthesisError};
+
+/// A two-to-one hash function, both natively and as a circuit.
+pub trait MerkleHasher<E: ScalarEngine> {
+    fn hash(&self, left: &E::Fr, right: &E::Fr) -> E::Fr;
+
+    fn hash_circuit<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        left: &AllocatedNum<E>,
+        right: &AllocatedNum<E>,
+    ) -> Result<AllocatedNum<E>, SynthesisError>;
+}
+
+/// Poseidon with a width of 3.
+impl<E: ScalarEngine> MerkleHasher<E> for PoseidonConstants<E> {
+    fn hash(&self, left: &E::Fr, right: &E::Fr) -> E::Fr {
+        poseidon_hash(self, &[*left, *right])
+    }
+
+    fn hash_circuit<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        left: &AllocatedNum<E>,
+        right: &AllocatedNum<E>,
+    ) -> Result<AllocatedNum<E>, SynthesisError> {
+        poseidon_hash_circuit(cs, self, &[left.clone(), right.clone()])
+    }
+}
+
+/// The Pedersen hash of the bits of both nodes, which needs parameters with at
+/// least three generators. The result is the x-coordinate of the hash.
+impl<E: ScalarEngine> MerkleHasher<E> for PedersenParams<E> {
+    fn hash(&self, left: &E::Fr, right: &E::Fr) -> E::Fr {
+        let bits = field_into_bits_le(left)
+            .into_iter()
+            .chain(field_into_bits_le(right));
+
+        pedersen_hash(self, bits).get_x()
+    }
+
+    fn hash_circuit<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+        left: &AllocatedNum<E>,
+        right: &AllocatedNum<E>,
+    ) -> Result<AllocatedNum<E>, SynthesisError> {
+        let mut bits = left.to_bits_le_strict(cs.namespace(|| "left bits"))?;
+        bits.extend(right.to_bits_le_strict(cs.namespace(|| "right bits"))?);
+
+        let point = pedersen_hash_circuit(cs.namespace(|| "hash"), self, &bits)?;
+
+        Ok(point.get_x().clone())
+    }
+}
+
+fn field_into_bits_le<F: PrimeField>(value: &F) -> Vec<bool> {
+    let mut bits = BitIterator::new(value.into_repr()).collect::<Vec<_>>();
+    bits.reverse();
+    bits.truncate(F::NUM_BITS as usize);
+
+    bits
+}
+
+/// Computes the root natively.
+pub fn compute_root<E, H>(hasher: &H, leaf: &E::Fr, path: &[(E::Fr, bool)]) -> E::Fr
+where
+    E: ScalarEngine,
+    H: MerkleHasher<E>,
+{
+    path.iter().fold(*leaf, |cur, (sibling, is_right)| {
+        if *is_right {
+            hasher.hash(sibling, &cur)
+        } else {
+            hasher.hash(&cur, sibling)
+        }
+    })
+}
+
+/// Computes the root within the circuit.
+pub fn compute_root_circuit<E, H, CS>(
+    mut cs: CS,
+    hasher: &H,
+    leaf: &AllocatedNum<E>,
+    path: &[(AllocatedNum<E>, Boolean)],
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: ScalarEngine,
+    H: MerkleHasher<E>,
+    CS: ConstraintSystem<E>,
+{
+    let mut cur = leaf.clone();
+    for (i, (sibling, is_right)) in path.iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("level {}", i));
+
+        // Swap the nodes if the current one is the right child.
+        let (left, right) = AllocatedNum::conditionally_reverse(
+            cs.namespace(|| "conditional reversal"),
+            &cur,
+            sibling,
+            is_right,
+        )?;
+
+        cur = hasher.hash_circuit(cs.namespace(|| "hash"), &left, &right)?;
+    }
+
+    Ok(cur)
+}
+
+/// Enforces that `leaf` is part of the tree with the given `root`.
+pub fn enforce_membership<E, H, CS>(
+    mut cs: CS,
+    hasher: &H,
+    leaf: &AllocatedNum<E>,
+    path: &[(AllocatedNum<E>, Boolean)],
+    root: &AllocatedNum<E>,
+) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    H: MerkleHasher<E>,
+    CS: ConstraintSystem<E>,
+{
+    let computed = compute_root_circuit(cs.namespace(|| "root"), hasher, leaf, path)?;
+
+    cs.enforce(
+        || "root equality",
+        |lc| lc + computed.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + root.get_variable(),
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bls::{Bls12, Fr};
+    use ff::Field;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use super::*;
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::gadgets::ecc::EdwardsParams;
+    use crate::gadgets::test::TestConstraintSystem;
+
+    fn check_membership<H: MerkleHasher<Bls12>>(hasher: &H, depth: usize) {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let leaf = Fr::random(&mut rng);
+        let path = (0..depth)
+            .map(|_| (Fr::random(&mut rng), rng.next_u32() & 1 == 1))
+            .collect::<Vec<_>>();
+        let root = compute_root(hasher, &leaf, &path);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let leaf_num = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(leaf)).unwrap();
+        let root_num = AllocatedNum::alloc(cs.namespace(|| "root"), || Ok(root)).unwrap();
+        let path_nums = path
+            .iter()
+            .enumerate()
+            .map(|(i, (sibling, is_right))| {
+                let sibling =
+                    AllocatedNum::alloc(cs.namespace(|| format!("sibling {}", i)), || Ok(*sibling))
+                        .unwrap();
+                let is_right = AllocatedBit::alloc(
+                    cs.namespace(|| format!("is right {}", i)),
+                    Some(*is_right),
+                )
+                .unwrap();
+                (sibling, Boolean::from(is_right))
+            })
+            .collect::<Vec<_>>();
+
+        enforce_membership(
+            cs.namespace(|| "membership"),
+            hasher,
+            &leaf_num,
+            &path_nums,
+            &root_num,
+        )
+        .unwrap();
+        assert!(cs.is_satisfied());
+
+        // Flipping the position of a node changes the root.
+        let flipped = if path[0].1 { Fr::zero() } else { Fr::one() };
+        cs.set("is right 0/boolean", flipped);
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_merkle_poseidon() {
+        check_membership(&PoseidonConstants::<Bls12>::new(3), 4);
+    }
+
+    #[test]
+    fn test_merkle_pedersen() {
+        check_membership(
+            &PedersenParams::<Bls12>::new(EdwardsParams::jubjub(), b"Test_MT_", 3),
+            2,
+        );
+    }
+
+    #[test]
+    fn test_merkle_ordering() {
+        let hasher = PoseidonConstants::<Bls12>::new(3);
+        let a = Fr::one();
+        let mut b = Fr::one();
+        b.double();
+
+        assert_eq!(
+            compute_root(&hasher, &a, &[(b, false)]),
+            hasher.hash(&a, &b)
+        );
+        assert_eq!(compute_root(&hasher, &a, &[(b, true)]), hasher.hash(&b, &a));
+    }
+}
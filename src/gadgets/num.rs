@@ -306,6 +306,74 @@ impl<E: ScalarEngine> AllocatedNum<E> {
         Ok(())
     }
 
+    /// Returns the multiplicative inverse, which also
+    /// asserts that the number is nonzero.
+    pub fn inverse<CS>(&self, mut cs: CS) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let mut value = None;
+
+        let var = cs.alloc(
+            || "inverse num",
+            || {
+                let tmp = *self.value.get()?;
+
+                match tmp.inverse() {
+                    Some(inv) => {
+                        value = Some(inv);
+
+                        Ok(inv)
+                    }
+                    None => Err(SynthesisError::DivisionByZero),
+                }
+            },
+        )?;
+
+        // Constrain: a * inv = 1
+        cs.enforce(
+            || "inverse constraint",
+            |lc| lc + self.variable,
+            |lc| lc + var,
+            |lc| lc + CS::one(),
+        );
+
+        Ok(AllocatedNum {
+            value,
+            variable: var,
+        })
+    }
+
+    /// Takes two allocated numbers (a, b) and returns
+    /// a if the condition is true, and b otherwise.
+    pub fn conditionally_select<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        condition: &Boolean,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let c = Self::alloc(cs.namespace(|| "conditional select result"), || {
+            if *condition.get_value().get()? {
+                Ok(*a.value.get()?)
+            } else {
+                Ok(*b.value.get()?)
+            }
+        })?;
+
+        // Constrain: (a - b) * condition = c - b
+        cs.enforce(
+            || "conditional select constraint",
+            |lc| lc + a.variable - b.variable,
+            |_| condition.lc(CS::one(), E::Fr::one()),
+            |lc| lc + c.variable - b.variable,
+        );
+
+        Ok(c)
+    }
+
     /// Takes two allocated numbers (a, b) and returns
     /// (b, a) if the condition is true, and (a, b)
     /// otherwise.
@@ -411,13 +479,13 @@ impl<E: ScalarEngine> Num<E> {
 
 #[cfg(test)]
 mod test {
+    use crate::bls::{Bls12, Fr};
     use crate::ConstraintSystem;
     use ff::{BitIterator, Field, PrimeField};
-    use paired::bls12_381::{Bls12, Fr};
     use rand_core::SeedableRng;
     use rand_xorshift::XorShiftRng;
 
-    use super::{AllocatedNum, Boolean};
+    use super::{AllocatedBit, AllocatedNum, Boolean};
     use crate::gadgets::test::*;
 
     #[test]
@@ -515,6 +583,59 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_num_inverse() {
+        {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let n = AllocatedNum::alloc(&mut cs, || Ok(Fr::from_str("3").unwrap())).unwrap();
+            let inv = n.inverse(&mut cs).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert!(inv.value.unwrap() == Fr::from_str("3").unwrap().inverse().unwrap());
+            cs.set("inverse num", Fr::from_str("3").unwrap());
+            assert!(cs.which_is_unsatisfied() == Some("inverse constraint"));
+        }
+        {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let n = AllocatedNum::alloc(&mut cs, || Ok(Fr::zero())).unwrap();
+            assert!(n.inverse(&mut cs).is_err());
+        }
+    }
+
+    #[test]
+    fn test_num_conditional_select() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for &condition in &[false, true] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::random(&mut rng))).unwrap();
+            let bit = AllocatedBit::alloc(cs.namespace(|| "condition"), Some(condition)).unwrap();
+            let c =
+                AllocatedNum::conditionally_select(&mut cs, &a, &b, &Boolean::from(bit)).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            if condition {
+                assert_eq!(a.value.unwrap(), c.value.unwrap());
+            } else {
+                assert_eq!(b.value.unwrap(), c.value.unwrap());
+            }
+
+            cs.set("conditional select result/num", Fr::random(&mut rng));
+            assert_eq!(
+                cs.which_is_unsatisfied(),
+                Some("conditional select constraint")
+            );
+        }
+    }
+
     #[test]
     fn test_into_bits_strict() {
         let mut negone = Fr::one();
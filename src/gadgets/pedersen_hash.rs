@@ -0,0 +1,196 @@
+//! A Pedersen hash over the embedded curve from [`super::ecc`].
+//!
+//! The input is split into 3-bit chunks, and chunk `j` of segment `i` selects
+//! `k * 8^j * G_i` from a window table, where `k` is the value of the chunk.
+//! Every generator `G_i` covers 63 chunks, so the scalar it gets multiplied
+//! with stays below the order of Jubjub's prime-order subgroup. The hash is
+//! collision resistant for inputs of a fixed length only; inputs of varying
+//! length must be padded or prefixed with their length.
+
+use ff::ScalarEngine;
+
+use super::boolean::Boolean;
+use super::ecc::{
+    fixed_base_table, group_hash, AllocatedEdwardsPoint, EdwardsParams, EdwardsPoint,
+};
+use crate::{ConstraintSystem, SynthesisError};
+
+/// The number of 3-bit chunks handled by a single generator.
+pub const CHUNKS_PER_GENERATOR: usize = 63;
+
+/// The generators of the Pedersen hash together with their window tables.
+pub struct PedersenParams<E: ScalarEngine> {
+    curve: EdwardsParams<E>,
+    generators: Vec<EdwardsPoint<E>>,
+    tables: Vec<Vec<Vec<(E::Fr, E::Fr)>>>,
+}
+
+impl<E: ScalarEngine> PedersenParams<E> {
+    /// Derives `num_generators` generators from `personalization`, which allows
+    /// for hashing up to `num_generators * 189` bits.
+    pub fn new(curve: EdwardsParams<E>, personalization: &[u8; 8], num_generators: usize) -> Self {
+        let generators = (0..num_generators)
+            .map(|i| group_hash(&curve, personalization, i))
+            .collect::<Vec<_>>();
+        let tables = generators
+            .iter()
+            .map(|g| fixed_base_table(g, CHUNKS_PER_GENERATOR, &curve))
+            .collect();
+
+        PedersenParams {
+            curve,
+            generators,
+            tables,
+        }
+    }
+
+    pub fn curve(&self) -> &EdwardsParams<E> {
+        &self.curve
+    }
+
+    pub fn generators(&self) -> &[EdwardsPoint<E>] {
+        &self.generators
+    }
+
+    /// The maximum number of input bits.
+    pub fn max_bits(&self) -> usize {
+        self.generators.len() * CHUNKS_PER_GENERATOR * 3
+    }
+}
+
+/// Hashes the bits natively, matching [`pedersen_hash_circuit`].
+pub fn pedersen_hash<E, I>(params: &PedersenParams<E>, bits: I) -> EdwardsPoint<E>
+where
+    E: ScalarEngine,
+    I: IntoIterator<Item = bool>,
+{
+    let bits = bits.into_iter().collect::<Vec<_>>();
+    assert!(!bits.is_empty());
+    assert!(bits.len() <= params.max_bits());
+
+    let windows = params.tables.iter().flat_map(|table| table.iter());
+    bits.chunks(3)
+        .zip(windows)
+        .fold(EdwardsPoint::identity(), |acc, (chunk, window)| {
+            let k = chunk
+                .iter()
+                .enumerate()
+                .fold(0, |k, (i, &bit)| if bit { k | (1 << i) } else { k });
+            let (x, y) = window[k];
+            let point = EdwardsPoint::from_coordinates(x, y, &params.curve)
+                .expect("window tables only contain curve points");
+
+            acc.add(&point, &params.curve)
+        })
+}
+
+/// Hashes the bits into a point on the embedded curve.
+pub fn pedersen_hash_circuit<E, CS>(
+    mut cs: CS,
+    params: &PedersenParams<E>,
+    bits: &[Boolean],
+) -> Result<AllocatedEdwardsPoint<E>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert!(!bits.is_empty());
+    assert!(bits.len() <= params.max_bits());
+
+    let mut result: Option<AllocatedEdwardsPoint<E>> = None;
+    for (i, (segment, table)) in bits
+        .chunks(CHUNKS_PER_GENERATOR * 3)
+        .zip(params.tables.iter())
+        .enumerate()
+    {
+        let mut cs = cs.namespace(|| format!("segment {}", i));
+
+        let point = AllocatedEdwardsPoint::fixed_base_mul(
+            cs.namespace(|| "multiplication"),
+            table,
+            segment,
+            &params.curve,
+        )?;
+
+        result = Some(match result {
+            None => point,
+            Some(acc) => acc.add(cs.namespace(|| "addition"), &point, &params.curve)?,
+        });
+    }
+
+    Ok(result.unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bls::Bls12;
+    use ff::Field;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use super::*;
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::gadgets::test::TestConstraintSystem;
+
+    #[test]
+    fn test_pedersen_hash() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = PedersenParams::<Bls12>::new(EdwardsParams::jubjub(), b"Test_PH_", 3);
+
+        for &num_bits in &[1, 8, 189, 190, 400] {
+            let bits = (0..num_bits)
+                .map(|_| rng.next_u32() & 1 == 1)
+                .collect::<Vec<_>>();
+            let expected = pedersen_hash(&params, bits.iter().cloned());
+
+            // The hash is a sum of multiples of the generators.
+            let mut sum = EdwardsPoint::identity();
+            for (segment, g) in bits.chunks(189).zip(params.generators()) {
+                sum = sum.add(
+                    &g.mul(segment.iter().cloned(), params.curve()),
+                    params.curve(),
+                );
+            }
+            assert_eq!(expected, sum);
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let allocated = bits
+                .iter()
+                .enumerate()
+                .map(|(i, b)| {
+                    Boolean::from(
+                        AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(*b))
+                            .unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let result =
+                pedersen_hash_circuit(cs.namespace(|| "hash"), &params, &allocated).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(result.get_value().unwrap(), expected);
+
+            cs.set("bit 0/boolean", Field::one());
+            if !bits[0] {
+                assert!(!cs.is_satisfied());
+            }
+        }
+    }
+
+    #[test]
+    fn test_pedersen_generators() {
+        let params = PedersenParams::<Bls12>::new(EdwardsParams::jubjub(), b"Test_PH_", 2);
+        let other = PedersenParams::<Bls12>::new(EdwardsParams::jubjub(), b"Other_PH", 2);
+
+        for g in params.generators() {
+            assert!(g.is_on_curve(params.curve()));
+            assert!(!g.is_identity());
+        }
+        assert!(params.generators()[0] != params.generators()[1]);
+        assert!(params.generators()[0] != other.generators()[0]);
+    }
+}
@@ -0,0 +1,457 @@
+//! The [Poseidon] hash function, as a circuit and as a native implementation
+//! over the scalar field.
+//!
+//! The S-box is `x^5`, so the scalar field must satisfy `gcd(5, r - 1) = 1`,
+//! which is the case for BLS12-381.
+//!
+//! [Poseidon]: https://eprint.iacr.org/2019/458
+
+use blake2s_simd::Params as Blake2sParams;
+use byteorder::{ByteOrder, LittleEndian};
+use ff::{Field, PrimeField, PrimeFieldRepr, ScalarEngine};
+
+use super::num::AllocatedNum;
+use super::Assignment;
+use crate::{ConstraintSystem, LinearCombination, SynthesisError, Variable};
+
+/// The round constants and the MDS matrix of a Poseidon instance.
+#[derive(Clone)]
+pub struct PoseidonConstants<E: ScalarEngine> {
+    width: usize,
+    full_rounds: usize,
+    partial_rounds: usize,
+    round_constants: Vec<E::Fr>,
+    mds: Vec<Vec<E::Fr>>,
+}
+
+impl<E: ScalarEngine> PoseidonConstants<E> {
+    /// Creates the constants for a permutation over `width` elements, using the
+    /// number of rounds recommended for 128-bit security over a 255-bit field.
+    pub fn new(width: usize) -> Self {
+        let partial_rounds = match width {
+            0..=3 => 57,
+            4..=5 => 60,
+            6..=9 => 63,
+            _ => 64,
+        };
+
+        Self::new_with_rounds(width, 8, partial_rounds)
+    }
+
+    /// Creates the constants for a permutation with a custom number of rounds.
+    ///
+    /// The round constants are derived with BLAKE2s, hence the resulting hash is
+    /// not compatible with other Poseidon instantiations. Use
+    /// [`PoseidonConstants::from_parts`] for supplying the constants of those.
+    pub fn new_with_rounds(width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        assert!(width >= 2, "the width must be at least 2");
+        assert!(
+            full_rounds % 2 == 0,
+            "the number of full rounds must be even"
+        );
+
+        let round_constants = (0..width * (full_rounds + partial_rounds))
+            .map(|i| derive_constant::<E>(width, i))
+            .collect();
+
+        // A Cauchy matrix `1 / (x_i + y_j)` with `x_i = i` and `y_j = width + j`,
+        // which is always invertible as all `x_i` and `y_j` are distinct.
+        let mds = (0..width)
+            .map(|i| {
+                (0..width)
+                    .map(|j| {
+                        E::Fr::from_str(&format!("{}", i + width + j))
+                            .expect("small integers are valid field elements")
+                            .inverse()
+                            .expect("small integers are non-zero")
+                    })
+                    .collect()
+            })
+            .collect();
+
+        PoseidonConstants {
+            width,
+            full_rounds,
+            partial_rounds,
+            round_constants,
+            mds,
+        }
+    }
+
+    /// Creates the constants from their parts. `round_constants` contains
+    /// `width` elements per round, `mds` is a `width` by `width` matrix.
+    pub fn from_parts(
+        full_rounds: usize,
+        partial_rounds: usize,
+        round_constants: Vec<E::Fr>,
+        mds: Vec<Vec<E::Fr>>,
+    ) -> Self {
+        let width = mds.len();
+        assert!(width >= 2, "the width must be at least 2");
+        assert!(
+            full_rounds % 2 == 0,
+            "the number of full rounds must be even"
+        );
+        assert!(mds.iter().all(|row| row.len() == width));
+        assert_eq!(
+            round_constants.len(),
+            width * (full_rounds + partial_rounds)
+        );
+
+        PoseidonConstants {
+            width,
+            full_rounds,
+            partial_rounds,
+            round_constants,
+            mds,
+        }
+    }
+
+    /// The number of elements the permutation operates on.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The number of elements absorbed by [`poseidon_hash`].
+    pub fn arity(&self) -> usize {
+        self.width - 1
+    }
+
+    fn is_full_round(&self, round: usize) -> bool {
+        let half = self.full_rounds / 2;
+        round < half || round >= half + self.partial_rounds
+    }
+
+    fn num_rounds(&self) -> usize {
+        self.full_rounds + self.partial_rounds
+    }
+}
+
+/// Derives the `index`-th round constant by hashing it with BLAKE2s, rejecting
+/// values that are not in the field.
+fn derive_constant<E: ScalarEngine>(width: usize, index: usize) -> E::Fr {
+    let mut counter = 0u64;
+    loop {
+        let mut repr = <E::Fr as PrimeField>::Repr::default();
+        let mut bytes = vec![0u8; repr.as_ref().len() * 8];
+        for (i, chunk) in bytes.chunks_mut(32).enumerate() {
+            let mut buf = [0u8; 32];
+            LittleEndian::write_u64(&mut buf[0..8], width as u64);
+            LittleEndian::write_u64(&mut buf[8..16], index as u64);
+            LittleEndian::write_u64(&mut buf[16..24], counter);
+            LittleEndian::write_u64(&mut buf[24..32], i as u64);
+
+            let hash = Blake2sParams::new()
+                .hash_length(32)
+                .personal(b"Poseidon")
+                .hash(&buf);
+            chunk.copy_from_slice(&hash.as_bytes()[..chunk.len()]);
+        }
+        repr.read_le(&bytes[..])
+            .expect("the buffer has the size of the representation");
+
+        // Only keep as many bits as the modulus has, to make the rejection rare.
+        let num_bits = E::Fr::NUM_BITS as usize;
+        for (i, limb) in repr.as_mut().iter_mut().enumerate() {
+            if (i + 1) * 64 > num_bits {
+                let keep = num_bits.saturating_sub(i * 64);
+                *limb = if keep == 0 {
+                    0
+                } else {
+                    *limb & (u64::max_value() >> (64 - keep))
+                };
+            }
+        }
+
+        if let Ok(constant) = E::Fr::from_repr(repr) {
+            return constant;
+        }
+        counter += 1;
+    }
+}
+
+fn sbox<F: Field>(x: &mut F) {
+    let mut tmp = *x;
+    tmp.square();
+    tmp.square();
+    x.mul_assign(&tmp);
+}
+
+/// Applies the Poseidon permutation to `state`.
+pub fn poseidon_permutation<E: ScalarEngine>(
+    constants: &PoseidonConstants<E>,
+    state: &mut [E::Fr],
+) {
+    assert_eq!(state.len(), constants.width);
+
+    let mut round_constants = constants.round_constants.iter();
+    for round in 0..constants.num_rounds() {
+        for s in state.iter_mut() {
+            s.add_assign(round_constants.next().unwrap());
+        }
+
+        if constants.is_full_round(round) {
+            for s in state.iter_mut() {
+                sbox(s);
+            }
+        } else {
+            sbox(&mut state[0]);
+        }
+
+        let mixed = constants
+            .mds
+            .iter()
+            .map(|row| {
+                let mut acc = E::Fr::zero();
+                for (m, s) in row.iter().zip(state.iter()) {
+                    let mut tmp = *m;
+                    tmp.mul_assign(s);
+                    acc.add_assign(&tmp);
+                }
+                acc
+            })
+            .collect::<Vec<_>>();
+        state.copy_from_slice(&mixed);
+    }
+}
+
+/// Hashes exactly `constants.arity()` field elements into one.
+pub fn poseidon_hash<E: ScalarEngine>(constants: &PoseidonConstants<E>, inputs: &[E::Fr]) -> E::Fr {
+    assert_eq!(inputs.len(), constants.arity());
+
+    // The first element is the capacity, it starts out as zero.
+    let mut state = vec![E::Fr::zero()];
+    state.extend_from_slice(inputs);
+    poseidon_permutation(constants, &mut state);
+
+    state[1]
+}
+
+/// An element of the state within the circuit: a linear combination together
+/// with its value. Terms of the same variable are merged, so that the linear
+/// combinations stay small during the partial rounds.
+struct Elt<E: ScalarEngine> {
+    value: Option<E::Fr>,
+    terms: Vec<(Variable, E::Fr)>,
+}
+
+impl<E: ScalarEngine> Elt<E> {
+    fn zero() -> Self {
+        Elt {
+            value: Some(E::Fr::zero()),
+            terms: vec![],
+        }
+    }
+
+    fn from_variable(variable: Variable, value: Option<E::Fr>) -> Self {
+        Elt {
+            value,
+            terms: vec![(variable, E::Fr::one())],
+        }
+    }
+
+    fn add_term(&mut self, variable: Variable, coeff: E::Fr) {
+        match self
+            .terms
+            .iter_mut()
+            .find(|(v, _)| v.get_unchecked() == variable.get_unchecked())
+        {
+            Some(term) => term.1.add_assign(&coeff),
+            None => self.terms.push((variable, coeff)),
+        }
+    }
+
+    fn add_constant(&mut self, one: Variable, constant: &E::Fr) {
+        if let Some(ref mut value) = self.value {
+            value.add_assign(constant);
+        }
+        self.add_term(one, *constant);
+    }
+
+    fn add_scaled(&mut self, other: &Self, coeff: &E::Fr) {
+        self.value = match (self.value, other.value) {
+            (Some(mut value), Some(mut other)) => {
+                other.mul_assign(coeff);
+                value.add_assign(&other);
+                Some(value)
+            }
+            _ => None,
+        };
+        for &(variable, c) in &other.terms {
+            let mut c = c;
+            c.mul_assign(coeff);
+            self.add_term(variable, c);
+        }
+    }
+
+    fn lc(&self) -> LinearCombination<E> {
+        self.terms
+            .iter()
+            .fold(LinearCombination::zero(), |lc, &(variable, coeff)| {
+                lc + (coeff, variable)
+            })
+    }
+}
+
+/// Computes `x^5` with three constraints.
+fn sbox_circuit<E, CS>(mut cs: CS, x: &Elt<E>) -> Result<Elt<E>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let x2_value = x.value.map(|mut v| {
+        v.square();
+        v
+    });
+    let x2 = cs.alloc(|| "x^2", || Ok(*x2_value.get()?))?;
+    cs.enforce(|| "x^2 = x * x", |_| x.lc(), |_| x.lc(), |lc| lc + x2);
+
+    let x4_value = x2_value.map(|mut v| {
+        v.square();
+        v
+    });
+    let x4 = cs.alloc(|| "x^4", || Ok(*x4_value.get()?))?;
+    cs.enforce(
+        || "x^4 = x^2 * x^2",
+        |lc| lc + x2,
+        |lc| lc + x2,
+        |lc| lc + x4,
+    );
+
+    let x5_value = match (x4_value, x.value) {
+        (Some(mut x4), Some(x)) => {
+            x4.mul_assign(&x);
+            Some(x4)
+        }
+        _ => None,
+    };
+    let x5 = cs.alloc(|| "x^5", || Ok(*x5_value.get()?))?;
+    cs.enforce(|| "x^5 = x^4 * x", |lc| lc + x4, |_| x.lc(), |lc| lc + x5);
+
+    Ok(Elt::from_variable(x5, x5_value))
+}
+
+/// Hashes exactly `constants.arity()` allocated numbers into one, matching
+/// [`poseidon_hash`].
+pub fn poseidon_hash_circuit<E, CS>(
+    mut cs: CS,
+    constants: &PoseidonConstants<E>,
+    inputs: &[AllocatedNum<E>],
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert_eq!(inputs.len(), constants.arity());
+
+    let mut state = vec![Elt::zero()];
+    state.extend(
+        inputs
+            .iter()
+            .map(|input| Elt::from_variable(input.get_variable(), input.get_value())),
+    );
+
+    let mut round_constants = constants.round_constants.iter();
+    for round in 0..constants.num_rounds() {
+        let mut cs = cs.namespace(|| format!("round {}", round));
+
+        for s in state.iter_mut() {
+            s.add_constant(CS::one(), round_constants.next().unwrap());
+        }
+
+        if constants.is_full_round(round) {
+            for (i, s) in state.iter_mut().enumerate() {
+                *s = sbox_circuit(cs.namespace(|| format!("sbox {}", i)), s)?;
+            }
+        } else {
+            state[0] = sbox_circuit(cs.namespace(|| "sbox 0"), &state[0])?;
+        }
+
+        state = constants
+            .mds
+            .iter()
+            .map(|row| {
+                let mut acc = Elt::zero();
+                for (m, s) in row.iter().zip(state.iter()) {
+                    acc.add_scaled(s, m);
+                }
+                acc
+            })
+            .collect();
+    }
+
+    let out = &state[1];
+    let result = AllocatedNum::alloc(cs.namespace(|| "result"), || Ok(*out.value.get()?))?;
+    cs.enforce(
+        || "result computation",
+        |_| out.lc(),
+        |lc| lc + CS::one(),
+        |lc| lc + result.get_variable(),
+    );
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bls::{Bls12, Fr};
+    use ff::Field;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    use super::*;
+    use crate::gadgets::test::TestConstraintSystem;
+
+    #[test]
+    fn test_poseidon_hash() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for &width in &[2, 3, 5] {
+            let constants = PoseidonConstants::<Bls12>::new(width);
+
+            for _ in 0..5 {
+                let values = (0..constants.arity())
+                    .map(|_| Fr::random(&mut rng))
+                    .collect::<Vec<_>>();
+                let expected = poseidon_hash(&constants, &values);
+
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+                let inputs = values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| {
+                        AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*v))
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>();
+
+                let out = poseidon_hash_circuit(&mut cs, &constants, &inputs).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(out.get_value().unwrap(), expected);
+                assert_eq!(
+                    cs.num_constraints(),
+                    3 * (width * constants.full_rounds + constants.partial_rounds) + 1
+                );
+
+                cs.set("result/num", Fr::random(&mut rng));
+                assert!(!cs.is_satisfied());
+            }
+        }
+    }
+
+    #[test]
+    fn test_poseidon_constants_deterministic() {
+        let a = PoseidonConstants::<Bls12>::new(3);
+        let b = PoseidonConstants::<Bls12>::new(3);
+        assert_eq!(a.round_constants, b.round_constants);
+
+        // Different inputs lead to different digests.
+        let one = poseidon_hash(&a, &[Fr::one(), Fr::zero()]);
+        let two = poseidon_hash(&a, &[Fr::zero(), Fr::one()]);
+        assert!(one != two);
+    }
+}
@@ -0,0 +1,223 @@
+//! Range checks on allocated numbers.
+
+use ff::{Field, PrimeField, PrimeFieldRepr, ScalarEngine};
+
+use super::boolean::{AllocatedBit, Boolean};
+use super::num::AllocatedNum;
+use crate::{ConstraintSystem, LinearCombination, SynthesisError};
+
+/// Returns the little-endian bits of `value` up to `num_bits`.
+fn bits_le<F: PrimeField>(value: Option<F>, num_bits: usize) -> Vec<Option<bool>> {
+    match value {
+        Some(value) => {
+            let repr = value.into_repr();
+            let limbs = repr.as_ref();
+            (0..num_bits)
+                .map(|i| Some(i < limbs.len() * 64 && (limbs[i / 64] >> (i % 64)) & 1 == 1))
+                .collect()
+        }
+        None => vec![None; num_bits],
+    }
+}
+
+/// Decomposes the linear combination into `num_bits` bits and enforces that
+/// they add up to it.
+fn decompose<E, CS>(
+    mut cs: CS,
+    value: Option<E::Fr>,
+    lc: LinearCombination<E>,
+    num_bits: usize,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert!(num_bits > 0);
+    assert!(num_bits <= E::Fr::CAPACITY as usize);
+
+    let bits = bits_le(value, num_bits)
+        .into_iter()
+        .enumerate()
+        .map(|(i, b)| AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), b))
+        .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+    let mut packed = LinearCombination::zero();
+    let mut coeff = E::Fr::one();
+    for bit in &bits {
+        packed = packed + (coeff, bit.get_variable());
+
+        coeff.double();
+    }
+
+    cs.enforce(
+        || "packing constraint",
+        |_| packed,
+        |lc| lc + CS::one(),
+        |_| lc,
+    );
+
+    Ok(bits.into_iter().map(Boolean::from).collect())
+}
+
+/// Enforces that `num < 2^num_bits` and returns its little-endian bits.
+///
+/// `num_bits` must not exceed the capacity of the field, as otherwise the bits
+/// could represent a number that wraps around the modulus.
+pub fn assert_in_range<E, CS>(
+    cs: CS,
+    num: &AllocatedNum<E>,
+    num_bits: usize,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    decompose(
+        cs,
+        num.get_value(),
+        LinearCombination::zero() + num.get_variable(),
+        num_bits,
+    )
+}
+
+/// Enforces that `num < bound` for a constant, non-zero `bound` and returns
+/// the little-endian bits of `num`.
+///
+/// With `n` being the bit length of `bound - 1`, this checks that both `num`
+/// and `bound - 1 - num` fit into `n` bits, which costs `2 * n + 2`
+/// constraints. `n` must be smaller than the capacity of the field.
+pub fn assert_less_than<E, CS>(
+    mut cs: CS,
+    num: &AllocatedNum<E>,
+    bound: &E::Fr,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert!(!bound.is_zero(), "nothing is smaller than zero");
+
+    let mut max = *bound;
+    max.sub_assign(&E::Fr::one());
+
+    let num_bits = {
+        let repr = max.into_repr();
+        (repr.num_bits() as usize).max(1)
+    };
+    assert!(
+        num_bits < E::Fr::CAPACITY as usize,
+        "the bound is too large"
+    );
+
+    let bits = assert_in_range(cs.namespace(|| "num"), num, num_bits)?;
+
+    let difference = num.get_value().map(|value| {
+        let mut tmp = max;
+        tmp.sub_assign(&value);
+        tmp
+    });
+    decompose(
+        cs.namespace(|| "difference"),
+        difference,
+        LinearCombination::zero() + (max, CS::one()) - num.get_variable(),
+        num_bits,
+    )?;
+
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bls::{Bls12, Fr};
+    use ff::{Field, PrimeField};
+
+    use super::*;
+    use crate::gadgets::test::TestConstraintSystem;
+
+    #[test]
+    fn test_assert_in_range() {
+        for &(value, num_bits, expected) in &[
+            ("0", 1, true),
+            ("1", 1, true),
+            ("2", 1, false),
+            ("255", 8, true),
+            ("256", 8, false),
+            ("65535", 16, true),
+        ] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let num =
+                AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(Fr::from_str(value).unwrap()))
+                    .unwrap();
+
+            let bits = assert_in_range(cs.namespace(|| "range"), &num, num_bits).unwrap();
+
+            assert_eq!(bits.len(), num_bits);
+            assert_eq!(cs.num_constraints(), num_bits + 1);
+            assert_eq!(cs.is_satisfied(), expected, "{} < 2^{}", value, num_bits);
+        }
+    }
+
+    #[test]
+    fn test_assert_in_range_negative() {
+        // -1 is the largest field element, which must not pass.
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let num = AllocatedNum::alloc(cs.namespace(|| "num"), || {
+            let mut tmp = Fr::one();
+            tmp.negate();
+            Ok(tmp)
+        })
+        .unwrap();
+
+        assert_in_range(cs.namespace(|| "range"), &num, 64).unwrap();
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_assert_less_than() {
+        for &(value, bound, expected) in &[
+            ("0", "1", true),
+            ("1", "1", false),
+            ("9", "10", true),
+            ("10", "10", false),
+            ("11", "10", false),
+            ("15", "10", false),
+            ("16", "10", false),
+            ("0", "256", true),
+            ("255", "256", true),
+            ("256", "256", false),
+        ] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let num =
+                AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(Fr::from_str(value).unwrap()))
+                    .unwrap();
+
+            assert_less_than(
+                cs.namespace(|| "less than"),
+                &num,
+                &Fr::from_str(bound).unwrap(),
+            )
+            .unwrap();
+
+            assert_eq!(cs.is_satisfied(), expected, "{} < {}", value, bound);
+        }
+    }
+
+    #[test]
+    fn test_assert_less_than_negative() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let num = AllocatedNum::alloc(cs.namespace(|| "num"), || {
+            let mut tmp = Fr::one();
+            tmp.negate();
+            Ok(tmp)
+        })
+        .unwrap();
+
+        assert_less_than(
+            cs.namespace(|| "less than"),
+            &num,
+            &Fr::from_str("1000").unwrap(),
+        )
+        .unwrap();
+        assert!(!cs.is_satisfied());
+    }
+}
@@ -0,0 +1,532 @@
+//! A constraint system that records the full R1CS of a circuit, together with
+//! the witness if there is one, so that it can be exported. For inspecting
+//! the structure of a circuit, the constraint graph can be exported with
+//! [`RecordingCS::write_dot`] and [`RecordingCS::write_json`] as well.
+//!
+//! The export uses the binary `.r1cs` format of [circom], which other proving
+//! backends and analysis tools understand as well. All integers are
+//! little-endian:
+//!
+//! ```text
+//! "r1cs" | version: u32 = 1 | number of sections: u32
+//! every section: type: u32 | size in bytes: u64 | content
+//!
+//! header (type 1):
+//!     field element size n8: u32 | prime: n8 bytes | number of wires: u32 |
+//!     public outputs: u32 | public inputs: u32 | private inputs: u32 |
+//!     number of labels: u64 | number of constraints: u32
+//! constraints (type 2), for every constraint A, B and C:
+//!     number of terms: u32 | every term: wire: u32 | coefficient: n8 bytes
+//! wire to label map (type 3):
+//!     label of every wire: u64
+//! ```
+//!
+//! Wire `0` is the constant `ONE`, followed by the public inputs and then the
+//! auxiliary variables. The public inputs are exported as public inputs, there
+//! are no public outputs and no private inputs. The labels are the wire ids.
+//!
+//! [circom]: https://github.com/iden3/r1csfile/blob/master/doc/r1cs_bin_format.md
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use byteorder::{LittleEndian, WriteBytesExt};
+use ff::{Field, PrimeField, PrimeFieldRepr, ScalarEngine};
+
+use crate::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+pub(crate) const R1CS_MAGIC: &[u8; 4] = b"r1cs";
+pub(crate) const R1CS_VERSION: u32 = 1;
+pub(crate) const R1CS_HEADER_SECTION: u32 = 1;
+pub(crate) const R1CS_CONSTRAINTS_SECTION: u32 = 2;
+pub(crate) const R1CS_WIRE_TO_LABEL_SECTION: u32 = 3;
+
+/// A constraint with the variables replaced by wire ids, without duplicate or
+/// zero terms.
+pub type WireConstraint<F> = (Vec<(usize, F)>, Vec<(usize, F)>, Vec<(usize, F)>);
+
+/// Constraint system that records all constraints and assignments.
+pub struct RecordingCS<E: ScalarEngine> {
+    inputs: Vec<(Option<E::Fr>, String)>,
+    aux: Vec<(Option<E::Fr>, String)>,
+    constraints: Vec<(
+        LinearCombination<E>,
+        LinearCombination<E>,
+        LinearCombination<E>,
+        String,
+    )>,
+    current_namespace: Vec<String>,
+}
+
+impl<E: ScalarEngine> RecordingCS<E> {
+    pub fn new() -> Self {
+        RecordingCS {
+            inputs: vec![(Some(E::Fr::one()), "ONE".into())],
+            aux: vec![],
+            constraints: vec![],
+            current_namespace: vec![],
+        }
+    }
+
+    /// The number of inputs, including `ONE`.
+    pub fn num_inputs(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn num_aux(&self) -> usize {
+        self.aux.len()
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    pub fn num_wires(&self) -> usize {
+        self.inputs.len() + self.aux.len()
+    }
+
+    /// The wire id of a variable, inputs come first.
+    pub fn wire(&self, var: Variable) -> usize {
+        match var.get_unchecked() {
+            Index::Input(i) => i,
+            Index::Aux(i) => self.inputs.len() + i,
+        }
+    }
+
+    /// The annotation of the variable on the given wire.
+    pub fn wire_name(&self, wire: usize) -> &str {
+        if wire < self.inputs.len() {
+            &self.inputs[wire].1
+        } else {
+            &self.aux[wire - self.inputs.len()].1
+        }
+    }
+
+    /// The annotation of the constraint with the given index.
+    pub fn constraint_name(&self, index: usize) -> &str {
+        &self.constraints[index].3
+    }
+
+    fn wire_terms(&self, lc: &LinearCombination<E>) -> Vec<(usize, E::Fr)> {
+        let mut terms = BTreeMap::new();
+        for &(var, coeff) in lc.as_ref() {
+            terms
+                .entry(self.wire(var))
+                .or_insert_with(E::Fr::zero)
+                .add_assign(&coeff);
+        }
+
+        terms
+            .into_iter()
+            .filter(|(_, coeff)| !coeff.is_zero())
+            .collect()
+    }
+
+    /// Returns the constraints in terms of wire ids.
+    pub fn wire_constraints(&self) -> Vec<WireConstraint<E::Fr>> {
+        self.constraints
+            .iter()
+            .map(|(a, b, c, _)| (self.wire_terms(a), self.wire_terms(b), self.wire_terms(c)))
+            .collect()
+    }
+
+    /// Returns the assignment of every wire, if the circuit was synthesized with
+    /// a witness.
+    pub fn witness(&self) -> Option<Vec<E::Fr>> {
+        self.inputs
+            .iter()
+            .chain(self.aux.iter())
+            .map(|(value, _)| *value)
+            .collect()
+    }
+
+    /// Returns the wires other than `ONE` that don't appear in any constraint.
+    /// They can take any value, which is usually a bug.
+    pub fn unconstrained_wires(&self) -> Vec<usize> {
+        let mut constrained = vec![false; self.num_wires()];
+        constrained[0] = true;
+        for (a, b, c) in self.wire_constraints() {
+            for (wire, _) in a.iter().chain(b.iter()).chain(c.iter()) {
+                constrained[*wire] = true;
+            }
+        }
+
+        (0..self.num_wires())
+            .filter(|wire| !constrained[*wire])
+            .collect()
+    }
+
+    /// Writes the bipartite graph of constraints and wires in the Graphviz DOT
+    /// format. Inputs are drawn as double circles, constraints as boxes and
+    /// unconstrained wires in red. Every edge is labeled with the matrices the
+    /// wire appears in.
+    pub fn write_dot<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let unconstrained = self.unconstrained_wires();
+
+        writeln!(writer, "graph circuit {{")?;
+        for wire in 0..self.num_wires() {
+            let shape = if wire < self.inputs.len() {
+                "doublecircle"
+            } else {
+                "ellipse"
+            };
+            write!(
+                writer,
+                "  w{} [label=\"{}\", shape={}",
+                wire,
+                escape(self.wire_name(wire)),
+                shape
+            )?;
+            if unconstrained.contains(&wire) {
+                write!(writer, ", color=red")?;
+            }
+            writeln!(writer, "];")?;
+        }
+        for (i, constraint) in self.wire_constraints().iter().enumerate() {
+            writeln!(
+                writer,
+                "  c{} [label=\"{}\", shape=box];",
+                i,
+                escape(self.constraint_name(i))
+            )?;
+            for (wire, matrices) in edges(constraint) {
+                writeln!(writer, "  c{} -- w{} [label=\"{}\"];", i, wire, matrices)?;
+            }
+        }
+        writeln!(writer, "}}")
+    }
+
+    /// Writes the graph of [`write_dot`] as compact JSON, listing every wire and
+    /// the wires every constraint refers to:
+    ///
+    /// ```text
+    /// {"wires":[{"name":"ONE","input":true,"constrained":true},...],
+    ///  "constraints":[{"name":"square","a":[2],"b":[2],"c":[1]},...]}
+    /// ```
+    ///
+    /// [`write_dot`]: RecordingCS::write_dot
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let unconstrained = self.unconstrained_wires();
+
+        write!(writer, "{{\"wires\":[")?;
+        for wire in 0..self.num_wires() {
+            if wire > 0 {
+                write!(writer, ",")?;
+            }
+            write!(
+                writer,
+                "{{\"name\":\"{}\",\"input\":{},\"constrained\":{}}}",
+                escape(self.wire_name(wire)),
+                wire < self.inputs.len(),
+                !unconstrained.contains(&wire)
+            )?;
+        }
+        write!(writer, "],\"constraints\":[")?;
+        for (i, (a, b, c)) in self.wire_constraints().iter().enumerate() {
+            if i > 0 {
+                write!(writer, ",")?;
+            }
+            let wires = |terms: &[(usize, E::Fr)]| {
+                terms
+                    .iter()
+                    .map(|(wire, _)| wire.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            };
+            write!(
+                writer,
+                "{{\"name\":\"{}\",\"a\":[{}],\"b\":[{}],\"c\":[{}]}}",
+                escape(self.constraint_name(i)),
+                wires(a),
+                wires(b),
+                wires(c)
+            )?;
+        }
+        write!(writer, "]}}")
+    }
+
+    /// Writes the R1CS in the `.r1cs` format described in the module
+    /// documentation.
+    pub fn write_r1cs<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let n8 = field_size::<E::Fr>();
+
+        let mut header = vec![];
+        header.write_u32::<LittleEndian>(n8 as u32)?;
+        E::Fr::char().write_le(&mut header)?;
+        header.write_u32::<LittleEndian>(self.num_wires() as u32)?;
+        header.write_u32::<LittleEndian>(0)?;
+        header.write_u32::<LittleEndian>((self.inputs.len() - 1) as u32)?;
+        header.write_u32::<LittleEndian>(0)?;
+        header.write_u64::<LittleEndian>(self.num_wires() as u64)?;
+        header.write_u32::<LittleEndian>(self.constraints.len() as u32)?;
+
+        let mut constraints = vec![];
+        for (a, b, c) in self.wire_constraints() {
+            for terms in &[a, b, c] {
+                constraints.write_u32::<LittleEndian>(terms.len() as u32)?;
+                for (wire, coeff) in terms {
+                    constraints.write_u32::<LittleEndian>(*wire as u32)?;
+                    coeff.into_repr().write_le(&mut constraints)?;
+                }
+            }
+        }
+
+        let mut labels = vec![];
+        for wire in 0..self.num_wires() {
+            labels.write_u64::<LittleEndian>(wire as u64)?;
+        }
+
+        writer.write_all(R1CS_MAGIC)?;
+        writer.write_u32::<LittleEndian>(R1CS_VERSION)?;
+        writer.write_u32::<LittleEndian>(3)?;
+        for (section_type, content) in &[
+            (R1CS_HEADER_SECTION, header),
+            (R1CS_CONSTRAINTS_SECTION, constraints),
+            (R1CS_WIRE_TO_LABEL_SECTION, labels),
+        ] {
+            writer.write_u32::<LittleEndian>(*section_type)?;
+            writer.write_u64::<LittleEndian>(content.len() as u64)?;
+            writer.write_all(content)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<E: ScalarEngine> Default for RecordingCS<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The number of bytes of a serialized field element.
+pub(crate) fn field_size<F: PrimeField>() -> usize {
+    F::Repr::default().as_ref().len() * 8
+}
+
+/// Escapes a string, so that it can be used within double quotes in JSON and
+/// in DOT.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// The wires of a constraint, together with the matrices they appear in.
+fn edges<F>(constraint: &WireConstraint<F>) -> BTreeMap<usize, String> {
+    let (a, b, c) = constraint;
+    let mut edges = BTreeMap::new();
+    for (matrix, terms) in &[('A', a), ('B', b), ('C', c)] {
+        for (wire, _) in terms.iter() {
+            edges.entry(*wire).or_insert_with(String::new).push(*matrix);
+        }
+    }
+
+    edges
+}
+
+fn compute_path(ns: &[String], this: String) -> String {
+    ns.iter()
+        .chain(Some(&this))
+        .map(|s| s.as_str())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn record_assignment<F, T>(f: F) -> Result<Option<T>, SynthesisError>
+where
+    F: FnOnce() -> Result<T, SynthesisError>,
+{
+    match f() {
+        Ok(value) => Ok(Some(value)),
+        // Recording the shape without a witness is fine.
+        Err(SynthesisError::AssignmentMissing) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+impl<E: ScalarEngine> ConstraintSystem<E> for RecordingCS<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let index = self.aux.len();
+        let path = compute_path(&self.current_namespace, annotation().into());
+        let value = record_assignment(f).map_err(|e| e.at_path(path.clone()))?;
+        self.aux.push((value, path));
+
+        Ok(Variable::new_unchecked(Index::Aux(index)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let index = self.inputs.len();
+        let path = compute_path(&self.current_namespace, annotation().into());
+        let value = record_assignment(f).map_err(|e| e.at_path(path.clone()))?;
+        self.inputs.push((value, path));
+
+        Ok(Variable::new_unchecked(Index::Input(index)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        let path = compute_path(&self.current_namespace, annotation().into());
+
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+
+        self.constraints.push((a, b, c, path));
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.current_namespace.push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self) {
+        assert!(self.current_namespace.pop().is_some());
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bls::{Bls12, Fr};
+    use byteorder::{ByteOrder, LittleEndian};
+
+    use super::*;
+
+    #[test]
+    fn test_recording_cs() {
+        let mut cs = RecordingCS::<Bls12>::new();
+        let a = cs
+            .namespace(|| "a")
+            .alloc(|| "var", || Ok(Fr::from_str("3").unwrap()))
+            .unwrap();
+        let b = cs
+            .alloc_input(|| "b", || Ok(Fr::from_str("9").unwrap()))
+            .unwrap();
+        // Duplicate terms get merged, zero terms dropped.
+        cs.enforce(
+            || "square",
+            |lc| lc + a,
+            |lc| lc + a + a - a,
+            |lc| lc + b + (Fr::zero(), a),
+        );
+
+        assert_eq!(cs.num_wires(), 3);
+        assert_eq!(cs.wire(a), 2);
+        assert_eq!(cs.wire_name(2), "a/var");
+        assert_eq!(cs.constraint_name(0), "square");
+        assert_eq!(
+            cs.wire_constraints(),
+            vec![(
+                vec![(2, Fr::one())],
+                vec![(2, Fr::one())],
+                vec![(1, Fr::one())]
+            )]
+        );
+        assert_eq!(
+            cs.witness().unwrap(),
+            vec![
+                Fr::one(),
+                Fr::from_str("9").unwrap(),
+                Fr::from_str("3").unwrap()
+            ]
+        );
+
+        let mut buf = vec![];
+        cs.write_r1cs(&mut buf).unwrap();
+
+        assert_eq!(&buf[0..4], b"r1cs");
+        assert_eq!(LittleEndian::read_u32(&buf[4..8]), 1);
+        assert_eq!(LittleEndian::read_u32(&buf[8..12]), 3);
+        // The header section.
+        assert_eq!(LittleEndian::read_u32(&buf[12..16]), 1);
+        assert_eq!(LittleEndian::read_u64(&buf[16..24]), 4 + 32 + 4 * 4 + 8 + 4);
+        assert_eq!(LittleEndian::read_u32(&buf[24..28]), 32);
+        let header = &buf[60..];
+        // Wires, public outputs, public inputs, private inputs.
+        assert_eq!(LittleEndian::read_u32(&header[0..4]), 3);
+        assert_eq!(LittleEndian::read_u32(&header[4..8]), 0);
+        assert_eq!(LittleEndian::read_u32(&header[8..12]), 1);
+        assert_eq!(LittleEndian::read_u32(&header[12..16]), 0);
+        // Labels and constraints.
+        assert_eq!(LittleEndian::read_u64(&header[16..24]), 3);
+        assert_eq!(LittleEndian::read_u32(&header[24..28]), 1);
+    }
+
+    #[test]
+    fn test_recording_cs_without_witness() {
+        let mut cs = RecordingCS::<Bls12>::new();
+        cs.alloc(|| "var", || Err(SynthesisError::AssignmentMissing))
+            .unwrap();
+
+        assert_eq!(cs.num_aux(), 1);
+        assert!(cs.witness().is_none());
+    }
+
+    #[test]
+    fn test_graph_export() {
+        let mut cs = RecordingCS::<Bls12>::new();
+        let a = cs.alloc(|| "a", || Ok(Fr::one())).unwrap();
+        cs.alloc(|| "\"b\"", || Ok(Fr::one())).unwrap();
+        cs.enforce(|| "square", |lc| lc + a, |lc| lc + a, |lc| lc + a);
+
+        assert_eq!(cs.unconstrained_wires(), vec![2]);
+
+        let mut json = vec![];
+        cs.write_json(&mut json).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            "{\"wires\":[\
+             {\"name\":\"ONE\",\"input\":true,\"constrained\":true},\
+             {\"name\":\"a\",\"input\":false,\"constrained\":true},\
+             {\"name\":\"\\\"b\\\"\",\"input\":false,\"constrained\":false}],\
+             \"constraints\":[{\"name\":\"square\",\"a\":[1],\"b\":[1],\"c\":[1]}]}"
+        );
+
+        let mut dot = vec![];
+        cs.write_dot(&mut dot).unwrap();
+        assert_eq!(
+            String::from_utf8(dot).unwrap(),
+            "graph circuit {\n\
+             \x20 w0 [label=\"ONE\", shape=doublecircle];\n\
+             \x20 w1 [label=\"a\", shape=ellipse];\n\
+             \x20 w2 [label=\"\\\"b\\\"\", shape=ellipse, color=red];\n\
+             \x20 c0 [label=\"square\", shape=box];\n\
+             \x20 c0 -- w1 [label=\"ABC\"];\n\
+             }\n"
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! Schnorr signatures over the embedded curve from [`super::ecc`], with a
+//! Poseidon challenge so that verification is cheap within a circuit.
+//!
+//! A signature on the field element `m` under the public key `A = a * G` is a
+//! pair `(R, s)` with `s * G = R + H(R, A, m) * A`. Verification is cofactored,
+//! both sides are multiplied by the cofactor before comparing them. Scalars
+//! are represented as elements of the scalar field that are smaller than the
+//! order of the prime-order subgroup.
+
+use blake2s_simd::Params as Blake2sParams;
+use ff::{BitIterator, PrimeField, PrimeFieldRepr, ScalarEngine};
+
+use super::ecc::{
+    fixed_base_table, group_hash, AllocatedEdwardsPoint, EdwardsParams, EdwardsPoint,
+};
+use super::num::AllocatedNum;
+use super::poseidon::{poseidon_hash, poseidon_hash_circuit, PoseidonConstants};
+use super::range::assert_less_than;
+use crate::{ConstraintSystem, SynthesisError};
+
+/// The generator and the challenge hash of the signature scheme.
+pub struct SchnorrParams<E: ScalarEngine> {
+    curve: EdwardsParams<E>,
+    generator: EdwardsPoint<E>,
+    table: Vec<Vec<(E::Fr, E::Fr)>>,
+    hasher: PoseidonConstants<E>,
+}
+
+impl<E: ScalarEngine> SchnorrParams<E> {
+    pub fn new(curve: EdwardsParams<E>) -> Self {
+        let generator = group_hash(&curve, b"Schnorr_", 0);
+        let num_bits = curve.order().into_repr().num_bits() as usize;
+        let table = fixed_base_table(&generator, (num_bits + 2) / 3, &curve);
+
+        SchnorrParams {
+            curve,
+            generator,
+            table,
+            hasher: PoseidonConstants::new(6),
+        }
+    }
+
+    pub fn curve(&self) -> &EdwardsParams<E> {
+        &self.curve
+    }
+
+    pub fn generator(&self) -> &EdwardsPoint<E> {
+        &self.generator
+    }
+
+    fn challenge(&self, r: &EdwardsPoint<E>, pk: &EdwardsPoint<E>, msg: &E::Fr) -> E::Fr {
+        poseidon_hash(
+            &self.hasher,
+            &[r.get_x(), r.get_y(), pk.get_x(), pk.get_y(), *msg],
+        )
+    }
+}
+
+pub struct Signature<E: ScalarEngine> {
+    pub r: EdwardsPoint<E>,
+    pub s: E::Fr,
+}
+
+impl<E: ScalarEngine> Clone for Signature<E> {
+    fn clone(&self) -> Self {
+        Signature {
+            r: self.r,
+            s: self.s,
+        }
+    }
+}
+
+fn bits_le<F: PrimeField>(value: &F) -> Vec<bool> {
+    let mut bits = BitIterator::new(value.into_repr()).collect::<Vec<_>>();
+    bits.reverse();
+
+    bits
+}
+
+fn reduce<R: PrimeFieldRepr>(value: &mut R, order: &R) {
+    if *value >= *order {
+        value.sub_noborrow(order);
+    }
+}
+
+/// Computes `a + b * c mod order` on the integer representations, where `a`
+/// and `c` are already reduced.
+fn mul_add_mod<F: PrimeField>(a: &F, b: &F, c: &F, order: &F) -> F {
+    let order = order.into_repr();
+    let c = c.into_repr();
+
+    let mut acc = F::Repr::default();
+    for bit in BitIterator::new(b.into_repr()) {
+        acc.mul2();
+        reduce(&mut acc, &order);
+        if bit {
+            acc.add_nocarry(&c);
+            reduce(&mut acc, &order);
+        }
+    }
+    acc.add_nocarry(&a.into_repr());
+    reduce(&mut acc, &order);
+
+    F::from_repr(acc).expect("the order is smaller than the modulus")
+}
+
+/// Returns the public key for the secret key `sk`, which must be smaller than
+/// the order of the subgroup.
+pub fn public_key<E: ScalarEngine>(params: &SchnorrParams<E>, sk: &E::Fr) -> EdwardsPoint<E> {
+    params.generator.mul(bits_le(sk), &params.curve)
+}
+
+/// Signs `msg` with a nonce that is derived deterministically from the secret
+/// key and the message.
+pub fn sign<E: ScalarEngine>(params: &SchnorrParams<E>, sk: &E::Fr, msg: &E::Fr) -> Signature<E> {
+    let order = params.curve.order();
+    assert!(sk.into_repr() < order.into_repr(), "invalid secret key");
+
+    let mut buf = vec![];
+    sk.into_repr().write_le(&mut buf).unwrap();
+    msg.into_repr().write_le(&mut buf).unwrap();
+    let hash = Blake2sParams::new()
+        .hash_length(32)
+        .personal(b"SchnorrN")
+        .hash(&buf);
+
+    // Drop the top bits, so that the nonce is smaller than the order.
+    let mut nonce = <E::Fr as PrimeField>::Repr::default();
+    nonce
+        .read_le(hash.as_bytes())
+        .expect("the hash has the size of the representation");
+    let keep = order.into_repr().num_bits() as usize - 1;
+    for (i, limb) in nonce.as_mut().iter_mut().enumerate() {
+        if (i + 1) * 64 > keep {
+            let bits = keep.saturating_sub(i * 64);
+            *limb = if bits == 0 {
+                0
+            } else {
+                *limb & (u64::max_value() >> (64 - bits))
+            };
+        }
+    }
+    let k = E::Fr::from_repr(nonce).expect("the nonce is smaller than the order");
+
+    let r = params.generator.mul(bits_le(&k), &params.curve);
+    let pk = public_key(params, sk);
+    let c = params.challenge(&r, &pk, msg);
+    let s = mul_add_mod(&k, &c, sk, order);
+
+    Signature { r, s }
+}
+
+/// Verifies the signature natively, matching [`verify_circuit`].
+pub fn verify<E: ScalarEngine>(
+    params: &SchnorrParams<E>,
+    pk: &EdwardsPoint<E>,
+    msg: &E::Fr,
+    sig: &Signature<E>,
+) -> bool {
+    let curve = &params.curve;
+    if sig.s.into_repr() >= curve.order().into_repr()
+        || !pk.is_on_curve(curve)
+        || !sig.r.is_on_curve(curve)
+    {
+        return false;
+    }
+
+    let c = params.challenge(&sig.r, pk, msg);
+    let lhs = params.generator.mul(bits_le(&sig.s), curve);
+    let rhs = sig.r.add(&pk.mul(bits_le(&c), curve), curve);
+
+    lhs.mul_by_cofactor(curve) == rhs.mul_by_cofactor(curve)
+}
+
+/// Enforces that `(r, s)` is a valid signature on `msg` under `pk`. The points
+/// are expected to be checked to be on the curve already, as done by
+/// [`AllocatedEdwardsPoint::alloc`].
+pub fn verify_circuit<E, CS>(
+    mut cs: CS,
+    params: &SchnorrParams<E>,
+    pk: &AllocatedEdwardsPoint<E>,
+    msg: &AllocatedNum<E>,
+    r: &AllocatedEdwardsPoint<E>,
+    s: &AllocatedNum<E>,
+) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let curve = &params.curve;
+
+    // Rejecting s >= order makes signatures non-malleable.
+    let s_bits = assert_less_than(cs.namespace(|| "s range"), s, curve.order())?;
+
+    let c = poseidon_hash_circuit(
+        cs.namespace(|| "challenge"),
+        &params.hasher,
+        &[
+            r.get_x().clone(),
+            r.get_y().clone(),
+            pk.get_x().clone(),
+            pk.get_y().clone(),
+            msg.clone(),
+        ],
+    )?;
+    let c_bits = c.to_bits_le_strict(cs.namespace(|| "challenge bits"))?;
+
+    let lhs = AllocatedEdwardsPoint::fixed_base_mul(
+        cs.namespace(|| "s * G"),
+        &params.table,
+        &s_bits,
+        curve,
+    )?;
+    let c_pk = pk.mul(cs.namespace(|| "c * A"), &c_bits, curve)?;
+    let rhs = r.add(cs.namespace(|| "R + c * A"), &c_pk, curve)?;
+
+    let lhs = lhs.mul_by_cofactor(cs.namespace(|| "lhs cofactor"), curve)?;
+    let rhs = rhs.mul_by_cofactor(cs.namespace(|| "rhs cofactor"), curve)?;
+
+    AllocatedEdwardsPoint::enforce_equal(cs.namespace(|| "verification equation"), &lhs, &rhs)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bls::{Bls12, Fr};
+    use ff::Field;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    use super::*;
+    use crate::gadgets::test::TestConstraintSystem;
+
+    fn random_secret_key(rng: &mut XorShiftRng, params: &SchnorrParams<Bls12>) -> Fr {
+        loop {
+            let sk = Fr::random(rng);
+            if sk.into_repr() < params.curve().order().into_repr() {
+                return sk;
+            }
+        }
+    }
+
+    #[test]
+    fn test_schnorr_native() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = SchnorrParams::<Bls12>::new(EdwardsParams::jubjub());
+
+        for _ in 0..5 {
+            let sk = random_secret_key(&mut rng, &params);
+            let pk = public_key(&params, &sk);
+            let msg = Fr::random(&mut rng);
+            let sig = sign(&params, &sk, &msg);
+
+            assert!(verify(&params, &pk, &msg, &sig));
+            assert!(!verify(&params, &pk, &Fr::random(&mut rng), &sig));
+
+            let other = public_key(&params, &random_secret_key(&mut rng, &params));
+            assert!(!verify(&params, &other, &msg, &sig));
+
+            // Adding the order to s still satisfies the equation, but must be
+            // rejected.
+            let mut malleated = sig.clone();
+            malleated.s.add_assign(params.curve().order());
+            assert!(!verify(&params, &pk, &msg, &malleated));
+        }
+    }
+
+    #[test]
+    fn test_schnorr_circuit() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = SchnorrParams::<Bls12>::new(EdwardsParams::jubjub());
+
+        let sk = random_secret_key(&mut rng, &params);
+        let pk = public_key(&params, &sk);
+        let msg = Fr::random(&mut rng);
+        let sig = sign(&params, &sk, &msg);
+
+        for &valid in &[true, false] {
+            let msg = if valid { msg } else { Fr::random(&mut rng) };
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let pk = AllocatedEdwardsPoint::alloc(cs.namespace(|| "pk"), Some(pk), params.curve())
+                .unwrap();
+            let r = AllocatedEdwardsPoint::alloc(cs.namespace(|| "r"), Some(sig.r), params.curve())
+                .unwrap();
+            let s = AllocatedNum::alloc(cs.namespace(|| "s"), || Ok(sig.s)).unwrap();
+            let msg = AllocatedNum::alloc(cs.namespace(|| "msg"), || Ok(msg)).unwrap();
+
+            verify_circuit(cs.namespace(|| "verify"), &params, &pk, &msg, &r, &s).unwrap();
+
+            assert_eq!(cs.is_satisfied(), valid);
+        }
+    }
+}
@@ -0,0 +1,295 @@
+//! A constraint system that only records the shape of a circuit.
+
+use std::marker::PhantomData;
+
+use blake2s_simd::{Params as Blake2sParams, State as Blake2sState};
+use byteorder::{BigEndian, ByteOrder};
+use ff::{Field, ScalarEngine};
+
+use super::test::hash_lc;
+use crate::multiexp::DensityTracker;
+use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// The size of a synthesized circuit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircuitShape {
+    /// Number of public inputs, including the implicit `ONE` input.
+    pub num_inputs: usize,
+    pub num_aux: usize,
+    pub num_constraints: usize,
+    /// Number of auxiliary variables that appear in `A` of any constraint.
+    pub a_aux_density: usize,
+    /// Number of inputs that appear in `B` of any constraint.
+    pub b_input_density: usize,
+    /// Number of auxiliary variables that appear in `B` of any constraint.
+    pub b_aux_density: usize,
+    /// Number of non-zero terms in all `A`, `B` and `C` linear combinations.
+    pub a_terms: usize,
+    pub b_terms: usize,
+    pub c_terms: usize,
+}
+
+/// Constraint system that synthesizes a circuit without a witness, none of
+/// the assignment closures are called. It's a cheap way of sizing parameters
+/// or estimating proving costs without running a `TestConstraintSystem`.
+pub struct ShapeCS<E: ScalarEngine> {
+    num_inputs: usize,
+    num_aux: usize,
+    num_constraints: usize,
+    a_aux_density: DensityTracker,
+    b_input_density: DensityTracker,
+    b_aux_density: DensityTracker,
+    a_terms: usize,
+    b_terms: usize,
+    c_terms: usize,
+    hasher: Blake2sState,
+    _engine: PhantomData<E>,
+}
+
+impl<E: ScalarEngine> ShapeCS<E> {
+    pub fn new() -> Self {
+        let mut b_input_density = DensityTracker::new();
+        // The `ONE` input.
+        b_input_density.add_element();
+
+        ShapeCS {
+            num_inputs: 1,
+            num_aux: 0,
+            num_constraints: 0,
+            a_aux_density: DensityTracker::new(),
+            b_input_density,
+            b_aux_density: DensityTracker::new(),
+            a_terms: 0,
+            b_terms: 0,
+            c_terms: 0,
+            hasher: Blake2sParams::new().hash_length(32).to_state(),
+            _engine: PhantomData,
+        }
+    }
+
+    /// Synthesizes the circuit and returns its shape.
+    pub fn synthesize<C: Circuit<E>>(circuit: C) -> Result<CircuitShape, SynthesisError> {
+        let mut cs = Self::new();
+        circuit.synthesize(&mut cs)?;
+
+        Ok(cs.shape())
+    }
+
+    pub fn shape(&self) -> CircuitShape {
+        CircuitShape {
+            num_inputs: self.num_inputs,
+            num_aux: self.num_aux,
+            num_constraints: self.num_constraints,
+            a_aux_density: self.a_aux_density.get_total_density(),
+            b_input_density: self.b_input_density.get_total_density(),
+            b_aux_density: self.b_aux_density.get_total_density(),
+            a_terms: self.a_terms,
+            b_terms: self.b_terms,
+            c_terms: self.c_terms,
+        }
+    }
+
+    /// A hash of the constraint matrices and the variable layout, as hex
+    /// string. It changes whenever the structure of the circuit changes, which
+    /// means that parameters generated for it are no longer valid, but doesn't
+    /// depend on the witness or on annotations.
+    pub fn fingerprint(&self) -> String {
+        let mut h = self.hasher.clone();
+
+        let mut buf = [0u8; 24];
+        BigEndian::write_u64(&mut buf[0..8], self.num_inputs as u64);
+        BigEndian::write_u64(&mut buf[8..16], self.num_aux as u64);
+        BigEndian::write_u64(&mut buf[16..24], self.num_constraints as u64);
+        h.update(&buf);
+
+        let mut s = String::new();
+        for b in h.finalize().as_ref() {
+            s += &format!("{:02x}", b);
+        }
+
+        s
+    }
+}
+
+impl<E: ScalarEngine> Default for ShapeCS<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn count_terms<E: ScalarEngine>(lc: &LinearCombination<E>) -> usize {
+    lc.as_ref()
+        .iter()
+        .filter(|(_, coeff)| !coeff.is_zero())
+        .count()
+}
+
+impl<E: ScalarEngine> ConstraintSystem<E> for ShapeCS<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let index = self.num_aux;
+        self.num_aux += 1;
+
+        self.a_aux_density.add_element();
+        self.b_aux_density.add_element();
+
+        Ok(Variable::new_unchecked(Index::Aux(index)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let index = self.num_inputs;
+        self.num_inputs += 1;
+
+        self.b_input_density.add_element();
+
+        Ok(Variable::new_unchecked(Index::Input(index)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+
+        for (var, coeff) in a.as_ref() {
+            if let Index::Aux(i) = var.get_unchecked() {
+                if !coeff.is_zero() {
+                    self.a_aux_density.inc(i);
+                }
+            }
+        }
+        for (var, coeff) in b.as_ref() {
+            if coeff.is_zero() {
+                continue;
+            }
+            match var.get_unchecked() {
+                Index::Input(i) => self.b_input_density.inc(i),
+                Index::Aux(i) => self.b_aux_density.inc(i),
+            }
+        }
+
+        hash_lc::<E>(a.as_ref(), &mut self.hasher);
+        hash_lc::<E>(b.as_ref(), &mut self.hasher);
+        hash_lc::<E>(c.as_ref(), &mut self.hasher);
+
+        self.a_terms += count_terms(&a);
+        self.b_terms += count_terms(&b);
+        self.c_terms += count_terms(&c);
+        self.num_constraints += 1;
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bls::{Bls12, Fr};
+
+    use super::*;
+    use crate::gadgets::num::AllocatedNum;
+    use crate::gadgets::test::TestConstraintSystem;
+
+    struct Squares {
+        value: Option<Fr>,
+        rounds: usize,
+    }
+
+    impl Circuit<Bls12> for Squares {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let value = self.value;
+            let mut num = AllocatedNum::alloc(cs.namespace(|| "num"), || {
+                value.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            for i in 0..self.rounds {
+                num = num.square(cs.namespace(|| format!("square {}", i)))?;
+            }
+            num.inputize(cs.namespace(|| "output"))
+        }
+    }
+
+    #[test]
+    fn test_shape_cs() {
+        // No witness is needed.
+        let shape = ShapeCS::<Bls12>::synthesize(Squares {
+            value: None,
+            rounds: 3,
+        })
+        .unwrap();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        Squares {
+            value: Some(Fr::one()),
+            rounds: 3,
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+
+        assert_eq!(shape.num_constraints, cs.num_constraints());
+        assert_eq!(shape.num_inputs, cs.num_inputs());
+        assert_eq!(
+            shape,
+            CircuitShape {
+                num_inputs: 2,
+                num_aux: 4,
+                num_constraints: 4,
+                a_aux_density: 3,
+                b_input_density: 1,
+                b_aux_density: 3,
+                a_terms: 4,
+                b_terms: 4,
+                c_terms: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let fingerprint = |value, rounds| {
+            let mut cs = ShapeCS::<Bls12>::new();
+            Squares { value, rounds }.synthesize(&mut cs).unwrap();
+            cs.fingerprint()
+        };
+
+        let three = fingerprint(None, 3);
+        assert_eq!(three.len(), 64);
+        // The witness doesn't matter.
+        assert_eq!(three, fingerprint(Some(Fr::one()), 3));
+        // The structure does.
+        assert!(three != fingerprint(None, 2));
+        assert!(three != fingerprint(None, 4));
+    }
+}
@@ -9,7 +9,7 @@ use std::fmt::Write;
 
 use byteorder::{BigEndian, ByteOrder};
 use std::cmp::Ordering;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use blake2s_simd::{Params as Blake2sParams, State as Blake2sState};
 
@@ -86,7 +86,7 @@ fn proc_lc<E: ScalarEngine>(terms: &[(Variable, E::Fr)]) -> BTreeMap<OrderedVari
     map
 }
 
-fn hash_lc<E: ScalarEngine>(terms: &[(Variable, E::Fr)], h: &mut Blake2sState) {
+pub(crate) fn hash_lc<E: ScalarEngine>(terms: &[(Variable, E::Fr)], h: &mut Blake2sState) {
     let map = proc_lc::<E>(terms);
 
     let mut buf = [0u8; 9 + 32];
@@ -240,20 +240,61 @@ impl<E: ScalarEngine> TestConstraintSystem<E> {
         s
     }
 
+    fn first_unsatisfied(&self) -> Option<usize> {
+        self.constraints
+            .iter()
+            .position(|&(ref a, ref b, ref c, _)| {
+                let mut a = eval_lc::<E>(a.as_ref(), &self.inputs, &self.aux);
+                let b = eval_lc::<E>(b.as_ref(), &self.inputs, &self.aux);
+                let c = eval_lc::<E>(c.as_ref(), &self.inputs, &self.aux);
+
+                a.mul_assign(&b);
+
+                a != c
+            })
+    }
+
     pub fn which_is_unsatisfied(&self) -> Option<&str> {
-        for &(ref a, ref b, ref c, ref path) in &self.constraints {
-            let mut a = eval_lc::<E>(a.as_ref(), &self.inputs, &self.aux);
-            let b = eval_lc::<E>(b.as_ref(), &self.inputs, &self.aux);
-            let c = eval_lc::<E>(c.as_ref(), &self.inputs, &self.aux);
+        self.first_unsatisfied()
+            .map(|index| &*self.constraints[index].3)
+    }
 
-            a.mul_assign(&b);
+    /// Describes the first unsatisfied constraint: its path, the evaluations
+    /// of its linear combinations and the values of all variables involved.
+    pub fn explain_unsatisfied(&self) -> Option<String> {
+        let index = self.first_unsatisfied()?;
+        let (ref a, ref b, ref c, ref path) = self.constraints[index];
 
-            if a != c {
-                return Some(&*path);
+        let a_value = eval_lc::<E>(a.as_ref(), &self.inputs, &self.aux);
+        let b_value = eval_lc::<E>(b.as_ref(), &self.inputs, &self.aux);
+        let c_value = eval_lc::<E>(c.as_ref(), &self.inputs, &self.aux);
+        let mut ab_value = a_value;
+        ab_value.mul_assign(&b_value);
+
+        let mut s = String::new();
+        writeln!(&mut s, "constraint `{}` is not satisfied:", path).unwrap();
+        writeln!(&mut s, "  A = {}", a_value).unwrap();
+        writeln!(&mut s, "  B = {}", b_value).unwrap();
+        writeln!(&mut s, "  C = {}", c_value).unwrap();
+        writeln!(&mut s, "  A * B = {}", ab_value).unwrap();
+        writeln!(&mut s, "involved variables:").unwrap();
+
+        let mut vars = BTreeSet::new();
+        for lc in &[a, b, c] {
+            let terms: &[(Variable, E::Fr)] = lc.as_ref();
+            for &(var, _) in terms {
+                vars.insert(OrderedVariable(var));
             }
         }
+        for var in &vars {
+            let (ref value, ref name) = match var.0.get_unchecked() {
+                Index::Input(i) => &self.inputs[i],
+                Index::Aux(i) => &self.aux[i],
+            };
+            writeln!(&mut s, "  `{}` = {}", name, value).unwrap();
+        }
 
-        None
+        Some(s)
     }
 
     pub fn is_satisfied(&self) -> bool {
@@ -356,7 +397,8 @@ impl<E: ScalarEngine> ConstraintSystem<E> for TestConstraintSystem<E> {
     {
         let index = self.aux.len();
         let path = compute_path(&self.current_namespace, annotation().into());
-        self.aux.push((f()?, path.clone()));
+        let value = f().map_err(|e| e.at_path(path.clone()))?;
+        self.aux.push((value, path.clone()));
         let var = Variable::new_unchecked(Index::Aux(index));
         self.set_named_obj(path, NamedObject::Var(var));
 
@@ -371,7 +413,8 @@ impl<E: ScalarEngine> ConstraintSystem<E> for TestConstraintSystem<E> {
     {
         let index = self.inputs.len();
         let path = compute_path(&self.current_namespace, annotation().into());
-        self.inputs.push((f()?, path.clone()));
+        let value = f().map_err(|e| e.at_path(path.clone()))?;
+        self.inputs.push((value, path.clone()));
         let var = Variable::new_unchecked(Index::Input(index));
         self.set_named_obj(path, NamedObject::Var(var));
 
@@ -419,8 +462,8 @@ impl<E: ScalarEngine> ConstraintSystem<E> for TestConstraintSystem<E> {
 
 #[test]
 fn test_cs() {
+    use crate::bls::{Bls12, Fr};
     use ff::PrimeField;
-    use paired::bls12_381::{Bls12, Fr};
 
     let mut cs = TestConstraintSystem::<Bls12>::new();
     assert!(cs.is_satisfied());
@@ -462,3 +505,85 @@ fn test_cs() {
 
     assert!(cs.get("test1/test2/hehe") == Fr::one());
 }
+
+#[test]
+fn test_explain_unsatisfied() {
+    use crate::bls::{Bls12, Fr};
+    use ff::PrimeField;
+
+    let mut cs = TestConstraintSystem::<Bls12>::new();
+    let a = cs
+        .namespace(|| "a")
+        .alloc(|| "var", || Ok(Fr::from_str("3").unwrap()))
+        .unwrap();
+    let b = cs.alloc(|| "b", || Ok(Fr::from_str("4").unwrap())).unwrap();
+    let c = cs
+        .alloc(|| "product", || Ok(Fr::from_str("12").unwrap()))
+        .unwrap();
+    cs.namespace(|| "checks")
+        .enforce(|| "mult", |lc| lc + a, |lc| lc + b, |lc| lc + c);
+    assert!(cs.explain_unsatisfied().is_none());
+
+    cs.set("product", Fr::from_str("13").unwrap());
+    let report = cs.explain_unsatisfied().unwrap();
+
+    assert!(report.starts_with("constraint `checks/mult` is not satisfied"));
+    assert!(report.contains(&format!("A * B = {}", Fr::from_str("12").unwrap())));
+    assert!(report.contains(&format!("C = {}", Fr::from_str("13").unwrap())));
+    for name in &["`a/var`", "`b`", "`product`"] {
+        assert!(report.contains(name), "{} is missing", name);
+    }
+    assert!(!report.contains("`ONE`"));
+}
+
+#[test]
+fn test_multiply() {
+    use crate::bls::{Bls12, Fr};
+    use ff::PrimeField;
+
+    let mut cs = TestConstraintSystem::<Bls12>::new();
+    let (a, _, c) = cs
+        .namespace(|| "gadget")
+        .multiply(
+            || "mult",
+            || Ok((Fr::from_str("3").unwrap(), Fr::from_str("5").unwrap())),
+        )
+        .unwrap();
+    assert!(cs.is_satisfied());
+    assert_eq!(cs.num_constraints(), 1);
+    assert!(cs.get("gadget/mult/c") == Fr::from_str("15").unwrap());
+
+    let fifteen = cs
+        .alloc(|| "fifteen", || Ok(Fr::from_str("15").unwrap()))
+        .unwrap();
+    cs.enforce_zero(|| "c = 15", |lc| lc + c - fifteen);
+    assert!(cs.is_satisfied());
+
+    cs.enforce_zero(|| "a = 15", |lc| lc + a - fifteen);
+    assert!(cs.which_is_unsatisfied() == Some("a = 15"));
+}
+
+#[test]
+fn test_assignment_error_path() {
+    use crate::bls::Bls12;
+
+    let mut cs = TestConstraintSystem::<Bls12>::new();
+    let err = cs
+        .namespace(|| "gadget")
+        .namespace(|| "inner")
+        .alloc(|| "var", || Err(SynthesisError::AssignmentMissing))
+        .unwrap_err();
+
+    match err {
+        SynthesisError::AtPath(ref path, _) => assert_eq!(path, "gadget/inner/var"),
+        _ => panic!("the path is missing"),
+    }
+    match err.root_cause() {
+        SynthesisError::AssignmentMissing => {}
+        _ => panic!("unexpected error"),
+    }
+    assert_eq!(
+        err.to_string(),
+        "an assignment for a variable could not be computed at `gadget/inner/var`"
+    );
+}
@@ -385,12 +385,12 @@ impl UInt32 {
 #[cfg(test)]
 mod test {
     use super::UInt32;
+    use crate::bls::Bls12;
     use crate::gadgets::boolean::Boolean;
     use crate::gadgets::multieq::MultiEq;
     use crate::gadgets::test::*;
     use crate::ConstraintSystem;
     use ff::Field;
-    use paired::bls12_381::Bls12;
     use rand_core::{RngCore, SeedableRng};
     use rand_xorshift::XorShiftRng;
 
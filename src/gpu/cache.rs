@@ -0,0 +1,147 @@
+//! The verdicts of the GPU self-tests, kept on disk so that only the first process on a machine
+//! pays for them. A verdict is keyed by the kernel, the devices with their driver versions and
+//! the version of the crate, so it's thrown away when any of them changes.
+//!
+//! The cache is at `BELLMAN_GPU_CACHE`, or in the temporary directory if it isn't set. Deleting it
+//! makes the next process run the self-tests again.
+
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use fs2::FileExt;
+use log::{info, warn};
+use ocl::enums::{DeviceInfo, DeviceInfoResult};
+
+use super::GPU_NVIDIA_DEVICES;
+
+const CACHE_NAME: &str = "bellman.gpu-cache";
+
+/// The cached verdict of the self-test of `kernel` on the devices of this machine, if there is
+/// one.
+pub fn cached_verdict(kernel: &str) -> Option<bool> {
+    let key = key(kernel)?;
+    match read(&path(), &key) {
+        Ok(Some(supported)) => {
+            info!("Using the cached GPU self-test verdict of {}", kernel);
+            Some(supported)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Cannot read the GPU self-test cache: {}", e);
+            None
+        }
+    }
+}
+
+/// Caches the verdict of the self-test of `kernel` on the devices of this machine.
+pub fn cache_verdict(kernel: &str, supported: bool) {
+    if let Some(key) = key(kernel) {
+        if let Err(e) = write(&path(), &key, supported) {
+            warn!("Cannot write the GPU self-test cache: {}", e);
+        }
+    }
+}
+
+fn path() -> PathBuf {
+    env::var_os("BELLMAN_GPU_CACHE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| env::temp_dir().join(CACHE_NAME))
+}
+
+/// The key of `kernel` on the devices of this machine, `None` if a device can't be identified.
+fn key(kernel: &str) -> Option<String> {
+    if GPU_NVIDIA_DEVICES.is_empty() {
+        return None;
+    }
+
+    let mut key = format!("{} {}", env!("CARGO_PKG_VERSION"), kernel);
+    for device in GPU_NVIDIA_DEVICES.iter() {
+        let driver = match device.info(DeviceInfo::DriverVersion).ok()? {
+            DeviceInfoResult::DriverVersion(driver) => driver,
+            _ => return None,
+        };
+        key.push_str(&format!(" | {} {}", device.name().ok()?, driver));
+    }
+    // Keys are lines of the cache.
+    Some(key.replace(|c| c == '\n' || c == '\t', " "))
+}
+
+/// Every line of the cache is a key and its verdict, separated by a tab.
+fn read(path: &Path, key: &str) -> io::Result<Option<bool>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    file.lock_shared()?;
+
+    for line in BufReader::new(&file).lines() {
+        let line = line?;
+        let mut fields = line.splitn(2, '\t');
+        if fields.next() == Some(key) {
+            return Ok(match fields.next() {
+                Some("supported") => Some(true),
+                Some("unsupported") => Some(false),
+                _ => None,
+            });
+        }
+    }
+    Ok(None)
+}
+
+fn write(path: &Path, key: &str, supported: bool) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open(path)?;
+    file.lock_exclusive()?;
+
+    // Replaces an older verdict of the same key.
+    let mut lines = vec![];
+    for line in BufReader::new(&file).lines() {
+        let line = line?;
+        if line.splitn(2, '\t').next() != Some(key) {
+            lines.push(line);
+        }
+    }
+    let verdict = if supported {
+        "supported"
+    } else {
+        "unsupported"
+    };
+    lines.push(format!("{}\t{}", key, verdict));
+
+    file.seek(SeekFrom::Start(0))?;
+    file.set_len(0)?;
+    for line in lines {
+        writeln!(file, "{}", line)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_verdict_cache() {
+        let path = env::temp_dir().join(format!("bellman-gpu-cache-{}", std::process::id()));
+        assert_eq!(read(&path, "fft").unwrap(), None);
+
+        write(&path, "fft", true).unwrap();
+        write(&path, "multiexp", false).unwrap();
+        assert_eq!(read(&path, "fft").unwrap(), Some(true));
+        assert_eq!(read(&path, "multiexp").unwrap(), Some(false));
+
+        // A new verdict replaces the old one.
+        write(&path, "fft", false).unwrap();
+        assert_eq!(read(&path, "fft").unwrap(), Some(false));
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -72,6 +72,28 @@ FIELD FIELD_reduce(limb *limbs) {
   return result;
 }
 
+// Long multiplication without the Montgomery reduction, for lazy reductions.
+void FIELD_mul_wide(FIELD a, FIELD b, limb *res) {
+  for(uchar i = 0; i < FIELD_LIMBS * 2; i++) res[i] = 0;
+  for(uchar i = 0; i < FIELD_LIMBS; i++) {
+    limb carry = 0;
+    for(uchar j = 0; j < FIELD_LIMBS; j++)
+      res[i + j] = mac_with_carry(a.val[i], b.val[j], res[i + j], &carry);
+    res[i + FIELD_LIMBS] = carry;
+  }
+}
+
+// Subtraction of double-width numbers in place, returns the borrow
+bool FIELD_sub_wide(limb *a, limb *b) {
+  bool borrow = 0;
+  for(uchar i = 0; i < FIELD_LIMBS * 2; i++) {
+    limb old = a[i];
+    a[i] -= b[i] + borrow;
+    borrow = borrow ? old <= a[i] : old < a[i];
+  }
+  return borrow;
+}
+
 // Modular multiplication
 FIELD FIELD_mul(FIELD a, FIELD b) {
 
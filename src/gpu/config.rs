@@ -0,0 +1,176 @@
+//! The GPUs the kernels know how to size their launches for. The built-in table is extended by
+//! `BELLMAN_CUSTOM_GPU`, a comma separated list of `name:cores` pairs, and by the JSON file at
+//! `BELLMAN_GPU_CONFIG`, or at runtime with [`load_config`]. A config file maps device names to
+//! their settings:
+//!
+//! ```json
+//! {
+//!     "GeForce RTX 3090": { "cores": 10496, "chunk_size": 33554432 }
+//! }
+//! ```
+//!
+//! Later definitions override earlier ones. Malformed definitions from the environment are
+//! logged and ignored.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+
+use log::{info, warn};
+use serde_json::Value;
+
+use super::error::{GPUError, GPUResult};
+
+/// The settings of a GPU model.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GpuConfig {
+    /// The number of CUDA cores.
+    pub cores: usize,
+    /// The number of bases a multiexp kernel processes at once, derived from the memory of the
+    /// device if `None`.
+    pub chunk_size: Option<usize>,
+}
+
+impl GpuConfig {
+    fn cores(cores: usize) -> Self {
+        GpuConfig {
+            cores,
+            chunk_size: None,
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref CONFIGS: RwLock<HashMap<String, GpuConfig>> = {
+        let mut configs: HashMap<String, GpuConfig> = vec![
+            ("GeForce RTX 2080 Ti".to_string(), 4352),
+            ("GeForce RTX 2080 SUPER".to_string(), 3072),
+            ("GeForce RTX 2080".to_string(), 2944),
+            ("GeForce GTX 1080 Ti".to_string(), 3584),
+            ("GeForce GTX 1080".to_string(), 2560),
+            ("GeForce GTX 1060".to_string(), 1280),
+        ]
+        .into_iter()
+        .map(|(name, cores)| (name, GpuConfig::cores(cores)))
+        .collect();
+
+        if let Ok(var) = env::var("BELLMAN_CUSTOM_GPU") {
+            match parse_custom_gpus(&var) {
+                Ok(custom) => configs.extend(custom),
+                Err(e) => warn!("Ignoring BELLMAN_CUSTOM_GPU: {}", e),
+            }
+        }
+        if let Some(path) = env::var_os("BELLMAN_GPU_CONFIG") {
+            match read_config(Path::new(&path)) {
+                Ok(custom) => configs.extend(custom),
+                Err(e) => warn!("Ignoring BELLMAN_GPU_CONFIG: {}", e),
+            }
+        }
+
+        RwLock::new(configs)
+    };
+}
+
+/// The settings of the GPU model called `name`, if it's known.
+pub fn gpu_config(name: &str) -> Option<GpuConfig> {
+    CONFIGS.read().unwrap().get(name).cloned()
+}
+
+/// Adds the GPUs of the config file at `path`, replacing the settings of GPUs that are known
+/// already. Kernels that exist already keep their settings.
+pub fn load_config<P: AsRef<Path>>(path: P) -> GPUResult<()> {
+    let custom = read_config(path.as_ref())?;
+    CONFIGS.write().unwrap().extend(custom);
+    Ok(())
+}
+
+fn read_config(path: &Path) -> GPUResult<Vec<(String, GpuConfig)>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| GPUError::Config(format!("cannot read {}: {}", path.display(), e)))?;
+    parse_config(&contents).map_err(|e| GPUError::Config(format!("{}: {}", path.display(), e)))
+}
+
+fn parse_config(contents: &str) -> GPUResult<Vec<(String, GpuConfig)>> {
+    let value: Value = serde_json::from_str(contents)
+        .map_err(|e| GPUError::Config(format!("invalid JSON: {}", e)))?;
+    let gpus = value
+        .as_object()
+        .ok_or_else(|| GPUError::Config("expected an object of GPU names".to_string()))?;
+
+    let mut configs = vec![];
+    for (name, settings) in gpus {
+        let field = |key: &str| -> GPUResult<Option<usize>> {
+            match settings.get(key) {
+                None => Ok(None),
+                Some(value) => value.as_u64().map(|v| Some(v as usize)).ok_or_else(|| {
+                    GPUError::Config(format!("{} of \"{}\" isn't a positive integer", key, name))
+                }),
+            }
+        };
+        let cores = field("cores")?
+            .ok_or_else(|| GPUError::Config(format!("\"{}\" has no cores", name)))?;
+        let chunk_size = field("chunk_size")?;
+
+        info!("Adding \"{}\" to GPU list with {} CUDA cores.", name, cores);
+        configs.push((name.clone(), GpuConfig { cores, chunk_size }));
+    }
+    Ok(configs)
+}
+
+fn parse_custom_gpus(var: &str) -> GPUResult<Vec<(String, GpuConfig)>> {
+    var.split(',')
+        .map(|card| {
+            let invalid = || GPUError::Config(format!("expected name:cores, got \"{}\"", card));
+            let mut splitted = card.split(':');
+            let (name, cores) = match (splitted.next(), splitted.next(), splitted.next()) {
+                (Some(name), Some(cores), None) => (name.trim(), cores.trim()),
+                _ => return Err(invalid()),
+            };
+            let cores = cores.parse().map_err(|_| invalid())?;
+
+            info!("Adding \"{}\" to GPU list with {} CUDA cores.", name, cores);
+            Ok((name.to_string(), GpuConfig::cores(cores)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_gpus() {
+        assert_eq!(
+            parse_custom_gpus("GeForce RTX 3090: 10496, Tesla V100:5120").unwrap(),
+            vec![
+                ("GeForce RTX 3090".to_string(), GpuConfig::cores(10496)),
+                ("Tesla V100".to_string(), GpuConfig::cores(5120)),
+            ]
+        );
+        assert!(parse_custom_gpus("GeForce RTX 3090").is_err());
+        assert!(parse_custom_gpus("GeForce RTX 3090:many").is_err());
+    }
+
+    #[test]
+    fn test_config_file() {
+        let configs =
+            parse_config(r#"{ "GeForce RTX 3090": { "cores": 10496, "chunk_size": 1024 } }"#)
+                .unwrap();
+        assert_eq!(
+            configs,
+            vec![(
+                "GeForce RTX 3090".to_string(),
+                GpuConfig {
+                    cores: 10496,
+                    chunk_size: Some(1024),
+                }
+            )]
+        );
+
+        assert!(parse_config("[]").is_err());
+        assert!(parse_config(r#"{ "GeForce RTX 3090": {} }"#).is_err());
+        assert!(parse_config(r#"{ "GeForce RTX 3090": { "cores": -1 } }"#).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use std::fmt;
+use std::time::Duration;
+
+/// The API a GPU is driven through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    OpenCl,
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Backend::OpenCl => write!(f, "OpenCL"),
+        }
+    }
+}
+
+/// A GPU of the machine, see [`devices`](super::devices).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub vendor: String,
+    /// The global memory in bytes.
+    pub memory: u64,
+    pub compute_units: u32,
+    /// The PCI bus of the device, if the driver tells it.
+    pub bus_id: Option<u32>,
+    pub backend: Backend,
+}
+
+/// The outcome of one check of [`diagnostics`](super::diagnostics).
+#[derive(Clone, Debug)]
+pub struct Check {
+    /// Why the check failed, `None` if it passed.
+    pub error: Option<String>,
+    pub duration: Duration,
+}
+
+impl Check {
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// The checks of a GPU the provers use, see [`diagnostics`](super::diagnostics).
+#[derive(Clone, Debug)]
+pub struct DeviceDiagnostics {
+    /// The index of the device among the ones the provers use.
+    pub index: usize,
+    /// The description of the device, `None` if it can't be queried.
+    pub info: Option<DeviceInfo>,
+    /// Building the kernels from their sources.
+    pub build: Check,
+    /// Comparing an FFT against the CPU.
+    pub fft: Check,
+    /// Comparing multiexps in G1 and G2 against the CPU.
+    pub multiexp: Check,
+}
+
+impl DeviceDiagnostics {
+    pub fn passed(&self) -> bool {
+        self.build.passed() && self.fft.passed() && self.multiexp.passed()
+    }
+}
@@ -1,26 +1,113 @@
 use std::error;
 use std::fmt;
+use std::io;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
-pub struct GPUError {
-    pub msg: String,
+pub enum GPUError {
+    /// The GPU accelerator is disabled, by `BELLMAN_NO_GPU` or by building without the `gpu`
+    /// feature.
+    Disabled,
+    /// There is no GPU the kernels work on, or not the one asked for.
+    NoDevice(String),
+    /// An OpenCL call failed.
+    Driver(String),
+    /// An OpenCL call ran out of memory on the device or the host, e.g. for the buffers of a
+    /// kernel. Smaller ones may fit.
+    OutOfMemory(String),
+    /// The GPU lock couldn't be taken.
+    Lock(Arc<io::Error>),
+    /// The kernel gave the GPU up for a proof of higher priority, see
+    /// `MultiexpKernel::set_preemption`.
+    Preempted,
+    /// The results of the GPU differ from the ones of the CPU.
+    WrongResults(String),
+    /// A GPU definition of `BELLMAN_CUSTOM_GPU` or of a config file is malformed.
+    Config(String),
+    /// The kernel can't compute the input, e.g. a multiexp in a group other than G1 and G2.
+    InvalidInput(String),
+    /// Any other error, e.g. of a kernel that panicked.
+    Other(String),
 }
 
 pub type GPUResult<T> = std::result::Result<T, GPUError>;
 
+// The statuses of OpenCL calls that ran out of memory on the device or the host.
+#[cfg(feature = "gpu")]
+const OUT_OF_MEMORY: [&str; 3] = [
+    "CL_MEM_OBJECT_ALLOCATION_FAILURE",
+    "CL_OUT_OF_RESOURCES",
+    "CL_OUT_OF_HOST_MEMORY",
+];
+
+impl GPUError {
+    // The error of an OpenCL call that failed with `msg`, which tells the status of the call.
+    #[cfg(feature = "gpu")]
+    pub(crate) fn driver(msg: String) -> Self {
+        if OUT_OF_MEMORY.iter().any(|status| msg.contains(status)) {
+            GPUError::OutOfMemory(msg)
+        } else {
+            GPUError::Driver(msg)
+        }
+    }
+
+    /// There is no GPU to run on, the CPU has to do the work.
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            GPUError::Disabled | GPUError::NoDevice(_) => true,
+            _ => false,
+        }
+    }
+
+    /// The GPU is taken by another prover: its lock couldn't be taken or a proof of higher
+    /// priority preempted it. It may be free again later.
+    pub fn is_busy(&self) -> bool {
+        match self {
+            GPUError::Lock(_) | GPUError::Preempted => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_preempted(&self) -> bool {
+        match self {
+            GPUError::Preempted => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_out_of_memory(&self) -> bool {
+        match self {
+            GPUError::OutOfMemory(_) => true,
+            _ => false,
+        }
+    }
+}
+
 impl fmt::Display for GPUError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.msg)
+        match self {
+            GPUError::Disabled => write!(f, "GPU accelerator is disabled!"),
+            GPUError::Lock(e) => write!(f, "Cannot take the GPU lock: {}", e),
+            GPUError::Preempted => {
+                write!(f, "The GPU was preempted by a proof of higher priority!")
+            }
+            GPUError::NoDevice(msg)
+            | GPUError::Driver(msg)
+            | GPUError::OutOfMemory(msg)
+            | GPUError::WrongResults(msg)
+            | GPUError::Config(msg)
+            | GPUError::InvalidInput(msg)
+            | GPUError::Other(msg) => write!(f, "{}", msg),
+        }
     }
 }
 
 impl error::Error for GPUError {
-    fn description(&self) -> &str {
-        self.msg.as_str()
-    }
-
-    fn cause(&self) -> Option<&dyn error::Error> {
-        None
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            GPUError::Lock(e) => Some(&**e),
+            _ => None,
+        }
     }
 }
 
@@ -30,9 +117,7 @@ use ocl;
 #[cfg(feature = "gpu")]
 impl From<ocl::Error> for GPUError {
     fn from(error: ocl::Error) -> Self {
-        GPUError {
-            msg: error.to_string(),
-        }
+        GPUError::driver(error.to_string())
     }
 }
 
@@ -41,9 +126,7 @@ impl From<std::boxed::Box<dyn std::any::Any + std::marker::Send>> for GPUError {
     fn from(e: std::boxed::Box<dyn std::any::Any + std::marker::Send>) -> Self {
         match &e.downcast_ref::<Self>() {
             &Some(err) => err.clone(),
-            &None => GPUError {
-                msg: "An unknown GPU error happened!".to_string(),
-            },
+            &None => GPUError::Other("An unknown GPU error happened!".to_string()),
         }
     }
 }
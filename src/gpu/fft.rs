@@ -1,11 +1,11 @@
+use crate::bls::Engine;
 use crate::gpu::{
     error::{GPUError, GPUResult},
-    sources, structs, GPU_NVIDIA_DEVICES,
+    sources, structs, utils, GPU_NVIDIA_DEVICES,
 };
 use ff::Field;
 use log::info;
-use ocl::{Buffer, MemFlags, ProQue};
-use paired::Engine;
+use ocl::{Buffer, Device, MemFlags, ProQue};
 use std::cmp;
 
 // NOTE: Please read `structs.rs` for an explanation for unsafe transmutes of this code!
@@ -13,6 +13,20 @@ use std::cmp;
 const LOG2_MAX_ELEMENTS: usize = 32; // At most 2^32 elements is supported.
 const MAX_RADIX_DEGREE: u32 = 8; // Radix256
 const MAX_LOCAL_WORK_SIZE_DEGREE: u32 = 7; // 128
+const MAX_CACHED_TWIDDLES: usize = 2; // For an FFT and its inverse
+
+// The precalculated values of FFTs of a size with an `omega`, which stay on the GPU for the next
+// FFT with the same parameters. The prover runs its FFTs back-to-back with two omegas.
+struct Twiddles<E>
+where
+    E: Engine,
+{
+    omega: E::Fr,
+    n: usize,
+    max_deg: u32,
+    pq_buffer: Buffer<structs::PrimeFieldStruct<E::Fr>>,
+    omg_buffer: Buffer<structs::PrimeFieldStruct<E::Fr>>,
+}
 
 pub struct FFTKernel<E>
 where
@@ -21,8 +35,13 @@ where
     proque: ProQue,
     fft_src_buffer: Buffer<structs::PrimeFieldStruct<E::Fr>>,
     fft_dst_buffer: Buffer<structs::PrimeFieldStruct<E::Fr>>,
-    fft_pq_buffer: Buffer<structs::PrimeFieldStruct<E::Fr>>,
-    fft_omg_buffer: Buffer<structs::PrimeFieldStruct<E::Fr>>,
+    // The least recently computed first.
+    twiddles: Vec<Twiddles<E>>,
+    // The largest radix whose elements fit into the local memory of a work group.
+    max_radix_degree: u32,
+    max_local_work_size_degree: u32,
+    // The global memory of the device, which bounds the size of batches.
+    memory: u64,
 }
 
 impl<E> FFTKernel<E>
@@ -30,16 +49,32 @@ where
     E: Engine,
 {
     pub fn create(n: u32) -> GPUResult<FFTKernel<E>> {
-        let src = sources::kernel::<E>();
         let devices = &GPU_NVIDIA_DEVICES;
         if devices.is_empty() {
-            return Err(GPUError {
-                msg: "No working GPUs found!".to_string(),
-            });
+            return Err(GPUError::NoDevice("No working GPUs found!".to_string()));
         }
-        let device = devices[0]; // Select the first device for FFT
+        FFTKernel::create_on(devices[0], n) // Select the first device for FFT
+    }
+
+    // Creates a kernel for FFTs of up to `n` elements on the given device.
+    pub fn create_on(device: Device, n: u32) -> GPUResult<FFTKernel<E>> {
+        let src = sources::kernel::<E>();
         let pq = ProQue::builder().device(device).src(src).dims(n).build()?;
 
+        let elem_size = std::mem::size_of::<E::Fr>() as u64;
+        let local_elems = utils::get_local_memory(device)? / elem_size;
+        let max_radix_degree = cmp::min(MAX_RADIX_DEGREE, log2_floor(local_elems));
+        let max_local_work_size_degree = cmp::min(
+            MAX_LOCAL_WORK_SIZE_DEGREE,
+            log2_floor(utils::get_max_work_group_size(device)? as u64),
+        );
+        let memory = utils::get_memory(device)?;
+        if max_radix_degree == 0 {
+            return Err(GPUError::NoDevice(
+                "GPU local memory is too small for FFT!".to_string(),
+            ));
+        }
+
         let srcbuff = Buffer::builder()
             .queue(pq.queue().clone())
             .flags(MemFlags::new().read_write())
@@ -50,16 +85,6 @@ where
             .flags(MemFlags::new().read_write())
             .len(n)
             .build()?;
-        let pqbuff = Buffer::builder()
-            .queue(pq.queue().clone())
-            .flags(MemFlags::new().read_write())
-            .len(1 << MAX_RADIX_DEGREE >> 1)
-            .build()?;
-        let omgbuff = Buffer::builder()
-            .queue(pq.queue().clone())
-            .flags(MemFlags::new().read_write())
-            .len(LOG2_MAX_ELEMENTS)
-            .build()?;
 
         info!("FFT: 1 working device(s) selected.");
         info!("FFT: Device 0: {}", pq.device().name()?);
@@ -68,31 +93,71 @@ where
             proque: pq,
             fft_src_buffer: srcbuff,
             fft_dst_buffer: dstbuff,
-            fft_pq_buffer: pqbuff,
-            fft_omg_buffer: omgbuff,
+            twiddles: Vec::with_capacity(MAX_CACHED_TWIDDLES),
+            max_radix_degree,
+            max_local_work_size_degree,
+            memory,
         })
     }
 
+    // Replaces the buffers by ones of `len` elements if they are smaller. The old ones are dropped
+    // first, so that they don't have to fit into the memory of the GPU next to the new ones.
+    fn reserve(&mut self, len: usize) -> ocl::Result<()> {
+        if self.fft_src_buffer.len() >= len {
+            return Ok(());
+        }
+
+        let queue = self.proque.queue().clone();
+        let buffer = |len| {
+            Buffer::builder()
+                .queue(queue.clone())
+                .flags(MemFlags::new().read_write())
+                .len(len)
+                .build()
+        };
+        self.fft_src_buffer = buffer(1)?;
+        self.fft_dst_buffer = buffer(1)?;
+        self.fft_src_buffer = buffer(len)?;
+        self.fft_dst_buffer = buffer(len)?;
+        Ok(())
+    }
+
+    // The number of polynomials of `n` elements whose FFTs run at once. Their source and
+    // destination buffers take at most half of the memory of the GPU.
+    fn max_batch(&self, n: usize) -> usize {
+        let elem_size = std::mem::size_of::<E::Fr>() as u64;
+        let by_memory = self.memory / 2 / (2 * n as u64 * elem_size);
+        // The kernel indexes the elements of all polynomials with 32 bits.
+        let by_index = (1u64 << LOG2_MAX_ELEMENTS) / n as u64;
+        cmp::max(1, cmp::min(by_memory, by_index)) as usize
+    }
+
     /// Peforms a FFT round
     /// * `lgn` - Specifies log2 of number of elements
     /// * `lgp` - Specifies log2 of `p`, (http://www.bealto.com/gpu-fft_group-1.html)
     /// * `deg` - 1=>radix2, 2=>radix4, 3=>radix8, ...
     /// * `max_deg` - The precalculated values pq` and `omegas` are valid for radix degrees up to `max_deg`
+    /// * `twiddles` - The index of the precalculated values
+    /// * `polys` - The number of polynomials of the batch
+    #[allow(clippy::too_many_arguments)]
     fn radix_fft_round(
         &mut self,
+        polys: u32,
         lgn: u32,
         lgp: u32,
         deg: u32,
         max_deg: u32,
         in_src: bool,
+        twiddles: usize,
     ) -> ocl::Result<()> {
         let n = 1u32 << lgn;
-        let lwsd = cmp::min(deg - 1, MAX_LOCAL_WORK_SIZE_DEGREE);
+        let twiddles = &self.twiddles[twiddles];
+        let lwsd = cmp::min(deg - 1, self.max_local_work_size_degree);
         let kernel = self
             .proque
             .kernel_builder("radix_fft")
-            .global_work_size([n >> deg << lwsd])
-            .local_work_size(1 << lwsd)
+            .global_work_size([n >> deg << lwsd, polys])
+            .local_work_size([1 << lwsd, 1])
             .arg(if in_src {
                 &self.fft_src_buffer
             } else {
@@ -103,8 +168,8 @@ where
             } else {
                 &self.fft_src_buffer
             })
-            .arg(&self.fft_pq_buffer)
-            .arg(&self.fft_omg_buffer)
+            .arg(&twiddles.pq_buffer)
+            .arg(&twiddles.omg_buffer)
             .arg_local::<structs::PrimeFieldStruct<E::Fr>>(1 << deg)
             .arg(n)
             .arg(lgp)
@@ -117,8 +182,35 @@ where
         Ok(())
     }
 
-    /// Share some precalculated values between threads to boost the performance
-    fn setup_pq(&mut self, omega: &E::Fr, n: usize, max_deg: u32) -> ocl::Result<()> {
+    /// Share some precalculated values between threads to boost the performance. They are only
+    /// calculated and uploaded if they aren't on the GPU already, their index is returned.
+    fn setup_pq(&mut self, omega: &E::Fr, n: usize, max_deg: u32) -> ocl::Result<usize> {
+        let cached = self
+            .twiddles
+            .iter()
+            .position(|t| t.omega == *omega && t.n == n && t.max_deg == max_deg);
+        if let Some(index) = cached {
+            return Ok(index);
+        }
+
+        // The buffers of the least recently computed values are reused.
+        let (pq_buffer, omg_buffer) = if self.twiddles.len() < MAX_CACHED_TWIDDLES {
+            let pq_buffer = Buffer::builder()
+                .queue(self.proque.queue().clone())
+                .flags(MemFlags::new().read_write())
+                .len(1 << MAX_RADIX_DEGREE >> 1)
+                .build()?;
+            let omg_buffer = Buffer::builder()
+                .queue(self.proque.queue().clone())
+                .flags(MemFlags::new().read_write())
+                .len(LOG2_MAX_ELEMENTS)
+                .build()?;
+            (pq_buffer, omg_buffer)
+        } else {
+            let oldest = self.twiddles.remove(0);
+            (oldest.pq_buffer, oldest.omg_buffer)
+        };
+
         // Precalculate:
         // [omega^(0/(2^(deg-1))), omega^(1/(2^(deg-1))), ..., omega^((2^(deg-1)-1)/(2^(deg-1)))]
         let mut tpq = vec![structs::PrimeFieldStruct::<E::Fr>::default(); 1 << max_deg >> 1];
@@ -134,7 +226,7 @@ where
                 pq[i].mul_assign(&tw);
             }
         }
-        self.fft_pq_buffer.write(&tpq).enq()?;
+        pq_buffer.write(&tpq).enq()?;
 
         // Precalculate [omega, omega^2, omega^4, omega^8, ..., omega^(2^31)]
         let mut tom = vec![structs::PrimeFieldStruct::<E::Fr>::default(); 32];
@@ -145,39 +237,77 @@ where
         for i in 1..LOG2_MAX_ELEMENTS {
             om[i] = om[i - 1].pow([2u64]);
         }
-        self.fft_omg_buffer.write(&tom).enq()?;
+        omg_buffer.write(&tom).enq()?;
 
-        Ok(())
+        self.twiddles.push(Twiddles {
+            omega: *omega,
+            n,
+            max_deg,
+            pq_buffer,
+            omg_buffer,
+        });
+        Ok(self.twiddles.len() - 1)
     }
 
     /// Performs FFT on `a`
     /// * `omega` - Special value `omega` is used for FFT over finite-fields
     /// * `lgn` - Specifies log2 of number of elements
     pub fn radix_fft(&mut self, a: &mut [E::Fr], omega: &E::Fr, lgn: u32) -> GPUResult<()> {
+        self.radix_fft_many(&mut [a], omega, lgn)
+    }
+
+    /// Performs FFTs on all of `polys`, which have the same size, with the same `omega`. The
+    /// polynomials stay on the GPU together, as many as fit into its memory, so that every round
+    /// is a single launch for all of them.
+    /// * `omega` - Special value `omega` is used for FFT over finite-fields
+    /// * `lgn` - Specifies log2 of number of elements of each polynomial
+    pub fn radix_fft_many(
+        &mut self,
+        polys: &mut [&mut [E::Fr]],
+        omega: &E::Fr,
+        lgn: u32,
+    ) -> GPUResult<()> {
         let n = 1 << lgn;
+        assert!(
+            polys.iter().all(|a| a.len() == n),
+            "the polynomials must have 2^lgn elements"
+        );
 
-        let ta = unsafe {
-            std::mem::transmute::<&mut [E::Fr], &mut [structs::PrimeFieldStruct<E::Fr>]>(a)
-        };
+        let max_deg = cmp::min(self.max_radix_degree, lgn);
+        let twiddles = self.setup_pq(omega, n, max_deg)?;
 
-        let max_deg = cmp::min(MAX_RADIX_DEGREE, lgn);
-        self.setup_pq(omega, n, max_deg)?;
+        let batch = self.max_batch(n);
+        for batch in polys.chunks_mut(batch) {
+            self.reserve(batch.len() * n)?;
+            for (i, a) in batch.iter_mut().enumerate() {
+                let ta = unsafe {
+                    std::mem::transmute::<&mut [E::Fr], &mut [structs::PrimeFieldStruct<E::Fr>]>(a)
+                };
+                self.fft_src_buffer.write(&*ta).offset(i * n).enq()?;
+            }
 
-        self.fft_src_buffer.write(&*ta).enq()?;
-        let mut in_src = true;
-        let mut lgp = 0u32;
-        while lgp < lgn {
-            let deg = cmp::min(max_deg, lgn - lgp);
-            self.radix_fft_round(lgn, lgp, deg, max_deg, in_src)?;
-            lgp += deg;
-            in_src = !in_src; // Destination of this FFT round is source of the next round.
-        }
-        if in_src {
-            self.fft_src_buffer.read(ta).enq()?;
-        } else {
-            self.fft_dst_buffer.read(ta).enq()?;
+            let mut in_src = true;
+            let mut lgp = 0u32;
+            while lgp < lgn {
+                let deg = cmp::min(max_deg, lgn - lgp);
+                self.radix_fft_round(batch.len() as u32, lgn, lgp, deg, max_deg, in_src, twiddles)?;
+                lgp += deg;
+                in_src = !in_src; // Destination of this FFT round is source of the next round.
+            }
+
+            let result = if in_src {
+                &self.fft_src_buffer
+            } else {
+                &self.fft_dst_buffer
+            };
+            for (i, a) in batch.iter_mut().enumerate() {
+                let ta = unsafe {
+                    std::mem::transmute::<&mut [E::Fr], &mut [structs::PrimeFieldStruct<E::Fr>]>(a)
+                };
+                result.read(ta).offset(i * n).enq()?;
+            }
+            self.proque.finish()?; // Wait for all commands in the queue (Including read commands)
         }
-        self.proque.finish()?; // Wait for all commands in the queue (Including read command)
 
         Ok(())
     }
@@ -206,4 +336,75 @@ where
         self.proque.finish()?;
         Ok(())
     }
+
+    /// Multiplies the elements of `a` by the ones of `b`
+    pub fn mul_assign(&mut self, a: &mut [E::Fr], b: &[E::Fr]) -> GPUResult<()> {
+        self.elementwise("mul_assign", a, b)
+    }
+
+    /// Subtracts the elements of `b` from the ones of `a`
+    pub fn sub_assign(&mut self, a: &mut [E::Fr], b: &[E::Fr]) -> GPUResult<()> {
+        self.elementwise("sub_assign", a, b)
+    }
+
+    /// Runs a kernel combining the elements of `a` and `b` pairwise, the result is
+    /// stored in `a`
+    fn elementwise(&mut self, name: &str, a: &mut [E::Fr], b: &[E::Fr]) -> GPUResult<()> {
+        assert_eq!(a.len(), b.len());
+
+        let n = a.len() as u32;
+        let ta = unsafe {
+            std::mem::transmute::<&mut [E::Fr], &mut [structs::PrimeFieldStruct<E::Fr>]>(a)
+        };
+        let tb = unsafe { std::mem::transmute::<&[E::Fr], &[structs::PrimeFieldStruct<E::Fr>]>(b) };
+        self.fft_src_buffer.write(&*ta).enq()?;
+        self.fft_dst_buffer.write(tb).enq()?;
+        let kernel = self
+            .proque
+            .kernel_builder(name)
+            .global_work_size([n])
+            .arg(&self.fft_src_buffer)
+            .arg(&self.fft_dst_buffer)
+            .arg(n)
+            .build()?;
+        unsafe {
+            kernel.enq()?;
+        }
+        self.fft_src_buffer.read(ta).enq()?;
+        self.proque.finish()?;
+        Ok(())
+    }
+
+    /// Multiplies the i-th element of `a` by `g^i`
+    pub fn distribute_powers(&mut self, a: &mut [E::Fr], g: &E::Fr) -> GPUResult<()> {
+        let n = a.len() as u32;
+        let ta = unsafe {
+            std::mem::transmute::<&mut [E::Fr], &mut [structs::PrimeFieldStruct<E::Fr>]>(a)
+        };
+        let g = structs::PrimeFieldStruct::<E::Fr>(*g);
+        self.fft_src_buffer.write(&*ta).enq()?;
+        let kernel = self
+            .proque
+            .kernel_builder("distribute_powers")
+            .global_work_size([n])
+            .arg(&self.fft_src_buffer)
+            .arg(n)
+            .arg(g)
+            .build()?;
+        unsafe {
+            kernel.enq()?;
+        }
+        self.fft_src_buffer.read(ta).enq()?;
+        self.proque.finish()?;
+        Ok(())
+    }
+}
+
+// The largest `k` with 2^k <= n, zero for zero.
+fn log2_floor(n: u64) -> u32 {
+    if n == 0 {
+        0
+    } else {
+        63 - n.leading_zeros()
+    }
 }
@@ -20,6 +20,11 @@ __kernel void radix_fft(__global FIELD* x, // Source buffer
                         uint deg, // 1=>radix2, 2=>radix4, 3=>radix8, ...
                         uint max_deg) // Maximum degree supported, according to `pq` and `omegas`
 {
+  // The second dimension selects the polynomial of a batch, which follow each other in the buffers.
+  uint poly = get_group_id(1);
+  x += poly * n;
+  y += poly * n;
+
   uint lid = get_local_id(0);
   uint lsize = get_local_size(0);
   uint index = get_group_id(0);
@@ -74,3 +79,27 @@ __kernel void mul_by_field(__global FIELD* elements,
   uint gid = get_global_id(0);
   elements[gid] = FIELD_mul(elements[gid], field);
 }
+
+/// Multiplies the elements of `x` by the ones of `y`
+__kernel void mul_assign(__global FIELD* x,
+                        __global FIELD* y,
+                        uint n) {
+  uint gid = get_global_id(0);
+  x[gid] = FIELD_mul(x[gid], y[gid]);
+}
+
+/// Subtracts the elements of `y` from the ones of `x`
+__kernel void sub_assign(__global FIELD* x,
+                        __global FIELD* y,
+                        uint n) {
+  uint gid = get_global_id(0);
+  x[gid] = FIELD_sub(x[gid], y[gid]);
+}
+
+/// Multiplies the i-th element by `g^i`
+__kernel void distribute_powers(__global FIELD* elements,
+                        uint n,
+                        FIELD g) {
+  uint gid = get_global_id(0);
+  elements[gid] = FIELD_mul(elements[gid], FIELD_pow(g, gid));
+}
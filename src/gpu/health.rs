@@ -0,0 +1,91 @@
+use std::time::Instant;
+
+use log::{info, warn};
+use ocl::{Device, ProQue};
+
+use super::device::{Check, DeviceDiagnostics};
+use super::error::GPUError;
+use super::{sources, utils, FFTKernel, MultiexpKernel, GPU_NVIDIA_DEVICES};
+use crate::bls::Engine;
+use crate::domain::gpu_fft_self_test;
+use crate::multiexp::gpu_multiexp_self_test;
+
+// The FFT is checked on domains of up to this many elements, see `gpu_fft_self_test`.
+const FFT_TEST_SIZE: u32 = 1 << 10;
+
+/// Checks every GPU the provers use: builds the kernels of the engine `E` on
+/// it and compares an FFT and multiexps against the CPU. Unlike the self-tests
+/// of the provers, it runs every check again, and tells which device failed
+/// which check why. It holds the GPU lock while it runs.
+pub fn diagnostics<E: Engine>() -> Vec<DeviceDiagnostics> {
+    let lock = utils::lock();
+    if let Err(ref e) = lock {
+        warn!("Cannot take the GPU lock, checking without it: {}", e);
+    }
+
+    let diagnostics = GPU_NVIDIA_DEVICES
+        .iter()
+        .enumerate()
+        .map(|(index, &device)| {
+            let diagnostics = DeviceDiagnostics {
+                index,
+                info: utils::device_info(device).ok(),
+                build: check(|| build::<E>(device).map(|_| true)),
+                fft: check(|| {
+                    let mut kern = FFTKernel::<E>::create_on(device, FFT_TEST_SIZE)?;
+                    gpu_fft_self_test(&mut kern)
+                }),
+                multiexp: check(|| {
+                    let mut kern = Some(MultiexpKernel::<E>::create_on(device)?);
+                    gpu_multiexp_self_test(&mut kern).map_err(|e| {
+                        e.gpu_error()
+                            .cloned()
+                            .unwrap_or_else(|| GPUError::Other(e.to_string()))
+                    })
+                }),
+            };
+            info!(
+                "Diagnostics: Device {}: {}",
+                index,
+                if diagnostics.passed() {
+                    "passed"
+                } else {
+                    "failed"
+                }
+            );
+            diagnostics
+        })
+        .collect();
+
+    if let Ok(lock) = lock {
+        utils::unlock(lock);
+    }
+    diagnostics
+}
+
+// Builds the program of the kernels for the device.
+fn build<E: Engine>(device: Device) -> Result<ProQue, GPUError> {
+    Ok(ProQue::builder()
+        .device(device)
+        .src(sources::kernel::<E>())
+        .dims(1)
+        .build()?)
+}
+
+// Times `f`, which tells whether the GPU computed the same as the CPU.
+fn check<F>(f: F) -> Check
+where
+    F: FnOnce() -> Result<bool, GPUError>,
+{
+    let started = Instant::now();
+    let error = match f() {
+        Ok(true) => None,
+        Ok(false) => Some("the results differ from the CPU".to_string()),
+        Err(e) => Some(e.to_string()),
+    };
+
+    Check {
+        error,
+        duration: started.elapsed(),
+    }
+}
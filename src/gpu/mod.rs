@@ -1,6 +1,13 @@
+//! The OpenCL kernels of the FFT and the multiexp, with the GPU lock that
+//! provers take turns on and the devices they run on. Without the `gpu`
+//! feature, the kernels fail to be created and no devices are found.
+
 mod error;
 pub use self::error::*;
 
+mod device;
+pub use self::device::*;
+
 #[cfg(feature = "gpu")]
 mod sources;
 #[cfg(feature = "gpu")]
@@ -11,6 +18,19 @@ mod utils;
 #[cfg(feature = "gpu")]
 pub use self::utils::*;
 
+#[cfg(feature = "gpu")]
+mod queue;
+
+#[cfg(feature = "gpu")]
+mod config;
+#[cfg(feature = "gpu")]
+pub use self::config::{gpu_config, load_config, GpuConfig};
+
+#[cfg(feature = "gpu")]
+mod cache;
+#[cfg(feature = "gpu")]
+pub use self::cache::*;
+
 #[cfg(feature = "gpu")]
 mod structs;
 #[cfg(feature = "gpu")]
@@ -26,6 +46,16 @@ mod multiexp;
 #[cfg(feature = "gpu")]
 pub use self::multiexp::*;
 
+#[cfg(feature = "gpu")]
+mod health;
+#[cfg(feature = "gpu")]
+pub use self::health::*;
+
+#[cfg(feature = "gpu")]
+mod program;
+#[cfg(feature = "gpu")]
+pub use self::program::*;
+
 #[cfg(not(feature = "gpu"))]
 mod nogpu;
 #[cfg(not(feature = "gpu"))]
@@ -3,19 +3,58 @@ use super::sources;
 use super::structs;
 use super::utils;
 use super::GPU_NVIDIA_DEVICES;
+use crate::bls::Engine;
+use crate::multiexp::window_digit;
 use crossbeam::thread;
 use ff::{PrimeField, ScalarEngine};
 use groupy::{CurveAffine, CurveProjective};
-use log::info;
+use log::{info, warn};
 use ocl::{Buffer, Device, MemFlags, ProQue};
-use paired::Engine;
+use rand::Rng;
+use std::cmp;
+use std::env;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 // NOTE: Please read `structs.rs` for an explanation for unsafe transmutes of this code!
 
-const MAX_WINDOW_SIZE: usize = 10;
-const LOCAL_WORK_SIZE: usize = 256;
+// Launch parameters of a multiexp kernel. G2 arithmetic over Fq2 takes about three times the
+// registers and twice the memory of G1 arithmetic, so its kernel runs with smaller windows and
+// work groups.
+struct KernelConfig {
+    max_window_size: usize,
+    local_work_size: usize,
+}
+
+const G1_CONFIG: KernelConfig = KernelConfig {
+    max_window_size: 10,
+    local_work_size: 256,
+};
+const G2_CONFIG: KernelConfig = KernelConfig {
+    max_window_size: 9,
+    local_work_size: 128,
+};
 const MEMORY_PADDING: usize = 1 * 1024 * 1024 * 1024; // Consider 1GB of free memory for the GPU
+                                                      // The first chunk of a preemptible multiexp, the next ones are sized by how long it took.
+const FIRST_PREEMPTIBLE_CHUNK: usize = 1 << 20;
+const MIN_PREEMPTIBLE_CHUNK: usize = 1 << 16;
+const DEFAULT_OOM_RETRIES: usize = 2;
+
+// When a multiexp gives the GPU up, and how long its chunks may take at most between the checks.
+#[derive(Clone)]
+struct Preemption {
+    target: Duration,
+    preempt: Arc<dyn Fn() -> bool + Send + Sync>,
+}
+
+impl Preemption {
+    // The length of the chunk after one of `len` bases that took `elapsed`, so that it takes
+    // about the target.
+    fn next_chunk(&self, len: usize, elapsed: Duration, max: usize) -> usize {
+        let scaled = len as f64 * self.target.as_secs_f64() / elapsed.as_secs_f64().max(1e-3);
+        cmp::min(max, cmp::max(MIN_PREEMPTIBLE_CHUNK, scaled as usize))
+    }
+}
 
 // Multiexp kernel for a single GPU
 pub struct SingleMultiexpKernel<E>
@@ -35,7 +74,102 @@ where
     exp_buffer: Buffer<structs::PrimeFieldStruct<E::Fr>>,
 
     core_count: usize,
+    max_work_group_size: usize,
+    n: usize,
+    // How often the buffers were shrunk after running out of memory.
+    shrinks: usize,
+    spot_checks: usize,
+}
+
+// The number of partial results of every multiexp that are recomputed on the CPU, set with
+// `BELLMAN_GPU_SPOT_CHECKS`. Every partial result covers a single window of a group of bases, so
+// a check costs a small fraction of the multiexp, but catches a corrupted GPU sooner or later.
+// How often the buffers of a kernel are shrunk after it ran out of GPU memory, before it fails,
+// set with `BELLMAN_GPU_OOM_RETRIES`. Every time, they hold half the bases and the windows are a
+// bit smaller.
+fn oom_retries() -> usize {
+    env::var("BELLMAN_GPU_OOM_RETRIES")
+        .ok()
+        .and_then(|retries| retries.parse().ok())
+        .unwrap_or(DEFAULT_OOM_RETRIES)
+}
+
+// The window size of the kernels after shrinking their buffers `shrinks` times.
+fn max_window_size(config: &KernelConfig, shrinks: usize) -> usize {
+    cmp::max(1, config.max_window_size.saturating_sub(shrinks))
+}
+
+fn spot_checks() -> usize {
+    env::var("BELLMAN_GPU_SPOT_CHECKS")
+        .ok()
+        .and_then(|checks| checks.parse().ok())
+        .unwrap_or(0)
+}
+
+// Computes the partial result of the thread of the kernel that handles the `window`-th window of
+// the `group`-th group of bases.
+#[allow(clippy::too_many_arguments)]
+fn partial_result<G>(
+    bases: &[G],
+    exps: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
     n: usize,
+    num_groups: usize,
+    group: usize,
+    window: usize,
+    window_size: usize,
+    exp_bits: usize,
+) -> <G as CurveAffine>::Projective
+where
+    G: CurveAffine,
+{
+    let len = (n + num_groups - 1) / num_groups;
+    let start = std::cmp::min(len * group, n);
+    let end = std::cmp::min(start + len, n);
+    let bits = window * window_size;
+    let w = std::cmp::min(window_size, exp_bits - bits);
+
+    let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << w) - 1];
+    for (base, exp) in bases[start..end].iter().zip(exps[start..end].iter()) {
+        let digit = window_digit(exp.as_ref(), bits, w as u32);
+        if digit != 0 {
+            buckets[digit - 1].add_assign_mixed(base);
+        }
+    }
+
+    let mut acc = <G as CurveAffine>::Projective::zero();
+    let mut res = <G as CurveAffine>::Projective::zero();
+    for bucket in buckets.iter().rev() {
+        acc.add_assign(bucket);
+        res.add_assign(&acc);
+    }
+    res
+}
+
+// Accumulates the partial results of the `num_groups` * `num_windows` threads of a multiexp, the
+// windows of a group follow each other.
+fn accumulate<G>(
+    res: &[G],
+    num_groups: usize,
+    num_windows: usize,
+    window_size: usize,
+    exp_bits: usize,
+) -> G
+where
+    G: CurveProjective,
+{
+    let mut acc = G::zero();
+    let mut bits = 0;
+    for i in 0..num_windows {
+        let w = std::cmp::min(window_size, exp_bits - bits);
+        for _ in 0..w {
+            acc.double();
+        }
+        for g in 0..num_groups {
+            acc.add_assign(&res[g * num_windows + i]);
+        }
+        bits += w; // Process the next window
+    }
+    acc
 }
 
 fn calc_num_groups(core_count: usize, num_windows: usize) -> usize {
@@ -43,7 +177,7 @@ fn calc_num_groups(core_count: usize, num_windows: usize) -> usize {
     return 2 * core_count / num_windows;
 }
 
-fn calc_window_size(n: usize, exp_bits: usize, core_count: usize) -> usize {
+fn calc_window_size(n: usize, exp_bits: usize, core_count: usize, max_window_size: usize) -> usize {
     // window_size = ln(n / num_groups)
     // num_windows = exp_bits / window_size
     // num_groups = 2 * core_count / num_windows = 2 * core_count * window_size / exp_bits
@@ -53,12 +187,12 @@ fn calc_window_size(n: usize, exp_bits: usize, core_count: usize) -> usize {
     // Thus we need to solve the following equation:
     // window_size + ln(window_size) = ln(exp_bits * n / (2 * core_count))
     let lower_bound = (((exp_bits * n) as f64) / ((2 * core_count) as f64)).ln();
-    for w in 0..MAX_WINDOW_SIZE {
+    for w in 0..max_window_size {
         if (w as f64) + (w as f64).ln() > lower_bound {
             return w;
         }
     }
-    return MAX_WINDOW_SIZE;
+    return max_window_size;
 }
 
 fn calc_chunk_size<E>(mem: u64, core_count: usize) -> usize
@@ -67,9 +201,9 @@ where
 {
     let aff_size = std::mem::size_of::<E::G1Affine>() + std::mem::size_of::<E::G2Affine>();
     let exp_size = std::mem::size_of::<E::Fr>();
-    let proj_size = std::mem::size_of::<E::G1>() + std::mem::size_of::<E::G2>();
-    ((mem as usize) - MEMORY_PADDING - (2 * core_count * ((1 << MAX_WINDOW_SIZE) + 1) * proj_size))
-        / (aff_size + exp_size)
+    let buckets_size = ((1 << G1_CONFIG.max_window_size) + 1) * std::mem::size_of::<E::G1>()
+        + ((1 << G2_CONFIG.max_window_size) + 1) * std::mem::size_of::<E::G2>();
+    ((mem as usize) - MEMORY_PADDING - (2 * core_count * buckets_size)) / (aff_size + exp_size)
 }
 
 impl<E> SingleMultiexpKernel<E>
@@ -81,10 +215,42 @@ where
         let pq = ProQue::builder().device(d).src(src).dims(1).build()?;
 
         let core_count = utils::get_core_count(d)?;
+        let max_work_group_size = utils::get_max_work_group_size(d)?;
         let mem = utils::get_memory(d)?;
-        let max_n = calc_chunk_size::<E>(mem, core_count);
-        let max_bucket_len = 1 << MAX_WINDOW_SIZE;
+        let max_n =
+            utils::get_chunk_size(d)?.unwrap_or_else(|| calc_chunk_size::<E>(mem, core_count));
 
+        let mut shrinks = 0;
+        loop {
+            let n = cmp::max(1, max_n >> shrinks);
+            match SingleMultiexpKernel::allocate(
+                pq.clone(),
+                core_count,
+                max_work_group_size,
+                n,
+                shrinks,
+            ) {
+                Err(ref e) if e.is_out_of_memory() && shrinks < oom_retries() => {
+                    warn!(
+                        "Multiexp: Out of GPU memory, retrying with smaller buffers: {}",
+                        e
+                    );
+                    shrinks += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    // Allocates the buffers for chunks of `n` bases, with windows that are `shrinks` bits smaller
+    // than the ones of the kernel configs.
+    fn allocate(
+        pq: ProQue,
+        core_count: usize,
+        max_work_group_size: usize,
+        n: usize,
+        shrinks: usize,
+    ) -> GPUResult<SingleMultiexpKernel<E>> {
         // Each group will have `num_windows` threads and as there are `num_groups` groups, there will
         // be `num_groups` * `num_windows` threads in total.
         // Each thread will use `num_groups` * `num_windows` * `bucket_len` buckets.
@@ -92,12 +258,12 @@ where
         let g1basebuff = Buffer::builder()
             .queue(pq.queue().clone())
             .flags(MemFlags::new().read_write())
-            .len(max_n)
+            .len(n)
             .build()?;
         let g1buckbuff = Buffer::builder()
             .queue(pq.queue().clone())
             .flags(MemFlags::new().read_write())
-            .len(2 * core_count * max_bucket_len)
+            .len(2 * core_count * (1 << max_window_size(&G1_CONFIG, shrinks)))
             .build()?;
         let g1resbuff = Buffer::builder()
             .queue(pq.queue().clone())
@@ -108,12 +274,12 @@ where
         let g2basebuff = Buffer::builder()
             .queue(pq.queue().clone())
             .flags(MemFlags::new().read_write())
-            .len(max_n)
+            .len(n)
             .build()?;
         let g2buckbuff = Buffer::builder()
             .queue(pq.queue().clone())
             .flags(MemFlags::new().read_write())
-            .len(2 * core_count * max_bucket_len)
+            .len(2 * core_count * (1 << max_window_size(&G2_CONFIG, shrinks)))
             .build()?;
         let g2resbuff = Buffer::builder()
             .queue(pq.queue().clone())
@@ -124,7 +290,7 @@ where
         let expbuff = Buffer::builder()
             .queue(pq.queue().clone())
             .flags(MemFlags::new().read_write())
-            .len(max_n)
+            .len(n)
             .build()?;
 
         Ok(SingleMultiexpKernel {
@@ -137,10 +303,29 @@ where
             g2_result_buffer: g2resbuff,
             exp_buffer: expbuff,
             core_count: core_count,
-            n: max_n,
+            max_work_group_size,
+            n,
+            shrinks,
+            spot_checks: spot_checks(),
         })
     }
 
+    // Replaces the buffers with ones for half the bases and smaller windows after the GPU ran out
+    // of memory. Returns false if they were shrunk `BELLMAN_GPU_OOM_RETRIES` times already.
+    fn shrink(&mut self) -> GPUResult<bool> {
+        if self.shrinks >= oom_retries() || self.n == 1 {
+            return Ok(false);
+        }
+        *self = SingleMultiexpKernel::allocate(
+            self.proque.clone(),
+            self.core_count,
+            self.max_work_group_size,
+            self.n / 2,
+            self.shrinks + 1,
+        )?;
+        Ok(true)
+    }
+
     pub fn multiexp<G>(
         &mut self,
         bases: &[G],
@@ -150,8 +335,20 @@ where
     where
         G: CurveAffine,
     {
+        let sz = std::mem::size_of::<G>(); // Trick, used for dispatching between G1 and G2!
+        let config = if sz == std::mem::size_of::<E::G1Affine>() {
+            &G1_CONFIG
+        } else {
+            &G2_CONFIG
+        };
+
         let exp_bits = std::mem::size_of::<E::Fr>() * 8;
-        let window_size = calc_window_size(n as usize, exp_bits, self.core_count);
+        let window_size = calc_window_size(
+            n as usize,
+            exp_bits,
+            self.core_count,
+            max_window_size(config, self.shrinks),
+        );
         let num_windows = ((exp_bits as f64) / (window_size as f64)).ceil() as usize;
         let num_groups = calc_num_groups(self.core_count, num_windows);
 
@@ -164,11 +361,11 @@ where
         };
         self.exp_buffer.write(texps).enq()?;
 
-        // Make global work size divisible by `LOCAL_WORK_SIZE`
+        // Make global work size divisible by the local work size
+        let lws = std::cmp::min(config.local_work_size, self.max_work_group_size);
         let mut gws = num_windows * num_groups;
-        gws += (LOCAL_WORK_SIZE - (gws % LOCAL_WORK_SIZE)) % LOCAL_WORK_SIZE;
+        gws += (lws - (gws % lws)) % lws;
 
-        let sz = std::mem::size_of::<G>(); // Trick, used for dispatching between G1 and G2!
         if sz == std::mem::size_of::<E::G1Affine>() {
             let tbases = unsafe {
                 &*(bases as *const [G]
@@ -179,6 +376,7 @@ where
                 .proque
                 .kernel_builder("G1_bellman_multiexp")
                 .global_work_size([gws])
+                .local_work_size([lws])
                 .arg(&self.g1_base_buffer)
                 .arg(&self.g1_bucket_buffer)
                 .arg(&self.g1_result_buffer)
@@ -206,6 +404,7 @@ where
                 .proque
                 .kernel_builder("G2_bellman_multiexp")
                 .global_work_size([gws])
+                .local_work_size([lws])
                 .arg(&self.g2_base_buffer)
                 .arg(&self.g2_bucket_buffer)
                 .arg(&self.g2_result_buffer)
@@ -224,27 +423,274 @@ where
             };
             self.g2_result_buffer.read(tres).enq()?;
         } else {
-            return Err(GPUError {
-                msg: "Only E::G1 and E::G2 are supported!".to_string(),
-            });
+            return Err(GPUError::InvalidInput(
+                "Only E::G1 and E::G2 are supported!".to_string(),
+            ));
         }
 
-        // Using the algorithm below, we can calculate the final result by accumulating the results
-        // of those `NUM_GROUPS` * `NUM_WINDOWS` threads.
-        let mut acc = <G as CurveAffine>::Projective::zero();
-        let mut bits = 0;
-        for i in 0..num_windows {
-            let w = std::cmp::min(window_size, exp_bits - bits);
-            for _ in 0..w {
-                acc.double();
+        // Recompute some of the partial results at random to catch silent corruption
+        let mut rng = rand::thread_rng();
+        for _ in 0..self.spot_checks {
+            let group = rng.gen_range(0, num_groups);
+            let window = rng.gen_range(0, num_windows);
+            let expected = partial_result(
+                bases,
+                exps,
+                n,
+                num_groups,
+                group,
+                window,
+                window_size,
+                exp_bits,
+            );
+            if res[group * num_windows + window] != expected {
+                warn!(
+                    "Multiexp: Spot check of group {} and window {} failed!",
+                    group, window
+                );
+                return Err(GPUError::WrongResults(
+                    "GPU Multiexp spot check failed!".to_string(),
+                ));
             }
-            for g in 0..num_groups {
-                acc.add_assign(&res[g * num_windows + i]);
+        }
+
+        // We can calculate the final result by accumulating the results of those
+        // `NUM_GROUPS` * `NUM_WINDOWS` threads.
+        Ok(accumulate(
+            &res,
+            num_groups,
+            num_windows,
+            window_size,
+            exp_bits,
+        ))
+    }
+
+    // The number of the first `jobs` that fit into a single launch of the batch kernel, with the
+    // window size of their threads. Every job needs a thread per window at least, and the bases
+    // of all of them have to fit into the buffers.
+    fn plan_batch<G>(
+        &self,
+        jobs: &[(
+            &[G],
+            &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+        )],
+        config: &KernelConfig,
+        exp_bits: usize,
+    ) -> (usize, usize)
+    where
+        G: CurveAffine,
+    {
+        let mut planned = (0, 0);
+        let mut total = 0;
+        let mut max_len = 0;
+        for (i, (bases, _)) in jobs.iter().enumerate() {
+            total += bases.len();
+            max_len = std::cmp::max(max_len, bases.len());
+            if total > self.n {
+                break;
+            }
+
+            let count = i + 1;
+            let window_size = calc_window_size(
+                max_len,
+                exp_bits,
+                std::cmp::max(1, self.core_count / count),
+                max_window_size(config, self.shrinks),
+            );
+            let num_windows = ((exp_bits as f64) / (window_size as f64)).ceil() as usize;
+            if count * num_windows > 2 * self.core_count {
+                break;
             }
-            bits += w; // Process the next window
+            planned = (count, window_size);
         }
+        planned
+    }
+
+    // Computes several multiexps with as few kernel launches as possible, which saves the launch
+    // and transfer overhead of every single one of them when they are small. Each job has to fit
+    // into the buffers on its own.
+    pub fn multiexp_batch<G>(
+        &mut self,
+        jobs: &[(
+            &[G],
+            &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+        )],
+    ) -> GPUResult<Vec<<G as CurveAffine>::Projective>>
+    where
+        G: CurveAffine,
+    {
+        let sz = std::mem::size_of::<G>(); // Trick, used for dispatching between G1 and G2!
+        let config = if sz == std::mem::size_of::<E::G1Affine>() {
+            &G1_CONFIG
+        } else {
+            &G2_CONFIG
+        };
+        let exp_bits = std::mem::size_of::<E::Fr>() * 8;
 
-        Ok(acc)
+        let mut results = Vec::with_capacity(jobs.len());
+        let mut rest = jobs;
+        while !rest.is_empty() {
+            let (count, window_size) = self.plan_batch(rest, config, exp_bits);
+            if count == 0 {
+                return Err(GPUError::InvalidInput(
+                    "Multiexp is too large for a batch!".to_string(),
+                ));
+            }
+            results.extend(self.launch_batch(&rest[..count], window_size, config, exp_bits)?);
+            rest = &rest[count..];
+        }
+        Ok(results)
+    }
+
+    fn launch_batch<G>(
+        &mut self,
+        jobs: &[(
+            &[G],
+            &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+        )],
+        window_size: usize,
+        config: &KernelConfig,
+        exp_bits: usize,
+    ) -> GPUResult<Vec<<G as CurveAffine>::Projective>>
+    where
+        G: CurveAffine,
+    {
+        let sz = std::mem::size_of::<G>();
+        let num_windows = ((exp_bits as f64) / (window_size as f64)).ceil() as usize;
+        let num_groups = std::cmp::max(1, 2 * self.core_count / (jobs.len() * num_windows));
+        let threads = num_windows * num_groups;
+
+        // The bases and exponents of all jobs go into the buffers one after the other.
+        let mut offsets = vec![0u32];
+        let mut bases = vec![];
+        let mut exps = vec![];
+        for (b, e) in jobs {
+            if b.len() != e.len() {
+                return Err(GPUError::InvalidInput(
+                    "Multiexp has a different number of bases and exponents!".to_string(),
+                ));
+            }
+            bases.extend_from_slice(b);
+            exps.extend_from_slice(e);
+            offsets.push(bases.len() as u32);
+        }
+
+        let mut res = vec![<G as CurveAffine>::Projective::zero(); jobs.len() * threads];
+        let texps = unsafe {
+            std::mem::transmute::<
+                &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+                &[structs::PrimeFieldStruct<E::Fr>],
+            >(&exps[..])
+        };
+        self.exp_buffer.write(texps).enq()?;
+        let offset_buffer = Buffer::<u32>::builder()
+            .queue(self.proque.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(offsets.len())
+            .copy_host_slice(&offsets)
+            .build()?;
+
+        // Make global work size divisible by the local work size
+        let lws = std::cmp::min(config.local_work_size, self.max_work_group_size);
+        let mut gws = jobs.len() * threads;
+        gws += (lws - (gws % lws)) % lws;
+
+        if sz == std::mem::size_of::<E::G1Affine>() {
+            let tbases = unsafe {
+                &*(&bases[..] as *const [G]
+                    as *const [structs::CurveAffineStruct<<E as Engine>::G1Affine>])
+            };
+            self.g1_base_buffer.write(tbases).enq()?;
+            let kernel = self
+                .proque
+                .kernel_builder("G1_bellman_multiexp_batch")
+                .global_work_size([gws])
+                .local_work_size([lws])
+                .arg(&self.g1_base_buffer)
+                .arg(&self.g1_bucket_buffer)
+                .arg(&self.g1_result_buffer)
+                .arg(&self.exp_buffer)
+                .arg(&offset_buffer)
+                .arg(jobs.len() as u32)
+                .arg(num_groups as u32)
+                .arg(num_windows as u32)
+                .arg(window_size as u32)
+                .build()?;
+            unsafe {
+                kernel.enq()?;
+            }
+            let tres = unsafe {
+                &mut *(&mut res as *mut Vec<<G as CurveAffine>::Projective>
+                    as *mut Vec<structs::CurveProjectiveStruct<<E as Engine>::G1>>)
+            };
+            self.g1_result_buffer.read(tres).enq()?;
+        } else if sz == std::mem::size_of::<E::G2Affine>() {
+            let tbases = unsafe {
+                &*(&bases[..] as *const [G]
+                    as *const [structs::CurveAffineStruct<<E as Engine>::G2Affine>])
+            };
+            self.g2_base_buffer.write(tbases).enq()?;
+            let kernel = self
+                .proque
+                .kernel_builder("G2_bellman_multiexp_batch")
+                .global_work_size([gws])
+                .local_work_size([lws])
+                .arg(&self.g2_base_buffer)
+                .arg(&self.g2_bucket_buffer)
+                .arg(&self.g2_result_buffer)
+                .arg(&self.exp_buffer)
+                .arg(&offset_buffer)
+                .arg(jobs.len() as u32)
+                .arg(num_groups as u32)
+                .arg(num_windows as u32)
+                .arg(window_size as u32)
+                .build()?;
+            unsafe {
+                kernel.enq()?;
+            }
+            let tres = unsafe {
+                &mut *(&mut res as *mut Vec<<G as CurveAffine>::Projective>
+                    as *mut Vec<structs::CurveProjectiveStruct<<E as Engine>::G2>>)
+            };
+            self.g2_result_buffer.read(tres).enq()?;
+        } else {
+            return Err(GPUError::InvalidInput(
+                "Only E::G1 and E::G2 are supported!".to_string(),
+            ));
+        }
+
+        // Recompute some of the partial results at random to catch silent corruption
+        let mut rng = rand::thread_rng();
+        for _ in 0..self.spot_checks {
+            let job = rng.gen_range(0, jobs.len());
+            let group = rng.gen_range(0, num_groups);
+            let window = rng.gen_range(0, num_windows);
+            let (bases, exps) = jobs[job];
+            let expected = partial_result(
+                bases,
+                exps,
+                bases.len(),
+                num_groups,
+                group,
+                window,
+                window_size,
+                exp_bits,
+            );
+            if res[job * threads + group * num_windows + window] != expected {
+                warn!(
+                    "Multiexp: Spot check of job {}, group {} and window {} failed!",
+                    job, group, window
+                );
+                return Err(GPUError::WrongResults(
+                    "GPU Multiexp spot check failed!".to_string(),
+                ));
+            }
+        }
+
+        Ok(res
+            .chunks(threads)
+            .map(|res| accumulate(res, num_groups, num_windows, window_size, exp_bits))
+            .collect())
     }
 }
 
@@ -254,6 +700,7 @@ where
     E: Engine,
 {
     kernels: Vec<SingleMultiexpKernel<E>>,
+    preemption: Option<Preemption>,
 }
 
 impl<E> MultiexpKernel<E>
@@ -268,9 +715,7 @@ where
             .map(|res| res.unwrap())
             .collect();
         if kernels.is_empty() {
-            return Err(GPUError {
-                msg: "No working GPUs found!".to_string(),
-            });
+            return Err(GPUError::NoDevice("No working GPUs found!".to_string()));
         }
         info!("Multiexp: {} working device(s) selected.", kernels.len());
         for (i, k) in kernels.iter().enumerate() {
@@ -281,7 +726,31 @@ where
                 k.n
             );
         }
-        return Ok(MultiexpKernel::<E> { kernels });
+        return Ok(MultiexpKernel::<E> {
+            kernels,
+            preemption: None,
+        });
+    }
+
+    // Creates a kernel that only runs on the given device.
+    pub fn create_on(device: Device) -> GPUResult<MultiexpKernel<E>> {
+        Ok(MultiexpKernel::<E> {
+            kernels: vec![SingleMultiexpKernel::<E>::create(device)?],
+            preemption: None,
+        })
+    }
+
+    // Splits multiexps into chunks that take about `target` each, and fails them with
+    // `GPUError::Preempted` before the next chunk once `preempt` returns true. Without it, the
+    // chunks are as large as the buffers.
+    pub fn set_preemption<F>(&mut self, target: Duration, preempt: F)
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        self.preemption = Some(Preemption {
+            target,
+            preempt: Arc::new(preempt),
+        });
     }
 
     pub fn multiexp<G>(
@@ -298,13 +767,28 @@ where
             return Ok(<G as CurveAffine>::Projective::zero());
         }
 
-        let num_devices = self.kernels.len();
-        let chunk_size = ((n as f64) / (num_devices as f64)).ceil() as usize;
         // Bases are skipped by `self.1` elements, when converted from (Arc<Vec<G>>, usize) to Source
         // https://github.com/zkcrypto/bellman/blob/10c5010fd9c2ca69442dc9775ea271e286e776d8/src/multiexp.rs#L38
-        let bases = &bases[skip..(skip + n)];
+        self.multiexp_slices(&bases[skip..(skip + n)], &exps[..n])
+    }
 
-        let exps = &exps[..n];
+    // Splits the multiexp among the devices, and into chunks that fit into their buffers.
+    fn multiexp_slices<G>(
+        &mut self,
+        bases: &[G],
+        exps: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    ) -> GPUResult<<G as CurveAffine>::Projective>
+    where
+        G: CurveAffine,
+    {
+        let n = exps.len();
+        if n == 0 {
+            return Ok(<G as CurveAffine>::Projective::zero());
+        }
+
+        let num_devices = self.kernels.len();
+        let chunk_size = ((n as f64) / (num_devices as f64)).ceil() as usize;
+        let preemption = &self.preemption;
 
         match thread::scope(|s| -> Result<<G as CurveAffine>::Projective, GPUError> {
             let mut acc = <G as CurveAffine>::Projective::zero();
@@ -317,9 +801,38 @@ where
                 threads.push(s.spawn(
                     move |_| -> Result<<G as CurveAffine>::Projective, GPUError> {
                         let mut acc = <G as CurveAffine>::Projective::zero();
-                        for (bases, exps) in bases.chunks(kern.n).zip(exps.chunks(kern.n)) {
-                            let result = kern.multiexp(bases, exps, bases.len())?;
-                            acc.add_assign(&result);
+                        let mut done = 0;
+                        let mut len = match preemption {
+                            Some(_) => cmp::min(kern.n, FIRST_PREEMPTIBLE_CHUNK),
+                            None => kern.n,
+                        };
+                        while done < bases.len() {
+                            if let Some(preemption) = preemption {
+                                if (preemption.preempt)() {
+                                    info!("Multiexp: Giving the GPU up");
+                                    return Err(GPUError::Preempted);
+                                }
+                            }
+
+                            let end = cmp::min(done + len, bases.len());
+                            let started = Instant::now();
+                            let result =
+                                kern.multiexp(&bases[done..end], &exps[done..end], end - done);
+                            if let Err(ref e) = result {
+                                if e.is_out_of_memory() && kern.shrink()? {
+                                    warn!(
+                                        "Multiexp: Out of GPU memory, retrying with chunks of {}: {}",
+                                        kern.n, e
+                                    );
+                                    len = cmp::min(len, kern.n);
+                                    continue;
+                                }
+                            }
+                            acc.add_assign(&result?);
+                            if let Some(preemption) = preemption {
+                                len = preemption.next_chunk(end - done, started.elapsed(), kern.n);
+                            }
+                            done = end;
                         }
                         Ok(acc)
                     },
@@ -335,4 +848,98 @@ where
             Err(e) => Err(GPUError::from(e)),
         }
     }
+
+    // Computes several multiexps of the given bases and exponents. Multiexps that fit into the
+    // buffers of the devices are packed into shared kernel launches, which are split among the
+    // devices, larger ones are computed like `multiexp`.
+    pub fn multiexp_batch<G>(
+        &mut self,
+        jobs: &[(
+            &[G],
+            &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+        )],
+    ) -> GPUResult<Vec<<G as CurveAffine>::Projective>>
+    where
+        G: CurveAffine,
+    {
+        let max_len = self.kernels.iter().map(|k| k.n).min().unwrap_or(0);
+        let (small, large): (Vec<usize>, Vec<usize>) =
+            (0..jobs.len()).partition(|&i| jobs[i].0.len() <= max_len);
+
+        let mut results = vec![<G as CurveAffine>::Projective::zero(); jobs.len()];
+        let small_jobs = small.iter().map(|&i| jobs[i]).collect::<Vec<_>>();
+        if !small_jobs.is_empty() {
+            let num_devices = self.kernels.len();
+            let chunk_size = ((small_jobs.len() as f64) / (num_devices as f64)).ceil() as usize;
+            let batches = thread::scope(|s| -> GPUResult<Vec<_>> {
+                let mut threads = Vec::new();
+                for (jobs, kern) in small_jobs.chunks(chunk_size).zip(self.kernels.iter_mut()) {
+                    threads.push(s.spawn(move |_| kern.multiexp_batch(jobs)));
+                }
+                let mut batches = Vec::new();
+                for t in threads {
+                    batches.extend(t.join()??);
+                }
+                Ok(batches)
+            })??;
+            for (&i, result) in small.iter().zip(batches) {
+                results[i] = result;
+            }
+        }
+
+        for i in large {
+            let (bases, exps) = jobs[i];
+            results[i] = self.multiexp_slices(bases, exps)?;
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preemptible_chunks() {
+        let preemption = Preemption {
+            target: Duration::from_secs(2),
+            preempt: Arc::new(|| false),
+        };
+        let max = 1 << 24;
+        // Chunks grow and shrink towards the target.
+        assert_eq!(
+            preemption.next_chunk(1 << 20, Duration::from_millis(500), max),
+            1 << 22
+        );
+        assert_eq!(
+            preemption.next_chunk(1 << 20, Duration::from_secs(8), max),
+            1 << 18
+        );
+        // Within the bounds.
+        assert_eq!(
+            preemption.next_chunk(1 << 20, Duration::from_secs(0), max),
+            max
+        );
+        assert_eq!(
+            preemption.next_chunk(1 << 20, Duration::from_secs(1000), max),
+            MIN_PREEMPTIBLE_CHUNK
+        );
+    }
+
+    #[test]
+    fn test_shrunk_windows() {
+        assert_eq!(max_window_size(&G1_CONFIG, 0), G1_CONFIG.max_window_size);
+        assert_eq!(
+            max_window_size(&G2_CONFIG, 2),
+            G2_CONFIG.max_window_size - 2
+        );
+        assert_eq!(max_window_size(&G1_CONFIG, 100), 1);
+
+        let error =
+            GPUError::driver("Buffer write failed: CL_MEM_OBJECT_ALLOCATION_FAILURE".to_string());
+        assert!(error.is_out_of_memory());
+        assert!(!GPUError::driver("CL_INVALID_VALUE".to_string()).is_out_of_memory());
+        assert!(!GPUError::Preempted.is_out_of_memory());
+    }
 }
@@ -8,34 +8,29 @@
  * threads running in parallel for calculating a multiexp instance.
  */
 
-__kernel void POINT_bellman_multiexp(
+// Runs the thread `tid` of a multiexp of `n` bases, which handles a window
+// of a group of the bases, with its own `buckets`.
+POINT_projective POINT_multiexp_thread(
     __global POINT_affine *bases,
     __global POINT_projective *buckets,
-    __global POINT_projective *results,
     __global EXPONENT *exps,
     uint n,
     uint num_groups,
     uint num_windows,
-    uint window_size) {
-
-  // We have `num_windows` * `num_groups` threads per multiexp.
-  uint gid = get_global_id(0);
-  if(gid >= num_windows * num_groups) return;
+    uint window_size,
+    uint tid) {
 
   // We have (2^window_size - 1) buckets.
   uint bucket_len = ((1 << window_size) - 1);
-
-  // Each thread has its own set of buckets in global memory.
-  buckets += bucket_len * gid;
   for(uint i = 0; i < bucket_len; i++) buckets[i] = POINT_ZERO;
 
   uint len = (uint)ceil(n / (float)num_groups); // Num of elements in each group
 
   // This thread runs the multiexp algorithm on elements from `nstart` to `nened`
   // on the window [`bits`, `bits` + `w`)
-  uint nstart = len * (gid / num_windows);
+  uint nstart = len * (tid / num_windows);
   uint nend = min(nstart + len, n);
-  uint bits = (gid % num_windows) * window_size;
+  uint bits = (tid % num_windows) * window_size;
   ushort w = min((ushort)window_size, (ushort)(EXPONENT_BITS - bits));
 
   POINT_projective res = POINT_ZERO;
@@ -59,5 +54,53 @@ __kernel void POINT_bellman_multiexp(
     res = POINT_add(res, acc);
   }
 
-  results[gid] = res;
+  return res;
+}
+
+__kernel void POINT_bellman_multiexp(
+    __global POINT_affine *bases,
+    __global POINT_projective *buckets,
+    __global POINT_projective *results,
+    __global EXPONENT *exps,
+    uint n,
+    uint num_groups,
+    uint num_windows,
+    uint window_size) {
+
+  // We have `num_windows` * `num_groups` threads per multiexp.
+  uint gid = get_global_id(0);
+  if(gid >= num_windows * num_groups) return;
+
+  // Each thread has its own set of buckets in global memory.
+  buckets += ((1 << window_size) - 1) * gid;
+
+  results[gid] = POINT_multiexp_thread(bases, buckets, exps, n, num_groups,
+                                       num_windows, window_size, gid);
+}
+
+// Several multiexps at once, with `num_windows` * `num_groups` threads each.
+// The bases and exponents of the `i`th multiexp start at `offsets[i]` and end
+// at `offsets[i + 1]`.
+__kernel void POINT_bellman_multiexp_batch(
+    __global POINT_affine *bases,
+    __global POINT_projective *buckets,
+    __global POINT_projective *results,
+    __global EXPONENT *exps,
+    __global uint *offsets,
+    uint num_jobs,
+    uint num_groups,
+    uint num_windows,
+    uint window_size) {
+
+  uint gid = get_global_id(0);
+  uint threads = num_windows * num_groups;
+  if(gid >= num_jobs * threads) return;
+
+  uint job = gid / threads;
+  uint start = offsets[job];
+  buckets += ((1 << window_size) - 1) * gid;
+
+  results[gid] = POINT_multiexp_thread(bases + start, buckets, exps + start,
+                                       offsets[job + 1] - start, num_groups,
+                                       num_windows, window_size, gid % threads);
 }
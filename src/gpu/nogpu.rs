@@ -3,6 +3,7 @@ use ff::{PrimeField, ScalarEngine};
 use groupy::CurveAffine;
 use std::marker::PhantomData;
 use std::sync::Arc;
+use std::time::Duration;
 
 // This module is compiled instead of `fft.rs` and `multiexp.rs` if `gpu` feature is disabled.
 
@@ -15,21 +16,31 @@ where
     E: ScalarEngine,
 {
     pub fn create(_: u32) -> GPUResult<FFTKernel<E>> {
-        return Err(GPUError {
-            msg: "GPU accelerator is not enabled!".to_string(),
-        });
+        return Err(GPUError::Disabled);
     }
 
     pub fn radix_fft(&mut self, _: &mut [E::Fr], _: &E::Fr, _: u32) -> GPUResult<()> {
-        return Err(GPUError {
-            msg: "GPU accelerator is not enabled!".to_string(),
-        });
+        return Err(GPUError::Disabled);
+    }
+
+    pub fn radix_fft_many(&mut self, _: &mut [&mut [E::Fr]], _: &E::Fr, _: u32) -> GPUResult<()> {
+        return Err(GPUError::Disabled);
     }
 
     pub fn mul_by_field(&mut self, _: &mut [E::Fr], _: &E::Fr, _: u32) -> GPUResult<()> {
-        return Err(GPUError {
-            msg: "GPU accelerator is not enabled!".to_string(),
-        });
+        return Err(GPUError::Disabled);
+    }
+
+    pub fn mul_assign(&mut self, _: &mut [E::Fr], _: &[E::Fr]) -> GPUResult<()> {
+        return Err(GPUError::Disabled);
+    }
+
+    pub fn sub_assign(&mut self, _: &mut [E::Fr], _: &[E::Fr]) -> GPUResult<()> {
+        return Err(GPUError::Disabled);
+    }
+
+    pub fn distribute_powers(&mut self, _: &mut [E::Fr], _: &E::Fr) -> GPUResult<()> {
+        return Err(GPUError::Disabled);
     }
 }
 
@@ -42,9 +53,13 @@ where
     E: ScalarEngine,
 {
     pub fn create() -> GPUResult<MultiexpKernel<E>> {
-        return Err(GPUError {
-            msg: "GPU accelerator is not enabled!".to_string(),
-        });
+        return Err(GPUError::Disabled);
+    }
+
+    pub fn set_preemption<F>(&mut self, _: Duration, _: F)
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
     }
 
     pub fn multiexp<G>(
@@ -57,8 +72,33 @@ where
     where
         G: CurveAffine,
     {
-        return Err(GPUError {
-            msg: "GPU accelerator is not enabled!".to_string(),
-        });
+        return Err(GPUError::Disabled);
     }
+
+    pub fn multiexp_batch<G>(
+        &mut self,
+        _: &[(
+            &[G],
+            &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+        )],
+    ) -> GPUResult<Vec<<G as CurveAffine>::Projective>>
+    where
+        G: CurveAffine,
+    {
+        return Err(GPUError::Disabled);
+    }
+}
+
+pub fn cached_verdict(_: &str) -> Option<bool> {
+    None
+}
+
+pub fn cache_verdict(_: &str, _: bool) {}
+
+pub fn devices() -> Vec<super::DeviceInfo> {
+    vec![]
+}
+
+pub fn diagnostics<E: crate::bls::Engine>() -> Vec<super::DeviceDiagnostics> {
+    vec![]
 }
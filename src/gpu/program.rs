@@ -0,0 +1,64 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use log::info;
+use ocl::ProQue;
+
+use super::error::{GPUError, GPUResult};
+use super::sources;
+use super::utils;
+use super::GPU_NVIDIA_DEVICES;
+use crate::bls::Engine;
+
+// An OpenCL program of a downstream crate, built on top of the field and curve arithmetic of the
+// crate, e.g. for hashing or building trees on the GPU. Its kernels are launched while holding the
+// GPU lock, so they take turns with the provers.
+pub struct Program<E>
+where
+    E: Engine,
+{
+    proque: ProQue,
+    _engine: PhantomData<E>,
+}
+
+impl<E> Program<E>
+where
+    E: Engine,
+{
+    // Builds `extension` after the sources of the crate, see `kernel_with`, for the `device`th
+    // GPU.
+    pub fn create(device: usize, extension: &str) -> GPUResult<Program<E>> {
+        let d = *GPU_NVIDIA_DEVICES
+            .get(device)
+            .ok_or_else(|| GPUError::NoDevice(format!("There is no GPU {}!", device)))?;
+        let src = sources::kernel_with::<E>(extension);
+        let proque = ProQue::builder().device(d).src(src).dims(1).build()?;
+        info!("Program: Device {}: {}", device, d.name()?);
+
+        Ok(Program {
+            proque,
+            _engine: PhantomData,
+        })
+    }
+
+    // The program with its context and queue, for creating buffers and kernels. Kernels must
+    // only be enqueued within `run`.
+    pub fn proque(&self) -> &ProQue {
+        &self.proque
+    }
+
+    // Takes the GPU lock, after the provers that asked for it earlier, and runs `f` with it.
+    // Waits for the queue to finish before the lock is released.
+    pub fn run<T, F>(&self, f: F) -> GPUResult<T>
+    where
+        F: FnOnce(&ProQue) -> GPUResult<T>,
+    {
+        let lock = utils::lock().map_err(|e| GPUError::Lock(Arc::new(e)))?;
+        let res = f(&self.proque);
+        let finished = self.proque.finish();
+        utils::unlock(lock);
+        let res = res?;
+        finished?;
+        Ok(res)
+    }
+}
@@ -0,0 +1,215 @@
+//! A queue in front of the GPU lock, which grants it in the order it was asked for. Waiters on a
+//! file lock are woken in no particular order, so under contention a prover could wait forever.
+//!
+//! Every waiter draws a ticket from a counter and puts a file named after it into the queue
+//! directory, which it keeps locked until it releases the GPU. It then waits for the ticket files
+//! ahead of it, one after the other, by locking them. The locks of a process are released when it
+//! dies, so a crashed prover doesn't stall the queue.
+
+use std::cmp;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use fs2::FileExt;
+
+/// How often a lock is tried while waiting for it with a deadline.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A place in the queue, which is left when it's dropped.
+#[derive(Debug)]
+pub struct Ticket {
+    path: PathBuf,
+    // Released after the file is removed, so nobody takes the removed file for a waiter.
+    _file: File,
+}
+
+impl Ticket {
+    /// Draws a ticket and waits until all tickets drawn before it have left the queue.
+    pub fn wait(dir: &Path) -> io::Result<Ticket> {
+        Self::wait_until(dir, None).map(|ticket| ticket.expect("waited without a deadline"))
+    }
+
+    /// Like [`Ticket::wait`], but leaves the queue and returns `None` at the deadline.
+    pub fn wait_until(dir: &Path, deadline: Option<Instant>) -> io::Result<Option<Ticket>> {
+        let ticket = Self::draw(dir)?;
+        loop {
+            let ahead = tickets(dir)?
+                .into_iter()
+                .filter(|path| *path < ticket.path)
+                .max();
+            let path = match ahead {
+                Some(path) => path,
+                None => return Ok(Some(ticket)),
+            };
+
+            match File::open(&path) {
+                Ok(file) => {
+                    // Whoever held the ticket is done or dead once it's locked.
+                    if !lock_until(&file, false, deadline)? {
+                        return Ok(None);
+                    }
+                    remove(&path)?;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn draw(dir: &Path) -> io::Result<Ticket> {
+        fs::create_dir_all(dir)?;
+
+        // The counter stays locked until the ticket file is in place, so tickets show up in the
+        // order they were drawn.
+        let mut counter = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(dir.join("next"))?;
+        counter.lock_exclusive()?;
+        let mut contents = String::new();
+        counter.read_to_string(&mut contents)?;
+        let number = contents.trim().parse::<u64>().unwrap_or(0);
+        counter.seek(SeekFrom::Start(0))?;
+        counter.set_len(0)?;
+        write!(counter, "{}", number + 1)?;
+
+        // The file is locked before it gets its name, so nobody sees it unlocked.
+        let pending = dir.join("pending");
+        let file = File::create(&pending)?;
+        file.lock_exclusive()?;
+        let path = dir.join(format!("{:020}", number));
+        fs::rename(&pending, &path)?;
+
+        Ok(Ticket { path, _file: file })
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        let _ = remove(&self.path);
+    }
+}
+
+/// Locks the file shared or exclusively, unless that takes until the deadline. Returns whether
+/// the file is locked.
+pub fn lock_until(file: &File, exclusive: bool, deadline: Option<Instant>) -> io::Result<bool> {
+    let deadline = match deadline {
+        Some(deadline) => deadline,
+        None if exclusive => return file.lock_exclusive().map(|_| true),
+        None => return file.lock_shared().map(|_| true),
+    };
+
+    loop {
+        let result = if exclusive {
+            file.try_lock_exclusive()
+        } else {
+            file.try_lock_shared()
+        };
+        match result {
+            Ok(()) => return Ok(true),
+            Err(ref e) if e.kind() == fs2::lock_contended_error().kind() => {}
+            Err(e) => return Err(e),
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(false);
+        }
+        thread::sleep(cmp::min(POLL_INTERVAL, deadline - now));
+    }
+}
+
+/// Whether nobody is waiting for the GPU or holding it. Tickets of dead processes are removed.
+pub fn is_empty(dir: &Path) -> io::Result<bool> {
+    for path in tickets(dir)? {
+        match File::open(&path) {
+            Ok(file) => match file.try_lock_shared() {
+                Ok(()) => remove(&path)?,
+                Err(ref e) if e.kind() == fs2::lock_contended_error().kind() => return Ok(false),
+                Err(e) => return Err(e),
+            },
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(true)
+}
+
+/// The ticket files in the queue, which sort like their numbers.
+fn tickets(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+
+    let mut tickets = vec![];
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        let is_ticket = name
+            .to_str()
+            .map_or(false, |name| name.bytes().all(|b| b.is_ascii_digit()));
+        if is_ticket {
+            tickets.push(entry.path());
+        }
+    }
+    Ok(tickets)
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_ticket_order() {
+        let dir = std::env::temp_dir().join(format!("bellman-queue-{}", std::process::id()));
+        let first = Ticket::wait(&dir).unwrap();
+        assert!(!is_empty(&dir).unwrap());
+
+        // Tickets are granted in the order they were drawn, the first one is still held.
+        let (sender, receiver) = mpsc::channel();
+        let waiters = (0..3)
+            .map(|i| {
+                let (dir, sender) = (dir.clone(), sender.clone());
+                let waiter = thread::spawn(move || {
+                    let ticket = Ticket::wait(&dir).unwrap();
+                    sender.send(i).unwrap();
+                    thread::sleep(Duration::from_millis(20));
+                    drop(ticket);
+                });
+                // Gives the waiter time to draw its ticket.
+                thread::sleep(Duration::from_millis(100));
+                waiter
+            })
+            .collect::<Vec<_>>();
+        assert!(receiver.try_recv().is_err());
+
+        // Gives up at the deadline without a trace in the queue.
+        let deadline = Instant::now() + Duration::from_millis(50);
+        assert!(Ticket::wait_until(&dir, Some(deadline)).unwrap().is_none());
+        assert_eq!(tickets(&dir).unwrap().len(), 4);
+
+        drop(first);
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert!(is_empty(&dir).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,6 @@
+use crate::bls::Engine;
 use ff::PrimeField;
 use itertools::join;
-use paired::Engine;
 
 // Instead of having a very large OpenCL program written for a specific curve, with a lot of
 // rudandant codes (As OpenCL doesn't have generic types or templates), this module will dynamically
@@ -66,9 +66,14 @@ where
         join(vec![0u32; limbs], ", ")
     );
     let inv_def = format!("#define {}_INV {}", name, inv);
+    let spare_bits_def = format!(
+        "#define {}_SPARE_BITS {}",
+        name,
+        limbs * 64 - F::NUM_BITS as usize
+    );
     return format!(
-        "{}\n{}\n{}\n{}\n{}",
-        limbs_def, one_def, p_def, zero_def, inv_def
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        limbs_def, one_def, p_def, zero_def, inv_def, spare_bits_def
     );
 }
 
@@ -116,13 +121,23 @@ fn multiexp(point: &str, exp: &str) -> String {
         .replace("EXPONENT", exp);
 }
 
-// WARNING: This function works only with Short Weierstrass Jacobian curves with Fq2 extension field.
+// WARNING: This function works only with Short Weierstrass Jacobian curves with `a = 0` and an
+// Fq2 extension field where u^2 + 1 = 0, like BLS12-381.
 pub fn kernel<E>() -> String
+where
+    E: Engine,
+{
+    kernel_with::<E>("")
+}
+
+/// The source of all kernels of the crate followed by `extension`, which can use the types and
+/// functions of the fields and curves of `E`, like `Fr_mul` or `G1_add`.
+pub fn kernel_with<E>(extension: &str) -> String
 where
     E: Engine,
 {
     return String::from(format!(
-        "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
+        "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
         DEFS_SRC,
         field::<E::Fr>("Fr"),
         fft("Fr"),
@@ -132,6 +147,7 @@ where
         multiexp("G1", "Exp"),
         field2("Fq2", "Fq"),
         ec("Fq2", "G2"),
-        multiexp("G2", "Exp")
+        multiexp("G2", "Exp"),
+        extension
     ));
 }
@@ -1,10 +1,14 @@
+use crate::gpu::config::gpu_config;
+use crate::gpu::device::{Backend, DeviceInfo};
 use crate::gpu::error::{GPUError, GPUResult};
+use crate::gpu::queue::{self, Ticket};
 use ocl::{Device, Platform};
 
 use fs2::FileExt;
-use log::info;
-use std::collections::HashMap;
+use log::{info, warn};
 use std::fs::File;
+use std::path::Path;
+use std::time::{Duration, Instant};
 use std::{env, io};
 
 pub const GPU_NVIDIA_PLATFORM_NAME: &str = "NVIDIA CUDA";
@@ -12,9 +16,7 @@ pub const GPU_NVIDIA_PLATFORM_NAME: &str = "NVIDIA CUDA";
 
 pub fn get_devices(platform_name: &str) -> GPUResult<Vec<Device>> {
     if env::var("BELLMAN_NO_GPU").is_ok() {
-        return Err(GPUError {
-            msg: "GPU accelerator is disabled!".to_string(),
-        });
+        return Err(GPUError::Disabled);
     }
 
     let platform = Platform::list()?.into_iter().find(|&p| match p.name() {
@@ -23,70 +25,192 @@ pub fn get_devices(platform_name: &str) -> GPUResult<Vec<Device>> {
     });
     match platform {
         Some(p) => Ok(Device::list_all(p)?),
-        None => Err(GPUError {
-            msg: "GPU platform not found!".to_string(),
-        }),
+        None => Err(GPUError::NoDevice("GPU platform not found!".to_string())),
     }
 }
 
-lazy_static::lazy_static! {
-    static ref CORE_COUNTS: HashMap<String, usize> = {
-        let mut core_counts : HashMap<String, usize> = vec![
-            ("GeForce RTX 2080 Ti".to_string(), 4352),
-            ("GeForce RTX 2080 SUPER".to_string(), 3072),
-            ("GeForce RTX 2080".to_string(), 2944),
-            ("GeForce GTX 1080 Ti".to_string(), 3584),
-            ("GeForce GTX 1080".to_string(), 2560),
-            ("GeForce GTX 1060".to_string(), 1280),
-        ].into_iter().collect();
-
-        match env::var("BELLMAN_CUSTOM_GPU").and_then(|var| {
-            for card in var.split(",") {
-                let splitted = card.split(":").collect::<Vec<_>>();
-                if splitted.len() != 2 { panic!("Invalid BELLMAN_CUSTOM_GPU!"); }
-                let name = splitted[0].trim().to_string();
-                let cores : usize = splitted[1].trim().parse().expect("Invalid BELLMAN_CUSTOM_GPU!");
-                info!("Adding \"{}\" to GPU list with {} CUDA cores.", name, cores);
-                core_counts.insert(name, cores);
-            }
-            Ok(())
-        }) { Err(_) => { }, Ok(_) => { } }
+/// The GPUs of all vendors on the machine, none if `BELLMAN_NO_GPU` is set.
+/// Devices that can't be queried are skipped.
+pub fn devices() -> Vec<DeviceInfo> {
+    if env::var("BELLMAN_NO_GPU").is_ok() {
+        return vec![];
+    }
 
-        core_counts
+    let platforms = match Platform::list() {
+        Ok(platforms) => platforms,
+        Err(e) => {
+            warn!("Cannot list the OpenCL platforms: {}", e);
+            return vec![];
+        }
     };
+
+    let mut devices = vec![];
+    for platform in platforms {
+        for device in Device::list_all(platform).unwrap_or_default() {
+            match device_info(device) {
+                Ok(info) => devices.push(info),
+                Err(e) => warn!("Cannot query a GPU: {}", e),
+            }
+        }
+    }
+    devices
+}
+
+/// Describes an OpenCL device.
+pub fn device_info(d: Device) -> GPUResult<DeviceInfo> {
+    Ok(DeviceInfo {
+        name: d.name()?,
+        vendor: d.vendor()?,
+        memory: get_memory(d)?,
+        compute_units: get_compute_units(d)?,
+        bus_id: get_bus_id(d),
+        backend: Backend::OpenCl,
+    })
+}
+
+/// The PCI bus of the device. Only NVIDIA drivers tell it, through
+/// `CL_DEVICE_PCI_BUS_ID_NV`.
+pub fn get_bus_id(d: Device) -> Option<u32> {
+    const CL_DEVICE_PCI_BUS_ID_NV: u32 = 0x4008;
+    let raw = d.info_raw(CL_DEVICE_PCI_BUS_ID_NV).ok()?;
+    if raw.len() != 4 {
+        return None;
+    }
+    Some(u32::from_ne_bytes([raw[0], raw[1], raw[2], raw[3]]))
 }
 
+/// The number of cores of the device. Devices that aren't in the table of
+/// known GPUs are assumed to have [`CORES_PER_COMPUTE_UNIT`] cores per compute
+/// unit, which may leave some of their cores idle.
 pub fn get_core_count(d: Device) -> GPUResult<usize> {
-    match CORE_COUNTS.get(&d.name()?[..]) {
-        Some(&cores) => Ok(cores),
-        None => Err(GPUError {
-            msg: "Device unknown!".to_string(),
-        }),
+    let name = d.name()?;
+    match gpu_config(&name) {
+        Some(config) => Ok(config.cores),
+        None => {
+            let cores = get_compute_units(d)? as usize * CORES_PER_COMPUTE_UNIT;
+            info!(
+                "Unknown GPU \"{}\", assuming {} cores from its compute units.",
+                name, cores
+            );
+            Ok(cores)
+        }
+    }
+}
+
+/// The cores of a streaming multiprocessor of NVIDIA GPUs since Volta.
+pub const CORES_PER_COMPUTE_UNIT: usize = 64;
+
+pub fn get_compute_units(d: Device) -> GPUResult<u32> {
+    match d.info(ocl::enums::DeviceInfo::MaxComputeUnits)? {
+        ocl::enums::DeviceInfoResult::MaxComputeUnits(units) => Ok(units),
+        _ => Err(GPUError::Driver(
+            "Cannot extract GPU compute units!".to_string(),
+        )),
+    }
+}
+
+/// The largest number of work items of a work group on the device.
+pub fn get_max_work_group_size(d: Device) -> GPUResult<usize> {
+    match d.info(ocl::enums::DeviceInfo::MaxWorkGroupSize)? {
+        ocl::enums::DeviceInfoResult::MaxWorkGroupSize(sz) => Ok(sz),
+        _ => Err(GPUError::Driver(
+            "Cannot extract GPU work group size!".to_string(),
+        )),
+    }
+}
+
+/// The local memory of a work group on the device, in bytes.
+pub fn get_local_memory(d: Device) -> GPUResult<u64> {
+    match d.info(ocl::enums::DeviceInfo::LocalMemSize)? {
+        ocl::enums::DeviceInfoResult::LocalMemSize(sz) => Ok(sz),
+        _ => Err(GPUError::Driver(
+            "Cannot extract GPU local memory!".to_string(),
+        )),
     }
 }
 
+/// The number of bases a multiexp kernel processes at once on the device, if
+/// it's configured.
+pub fn get_chunk_size(d: Device) -> GPUResult<Option<usize>> {
+    Ok(gpu_config(&d.name()?).and_then(|config| config.chunk_size))
+}
+
 pub fn get_memory(d: Device) -> GPUResult<u64> {
     match d.info(ocl::enums::DeviceInfo::GlobalMemSize)? {
         ocl::enums::DeviceInfoResult::GlobalMemSize(sz) => Ok(sz),
-        _ => Err(GPUError {
-            msg: "Cannot extract GPU memory!".to_string(),
-        }),
+        _ => Err(GPUError::Driver("Cannot extract GPU memory!".to_string())),
     }
 }
 
 #[derive(Debug)]
-pub struct LockedFile(File);
+pub struct LockedFile {
+    _file: File,
+    // Dropped after the lock file is released, so the next in the queue finds it free.
+    _ticket: Option<Ticket>,
+}
 
 pub const LOCK_NAME: &str = "/tmp/bellman.lock";
+/// The directory of the queue of the provers waiting for the GPU.
+pub const QUEUE_NAME: &str = "/tmp/bellman.lock.queue";
 
+/// Takes the GPU lock, after the provers that asked for it earlier.
 pub fn lock() -> io::Result<LockedFile> {
+    lock_until(None).map(|lock| lock.expect("waited without a deadline"))
+}
+
+/// Takes the GPU lock like [`lock`], unless that takes longer than `timeout`.
+pub fn lock_with_timeout(timeout: Duration) -> io::Result<Option<LockedFile>> {
+    lock_until(Some(Instant::now() + timeout))
+}
+
+fn lock_until(deadline: Option<Instant>) -> io::Result<Option<LockedFile>> {
+    let started = Instant::now();
+    let ticket = match Ticket::wait_until(Path::new(QUEUE_NAME), deadline)? {
+        Some(ticket) => ticket,
+        None => {
+            crate::metrics::gpu_lock_waited(started.elapsed());
+            return Ok(None);
+        }
+    };
+
     info!("Creating GPU lock file");
     let file = File::create(LOCK_NAME)?;
-
-    file.lock_exclusive()?;
+    let locked = queue::lock_until(&file, true, deadline)?;
+    crate::metrics::gpu_lock_waited(started.elapsed());
+    if !locked {
+        return Ok(None);
+    }
 
     info!("GPU lock file acquired");
-    Ok(LockedFile(file))
+    Ok(Some(LockedFile {
+        _file: file,
+        _ticket: Some(ticket),
+    }))
+}
+
+/// Takes the GPU lock if nobody else holds it or waits for it, without waiting.
+pub fn try_lock() -> io::Result<Option<LockedFile>> {
+    if !queue::is_empty(Path::new(QUEUE_NAME))? {
+        return Ok(None);
+    }
+
+    let file = File::create(LOCK_NAME)?;
+    match file.try_lock_exclusive() {
+        Ok(()) => {
+            info!("GPU lock file acquired");
+            Ok(Some(LockedFile {
+                _file: file,
+                _ticket: None,
+            }))
+        }
+        Err(ref e) if e.kind() == fs2::lock_contended_error().kind() => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether a prover waits for the GPU lock, i.e. one that doesn't give up if the GPU is busy.
+pub fn lock_is_awaited() -> io::Result<bool> {
+    Ok(!queue::is_empty(Path::new(QUEUE_NAME))?)
 }
 
 pub fn unlock(lock: LockedFile) {
@@ -0,0 +1,83 @@
+//! Conversions from and to the compressed canonical serialization of
+//! `ark-groth16` over `ark-bls12-381`, which encodes points like this crate
+//! does. Proofs are serialized identically, see [`Proof::write`].
+//!
+//! Proving keys aren't converted: their queries depend on how the constraints
+//! are reduced to a QAP, which differs between the two, so proofs from
+//! converted parameters wouldn't verify.
+
+use std::cmp;
+use std::io::{self, Read, Write};
+
+use crate::bls::Bls12;
+use crate::bls::Engine;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use groupy::{CurveAffine, EncodedPoint};
+
+use super::{
+    prepare_verifying_key, PreparedVerifyingKey, Proof, VerifyingKey, MAX_PREALLOCATED_POINTS,
+};
+
+fn read_point<G: CurveAffine, R: Read>(reader: &mut R) -> io::Result<G> {
+    let mut encoded = G::Compressed::empty();
+    reader.read_exact(encoded.as_mut())?;
+    encoded
+        .into_affine()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Writes `vk` like `VerifyingKey::serialize_compressed` of `ark-groth16`.
+pub fn write_verifying_key<W: Write>(vk: &VerifyingKey<Bls12>, mut writer: W) -> io::Result<()> {
+    writer.write_all(vk.alpha_g1.into_compressed().as_ref())?;
+    writer.write_all(vk.beta_g2.into_compressed().as_ref())?;
+    writer.write_all(vk.gamma_g2.into_compressed().as_ref())?;
+    writer.write_all(vk.delta_g2.into_compressed().as_ref())?;
+    writer.write_u64::<LittleEndian>(vk.ic.len() as u64)?;
+    for ic in &vk.ic {
+        writer.write_all(ic.into_compressed().as_ref())?;
+    }
+
+    Ok(())
+}
+
+/// Reads a verifying key written by `VerifyingKey::serialize_compressed` of
+/// `ark-groth16`. It lacks the elements that are only needed for proving, so
+/// the key is prepared for verification right away.
+pub fn read_verifying_key<R: Read>(mut reader: R) -> io::Result<PreparedVerifyingKey<Bls12>> {
+    let alpha_g1 = read_point(&mut reader)?;
+    let beta_g2 = read_point(&mut reader)?;
+    let gamma_g2 = read_point(&mut reader)?;
+    let delta_g2 = read_point(&mut reader)?;
+
+    let len = reader.read_u64::<LittleEndian>()? as usize;
+    if len == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "expected at least one IC point",
+        ));
+    }
+    let mut ic = Vec::with_capacity(cmp::min(len, MAX_PREALLOCATED_POINTS));
+    for _ in 0..len {
+        ic.push(read_point(&mut reader)?);
+    }
+
+    Ok(prepare_verifying_key(&VerifyingKey {
+        alpha_g1,
+        beta_g1: <Bls12 as Engine>::G1Affine::zero(),
+        beta_g2,
+        gamma_g2,
+        delta_g1: <Bls12 as Engine>::G1Affine::zero(),
+        delta_g2,
+        ic,
+    }))
+}
+
+/// Writes `proof` like `Proof::serialize_compressed` of `ark-groth16`.
+pub fn write_proof<W: Write>(proof: &Proof<Bls12>, writer: W) -> io::Result<()> {
+    proof.write(writer)
+}
+
+/// Reads a proof written by `Proof::serialize_compressed` of `ark-groth16`.
+pub fn read_proof<R: Read>(reader: R) -> io::Result<Proof<Bls12>> {
+    Proof::read(reader)
+}
@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use groupy::CurveAffine;
+
+use super::{ParameterSource, Parameters, VerifyingKey};
+use crate::bls::Engine;
+use crate::multicore::Worker;
+use crate::multiexp::{FixedBaseTables, SourceBuilder};
+use crate::SynthesisError;
+
+/// Parameters with precomputed multiples of all of their queries, for
+/// processes that create many proofs with the same parameters. The CPU
+/// multiexps of a proof then take a single addition per base and window,
+/// instead of sorting the bases into buckets again for every proof. Proofs
+/// on the GPU use the bases as they are.
+///
+/// The tables take `⌈255 / window⌉·(2^window - 1)` points for every point of
+/// the parameters, e.g. 960 of them for a window of four bits, so they are
+/// only worth it for small to medium circuits.
+pub struct CachedParameters<E: Engine> {
+    vk: VerifyingKey<E>,
+    h: CachedBases<E::G1Affine>,
+    l: CachedBases<E::G1Affine>,
+    a: CachedBases<E::G1Affine>,
+    b_g1: CachedBases<E::G1Affine>,
+    b_g2: CachedBases<E::G2Affine>,
+}
+
+impl<E: Engine> CachedParameters<E> {
+    /// Precomputes the multiples of the queries of `params` in windows of
+    /// `window` bits.
+    pub fn new(params: &Parameters<E>, window: u32) -> Self {
+        let worker = Worker::new();
+
+        CachedParameters {
+            vk: params.vk.clone(),
+            h: CachedBases::build(&worker, params.h.clone(), window),
+            l: CachedBases::build(&worker, params.l.clone(), window),
+            a: CachedBases::build(&worker, params.a.clone(), window),
+            b_g1: CachedBases::build(&worker, params.b_g1.clone(), window),
+            b_g2: CachedBases::build(&worker, params.b_g2.clone(), window),
+        }
+    }
+
+    pub fn vk(&self) -> &VerifyingKey<E> {
+        &self.vk
+    }
+}
+
+/// A query of [`CachedParameters`], which builds sources of its bases from
+/// `start` on.
+pub struct CachedBases<G: CurveAffine> {
+    bases: Arc<Vec<G>>,
+    tables: Arc<FixedBaseTables<G>>,
+    start: usize,
+}
+
+impl<G: CurveAffine> Clone for CachedBases<G> {
+    fn clone(&self) -> Self {
+        CachedBases {
+            bases: self.bases.clone(),
+            tables: self.tables.clone(),
+            start: self.start,
+        }
+    }
+}
+
+impl<G: CurveAffine> CachedBases<G> {
+    fn build(worker: &Worker, bases: Arc<Vec<G>>, window: u32) -> Self {
+        let tables = Arc::new(FixedBaseTables::new_parallel(worker, &bases, window));
+        CachedBases {
+            bases,
+            tables,
+            start: 0,
+        }
+    }
+
+    fn starting_at(&self, start: usize) -> Self {
+        CachedBases {
+            start,
+            ..self.clone()
+        }
+    }
+}
+
+impl<G: CurveAffine> SourceBuilder<G> for CachedBases<G> {
+    type Source = (Arc<Vec<G>>, usize);
+
+    fn new(self) -> (Arc<Vec<G>>, usize) {
+        (self.bases, self.start)
+    }
+
+    fn get(self) -> Result<(Arc<Vec<G>>, usize), SynthesisError> {
+        Ok((self.bases, self.start))
+    }
+
+    fn cheap_negation(&self) -> bool {
+        true
+    }
+
+    fn in_memory(&self) -> bool {
+        true
+    }
+
+    fn fixed_base_tables(&self) -> Option<(Arc<FixedBaseTables<G>>, usize)> {
+        Some((self.tables.clone(), self.start))
+    }
+}
+
+impl<'a, E: Engine> ParameterSource<E> for &'a CachedParameters<E> {
+    type G1Builder = CachedBases<E::G1Affine>;
+    type G2Builder = CachedBases<E::G2Affine>;
+
+    fn get_vk(&mut self, _: usize) -> Result<VerifyingKey<E>, SynthesisError> {
+        Ok(self.vk.clone())
+    }
+
+    fn get_h(&mut self, _: usize) -> Result<Self::G1Builder, SynthesisError> {
+        Ok(self.h.clone())
+    }
+
+    fn get_l(&mut self, _: usize) -> Result<Self::G1Builder, SynthesisError> {
+        Ok(self.l.clone())
+    }
+
+    fn get_a(
+        &mut self,
+        num_inputs: usize,
+        _: usize,
+    ) -> Result<(Self::G1Builder, Self::G1Builder), SynthesisError> {
+        Ok((self.a.clone(), self.a.starting_at(num_inputs)))
+    }
+
+    fn get_b_g1(
+        &mut self,
+        num_inputs: usize,
+        _: usize,
+    ) -> Result<(Self::G1Builder, Self::G1Builder), SynthesisError> {
+        Ok((self.b_g1.clone(), self.b_g1.starting_at(num_inputs)))
+    }
+
+    fn get_b_g2(
+        &mut self,
+        num_inputs: usize,
+        _: usize,
+    ) -> Result<(Self::G2Builder, Self::G2Builder), SynthesisError> {
+        Ok((self.b_g2.clone(), self.b_g2.starting_at(num_inputs)))
+    }
+}
@@ -0,0 +1,366 @@
+//! The versioned container format of [`Parameters`]. It starts with a header:
+//!
+//! - the magic bytes `BPPARAMS` and the format version as big-endian `u32`,
+//! - an identifier of the curve, the BLAKE2s hash of the generators,
+//! - the number of IC points of the verifying key and of the points of `h`,
+//!   `l`, `a`, `b_g1` and `b_g2`, as big-endian `u32`.
+//!
+//! It's followed by the sections: the verifying key like
+//! [`VerifyingKey::write`] and then the points of every query, uncompressed in
+//! version 1 and compressed in version 2. The header and every section end with
+//! the BLAKE2s hash of their bytes.
+//!
+//! Compressed points take half the space, but decompressing them takes a
+//! square root each. Points are encoded and decoded in chunks, spread over the
+//! threads of a [`Worker`], so that large parameters are read and written as
+//! fast as the storage allows.
+
+use std::cmp;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use crate::bls::Engine;
+use blake2s_simd::State;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use groupy::{CurveAffine, EncodedPoint};
+
+use super::{decode_point, Parameters, VerifyingKey};
+use crate::multicore::Worker;
+
+pub(super) const MAGIC: [u8; 8] = *b"BPPARAMS";
+const VERSION: u32 = 1;
+const VERSION_COMPRESSED: u32 = 2;
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+fn curve_id<E: Engine>() -> [u8; 32] {
+    let mut state = State::new();
+    state.update(E::G1Affine::one().into_uncompressed().as_ref());
+    state.update(E::G2Affine::one().into_uncompressed().as_ref());
+
+    let mut id = [0; 32];
+    id.copy_from_slice(state.finalize().as_bytes());
+    id
+}
+
+/// Hashes everything written since the end of the last section.
+struct HashWriter<W> {
+    writer: W,
+    state: State,
+}
+
+impl<W: Write> HashWriter<W> {
+    fn end_section(&mut self) -> io::Result<()> {
+        let hash = mem::replace(&mut self.state, State::new()).finalize();
+        self.writer.write_all(hash.as_bytes())
+    }
+}
+
+impl<W: Write> Write for HashWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        self.state.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Hashes everything read since the end of the last section.
+struct HashReader<R> {
+    reader: R,
+    state: State,
+}
+
+impl<R: Read> HashReader<R> {
+    fn end_section(&mut self, section: &str) -> io::Result<()> {
+        let hash = mem::replace(&mut self.state, State::new()).finalize();
+        let mut expected = [0; 32];
+        self.reader.read_exact(&mut expected)?;
+
+        if hash.as_bytes()[..] == expected[..] {
+            Ok(())
+        } else {
+            Err(invalid(format!(
+                "checksum mismatch in the {} section",
+                section
+            )))
+        }
+    }
+}
+
+impl<R: Read> Read for HashReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.state.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+/// The most points that are encoded or decoded at once. The bytes of a chunk
+/// are written or read with a single call.
+const CHUNK_POINTS: usize = 1 << 18;
+
+fn write_points<G: CurveAffine, W: Write>(
+    writer: &mut HashWriter<W>,
+    points: &[G],
+    compressed: bool,
+    worker: &Worker,
+) -> io::Result<()> {
+    if compressed {
+        encode_points::<G::Compressed, _>(writer, points, worker)?;
+    } else {
+        encode_points::<G::Uncompressed, _>(writer, points, worker)?;
+    }
+    writer.end_section()
+}
+
+/// Encodes the points as `P` in parallel on `worker` and writes them.
+fn encode_points<P: EncodedPoint, W: Write>(
+    writer: &mut W,
+    points: &[P::Affine],
+    worker: &Worker,
+) -> io::Result<()> {
+    let size = P::size();
+    let mut bytes = vec![];
+    for points in points.chunks(CHUNK_POINTS) {
+        bytes.resize(points.len() * size, 0);
+        worker.scope(points.len(), |scope, chunk| {
+            for (points, bytes) in points.chunks(chunk).zip(bytes.chunks_mut(chunk * size)) {
+                scope.spawn(move |_| {
+                    for (point, bytes) in points.iter().zip(bytes.chunks_mut(size)) {
+                        bytes.copy_from_slice(P::from_affine(*point).as_ref());
+                    }
+                });
+            }
+        });
+        writer.write_all(&bytes)?;
+    }
+
+    Ok(())
+}
+
+/// Reads `len` points encoded as `P` and decodes them in parallel on `worker`,
+/// like `read_points`.
+pub(super) fn read_points_parallel<P: EncodedPoint, R: Read>(
+    reader: &mut R,
+    len: usize,
+    checked: bool,
+    worker: &Worker,
+) -> io::Result<Vec<P::Affine>> {
+    let size = P::size();
+    // Grows with what is read rather than with `len`, which comes from the
+    // input.
+    let mut points = vec![];
+    let mut bytes = vec![];
+    while points.len() < len {
+        let n = cmp::min(len - points.len(), CHUNK_POINTS);
+        bytes.clear();
+        reader
+            .by_ref()
+            .take((n * size) as u64)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() != n * size {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let start = points.len();
+        points.resize(start + n, P::Affine::zero());
+        decode_points::<P>(&bytes, &mut points[start..], checked, worker)?;
+    }
+
+    Ok(points)
+}
+
+/// Decodes the points encoded as `P` in `bytes` in parallel.
+fn decode_points<P: EncodedPoint>(
+    bytes: &[u8],
+    points: &mut [P::Affine],
+    checked: bool,
+    worker: &Worker,
+) -> io::Result<()> {
+    let size = P::size();
+    let error = Mutex::new(None);
+    worker.scope(points.len(), |scope, chunk| {
+        for (points, bytes) in points.chunks_mut(chunk).zip(bytes.chunks(chunk * size)) {
+            let error = &error;
+            scope.spawn(move |_| {
+                for (point, bytes) in points.iter_mut().zip(bytes.chunks(size)) {
+                    let mut repr = P::empty();
+                    repr.as_mut().copy_from_slice(bytes);
+                    match decode_point(&repr, checked) {
+                        Ok(decoded) => *point = decoded,
+                        Err(e) => {
+                            *error.lock().unwrap() = Some(e);
+                            return;
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    match error.into_inner().unwrap() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Writes the parameters, the points are encoded on `worker`.
+pub(super) fn write<E: Engine, W: Write>(
+    params: &Parameters<E>,
+    writer: W,
+    compressed: bool,
+    worker: &Worker,
+) -> io::Result<()> {
+    let mut writer = HashWriter {
+        writer,
+        state: State::new(),
+    };
+
+    writer.write_all(&MAGIC)?;
+    writer.write_u32::<BigEndian>(if compressed {
+        VERSION_COMPRESSED
+    } else {
+        VERSION
+    })?;
+    writer.write_all(&curve_id::<E>())?;
+    for len in &[
+        params.vk.ic.len(),
+        params.h.len(),
+        params.l.len(),
+        params.a.len(),
+        params.b_g1.len(),
+        params.b_g2.len(),
+    ] {
+        writer.write_u32::<BigEndian>(*len as u32)?;
+    }
+    writer.end_section()?;
+
+    params.vk.write(&mut writer)?;
+    writer.end_section()?;
+
+    write_points(&mut writer, &params.h, compressed, worker)?;
+    write_points(&mut writer, &params.l, compressed, worker)?;
+    write_points(&mut writer, &params.a, compressed, worker)?;
+    write_points(&mut writer, &params.b_g1, compressed, worker)?;
+    write_points(&mut writer, &params.b_g2, compressed, worker)?;
+
+    Ok(())
+}
+
+/// The header and the verifying key of a parameter file.
+pub(super) struct Header<E: Engine> {
+    pub(super) vk: VerifyingKey<E>,
+    pub(super) compressed: bool,
+    /// The number of points of `h`, `l`, `a`, `b_g1` and `b_g2`.
+    pub(super) lens: [usize; 5],
+}
+
+/// The names of the sections of the queries, in the order of the file.
+pub(super) const QUERIES: [&str; 5] = ["h", "l", "a", "b_g1", "b_g2"];
+
+/// Reads the header and the verifying key of parameters whose magic bytes
+/// have been read already.
+pub(super) fn read_header<E: Engine, R: Read>(reader: R) -> io::Result<Header<E>> {
+    let mut reader = HashReader {
+        reader,
+        state: State::new(),
+    };
+    reader.state.update(&MAGIC);
+
+    let compressed = match reader.read_u32::<BigEndian>()? {
+        VERSION => false,
+        VERSION_COMPRESSED => true,
+        version => {
+            return Err(invalid(format!(
+                "unsupported parameter format version {}",
+                version
+            )))
+        }
+    };
+
+    let mut curve = [0; 32];
+    reader.read_exact(&mut curve)?;
+    if curve != curve_id::<E>() {
+        return Err(invalid("the parameters are for a different curve"));
+    }
+
+    let mut lens = [0; 6];
+    for len in lens.iter_mut() {
+        *len = reader.read_u32::<BigEndian>()? as usize;
+    }
+    reader.end_section("header")?;
+
+    let vk = VerifyingKey::read(&mut reader)?;
+    reader.end_section("verifying key")?;
+    if vk.ic.len() != lens[0] {
+        return Err(invalid("the verifying key doesn't match the header"));
+    }
+
+    Ok(Header {
+        vk,
+        compressed,
+        lens: [lens[1], lens[2], lens[3], lens[4], lens[5]],
+    })
+}
+
+/// Reads the section of the `index`th query, which has `len` points. The
+/// points are decoded on `worker`.
+pub(super) fn read_query<G: CurveAffine, R: Read>(
+    reader: R,
+    index: usize,
+    len: usize,
+    checked: bool,
+    compressed: bool,
+    worker: &Worker,
+) -> io::Result<Arc<Vec<G>>> {
+    let mut reader = HashReader {
+        reader,
+        state: State::new(),
+    };
+    let points = if compressed {
+        read_points_parallel::<G::Compressed, _>(&mut reader, len, checked, worker)?
+    } else {
+        read_points_parallel::<G::Uncompressed, _>(&mut reader, len, checked, worker)?
+    };
+    reader.end_section(QUERIES[index])?;
+
+    Ok(Arc::new(points))
+}
+
+/// The size of the section of a query with `len` points, with its checksum.
+pub(super) fn query_size<G: CurveAffine>(len: usize, compressed: bool) -> u64 {
+    let size = if compressed {
+        G::Compressed::size()
+    } else {
+        G::Uncompressed::size()
+    };
+
+    (len as u64) * (size as u64) + 32
+}
+
+/// Reads parameters whose magic bytes have been read already. The points are
+/// decoded on `worker`.
+pub(super) fn read<E: Engine, R: Read>(
+    mut reader: R,
+    checked: bool,
+    worker: &Worker,
+) -> io::Result<Parameters<E>> {
+    let header = read_header::<E, _>(&mut reader)?;
+    let compressed = header.compressed;
+
+    Ok(Parameters {
+        vk: header.vk,
+        h: read_query(&mut reader, 0, header.lens[0], checked, compressed, worker)?,
+        l: read_query(&mut reader, 1, header.lens[1], checked, compressed, worker)?,
+        a: read_query(&mut reader, 2, header.lens[2], checked, compressed, worker)?,
+        b_g1: read_query(&mut reader, 3, header.lens[3], checked, compressed, worker)?,
+        b_g2: read_query(&mut reader, 4, header.lens[4], checked, compressed, worker)?,
+    })
+}
@@ -0,0 +1,41 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+/// The largest frame that is accepted, larger ones are rejected before
+/// anything is allocated for them.
+pub const MAX_FRAME_LEN: usize = 1 << 30;
+
+/// Writes a frame: its length as a big-endian `u32`, then its bytes.
+pub fn write_frame<W: Write>(writer: &mut W, frame: &[u8]) -> io::Result<()> {
+    if frame.len() > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "frame too large",
+        ));
+    }
+
+    writer.write_u32::<BigEndian>(frame.len() as u32)?;
+    writer.write_all(frame)?;
+    writer.flush()
+}
+
+/// Reads the next frame, `None` if the peer closed the connection instead.
+pub fn read_frame<R: Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let len = match reader.read_u32::<BigEndian>() {
+        Ok(len) => len as usize,
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "frame too large",
+        ));
+    }
+
+    let mut frame = vec![0; len];
+    reader.read_exact(&mut frame)?;
+
+    Ok(Some(frame))
+}
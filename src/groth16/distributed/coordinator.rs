@@ -0,0 +1,170 @@
+use std::io;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+
+use ff::PrimeField;
+use groupy::{CurveAffine, CurveProjective};
+
+use super::{read_frame, write_frame, FftKind, Query, Request, Response};
+use crate::bls::Engine;
+use crate::multicore::{CancellationToken, Worker, WorkerFuture};
+use crate::multiexp::{dense_exponents, QueryDensity};
+use crate::SynthesisError;
+
+/// A connection to a worker running [`serve`](super::serve).
+pub struct RemoteWorker {
+    stream: TcpStream,
+}
+
+impl RemoteWorker {
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+
+        Ok(RemoteWorker { stream })
+    }
+
+    fn send<E: Engine>(&mut self, request: &Request<E>) -> io::Result<()> {
+        write_frame(&mut self.stream, &request.encode())
+    }
+
+    /// Waits for the response to the oldest request that wasn't answered yet.
+    /// A failed request is an error.
+    fn receive<E: Engine>(&mut self) -> Result<Response<E>, SynthesisError> {
+        let frame = read_frame(&mut self.stream)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the worker closed the connection",
+            )
+        })?;
+
+        match Response::decode(&frame)? {
+            Response::Error(message) => Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("the worker failed: {}", message),
+            )
+            .into()),
+            response => Ok(response),
+        }
+    }
+
+    /// Sends a request and waits for its response.
+    pub fn call<E: Engine>(&mut self, request: &Request<E>) -> Result<Response<E>, SynthesisError> {
+        self.send(request)?;
+        self.receive()
+    }
+
+    /// Transforms the values on the worker, they are padded to the next power
+    /// of two.
+    pub fn fft<E: Engine>(
+        &mut self,
+        kind: FftKind,
+        values: Vec<E::Fr>,
+    ) -> Result<Vec<E::Fr>, SynthesisError> {
+        match self.call::<E>(&Request::Fft { kind, values })? {
+            Response::Values(values) => Ok(values),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "expected values").into()),
+        }
+    }
+}
+
+/// The workers a proof is distributed over: every multiexp is split into a
+/// segment for each of them.
+#[derive(Clone)]
+pub struct Cluster {
+    workers: Arc<Vec<Mutex<RemoteWorker>>>,
+}
+
+impl Cluster {
+    /// Panics without any workers.
+    pub fn new(workers: Vec<RemoteWorker>) -> Self {
+        assert!(!workers.is_empty(), "a cluster needs at least one worker");
+
+        Cluster {
+            workers: Arc::new(workers.into_iter().map(Mutex::new).collect()),
+        }
+    }
+
+    pub fn connect<A: ToSocketAddrs>(addrs: &[A]) -> io::Result<Self> {
+        let workers = addrs
+            .iter()
+            .map(RemoteWorker::connect)
+            .collect::<io::Result<_>>()?;
+
+        Ok(Cluster::new(workers))
+    }
+
+    pub fn num_workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Computes a multiexp of a proof over the bases of `query` on the
+    /// workers. Like the GPU, they take the exponents of the dense bases only.
+    pub(crate) fn multiexp<E, G, Q, D>(
+        &self,
+        pool: &Worker,
+        query: Query,
+        density_map: D,
+        exponents: Arc<Vec<<E::Fr as PrimeField>::Repr>>,
+        cancel: &CancellationToken,
+    ) -> WorkerFuture<Result<G::Projective, SynthesisError>>
+    where
+        E: Engine,
+        G: CurveAffine,
+        for<'a> &'a Q: QueryDensity,
+        D: AsRef<Q>,
+    {
+        let exponents = dense_exponents(exponents, density_map.as_ref());
+        let workers = self.workers.clone();
+        let cancel = cancel.clone();
+
+        pool.compute(move || {
+            cancel.check()?;
+            let mut acc = G::Projective::zero();
+            if exponents.is_empty() {
+                return Ok(acc);
+            }
+
+            // All of the workers are locked up front and in order, so that
+            // concurrent multiexps neither deadlock nor take each other's
+            // responses.
+            let mut locked = workers
+                .iter()
+                .map(|worker| worker.lock().expect("a request panicked"))
+                .collect::<Vec<_>>();
+
+            let chunk = (exponents.len() + locked.len() - 1) / locked.len();
+            let sent = exponents
+                .chunks(chunk)
+                .zip(locked.iter_mut())
+                .enumerate()
+                .map(|(i, (exponents, worker))| {
+                    worker.send::<E>(&Request::Multiexp {
+                        query,
+                        start: i * chunk,
+                        exponents: exponents.to_vec(),
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            // Every response is read, even after an error, so that the
+            // connections stay in sync.
+            let mut error = None;
+            for (worker, sent) in locked.iter_mut().zip(sent) {
+                let point = sent
+                    .map_err(SynthesisError::from)
+                    .and_then(|()| worker.receive::<E>())
+                    .and_then(|response| Ok(response.point::<G>()?));
+                match point {
+                    Ok(point) => acc.add_assign(&point),
+                    Err(e) => error = error.or(Some(e)),
+                }
+            }
+
+            match error {
+                Some(e) => Err(e),
+                None => Ok(acc),
+            }
+        })
+    }
+}
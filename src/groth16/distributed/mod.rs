@@ -0,0 +1,309 @@
+//! Proving a single proof on several machines. A coordinator synthesizes the
+//! circuit and computes the quotient polynomial, then ships the assignments,
+//! split into segments, to remote workers, which compute the multiexps over
+//! their own copy of the parameters, typically on a GPU, and send back the
+//! partial results. The workers run [`serve`] on a TCP port, the coordinator
+//! connects to them with a [`Cluster`] and proves with
+//! [`create_proof_distributed`](super::create_proof_distributed).
+//!
+//! The protocol is a sequence of frames over a TCP stream: every frame is its
+//! length as a big-endian `u32` followed by a message, a [`Request`] from the
+//! coordinator or the [`Response`] of the worker to it, which are answered in
+//! order. Numbers are big-endian, scalars are encoded as big-endian
+//! representations and points uncompressed. Besides multiexps, workers
+//! compute whole FFTs, see [`RemoteWorker::fft`].
+//!
+//! The workers are trusted: nothing keeps them from returning wrong results,
+//! which only surface when the proof is verified, and the witness is sent to
+//! them in the clear.
+
+mod codec;
+mod coordinator;
+mod worker;
+
+pub use self::codec::{read_frame, write_frame, MAX_FRAME_LEN};
+pub use self::coordinator::{Cluster, RemoteWorker};
+pub use self::worker::{handle, serve};
+
+use std::io::{self, Read, Write};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use ff::{PrimeField, PrimeFieldRepr};
+use groupy::{CurveAffine, CurveProjective};
+
+use crate::bls::Engine;
+use crate::multiexp::{read_partial, write_partial};
+
+/// The query of the parameters a multiexp is over, with the arguments of the
+/// [`ParameterSource`](super::ParameterSource) method that returns it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Query {
+    H(usize),
+    L(usize),
+    A {
+        aux: bool,
+        num_inputs: usize,
+        num_aux: usize,
+    },
+    BG1 {
+        aux: bool,
+        num_inputs: usize,
+        num_aux: usize,
+    },
+    BG2 {
+        aux: bool,
+        num_inputs: usize,
+        num_aux: usize,
+    },
+}
+
+impl Query {
+    fn write<W: Write>(self, writer: &mut W) -> io::Result<()> {
+        let (tag, first, second) = match self {
+            Query::H(num_h) => (0, num_h, 0),
+            Query::L(num_l) => (1, num_l, 0),
+            Query::A {
+                aux,
+                num_inputs,
+                num_aux,
+            } => (2 + aux as u8, num_inputs, num_aux),
+            Query::BG1 {
+                aux,
+                num_inputs,
+                num_aux,
+            } => (4 + aux as u8, num_inputs, num_aux),
+            Query::BG2 {
+                aux,
+                num_inputs,
+                num_aux,
+            } => (6 + aux as u8, num_inputs, num_aux),
+        };
+        writer.write_u8(tag)?;
+        writer.write_u64::<BigEndian>(first as u64)?;
+        writer.write_u64::<BigEndian>(second as u64)
+    }
+
+    fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let tag = reader.read_u8()?;
+        let first = read_len(reader)?;
+        let second = read_len(reader)?;
+        let aux = tag % 2 == 1;
+
+        Ok(match tag {
+            0 => Query::H(first),
+            1 => Query::L(first),
+            2 | 3 => Query::A {
+                aux,
+                num_inputs: first,
+                num_aux: second,
+            },
+            4 | 5 => Query::BG1 {
+                aux,
+                num_inputs: first,
+                num_aux: second,
+            },
+            6 | 7 => Query::BG2 {
+                aux,
+                num_inputs: first,
+                num_aux: second,
+            },
+            _ => return Err(invalid_data("unknown query")),
+        })
+    }
+}
+
+/// The transform of an FFT computed remotely, named after the methods of
+/// [`EvaluationDomain`](crate::domain::EvaluationDomain).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FftKind {
+    Fft,
+    Ifft,
+    CosetFft,
+    IcosetFft,
+}
+
+/// A message from the coordinator to a worker.
+#[derive(Clone, Debug)]
+pub enum Request<E: Engine> {
+    /// The multiexp of the exponents with the bases of the query, starting
+    /// with the `start`th of them.
+    Multiexp {
+        query: Query,
+        start: usize,
+        exponents: Vec<<E::Fr as PrimeField>::Repr>,
+    },
+    /// A transform of the values, padded to the next power of two.
+    Fft { kind: FftKind, values: Vec<E::Fr> },
+}
+
+impl<E: Engine> Request<E> {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        match self {
+            Request::Multiexp {
+                query,
+                start,
+                exponents,
+            } => {
+                buf.push(1);
+                query.write(&mut buf).unwrap();
+                buf.write_u64::<BigEndian>(*start as u64).unwrap();
+                write_reprs(&mut buf, exponents.iter().cloned()).unwrap();
+            }
+            Request::Fft { kind, values } => {
+                buf.push(2);
+                buf.push(*kind as u8);
+                write_reprs(&mut buf, values.iter().map(|v| v.into_repr())).unwrap();
+            }
+        }
+        buf
+    }
+
+    pub fn decode(mut frame: &[u8]) -> io::Result<Self> {
+        let request = match frame.read_u8()? {
+            1 => Request::Multiexp {
+                query: Query::read(&mut frame)?,
+                start: read_len(&mut frame)?,
+                exponents: read_reprs::<E>(&mut frame)?,
+            },
+            2 => {
+                let kind = match frame.read_u8()? {
+                    0 => FftKind::Fft,
+                    1 => FftKind::Ifft,
+                    2 => FftKind::CosetFft,
+                    3 => FftKind::IcosetFft,
+                    _ => return Err(invalid_data("unknown transform")),
+                };
+                let values = read_reprs::<E>(&mut frame)?
+                    .into_iter()
+                    .map(|repr| E::Fr::from_repr(repr).map_err(invalid_data))
+                    .collect::<Result<_, _>>()?;
+                Request::Fft { kind, values }
+            }
+            _ => return Err(invalid_data("unknown request")),
+        };
+        check_consumed(frame)?;
+
+        Ok(request)
+    }
+}
+
+/// A message from a worker to the coordinator, in reply to a request.
+#[derive(Clone, Debug)]
+pub enum Response<E: Engine> {
+    /// The result of a multiexp, uncompressed, in G1 or G2 depending on the
+    /// query, see [`Response::point`].
+    Point(Vec<u8>),
+    /// The values of an FFT.
+    Values(Vec<E::Fr>),
+    /// The request failed.
+    Error(String),
+}
+
+impl<E: Engine> Response<E> {
+    pub fn from_point<G: CurveProjective>(point: G) -> Self {
+        let mut bytes = vec![];
+        write_partial(&point, &mut bytes).unwrap();
+        Response::Point(bytes)
+    }
+
+    /// Decodes the result of a multiexp, checking that it's in the subgroup.
+    pub fn point<G: CurveAffine>(&self) -> io::Result<G::Projective> {
+        match self {
+            Response::Point(bytes) => {
+                let mut reader = &bytes[..];
+                let point = read_partial::<G, _>(&mut reader)?;
+                check_consumed(reader)?;
+                Ok(point)
+            }
+            _ => Err(invalid_data("expected a point")),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        match self {
+            Response::Point(bytes) => {
+                buf.push(0x81);
+                buf.extend_from_slice(bytes);
+            }
+            Response::Values(values) => {
+                buf.push(0x82);
+                write_reprs(&mut buf, values.iter().map(|v| v.into_repr())).unwrap();
+            }
+            Response::Error(message) => {
+                buf.push(0xff);
+                buf.extend_from_slice(message.as_bytes());
+            }
+        }
+        buf
+    }
+
+    pub fn decode(mut frame: &[u8]) -> io::Result<Self> {
+        Ok(match frame.read_u8()? {
+            0x81 => Response::Point(frame.to_vec()),
+            0x82 => {
+                let values = read_reprs::<E>(&mut frame)?
+                    .into_iter()
+                    .map(|repr| E::Fr::from_repr(repr).map_err(invalid_data))
+                    .collect::<Result<_, _>>()?;
+                check_consumed(frame)?;
+                Response::Values(values)
+            }
+            0xff => Response::Error(String::from_utf8_lossy(frame).into_owned()),
+            _ => return Err(invalid_data("unknown response")),
+        })
+    }
+}
+
+fn write_reprs<R, W>(writer: &mut W, reprs: impl ExactSizeIterator<Item = R>) -> io::Result<()>
+where
+    R: PrimeFieldRepr,
+    W: Write,
+{
+    writer.write_u64::<BigEndian>(reprs.len() as u64)?;
+    for repr in reprs {
+        repr.write_be(&mut *writer)?;
+    }
+
+    Ok(())
+}
+
+fn read_reprs<E: Engine>(frame: &mut &[u8]) -> io::Result<Vec<<E::Fr as PrimeField>::Repr>> {
+    let len = read_len(frame)?;
+    // Checked against the rest of the frame before anything is allocated.
+    let size = <E::Fr as PrimeField>::Repr::default().as_ref().len() * 8;
+    if len > frame.len() / size {
+        return Err(invalid_data("truncated frame"));
+    }
+
+    let mut reprs = Vec::with_capacity(len);
+    for _ in 0..len {
+        let mut repr = <E::Fr as PrimeField>::Repr::default();
+        repr.read_be(&mut *frame)?;
+        reprs.push(repr);
+    }
+
+    Ok(reprs)
+}
+
+fn read_len<R: Read>(reader: &mut R) -> io::Result<usize> {
+    let len = reader.read_u64::<BigEndian>()?;
+    if len > usize::max_value() as u64 {
+        return Err(invalid_data("length too large"));
+    }
+
+    Ok(len as usize)
+}
+
+fn check_consumed(frame: &[u8]) -> io::Result<()> {
+    if frame.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid_data("trailing bytes"))
+    }
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
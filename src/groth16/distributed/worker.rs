@@ -0,0 +1,196 @@
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+
+use ff::{PrimeField, ScalarEngine};
+use groupy::CurveAffine;
+use log::{info, warn};
+
+use super::{read_frame, write_frame, FftKind, Query, Request, Response};
+use crate::bls::Engine;
+use crate::domain::{gpu_fft_supported, EvaluationDomain, Scalar};
+use crate::gpu;
+use crate::groth16::ParameterSource;
+use crate::multicore::Worker;
+use crate::multiexp::{gpu_multiexp_supported, multiexp, FullDensity, SourceBuilder};
+use crate::SynthesisError;
+
+/// Serves the coordinators connecting to `listener` one after another, until
+/// accepting a connection fails. `params` have to be the parameters the
+/// coordinators prove with, a failed connection is logged and dropped.
+pub fn serve<E, P>(listener: &TcpListener, params: P, pool: &Worker) -> io::Result<()>
+where
+    E: Engine,
+    P: ParameterSource<E> + Clone,
+{
+    for stream in listener.incoming() {
+        let stream = stream?;
+        stream.set_nodelay(true)?;
+        let peer = stream.peer_addr()?;
+
+        info!("serving coordinator {}", peer);
+        match handle(stream, params.clone(), pool) {
+            Ok(()) => info!("coordinator {} disconnected", peer),
+            Err(e) => warn!("connection to coordinator {} failed: {}", peer, e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Answers the requests of a single coordinator until it disconnects. A
+/// request that fails is answered with an error, only a broken stream ends
+/// the connection early.
+pub fn handle<E, P, S>(mut stream: S, mut params: P, pool: &Worker) -> io::Result<()>
+where
+    E: Engine,
+    P: ParameterSource<E>,
+    S: Read + Write,
+{
+    let mut multiexp_kern = gpu_multiexp_supported::<E>().ok();
+    let mut fft_kern = None;
+
+    while let Some(frame) = read_frame(&mut stream)? {
+        let response = match Request::<E>::decode(&frame) {
+            Ok(request) => respond(
+                request,
+                &mut params,
+                pool,
+                &mut multiexp_kern,
+                &mut fft_kern,
+            )
+            .unwrap_or_else(|e| Response::Error(e.to_string())),
+            Err(e) => Response::Error(e.to_string()),
+        };
+        write_frame(&mut stream, &response.encode())?;
+    }
+
+    Ok(())
+}
+
+/// The FFT kernel of a connection and the size of the domains it was created
+/// for, the kernel is `None` if the GPU isn't supported.
+type FftKernel<E> = Option<(u32, Option<gpu::FFTKernel<E>>)>;
+
+fn respond<E, P>(
+    request: Request<E>,
+    params: &mut P,
+    pool: &Worker,
+    kern: &mut Option<gpu::MultiexpKernel<E>>,
+    fft_kern: &mut FftKernel<E>,
+) -> Result<Response<E>, SynthesisError>
+where
+    E: Engine,
+    P: ParameterSource<E>,
+{
+    match request {
+        Request::Multiexp {
+            query,
+            start,
+            exponents,
+        } => {
+            let exponents = Arc::new(exponents);
+
+            Ok(match query {
+                Query::H(num_h) => {
+                    Response::from_point(run(pool, params.get_h(num_h)?, start, exponents, kern)?)
+                }
+                Query::L(num_l) => {
+                    Response::from_point(run(pool, params.get_l(num_l)?, start, exponents, kern)?)
+                }
+                Query::A {
+                    aux,
+                    num_inputs,
+                    num_aux,
+                } => {
+                    let bases = pick(params.get_a(num_inputs, num_aux)?, aux);
+                    Response::from_point(run(pool, bases, start, exponents, kern)?)
+                }
+                Query::BG1 {
+                    aux,
+                    num_inputs,
+                    num_aux,
+                } => {
+                    let bases = pick(params.get_b_g1(num_inputs, num_aux)?, aux);
+                    Response::from_point(run(pool, bases, start, exponents, kern)?)
+                }
+                Query::BG2 {
+                    aux,
+                    num_inputs,
+                    num_aux,
+                } => {
+                    let bases = pick(params.get_b_g2(num_inputs, num_aux)?, aux);
+                    Response::from_point(run(pool, bases, start, exponents, kern)?)
+                }
+            })
+        }
+        Request::Fft { kind, values } => {
+            let mut domain =
+                EvaluationDomain::<E, _>::from_coeffs(values.into_iter().map(Scalar).collect())?;
+            let log_d = domain.coeffs().len().trailing_zeros();
+            let fft_kern = fft_kernel(fft_kern, log_d);
+            match kind {
+                FftKind::Fft => domain.fft(pool, fft_kern)?,
+                FftKind::Ifft => domain.ifft(pool, fft_kern)?,
+                FftKind::CosetFft => domain.coset_fft(pool, fft_kern)?,
+                FftKind::IcosetFft => domain.icoset_fft(pool, fft_kern)?,
+            }
+
+            Ok(Response::Values(
+                domain.into_coeffs().into_iter().map(|s| s.0).collect(),
+            ))
+        }
+    }
+}
+
+/// The FFT kernel for domains of 2<sup>`log_d`</sup> elements. It's only
+/// created again for domains larger than the ones it was created for.
+fn fft_kernel<E: Engine>(
+    fft_kern: &mut FftKernel<E>,
+    log_d: u32,
+) -> &mut Option<gpu::FFTKernel<E>> {
+    let stale = match fft_kern {
+        Some((created, _)) => *created < log_d,
+        None => true,
+    };
+    if stale {
+        *fft_kern = Some((log_d, gpu_fft_supported::<E>(log_d).ok()));
+    }
+
+    &mut fft_kern.as_mut().expect("the kernel was just created").1
+}
+
+fn pick<S>((inputs, aux): (S, S), is_aux: bool) -> S {
+    if is_aux {
+        aux
+    } else {
+        inputs
+    }
+}
+
+/// Computes the multiexp of the exponents with the bases starting at `start`,
+/// on the GPU if there is one.
+fn run<G, S>(
+    pool: &Worker,
+    bases: S,
+    start: usize,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    kern: &mut Option<gpu::MultiexpKernel<G::Engine>>,
+) -> Result<G::Projective, SynthesisError>
+where
+    G: CurveAffine,
+    G::Engine: Engine,
+    S: SourceBuilder<G>,
+{
+    let (bases, skip) = bases.get()?;
+    let skip = skip.saturating_add(start);
+    if skip.saturating_add(exponents.len()) > bases.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "more exponents than bases, the parameters don't match",
+        )
+        .into());
+    }
+
+    multiexp(pool, (bases, skip), FullDensity, exponents, kern).wait()
+}
@@ -0,0 +1,155 @@
+//! String encodings of proofs and verifying keys: hex and base64 of the
+//! binary encoding of [`Proof::write`] and [`VerifyingKey::write`].
+
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use crate::bls::Engine;
+
+use super::{Proof, VerifyingKey};
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Encodes `bytes` as lowercase hex.
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        hex.push(DIGITS[(byte >> 4) as usize] as char);
+        hex.push(DIGITS[(byte & 0xf) as usize] as char);
+    }
+    hex
+}
+
+/// Decodes hex of either case, with an optional `0x` prefix.
+pub(crate) fn from_hex(hex: &str) -> io::Result<Vec<u8>> {
+    fn digit(c: u8) -> io::Result<u8> {
+        match c {
+            b'0'..=b'9' => Ok(c - b'0'),
+            b'a'..=b'f' => Ok(c - b'a' + 10),
+            b'A'..=b'F' => Ok(c - b'A' + 10),
+            _ => Err(invalid(format!("invalid hex digit {:?}", c as char))),
+        }
+    }
+
+    let hex = hex.trim();
+    let hex = if hex.starts_with("0x") {
+        &hex[2..]
+    } else {
+        hex
+    };
+    if hex.len() % 2 != 0 {
+        return Err(invalid("odd number of hex digits"));
+    }
+
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| Ok(digit(pair[0])? << 4 | digit(pair[1])?))
+        .collect()
+}
+
+/// Reads a value from `bytes`, which must not have any bytes left over.
+fn read_all<T>(mut bytes: &[u8], read: impl FnOnce(&mut &[u8]) -> io::Result<T>) -> io::Result<T> {
+    let value = read(&mut bytes)?;
+    if !bytes.is_empty() {
+        return Err(invalid(format!("{} trailing bytes", bytes.len())));
+    }
+
+    Ok(value)
+}
+
+impl<E: Engine> Proof<E> {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        self.write(&mut bytes)
+            .expect("writing to a vector never fails");
+        bytes
+    }
+
+    /// Encodes the proof like [`Proof::write`] as lowercase hex.
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.to_bytes())
+    }
+
+    /// Decodes a proof from hex, with an optional `0x` prefix.
+    pub fn from_hex(hex: &str) -> io::Result<Self> {
+        read_all(&from_hex(hex)?, |r| Self::read(r))
+    }
+
+    /// Encodes the proof like [`Proof::write`] as standard base64.
+    pub fn to_base64(&self) -> String {
+        base64::encode(&self.to_bytes())
+    }
+
+    /// Decodes a proof from standard base64.
+    pub fn from_base64(base64: &str) -> io::Result<Self> {
+        let bytes = base64::decode(base64.trim()).map_err(invalid)?;
+        read_all(&bytes, |r| Self::read(r))
+    }
+}
+
+/// Formats the proof as hex, see [`Proof::to_hex`].
+impl<E: Engine> fmt::Display for Proof<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+/// Parses a proof from hex, see [`Proof::from_hex`].
+impl<E: Engine> FromStr for Proof<E> {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        Self::from_hex(s)
+    }
+}
+
+impl<E: Engine> VerifyingKey<E> {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        self.write(&mut bytes)
+            .expect("writing to a vector never fails");
+        bytes
+    }
+
+    /// Encodes the key like [`VerifyingKey::write`] as lowercase hex.
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.to_bytes())
+    }
+
+    /// Decodes a key from hex, with an optional `0x` prefix.
+    pub fn from_hex(hex: &str) -> io::Result<Self> {
+        read_all(&from_hex(hex)?, |r| Self::read(r))
+    }
+
+    /// Encodes the key like [`VerifyingKey::write`] as standard base64.
+    pub fn to_base64(&self) -> String {
+        base64::encode(&self.to_bytes())
+    }
+
+    /// Decodes a key from standard base64.
+    pub fn from_base64(base64: &str) -> io::Result<Self> {
+        let bytes = base64::decode(base64.trim()).map_err(invalid)?;
+        read_all(&bytes, |r| Self::read(r))
+    }
+}
+
+/// Formats the key as hex, see [`VerifyingKey::to_hex`].
+impl<E: Engine> fmt::Display for VerifyingKey<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+/// Parses a key from hex, see [`VerifyingKey::from_hex`].
+impl<E: Engine> FromStr for VerifyingKey<E> {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        Self::from_hex(s)
+    }
+}
@@ -0,0 +1,124 @@
+use std::mem;
+
+use crate::bls::Engine;
+use ff::{PrimeField, ScalarEngine};
+
+use super::Parameters;
+
+/// The number of bases of each query that is multiexponentiated when proving.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiexpSizes {
+    pub h: usize,
+    pub l: usize,
+    pub a: usize,
+    pub b_g1: usize,
+    pub b_g2: usize,
+}
+
+/// Estimated resources needed for creating a single proof.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Estimate {
+    /// Main memory needed, including the parameters themselves.
+    pub ram_bytes: usize,
+    /// GPU memory needed to run the largest FFT or multiexp in one go.
+    pub vram_bytes: usize,
+    /// Size of the evaluation domain used for the FFTs.
+    pub fft_size: usize,
+    pub multiexp_sizes: MultiexpSizes,
+}
+
+/// Estimates the resources a proof with the given parameters takes, so that it
+/// can be decided whether a machine is able to run it before starting.
+pub fn estimate_resources<E: Engine>(params: &Parameters<E>) -> Estimate {
+    let num_inputs = params.vk.ic.len();
+    let num_aux = params.l.len();
+
+    let multiexp_sizes = MultiexpSizes {
+        h: params.h.len(),
+        l: params.l.len(),
+        a: params.a.len(),
+        b_g1: params.b_g1.len(),
+        b_g2: params.b_g2.len(),
+    };
+
+    let g1_size = mem::size_of::<E::G1Affine>();
+    let g2_size = mem::size_of::<E::G2Affine>();
+    let params_bytes =
+        (num_inputs + multiexp_sizes.h + multiexp_sizes.l + multiexp_sizes.a + multiexp_sizes.b_g1)
+            * g1_size
+            + multiexp_sizes.b_g2 * g2_size;
+
+    let mut estimate = estimate_prover::<E>(num_inputs, num_aux, params.h.len() + 1);
+    estimate.ram_bytes += params_bytes;
+    estimate.multiexp_sizes = multiexp_sizes;
+
+    estimate
+}
+
+/// Estimates the resources of a proof for a circuit of the given size, without
+/// having the parameters at hand. All queries are assumed to be fully dense.
+pub fn estimate_resources_for_size<E: Engine>(
+    num_inputs: usize,
+    num_aux: usize,
+    num_constraints: usize,
+) -> Estimate {
+    // Every input gets an additional constraint.
+    let fft_size = (num_constraints + num_inputs).next_power_of_two();
+    let num_vars = num_inputs + num_aux;
+
+    let multiexp_sizes = MultiexpSizes {
+        h: fft_size - 1,
+        l: num_aux,
+        a: num_vars,
+        b_g1: num_vars,
+        b_g2: num_vars,
+    };
+
+    let params_bytes = (num_inputs + fft_size - 1 + num_aux + 2 * num_vars)
+        * mem::size_of::<E::G1Affine>()
+        + num_vars * mem::size_of::<E::G2Affine>();
+
+    let mut estimate = estimate_prover::<E>(num_inputs, num_aux, fft_size);
+    estimate.ram_bytes += params_bytes;
+    estimate.multiexp_sizes = multiexp_sizes;
+
+    estimate
+}
+
+/// The memory the prover itself allocates, excluding the parameters.
+pub(super) fn estimate_prover<E: Engine>(
+    num_inputs: usize,
+    num_aux: usize,
+    fft_size: usize,
+) -> Estimate {
+    let fr_size = mem::size_of::<E::Fr>();
+    let repr_size = mem::size_of::<<<E as ScalarEngine>::Fr as PrimeField>::Repr>();
+    let num_vars = num_inputs + num_aux;
+
+    // The a, b and c evaluations are alive at the same time, the assignments are
+    // converted into their representations before the multiexps start.
+    let fft_bytes = 3 * fft_size * fr_size + num_vars * fr_size;
+    let multiexp_bytes = (fft_size - 1 + num_vars) * repr_size;
+    let ram_bytes = fft_bytes.max(multiexp_bytes + num_vars * fr_size);
+
+    // The GPU FFT keeps a source and a destination buffer, the multiexp kernel
+    // holds bases of both groups and the exponents.
+    let largest_multiexp = (fft_size - 1).max(num_vars);
+    let vram_bytes = (2 * fft_size * fr_size).max(
+        largest_multiexp
+            * (mem::size_of::<E::G1Affine>() + mem::size_of::<E::G2Affine>() + repr_size),
+    );
+
+    Estimate {
+        ram_bytes,
+        vram_bytes,
+        fft_size,
+        multiexp_sizes: MultiexpSizes {
+            h: 0,
+            l: 0,
+            a: 0,
+            b_g1: 0,
+            b_g2: 0,
+        },
+    }
+}
@@ -0,0 +1,175 @@
+//! Export of verifying keys and proofs for verification on EVM chains. The
+//! generated contract checks proofs with the BLS12-381 precompiles of
+//! [EIP-2537], which encode field elements as 64 big-endian bytes.
+//!
+//! [EIP-2537]: https://eips.ethereum.org/EIPS/eip-2537
+
+use crate::bls::{Bls12, Fr, G1Affine, G2Affine};
+use ff::{PrimeField, PrimeFieldRepr};
+use groupy::{CurveAffine, EncodedPoint};
+
+use super::encoding::to_hex as hex;
+use super::{Proof, VerifyingKey};
+
+/// The selector of `verifyProof(bytes,bytes,bytes,uint256[])`.
+const VERIFY_PROOF_SELECTOR: [u8; 4] = [0x05, 0x80, 0x72, 0xbd];
+
+/// Pads every field element of 48 bytes to 64 bytes.
+fn pad(elements: &[&[u8]]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(elements.len() * 64);
+    for element in elements {
+        bytes.extend_from_slice(&[0; 16]);
+        bytes.extend_from_slice(element);
+    }
+    bytes
+}
+
+/// Encodes a G1 point as `x || y`, the point at infinity as zeros.
+fn g1_bytes(point: &G1Affine) -> Vec<u8> {
+    if point.is_zero() {
+        return vec![0; 128];
+    }
+
+    let uncompressed = point.into_uncompressed();
+    let bytes = uncompressed.as_ref();
+    pad(&[&bytes[..48], &bytes[48..]])
+}
+
+/// Encodes a G2 point as `x.c0 || x.c1 || y.c0 || y.c1`, the point at infinity
+/// as zeros. The uncompressed encoding puts `c1` first.
+fn g2_bytes(point: &G2Affine) -> Vec<u8> {
+    if point.is_zero() {
+        return vec![0; 256];
+    }
+
+    let uncompressed = point.into_uncompressed();
+    let bytes = uncompressed.as_ref();
+    pad(&[&bytes[48..96], &bytes[..48], &bytes[144..], &bytes[96..144]])
+}
+
+fn neg_g2_bytes(point: &G2Affine) -> Vec<u8> {
+    let mut point = *point;
+    point.negate();
+    g2_bytes(&point)
+}
+
+fn repr_bytes<R: PrimeFieldRepr>(repr: R) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(32);
+    repr.write_be(&mut bytes)
+        .expect("writing to a vector never fails");
+    bytes
+}
+
+/// Generates a Solidity contract that verifies proofs for `vk`. Its
+/// `verifyProof(bytes a, bytes b, bytes c, uint256[] inputs)` takes the proof
+/// points in the encoding of EIP-2537, see [`solidity_calldata`].
+pub fn solidity(vk: &VerifyingKey<Bls12>) -> String {
+    let ic = vk.ic.iter().flat_map(g1_bytes).collect::<Vec<_>>();
+
+    format!(
+        r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+/// Verifies Groth16 proofs over BLS12-381 with the precompiles of EIP-2537.
+contract Groth16Verifier {{
+    // The scalar field, public inputs have to be below it.
+    uint256 constant R = 0x{r};
+
+    uint256 constant NUM_INPUTS = {num_inputs};
+
+    bytes constant ALPHA_G1 = hex"{alpha_g1}";
+    bytes constant NEG_BETA_G2 = hex"{neg_beta_g2}";
+    bytes constant NEG_GAMMA_G2 = hex"{neg_gamma_g2}";
+    bytes constant NEG_DELTA_G2 = hex"{neg_delta_g2}";
+    bytes constant IC = hex"{ic}";
+
+    address constant G1_MSM = address(0x0c);
+    address constant PAIRING_CHECK = address(0x0f);
+
+    /// `a` and `c` are G1 points of 128 bytes, `b` is a G2 point of 256 bytes.
+    function verifyProof(
+        bytes calldata a,
+        bytes calldata b,
+        bytes calldata c,
+        uint256[] calldata inputs
+    ) external view returns (bool) {{
+        require(a.length == 128 && b.length == 256 && c.length == 128, "malformed proof");
+        require(inputs.length == NUM_INPUTS, "wrong number of inputs");
+
+        // acc = IC[0] + inputs[0] * IC[1] + ...
+        bytes memory ic = IC;
+        bytes memory msm = new bytes(160 * (NUM_INPUTS + 1));
+        for (uint256 i = 0; i <= NUM_INPUTS; i++) {{
+            uint256 scalar = i == 0 ? 1 : inputs[i - 1];
+            require(scalar < R, "input out of range");
+            assembly {{
+                let dst := add(add(msm, 32), mul(i, 160))
+                let src := add(add(ic, 32), mul(i, 128))
+                mstore(dst, mload(src))
+                mstore(add(dst, 32), mload(add(src, 32)))
+                mstore(add(dst, 64), mload(add(src, 64)))
+                mstore(add(dst, 96), mload(add(src, 96)))
+                mstore(add(dst, 128), scalar)
+            }}
+        }}
+        (bool ok, bytes memory acc) = G1_MSM.staticcall(msm);
+        require(ok && acc.length == 128, "msm failed");
+
+        // e(A, B) * e(alpha, -beta) * e(acc, -gamma) * e(C, -delta) == 1
+        bytes memory pairs = abi.encodePacked(
+            a, b, ALPHA_G1, NEG_BETA_G2, acc, NEG_GAMMA_G2, c, NEG_DELTA_G2
+        );
+        bytes memory result;
+        (ok, result) = PAIRING_CHECK.staticcall(pairs);
+        // Invalid points make the precompile fail.
+        if (!ok || result.length != 32) {{
+            return false;
+        }}
+        return abi.decode(result, (uint256)) == 1;
+    }}
+}}
+"#,
+        r = hex(&repr_bytes(Fr::char())),
+        num_inputs = vk.ic.len() - 1,
+        alpha_g1 = hex(&g1_bytes(&vk.alpha_g1)),
+        neg_beta_g2 = hex(&neg_g2_bytes(&vk.beta_g2)),
+        neg_gamma_g2 = hex(&neg_g2_bytes(&vk.gamma_g2)),
+        neg_delta_g2 = hex(&neg_g2_bytes(&vk.delta_g2)),
+        ic = hex(&ic),
+    )
+}
+
+/// Encodes the call of `verifyProof` of the [`solidity`] contract for `proof`
+/// and its public inputs.
+pub fn solidity_calldata(proof: &Proof<Bls12>, public_inputs: &[Fr]) -> Vec<u8> {
+    fn word(value: usize) -> [u8; 32] {
+        let mut word = [0; 32];
+        word[24..].copy_from_slice(&(value as u64).to_be_bytes());
+        word
+    }
+
+    let a = g1_bytes(&proof.a);
+    let b = g2_bytes(&proof.b);
+    let c = g1_bytes(&proof.c);
+
+    let mut calldata = VERIFY_PROOF_SELECTOR.to_vec();
+
+    // The offsets of the dynamic arguments, after the four head words
+    let mut offset = 4 * 32;
+    for len in &[a.len(), b.len(), c.len()] {
+        calldata.extend_from_slice(&word(offset));
+        offset += 32 + len;
+    }
+    calldata.extend_from_slice(&word(offset));
+
+    for bytes in &[a, b, c] {
+        calldata.extend_from_slice(&word(bytes.len()));
+        calldata.extend_from_slice(bytes);
+    }
+    calldata.extend_from_slice(&word(public_inputs.len()));
+    for input in public_inputs {
+        calldata.extend_from_slice(&repr_bytes(input.into_repr()));
+    }
+
+    calldata
+}
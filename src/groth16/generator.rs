@@ -2,9 +2,9 @@ use rand_core::RngCore;
 
 use std::sync::Arc;
 
+use crate::bls::Engine;
 use ff::{Field, PrimeField};
 use groupy::{CurveAffine, CurveProjective, Wnaf};
-use paired::Engine;
 
 use super::{Parameters, VerifyingKey};
 
@@ -36,6 +36,47 @@ where
     generate_parameters::<E, C>(circuit, g1, g2, alpha, beta, gamma, delta, tau)
 }
 
+/// Generates a random common reference string for a circuit, like
+/// [`generate_random_parameters`], together with the statistics of the
+/// circuit.
+pub fn generate_random_parameters_with_stats<E, C, R>(
+    circuit: C,
+    rng: &mut R,
+) -> Result<(Parameters<E>, CircuitStats), SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+    R: RngCore,
+{
+    let g1 = E::G1::random(rng);
+    let g2 = E::G2::random(rng);
+    let alpha = E::Fr::random(rng);
+    let beta = E::Fr::random(rng);
+    let gamma = E::Fr::random(rng);
+    let delta = E::Fr::random(rng);
+    let tau = E::Fr::random(rng);
+
+    generate_parameters_with_stats::<E, C>(circuit, g1, g2, alpha, beta, gamma, delta, tau)
+}
+
+/// The size of a circuit, as the generator sees it while synthesizing it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircuitStats {
+    /// The number of constraints, including the ones added for every input.
+    pub num_constraints: usize,
+    /// The number of inputs, including `ONE`.
+    pub num_inputs: usize,
+    pub num_aux: usize,
+    /// The number of auxiliary variables in the A query.
+    pub a_aux_density: usize,
+    /// The number of inputs in the B query.
+    pub b_input_density: usize,
+    /// The number of auxiliary variables in the B query.
+    pub b_aux_density: usize,
+    /// The size of the evaluation domain of the FFTs.
+    pub fft_size: usize,
+}
+
 /// This is our assembly structure that we'll use to synthesize the
 /// circuit into a QAP.
 struct KeypairAssembly<E: Engine> {
@@ -135,6 +176,38 @@ impl<E: Engine> ConstraintSystem<E> for KeypairAssembly<E> {
         self.num_constraints += 1;
     }
 
+    fn multiply<F, A, AR>(
+        &mut self,
+        _: A,
+        _: F,
+    ) -> Result<(Variable, Variable, Variable), SynthesisError>
+    where
+        F: FnOnce() -> Result<(E::Fr, E::Fr), SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // Same as allocating the three variables and enforcing the
+        // constraint, without building any linear combinations.
+        let first = self.num_aux;
+        self.num_aux += 3;
+        for _ in 0..3 {
+            self.at_aux.push(vec![]);
+            self.bt_aux.push(vec![]);
+            self.ct_aux.push(vec![]);
+        }
+
+        self.at_aux[first].push((E::Fr::one(), self.num_constraints));
+        self.bt_aux[first + 1].push((E::Fr::one(), self.num_constraints));
+        self.ct_aux[first + 2].push((E::Fr::one(), self.num_constraints));
+        self.num_constraints += 1;
+
+        Ok((
+            Variable(Index::Aux(first)),
+            Variable(Index::Aux(first + 1)),
+            Variable(Index::Aux(first + 2)),
+        ))
+    }
+
     fn push_namespace<NR, N>(&mut self, _: N)
     where
         NR: Into<String>,
@@ -163,6 +236,27 @@ pub fn generate_parameters<E, C>(
     delta: E::Fr,
     tau: E::Fr,
 ) -> Result<Parameters<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    generate_parameters_with_stats(circuit, g1, g2, alpha, beta, gamma, delta, tau)
+        .map(|(params, _)| params)
+}
+
+/// Create parameters for a circuit, given some toxic waste, together with the
+/// statistics of the circuit.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_parameters_with_stats<E, C>(
+    circuit: C,
+    g1: E::G1,
+    g2: E::G2,
+    alpha: E::Fr,
+    beta: E::Fr,
+    gamma: E::Fr,
+    delta: E::Fr,
+    tau: E::Fr,
+) -> Result<(Parameters<E>, CircuitStats), SynthesisError>
 where
     E: Engine,
     C: Circuit<E>,
@@ -195,6 +289,18 @@ where
     let powers_of_tau = vec![Scalar::<E>(E::Fr::zero()); assembly.num_constraints];
     let mut powers_of_tau = EvaluationDomain::from_coeffs(powers_of_tau)?;
 
+    let density =
+        |queries: &[Vec<(E::Fr, usize)>]| queries.iter().filter(|q| !q.is_empty()).count();
+    let stats = CircuitStats {
+        num_constraints: assembly.num_constraints,
+        num_inputs: assembly.num_inputs,
+        num_aux: assembly.num_aux,
+        a_aux_density: density(&assembly.at_aux),
+        b_input_density: density(&assembly.bt_inputs),
+        b_aux_density: density(&assembly.bt_aux),
+        fft_size: powers_of_tau.as_ref().len(),
+    };
+
     // Compute G1 window table
     let mut g1_wnaf = Wnaf::new();
     let g1_wnaf = g1_wnaf.base(g1, {
@@ -450,7 +556,7 @@ where
         ic: ic.into_iter().map(|e| e.into_affine()).collect(),
     };
 
-    Ok(Parameters {
+    let params = Parameters {
         vk,
         h: Arc::new(h.into_iter().map(|e| e.into_affine()).collect()),
         l: Arc::new(l.into_iter().map(|e| e.into_affine()).collect()),
@@ -474,5 +580,7 @@ where
                 .map(|e| e.into_affine())
                 .collect(),
         ),
-    })
+    };
+
+    Ok((params, stats))
 }
@@ -0,0 +1,109 @@
+use ff::Field;
+
+use crate::bls::Engine;
+use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// The public inputs of `circuit`, without `ONE`, as [`verify_proof`]
+/// expects them. Verifiers derive them from the same circuit code as the
+/// prover, with the assignments of the public values. The assignments of the
+/// auxiliary variables are computed as well, as gadgets may derive the values
+/// of inputs from them, but they are dropped right away.
+///
+/// [`verify_proof`]: super::verify_proof
+pub fn public_inputs<E, C>(circuit: C) -> Result<Vec<E::Fr>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    let mut cs = PublicInputsCS::new();
+    circuit.synthesize(&mut cs)?;
+
+    Ok(cs.into_inputs())
+}
+
+/// A constraint system that only records the assignments of the inputs.
+pub struct PublicInputsCS<E: Engine> {
+    // Including `ONE`.
+    inputs: Vec<E::Fr>,
+    num_aux: usize,
+}
+
+impl<E: Engine> PublicInputsCS<E> {
+    /// A constraint system with just the `ONE` input.
+    pub fn new() -> Self {
+        PublicInputsCS {
+            inputs: vec![E::Fr::one()],
+            num_aux: 0,
+        }
+    }
+
+    /// The recorded inputs, without `ONE`.
+    pub fn inputs(&self) -> &[E::Fr] {
+        &self.inputs[1..]
+    }
+
+    pub fn into_inputs(mut self) -> Vec<E::Fr> {
+        self.inputs.remove(0);
+        self.inputs
+    }
+}
+
+impl<E: Engine> Default for PublicInputsCS<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for PublicInputsCS<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        f()?;
+        self.num_aux += 1;
+
+        Ok(Variable(Index::Aux(self.num_aux - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inputs.push(f()?);
+
+        Ok(Variable(Index::Input(self.inputs.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, _: LA, _: LB, _: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        // Do nothing; the constraints don't matter for the inputs.
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
@@ -0,0 +1,231 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+
+use byteorder::{BigEndian, ReadBytesExt};
+use groupy::{CurveAffine, EncodedPoint};
+
+use super::{container, ParameterSource, VerifyingKey};
+use crate::bls::Engine;
+use crate::multicore::Worker;
+use crate::multiexp::BaseRange;
+use crate::SynthesisError;
+
+/// Where the points of a query are in the file.
+#[derive(Clone, Copy, Debug, Default)]
+struct Section {
+    offset: u64,
+    len: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Format {
+    Legacy,
+    Container { compressed: bool },
+}
+
+/// Parameters whose queries are read from their file when the prover asks
+/// for them. They aren't kept around, so every query is freed once its
+/// multiexps are done, rather than all of them staying in memory for the
+/// whole proof. Proofs that ask for a query another one still uses, e.g.
+/// ones running at the same time, share it instead of reading it again.
+///
+/// Only the verifying key and the offsets of the queries are read when the
+/// file is opened. The file must not change while it's in use.
+pub struct LazyParameters<E: Engine> {
+    path: PathBuf,
+    vk: VerifyingKey<E>,
+    format: Format,
+    /// The sections of `h`, `l`, `a`, `b_g1` and `b_g2`.
+    sections: [Section; 5],
+    checked: bool,
+    worker: Worker,
+    loaded: Mutex<Loaded<E>>,
+}
+
+/// The queries that are in use, `h`, `l`, `a` and `b_g1` in G1.
+struct Loaded<E: Engine> {
+    g1: [Weak<Vec<E::G1Affine>>; 4],
+    b_g2: Weak<Vec<E::G2Affine>>,
+}
+
+impl<E: Engine> Default for Loaded<E> {
+    fn default() -> Self {
+        Loaded {
+            g1: [Weak::new(), Weak::new(), Weak::new(), Weak::new()],
+            b_g2: Weak::new(),
+        }
+    }
+}
+
+impl<E: Engine> LazyParameters<E> {
+    /// Opens parameters in the container or in the legacy format, see
+    /// [`Parameters::read`](super::Parameters::read).
+    pub fn open<P: AsRef<Path>>(path: P, checked: bool) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = BufReader::new(File::open(&path)?);
+        let file_len = file.get_ref().metadata()?.len();
+
+        let mut magic = [0; 8];
+        file.read_exact(&mut magic)?;
+
+        let mut sections = [Section::default(); 5];
+        let (vk, format, end) = if magic == container::MAGIC {
+            let header = container::read_header::<E, _>(&mut file)?;
+            let mut offset = file.seek(SeekFrom::Current(0))?;
+            for (i, section) in sections.iter_mut().enumerate() {
+                let len = header.lens[i];
+                *section = Section { offset, len };
+                offset += if i < 4 {
+                    container::query_size::<E::G1Affine>(len, header.compressed)
+                } else {
+                    container::query_size::<E::G2Affine>(len, header.compressed)
+                };
+            }
+
+            let format = Format::Container {
+                compressed: header.compressed,
+            };
+            (header.vk, format, offset)
+        } else {
+            file.seek(SeekFrom::Start(0))?;
+            let vk = VerifyingKey::read(&mut file)?;
+            let mut offset = file.seek(SeekFrom::Current(0))?;
+            // Every query is its length followed by its points.
+            for (i, section) in sections.iter_mut().enumerate() {
+                let len = file.read_u32::<BigEndian>()? as usize;
+                let size = if i < 4 {
+                    <E::G1Affine as CurveAffine>::Uncompressed::size()
+                } else {
+                    <E::G2Affine as CurveAffine>::Uncompressed::size()
+                };
+                *section = Section {
+                    offset: offset + 4,
+                    len,
+                };
+                offset += 4 + (len as u64) * (size as u64);
+                file.seek(SeekFrom::Start(offset))?;
+            }
+
+            (vk, Format::Legacy, offset)
+        };
+
+        if end > file_len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the parameter file is truncated",
+            ));
+        }
+
+        Ok(LazyParameters {
+            path,
+            vk,
+            format,
+            sections,
+            checked,
+            worker: Worker::new(),
+            loaded: Mutex::new(Loaded::default()),
+        })
+    }
+
+    pub fn vk(&self) -> &VerifyingKey<E> {
+        &self.vk
+    }
+
+    /// The `index`th query in G1, read unless it's still in use.
+    fn load_g1(&self, index: usize) -> io::Result<Arc<Vec<E::G1Affine>>> {
+        let mut loaded = self
+            .loaded
+            .lock()
+            .expect("a query was read by a panicking thread");
+        if let Some(query) = loaded.g1[index].upgrade() {
+            return Ok(query);
+        }
+        let query = self.read(index)?;
+        loaded.g1[index] = Arc::downgrade(&query);
+
+        Ok(query)
+    }
+
+    /// The `b_g2` query, read unless it's still in use.
+    fn load_b_g2(&self) -> io::Result<Arc<Vec<E::G2Affine>>> {
+        let mut loaded = self
+            .loaded
+            .lock()
+            .expect("a query was read by a panicking thread");
+        if let Some(query) = loaded.b_g2.upgrade() {
+            return Ok(query);
+        }
+        let query = self.read(4)?;
+        loaded.b_g2 = Arc::downgrade(&query);
+
+        Ok(query)
+    }
+
+    /// Reads the `index`th query.
+    fn read<G: CurveAffine>(&self, index: usize) -> io::Result<Arc<Vec<G>>> {
+        let section = self.sections[index];
+        let mut file = BufReader::new(File::open(&self.path)?);
+        file.seek(SeekFrom::Start(section.offset))?;
+
+        match self.format {
+            Format::Legacy => container::read_points_parallel::<G::Uncompressed, _>(
+                &mut file,
+                section.len,
+                self.checked,
+                &self.worker,
+            )
+            .map(Arc::new),
+            Format::Container { compressed } => container::read_query(
+                &mut file,
+                index,
+                section.len,
+                self.checked,
+                compressed,
+                &self.worker,
+            ),
+        }
+    }
+}
+
+impl<'a, E: Engine> ParameterSource<E> for &'a LazyParameters<E> {
+    type G1Builder = BaseRange<E::G1Affine>;
+    type G2Builder = BaseRange<E::G2Affine>;
+
+    fn get_vk(&mut self, _: usize) -> Result<VerifyingKey<E>, SynthesisError> {
+        Ok(self.vk.clone())
+    }
+
+    fn get_h(&mut self, _: usize) -> Result<Self::G1Builder, SynthesisError> {
+        Ok(BaseRange::new(self.load_g1(0)?))
+    }
+
+    fn get_l(&mut self, _: usize) -> Result<Self::G1Builder, SynthesisError> {
+        Ok(BaseRange::new(self.load_g1(1)?))
+    }
+
+    fn get_a(
+        &mut self,
+        num_inputs: usize,
+        _: usize,
+    ) -> Result<(Self::G1Builder, Self::G1Builder), SynthesisError> {
+        Ok(BaseRange::new(self.load_g1(2)?).split_at(num_inputs))
+    }
+
+    fn get_b_g1(
+        &mut self,
+        num_inputs: usize,
+        _: usize,
+    ) -> Result<(Self::G1Builder, Self::G1Builder), SynthesisError> {
+        Ok(BaseRange::new(self.load_g1(3)?).split_at(num_inputs))
+    }
+
+    fn get_b_g2(
+        &mut self,
+        num_inputs: usize,
+        _: usize,
+    ) -> Result<(Self::G2Builder, Self::G2Builder), SynthesisError> {
+        Ok(BaseRange::new(self.load_b_g2()?).split_at(num_inputs))
+    }
+}
@@ -2,26 +2,59 @@
 //!
 //! [Groth16]: https://eprint.iacr.org/2016/260
 
+use crate::bls::{Engine, PairingCurveAffine};
 use groupy::{CurveAffine, EncodedPoint};
-use paired::{Engine, PairingCurveAffine};
 
-use crate::multiexp::SourceBuilder;
+use crate::multicore::Worker;
+use crate::multiexp::{BaseRange, FixedBaseTables, SourceBuilder};
 use crate::SynthesisError;
 
+use blake2s_simd::Params;
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::cmp;
+use std::fmt;
 use std::io::{self, Read, Write};
 use std::sync::Arc;
 
 #[cfg(test)]
 mod tests;
 
+pub mod ark;
+mod cached;
+mod container;
+pub mod distributed;
+mod encoding;
+mod estimate;
+pub mod export;
 mod generator;
+mod inputs;
+mod lazy;
+mod options;
+mod progress;
 mod prover;
+mod report;
+mod satisfaction;
+#[cfg(feature = "snarkjs")]
+pub mod snarkjs;
+mod specialize;
+mod spill;
 mod verifier;
+mod witness;
+pub mod zcash;
 
+pub use self::cached::{CachedBases, CachedParameters};
+pub use self::estimate::*;
 pub use self::generator::*;
+pub use self::inputs::{public_inputs, PublicInputsCS};
+pub use self::lazy::LazyParameters;
+pub use self::options::{CheckMode, Priority, ProverOptions};
+pub use self::progress::{GpuEvent, ProgressSink, ProvingStage};
 pub use self::prover::*;
+pub use self::report::{ProvingReport, StageReport};
+pub use self::satisfaction::circuit_is_satisfied;
+pub use self::specialize::{specialize_verifying_key, SpecializedParameters};
 pub use self::verifier::*;
+pub use self::witness::ProvingWitness;
 
 #[derive(Clone)]
 pub struct Proof<E: Engine> {
@@ -46,55 +79,93 @@ impl<E: Engine> Proof<E> {
     }
 
     pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
-        let mut g1_repr = <E::G1Affine as CurveAffine>::Compressed::empty();
-        let mut g2_repr = <E::G2Affine as CurveAffine>::Compressed::empty();
+        let mut bytes = vec![0; Self::size()];
+        reader.read_exact(&mut bytes)?;
 
-        reader.read_exact(g1_repr.as_mut())?;
-        let a = g1_repr
-            .into_affine()
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-            .and_then(|e| {
-                if e.is_zero() {
-                    Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        "point at infinity",
-                    ))
-                } else {
-                    Ok(e)
-                }
-            })?;
+        Ok(Self::from_bytes(&bytes)?)
+    }
 
-        reader.read_exact(g2_repr.as_mut())?;
-        let b = g2_repr
-            .into_affine()
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-            .and_then(|e| {
-                if e.is_zero() {
-                    Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        "point at infinity",
-                    ))
-                } else {
-                    Ok(e)
-                }
-            })?;
+    /// The number of bytes of the encoding of [`write`](Proof::write).
+    pub fn size() -> usize {
+        let g1 = <E::G1Affine as CurveAffine>::Compressed::size();
+        let g2 = <E::G2Affine as CurveAffine>::Compressed::size();
+        2 * g1 + g2
+    }
 
-        reader.read_exact(g1_repr.as_mut())?;
-        let c = g1_repr
-            .into_affine()
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-            .and_then(|e| {
-                if e.is_zero() {
-                    Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        "point at infinity",
-                    ))
-                } else {
-                    Ok(e)
-                }
-            })?;
-
-        Ok(Proof { a, b, c })
+    /// Decodes a proof from exactly the bytes [`write`](Proof::write) writes.
+    /// Unlike [`read`](Proof::read) it needs neither a reader nor I/O errors,
+    /// so that environments without `std` can decode proofs once the curve
+    /// crates support them.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProofDecodingError> {
+        if bytes.len() != Self::size() {
+            return Err(ProofDecodingError::Length {
+                expected: Self::size(),
+                actual: bytes.len(),
+            });
+        }
+
+        let g1 = <E::G1Affine as CurveAffine>::Compressed::size();
+        let (a, rest) = bytes.split_at(g1);
+        let (b, c) = rest.split_at(rest.len() - g1);
+
+        Ok(Proof {
+            a: decode_point::<E::G1Affine>(a, "a")?,
+            b: decode_point::<E::G2Affine>(b, "b")?,
+            c: decode_point::<E::G1Affine>(c, "c")?,
+        })
+    }
+}
+
+/// Decodes a compressed point of a proof, which is never at infinity.
+fn decode_point<G: CurveAffine>(bytes: &[u8], name: &'static str) -> Result<G, ProofDecodingError> {
+    let mut repr = G::Compressed::empty();
+    repr.as_mut().copy_from_slice(bytes);
+    let point = repr
+        .into_affine()
+        .map_err(|_| ProofDecodingError::InvalidPoint(name))?;
+    if point.is_zero() {
+        return Err(ProofDecodingError::PointAtInfinity(name));
+    }
+
+    Ok(point)
+}
+
+/// Why bytes aren't a proof, see [`Proof::from_bytes`]. Only `Display` and the
+/// conversion into an I/O error need `std`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofDecodingError {
+    /// A proof takes `expected` bytes, not `actual`.
+    Length { expected: usize, actual: usize },
+    /// The point of the given name isn't the encoding of a point of its group.
+    InvalidPoint(&'static str),
+    /// The point of the given name is the point at infinity.
+    PointAtInfinity(&'static str),
+}
+
+impl fmt::Display for ProofDecodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofDecodingError::Length { expected, actual } => {
+                write!(f, "a proof takes {} bytes, not {}", expected, actual)
+            }
+            ProofDecodingError::InvalidPoint(name) => write!(f, "invalid point {}", name),
+            ProofDecodingError::PointAtInfinity(name) => write!(f, "point {} is at infinity", name),
+        }
+    }
+}
+
+impl std::error::Error for ProofDecodingError {}
+
+/// Too few bytes are an unexpected end of file, like for [`Proof::read`].
+impl From<ProofDecodingError> for io::Error {
+    fn from(e: ProofDecodingError) -> io::Error {
+        let kind = match e {
+            ProofDecodingError::Length { expected, actual } if actual < expected => {
+                io::ErrorKind::UnexpectedEof
+            }
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, e)
     }
 }
 
@@ -138,7 +209,26 @@ impl<E: Engine> PartialEq for VerifyingKey<E> {
     }
 }
 
+/// Personalization of the BLAKE2s hash of a verifying key.
+const FINGERPRINT_PERSONALIZATION: &[u8; 8] = b"BellVkFp";
+
 impl<E: Engine> VerifyingKey<E> {
+    /// The BLAKE2s hash of the key as written by [`VerifyingKey::write`],
+    /// which identifies the circuit and the setup it belongs to. It stays the
+    /// same across versions of the crate.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut state = Params::new()
+            .hash_length(32)
+            .personal(FINGERPRINT_PERSONALIZATION)
+            .to_state();
+        self.write(&mut state)
+            .expect("writing to a hash never fails");
+
+        let mut fingerprint = [0; 32];
+        fingerprint.copy_from_slice(state.finalize().as_bytes());
+        fingerprint
+    }
+
     pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
         writer.write_all(self.alpha_g1.into_uncompressed().as_ref())?;
         writer.write_all(self.beta_g1.into_uncompressed().as_ref())?;
@@ -189,27 +279,14 @@ impl<E: Engine> VerifyingKey<E> {
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
         let ic_len = reader.read_u32::<BigEndian>()? as usize;
-
-        let mut ic = vec![];
-
-        for _ in 0..ic_len {
-            reader.read_exact(g1_repr.as_mut())?;
-            let g1 = g1_repr
-                .into_affine()
-                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-                .and_then(|e| {
-                    if e.is_zero() {
-                        Err(io::Error::new(
-                            io::ErrorKind::InvalidData,
-                            "point at infinity",
-                        ))
-                    } else {
-                        Ok(e)
-                    }
-                })?;
-
-            ic.push(g1);
+        // There is always the IC point of the constant one input.
+        if ic_len == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "expected at least one IC point",
+            ));
         }
+        let ic = read_points(&mut reader, ic_len, true)?;
 
         Ok(VerifyingKey {
             alpha_g1,
@@ -223,6 +300,9 @@ impl<E: Engine> VerifyingKey<E> {
     }
 }
 
+/// The parameters of a circuit, whose queries are reference counted, so
+/// cloning them is cheap. They are `Send` and `Sync`, so many threads can
+/// prove with the same parameters at once, by reference or in an [`Arc`].
 #[derive(Clone)]
 pub struct Parameters<E: Engine> {
     pub vk: VerifyingKey<E>,
@@ -259,8 +339,82 @@ impl<E: Engine> PartialEq for Parameters<E> {
     }
 }
 
+/// Reads an uncompressed point, which must not be the point at infinity.
+fn read_point<G: CurveAffine, R: Read>(reader: &mut R, checked: bool) -> io::Result<G> {
+    let mut repr = G::Uncompressed::empty();
+    reader.read_exact(repr.as_mut())?;
+
+    decode_point(&repr, checked)
+}
+
+/// Decodes a point, which must not be the point at infinity.
+fn decode_point<P: EncodedPoint>(repr: &P, checked: bool) -> io::Result<P::Affine> {
+    if checked {
+        repr.into_affine()
+    } else {
+        repr.into_affine_unchecked()
+    }
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    .and_then(|e| {
+        if e.is_zero() {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "point at infinity",
+            ))
+        } else {
+            Ok(e)
+        }
+    })
+}
+
+/// The most points that are allocated for before they are read. Lengths come
+/// from the input, which may claim far more points than it has.
+const MAX_PREALLOCATED_POINTS: usize = 1 << 16;
+
+/// Reads `len` points like [`read_point`].
+fn read_points<G: CurveAffine, R: Read>(
+    reader: &mut R,
+    len: usize,
+    checked: bool,
+) -> io::Result<Vec<G>> {
+    let mut points = Vec::with_capacity(cmp::min(len, MAX_PREALLOCATED_POINTS));
+    for _ in 0..len {
+        points.push(read_point(reader, checked)?);
+    }
+
+    Ok(points)
+}
+
 impl<E: Engine> Parameters<E> {
-    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+    /// Writes the parameters in the versioned container format, which has a
+    /// header with the curve and the number of points and a checksum for
+    /// every section. The points are encoded in parallel.
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        container::write(self, writer, false, &Worker::new())
+    }
+
+    /// Writes the parameters in the container format with the points of the
+    /// queries compressed, which makes the file about half as large. Reading
+    /// them back takes a square root per point, which is computed in
+    /// parallel.
+    pub fn write_compressed<W: Write>(&self, writer: W) -> io::Result<()> {
+        container::write(self, writer, true, &Worker::new())
+    }
+
+    /// Like [`Parameters::write`] or [`Parameters::write_compressed`],
+    /// encoding the points on `worker`.
+    pub fn write_with_worker<W: Write>(
+        &self,
+        writer: W,
+        compressed: bool,
+        worker: &Worker,
+    ) -> io::Result<()> {
+        container::write(self, writer, compressed, worker)
+    }
+
+    /// Writes the parameters in the legacy format without a header or
+    /// checksums, for readers that predate the container format.
+    pub fn write_legacy<W: Write>(&self, mut writer: W) -> io::Result<()> {
         self.vk.write(&mut writer)?;
 
         writer.write_u32::<BigEndian>(self.h.len() as u32)?;
@@ -291,101 +445,62 @@ impl<E: Engine> Parameters<E> {
         Ok(())
     }
 
-    pub fn read<R: Read>(mut reader: R, checked: bool) -> io::Result<Self> {
-        let read_g1 = |reader: &mut R| -> io::Result<E::G1Affine> {
-            let mut repr = <E::G1Affine as CurveAffine>::Uncompressed::empty();
-            reader.read_exact(repr.as_mut())?;
-
-            if checked {
-                repr.into_affine()
-            } else {
-                repr.into_affine_unchecked()
-            }
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-            .and_then(|e| {
-                if e.is_zero() {
-                    Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        "point at infinity",
-                    ))
-                } else {
-                    Ok(e)
-                }
-            })
-        };
-
-        let read_g2 = |reader: &mut R| -> io::Result<E::G2Affine> {
-            let mut repr = <E::G2Affine as CurveAffine>::Uncompressed::empty();
-            reader.read_exact(repr.as_mut())?;
-
-            if checked {
-                repr.into_affine()
-            } else {
-                repr.into_affine_unchecked()
-            }
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-            .and_then(|e| {
-                if e.is_zero() {
-                    Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        "point at infinity",
-                    ))
-                } else {
-                    Ok(e)
-                }
-            })
-        };
-
-        let vk = VerifyingKey::<E>::read(&mut reader)?;
-
-        let mut h = vec![];
-        let mut l = vec![];
-        let mut a = vec![];
-        let mut b_g1 = vec![];
-        let mut b_g2 = vec![];
-
-        {
-            let len = reader.read_u32::<BigEndian>()? as usize;
-            for _ in 0..len {
-                h.push(read_g1(&mut reader)?);
-            }
+    /// Checks that the verifying key of the parameters has the `expected`
+    /// [fingerprint](VerifyingKey::fingerprint), e.g. the one of a separately
+    /// loaded verifying key or the one recorded for a version of the circuit.
+    pub fn check_fingerprint(&self, expected: &[u8; 32]) -> Result<(), SynthesisError> {
+        let actual = self.vk.fingerprint();
+        if actual != *expected {
+            return Err(SynthesisError::FingerprintMismatch(*expected, actual));
         }
 
-        {
-            let len = reader.read_u32::<BigEndian>()? as usize;
-            for _ in 0..len {
-                l.push(read_g1(&mut reader)?);
-            }
-        }
+        Ok(())
+    }
 
-        {
-            let len = reader.read_u32::<BigEndian>()? as usize;
-            for _ in 0..len {
-                a.push(read_g1(&mut reader)?);
-            }
-        }
+    /// Reads parameters in the container format, validating the header and
+    /// the checksums, or in the legacy format.
+    pub fn read<R: Read>(reader: R, checked: bool) -> io::Result<Self> {
+        Self::read_with_worker(reader, checked, &Worker::new())
+    }
 
-        {
-            let len = reader.read_u32::<BigEndian>()? as usize;
-            for _ in 0..len {
-                b_g1.push(read_g1(&mut reader)?);
-            }
+    /// Like [`Parameters::read`], decoding the points on `worker`.
+    pub fn read_with_worker<R: Read>(
+        mut reader: R,
+        checked: bool,
+        worker: &Worker,
+    ) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+
+        if magic == container::MAGIC {
+            container::read(reader, checked, worker)
+        } else {
+            // A legacy file starts with a point, which can't be the magic bytes.
+            Self::read_legacy((&magic[..]).chain(reader), checked, worker)
         }
+    }
 
-        {
+    fn read_legacy<R: Read>(mut reader: R, checked: bool, worker: &Worker) -> io::Result<Self> {
+        let vk = VerifyingKey::<E>::read(&mut reader)?;
+
+        // Every query is its length followed by its points.
+        fn read_query<G: CurveAffine, R: Read>(
+            reader: &mut R,
+            checked: bool,
+            worker: &Worker,
+        ) -> io::Result<Arc<Vec<G>>> {
             let len = reader.read_u32::<BigEndian>()? as usize;
-            for _ in 0..len {
-                b_g2.push(read_g2(&mut reader)?);
-            }
+            container::read_points_parallel::<G::Uncompressed, _>(reader, len, checked, worker)
+                .map(Arc::new)
         }
 
         Ok(Parameters {
             vk,
-            h: Arc::new(h),
-            l: Arc::new(l),
-            a: Arc::new(a),
-            b_g1: Arc::new(b_g1),
-            b_g2: Arc::new(b_g2),
+            h: read_query(&mut reader, checked, worker)?,
+            l: read_query(&mut reader, checked, worker)?,
+            a: read_query(&mut reader, checked, worker)?,
+            b_g1: read_query(&mut reader, checked, worker)?,
+            b_g2: read_query(&mut reader, checked, worker)?,
         })
     }
 }
@@ -397,10 +512,28 @@ pub struct PreparedVerifyingKey<E: Engine> {
     neg_gamma_g2: <E::G2Affine as PairingCurveAffine>::Prepared,
     /// -delta in G2
     neg_delta_g2: <E::G2Affine as PairingCurveAffine>::Prepared,
-    /// Copy of IC from `VerifiyingKey`.
-    ic: Vec<E::G1Affine>,
+    /// Copy of IC from `VerifiyingKey`, shared with the multiexps of
+    /// `verify_proof_with_worker`.
+    ic: Arc<Vec<E::G1Affine>>,
+    /// Precomputed multiples of IC without its first element.
+    ic_tables: Option<FixedBaseTables<E::G1Affine>>,
 }
 
+impl<E: Engine> PreparedVerifyingKey<E> {
+    /// Precomputes multiples of the IC bases in windows of `window` bits,
+    /// which makes accumulating the public inputs take a single addition per
+    /// input and window. Every input takes `⌈255 / window⌉·(2^window - 1)`
+    /// points of memory, e.g. about 90 KiB for a window of four bits.
+    pub fn precompute_ic_tables(&mut self, window: u32) {
+        self.ic_tables = Some(FixedBaseTables::new(&self.ic[1..], window));
+    }
+}
+
+/// Where the prover takes the verifying key and the queries from. The
+/// builders of the sources are range views of shared bases, like
+/// [`BaseRange`]: the input and the auxiliary parts of a query are two ranges
+/// of the same allocation, and asking for a query that is still in use shares
+/// it rather than copying it.
 pub trait ParameterSource<E: Engine> {
     type G1Builder: SourceBuilder<E::G1Affine>;
     type G2Builder: SourceBuilder<E::G2Affine>;
@@ -423,22 +556,29 @@ pub trait ParameterSource<E: Engine> {
         num_inputs: usize,
         num_aux: usize,
     ) -> Result<(Self::G2Builder, Self::G2Builder), SynthesisError>;
+
+    /// The public inputs the parameters are specialized to, by their position
+    /// in the public inputs and ordered by it, see [`SpecializedParameters`].
+    /// The prover leaves them out of the input queries.
+    fn fixed_inputs(&self) -> &[(usize, E::Fr)] {
+        &[]
+    }
 }
 
 impl<'a, E: Engine> ParameterSource<E> for &'a Parameters<E> {
-    type G1Builder = (Arc<Vec<E::G1Affine>>, usize);
-    type G2Builder = (Arc<Vec<E::G2Affine>>, usize);
+    type G1Builder = BaseRange<E::G1Affine>;
+    type G2Builder = BaseRange<E::G2Affine>;
 
     fn get_vk(&mut self, _: usize) -> Result<VerifyingKey<E>, SynthesisError> {
         Ok(self.vk.clone())
     }
 
     fn get_h(&mut self, _: usize) -> Result<Self::G1Builder, SynthesisError> {
-        Ok((self.h.clone(), 0))
+        Ok(BaseRange::new(self.h.clone()))
     }
 
     fn get_l(&mut self, _: usize) -> Result<Self::G1Builder, SynthesisError> {
-        Ok((self.l.clone(), 0))
+        Ok(BaseRange::new(self.l.clone()))
     }
 
     fn get_a(
@@ -446,7 +586,7 @@ impl<'a, E: Engine> ParameterSource<E> for &'a Parameters<E> {
         num_inputs: usize,
         _: usize,
     ) -> Result<(Self::G1Builder, Self::G1Builder), SynthesisError> {
-        Ok(((self.a.clone(), 0), (self.a.clone(), num_inputs)))
+        Ok(BaseRange::new(self.a.clone()).split_at(num_inputs))
     }
 
     fn get_b_g1(
@@ -454,7 +594,7 @@ impl<'a, E: Engine> ParameterSource<E> for &'a Parameters<E> {
         num_inputs: usize,
         _: usize,
     ) -> Result<(Self::G1Builder, Self::G1Builder), SynthesisError> {
-        Ok(((self.b_g1.clone(), 0), (self.b_g1.clone(), num_inputs)))
+        Ok(BaseRange::new(self.b_g1.clone()).split_at(num_inputs))
     }
 
     fn get_b_g2(
@@ -462,18 +602,61 @@ impl<'a, E: Engine> ParameterSource<E> for &'a Parameters<E> {
         num_inputs: usize,
         _: usize,
     ) -> Result<(Self::G2Builder, Self::G2Builder), SynthesisError> {
-        Ok(((self.b_g2.clone(), 0), (self.b_g2.clone(), num_inputs)))
+        Ok(BaseRange::new(self.b_g2.clone()).split_at(num_inputs))
+    }
+}
+
+/// Lets threads that outlive the parameters' owner prove with them, e.g.
+/// spawned ones, by sharing them in an `Arc` instead of cloning them.
+impl<E: Engine> ParameterSource<E> for Arc<Parameters<E>> {
+    type G1Builder = BaseRange<E::G1Affine>;
+    type G2Builder = BaseRange<E::G2Affine>;
+
+    fn get_vk(&mut self, num_ic: usize) -> Result<VerifyingKey<E>, SynthesisError> {
+        (&**self).get_vk(num_ic)
+    }
+
+    fn get_h(&mut self, num_h: usize) -> Result<Self::G1Builder, SynthesisError> {
+        (&**self).get_h(num_h)
+    }
+
+    fn get_l(&mut self, num_l: usize) -> Result<Self::G1Builder, SynthesisError> {
+        (&**self).get_l(num_l)
+    }
+
+    fn get_a(
+        &mut self,
+        num_inputs: usize,
+        num_aux: usize,
+    ) -> Result<(Self::G1Builder, Self::G1Builder), SynthesisError> {
+        (&**self).get_a(num_inputs, num_aux)
+    }
+
+    fn get_b_g1(
+        &mut self,
+        num_inputs: usize,
+        num_aux: usize,
+    ) -> Result<(Self::G1Builder, Self::G1Builder), SynthesisError> {
+        (&**self).get_b_g1(num_inputs, num_aux)
+    }
+
+    fn get_b_g2(
+        &mut self,
+        num_inputs: usize,
+        num_aux: usize,
+    ) -> Result<(Self::G2Builder, Self::G2Builder), SynthesisError> {
+        (&**self).get_b_g2(num_inputs, num_aux)
     }
 }
 
 #[cfg(test)]
 mod test_with_bls12_381 {
     use super::*;
-    use crate::{Circuit, ConstraintSystem, SynthesisError};
+    use crate::{Circuit, ConstraintSystem, ParallelCircuit, SequentialCircuit, SynthesisError};
 
+    use crate::bls::{Bls12, Fr};
+    use crate::testing::test_rng;
     use ff::Field;
-    use paired::bls12_381::{Bls12, Fr};
-    use rand::thread_rng;
 
     #[test]
     fn serialization() {
@@ -506,7 +689,7 @@ mod test_with_bls12_381 {
             }
         }
 
-        let rng = &mut thread_rng();
+        let rng = &mut test_rng();
 
         let params =
             generate_random_parameters::<Bls12, _, _>(MySillyCircuit { a: None, b: None }, rng)
@@ -516,13 +699,97 @@ mod test_with_bls12_381 {
             let mut v = vec![];
 
             params.write(&mut v).unwrap();
-            assert_eq!(v.len(), 2136);
+            assert_eq!(v.len(), 2408);
 
             let de_params = Parameters::read(&v[..], true).unwrap();
             assert!(params == de_params);
 
             let de_params = Parameters::read(&v[..], false).unwrap();
             assert!(params == de_params);
+
+            // A flipped bit in a section fails its checksum, even if the
+            // point is still valid when decoded unchecked.
+            let mut corrupted = v.clone();
+            let last = corrupted.len() - 33;
+            corrupted[last] ^= 1;
+            assert!(Parameters::<Bls12>::read(&corrupted[..], false).is_err());
+            assert!(Parameters::<Bls12>::read(&v[..v.len() - 1], false).is_err());
+
+            let mut compressed = vec![];
+            params.write_compressed(&mut compressed).unwrap();
+            assert_eq!(compressed.len(), 1880);
+
+            let worker = crate::multicore::Worker::with_threads(2);
+            let de_params = Parameters::read_with_worker(&compressed[..], true, &worker).unwrap();
+            assert!(params == de_params);
+
+            let de_params = Parameters::read(&compressed[..], false).unwrap();
+            assert!(params == de_params);
+
+            assert!(
+                Parameters::<Bls12>::read(&compressed[..compressed.len() - 100], false).is_err()
+            );
+
+            let mut legacy = vec![];
+            params.write_legacy(&mut legacy).unwrap();
+            assert_eq!(legacy.len(), 2136);
+
+            let de_params = Parameters::read(&legacy[..], true).unwrap();
+            assert!(params == de_params);
+
+            // Lengths that claim more points than there are fail once the
+            // input runs out, instead of allocating for them.
+            let mut vk = vec![];
+            params.vk.write(&mut vk).unwrap();
+            let ic_start = vk.len() - 4 - 96 * params.vk.ic.len();
+            vk.truncate(ic_start);
+            for &(len, kind) in &[
+                (0, std::io::ErrorKind::InvalidData),
+                (u32::max_value(), std::io::ErrorKind::UnexpectedEof),
+            ] {
+                let mut encoded = vk.clone();
+                encoded.write_u32::<BigEndian>(len).unwrap();
+                assert_eq!(
+                    VerifyingKey::<Bls12>::read(&encoded[..])
+                        .err()
+                        .unwrap()
+                        .kind(),
+                    kind
+                );
+            }
+
+            let b_g2_start = legacy.len() - 4 - 192 * params.b_g2.len();
+            let mut truncated = legacy[..b_g2_start].to_vec();
+            truncated.write_u32::<BigEndian>(u32::max_value()).unwrap();
+            assert_eq!(
+                Parameters::<Bls12>::read(&truncated[..], false)
+                    .err()
+                    .unwrap()
+                    .kind(),
+                std::io::ErrorKind::UnexpectedEof
+            );
+        }
+
+        // The fingerprint is the same for a deserialized key, and differs for
+        // the parameters of another setup.
+        let fingerprint = params.vk.fingerprint();
+        let mut vk = vec![];
+        params.vk.write(&mut vk).unwrap();
+        assert_eq!(
+            VerifyingKey::<Bls12>::read(&vk[..]).unwrap().fingerprint(),
+            fingerprint
+        );
+        params.check_fingerprint(&fingerprint).unwrap();
+
+        let other =
+            generate_random_parameters::<Bls12, _, _>(MySillyCircuit { a: None, b: None }, rng)
+                .unwrap();
+        match other.check_fingerprint(&fingerprint) {
+            Err(SynthesisError::FingerprintMismatch(expected, actual)) => {
+                assert_eq!(expected, fingerprint);
+                assert_eq!(actual, other.vk.fingerprint());
+            }
+            _ => panic!("expected a fingerprint mismatch"),
         }
 
         let pvk = prepare_verifying_key::<Bls12>(&params.vk);
@@ -555,4 +822,213 @@ mod test_with_bls12_381 {
             assert!(!verify_proof(&pvk, &proof, &[a]).unwrap());
         }
     }
+
+    #[test]
+    fn multiply() {
+        struct CubeCircuit<E: Engine> {
+            x: Option<E::Fr>,
+        }
+
+        impl<E: Engine> Circuit<E> for CubeCircuit<E> {
+            fn synthesize<CS: ConstraintSystem<E>>(
+                self,
+                cs: &mut CS,
+            ) -> Result<(), SynthesisError> {
+                let x = self.x;
+                let (_, _, x2) = cs.multiply(
+                    || "x^2",
+                    || {
+                        let x = x.ok_or(SynthesisError::AssignmentMissing)?;
+                        Ok((x, x))
+                    },
+                )?;
+                let (_, x2_copy, x3) = cs.multiply(
+                    || "x^3",
+                    || {
+                        let x = x.ok_or(SynthesisError::AssignmentMissing)?;
+                        let mut x2 = x;
+                        x2.square();
+                        Ok((x, x2))
+                    },
+                )?;
+                cs.enforce_zero(|| "x^2 copy", |lc| lc + x2 - x2_copy);
+
+                let out = cs.alloc_input(
+                    || "out",
+                    || {
+                        let mut x3 = x.ok_or(SynthesisError::AssignmentMissing)?;
+                        x3.square();
+                        x3.mul_assign(&x.unwrap());
+                        Ok(x3)
+                    },
+                )?;
+                cs.enforce_zero(|| "out", |lc| lc + x3 - out);
+
+                Ok(())
+            }
+        }
+
+        let rng = &mut test_rng();
+
+        let params =
+            generate_random_parameters::<Bls12, _, _>(CubeCircuit { x: None }, rng).unwrap();
+        let pvk = prepare_verifying_key::<Bls12>(&params.vk);
+
+        let x = Fr::random(rng);
+        let mut out = x;
+        out.square();
+        out.mul_assign(&x);
+
+        let proof = create_random_proof(CubeCircuit { x: Some(x) }, &params, rng).unwrap();
+        assert!(verify_proof(&pvk, &proof, &[out]).unwrap());
+        assert!(!verify_proof(&pvk, &proof, &[x]).unwrap());
+    }
+
+    #[test]
+    fn parallel() {
+        // Every segment proves knowledge of a square root of a public input.
+        struct SquareRoot<E: Engine> {
+            root: Option<E::Fr>,
+        }
+
+        impl<E: Engine> Circuit<E> for SquareRoot<E> {
+            fn synthesize<CS: ConstraintSystem<E>>(
+                self,
+                cs: &mut CS,
+            ) -> Result<(), SynthesisError> {
+                let root = self.root;
+                let square = cs.alloc_input(
+                    || "square",
+                    || {
+                        let mut square = root.ok_or(SynthesisError::AssignmentMissing)?;
+                        square.square();
+                        Ok(square)
+                    },
+                )?;
+                let root = cs.alloc(|| "root", || root.ok_or(SynthesisError::AssignmentMissing))?;
+                cs.enforce(
+                    || "square",
+                    |lc| lc + root,
+                    |lc| lc + root,
+                    |lc| lc + square,
+                );
+
+                Ok(())
+            }
+        }
+
+        struct SquareRoots<E: Engine> {
+            roots: Vec<Option<E::Fr>>,
+        }
+
+        impl<E: Engine> ParallelCircuit<E> for SquareRoots<E> {
+            type Segment = SquareRoot<E>;
+
+            fn segments(self) -> Vec<Self::Segment> {
+                self.roots
+                    .into_iter()
+                    .map(|root| SquareRoot { root })
+                    .collect()
+            }
+        }
+
+        let rng = &mut test_rng();
+
+        let params = generate_random_parameters::<Bls12, _, _>(
+            SequentialCircuit(SquareRoots {
+                roots: vec![None; 5],
+            }),
+            rng,
+        )
+        .unwrap();
+        let pvk = prepare_verifying_key::<Bls12>(&params.vk);
+
+        let roots = (0..5).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+        let squares = roots
+            .iter()
+            .map(|root| {
+                let mut square = *root;
+                square.square();
+                square
+            })
+            .collect::<Vec<_>>();
+        let circuit = || SquareRoots {
+            roots: roots.iter().cloned().map(Some).collect(),
+        };
+
+        let proof = create_random_proof_parallel(circuit(), &params, rng).unwrap();
+        assert!(verify_proof(&pvk, &proof, &squares).unwrap());
+        assert!(!verify_proof(&pvk, &proof, &roots).unwrap());
+
+        // Proving sequentially works with the same parameters.
+        let proof = create_random_proof(SequentialCircuit(circuit()), &params, rng).unwrap();
+        assert!(verify_proof(&pvk, &proof, &squares).unwrap());
+    }
+
+    #[test]
+    fn cached() {
+        struct Cube<E: Engine> {
+            x: Option<E::Fr>,
+        }
+
+        impl<E: Engine> Circuit<E> for Cube<E> {
+            fn synthesize<CS: ConstraintSystem<E>>(
+                self,
+                cs: &mut CS,
+            ) -> Result<(), SynthesisError> {
+                let value = |power| {
+                    let x = self.x.ok_or(SynthesisError::AssignmentMissing)?;
+                    let mut result = x;
+                    for _ in 1..power {
+                        result.mul_assign(&x);
+                    }
+                    Ok(result)
+                };
+
+                let x = cs.alloc(|| "x", || value(1))?;
+                let x2 = cs.alloc(|| "x^2", || value(2))?;
+                let x3 = cs.alloc_input(|| "x^3", || value(3))?;
+                cs.enforce(|| "x^2", |lc| lc + x, |lc| lc + x, |lc| lc + x2);
+                cs.enforce(|| "x^3", |lc| lc + x2, |lc| lc + x + x - x, |lc| lc + x3);
+
+                Ok(())
+            }
+        }
+
+        let rng = &mut test_rng();
+
+        let params = generate_random_parameters::<Bls12, _, _>(Cube { x: None }, rng).unwrap();
+        let pvk = prepare_verifying_key::<Bls12>(&params.vk);
+
+        let cached = CachedCircuit::new(Cube { x: None }).unwrap();
+        assert_eq!(cached.num_inputs(), 2);
+        assert_eq!(cached.num_aux(), 2);
+        assert_eq!(cached.num_constraints(), 2);
+
+        for _ in 0..5 {
+            let x = Fr::random(rng);
+            let mut x3 = x;
+            x3.square();
+            x3.mul_assign(&x);
+
+            let proof =
+                create_random_proof_cached(&cached, Cube { x: Some(x) }, &params, rng).unwrap();
+            assert!(verify_proof(&pvk, &proof, &[x3]).unwrap());
+            assert!(!verify_proof(&pvk, &proof, &[x]).unwrap());
+        }
+
+        // The cache doesn't fit a different circuit.
+        struct Empty;
+
+        impl<E: Engine> Circuit<E> for Empty {
+            fn synthesize<CS: ConstraintSystem<E>>(self, _: &mut CS) -> Result<(), SynthesisError> {
+                Ok(())
+            }
+        }
+
+        let empty = CachedCircuit::new(Empty).unwrap();
+        assert!(
+            create_random_proof_cached(&empty, Cube { x: Some(Fr::one()) }, &params, rng).is_err()
+        );
+    }
 }
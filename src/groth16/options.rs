@@ -0,0 +1,136 @@
+use std::env;
+#[cfg(feature = "gpu")]
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use super::progress::ProgressSink;
+#[cfg(feature = "gpu")]
+use crate::gpu;
+use crate::multicore::Worker;
+
+/// How urgently a proof needs the GPU, which is shared by all proofs on the
+/// machine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Priority {
+    /// Waits until the GPU is free.
+    High,
+    /// Uses the GPU only if it's free right away.
+    Low,
+}
+
+/// Whether the GPU kernels are checked before they are trusted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckMode {
+    /// The kernels are compared against the CPU once per process, a GPU that
+    /// computes something else isn't used.
+    SelfTest,
+    /// The kernels are used right away, like with `BELLMAN_GPU_NO_CHECK`.
+    Skip,
+}
+
+/// The configuration of a single proof, so that proofs with different
+/// configurations can run in the same process, see
+/// [`create_proof_with_opts`](super::create_proof_with_opts) and
+/// [`verify_proof_with_opts`](super::verify_proof_with_opts).
+///
+/// The defaults are the ones of the other entry points, which take the check
+/// mode from the `BELLMAN_GPU_NO_CHECK` environment variable and run on a new
+/// worker.
+#[derive(Clone, Copy)]
+pub struct ProverOptions<'a> {
+    /// Whether the GPU is used if there is one.
+    pub gpu: bool,
+    pub priority: Priority,
+    /// How long a proof of high priority waits for the GPU, forever if
+    /// `None`.
+    pub lock_timeout: Option<Duration>,
+    /// How long a proof of low priority keeps the GPU at most once a proof of
+    /// high priority waits for it. Its multiexps then run in chunks of about
+    /// this duration, and move to the CPU if a proof waits after a chunk.
+    /// Proofs of low priority aren't preempted if `None`.
+    pub preemption_target: Option<Duration>,
+    /// Whether a proof that doesn't get the GPU proves on the CPU instead of
+    /// failing with an I/O error of kind `TimedOut`.
+    pub cpu_fallback: bool,
+    /// The worker all of the parallel work runs on, a new one with a thread
+    /// per CPU if `None`.
+    pub worker: Option<&'a Worker>,
+    /// The memory the prover may allocate besides the parameters, in bytes.
+    /// Proofs that are estimated to need more fail with
+    /// [`SynthesisError::MemoryBudgetExceeded`](crate::SynthesisError::MemoryBudgetExceeded)
+    /// right after synthesis.
+    pub memory_budget: Option<usize>,
+    /// Where a proof that would exceed its memory budget moves the
+    /// evaluations of `b` and `c` and the aux assignment to while it doesn't
+    /// need them, instead of failing. The files are wiped and removed when
+    /// they are read back.
+    pub spill_dir: Option<&'a Path>,
+    /// Where the evaluations of `b` and `c` are streamed to in chunks while
+    /// the circuit is synthesized, so that only the ones of `a` are in memory
+    /// once synthesis is done. They are read back one after the other for
+    /// their FFTs, and wiped and removed then.
+    pub stream_dir: Option<&'a Path>,
+    /// The window size of the CPU multiexps of the proof, the one chosen by
+    /// [`window_size`](crate::multiexp::window_size) for each of them if
+    /// `None`.
+    pub window_size: Option<u32>,
+    pub check: CheckMode,
+    /// Where the stages of the proof are reported to.
+    pub progress: Option<&'a dyn ProgressSink>,
+}
+
+impl<'a> ProverOptions<'a> {
+    /// Options for proving on the CPU only.
+    pub fn cpu() -> Self {
+        ProverOptions {
+            gpu: false,
+            ..Default::default()
+        }
+    }
+
+    /// Takes the GPU lock if the GPU is used. Returns `None` if it isn't, or
+    /// if the proof falls back to the CPU because the GPU is busy.
+    #[cfg(feature = "gpu")]
+    pub(crate) fn lock_gpu(&self) -> io::Result<Option<gpu::LockedFile>> {
+        if !self.gpu {
+            return Ok(None);
+        }
+
+        let lock = match (self.priority, self.lock_timeout) {
+            (Priority::High, None) => Some(gpu::lock()?),
+            (Priority::High, Some(timeout)) => gpu::lock_with_timeout(timeout)?,
+            (Priority::Low, _) => gpu::try_lock()?,
+        };
+        if lock.is_none() {
+            if !self.cpu_fallback {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "the GPU is busy"));
+            }
+            log::info!("GPU is busy, proving on the CPU");
+        }
+        Ok(lock)
+    }
+}
+
+impl<'a> Default for ProverOptions<'a> {
+    fn default() -> Self {
+        ProverOptions {
+            gpu: true,
+            priority: Priority::High,
+            lock_timeout: None,
+            preemption_target: None,
+            cpu_fallback: true,
+            worker: None,
+            memory_budget: None,
+            spill_dir: None,
+            stream_dir: None,
+            window_size: None,
+            check: if env::var("BELLMAN_GPU_NO_CHECK").is_ok() {
+                CheckMode::Skip
+            } else {
+                CheckMode::SelfTest
+            },
+            progress: None,
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::fmt;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+use log::info;
+
+use super::report::{ProvingReport, StageReport};
+use crate::metrics;
+use crate::multicore::CancellationToken;
+use crate::SynthesisError;
+
+/// A stage of creating a proof, reported once it's finished.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProvingStage {
+    /// The circuit was synthesized.
+    Synthesis,
+    /// One of the seven FFTs of the quotient polynomial, e.g. `"ifft a"`.
+    Fft(&'static str),
+    /// One of the eight multiexponentiations, named after its query, e.g.
+    /// `"b_g2 aux"`.
+    Multiexp(&'static str),
+}
+
+impl ProvingStage {
+    /// The rough share of the whole proof this stage takes.
+    fn weight(self) -> f64 {
+        match self {
+            ProvingStage::Synthesis => 0.1,
+            ProvingStage::Fft(_) => 0.2 / 7.0,
+            ProvingStage::Multiexp(_) => 0.7 / 8.0,
+        }
+    }
+}
+
+impl fmt::Display for ProvingStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProvingStage::Synthesis => write!(f, "synthesis"),
+            ProvingStage::Fft(name) => write!(f, "{}", name),
+            ProvingStage::Multiexp(query) => write!(f, "multiexp {}", query),
+        }
+    }
+}
+
+/// A change of whether a proof runs on the GPU, which is shared by all proofs
+/// on the machine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GpuEvent {
+    /// The proof got the GPU.
+    Acquired,
+    /// The GPU is busy, the proof runs on the CPU.
+    FellBack,
+    /// The proof is done and gave up the GPU.
+    Released,
+}
+
+/// Where a stage of a proof ran.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Device {
+    Cpu,
+    Gpu,
+    /// The workers of a distributed proof.
+    Remote,
+}
+
+impl Device {
+    pub fn new(gpu: bool) -> Self {
+        if gpu {
+            Device::Gpu
+        } else {
+            Device::Cpu
+        }
+    }
+}
+
+impl Device {
+    pub fn name(self) -> &'static str {
+        match self {
+            Device::Cpu => "cpu",
+            Device::Gpu => "gpu",
+            Device::Remote => "remote",
+        }
+    }
+}
+
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The current time, if there is a clock: `wasm32-unknown-unknown` has none.
+pub(crate) fn now() -> Option<Instant> {
+    if cfg!(target_arch = "wasm32") {
+        None
+    } else {
+        Some(Instant::now())
+    }
+}
+
+/// Logs how long a part of a proof took with key-value fields under the
+/// `bellperson::groth16::timing` target, e.g.
+/// `stage="coset fft a" device=gpu elapsed_ms=12`. Returns the time taken.
+pub(crate) fn log_timing(
+    stage: &dyn fmt::Display,
+    device: Device,
+    started: Option<Instant>,
+) -> Option<Duration> {
+    let elapsed = started?.elapsed();
+    metrics::stage_finished(&stage.to_string(), device.name(), elapsed);
+
+    info!(
+        target: "bellperson::groth16::timing",
+        "stage=\"{}\" device={} elapsed_ms={}",
+        stage,
+        device,
+        elapsed.as_millis()
+    );
+
+    Some(elapsed)
+}
+
+/// Receives the progress of a proof at its stage boundaries.
+pub trait ProgressSink {
+    /// Called whenever `stage` is finished, `done` is the estimated fraction
+    /// of the proof that is done, between zero and one.
+    fn report(&self, stage: ProvingStage, done: f64);
+
+    /// Called when the proof gets or gives up the GPU, so that schedulers can
+    /// react to where it runs. Does nothing by default.
+    fn gpu(&self, _event: GpuEvent) {}
+}
+
+impl<F: Fn(ProvingStage, f64)> ProgressSink for F {
+    fn report(&self, stage: ProvingStage, done: f64) {
+        self(stage, done)
+    }
+}
+
+/// Sends the progress to a channel, a disconnected receiver is ignored.
+impl ProgressSink for Sender<(ProvingStage, f64)> {
+    fn report(&self, stage: ProvingStage, done: f64) {
+        let _ = self.send((stage, done));
+    }
+}
+
+/// Sums up the finished stages of a single proof, between them the proof can
+/// be cancelled. Every stage is timed, see [`log_timing`], and optionally
+/// recorded in a report.
+pub(crate) struct Progress<'a> {
+    sink: Option<&'a dyn ProgressSink>,
+    cancel: CancellationToken,
+    done: f64,
+    last: Option<Instant>,
+    record: Option<ProvingReport>,
+}
+
+impl<'a> Progress<'a> {
+    pub fn new(sink: Option<&'a dyn ProgressSink>) -> Self {
+        Progress {
+            sink,
+            cancel: CancellationToken::new(),
+            done: 0.0,
+            last: now(),
+            record: None,
+        }
+    }
+
+    pub fn with_report(self) -> Self {
+        Progress {
+            record: Some(ProvingReport::default()),
+            ..self
+        }
+    }
+
+    /// The report, if one is recorded.
+    pub fn report_mut(&mut self) -> Option<&mut ProvingReport> {
+        self.record.as_mut()
+    }
+
+    pub fn take_report(&mut self) -> Option<ProvingReport> {
+        self.record.take()
+    }
+
+    pub fn with_cancel(self, cancel: &CancellationToken) -> Self {
+        Progress {
+            cancel: cancel.clone(),
+            ..self
+        }
+    }
+
+    pub fn cancel(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    /// Reports that the proof got, didn't get or gave up the GPU.
+    #[cfg(feature = "gpu")]
+    pub fn gpu(&self, event: GpuEvent) {
+        info!("GPU event of the proof: {:?}", event);
+        if let Some(sink) = self.sink {
+            sink.gpu(event);
+        }
+    }
+
+    /// Reports the stage, which started when the previous one finished, as
+    /// finished and fails if the proof was cancelled in the meantime.
+    pub fn report(&mut self, stage: ProvingStage, device: Device) -> Result<(), SynthesisError> {
+        let last = self.last;
+        self.report_since(stage, device, last)
+    }
+
+    /// Reports a stage that started at `started` as finished, e.g. one that
+    /// overlaps with others.
+    pub fn report_since(
+        &mut self,
+        stage: ProvingStage,
+        device: Device,
+        started: Option<Instant>,
+    ) -> Result<(), SynthesisError> {
+        let elapsed = log_timing(&stage, device, started);
+        self.last = now();
+        if let Some(ref mut record) = self.record {
+            record.stages.push(StageReport {
+                stage,
+                gpu: device == Device::Gpu,
+                elapsed,
+            });
+        }
+
+        self.done = (self.done + stage.weight()).min(1.0);
+        if let Some(sink) = self.sink {
+            sink.report(stage, self.done);
+        }
+
+        self.cancel.check()
+    }
+}
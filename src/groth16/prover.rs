@@ -1,22 +1,47 @@
-use rand_core::RngCore;
+use rand_core::{CryptoRng, RngCore};
 
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+#[cfg(feature = "gpu")]
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{compiler_fence, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 
-use ff::{Field, PrimeField};
-use futures::Future;
+use crate::bls::Engine;
+use ff::{Field, PrimeField, PrimeFieldRepr};
 use groupy::{CurveAffine, CurveProjective};
 use log::info;
-use paired::Engine;
+use zeroize::Zeroize;
 
+use super::distributed::{Cluster, Query};
+use super::estimate::estimate_prover;
+use super::options::{CheckMode, Priority, ProverOptions};
+#[cfg(feature = "gpu")]
+use super::progress::GpuEvent;
+use super::progress::{log_timing, now, Device, Progress, ProgressSink, ProvingStage};
+use super::report::ProvingReport;
+use super::specialize::{is_fixed, specialize_density};
+use super::spill::{SpillWriter, Spilled};
+use super::witness::ProvingWitness;
 use super::{ParameterSource, Proof};
-use crate::domain::{gpu_fft_supported, EvaluationDomain, Scalar};
+use crate::domain::{gpu_fft_kernel, DomainPrecomputed, EvaluationDomain, Scalar};
 #[cfg(feature = "gpu")]
 use crate::gpu;
-use crate::multicore::Worker;
-use crate::multiexp::{gpu_multiexp_supported, multiexp, DensityTracker, FullDensity};
-use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+use crate::metrics;
+use crate::multicore::{CancellationToken, Worker, WorkerFuture};
+use crate::multiexp::{
+    gpu_multiexp_kernel, multiexp_with_cancel, DensityTracker, FullDensity, QueryDensity,
+    SourceBuilder,
+};
+use crate::{
+    Circuit, ConstraintSystem, Index, LinearCombination, ParallelCircuit, SynthesisError, Variable,
+};
 
-fn eval<E: Engine>(
+pub(super) fn eval<E: Engine>(
     lc: &LinearCombination<E>,
     mut input_density: Option<&mut DensityTracker>,
     mut aux_density: Option<&mut DensityTracker>,
@@ -68,6 +93,358 @@ struct ProvingAssignment<E: Engine> {
     // Assignments of variables
     input_assignment: Vec<E::Fr>,
     aux_assignment: Vec<E::Fr>,
+
+    // Whether the buffers above are kept for another proof
+    retain_buffers: bool,
+
+    // Tables of the FFTs, kept for another proof of the same size
+    precomputed: Option<Arc<DomainPrecomputed<E>>>,
+
+    // Files the evaluations of B and C are moved to during synthesis
+    streams: Option<EvaluationStreams<E>>,
+}
+
+/// The evaluations of B and C are moved to their files in chunks of this
+/// many constraints, see [`ProverOptions::stream_dir`].
+const STREAM_CHUNK: usize = 1 << 12;
+
+/// The files the evaluations of B and C are streamed to.
+struct EvaluationStreams<E: Engine> {
+    b: SpillWriter<Scalar<E>>,
+    c: SpillWriter<Scalar<E>>,
+    // The first error of writing to the files, nothing is written after it.
+    error: Option<io::Error>,
+}
+
+impl<E: Engine> ProvingAssignment<E> {
+    fn new(retain_buffers: bool) -> Self {
+        ProvingAssignment {
+            a_aux_density: DensityTracker::new(),
+            b_input_density: DensityTracker::new(),
+            b_aux_density: DensityTracker::new(),
+            a: vec![],
+            b: vec![],
+            c: vec![],
+            input_assignment: vec![],
+            aux_assignment: vec![],
+            retain_buffers,
+            precomputed: None,
+            streams: None,
+        }
+    }
+
+    /// Streams the evaluations of B and C to new files in `dir` from now on.
+    fn stream_to(&mut self, dir: &Path) -> io::Result<()> {
+        self.streams = Some(EvaluationStreams {
+            b: SpillWriter::create(dir)?,
+            c: SpillWriter::create(dir)?,
+            error: None,
+        });
+        Ok(())
+    }
+
+    /// Moves the evaluations of B and C to their files once there is a chunk
+    /// of them, or right away if `all` is set.
+    fn flush_streams(&mut self, all: bool) {
+        let streams = match self.streams {
+            Some(ref mut streams) if streams.error.is_none() => streams,
+            _ => return,
+        };
+        if !all && self.b.len() < STREAM_CHUNK {
+            return;
+        }
+
+        if let Err(e) = streams
+            .b
+            .append(&self.b)
+            .and_then(|_| streams.c.append(&self.c))
+        {
+            streams.error = Some(e);
+            return;
+        }
+        let zero = Scalar(E::Fr::zero());
+        wipe(&mut self.b, zero);
+        wipe(&mut self.c, zero);
+        self.b.clear();
+        self.c.clear();
+    }
+
+    /// Ends the streams, with the files of all evaluations of B and C.
+    fn finish_streams(&mut self) -> io::Result<Option<(Spilled<Scalar<E>>, Spilled<Scalar<E>>)>> {
+        self.flush_streams(true);
+        match self.streams.take() {
+            Some(streams) => {
+                if let Some(e) = streams.error {
+                    return Err(e);
+                }
+                Ok(Some((streams.b.finish()?, streams.c.finish()?)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Appends a separately synthesized part of the circuit, which has its own
+    /// `ONE` input.
+    fn extend(&mut self, other: ProvingAssignment<E>) {
+        self.a_aux_density.extend(&other.a_aux_density, false);
+        self.b_input_density.extend(&other.b_input_density, true);
+        self.b_aux_density.extend(&other.b_aux_density, false);
+
+        self.a.extend_from_slice(&other.a);
+        self.b.extend_from_slice(&other.b);
+        self.c.extend_from_slice(&other.c);
+        self.flush_streams(false);
+
+        self.input_assignment
+            .extend_from_slice(&other.input_assignment[1..]);
+        self.aux_assignment.extend_from_slice(&other.aux_assignment);
+    }
+
+    /// Empties all buffers while keeping their allocations.
+    fn clear(&mut self) {
+        self.zeroize();
+        self.a_aux_density.clear();
+        self.b_input_density.clear();
+        self.b_aux_density.clear();
+        self.a.clear();
+        self.b.clear();
+        self.c.clear();
+        self.input_assignment.clear();
+        self.aux_assignment.clear();
+        self.streams = None;
+    }
+
+    /// Leaves the inputs that the parameters are specialized to out of the
+    /// input queries, after checking their values.
+    fn remove_fixed_inputs(&mut self, fixed: &[(usize, E::Fr)]) -> Result<(), SynthesisError> {
+        for (i, value) in fixed {
+            match self.input_assignment.get(i + 1) {
+                Some(assigned) if assigned == value => {}
+                Some(_) => return Err(SynthesisError::Unsatisfiable),
+                None => return Err(SynthesisError::MalformedVerifyingKey),
+            }
+        }
+
+        self.b_input_density = specialize_density(&self.b_input_density, fixed);
+        let mut input = 0;
+        self.input_assignment.retain(|_| {
+            input += 1;
+            !is_fixed(fixed, input - 1)
+        });
+
+        Ok(())
+    }
+}
+
+/// The evaluations and assignments are derived from the witness, they are
+/// wiped before the buffers are emptied or freed. Copies left behind when a
+/// buffer grew during synthesis aren't covered.
+impl<E: Engine> Zeroize for ProvingAssignment<E> {
+    fn zeroize(&mut self) {
+        let zero = Scalar(E::Fr::zero());
+        wipe(&mut self.a, zero);
+        wipe(&mut self.b, zero);
+        wipe(&mut self.c, zero);
+        wipe(&mut self.input_assignment, E::Fr::zero());
+        wipe(&mut self.aux_assignment, E::Fr::zero());
+    }
+}
+
+impl<E: Engine> Drop for ProvingAssignment<E> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// Overwrites `values` with `zero` the way `zeroize` does for its own types,
+/// which the field elements aren't: the writes are volatile, so that they
+/// aren't optimized away as dead stores.
+pub(super) fn wipe<T: Copy>(values: &mut [T], zero: T) {
+    for value in values.iter_mut() {
+        unsafe { ptr::write_volatile(value, zero) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Wipes a single secret scalar, e.g. one of the blinding factors.
+fn wipe_scalar<F: Field>(value: &mut F) {
+    wipe(slice::from_mut(value), F::zero());
+}
+
+/// Wipes the representations of an assignment once the multiexps are done
+/// with them. If one of them still holds a reference, e.g. because it failed,
+/// they are freed without.
+fn wipe_reprs<R: PrimeFieldRepr>(reprs: Arc<Vec<R>>) {
+    if let Ok(mut reprs) = Arc::try_unwrap(reprs) {
+        for repr in reprs.iter_mut() {
+            repr.as_mut().zeroize();
+        }
+    }
+}
+
+/// Hands a buffer back after use, keeping it only if the assignment is going to
+/// be reused. It's wiped either way.
+fn recycle<T: Copy>(retain: bool, buffer: &mut Vec<T>, mut used: Vec<T>, zero: T) {
+    wipe(&mut used, zero);
+    if retain {
+        used.clear();
+        *buffer = used;
+    }
+}
+
+/// Moves a vector into a file in `dir`, it's wiped and freed.
+fn spill<T: Copy>(dir: &Path, values: &mut Vec<T>, zero: T) -> io::Result<Spilled<T>> {
+    let spilled = Spilled::write(dir, values)?;
+    wipe(values, zero);
+    *values = vec![];
+    Ok(spilled)
+}
+
+/// Converts an assignment into its representation form. The assignment itself is
+/// wiped and either emptied (keeping its allocation) or freed right away.
+fn into_reprs<F: PrimeField>(assignment: &mut Vec<F>, retain: bool) -> Vec<F::Repr> {
+    let reprs = assignment.iter().map(|s| s.into_repr()).collect();
+    wipe(assignment, F::zero());
+    if retain {
+        assignment.clear();
+    } else {
+        *assignment = vec![];
+    }
+    reprs
+}
+
+/// Takes a density tracker back from a finished multiexp, so that its
+/// allocation can be reused.
+fn reclaim_density(density: Arc<DensityTracker>) -> DensityTracker {
+    Arc::try_unwrap(density).unwrap_or_else(|_| DensityTracker::new())
+}
+
+/// A Groth16 prover that keeps its internal buffers across proofs.
+///
+/// Proving many circuits of the same size with `create_proof` allocates (and
+/// frees) the evaluation and assignment vectors every single time. A `Prover`
+/// holds on to these allocations instead, at the cost of keeping that memory
+/// reserved between proofs. It also keeps the tables of the CPU FFTs for the
+/// next proof of the same size.
+pub struct Prover<E: Engine> {
+    assignment: ProvingAssignment<E>,
+    worker: Worker,
+}
+
+impl<E: Engine> Prover<E> {
+    pub fn new() -> Self {
+        Self::with_worker(Worker::new())
+    }
+
+    /// A prover that runs all of its parallel work on the given worker.
+    pub fn with_worker(worker: Worker) -> Self {
+        Prover {
+            assignment: ProvingAssignment::new(true),
+            worker,
+        }
+    }
+
+    pub fn create_random_proof<C, R, P: ParameterSource<E>>(
+        &mut self,
+        circuit: C,
+        params: P,
+        rng: &mut R,
+    ) -> Result<Proof<E>, SynthesisError>
+    where
+        C: Circuit<E>,
+        R: RngCore + CryptoRng,
+    {
+        let r = E::Fr::random(rng);
+        let s = E::Fr::random(rng);
+
+        self.create_proof(circuit, params, r, s)
+    }
+
+    pub fn create_proof<C, P: ParameterSource<E>>(
+        &mut self,
+        circuit: C,
+        params: P,
+        r: E::Fr,
+        s: E::Fr,
+    ) -> Result<Proof<E>, SynthesisError>
+    where
+        C: Circuit<E>,
+    {
+        self.prove(circuit, params, r, s, Progress::new(None))
+    }
+
+    /// Creates a proof like [`create_proof`](Prover::create_proof), reporting
+    /// to `progress` after every stage.
+    pub fn create_proof_with_progress<C, P: ParameterSource<E>>(
+        &mut self,
+        circuit: C,
+        params: P,
+        r: E::Fr,
+        s: E::Fr,
+        progress: &dyn ProgressSink,
+    ) -> Result<Proof<E>, SynthesisError>
+    where
+        C: Circuit<E>,
+    {
+        self.prove(circuit, params, r, s, Progress::new(Some(progress)))
+    }
+
+    /// Creates a proof like [`create_proof`](Prover::create_proof), which
+    /// fails with [`SynthesisError::Cancelled`] once `cancel` is cancelled.
+    pub fn create_proof_cancellable<C, P: ParameterSource<E>>(
+        &mut self,
+        circuit: C,
+        params: P,
+        r: E::Fr,
+        s: E::Fr,
+        cancel: &CancellationToken,
+    ) -> Result<Proof<E>, SynthesisError>
+    where
+        C: Circuit<E>,
+    {
+        self.prove(
+            circuit,
+            params,
+            r,
+            s,
+            Progress::new(None).with_cancel(cancel),
+        )
+    }
+
+    fn prove<C, P: ParameterSource<E>>(
+        &mut self,
+        circuit: C,
+        params: P,
+        r: E::Fr,
+        s: E::Fr,
+        mut progress: Progress,
+    ) -> Result<Proof<E>, SynthesisError>
+    where
+        C: Circuit<E>,
+    {
+        // Start from empty buffers, a previous proof might have failed half-way through.
+        self.assignment.clear();
+
+        create_proof_inner(
+            &mut self.assignment,
+            |prover| circuit.synthesize(prover),
+            params,
+            r,
+            s,
+            &mut progress,
+            &ProverOptions {
+                worker: Some(&self.worker),
+                ..Default::default()
+            },
+            None,
+        )
+    }
+}
+
+impl<E: Engine> Default for Prover<E> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<E: Engine> ConstraintSystem<E> for ProvingAssignment<E> {
@@ -138,6 +515,44 @@ impl<E: Engine> ConstraintSystem<E> for ProvingAssignment<E> {
             &self.input_assignment,
             &self.aux_assignment,
         )));
+        self.flush_streams(false);
+    }
+
+    fn multiply<F, A, AR>(
+        &mut self,
+        _: A,
+        f: F,
+    ) -> Result<(Variable, Variable, Variable), SynthesisError>
+    where
+        F: FnOnce() -> Result<(E::Fr, E::Fr), SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // Same as allocating the three variables and enforcing the
+        // constraint, but without evaluating any linear combinations.
+        let (a, b) = f()?;
+        let mut c = a;
+        c.mul_assign(&b);
+
+        let first = self.aux_assignment.len();
+        self.aux_assignment.extend_from_slice(&[a, b, c]);
+        for _ in 0..3 {
+            self.a_aux_density.add_element();
+            self.b_aux_density.add_element();
+        }
+        self.a_aux_density.inc(first);
+        self.b_aux_density.inc(first + 1);
+
+        self.a.push(Scalar(a));
+        self.b.push(Scalar(b));
+        self.c.push(Scalar(c));
+        self.flush_streams(false);
+
+        Ok((
+            Variable(Index::Aux(first)),
+            Variable(Index::Aux(first + 1)),
+            Variable(Index::Aux(first + 2)),
+        ))
     }
 
     fn push_namespace<NR, N>(&mut self, _: N)
@@ -157,6 +572,9 @@ impl<E: Engine> ConstraintSystem<E> for ProvingAssignment<E> {
     }
 }
 
+/// Creates a proof with blinding factors drawn from `rng`, which has to be
+/// cryptographically secure. Tests that need reproducible proofs can wrap a
+/// seeded RNG in an `InsecureRng`, which requires the `testing` feature.
 pub fn create_random_proof<E, C, R, P: ParameterSource<E>>(
     circuit: C,
     params: P,
@@ -165,7 +583,7 @@ pub fn create_random_proof<E, C, R, P: ParameterSource<E>>(
 where
     E: Engine,
     C: Circuit<E>,
-    R: RngCore,
+    R: RngCore + CryptoRng,
 {
     let r = E::Fr::random(rng);
     let s = E::Fr::random(rng);
@@ -175,7 +593,7 @@ where
 
 pub fn create_proof<E, C, P: ParameterSource<E>>(
     circuit: C,
-    mut params: P,
+    params: P,
     r: E::Fr,
     s: E::Fr,
 ) -> Result<Proof<E>, SynthesisError>
@@ -183,176 +601,1235 @@ where
     E: Engine,
     C: Circuit<E>,
 {
-    #[cfg(feature = "gpu")]
-    let lock = gpu::lock()?;
-
-    let mut prover = ProvingAssignment {
-        a_aux_density: DensityTracker::new(),
-        b_input_density: DensityTracker::new(),
-        b_aux_density: DensityTracker::new(),
-        a: vec![],
-        b: vec![],
-        c: vec![],
-        input_assignment: vec![],
-        aux_assignment: vec![],
-    };
+    create_proof_with_worker(circuit, params, r, s, &Worker::new())
+}
 
-    prover.alloc_input(|| "", || Ok(E::Fr::one()))?;
+pub fn create_random_proof_with_worker<E, C, R, P: ParameterSource<E>>(
+    circuit: C,
+    params: P,
+    rng: &mut R,
+    worker: &Worker,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+    R: RngCore + CryptoRng,
+{
+    let r = E::Fr::random(rng);
+    let s = E::Fr::random(rng);
 
-    circuit.synthesize(&mut prover)?;
+    create_proof_with_worker::<E, C, P>(circuit, params, r, s, worker)
+}
 
-    for i in 0..prover.input_assignment.len() {
-        prover.enforce(|| "", |lc| lc + Variable(Index::Input(i)), |lc| lc, |lc| lc);
-    }
+/// Creates a proof like [`create_proof`], but runs all of the parallel work on
+/// the given worker instead of on a new one with a thread per CPU.
+pub fn create_proof_with_worker<E, C, P: ParameterSource<E>>(
+    circuit: C,
+    params: P,
+    r: E::Fr,
+    s: E::Fr,
+    worker: &Worker,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    create_proof_inner(
+        &mut ProvingAssignment::new(false),
+        |prover| circuit.synthesize(prover),
+        params,
+        r,
+        s,
+        &mut Progress::new(None),
+        &ProverOptions {
+            worker: Some(worker),
+            ..Default::default()
+        },
+        None,
+    )
+}
 
-    let worker = Worker::new();
+pub fn create_random_proof_with_opts<E, C, R, P: ParameterSource<E>>(
+    circuit: C,
+    params: P,
+    rng: &mut R,
+    opts: &ProverOptions,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+    R: RngCore + CryptoRng,
+{
+    let r = E::Fr::random(rng);
+    let s = E::Fr::random(rng);
 
-    let vk = params.get_vk(prover.input_assignment.len())?;
+    create_proof_with_opts::<E, C, P>(circuit, params, r, s, opts)
+}
 
-    let n = prover.a.len();
-    let mut log_d = 0u32;
-    while (1 << log_d) < n {
-        log_d += 1;
-    }
+/// Creates a proof like [`create_proof`], configured by `opts` instead of the
+/// environment, see [`ProverOptions`].
+///
+/// E.g. `ProverOptions { priority, ..Default::default() }` lets the proof wait
+/// for the GPU with the given [`Priority`]. The options only apply to this
+/// proof, so proofs of different priorities can run on different threads at
+/// the same time.
+pub fn create_proof_with_opts<E, C, P: ParameterSource<E>>(
+    circuit: C,
+    params: P,
+    r: E::Fr,
+    s: E::Fr,
+    opts: &ProverOptions,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    create_proof_inner(
+        &mut ProvingAssignment::new(false),
+        |prover| circuit.synthesize(prover),
+        params,
+        r,
+        s,
+        &mut Progress::new(opts.progress),
+        opts,
+        None,
+    )
+}
 
-    let a = {
-        let mut fft_kern = gpu_fft_supported::<E>(log_d).ok();
-        if fft_kern.is_some() {
-            info!("GPU FFT is supported!");
-        } else {
-            info!("GPU FFT is NOT supported!");
-        }
+pub fn create_random_proof_distributed<E, C, R, P: ParameterSource<E>>(
+    circuit: C,
+    params: P,
+    rng: &mut R,
+    cluster: &Cluster,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+    R: RngCore + CryptoRng,
+{
+    let r = E::Fr::random(rng);
+    let s = E::Fr::random(rng);
 
-        let mut a = EvaluationDomain::from_coeffs(prover.a)?;
-        let mut b = EvaluationDomain::from_coeffs(prover.b)?;
-        let mut c = EvaluationDomain::from_coeffs(prover.c)?;
-
-        a.ifft(&worker, &mut fft_kern)?;
-        a.coset_fft(&worker, &mut fft_kern)?;
-        b.ifft(&worker, &mut fft_kern)?;
-        b.coset_fft(&worker, &mut fft_kern)?;
-        c.ifft(&worker, &mut fft_kern)?;
-        c.coset_fft(&worker, &mut fft_kern)?;
-
-        a.mul_assign(&worker, &b);
-        drop(b);
-        a.sub_assign(&worker, &c);
-        drop(c);
-        a.divide_by_z_on_coset(&worker, &mut fft_kern)?;
-        a.icoset_fft(&worker, &mut fft_kern)?;
-        let mut a = a.into_coeffs();
-        let a_len = a.len() - 1;
-        a.truncate(a_len);
-        // TODO: parallelize if it's even helpful
-        Arc::new(a.into_iter().map(|s| s.0.into_repr()).collect::<Vec<_>>())
-    };
+    create_proof_distributed::<E, C, P>(circuit, params, r, s, cluster)
+}
 
-    let mut multiexp_kern = gpu_multiexp_supported::<E>().ok();
-    if multiexp_kern.is_some() {
-        info!("GPU Multiexp is supported!");
-    } else {
-        info!("GPU Multiexp is NOT supported!");
-    }
+/// Creates a proof like [`create_proof`], but computes the multiexps on the
+/// remote workers of `cluster`, which have to serve the same parameters, see
+/// [`distributed`](super::distributed).
+pub fn create_proof_distributed<E, C, P: ParameterSource<E>>(
+    circuit: C,
+    params: P,
+    r: E::Fr,
+    s: E::Fr,
+    cluster: &Cluster,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    create_proof_inner(
+        &mut ProvingAssignment::new(false),
+        |prover| circuit.synthesize(prover),
+        params,
+        r,
+        s,
+        &mut Progress::new(None),
+        &ProverOptions::default(),
+        Some(cluster),
+    )
+}
 
-    let h = multiexp(
-        &worker,
-        params.get_h(a.len())?,
-        FullDensity,
-        a,
-        &mut multiexp_kern,
-    );
+/// Creates a proof like [`create_proof`], reporting to `progress` after every
+/// stage.
+pub fn create_proof_with_progress<E, C, P: ParameterSource<E>>(
+    circuit: C,
+    params: P,
+    r: E::Fr,
+    s: E::Fr,
+    progress: &dyn ProgressSink,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    create_proof_inner(
+        &mut ProvingAssignment::new(false),
+        |prover| circuit.synthesize(prover),
+        params,
+        r,
+        s,
+        &mut Progress::new(Some(progress)),
+        &ProverOptions::default(),
+        None,
+    )
+}
 
-    // TODO: parallelize if it's even helpful
-    let input_assignment = Arc::new(
-        prover
-            .input_assignment
-            .into_iter()
-            .map(|s| s.into_repr())
-            .collect::<Vec<_>>(),
-    );
-    let aux_assignment = Arc::new(
-        prover
-            .aux_assignment
-            .into_iter()
-            .map(|s| s.into_repr())
-            .collect::<Vec<_>>(),
-    );
+/// Creates a proof like [`create_proof`] together with a report of the
+/// circuit size, the devices and the time of every stage, see
+/// [`ProvingReport`].
+pub fn create_proof_with_report<E, C, P: ParameterSource<E>>(
+    circuit: C,
+    params: P,
+    r: E::Fr,
+    s: E::Fr,
+) -> Result<(Proof<E>, ProvingReport), SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    let mut progress = Progress::new(None).with_report();
+    let proof = create_proof_inner(
+        &mut ProvingAssignment::new(false),
+        |prover| circuit.synthesize(prover),
+        params,
+        r,
+        s,
+        &mut progress,
+        &ProverOptions::default(),
+        None,
+    )?;
+    let report = progress.take_report().expect("the report is recorded");
 
-    let l = multiexp(
-        &worker,
-        params.get_l(aux_assignment.len())?,
-        FullDensity,
-        aux_assignment.clone(),
-        &mut multiexp_kern,
-    );
+    Ok((proof, report))
+}
 
-    let a_aux_density_total = prover.a_aux_density.get_total_density();
+/// Creates a proof like [`create_proof`], which fails with
+/// [`SynthesisError::Cancelled`] once `cancel` is cancelled. The cancellation
+/// is noticed between the FFTs and the multiexps, and within multiexps on the
+/// CPU.
+pub fn create_proof_cancellable<E, C, P: ParameterSource<E>>(
+    circuit: C,
+    params: P,
+    r: E::Fr,
+    s: E::Fr,
+    cancel: &CancellationToken,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    create_proof_inner(
+        &mut ProvingAssignment::new(false),
+        |prover| circuit.synthesize(prover),
+        params,
+        r,
+        s,
+        &mut Progress::new(None).with_cancel(cancel),
+        &ProverOptions::default(),
+        None,
+    )
+}
 
-    let (a_inputs_source, a_aux_source) =
-        params.get_a(input_assignment.len(), a_aux_density_total)?;
+/// Creates a proof like [`create_proof`], which fails with
+/// [`SynthesisError::Timeout`] if it isn't done by `deadline`. The deadline is
+/// checked at the same points as the cancellation of
+/// [`create_proof_cancellable`], which also accepts a token with a deadline.
+pub fn create_proof_with_deadline<E, C, P: ParameterSource<E>>(
+    circuit: C,
+    params: P,
+    r: E::Fr,
+    s: E::Fr,
+    deadline: Instant,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    create_proof_cancellable(
+        circuit,
+        params,
+        r,
+        s,
+        &CancellationToken::with_deadline(deadline),
+    )
+}
 
-    let a_inputs = multiexp(
-        &worker,
-        a_inputs_source,
-        FullDensity,
-        input_assignment.clone(),
-        &mut multiexp_kern,
-    );
-    let a_aux = multiexp(
-        &worker,
-        a_aux_source,
-        Arc::new(prover.a_aux_density),
-        aux_assignment.clone(),
-        &mut multiexp_kern,
-    );
+pub fn create_random_proof_parallel<E, C, R, P: ParameterSource<E>>(
+    circuit: C,
+    params: P,
+    rng: &mut R,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: ParallelCircuit<E>,
+    R: RngCore + CryptoRng,
+{
+    let r = E::Fr::random(rng);
+    let s = E::Fr::random(rng);
 
-    let b_input_density = Arc::new(prover.b_input_density);
-    let b_input_density_total = b_input_density.get_total_density();
-    let b_aux_density = Arc::new(prover.b_aux_density);
-    let b_aux_density_total = b_aux_density.get_total_density();
+    create_proof_parallel::<E, C, P>(circuit, params, r, s)
+}
+
+/// Creates a proof for a circuit whose segments are synthesized in parallel. The
+/// parameters are the ones of the [`SequentialCircuit`] of the same circuit.
+///
+/// [`SequentialCircuit`]: crate::SequentialCircuit
+pub fn create_proof_parallel<E, C, P: ParameterSource<E>>(
+    circuit: C,
+    params: P,
+    r: E::Fr,
+    s: E::Fr,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: ParallelCircuit<E>,
+{
+    let worker = Worker::new();
+    create_proof_inner(
+        &mut ProvingAssignment::new(false),
+        |prover| {
+            let mut segments = circuit.segments().into_iter().map(Some).collect::<Vec<_>>();
+            let mut results = (0..segments.len()).map(|_| None).collect::<Vec<_>>();
+
+            worker.scope(segments.len(), |scope, chunk| {
+                for (segments, results) in segments.chunks_mut(chunk).zip(results.chunks_mut(chunk))
+                {
+                    scope.spawn(move |_| {
+                        for (segment, result) in segments.iter_mut().zip(results.iter_mut()) {
+                            let segment = segment.take().expect("every segment is taken once");
+                            *result = Some(synthesize_segment(segment));
+                        }
+                    });
+                }
+            });
+
+            // Merging in order leads to the same assignment as sequential synthesis.
+            for result in results {
+                prover.extend(result.expect("every segment was synthesized")?);
+            }
+
+            Ok(())
+        },
+        params,
+        r,
+        s,
+        &mut Progress::new(None),
+        &ProverOptions {
+            worker: Some(&worker),
+            ..Default::default()
+        },
+        None,
+    )
+}
+
+fn synthesize_segment<E, C>(segment: C) -> Result<ProvingAssignment<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    let mut prover = ProvingAssignment::new(false);
+    prover.alloc_input(|| "", || Ok(E::Fr::one()))?;
+    segment.synthesize(&mut prover)?;
+
+    Ok(prover)
+}
+
+/// The constraints of a circuit together with the densities of its queries,
+/// which only depend on the structure of the circuit. Proofs for many
+/// witnesses can be created from it with [`create_proof_cached`], which only
+/// computes the assignments instead of synthesizing the whole circuit again.
+pub struct CachedCircuit<E: Engine> {
+    num_inputs: usize,
+    num_aux: usize,
+    constraints: Vec<(
+        LinearCombination<E>,
+        LinearCombination<E>,
+        LinearCombination<E>,
+    )>,
+    a_aux_density: DensityTracker,
+    b_input_density: DensityTracker,
+    b_aux_density: DensityTracker,
+}
+
+impl<E: Engine> CachedCircuit<E> {
+    /// Synthesizes the circuit, no assignments are computed.
+    pub fn new<C: Circuit<E>>(circuit: C) -> Result<Self, SynthesisError> {
+        let mut b_input_density = DensityTracker::new();
+        // The `ONE` input.
+        b_input_density.add_element();
+
+        let mut cached = CachedCircuit {
+            num_inputs: 1,
+            num_aux: 0,
+            constraints: vec![],
+            a_aux_density: DensityTracker::new(),
+            b_input_density,
+            b_aux_density: DensityTracker::new(),
+        };
+        circuit.synthesize(&mut cached)?;
+
+        Ok(cached)
+    }
+
+    /// The number of inputs, including `ONE`.
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    pub fn num_aux(&self) -> usize {
+        self.num_aux
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    pub(super) fn b_input_density(&self) -> &DensityTracker {
+        &self.b_input_density
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for CachedCircuit<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let index = self.num_aux;
+        self.num_aux += 1;
+
+        self.a_aux_density.add_element();
+        self.b_aux_density.add_element();
+
+        Ok(Variable(Index::Aux(index)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let index = self.num_inputs;
+        self.num_inputs += 1;
+
+        self.b_input_density.add_element();
+
+        Ok(Variable(Index::Input(index)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+
+        // The same densities as `ProvingAssignment::enforce` tracks.
+        for (var, _) in a.iter() {
+            if let Variable(Index::Aux(i)) = var {
+                self.a_aux_density.inc(*i);
+            }
+        }
+        for (var, _) in b.iter() {
+            match var {
+                Variable(Index::Input(i)) => self.b_input_density.inc(*i),
+                Variable(Index::Aux(i)) => self.b_aux_density.inc(*i),
+            }
+        }
+
+        self.constraints.push((a, b, c));
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// Computes the assignments of a circuit, ignoring its constraints.
+struct WitnessCS<E: Engine> {
+    input_assignment: Vec<E::Fr>,
+    aux_assignment: Vec<E::Fr>,
+}
+
+impl<E: Engine> ConstraintSystem<E> for WitnessCS<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.aux_assignment.push(f()?);
+
+        Ok(Variable(Index::Aux(self.aux_assignment.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.input_assignment.push(f()?);
+
+        Ok(Variable(Index::Input(self.input_assignment.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, _: LA, _: LB, _: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        // The constraints are cached already.
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+pub fn create_random_proof_cached<E, C, R, P: ParameterSource<E>>(
+    cached: &CachedCircuit<E>,
+    circuit: C,
+    params: P,
+    rng: &mut R,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+    R: RngCore + CryptoRng,
+{
+    let r = E::Fr::random(rng);
+    let s = E::Fr::random(rng);
+
+    create_proof_cached::<E, C, P>(cached, circuit, params, r, s)
+}
+
+/// Creates a proof for a circuit that was synthesized into `cached` before. The
+/// circuit is only used for computing the assignments, its constraints are
+/// taken from the cache.
+pub fn create_proof_cached<E, C, P: ParameterSource<E>>(
+    cached: &CachedCircuit<E>,
+    circuit: C,
+    params: P,
+    r: E::Fr,
+    s: E::Fr,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    create_proof_with_assignments(cached, |witness| circuit.synthesize(witness), params, r, s)
+}
+
+pub fn create_random_proof_with_witness<E, R, P: ParameterSource<E>>(
+    cached: &CachedCircuit<E>,
+    witness: ProvingWitness<E>,
+    params: P,
+    rng: &mut R,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    R: RngCore + CryptoRng,
+{
+    let r = E::Fr::random(rng);
+    let s = E::Fr::random(rng);
+
+    create_proof_with_witness::<E, P>(cached, witness, params, r, s)
+}
+
+/// Creates a proof from a witness that was computed separately, e.g. by
+/// another process, for the circuit that was synthesized into `cached`.
+pub fn create_proof_with_witness<E, P: ParameterSource<E>>(
+    cached: &CachedCircuit<E>,
+    mut witness: ProvingWitness<E>,
+    params: P,
+    r: E::Fr,
+    s: E::Fr,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+{
+    // A witness of a different circuit than the cached one.
+    if witness.a_aux_density != cached.a_aux_density
+        || witness.b_input_density != cached.b_input_density
+        || witness.b_aux_density != cached.b_aux_density
+    {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+
+    create_proof_with_assignments(
+        cached,
+        |assignments| {
+            assignments.input_assignment = mem::replace(&mut witness.input_assignment, vec![]);
+            assignments.aux_assignment = mem::replace(&mut witness.aux_assignment, vec![]);
+            Ok(())
+        },
+        params,
+        r,
+        s,
+    )
+}
+
+/// Creates a proof for the constraints of `cached` with the assignments that
+/// `assign` computes.
+fn create_proof_with_assignments<E, P: ParameterSource<E>>(
+    cached: &CachedCircuit<E>,
+    assign: impl FnOnce(&mut WitnessCS<E>) -> Result<(), SynthesisError>,
+    params: P,
+    r: E::Fr,
+    s: E::Fr,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+{
+    let worker = Worker::new();
+    create_proof_inner(
+        &mut ProvingAssignment::new(false),
+        |prover| {
+            let mut witness = WitnessCS::<E> {
+                input_assignment: vec![E::Fr::one()],
+                aux_assignment: vec![],
+            };
+            assign(&mut witness)?;
+            // A different circuit than the cached one.
+            if witness.input_assignment.len() != cached.num_inputs
+                || witness.aux_assignment.len() != cached.num_aux
+            {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+
+            let n = cached.constraints.len();
+            let mut a = vec![Scalar::<E>(E::Fr::zero()); n];
+            let mut b = vec![Scalar::<E>(E::Fr::zero()); n];
+            let mut c = vec![Scalar::<E>(E::Fr::zero()); n];
+
+            worker.scope(n, |scope, chunk| {
+                for (((constraints, a), b), c) in cached
+                    .constraints
+                    .chunks(chunk)
+                    .zip(a.chunks_mut(chunk))
+                    .zip(b.chunks_mut(chunk))
+                    .zip(c.chunks_mut(chunk))
+                {
+                    let inputs = &witness.input_assignment;
+                    let aux = &witness.aux_assignment;
+                    scope.spawn(move |_| {
+                        for ((((lc_a, lc_b, lc_c), a), b), c) in constraints
+                            .iter()
+                            .zip(a.iter_mut())
+                            .zip(b.iter_mut())
+                            .zip(c.iter_mut())
+                        {
+                            *a = Scalar(eval(lc_a, None, None, inputs, aux));
+                            *b = Scalar(eval(lc_b, None, None, inputs, aux));
+                            *c = Scalar(eval(lc_c, None, None, inputs, aux));
+                        }
+                    });
+                }
+            });
+
+            prover.a = a;
+            prover.b = b;
+            prover.c = c;
+            prover.input_assignment = witness.input_assignment;
+            prover.aux_assignment = witness.aux_assignment;
+            prover.a_aux_density = cached.a_aux_density.clone();
+            prover.b_input_density = cached.b_input_density.clone();
+            prover.b_aux_density = cached.b_aux_density.clone();
+
+            Ok(())
+        },
+        params,
+        r,
+        s,
+        &mut Progress::new(None),
+        &ProverOptions {
+            worker: Some(&worker),
+            ..Default::default()
+        },
+        None,
+    )
+}
+
+/// Waits for the multiexp of `query`, which was started at `started`, and
+/// reports it as done. On the CPU the multiexps overlap, so their times do too.
+fn wait<G>(
+    (started, multiexp): (Option<Instant>, WorkerFuture<Result<G, SynthesisError>>),
+    query: &'static str,
+    device: Device,
+    progress: &mut Progress,
+) -> Result<G, SynthesisError> {
+    let result = multiexp.wait()?;
+    progress.report_since(ProvingStage::Multiexp(query), device, started)?;
+
+    Ok(result)
+}
+
+/// Runs a multiexp on the workers of `cluster` if the proof is distributed,
+/// otherwise locally over `bases`.
+#[allow(clippy::too_many_arguments)]
+fn multiexp_on<E, G, Q, D, S>(
+    cluster: Option<&Cluster>,
+    query: Query,
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<E::Fr as PrimeField>::Repr>>,
+    kern: &mut Option<gpu::MultiexpKernel<E>>,
+    cancel: &CancellationToken,
+    window: Option<u32>,
+) -> WorkerFuture<Result<G::Projective, SynthesisError>>
+where
+    E: Engine,
+    G: CurveAffine<Engine = E>,
+    for<'a> &'a Q: QueryDensity,
+    D: Send + Sync + 'static + Clone + AsRef<Q>,
+    S: SourceBuilder<G>,
+{
+    match cluster {
+        Some(cluster) => {
+            cluster.multiexp::<E, G, Q, D>(pool, query, density_map, exponents, cancel)
+        }
+        None => multiexp_with_cancel(
+            pool,
+            bases,
+            density_map,
+            exponents,
+            kern,
+            Some(cancel.clone()),
+            window,
+        ),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_proof_inner<E, P: ParameterSource<E>>(
+    prover: &mut ProvingAssignment<E>,
+    synthesize: impl FnOnce(&mut ProvingAssignment<E>) -> Result<(), SynthesisError>,
+    params: P,
+    mut r: E::Fr,
+    mut s: E::Fr,
+    progress: &mut Progress,
+    opts: &ProverOptions,
+    cluster: Option<&Cluster>,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+{
+    let default_worker;
+    let worker = match opts.worker {
+        Some(worker) => worker,
+        None => {
+            default_worker = Worker::new();
+            &default_worker
+        }
+    };
+
+    metrics::proof_started();
+    let proof = prove(
+        prover, worker, synthesize, params, r, s, progress, opts, cluster,
+    );
+    metrics::proof_finished(proof.is_ok());
+    wipe_scalar(&mut r);
+    wipe_scalar(&mut s);
+
+    proof
+}
+
+#[allow(clippy::too_many_arguments)]
+fn prove<E, P: ParameterSource<E>>(
+    prover: &mut ProvingAssignment<E>,
+    worker: &Worker,
+    synthesize: impl FnOnce(&mut ProvingAssignment<E>) -> Result<(), SynthesisError>,
+    mut params: P,
+    r: E::Fr,
+    s: E::Fr,
+    progress: &mut Progress,
+    opts: &ProverOptions,
+    cluster: Option<&Cluster>,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+{
+    #[cfg(feature = "gpu")]
+    let lock = {
+        let started = now();
+        let lock = opts.lock_gpu()?;
+        if let (Some(report), true) = (progress.report_mut(), opts.gpu) {
+            report.gpu_lock_wait = started.map(|started| started.elapsed());
+        }
+        lock
+    };
+    #[cfg(feature = "gpu")]
+    let gpu = lock.is_some();
+    #[cfg(feature = "gpu")]
+    {
+        if gpu {
+            progress.gpu(GpuEvent::Acquired);
+        } else if opts.gpu {
+            progress.gpu(GpuEvent::FellBack);
+        }
+    }
+    #[cfg(not(feature = "gpu"))]
+    let gpu = opts.gpu;
+    let self_test = opts.check == CheckMode::SelfTest;
+
+    if let Some(dir) = opts.stream_dir {
+        prover.stream_to(dir)?;
+    }
+    prover.alloc_input(|| "", || Ok(E::Fr::one()))?;
+
+    synthesize(prover)?;
+
+    for i in 0..prover.input_assignment.len() {
+        prover.enforce(|| "", |lc| lc + Variable(Index::Input(i)), |lc| lc, |lc| lc);
+    }
+    if !params.fixed_inputs().is_empty() {
+        prover.remove_fixed_inputs(params.fixed_inputs())?;
+    }
+    let streamed = prover.finish_streams()?;
+    progress.report(ProvingStage::Synthesis, Device::Cpu)?;
+
+    let vk = params.get_vk(prover.input_assignment.len())?;
+
+    let n = prover.a.len();
+    let mut log_d = 0u32;
+    while (1 << log_d) < n {
+        log_d += 1;
+    }
+
+    if let Some(report) = progress.report_mut() {
+        report.num_inputs = prover.input_assignment.len();
+        report.num_aux = prover.aux_assignment.len();
+        report.num_constraints = n;
+        report.a_aux_density = prover.a_aux_density.get_total_density();
+        report.b_input_density = prover.b_input_density.get_total_density();
+        report.b_aux_density = prover.b_aux_density.get_total_density();
+        report.fft_size = 1 << log_d;
+        report.peak_memory_bytes =
+            estimate_prover::<E>(report.num_inputs, report.num_aux, report.fft_size).ram_bytes;
+    }
+    let mut spill_dir = None;
+    if let Some(budget) = opts.memory_budget {
+        let needed = estimate_prover::<E>(
+            prover.input_assignment.len(),
+            prover.aux_assignment.len(),
+            1 << log_d,
+        )
+        .ram_bytes;
+        if needed > budget {
+            match opts.spill_dir {
+                Some(dir) => {
+                    info!(
+                        "The proof needs about {} bytes, spilling to {}",
+                        needed,
+                        dir.display()
+                    );
+                    spill_dir = Some(dir);
+                }
+                None => return Err(SynthesisError::MemoryBudgetExceeded(budget)),
+            }
+        }
+    }
+    // Only a is needed for its FFTs, the others wait on disk for their turn.
+    let (spilled_b, spilled_c) = match (streamed, spill_dir) {
+        (Some((b, c)), _) => (Some(b), Some(c)),
+        (None, Some(dir)) => (
+            Some(spill(dir, &mut prover.b, Scalar(E::Fr::zero()))?),
+            Some(spill(dir, &mut prover.c, Scalar(E::Fr::zero()))?),
+        ),
+        (None, None) => (None, None),
+    };
+    let spilled_aux = match spill_dir {
+        Some(dir) => Some(spill(dir, &mut prover.aux_assignment, E::Fr::zero())?),
+        None => None,
+    };
+
+    let a = {
+        let mut fft_kern = if gpu {
+            gpu_fft_kernel::<E>(log_d, self_test).ok()
+        } else {
+            None
+        };
+        if fft_kern.is_some() {
+            info!("GPU FFT is supported!");
+        } else if gpu {
+            info!("GPU FFT is NOT supported!");
+            #[cfg(feature = "gpu")]
+            metrics::gpu_fallback("fft");
+        }
+        let device = Device::new(fft_kern.is_some());
+        let zero = Scalar(E::Fr::zero());
+
+        let mut a = EvaluationDomain::from_coeffs(mem::replace(&mut prover.a, vec![]))?;
+
+        // The tables are shared by all six FFTs, only the CPU uses them.
+        let precomputed = if fft_kern.is_none() {
+            let precomputed = match prover.precomputed {
+                Some(ref precomputed) if precomputed.size() == a.size() => precomputed.clone(),
+                _ => Arc::new(a.precompute(worker)),
+            };
+            if prover.retain_buffers {
+                prover.precomputed = Some(precomputed.clone());
+            }
+            Some(precomputed)
+        } else {
+            None
+        };
+
+        let to_coset = |domain: &mut EvaluationDomain<E, Scalar<E>>,
+                        kern: &mut Option<gpu::FFTKernel<E>>,
+                        progress: &mut Progress,
+                        name: [&'static str; 2]|
+         -> Result<(), SynthesisError> {
+            if let Some(ref precomputed) = precomputed {
+                domain.set_precomputed(precomputed.clone());
+            }
+            domain.ifft(worker, kern)?;
+            progress.report(ProvingStage::Fft(name[0]), device)?;
+            domain.coset_fft(worker, kern)?;
+            progress.report(ProvingStage::Fft(name[1]), device)?;
+            Ok(())
+        };
+
+        if fft_kern.is_some() && spilled_b.is_none() {
+            // On the GPU the FFTs of a and b run as batches, which saves
+            // launches and transfers. c is folded in afterwards like on the
+            // CPU, so that at most two of the domains are expanded at the
+            // same time.
+            let mut b = EvaluationDomain::from_coeffs(mem::replace(&mut prover.b, vec![]))?;
+            EvaluationDomain::ifft_many(&mut [&mut a, &mut b], worker, &mut fft_kern)?;
+            for name in &["ifft a", "ifft b"] {
+                progress.report(ProvingStage::Fft(*name), device)?;
+            }
+            EvaluationDomain::coset_fft_many(&mut [&mut a, &mut b], worker, &mut fft_kern)?;
+            for name in &["coset fft a", "coset fft b"] {
+                progress.report(ProvingStage::Fft(*name), device)?;
+            }
+            a.mul_assign(worker, &b, &mut fft_kern)?;
+            recycle(prover.retain_buffers, &mut prover.b, b.into_coeffs(), zero);
+        } else {
+            // b and c are folded into a one after the other, so that at most
+            // two of the domains are expanded at the same time: b is freed
+            // before c is padded and transformed.
+            to_coset(&mut a, &mut fft_kern, progress, ["ifft a", "coset fft a"])?;
+            if let Some(spilled) = spilled_b {
+                prover.b = spilled.read()?;
+            }
+            let mut b = EvaluationDomain::from_coeffs(mem::replace(&mut prover.b, vec![]))?;
+            to_coset(&mut b, &mut fft_kern, progress, ["ifft b", "coset fft b"])?;
+            a.mul_assign(worker, &b, &mut fft_kern)?;
+            recycle(prover.retain_buffers, &mut prover.b, b.into_coeffs(), zero);
+        }
+
+        if let Some(spilled) = spilled_c {
+            prover.c = spilled.read()?;
+        }
+        let mut c = EvaluationDomain::from_coeffs(mem::replace(&mut prover.c, vec![]))?;
+        to_coset(&mut c, &mut fft_kern, progress, ["ifft c", "coset fft c"])?;
+        a.sub_assign(worker, &c, &mut fft_kern)?;
+        recycle(prover.retain_buffers, &mut prover.c, c.into_coeffs(), zero);
+
+        a.divide_by_z_on_coset(worker, &mut fft_kern)?;
+        a.icoset_fft(worker, &mut fft_kern)?;
+        progress.report(ProvingStage::Fft("icoset fft h"), device)?;
+        let mut a = a.into_coeffs();
+        let a_len = a.len() - 1;
+        a.truncate(a_len);
+        let mut a_repr = vec![<E::Fr as PrimeField>::Repr::default(); a.len()];
+        if !a.is_empty() {
+            worker.scope(a.len(), |scope, chunk| {
+                for (a, a_repr) in a.chunks(chunk).zip(a_repr.chunks_mut(chunk)) {
+                    scope.spawn(move |_| {
+                        for (s, repr) in a.iter().zip(a_repr.iter_mut()) {
+                            *repr = s.0.into_repr();
+                        }
+                    });
+                }
+            });
+        }
+        let a_repr = Arc::new(a_repr);
+        recycle(prover.retain_buffers, &mut prover.a, a, zero);
+        a_repr
+    };
+
+    // The time spent in the checks for provers of higher priority, in nanoseconds.
+    #[cfg(feature = "gpu")]
+    let priority_checks = Arc::new(AtomicU64::new(0));
+    let (mut multiexp_kern, device) = match cluster {
+        Some(cluster) => {
+            info!(
+                "Distributing the multiexps over {} workers",
+                cluster.num_workers()
+            );
+            (None, Device::Remote)
+        }
+        None => {
+            #[allow(unused_mut)]
+            let mut kern = if gpu {
+                gpu_multiexp_kernel::<E>(self_test).ok()
+            } else {
+                None
+            };
+            #[cfg(feature = "gpu")]
+            {
+                if let (Some(k), Priority::Low, Some(target)) =
+                    (&mut kern, opts.priority, opts.preemption_target)
+                {
+                    let checks = priority_checks.clone();
+                    k.set_preemption(target, move || {
+                        let started = Instant::now();
+                        let awaited = gpu::lock_is_awaited().unwrap_or(false);
+                        checks.fetch_add(started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+                        awaited
+                    });
+                }
+            }
+            if kern.is_some() {
+                info!("GPU Multiexp is supported!");
+            } else if gpu {
+                info!("GPU Multiexp is NOT supported!");
+                #[cfg(feature = "gpu")]
+                metrics::gpu_fallback("multiexp");
+            }
+            let device = Device::new(kern.is_some());
+            (kern, device)
+        }
+    };
+
+    let h = (
+        now(),
+        multiexp_on(
+            cluster,
+            Query::H(a.len()),
+            worker,
+            params.get_h(a.len())?,
+            FullDensity,
+            a.clone(),
+            &mut multiexp_kern,
+            progress.cancel(),
+            opts.window_size,
+        ),
+    );
+
+    let input_assignment = Arc::new(into_reprs(
+        &mut prover.input_assignment,
+        prover.retain_buffers,
+    ));
+    if let Some(spilled) = spilled_aux {
+        prover.aux_assignment = spilled.read()?;
+    }
+    let aux_assignment = Arc::new(into_reprs(
+        &mut prover.aux_assignment,
+        prover.retain_buffers,
+    ));
+
+    let l = (
+        now(),
+        multiexp_on(
+            cluster,
+            Query::L(aux_assignment.len()),
+            worker,
+            params.get_l(aux_assignment.len())?,
+            FullDensity,
+            aux_assignment.clone(),
+            &mut multiexp_kern,
+            progress.cancel(),
+            opts.window_size,
+        ),
+    );
+
+    let a_aux_density_total = prover.a_aux_density.get_total_density();
+
+    let (a_inputs_source, a_aux_source) =
+        params.get_a(input_assignment.len(), a_aux_density_total)?;
+
+    let a_inputs = (
+        now(),
+        multiexp_on(
+            cluster,
+            Query::A {
+                aux: false,
+                num_inputs: input_assignment.len(),
+                num_aux: a_aux_density_total,
+            },
+            worker,
+            a_inputs_source,
+            FullDensity,
+            input_assignment.clone(),
+            &mut multiexp_kern,
+            progress.cancel(),
+            opts.window_size,
+        ),
+    );
+    let a_aux_density = Arc::new(mem::replace(
+        &mut prover.a_aux_density,
+        DensityTracker::new(),
+    ));
+    let a_aux = (
+        now(),
+        multiexp_on(
+            cluster,
+            Query::A {
+                aux: true,
+                num_inputs: input_assignment.len(),
+                num_aux: a_aux_density_total,
+            },
+            worker,
+            a_aux_source,
+            a_aux_density.clone(),
+            aux_assignment.clone(),
+            &mut multiexp_kern,
+            progress.cancel(),
+            opts.window_size,
+        ),
+    );
+
+    let b_input_density = Arc::new(mem::replace(
+        &mut prover.b_input_density,
+        DensityTracker::new(),
+    ));
+    let b_input_density_total = b_input_density.get_total_density();
+    let b_aux_density = Arc::new(mem::replace(
+        &mut prover.b_aux_density,
+        DensityTracker::new(),
+    ));
+    let b_aux_density_total = b_aux_density.get_total_density();
 
     let (b_g1_inputs_source, b_g1_aux_source) =
         params.get_b_g1(b_input_density_total, b_aux_density_total)?;
 
-    let b_g1_inputs = multiexp(
-        &worker,
-        b_g1_inputs_source,
-        b_input_density.clone(),
-        input_assignment.clone(),
-        &mut multiexp_kern,
+    let b_g1_inputs = (
+        now(),
+        multiexp_on(
+            cluster,
+            Query::BG1 {
+                aux: false,
+                num_inputs: b_input_density_total,
+                num_aux: b_aux_density_total,
+            },
+            worker,
+            b_g1_inputs_source,
+            b_input_density.clone(),
+            input_assignment.clone(),
+            &mut multiexp_kern,
+            progress.cancel(),
+            opts.window_size,
+        ),
     );
-    let b_g1_aux = multiexp(
-        &worker,
-        b_g1_aux_source,
-        b_aux_density.clone(),
-        aux_assignment.clone(),
-        &mut multiexp_kern,
+    let b_g1_aux = (
+        now(),
+        multiexp_on(
+            cluster,
+            Query::BG1 {
+                aux: true,
+                num_inputs: b_input_density_total,
+                num_aux: b_aux_density_total,
+            },
+            worker,
+            b_g1_aux_source,
+            b_aux_density.clone(),
+            aux_assignment.clone(),
+            &mut multiexp_kern,
+            progress.cancel(),
+            opts.window_size,
+        ),
     );
 
     let (b_g2_inputs_source, b_g2_aux_source) =
         params.get_b_g2(b_input_density_total, b_aux_density_total)?;
 
-    let b_g2_inputs = multiexp(
-        &worker,
-        b_g2_inputs_source,
-        b_input_density,
-        input_assignment,
-        &mut multiexp_kern,
+    let b_g2_inputs = (
+        now(),
+        multiexp_on(
+            cluster,
+            Query::BG2 {
+                aux: false,
+                num_inputs: b_input_density_total,
+                num_aux: b_aux_density_total,
+            },
+            worker,
+            b_g2_inputs_source,
+            b_input_density.clone(),
+            input_assignment.clone(),
+            &mut multiexp_kern,
+            progress.cancel(),
+            opts.window_size,
+        ),
     );
-    let b_g2_aux = multiexp(
-        &worker,
-        b_g2_aux_source,
-        b_aux_density,
-        aux_assignment,
-        &mut multiexp_kern,
+    let b_g2_aux = (
+        now(),
+        multiexp_on(
+            cluster,
+            Query::BG2 {
+                aux: true,
+                num_inputs: b_input_density_total,
+                num_aux: b_aux_density_total,
+            },
+            worker,
+            b_g2_aux_source,
+            b_aux_density.clone(),
+            aux_assignment.clone(),
+            &mut multiexp_kern,
+            progress.cancel(),
+            opts.window_size,
+        ),
     );
 
+    // The multiexps on the GPU are done once they are started, the ones on
+    // the CPU, e.g. after the GPU was preempted, don't need the lock.
+    #[cfg(feature = "gpu")]
+    {
+        drop(multiexp_kern);
+        let priority_checks =
+            std::time::Duration::from_nanos(priority_checks.load(Ordering::Relaxed));
+        if priority_checks > std::time::Duration::default() {
+            metrics::gpu_priority_checked(priority_checks);
+        }
+        if let Some(report) = progress.report_mut() {
+            report.priority_checks = priority_checks;
+        }
+        if let Some(lock) = lock {
+            gpu::unlock(lock);
+            progress.gpu(GpuEvent::Released);
+        }
+    }
+
     if vk.delta_g1.is_zero() || vk.delta_g2.is_zero() {
         // If this element is zero, someone is trying to perform a
         // subversion-CRS attack.
         return Err(SynthesisError::UnexpectedIdentity);
     }
 
+    let assembly = now();
     let mut g_a = vk.delta_g1.mul(r);
     g_a.add_assign_mixed(&vk.alpha_g1);
     let mut g_b = vk.delta_g2.mul(s);
@@ -365,26 +1842,35 @@ where
         g_c = vk.delta_g1.mul(rs);
         g_c.add_assign(&vk.alpha_g1.mul(s));
         g_c.add_assign(&vk.beta_g1.mul(r));
+        wipe_scalar(&mut rs);
     }
-    let mut a_answer = a_inputs.wait()?;
-    a_answer.add_assign(&a_aux.wait()?);
+    log_timing(&"assembly", Device::Cpu, assembly);
+    let mut a_answer = wait(a_inputs, "a inputs", device, progress)?;
+    a_answer.add_assign(&wait(a_aux, "a aux", device, progress)?);
     g_a.add_assign(&a_answer);
     a_answer.mul_assign(s);
     g_c.add_assign(&a_answer);
 
-    let mut b1_answer = b_g1_inputs.wait()?;
-    b1_answer.add_assign(&b_g1_aux.wait()?);
-    let mut b2_answer = b_g2_inputs.wait()?;
-    b2_answer.add_assign(&b_g2_aux.wait()?);
+    let mut b1_answer = wait(b_g1_inputs, "b_g1 inputs", device, progress)?;
+    b1_answer.add_assign(&wait(b_g1_aux, "b_g1 aux", device, progress)?);
+    let mut b2_answer = wait(b_g2_inputs, "b_g2 inputs", device, progress)?;
+    b2_answer.add_assign(&wait(b_g2_aux, "b_g2 aux", device, progress)?);
 
     g_b.add_assign(&b2_answer);
     b1_answer.mul_assign(r);
     g_c.add_assign(&b1_answer);
-    g_c.add_assign(&h.wait()?);
-    g_c.add_assign(&l.wait()?);
+    g_c.add_assign(&wait(h, "h", device, progress)?);
+    g_c.add_assign(&wait(l, "l", device, progress)?);
 
-    #[cfg(feature = "gpu")]
-    gpu::unlock(lock);
+    wipe_reprs(a);
+    wipe_reprs(input_assignment);
+    wipe_reprs(aux_assignment);
+
+    if prover.retain_buffers {
+        prover.a_aux_density = reclaim_density(a_aux_density);
+        prover.b_input_density = reclaim_density(b_input_density);
+        prover.b_aux_density = reclaim_density(b_aux_density);
+    }
 
     Ok(Proof {
         a: g_a.into_affine(),
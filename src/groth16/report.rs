@@ -0,0 +1,104 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use super::progress::ProvingStage;
+
+/// How long a stage of a proof took and where it ran.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StageReport {
+    pub stage: ProvingStage,
+    /// Whether the stage ran on the GPU, otherwise on the CPU.
+    pub gpu: bool,
+    /// The time the stage took, `None` without a clock, e.g. on wasm32. The
+    /// multiexps on the CPU overlap, so their times do too.
+    pub elapsed: Option<Duration>,
+}
+
+/// A machine-readable report of how a proof was created, see
+/// [`create_proof_with_report`](super::create_proof_with_report).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProvingReport {
+    /// The number of public inputs, including the one for the constant.
+    pub num_inputs: usize,
+    pub num_aux: usize,
+    /// The number of constraints, including the one added for every input.
+    pub num_constraints: usize,
+    /// The number of auxiliary variables in the A query.
+    pub a_aux_density: usize,
+    /// The number of inputs in the B query.
+    pub b_input_density: usize,
+    /// The number of auxiliary variables in the B query.
+    pub b_aux_density: usize,
+    /// The size of the evaluation domain of the FFTs.
+    pub fft_size: usize,
+    /// The estimated peak memory of the prover, without the parameters, see
+    /// [`estimate_resources`](super::estimate_resources).
+    pub peak_memory_bytes: usize,
+    /// How long the proof waited for the GPU lock, `None` if it didn't ask
+    /// for it or without a clock.
+    pub gpu_lock_wait: Option<Duration>,
+    /// How long the proof spent checking whether provers of higher priority
+    /// wait for the GPU, which proofs of low priority with a preemption
+    /// target do, see [`ProverOptions`](super::ProverOptions).
+    pub priority_checks: Duration,
+    /// The stages in the order they finished.
+    pub stages: Vec<StageReport>,
+}
+
+impl ProvingReport {
+    /// Encodes the report as a JSON object, with the stage times, the wait for
+    /// the GPU lock and the priority checks in milliseconds.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        write!(
+            json,
+            "{{\"num_inputs\":{},\"num_aux\":{},\"num_constraints\":{},\
+             \"a_aux_density\":{},\"b_input_density\":{},\"b_aux_density\":{},\
+             \"fft_size\":{},\"peak_memory_bytes\":{},\"gpu_lock_wait_ms\":",
+            self.num_inputs,
+            self.num_aux,
+            self.num_constraints,
+            self.a_aux_density,
+            self.b_input_density,
+            self.b_aux_density,
+            self.fft_size,
+            self.peak_memory_bytes,
+        )
+        .unwrap();
+        match self.gpu_lock_wait {
+            Some(wait) => write!(json, "{:.3}", millis(wait)).unwrap(),
+            None => json.push_str("null"),
+        }
+        write!(
+            json,
+            ",\"priority_checks_ms\":{:.3},\"stages\":[",
+            millis(self.priority_checks)
+        )
+        .unwrap();
+
+        for (i, stage) in self.stages.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            // The stage names are static strings without quotes.
+            write!(
+                json,
+                "{{\"stage\":\"{}\",\"device\":\"{}\",\"elapsed_ms\":",
+                stage.stage,
+                if stage.gpu { "gpu" } else { "cpu" }
+            )
+            .unwrap();
+            match stage.elapsed {
+                Some(elapsed) => write!(json, "{:.3}}}", millis(elapsed)).unwrap(),
+                None => json.push_str("null}"),
+            }
+        }
+        json.push_str("]}");
+
+        json
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1e3
+}
@@ -0,0 +1,100 @@
+use ff::Field;
+
+use super::prover::eval;
+use crate::bls::Engine;
+use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// Whether the assignments of `circuit` satisfy its constraints, checked
+/// without creating a proof, e.g. to validate a witness before an expensive
+/// proof. Every constraint is checked when it's enforced and dropped right
+/// away, only the assignments are kept.
+pub fn circuit_is_satisfied<E, C>(circuit: C) -> Result<bool, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    let mut cs = SatisfactionCS::<E> {
+        input_assignment: vec![],
+        aux_assignment: vec![],
+        satisfied: true,
+    };
+    cs.alloc_input(|| "", || Ok(E::Fr::one()))?;
+    circuit.synthesize(&mut cs)?;
+
+    Ok(cs.satisfied)
+}
+
+/// Evaluates the constraints against the assignments as they come.
+struct SatisfactionCS<E: Engine> {
+    input_assignment: Vec<E::Fr>,
+    aux_assignment: Vec<E::Fr>,
+    satisfied: bool,
+}
+
+impl<E: Engine> SatisfactionCS<E> {
+    fn eval(&self, lc: &LinearCombination<E>) -> E::Fr {
+        eval(lc, None, None, &self.input_assignment, &self.aux_assignment)
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for SatisfactionCS<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.aux_assignment.push(f()?);
+
+        Ok(Variable(Index::Aux(self.aux_assignment.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.input_assignment.push(f()?);
+
+        Ok(Variable(Index::Input(self.input_assignment.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        // Once a constraint failed, the others don't matter.
+        if !self.satisfied {
+            return;
+        }
+
+        let mut a = self.eval(&a(LinearCombination::zero()));
+        let b = self.eval(&b(LinearCombination::zero()));
+        let c = self.eval(&c(LinearCombination::zero()));
+        a.mul_assign(&b);
+        self.satisfied = a == c;
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
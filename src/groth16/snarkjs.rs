@@ -0,0 +1,259 @@
+//! Conversions from and to the JSON formats of [snarkjs] for BLS12-381: the
+//! `proof.json`, `verification_key.json` and `public.json` files. Points are
+//! written in projective coordinates with `z = 1`, as decimal strings.
+//!
+//! [snarkjs]: https://github.com/iden3/snarkjs
+
+use std::io;
+
+use crate::bls::Engine;
+use crate::bls::{Bls12, Fq, Fq12, Fq2, Fr, G1Affine, G1Uncompressed, G2Affine, G2Uncompressed};
+use ff::{Field, PrimeField, PrimeFieldRepr};
+use groupy::{CurveAffine, EncodedPoint};
+use serde_json::{json, Value};
+
+use super::{prepare_verifying_key, PreparedVerifyingKey, Proof, VerifyingKey};
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Writes the integer of the little-endian limbs in decimal.
+fn decimal<R: PrimeFieldRepr>(repr: R) -> String {
+    const TEN_19: u64 = 10_000_000_000_000_000_000;
+
+    let mut limbs = repr.as_ref().to_vec();
+    let mut chunks = vec![];
+    while limbs.iter().any(|&limb| limb != 0) {
+        let mut rem = 0u128;
+        for limb in limbs.iter_mut().rev() {
+            let cur = (rem << 64) | *limb as u128;
+            *limb = (cur / TEN_19 as u128) as u64;
+            rem = cur % TEN_19 as u128;
+        }
+        chunks.push(rem as u64);
+    }
+
+    match chunks.split_last() {
+        None => "0".to_string(),
+        Some((first, rest)) => {
+            let mut s = first.to_string();
+            for chunk in rest.iter().rev() {
+                s.push_str(&format!("{:019}", chunk));
+            }
+            s
+        }
+    }
+}
+
+fn fq(value: &Fq) -> Value {
+    Value::String(decimal(value.into_repr()))
+}
+
+fn fq2(value: &Fq2) -> Value {
+    json!([fq(&value.c0), fq(&value.c1)])
+}
+
+fn fr(value: &Fr) -> Value {
+    Value::String(decimal(value.into_repr()))
+}
+
+/// Splits the uncompressed encoding into its field elements of 48 bytes.
+fn coordinates<F: PrimeField>(bytes: &[u8]) -> Vec<F> {
+    bytes
+        .chunks(48)
+        .map(|chunk| {
+            let mut repr = F::Repr::default();
+            repr.read_be(chunk)
+                .expect("the chunk has the size of a field element");
+            F::from_repr(repr).expect("the point has valid coordinates")
+        })
+        .collect()
+}
+
+fn g1(point: &G1Affine) -> Value {
+    if point.is_zero() {
+        return json!(["0", "1", "0"]);
+    }
+
+    let xy = coordinates::<Fq>(point.into_uncompressed().as_ref());
+    json!([fq(&xy[0]), fq(&xy[1]), "1"])
+}
+
+fn g2(point: &G2Affine) -> Value {
+    if point.is_zero() {
+        return json!([["0", "0"], ["1", "0"], ["0", "0"]]);
+    }
+
+    // The uncompressed encoding puts `c1` first.
+    let xy = coordinates::<Fq>(point.into_uncompressed().as_ref());
+    json!([
+        [fq(&xy[1]), fq(&xy[0])],
+        [fq(&xy[3]), fq(&xy[2])],
+        ["1", "0"]
+    ])
+}
+
+fn fq12(value: &Fq12) -> Value {
+    json!([
+        [fq2(&value.c0.c0), fq2(&value.c0.c1), fq2(&value.c0.c2)],
+        [fq2(&value.c1.c0), fq2(&value.c1.c1), fq2(&value.c1.c2)]
+    ])
+}
+
+fn parse_field<F: PrimeField>(value: &Value) -> io::Result<F> {
+    value
+        .as_str()
+        .and_then(F::from_str)
+        .ok_or_else(|| invalid(format!("expected a field element, got {}", value)))
+}
+
+fn parse_array(value: &Value, len: usize) -> io::Result<&[Value]> {
+    match value.as_array() {
+        Some(array) if array.len() == len => Ok(array.as_slice()),
+        _ => Err(invalid(format!(
+            "expected an array of {}, got {}",
+            len, value
+        ))),
+    }
+}
+
+fn write_be<F: PrimeField>(bytes: &mut Vec<u8>, value: &F) {
+    value
+        .into_repr()
+        .write_be(bytes)
+        .expect("writing to a vector never fails");
+}
+
+fn parse_g1(value: &Value) -> io::Result<G1Affine> {
+    let xyz = parse_array(value, 3)?;
+    let z = parse_field::<Fq>(&xyz[2])?;
+    if z.is_zero() {
+        return Ok(G1Affine::zero());
+    }
+    if z != Fq::one() {
+        return Err(invalid("expected a point with z = 1"));
+    }
+
+    let mut bytes = vec![];
+    write_be(&mut bytes, &parse_field::<Fq>(&xyz[0])?);
+    write_be(&mut bytes, &parse_field::<Fq>(&xyz[1])?);
+    let mut encoded = G1Uncompressed::empty();
+    encoded.as_mut().copy_from_slice(&bytes);
+    encoded.into_affine().map_err(invalid)
+}
+
+fn parse_fq2(value: &Value) -> io::Result<Fq2> {
+    let c = parse_array(value, 2)?;
+    Ok(Fq2 {
+        c0: parse_field(&c[0])?,
+        c1: parse_field(&c[1])?,
+    })
+}
+
+fn parse_g2(value: &Value) -> io::Result<G2Affine> {
+    let xyz = parse_array(value, 3)?;
+    let z = parse_fq2(&xyz[2])?;
+    if z.is_zero() {
+        return Ok(G2Affine::zero());
+    }
+    if z != Fq2::one() {
+        return Err(invalid("expected a point with z = 1"));
+    }
+
+    let x = parse_fq2(&xyz[0])?;
+    let y = parse_fq2(&xyz[1])?;
+    let mut bytes = vec![];
+    for c in &[x.c1, x.c0, y.c1, y.c0] {
+        write_be(&mut bytes, c);
+    }
+    let mut encoded = G2Uncompressed::empty();
+    encoded.as_mut().copy_from_slice(&bytes);
+    encoded.into_affine().map_err(invalid)
+}
+
+fn parse_json(json: &str) -> io::Result<Value> {
+    serde_json::from_str(json).map_err(invalid)
+}
+
+/// Writes a proof like `proof.json`.
+pub fn proof_to_json(proof: &Proof<Bls12>) -> String {
+    let json = json!({
+        "pi_a": g1(&proof.a),
+        "pi_b": g2(&proof.b),
+        "pi_c": g1(&proof.c),
+        "protocol": "groth16",
+        "curve": "bls12381",
+    });
+    serde_json::to_string_pretty(&json).expect("the JSON is valid")
+}
+
+/// Reads a proof from `proof.json`, the points are checked to be in the
+/// subgroup.
+pub fn proof_from_json(json: &str) -> io::Result<Proof<Bls12>> {
+    let json = parse_json(json)?;
+    Ok(Proof {
+        a: parse_g1(&json["pi_a"])?,
+        b: parse_g2(&json["pi_b"])?,
+        c: parse_g1(&json["pi_c"])?,
+    })
+}
+
+/// Writes a verifying key like `verification_key.json`.
+pub fn verifying_key_to_json(vk: &VerifyingKey<Bls12>) -> String {
+    let json = json!({
+        "protocol": "groth16",
+        "curve": "bls12381",
+        "nPublic": vk.ic.len() - 1,
+        "vk_alpha_1": g1(&vk.alpha_g1),
+        "vk_beta_2": g2(&vk.beta_g2),
+        "vk_gamma_2": g2(&vk.gamma_g2),
+        "vk_delta_2": g2(&vk.delta_g2),
+        "vk_alphabeta_12": fq12(&Bls12::pairing(vk.alpha_g1, vk.beta_g2)),
+        "IC": vk.ic.iter().map(g1).collect::<Vec<_>>(),
+    });
+    serde_json::to_string_pretty(&json).expect("the JSON is valid")
+}
+
+/// Reads a verifying key from `verification_key.json`. The file lacks the
+/// elements that are only needed for proving, so the key is prepared for
+/// verification right away.
+pub fn prepared_verifying_key_from_json(json: &str) -> io::Result<PreparedVerifyingKey<Bls12>> {
+    let json = parse_json(json)?;
+    let ic = json["IC"]
+        .as_array()
+        .ok_or_else(|| invalid("expected an array of IC points"))?
+        .iter()
+        .map(parse_g1)
+        .collect::<io::Result<Vec<_>>>()?;
+    if ic.is_empty() {
+        return Err(invalid("expected at least one IC point"));
+    }
+
+    let vk = VerifyingKey {
+        alpha_g1: parse_g1(&json["vk_alpha_1"])?,
+        beta_g1: G1Affine::zero(),
+        beta_g2: parse_g2(&json["vk_beta_2"])?,
+        gamma_g2: parse_g2(&json["vk_gamma_2"])?,
+        delta_g1: G1Affine::zero(),
+        delta_g2: parse_g2(&json["vk_delta_2"])?,
+        ic,
+    };
+    Ok(prepare_verifying_key(&vk))
+}
+
+/// Writes public inputs like `public.json`.
+pub fn public_inputs_to_json(inputs: &[Fr]) -> String {
+    let json = Value::Array(inputs.iter().map(fr).collect());
+    serde_json::to_string_pretty(&json).expect("the JSON is valid")
+}
+
+/// Reads public inputs from `public.json`.
+pub fn public_inputs_from_json(json: &str) -> io::Result<Vec<Fr>> {
+    parse_json(json)?
+        .as_array()
+        .ok_or_else(|| invalid("expected an array of public inputs"))?
+        .iter()
+        .map(parse_field)
+        .collect()
+}
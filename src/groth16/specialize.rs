@@ -0,0 +1,235 @@
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective};
+
+use super::{CachedCircuit, ParameterSource, Parameters, VerifyingKey};
+use crate::bls::Engine;
+use crate::multiexp::DensityTracker;
+use crate::{Circuit, SynthesisError};
+
+/// Parameters of a circuit some of whose public inputs are fixed to
+/// constants, e.g. at deployment time. Their bases are folded into the ones
+/// of `ONE`, so the verifying key and the input parts of the A and B queries
+/// only have bases for the remaining inputs.
+///
+/// Proofs are created for the original circuit, whose fixed inputs must have
+/// the fixed values, and are verified with only the remaining public inputs.
+pub struct SpecializedParameters<E: Engine> {
+    params: Parameters<E>,
+    fixed: Vec<(usize, E::Fr)>,
+}
+
+impl<E: Engine> SpecializedParameters<E> {
+    /// Specializes the parameters of `circuit` to the `fixed` inputs, given
+    /// by their position in the public inputs passed to the verifier and their
+    /// value. The circuit is only synthesized, for finding the inputs that are
+    /// part of the B query, so it doesn't need any assignments.
+    ///
+    /// Fails with [`SynthesisError::MalformedVerifyingKey`] if an input is
+    /// fixed twice or doesn't exist, or if the parameters don't belong to the
+    /// circuit.
+    pub fn new<C: Circuit<E>>(
+        params: &Parameters<E>,
+        circuit: C,
+        fixed: &[(usize, E::Fr)],
+    ) -> Result<Self, SynthesisError> {
+        let circuit = CachedCircuit::new(circuit)?;
+        let num_inputs = circuit.num_inputs();
+        if num_inputs != params.vk.ic.len() {
+            return Err(SynthesisError::MalformedVerifyingKey);
+        }
+        let fixed = sorted(fixed, num_inputs).ok_or(SynthesisError::MalformedVerifyingKey)?;
+
+        let density = circuit.b_input_density();
+        let specialized = specialize_density(density, &fixed);
+        let num_b = density.get_total_density();
+        if params.a.len() < num_inputs || params.b_g1.len() < num_b || params.b_g2.len() < num_b {
+            return Err(SynthesisError::MalformedVerifyingKey);
+        }
+
+        let mut a = specialize_bases(&params.a[..num_inputs], &fixed);
+        a.extend_from_slice(&params.a[num_inputs..]);
+
+        Ok(SpecializedParameters {
+            params: Parameters {
+                vk: VerifyingKey {
+                    ic: specialize_bases(&params.vk.ic, &fixed),
+                    ..params.vk.clone()
+                },
+                h: params.h.clone(),
+                l: params.l.clone(),
+                a: a.into(),
+                b_g1: specialize_query(&params.b_g1, density, &specialized, &fixed).into(),
+                b_g2: specialize_query(&params.b_g2, density, &specialized, &fixed).into(),
+            },
+            fixed,
+        })
+    }
+
+    pub fn vk(&self) -> &VerifyingKey<E> {
+        &self.params.vk
+    }
+
+    /// The fixed inputs, ordered by their position in the public inputs.
+    pub fn fixed_inputs(&self) -> &[(usize, E::Fr)] {
+        &self.fixed
+    }
+}
+
+impl<'a, E: Engine> ParameterSource<E> for &'a SpecializedParameters<E> {
+    type G1Builder = <&'a Parameters<E> as ParameterSource<E>>::G1Builder;
+    type G2Builder = <&'a Parameters<E> as ParameterSource<E>>::G2Builder;
+
+    fn get_vk(&mut self, num_ic: usize) -> Result<VerifyingKey<E>, SynthesisError> {
+        (&self.params).get_vk(num_ic)
+    }
+
+    fn get_h(&mut self, num_h: usize) -> Result<Self::G1Builder, SynthesisError> {
+        (&self.params).get_h(num_h)
+    }
+
+    fn get_l(&mut self, num_l: usize) -> Result<Self::G1Builder, SynthesisError> {
+        (&self.params).get_l(num_l)
+    }
+
+    fn get_a(
+        &mut self,
+        num_inputs: usize,
+        num_aux: usize,
+    ) -> Result<(Self::G1Builder, Self::G1Builder), SynthesisError> {
+        (&self.params).get_a(num_inputs, num_aux)
+    }
+
+    fn get_b_g1(
+        &mut self,
+        num_inputs: usize,
+        num_aux: usize,
+    ) -> Result<(Self::G1Builder, Self::G1Builder), SynthesisError> {
+        (&self.params).get_b_g1(num_inputs, num_aux)
+    }
+
+    fn get_b_g2(
+        &mut self,
+        num_inputs: usize,
+        num_aux: usize,
+    ) -> Result<(Self::G2Builder, Self::G2Builder), SynthesisError> {
+        (&self.params).get_b_g2(num_inputs, num_aux)
+    }
+
+    fn fixed_inputs(&self) -> &[(usize, E::Fr)] {
+        &self.fixed
+    }
+}
+
+/// Specializes a verifying key to the `fixed` inputs, like
+/// [`SpecializedParameters::new`] does. Proofs are then verified with only the
+/// remaining public inputs.
+///
+/// # Panics
+///
+/// Panics if an input is fixed twice or doesn't exist.
+pub fn specialize_verifying_key<E: Engine>(
+    vk: &VerifyingKey<E>,
+    fixed: &[(usize, E::Fr)],
+) -> VerifyingKey<E> {
+    VerifyingKey {
+        ic: specialize_bases(
+            &vk.ic,
+            &sorted(fixed, vk.ic.len()).expect("an input is fixed twice or doesn't exist"),
+        ),
+        ..vk.clone()
+    }
+}
+
+/// The fixed inputs ordered by their position, `None` if an input is fixed
+/// twice or doesn't exist.
+fn sorted<F: PrimeField>(fixed: &[(usize, F)], num_inputs: usize) -> Option<Vec<(usize, F)>> {
+    let mut fixed = fixed.to_vec();
+    fixed.sort_by_key(|&(i, _)| i);
+    for (i, pair) in fixed.iter().enumerate() {
+        if pair.0 + 1 >= num_inputs || (i > 0 && fixed[i - 1].0 == pair.0) {
+            return None;
+        }
+    }
+
+    Some(fixed)
+}
+
+/// Whether the input with the given index, where `ONE` is 0, is fixed.
+pub(super) fn is_fixed<F>(fixed: &[(usize, F)], input: usize) -> bool {
+    input > 0
+        && fixed
+            .binary_search_by_key(&(input - 1), |&(i, _)| i)
+            .is_ok()
+}
+
+/// The density of the remaining inputs in the B query. `ONE` is used once a
+/// fixed input with a non-zero value was used.
+pub(super) fn specialize_density<F: PrimeField>(
+    density: &DensityTracker,
+    fixed: &[(usize, F)],
+) -> DensityTracker {
+    let mut specialized = DensityTracker::new();
+    for input in (0..density.len()).filter(|&input| !is_fixed(fixed, input)) {
+        specialized.add_element();
+        if density.get(input) {
+            specialized.inc(specialized.len() - 1);
+        }
+    }
+    if fixed
+        .iter()
+        .any(|&(i, ref value)| density.get(i + 1) && !value.is_zero())
+    {
+        specialized.inc(0);
+    }
+
+    specialized
+}
+
+/// Adds the bases of the fixed inputs times their values to the one of `ONE`
+/// and removes them. `bases` has one for every input.
+fn specialize_bases<G: CurveAffine>(bases: &[G], fixed: &[(usize, G::Scalar)]) -> Vec<G> {
+    let mut one = bases[0].into_projective();
+    for &(i, value) in fixed {
+        one.add_assign(&bases[i + 1].mul(value.into_repr()));
+    }
+
+    let mut specialized = vec![one.into_affine()];
+    specialized.extend(
+        bases
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|&(input, _)| !is_fixed(fixed, input))
+            .map(|(_, base)| *base),
+    );
+
+    specialized
+}
+
+/// Specializes a B query, which only has bases for the inputs in `density`.
+fn specialize_query<G: CurveAffine>(
+    query: &[G],
+    density: &DensityTracker,
+    specialized: &DensityTracker,
+    fixed: &[(usize, G::Scalar)],
+) -> Vec<G> {
+    let num_inputs = density.get_total_density();
+    let mut bases = query[..num_inputs].iter();
+    let inputs = (0..density.len())
+        .map(|input| {
+            if density.get(input) {
+                *bases.next().unwrap()
+            } else {
+                G::zero()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    specialize_bases(&inputs, fixed)
+        .into_iter()
+        .enumerate()
+        .filter(|&(input, _)| specialized.get(input))
+        .map(|(_, base)| base)
+        .chain(query[num_inputs..].iter().cloned())
+        .collect()
+}
@@ -0,0 +1,119 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+use std::mem;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A vector the prover moved out of memory into a temporary file, until it
+/// needs it again. The file is overwritten with zeros before it's removed, as
+/// it may hold parts of the witness.
+pub(crate) struct Spilled<T> {
+    path: PathBuf,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Copy> Spilled<T> {
+    /// Writes `values` to a new file in `dir`.
+    pub fn write(dir: &Path, values: &[T]) -> io::Result<Self> {
+        let mut writer = SpillWriter::create(dir)?;
+        writer.append(values)?;
+        writer.finish()
+    }
+
+    /// Reads the values back, the file is wiped and removed.
+    pub fn read(self) -> io::Result<Vec<T>> {
+        let mut values = Vec::<T>::with_capacity(self.len);
+        let mut file = File::open(&self.path)?;
+        // The values are plain data, like the field elements and their
+        // representations, so they are valid once all of their bytes are read.
+        unsafe {
+            let bytes = slice::from_raw_parts_mut(
+                values.as_mut_ptr() as *mut u8,
+                self.len * mem::size_of::<T>(),
+            );
+            file.read_exact(bytes)?;
+            values.set_len(self.len);
+        }
+
+        Ok(values)
+    }
+}
+
+/// A vector that is moved into a temporary file in parts, e.g. while it's
+/// computed, and read back as a whole as a [`Spilled`] one.
+pub(crate) struct SpillWriter<T> {
+    file: File,
+    // Wipes and removes the file if the writer is dropped before it's finished.
+    spilled: Spilled<T>,
+}
+
+impl<T: Copy> SpillWriter<T> {
+    /// Creates a new file in `dir`.
+    pub fn create(dir: &Path) -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let path = dir.join(format!(
+            "bellperson-spill-{}-{}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        // Only the owner may read the parts of the witness.
+        #[cfg(unix)]
+        options.mode(0o600);
+        let file = options.open(&path)?;
+
+        Ok(SpillWriter {
+            file,
+            spilled: Spilled {
+                path,
+                len: 0,
+                _marker: PhantomData,
+            },
+        })
+    }
+
+    pub fn append(&mut self, values: &[T]) -> io::Result<()> {
+        self.file.write_all(as_bytes(values))?;
+        self.spilled.len += values.len();
+        Ok(())
+    }
+
+    pub fn finish(self) -> io::Result<Spilled<T>> {
+        self.file.sync_all()?;
+        Ok(self.spilled)
+    }
+}
+
+fn as_bytes<T: Copy>(values: &[T]) -> &[u8] {
+    unsafe { slice::from_raw_parts(values.as_ptr() as *const u8, mem::size_of_val(values)) }
+}
+
+impl<T> Drop for Spilled<T> {
+    fn drop(&mut self) {
+        let wiped = OpenOptions::new()
+            .write(true)
+            .open(&self.path)
+            .and_then(|mut file| {
+                let zeros = vec![0u8; 1 << 16];
+                let mut left = self.len * mem::size_of::<T>();
+                while left > 0 {
+                    let n = left.min(zeros.len());
+                    file.write_all(&zeros[..n])?;
+                    left -= n;
+                }
+                file.sync_all()
+            });
+        if let Err(e) = wiped {
+            log::warn!("Cannot wipe {}: {}", self.path.display(), e);
+        }
+        let _ = fs::remove_file(&self.path);
+    }
+}
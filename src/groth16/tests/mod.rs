@@ -1,12 +1,21 @@
+use crate::bls::Engine;
 use ff::{Field, PrimeField};
-use paired::Engine;
 
 mod dummy_engine;
 use self::dummy_engine::*;
 
+use rand_core::RngCore;
+use std::cell::RefCell;
 use std::marker::PhantomData;
+use std::time::{Duration, Instant};
 
-use super::{create_proof, generate_parameters, prepare_verifying_key, verify_proof};
+use super::{
+    create_proof, create_proof_cancellable, create_proof_with_deadline, create_proof_with_progress,
+    create_proof_with_report, create_proof_with_worker, estimate_resources, generate_parameters,
+    generate_parameters_with_stats, generate_random_parameters, prepare_verifying_key,
+    verify_proof, verify_proof_with_worker, Parameters, PreparedVerifyingKey, Prover, ProvingStage,
+};
+use crate::multicore::{CancellationToken, Worker};
 use crate::{Circuit, ConstraintSystem, SynthesisError};
 
 struct XORDemo<E: Engine> {
@@ -87,6 +96,52 @@ impl<E: Engine> Circuit<E> for XORDemo<E> {
     }
 }
 
+impl<E: Engine> XORDemo<E> {
+    /// The circuit without an assignment, for generating parameters.
+    fn blank() -> Self {
+        XORDemo {
+            a: None,
+            b: None,
+            _marker: PhantomData,
+        }
+    }
+
+    fn new(a: bool, b: bool) -> Self {
+        XORDemo {
+            a: Some(a),
+            b: Some(b),
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Random parameters for `XORDemo` and their prepared verifying key.
+fn xor_demo_params<E: Engine, R: RngCore>(rng: &mut R) -> (Parameters<E>, PreparedVerifyingKey<E>) {
+    let params = generate_random_parameters(XORDemo::<E>::blank(), rng).unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+    (params, pvk)
+}
+
+/// The parameters of `test_xordemo`, whose toxic waste is known, with the
+/// blinding factors `r` and `s` of its proof.
+fn dummy_xor_demo_params() -> (Parameters<DummyEngine>, Fr, Fr) {
+    let params = generate_parameters(
+        XORDemo::blank(),
+        Fr::one(),
+        Fr::one(),
+        Fr::from_str("48577").unwrap(),
+        Fr::from_str("22580").unwrap(),
+        Fr::from_str("53332").unwrap(),
+        Fr::from_str("5481").unwrap(),
+        Fr::from_str("3673").unwrap(),
+    )
+    .unwrap();
+    let r = Fr::from_str("27134").unwrap();
+    let s = Fr::from_str("17146").unwrap();
+
+    (params, r, s)
+}
+
 #[test]
 fn test_xordemo() {
     let g1 = Fr::one();
@@ -378,3 +433,1055 @@ fn test_xordemo() {
 
     assert!(verify_proof(&pvk, &proof, &[Fr::one()]).unwrap());
 }
+
+#[test]
+fn test_prover_reuse() {
+    let (params, r, s) = dummy_xor_demo_params();
+
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let mut prover = Prover::new();
+
+    for &(a, b) in &[(true, false), (false, false), (true, true), (false, true)] {
+        let c = XORDemo::new(a, b);
+        let reused = prover.create_proof(c, &params, r, s).unwrap();
+
+        let c = XORDemo::new(a, b);
+        let fresh = create_proof(c, &params, r, s).unwrap();
+
+        assert!(reused == fresh);
+
+        let expected = if a ^ b { Fr::one() } else { Fr::zero() };
+        assert!(verify_proof(&pvk, &reused, &[expected]).unwrap());
+    }
+}
+
+#[test]
+fn test_with_worker() {
+    let (params, r, s) = dummy_xor_demo_params();
+
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let mut pvk_tables = prepare_verifying_key(&params.vk);
+    pvk_tables.precompute_ic_tables(3);
+
+    let worker = Worker::with_threads(1);
+    let mut prover = Prover::with_worker(Worker::with_threads(2));
+
+    for &(a, b) in &[(true, false), (false, false), (true, true), (false, true)] {
+        let c = XORDemo::new(a, b);
+        let bounded = create_proof_with_worker(c, &params, r, s, &worker).unwrap();
+
+        let c = XORDemo::new(a, b);
+        let reused = prover.create_proof(c, &params, r, s).unwrap();
+
+        assert!(bounded == reused);
+
+        let expected = if a ^ b { Fr::one() } else { Fr::zero() };
+        assert!(verify_proof_with_worker(&pvk, &bounded, &[expected], &worker).unwrap());
+        let wrong = if a ^ b { Fr::zero() } else { Fr::one() };
+        assert!(!verify_proof_with_worker(&pvk, &bounded, &[wrong], &worker).unwrap());
+        assert!(verify_proof(&pvk_tables, &bounded, &[expected]).unwrap());
+        assert!(!verify_proof(&pvk_tables, &bounded, &[wrong]).unwrap());
+    }
+}
+
+#[test]
+fn test_progress() {
+    let (params, r, s) = dummy_xor_demo_params();
+
+    let reports = RefCell::new(vec![]);
+    let c = XORDemo::new(true, false);
+    let sink = |stage: ProvingStage, done: f64| reports.borrow_mut().push((stage, done));
+    create_proof_with_progress(c, &params, r, s, &sink).unwrap();
+
+    let reports = reports.into_inner();
+    assert_eq!(reports.len(), 16);
+    assert_eq!(reports[0].0, ProvingStage::Synthesis);
+    let ffts = reports.iter().filter(|(stage, _)| match stage {
+        ProvingStage::Fft(_) => true,
+        _ => false,
+    });
+    assert_eq!(ffts.count(), 7);
+    for pair in reports.windows(2) {
+        assert!(pair[0].1 < pair[1].1);
+    }
+    assert!((reports[15].1 - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_proving_report() {
+    let (params, r, s) = dummy_xor_demo_params();
+
+    let c = XORDemo::new(true, false);
+    let (proof, report) = create_proof_with_report(c, &params, r, s).unwrap();
+
+    let pvk = prepare_verifying_key(&params.vk);
+    assert!(verify_proof(&pvk, &proof, &[Fr::one()]).unwrap());
+
+    assert_eq!(report.num_inputs, 2);
+    assert_eq!(report.num_aux, 2);
+    // Three constraints and one for each input
+    assert_eq!(report.num_constraints, 5);
+    assert_eq!(report.fft_size, 8);
+    assert_eq!(report.stages.len(), 16);
+    assert_eq!(report.stages[0].stage, ProvingStage::Synthesis);
+    assert!(report.peak_memory_bytes > 0);
+    // Without the `gpu` feature, there is no lock to wait for.
+    #[cfg(not(feature = "gpu"))]
+    assert_eq!(report.gpu_lock_wait, None);
+    assert_eq!(report.priority_checks, Duration::from_secs(0));
+
+    let json = report.to_json();
+    assert!(json.starts_with("{\"num_inputs\":2,\"num_aux\":2,\"num_constraints\":5,"));
+    assert!(json.contains("{\"stage\":\"synthesis\",\"device\":\"cpu\",\"elapsed_ms\":"));
+    assert!(json.ends_with("]}"));
+}
+
+#[test]
+fn test_cancellation() {
+    let (params, r, s) = dummy_xor_demo_params();
+
+    let cancel = CancellationToken::new();
+    let c = XORDemo::new(true, false);
+    assert!(create_proof_cancellable(c, &params, r, s, &cancel).is_ok());
+
+    cancel.cancel();
+    let c = XORDemo::new(true, false);
+    match create_proof_cancellable(c, &params, r, s, &cancel) {
+        Err(SynthesisError::Cancelled) => {}
+        _ => panic!("the proof should have been cancelled"),
+    }
+
+    let c = XORDemo::new(true, false);
+    assert!(create_proof_with_deadline(
+        c,
+        &params,
+        r,
+        s,
+        Instant::now() + Duration::from_secs(3600)
+    )
+    .is_ok());
+
+    let c = XORDemo::new(true, false);
+    match create_proof_with_deadline(c, &params, r, s, Instant::now()) {
+        Err(SynthesisError::Timeout) => {}
+        _ => panic!("the proof should have timed out"),
+    }
+}
+
+#[test]
+fn test_estimate_resources() {
+    let (params, _, _) = dummy_xor_demo_params();
+
+    let estimate = estimate_resources(&params);
+
+    // 3 constraints plus one for each of the 2 inputs.
+    assert_eq!(estimate.fft_size, 8);
+    assert_eq!(estimate.multiexp_sizes.h, 7);
+    assert_eq!(estimate.multiexp_sizes.l, 2);
+    assert_eq!(estimate.multiexp_sizes.a, 4);
+    assert_eq!(estimate.multiexp_sizes.b_g1, 2);
+    assert_eq!(estimate.multiexp_sizes.b_g2, 2);
+    assert!(estimate.ram_bytes > 0);
+    assert!(estimate.vram_bytes > 0);
+}
+
+#[test]
+fn test_circuit_stats() {
+    let c = XORDemo::<DummyEngine>::blank();
+
+    let g1 = Fr::one();
+    let g2 = Fr::one();
+    let alpha = Fr::from_str("48577").unwrap();
+    let beta = Fr::from_str("22580").unwrap();
+    let gamma = Fr::from_str("53332").unwrap();
+    let delta = Fr::from_str("5481").unwrap();
+    let tau = Fr::from_str("3673").unwrap();
+
+    let (params, stats) =
+        generate_parameters_with_stats(c, g1, g2, alpha, beta, gamma, delta, tau).unwrap();
+
+    assert_eq!(stats.num_inputs, 2);
+    assert_eq!(stats.num_aux, 2);
+    // Three constraints and one for each input
+    assert_eq!(stats.num_constraints, 5);
+    // `a` and `b` are in the A and B queries, no input is in the B query.
+    assert_eq!(stats.a_aux_density, 2);
+    assert_eq!(stats.b_input_density, 0);
+    assert_eq!(stats.b_aux_density, 2);
+    assert_eq!(stats.fft_size, 8);
+    assert_eq!(stats.fft_size, estimate_resources(&params).fft_size);
+}
+
+#[test]
+fn test_solidity_export() {
+    use super::create_random_proof;
+    use super::export::{solidity, solidity_calldata};
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut crate::testing::test_rng();
+    let (params, _) = xor_demo_params::<Bls12, _>(rng);
+    let c = XORDemo::new(true, false);
+    let proof = create_random_proof(c, &params, rng).unwrap();
+
+    let contract = solidity(&params.vk);
+    assert!(contract.contains("uint256 constant NUM_INPUTS = 1;"));
+    assert!(contract.contains(
+        "uint256 constant R = 0x73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001;"
+    ));
+
+    let calldata = solidity_calldata(&proof, &[Fr::one()]);
+    assert_eq!(&calldata[..4], &[0x05, 0x80, 0x72, 0xbd]);
+    // Four offsets, three points with their lengths and an array of one input
+    assert_eq!(
+        calldata.len(),
+        4 + 4 * 32 + (32 + 128) * 2 + (32 + 256) + 32 + 32
+    );
+    assert_eq!(calldata[calldata.len() - 1], 1);
+}
+
+#[cfg(feature = "snarkjs")]
+#[test]
+fn test_snarkjs_json() {
+    use super::create_random_proof;
+    use super::snarkjs::*;
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut crate::testing::test_rng();
+    let (params, _) = xor_demo_params::<Bls12, _>(rng);
+    let c = XORDemo::new(true, false);
+    let proof = create_random_proof(c, &params, rng).unwrap();
+
+    let proof_json = proof_to_json(&proof);
+    let vk_json = verifying_key_to_json(&params.vk);
+    let public_json = public_inputs_to_json(&[Fr::one()]);
+    assert_eq!(
+        public_json.split_whitespace().collect::<String>(),
+        r#"["1"]"#
+    );
+
+    let imported = proof_from_json(&proof_json).unwrap();
+    assert!(imported == proof);
+    let pvk = prepared_verifying_key_from_json(&vk_json).unwrap();
+    let inputs = public_inputs_from_json(&public_json).unwrap();
+    assert!(verify_proof(&pvk, &imported, &inputs).unwrap());
+
+    assert!(proof_from_json(r#"{"pi_a": ["1", "2", "1"]}"#).is_err());
+    assert!(public_inputs_from_json(r#"["-1"]"#).is_err());
+}
+
+#[test]
+fn test_ark_serialization() {
+    use super::ark::*;
+    use super::create_random_proof;
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut crate::testing::test_rng();
+    let (params, _) = xor_demo_params::<Bls12, _>(rng);
+    let c = XORDemo::new(true, false);
+    let proof = create_random_proof(c, &params, rng).unwrap();
+
+    let mut vk_bytes = vec![];
+    write_verifying_key(&params.vk, &mut vk_bytes).unwrap();
+    // Four points, the length of IC and two IC points
+    assert_eq!(vk_bytes.len(), 48 + 3 * 96 + 8 + 2 * 48);
+    let mut proof_bytes = vec![];
+    write_proof(&proof, &mut proof_bytes).unwrap();
+    assert_eq!(proof_bytes.len(), 48 + 96 + 48);
+
+    let pvk = read_verifying_key(&vk_bytes[..]).unwrap();
+    let read = read_proof(&proof_bytes[..]).unwrap();
+    assert!(verify_proof(&pvk, &read, &[Fr::one()]).unwrap());
+
+    assert!(read_verifying_key(&vk_bytes[..vk_bytes.len() - 1]).is_err());
+}
+
+#[test]
+fn test_string_encodings() {
+    use super::{create_random_proof, Proof, VerifyingKey};
+    use crate::bls::Bls12;
+
+    let rng = &mut crate::testing::test_rng();
+    let (params, _) = xor_demo_params::<Bls12, _>(rng);
+    let c = XORDemo::new(true, false);
+    let proof = create_random_proof(c, &params, rng).unwrap();
+
+    let hex = proof.to_hex();
+    assert_eq!(hex.len(), 2 * (48 + 96 + 48));
+    assert_eq!(hex, proof.to_string());
+    assert!(Proof::<Bls12>::from_hex(&hex).unwrap() == proof);
+    assert!(Proof::<Bls12>::from_hex(&format!("0x{}", hex.to_uppercase())).unwrap() == proof);
+    assert!(hex.parse::<Proof<Bls12>>().unwrap() == proof);
+    assert!(Proof::<Bls12>::from_base64(&proof.to_base64()).unwrap() == proof);
+
+    assert!(Proof::<Bls12>::from_hex(&hex[1..]).is_err());
+    assert!(Proof::<Bls12>::from_hex(&hex[2..]).is_err());
+    assert!(Proof::<Bls12>::from_hex(&format!("{}00", hex)).is_err());
+
+    let vk = &params.vk;
+    assert!(vk.to_string().parse::<VerifyingKey<Bls12>>().unwrap() == *vk);
+    assert!(VerifyingKey::<Bls12>::from_base64(&vk.to_base64()).unwrap() == *vk);
+}
+
+#[test]
+fn test_zcash_parameters() {
+    use super::zcash::*;
+    use super::{create_random_proof, Parameters};
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut crate::testing::test_rng();
+    let (params, _) = xor_demo_params::<Bls12, _>(rng);
+
+    let mut exported = vec![];
+    write_parameters(&params, &mut exported).unwrap();
+    let mut legacy = vec![];
+    params.write_legacy(&mut legacy).unwrap();
+    assert_eq!(exported, legacy);
+
+    let imported = read_parameters(&exported[..], true).unwrap();
+    assert!(imported == params);
+    assert!(Parameters::<Bls12>::read(&exported[..], true).unwrap() == params);
+
+    // The container format isn't upstream's.
+    let mut container = vec![];
+    params.write(&mut container).unwrap();
+    assert!(read_parameters(&container[..], false).is_err());
+
+    let c = XORDemo::new(true, false);
+    let proof = create_random_proof(c, &imported, rng).unwrap();
+    let mut vk = vec![];
+    write_verifying_key(&imported.vk, &mut vk).unwrap();
+    let pvk = prepare_verifying_key(&read_verifying_key(&vk[..]).unwrap());
+    assert!(verify_proof(&pvk, &proof, &[Fr::one()]).unwrap());
+}
+
+/// Generates parameters, proves and verifies on any engine, checking that the
+/// encodings have the sizes of the engine's points.
+fn engine_round_trip<E: Engine>() {
+    use super::{create_random_proof, Parameters, Proof};
+    use groupy::{CurveAffine, EncodedPoint};
+
+    let g1_size = <<E::G1Affine as CurveAffine>::Compressed as EncodedPoint>::size();
+    let g2_size = <<E::G2Affine as CurveAffine>::Compressed as EncodedPoint>::size();
+
+    let rng = &mut crate::testing::test_rng();
+    let (params, pvk) = xor_demo_params::<E, _>(rng);
+    let mut encoded = vec![];
+    params.write(&mut encoded).unwrap();
+    assert!(Parameters::<E>::read(&encoded[..], true).unwrap() == params);
+
+    for &(a, b) in &[(false, false), (false, true), (true, false), (true, true)] {
+        let c = XORDemo::<E>::new(a, b);
+        let proof = create_random_proof(c, &params, rng).unwrap();
+
+        let mut encoded = vec![];
+        proof.write(&mut encoded).unwrap();
+        assert_eq!(encoded.len(), 2 * g1_size + g2_size);
+        let proof = Proof::<E>::read(&encoded[..]).unwrap();
+
+        let c = if a ^ b { E::Fr::one() } else { E::Fr::zero() };
+        assert!(verify_proof(&pvk, &proof, &[c]).unwrap());
+    }
+}
+
+#[test]
+fn test_engine_round_trip() {
+    engine_round_trip::<crate::bls::Bls12>();
+}
+
+#[test]
+fn test_seeded_proofs() {
+    use super::create_random_proof;
+    use crate::bls::Bls12;
+    use crate::InsecureRng;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    let (params, pvk) = xor_demo_params::<Bls12, _>(&mut crate::testing::test_rng());
+
+    let prove = |seed| {
+        let c = XORDemo::<Bls12>::new(true, false);
+        let rng = &mut InsecureRng(XorShiftRng::from_seed(seed));
+        create_random_proof(c, &params, rng).unwrap()
+    };
+
+    // The same seed gives the same blinding factors, so the same proof.
+    let proof = prove([1; 16]);
+    assert!(proof == prove([1; 16]));
+    assert!(proof != prove([2; 16]));
+
+    assert!(verify_proof(&pvk, &proof, &[<Bls12 as Engine>::Fr::one()]).unwrap());
+}
+
+#[test]
+fn test_distributed_proof() {
+    use super::create_random_proof_distributed;
+    use super::distributed::{handle, Cluster, FftKind, RemoteWorker};
+    use crate::bls::{Bls12, Fr};
+    use std::net::TcpListener;
+    use std::thread;
+
+    let rng = &mut crate::testing::test_rng();
+    let (params, pvk) = xor_demo_params::<Bls12, _>(rng);
+
+    // Two workers, so that every multiexp is split, and one for an FFT.
+    let addrs = (0..3)
+        .map(|_| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            let params = params.clone();
+            thread::spawn(move || {
+                let (stream, _) = listener.accept().unwrap();
+                handle::<Bls12, _, _>(stream, &params, &Worker::new()).unwrap();
+            });
+            addr
+        })
+        .collect::<Vec<_>>();
+    let cluster = Cluster::connect(&addrs[..2]).unwrap();
+    assert_eq!(cluster.num_workers(), 2);
+
+    for &(a, b) in &[(false, false), (true, false)] {
+        let c = XORDemo::<Bls12>::new(a, b);
+        let proof = create_random_proof_distributed(c, &params, rng, &cluster).unwrap();
+
+        let c = if a ^ b { Fr::one() } else { Fr::zero() };
+        assert!(verify_proof(&pvk, &proof, &[c]).unwrap());
+    }
+
+    // A remote FFT is undone by a remote inverse FFT.
+    let mut worker = RemoteWorker::connect(addrs[2]).unwrap();
+    let values = (0..8).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+    let evals = worker.fft::<Bls12>(FftKind::Fft, values.clone()).unwrap();
+    assert!(evals != values);
+    assert_eq!(worker.fft::<Bls12>(FftKind::Ifft, evals).unwrap(), values);
+}
+
+#[test]
+fn test_prover_options() {
+    use super::{create_proof_with_opts, verify_proof_with_opts, Priority, ProverOptions};
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut crate::testing::test_rng();
+    let (params, pvk) = xor_demo_params::<Bls12, _>(rng);
+
+    let worker = Worker::new();
+    let opts = ProverOptions {
+        priority: Priority::Low,
+        lock_timeout: Some(Duration::from_secs(1)),
+        worker: Some(&worker),
+        memory_budget: Some(1 << 20),
+        ..ProverOptions::cpu()
+    };
+    let c = XORDemo::<Bls12>::new(true, false);
+    let proof =
+        create_proof_with_opts(c, &params, Fr::random(rng), Fr::random(rng), &opts).unwrap();
+    assert!(verify_proof_with_opts(&pvk, &proof, &[Fr::one()], &opts).unwrap());
+
+    let opts = ProverOptions {
+        memory_budget: Some(0),
+        ..Default::default()
+    };
+    let c = XORDemo::<Bls12>::new(true, false);
+    match create_proof_with_opts(c, &params, Fr::random(rng), Fr::random(rng), &opts) {
+        Err(SynthesisError::MemoryBudgetExceeded(0)) => {}
+        _ => panic!("the proof should have exceeded its budget"),
+    }
+
+    // With a place to spill to, the proof completes anyway.
+    let dir = std::env::temp_dir();
+    let opts = ProverOptions {
+        memory_budget: Some(0),
+        spill_dir: Some(&dir),
+        ..Default::default()
+    };
+    let c = XORDemo::<Bls12>::new(true, false);
+    let proof =
+        create_proof_with_opts(c, &params, Fr::random(rng), Fr::random(rng), &opts).unwrap();
+    assert!(verify_proof_with_opts(&pvk, &proof, &[Fr::one()], &opts).unwrap());
+}
+
+#[test]
+fn test_proofs_of_different_priorities() {
+    use super::{create_random_proof_with_opts, Priority, ProverOptions};
+    use crate::bls::{Bls12, Fr};
+    use std::thread;
+
+    let rng = &mut crate::testing::test_rng();
+    let (params, pvk) = xor_demo_params::<Bls12, _>(rng);
+
+    // A high and a low priority proof at the same time, neither of them fails.
+    let proofs = [Priority::High, Priority::Low]
+        .iter()
+        .map(|&priority| {
+            let params = params.clone();
+            thread::spawn(move || {
+                let c = XORDemo::<Bls12>::new(true, false);
+                let rng = &mut crate::testing::test_rng();
+                let opts = ProverOptions {
+                    priority,
+                    ..Default::default()
+                };
+                create_random_proof_with_opts(c, &params, rng, &opts).unwrap()
+            })
+        })
+        .collect::<Vec<_>>();
+    for proof in proofs {
+        let proof = proof.join().unwrap();
+        assert!(verify_proof(&pvk, &proof, &[Fr::one()]).unwrap());
+    }
+}
+
+#[test]
+fn test_streamed_evaluations() {
+    use super::{create_proof_with_opts, generate_random_parameters, ProverOptions};
+    use crate::bls::{Bls12, Fr};
+    use std::fs;
+
+    // Squares a number again and again, with more constraints than a chunk of
+    // the streams.
+    struct Squarings(usize);
+
+    impl Circuit<Bls12> for Squarings {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let mut value = Fr::from_str("3").unwrap();
+            let mut var = cs.alloc(|| "x0", || Ok(value))?;
+            for i in 0..self.0 {
+                value.square();
+                let next = cs.alloc(|| format!("x{}", i + 1), || Ok(value))?;
+                cs.enforce(
+                    || format!("square {}", i),
+                    |lc| lc + var,
+                    |lc| lc + var,
+                    |lc| lc + next,
+                );
+                var = next;
+            }
+            let out = cs.alloc_input(|| "out", || Ok(value))?;
+            cs.enforce(|| "out", |lc| lc + var, |lc| lc + CS::one(), |lc| lc + out);
+            Ok(())
+        }
+    }
+
+    const SQUARINGS: usize = 5000;
+    let mut out = Fr::from_str("3").unwrap();
+    for _ in 0..SQUARINGS {
+        out.square();
+    }
+
+    let rng = &mut crate::testing::test_rng();
+    let params = generate_random_parameters(Squarings(SQUARINGS), rng).unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let dir = std::env::temp_dir().join(format!("bellman-stream-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let opts = ProverOptions {
+        stream_dir: Some(&dir),
+        ..Default::default()
+    };
+    let proof = create_proof_with_opts(
+        Squarings(SQUARINGS),
+        &params,
+        Fr::random(rng),
+        Fr::random(rng),
+        &opts,
+    )
+    .unwrap();
+    assert!(verify_proof(&pvk, &proof, &[out]).unwrap());
+
+    // The streams are removed once they are read back.
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+    fs::remove_dir(&dir).unwrap();
+}
+
+#[test]
+fn test_streamed_multiplications() {
+    use super::{create_proof_with_opts, ProverOptions};
+    use crate::bls::{Bls12, Fr};
+    use std::fs;
+    use std::path::PathBuf;
+
+    // Only multiplications, which don't go through `enforce`, checking that
+    // their evaluations are streamed to the directory if there is one.
+    struct Multiplications(usize, Option<PathBuf>);
+
+    impl Circuit<Bls12> for Multiplications {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let three = Fr::from_str("3").unwrap();
+            let mut product = None;
+            for i in 0..self.0 {
+                let (_, _, c) = cs.multiply(|| format!("mul {}", i), || Ok((three, three)))?;
+                product = Some(c);
+            }
+            // The evaluations are in the files before synthesis ends.
+            if let Some(dir) = self.1 {
+                let streamed = fs::read_dir(&dir)
+                    .unwrap()
+                    .map(|entry| entry.unwrap().metadata().unwrap().len())
+                    .sum::<u64>();
+                assert!(streamed > 0);
+            }
+
+            let out = cs.alloc_input(|| "out", || Ok(Fr::from_str("9").unwrap()))?;
+            cs.enforce(
+                || "out",
+                |lc| lc + product.unwrap(),
+                |lc| lc + CS::one(),
+                |lc| lc + out,
+            );
+            Ok(())
+        }
+    }
+
+    const MULTIPLICATIONS: usize = 5000;
+    let dir = std::env::temp_dir().join(format!("bellman-multiply-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let rng = &mut crate::testing::test_rng();
+    let params = generate_random_parameters(Multiplications(MULTIPLICATIONS, None), rng).unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let opts = ProverOptions {
+        stream_dir: Some(&dir),
+        ..Default::default()
+    };
+    let proof = create_proof_with_opts(
+        Multiplications(MULTIPLICATIONS, Some(dir.clone())),
+        &params,
+        Fr::random(rng),
+        Fr::random(rng),
+        &opts,
+    )
+    .unwrap();
+    assert!(verify_proof(&pvk, &proof, &[Fr::from_str("9").unwrap()]).unwrap());
+
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+    fs::remove_dir(&dir).unwrap();
+}
+
+#[test]
+fn test_spill() {
+    use super::spill::Spilled;
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!("bellman-spill-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let values = (0..1000u64).map(|i| (i, i * i)).collect::<Vec<_>>();
+    let spilled = Spilled::write(&dir, &values).unwrap();
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    // Only the owner may read the file.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let entry = fs::read_dir(&dir).unwrap().next().unwrap().unwrap();
+        let mode = entry.metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    // The file is gone once the values are read back.
+    assert_eq!(spilled.read().unwrap(), values);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+    fs::remove_dir(&dir).unwrap();
+}
+
+#[test]
+fn test_circuit_is_satisfied() {
+    use super::circuit_is_satisfied;
+    use crate::bls::{Bls12, Fr};
+
+    // The product of 3 and 4 with a claimed result.
+    struct Product(u64);
+
+    impl Circuit<Bls12> for Product {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let fr = |n: u64| Fr::from_str(&n.to_string()).unwrap();
+            let a = cs.alloc(|| "a", || Ok(fr(3)))?;
+            let b = cs.alloc(|| "b", || Ok(fr(4)))?;
+            let c = cs.alloc_input(|| "c", || Ok(fr(self.0)))?;
+            cs.enforce(|| "a * b = c", |lc| lc + a, |lc| lc + b, |lc| lc + c);
+            Ok(())
+        }
+    }
+
+    assert!(circuit_is_satisfied(Product(12)).unwrap());
+    assert!(!circuit_is_satisfied(Product(13)).unwrap());
+
+    let c = XORDemo::<Bls12>::new(true, true);
+    assert!(circuit_is_satisfied(c).unwrap());
+}
+
+#[test]
+fn test_public_inputs() {
+    use super::{create_random_proof, public_inputs};
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut crate::testing::test_rng();
+    let (params, pvk) = xor_demo_params::<Bls12, _>(rng);
+    let circuit = |a, b| XORDemo::<Bls12>::new(a, b);
+
+    let proof = create_random_proof(circuit(true, false), &params, rng).unwrap();
+    let inputs = public_inputs(circuit(true, false)).unwrap();
+    assert!(inputs == vec![Fr::one()]);
+
+    assert!(verify_proof(&pvk, &proof, &inputs).unwrap());
+    let inputs = public_inputs(circuit(true, true)).unwrap();
+    assert!(!verify_proof(&pvk, &proof, &inputs).unwrap());
+
+    let c = XORDemo::<Bls12> {
+        a: Some(true),
+        b: None,
+        _marker: PhantomData,
+    };
+    match public_inputs(c) {
+        Err(SynthesisError::AssignmentMissing) => {}
+        _ => panic!("expected a missing assignment"),
+    }
+}
+
+#[test]
+fn test_verify_proof_from_bytes() {
+    use super::{create_random_proof, verify_proof_from_bytes};
+    use crate::bls::{Bls12, Fr};
+    use ff::PrimeFieldRepr;
+
+    let rng = &mut crate::testing::test_rng();
+    let (params, pvk) = xor_demo_params::<Bls12, _>(rng);
+    let c = XORDemo::<Bls12>::new(true, false);
+    let mut proof = vec![];
+    create_random_proof(c, &params, rng)
+        .unwrap()
+        .write(&mut proof)
+        .unwrap();
+    let encode = |inputs: &[Fr]| {
+        let mut bytes = vec![];
+        for input in inputs {
+            input.into_repr().write_be(&mut bytes).unwrap();
+        }
+        bytes
+    };
+
+    assert!(verify_proof_from_bytes(&pvk, &proof, &encode(&[Fr::one()])).unwrap());
+    assert!(!verify_proof_from_bytes(&pvk, &proof, &encode(&[Fr::zero()])).unwrap());
+
+    let invalid = |proof: &[u8], inputs: &[u8]| match verify_proof_from_bytes(&pvk, proof, inputs) {
+        Err(SynthesisError::IoError(e)) => e.kind() == std::io::ErrorKind::InvalidData,
+        _ => false,
+    };
+    // The modulus isn't a canonical encoding.
+    let mut modulus = vec![];
+    Fr::char().write_be(&mut modulus).unwrap();
+    assert!(invalid(&proof, &modulus));
+    assert!(invalid(&proof, &encode(&[Fr::one()])[1..]));
+    assert!(invalid(&proof, &encode(&[Fr::one(), Fr::one()])));
+    let mut trailing = proof.clone();
+    trailing.push(0);
+    assert!(invalid(&trailing, &encode(&[Fr::one()])));
+}
+
+#[test]
+fn test_proof_from_bytes() {
+    use super::{create_random_proof, Proof, ProofDecodingError};
+    use crate::bls::Bls12;
+    use groupy::{CurveAffine, EncodedPoint};
+
+    let rng = &mut crate::testing::test_rng();
+    let (params, _) = xor_demo_params::<Bls12, _>(rng);
+    let c = XORDemo::<Bls12>::new(true, false);
+    let proof = create_random_proof(c, &params, rng).unwrap();
+    let mut bytes = vec![];
+    proof.write(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), Proof::<Bls12>::size());
+    assert!(Proof::<Bls12>::from_bytes(&bytes).unwrap() == proof);
+
+    assert_eq!(
+        Proof::<Bls12>::from_bytes(&bytes[1..]).err(),
+        Some(ProofDecodingError::Length {
+            expected: bytes.len(),
+            actual: bytes.len() - 1,
+        })
+    );
+    let short = Proof::<Bls12>::read(&bytes[1..]).err().unwrap();
+    assert_eq!(short.kind(), std::io::ErrorKind::UnexpectedEof);
+
+    let zero = <Bls12 as crate::bls::Engine>::G1Affine::zero().into_compressed();
+    let mut infinity = bytes.clone();
+    infinity[..zero.as_ref().len()].copy_from_slice(zero.as_ref());
+    assert_eq!(
+        Proof::<Bls12>::from_bytes(&infinity).err(),
+        Some(ProofDecodingError::PointAtInfinity("a"))
+    );
+
+    // Not a point of G1
+    let mut invalid = bytes.clone();
+    let len = invalid.len();
+    for byte in &mut invalid[len - zero.as_ref().len() + 1..] {
+        *byte = 0xff;
+    }
+    assert_eq!(
+        Proof::<Bls12>::from_bytes(&invalid).err(),
+        Some(ProofDecodingError::InvalidPoint("c"))
+    );
+}
+
+#[test]
+fn test_lazy_parameters() {
+    use super::{create_random_proof, LazyParameters, ParameterSource, Parameters};
+    use crate::bls::{Bls12, Fr};
+    use std::fs;
+
+    let rng = &mut crate::testing::test_rng();
+    let (params, pvk) = xor_demo_params::<Bls12, _>(rng);
+
+    let write: [fn(&Parameters<Bls12>, &mut Vec<u8>) -> std::io::Result<()>; 3] = [
+        |params, out| params.write(out),
+        |params, out| params.write_compressed(out),
+        |params, out| params.write_legacy(out),
+    ];
+    for (i, write) in write.iter().enumerate() {
+        let path = std::env::temp_dir().join(format!(
+            "bellperson-lazy-{}-{}.params",
+            std::process::id(),
+            i
+        ));
+        let mut encoded = vec![];
+        write(&params, &mut encoded).unwrap();
+        fs::write(&path, &encoded).unwrap();
+
+        let lazy = LazyParameters::<Bls12>::open(&path, true).unwrap();
+        assert!(*lazy.vk() == params.vk);
+        let c = XORDemo::<Bls12>::new(true, false);
+        let proof = create_random_proof(c, &lazy, rng).unwrap();
+        assert!(verify_proof(&pvk, &proof, &[Fr::one()]).unwrap());
+
+        // A query that is still in use is shared instead of read again.
+        let mut source = &lazy;
+        let (a_inputs, a_aux) = source.get_a(2, 0).unwrap();
+        let (b_g2, _) = source.get_b_g2(2, 0).unwrap();
+        assert!(a_inputs.shares_bases(&a_aux));
+        assert!(a_inputs.as_slice() == &params.a[..2]);
+        assert!(a_aux.as_slice() == &params.a[2..]);
+        assert!(a_inputs.shares_bases(&source.get_a(2, 0).unwrap().0));
+        assert!(b_g2.shares_bases(&source.get_b_g2(2, 0).unwrap().0));
+
+        // Truncated files are rejected when they are opened.
+        fs::write(&path, &encoded[..encoded.len() - 1]).unwrap();
+        assert!(LazyParameters::<Bls12>::open(&path, true).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
+
+#[test]
+fn test_cached_parameters() {
+    use super::{create_proof, CachedParameters};
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut crate::testing::test_rng();
+    let (params, pvk) = xor_demo_params::<Bls12, _>(rng);
+
+    for &window in [1, 4].iter() {
+        let cached = CachedParameters::new(&params, window);
+        assert!(*cached.vk() == params.vk);
+
+        let r = Fr::random(rng);
+        let s = Fr::random(rng);
+        let prove = |a, b| XORDemo::<Bls12>::new(a, b);
+        for &(a, b) in [(true, false), (true, true)].iter() {
+            let proof = create_proof(prove(a, b), &cached, r, s).unwrap();
+            assert!(proof == create_proof(prove(a, b), &params, r, s).unwrap());
+            let input = if a ^ b { Fr::one() } else { Fr::zero() };
+            assert!(verify_proof(&pvk, &proof, &[input]).unwrap());
+        }
+    }
+}
+
+#[test]
+fn test_shared_parameters() {
+    use super::{
+        create_random_proof, CachedParameters, Parameters, PreparedVerifyingKey, Proof,
+        VerifyingKey,
+    };
+    use crate::bls::{Bls12, Fr};
+    use std::sync::Arc;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Parameters<Bls12>>();
+    assert_send_sync::<CachedParameters<Bls12>>();
+    assert_send_sync::<VerifyingKey<Bls12>>();
+    assert_send_sync::<PreparedVerifyingKey<Bls12>>();
+    assert_send_sync::<Proof<Bls12>>();
+
+    let rng = &mut crate::testing::test_rng();
+    let (params, pvk) = xor_demo_params::<Bls12, _>(rng);
+    let (params, pvk) = (Arc::new(params), Arc::new(pvk));
+
+    let threads = (0..4)
+        .map(|i| {
+            let params = params.clone();
+            let pvk = pvk.clone();
+            thread::spawn(move || {
+                let c = XORDemo::<Bls12>::new(i % 2 == 0, false);
+                let proof = create_random_proof(c, params, &mut rand::thread_rng()).unwrap();
+                let input = if i % 2 == 0 { Fr::one() } else { Fr::zero() };
+                assert!(verify_proof(&pvk, &proof, &[input]).unwrap());
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+#[test]
+fn test_specialized_parameters() {
+    use super::{
+        create_random_proof, generate_random_parameters, specialize_verifying_key,
+        SpecializedParameters,
+    };
+    use crate::bls::{Bls12, Fr};
+
+    // Proves `x * y * z = out` for public inputs `x`, `y`, `z` and `out`,
+    // where `y` and `z` are part of the B query.
+    struct Product {
+        inputs: Option<[u64; 3]>,
+    }
+
+    impl Circuit<Bls12> for Product {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let value = |i: usize| {
+                self.inputs
+                    .map(|inputs| Fr::from_str(&inputs[i].to_string()).unwrap())
+                    .ok_or(SynthesisError::AssignmentMissing)
+            };
+            let product = |n: usize| {
+                self.inputs
+                    .map(|inputs| {
+                        let product: u64 = inputs[..n].iter().product();
+                        Fr::from_str(&product.to_string()).unwrap()
+                    })
+                    .ok_or(SynthesisError::AssignmentMissing)
+            };
+
+            let x = cs.alloc_input(|| "x", || value(0))?;
+            let y = cs.alloc_input(|| "y", || value(1))?;
+            let z = cs.alloc_input(|| "z", || value(2))?;
+            let xy = cs.alloc(|| "xy", || product(2))?;
+            let out = cs.alloc_input(|| "out", || product(3))?;
+            cs.enforce(|| "x * y", |lc| lc + x, |lc| lc + y, |lc| lc + xy);
+            cs.enforce(|| "xy * z", |lc| lc + xy, |lc| lc + z, |lc| lc + out);
+
+            Ok(())
+        }
+    }
+
+    let rng = &mut crate::testing::test_rng();
+    let params = generate_random_parameters(Product { inputs: None }, rng).unwrap();
+
+    // Fix `y`, which is part of the B query, and `x`, which isn't.
+    let fixed = [
+        (1, Fr::from_str("3").unwrap()),
+        (0, Fr::from_str("2").unwrap()),
+    ];
+    let specialized =
+        SpecializedParameters::new(&params, Product { inputs: None }, &fixed).unwrap();
+    assert_eq!(specialized.vk().ic.len(), 3);
+    assert!(specialize_verifying_key(&params.vk, &fixed) == *specialized.vk());
+    let pvk = prepare_verifying_key(specialized.vk());
+
+    // Inputs that are fixed twice or don't exist are errors.
+    for fixed in &[
+        [(0, Fr::one()), (0, Fr::one())],
+        [(0, Fr::one()), (4, Fr::one())],
+    ] {
+        assert!(SpecializedParameters::new(&params, Product { inputs: None }, fixed).is_err());
+    }
+
+    let z = Fr::from_str("5").unwrap();
+    let out = Fr::from_str("30").unwrap();
+    let proof = create_random_proof(
+        Product {
+            inputs: Some([2, 3, 5]),
+        },
+        &specialized,
+        rng,
+    )
+    .unwrap();
+    assert!(verify_proof(&pvk, &proof, &[z, out]).unwrap());
+    assert!(!verify_proof(&pvk, &proof, &[out, z]).unwrap());
+
+    // Proofs with the original parameters verify as well.
+    let proof = create_random_proof(
+        Product {
+            inputs: Some([2, 3, 5]),
+        },
+        &params,
+        rng,
+    )
+    .unwrap();
+    assert!(verify_proof(&pvk, &proof, &[z, out]).unwrap());
+
+    // The fixed inputs must have their values.
+    let res = create_random_proof(
+        Product {
+            inputs: Some([2, 4, 5]),
+        },
+        &specialized,
+        rng,
+    );
+    assert!(match res {
+        Err(SynthesisError::Unsatisfiable) => true,
+        _ => false,
+    });
+}
+
+#[test]
+fn test_witness_serialization() {
+    use super::{create_random_proof_with_witness, CachedCircuit, ProvingWitness};
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut crate::testing::test_rng();
+    let circuit = |a, b| XORDemo::<Bls12> {
+        a,
+        b,
+        _marker: PhantomData,
+    };
+    let (params, pvk) = xor_demo_params::<Bls12, _>(rng);
+    let cached = CachedCircuit::new(circuit(None, None)).unwrap();
+
+    let witness = ProvingWitness::new(circuit(Some(true), Some(false))).unwrap();
+    assert_eq!(witness.num_inputs(), cached.num_inputs());
+    assert_eq!(witness.num_aux(), cached.num_aux());
+    let mut bytes = vec![];
+    witness.write(&mut bytes).unwrap();
+
+    let witness = ProvingWitness::<Bls12>::read(&bytes[..]).unwrap();
+    let mut written = vec![];
+    witness.write(&mut written).unwrap();
+    assert_eq!(written, bytes);
+
+    let proof = create_random_proof_with_witness(&cached, witness, &params, rng).unwrap();
+    assert!(verify_proof(&pvk, &proof, &[Fr::one()]).unwrap());
+    assert!(!verify_proof(&pvk, &proof, &[Fr::zero()]).unwrap());
+
+    // Truncated or corrupted witnesses are rejected.
+    assert!(ProvingWitness::<Bls12>::read(&bytes[..bytes.len() - 1]).is_err());
+    let mut corrupted = bytes.clone();
+    corrupted[0] ^= 1;
+    assert!(ProvingWitness::<Bls12>::read(&corrupted[..]).is_err());
+}
@@ -1,8 +1,13 @@
-use ff::PrimeField;
+use std::io;
+use std::sync::Arc;
+
+use crate::bls::{Engine, PairingCurveAffine};
+use ff::{PrimeField, PrimeFieldRepr};
 use groupy::{CurveAffine, CurveProjective};
-use paired::{Engine, PairingCurveAffine};
 
-use super::{PreparedVerifyingKey, Proof, VerifyingKey};
+use super::{PreparedVerifyingKey, Proof, ProverOptions, VerifyingKey};
+use crate::multicore::Worker;
+use crate::multiexp::{multiexp, FullDensity};
 use crate::SynthesisError;
 
 pub fn prepare_verifying_key<E: Engine>(vk: &VerifyingKey<E>) -> PreparedVerifyingKey<E> {
@@ -15,10 +20,14 @@ pub fn prepare_verifying_key<E: Engine>(vk: &VerifyingKey<E>) -> PreparedVerifyi
         alpha_g1_beta_g2: E::pairing(vk.alpha_g1, vk.beta_g2),
         neg_gamma_g2: gamma.prepare(),
         neg_delta_g2: delta.prepare(),
-        ic: vk.ic.clone(),
+        ic: Arc::new(vk.ic.clone()),
+        ic_tables: None,
     }
 }
 
+/// Verifies a proof on the calling thread. It takes no locks and doesn't touch
+/// the file system, so it also runs inside enclaves, see the SGX section of
+/// the README.
 pub fn verify_proof<'a, E: Engine>(
     pvk: &'a PreparedVerifyingKey<E>,
     proof: &Proof<E>,
@@ -30,10 +39,122 @@ pub fn verify_proof<'a, E: Engine>(
 
     let mut acc = pvk.ic[0].into_projective();
 
-    for (i, b) in public_inputs.iter().zip(pvk.ic.iter().skip(1)) {
-        acc.add_assign(&b.mul(i.into_repr()));
+    if let Some(ref tables) = pvk.ic_tables {
+        let exps = public_inputs
+            .iter()
+            .map(|i| i.into_repr())
+            .collect::<Vec<_>>();
+        acc.add_assign(&tables.multiexp(&exps));
+    } else {
+        for (i, b) in public_inputs.iter().zip(pvk.ic.iter().skip(1)) {
+            acc.add_assign(&b.mul(i.into_repr()));
+        }
     }
 
+    Ok(check_proof(pvk, proof, acc))
+}
+
+/// Verifies a proof like [`verify_proof`], but accumulates the public inputs
+/// with a multiexponentiation on the given worker, which pays off for many
+/// public inputs.
+pub fn verify_proof_with_worker<'a, E: Engine>(
+    pvk: &'a PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs: &[E::Fr],
+    worker: &Worker,
+) -> Result<bool, SynthesisError> {
+    if (public_inputs.len() + 1) != pvk.ic.len() {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+
+    let mut acc = pvk.ic[0].into_projective();
+
+    if !public_inputs.is_empty() {
+        let exps = public_inputs.iter().map(|i| i.into_repr()).collect();
+        acc.add_assign(
+            &multiexp(
+                worker,
+                (pvk.ic.clone(), 1),
+                FullDensity,
+                Arc::new(exps),
+                &mut None,
+            )
+            .wait()?,
+        );
+    }
+
+    Ok(check_proof(pvk, proof, acc))
+}
+
+/// Verifies a proof on the worker of `opts` if it has one, so that the same
+/// options can be passed to proving and verifying. Verification always runs
+/// on the CPU, the other options only apply to proving.
+pub fn verify_proof_with_opts<'a, E: Engine>(
+    pvk: &'a PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs: &[E::Fr],
+    opts: &ProverOptions,
+) -> Result<bool, SynthesisError> {
+    match opts.worker {
+        Some(worker) => verify_proof_with_worker(pvk, proof, public_inputs, worker),
+        None => verify_proof(pvk, proof, public_inputs),
+    }
+}
+
+/// Verifies a proof serialized like [`Proof::write`] writes it, against
+/// public inputs serialized as their canonical big-endian encodings, one
+/// after the other. Bytes that aren't such encodings, e.g. of scalars that
+/// aren't reduced or of the wrong number of inputs, are rejected with an I/O
+/// error of kind `InvalidData`.
+pub fn verify_proof_from_bytes<'a, E: Engine>(
+    pvk: &'a PreparedVerifyingKey<E>,
+    proof: &[u8],
+    public_inputs: &[u8],
+) -> Result<bool, SynthesisError> {
+    let proof = Proof::<E>::from_bytes(proof).map_err(io::Error::from)?;
+
+    let public_inputs = read_public_inputs::<E::Fr>(public_inputs)?;
+    if public_inputs.len() + 1 != pvk.ic.len() {
+        return Err(invalid_data(format!(
+            "expected {} public inputs, got {}",
+            pvk.ic.len() - 1,
+            public_inputs.len()
+        ))
+        .into());
+    }
+
+    verify_proof(pvk, &proof, &public_inputs)
+}
+
+/// Reads scalars from their canonical big-endian encodings, one after the
+/// other.
+pub fn read_public_inputs<F: PrimeField>(bytes: &[u8]) -> io::Result<Vec<F>> {
+    let size = F::Repr::default().as_ref().len() * 8;
+    if bytes.len() % size != 0 {
+        return Err(invalid_data(format!(
+            "public inputs are {} bytes, not a multiple of {}",
+            bytes.len(),
+            size
+        )));
+    }
+
+    bytes
+        .chunks(size)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut repr = F::Repr::default();
+            repr.read_be(chunk)?;
+            F::from_repr(repr)
+                .map_err(|_| invalid_data(format!("public input {} is not a canonical scalar", i)))
+        })
+        .collect()
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn check_proof<E: Engine>(pvk: &PreparedVerifyingKey<E>, proof: &Proof<E>, acc: E::G1) -> bool {
     // The original verification equation is:
     // A * B = alpha * beta + inputs * gamma + C * delta
     // ... however, we rearrange it so that it is:
@@ -42,7 +163,7 @@ pub fn verify_proof<'a, E: Engine>(
     // A * B + inputs * (-gamma) + C * (-delta) = alpha * beta
     // which allows us to do a single final exponentiation.
 
-    Ok(E::final_exponentiation(&E::miller_loop(
+    E::final_exponentiation(&E::miller_loop(
         [
             (&proof.a.prepare(), &proof.b.prepare()),
             (&acc.into_affine().prepare(), &pvk.neg_gamma_g2),
@@ -51,5 +172,5 @@ pub fn verify_proof<'a, E: Engine>(
         .iter(),
     ))
     .unwrap()
-        == pvk.alpha_g1_beta_g2)
+        == pvk.alpha_g1_beta_g2
 }
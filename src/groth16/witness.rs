@@ -0,0 +1,232 @@
+//! The binary format of a [`ProvingWitness`], for computing the witness in a
+//! different process or on a different machine than the proof. It starts with
+//! a header:
+//!
+//! - the magic bytes `BPWITNES` and the format version as big-endian `u32`,
+//! - the number of inputs, including `ONE`, and of auxiliary variables, as
+//!   big-endian `u32`.
+//!
+//! It's followed by the input and the auxiliary assignment, every element in
+//! its canonical big-endian encoding, and the densities of the auxiliary
+//! variables in the A query and of the inputs and auxiliary variables in the B
+//! query, every one a bit per variable, see [`DensityTracker::to_bytes`].
+
+use std::cmp;
+use std::io::{self, Read, Write};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use ff::{Field, PrimeField, PrimeFieldRepr};
+use zeroize::Zeroize;
+
+use super::prover::wipe;
+use crate::bls::Engine;
+use crate::multiexp::DensityTracker;
+use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+const MAGIC: [u8; 8] = *b"BPWITNES";
+const VERSION: u32 = 1;
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// The assignments of a circuit together with the densities of its queries,
+/// everything the prover needs besides the constraints, which it takes from a
+/// [`CachedCircuit`](super::CachedCircuit). Proofs are created from it with
+/// [`create_proof_with_witness`](super::create_proof_with_witness).
+///
+/// The assignments are wiped when the witness is dropped.
+pub struct ProvingWitness<E: Engine> {
+    pub(super) input_assignment: Vec<E::Fr>,
+    pub(super) aux_assignment: Vec<E::Fr>,
+    pub(super) a_aux_density: DensityTracker,
+    pub(super) b_input_density: DensityTracker,
+    pub(super) b_aux_density: DensityTracker,
+}
+
+impl<E: Engine> ProvingWitness<E> {
+    /// Synthesizes the circuit, keeping only its assignments and densities.
+    pub fn new<C: Circuit<E>>(circuit: C) -> Result<Self, SynthesisError> {
+        let mut b_input_density = DensityTracker::new();
+        // The `ONE` input.
+        b_input_density.add_element();
+
+        let mut witness = ProvingWitness {
+            input_assignment: vec![E::Fr::one()],
+            aux_assignment: vec![],
+            a_aux_density: DensityTracker::new(),
+            b_input_density,
+            b_aux_density: DensityTracker::new(),
+        };
+        circuit.synthesize(&mut witness)?;
+
+        Ok(witness)
+    }
+
+    /// The number of inputs, including `ONE`.
+    pub fn num_inputs(&self) -> usize {
+        self.input_assignment.len()
+    }
+
+    pub fn num_aux(&self) -> usize {
+        self.aux_assignment.len()
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&MAGIC)?;
+        writer.write_u32::<BigEndian>(VERSION)?;
+        writer.write_u32::<BigEndian>(self.input_assignment.len() as u32)?;
+        writer.write_u32::<BigEndian>(self.aux_assignment.len() as u32)?;
+
+        for value in self.input_assignment.iter().chain(&self.aux_assignment) {
+            value.into_repr().write_be(&mut writer)?;
+        }
+        writer.write_all(&self.a_aux_density.to_bytes())?;
+        writer.write_all(&self.b_input_density.to_bytes())?;
+        writer.write_all(&self.b_aux_density.to_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid("not a witness"));
+        }
+        let version = reader.read_u32::<BigEndian>()?;
+        if version != VERSION {
+            return Err(invalid(format!("unsupported witness version {}", version)));
+        }
+        let num_inputs = reader.read_u32::<BigEndian>()? as usize;
+        let num_aux = reader.read_u32::<BigEndian>()? as usize;
+        if num_inputs == 0 {
+            return Err(invalid("the witness has no ONE input"));
+        }
+
+        let mut witness = ProvingWitness {
+            input_assignment: read_assignment(&mut reader, num_inputs)?,
+            aux_assignment: vec![],
+            a_aux_density: DensityTracker::new(),
+            b_input_density: DensityTracker::new(),
+            b_aux_density: DensityTracker::new(),
+        };
+        if witness.input_assignment[0] != E::Fr::one() {
+            return Err(invalid("the ONE input isn't one"));
+        }
+        witness.aux_assignment = read_assignment(&mut reader, num_aux)?;
+        witness.a_aux_density = read_density(&mut reader, num_aux)?;
+        witness.b_input_density = read_density(&mut reader, num_inputs)?;
+        witness.b_aux_density = read_density(&mut reader, num_aux)?;
+
+        Ok(witness)
+    }
+}
+
+fn read_assignment<F: PrimeField, R: Read>(mut reader: R, len: usize) -> io::Result<Vec<F>> {
+    // The length isn't trusted for preallocating, the reader may end early.
+    let mut assignment = Vec::with_capacity(cmp::min(len, 1 << 16));
+    for _ in 0..len {
+        let mut repr = F::Repr::default();
+        repr.read_be(&mut reader)?;
+        assignment.push(F::from_repr(repr).map_err(invalid)?);
+    }
+
+    Ok(assignment)
+}
+
+fn read_density<R: Read>(mut reader: R, len: usize) -> io::Result<DensityTracker> {
+    let mut bytes = vec![];
+    reader
+        .by_ref()
+        .take(((len + 7) / 8) as u64)
+        .read_to_end(&mut bytes)?;
+    if bytes.len() * 8 < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(DensityTracker::from_bytes(&bytes, len))
+}
+
+impl<E: Engine> Zeroize for ProvingWitness<E> {
+    fn zeroize(&mut self) {
+        wipe(&mut self.input_assignment, E::Fr::zero());
+        wipe(&mut self.aux_assignment, E::Fr::zero());
+    }
+}
+
+impl<E: Engine> Drop for ProvingWitness<E> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for ProvingWitness<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.aux_assignment.push(f()?);
+        self.a_aux_density.add_element();
+        self.b_aux_density.add_element();
+
+        Ok(Variable(Index::Aux(self.aux_assignment.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.input_assignment.push(f()?);
+        self.b_input_density.add_element();
+
+        Ok(Variable(Index::Input(self.input_assignment.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, _: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+
+        // The same densities as `ProvingAssignment::enforce` tracks.
+        for (var, _) in a.iter() {
+            if let Variable(Index::Aux(i)) = var {
+                self.a_aux_density.inc(*i);
+            }
+        }
+        for (var, _) in b.iter() {
+            match var {
+                Variable(Index::Input(i)) => self.b_input_density.inc(*i),
+                Variable(Index::Aux(i)) => self.b_aux_density.inc(*i),
+            }
+        }
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
@@ -0,0 +1,36 @@
+//! Import and export of parameter files of upstream [zcash bellman], e.g. the
+//! Sapling parameters. Upstream encodes [`Parameters`] like this crate's
+//! legacy format and reduces constraints to a QAP the same way, so parameters
+//! from its ceremonies prove and verify with this crate unchanged.
+//!
+//! [zcash bellman]: https://github.com/zkcrypto/bellman
+
+use std::io::{self, Read, Write};
+
+use crate::bls::Bls12;
+
+use super::{Parameters, VerifyingKey};
+
+/// Reads parameters written by `Parameters::write` of zcash bellman. With
+/// `checked`, the points are checked to be in the subgroup, which takes a few
+/// minutes for the Sapling parameters.
+pub fn read_parameters<R: Read>(reader: R, checked: bool) -> io::Result<Parameters<Bls12>> {
+    Parameters::read_legacy(reader, checked)
+}
+
+/// Writes parameters in the format of `Parameters::write` of zcash bellman.
+pub fn write_parameters<W: Write>(params: &Parameters<Bls12>, writer: W) -> io::Result<()> {
+    params.write_legacy(writer)
+}
+
+/// Reads a verifying key written by `VerifyingKey::write` of zcash bellman,
+/// whose encoding is the same as this crate's.
+pub fn read_verifying_key<R: Read>(reader: R) -> io::Result<VerifyingKey<Bls12>> {
+    VerifyingKey::read(reader)
+}
+
+/// Writes a verifying key in the format of `VerifyingKey::write` of zcash
+/// bellman.
+pub fn write_verifying_key<W: Write>(vk: &VerifyingKey<Bls12>, writer: W) -> io::Result<()> {
+    vk.write(writer)
+}
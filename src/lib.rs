@@ -22,7 +22,7 @@
 //!     },
 //!     groth16, Circuit, ConstraintSystem, SynthesisError,
 //! };
-//! use paired::{bls12_381::Bls12, Engine};
+//! use bellperson::bls::{Bls12, Engine};
 //! use rand::rngs::OsRng;
 //! use sha2::{Digest, Sha256};
 //!
@@ -139,24 +139,60 @@
 #[macro_use]
 extern crate hex_literal;
 
+// Enclaves have no file system, no GPU and no threads to spare for a pool, see
+// the SGX section of the README.
+#[cfg(all(
+    target_env = "sgx",
+    any(
+        feature = "multicore",
+        feature = "rayon",
+        feature = "numa",
+        feature = "gpu",
+        feature = "disk",
+        feature = "metrics"
+    )
+))]
+compile_error!(
+    "SGX builds can't have the `multicore`, `rayon`, `numa`, `gpu`, `disk` or `metrics` features"
+);
+
+pub mod bls;
+pub mod circuits;
+pub mod commitments;
 pub mod domain;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod gadgets;
-mod gpu;
+pub mod gpu;
 #[cfg(feature = "groth16")]
 pub mod groth16;
+#[cfg(feature = "marlin")]
+pub mod marlin;
+pub mod metrics;
 pub mod multicore;
 pub mod multiexp;
+#[cfg(feature = "plonk")]
+pub mod plonk;
+pub mod poly;
+#[cfg(feature = "sonic")]
+pub mod sonic;
+pub mod srs;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod transcript;
 
 #[cfg(feature = "gpu")]
 pub use gpu::GPU_NVIDIA_DEVICES;
 
 use ff::{Field, ScalarEngine};
+use rand_core::{CryptoRng, RngCore};
 
+use std::collections::hash_map::{Entry, HashMap};
 use std::error::Error;
 use std::fmt;
 use std::io;
 use std::marker::PhantomData;
-use std::ops::{Add, Sub};
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
 /// Computations are expressed in terms of arithmetic circuits, in particular
 /// rank-1 quadratic constraint systems. The `Circuit` trait represents a
@@ -167,6 +203,61 @@ pub trait Circuit<E: ScalarEngine> {
     fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError>;
 }
 
+/// A circuit that consists of independent segments, which can be synthesized
+/// in parallel. Segments must not refer to variables of other segments, except
+/// for the `ONE` input. Synthesizing all segments one after another, as done by
+/// [`SequentialCircuit`], leads to the same constraint system.
+pub trait ParallelCircuit<E: ScalarEngine> {
+    type Segment: Circuit<E> + Send;
+
+    /// Split the circuit into its segments, in the order they are synthesized.
+    fn segments(self) -> Vec<Self::Segment>;
+}
+
+/// Synthesizes the segments of a [`ParallelCircuit`] one after another, e.g.
+/// for generating parameters.
+pub struct SequentialCircuit<C>(pub C);
+
+impl<E: ScalarEngine, C: ParallelCircuit<E>> Circuit<E> for SequentialCircuit<C> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        for (i, segment) in self.0.segments().into_iter().enumerate() {
+            segment.synthesize(&mut cs.namespace(|| format!("segment {}", i)))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Marks any RNG as cryptographically secure, so that it's accepted for the
+/// randomness of proofs. Proofs only hide the witness as long as that
+/// randomness is unpredictable: this is meant for deterministic tests with a
+/// seeded RNG and never for proving secret witnesses, so it requires the
+/// `testing` feature.
+#[cfg(any(test, feature = "testing"))]
+pub struct InsecureRng<R>(pub R);
+
+#[cfg(any(test, feature = "testing"))]
+impl<R: RngCore> RngCore for InsecureRng<R> {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl<R: RngCore> CryptoRng for InsecureRng<R> {}
+
 /// Represents a variable in our constraint system.
 #[derive(Copy, Clone, Debug)]
 pub struct Variable(Index);
@@ -187,7 +278,7 @@ impl Variable {
 
 /// Represents the index of either an input variable or
 /// auxiliary variable.
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum Index {
     Input(usize),
     Aux(usize),
@@ -204,10 +295,52 @@ impl<E: ScalarEngine> AsRef<[(Variable, E::Fr)]> for LinearCombination<E> {
     }
 }
 
+impl<E: ScalarEngine> fmt::Debug for LinearCombination<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.iter()).finish()
+    }
+}
+
 impl<E: ScalarEngine> LinearCombination<E> {
     pub fn zero() -> LinearCombination<E> {
         LinearCombination(vec![])
     }
+
+    /// Returns an iterator over the terms, which may contain the same variable
+    /// more than once.
+    pub fn iter(&self) -> std::slice::Iter<(Variable, E::Fr)> {
+        self.0.iter()
+    }
+
+    /// The number of terms.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Merges the terms of the same variable and drops the terms with a zero
+    /// coefficient, keeping the order of the first occurrences. Adding the
+    /// same variable repeatedly otherwise leads to a term for every addition,
+    /// which have to be evaluated one by one.
+    pub fn simplify(self) -> LinearCombination<E> {
+        let mut terms: Vec<(Variable, E::Fr)> = Vec::with_capacity(self.0.len());
+        let mut positions = HashMap::new();
+        for (var, coeff) in self.0 {
+            match positions.entry(var.0) {
+                Entry::Occupied(entry) => terms[*entry.get()].1.add_assign(&coeff),
+                Entry::Vacant(entry) => {
+                    entry.insert(terms.len());
+                    terms.push((var, coeff));
+                }
+            }
+        }
+        terms.retain(|(_, coeff)| !coeff.is_zero());
+
+        LinearCombination(terms)
+    }
 }
 
 impl<E: ScalarEngine> Add<(E::Fr, Variable)> for LinearCombination<E> {
@@ -299,6 +432,111 @@ impl<'a, E: ScalarEngine> Sub<(E::Fr, &'a LinearCombination<E>)> for LinearCombi
     }
 }
 
+impl<E: ScalarEngine> IntoIterator for LinearCombination<E> {
+    type Item = (Variable, E::Fr);
+    type IntoIter = std::vec::IntoIter<(Variable, E::Fr)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, E: ScalarEngine> IntoIterator for &'a LinearCombination<E> {
+    type Item = &'a (Variable, E::Fr);
+    type IntoIter = std::slice::Iter<'a, (Variable, E::Fr)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<E: ScalarEngine> AddAssign<(E::Fr, Variable)> for LinearCombination<E> {
+    fn add_assign(&mut self, (coeff, var): (E::Fr, Variable)) {
+        self.0.push((var, coeff));
+    }
+}
+
+impl<E: ScalarEngine> SubAssign<(E::Fr, Variable)> for LinearCombination<E> {
+    fn sub_assign(&mut self, (mut coeff, var): (E::Fr, Variable)) {
+        coeff.negate();
+        self.0.push((var, coeff));
+    }
+}
+
+impl<E: ScalarEngine> AddAssign<Variable> for LinearCombination<E> {
+    fn add_assign(&mut self, other: Variable) {
+        *self += (E::Fr::one(), other);
+    }
+}
+
+impl<E: ScalarEngine> SubAssign<Variable> for LinearCombination<E> {
+    fn sub_assign(&mut self, other: Variable) {
+        *self -= (E::Fr::one(), other);
+    }
+}
+
+impl<'a, E: ScalarEngine> AddAssign<&'a LinearCombination<E>> for LinearCombination<E> {
+    fn add_assign(&mut self, other: &'a LinearCombination<E>) {
+        self.0.extend_from_slice(&other.0);
+    }
+}
+
+impl<'a, E: ScalarEngine> SubAssign<&'a LinearCombination<E>> for LinearCombination<E> {
+    fn sub_assign(&mut self, other: &'a LinearCombination<E>) {
+        for &(var, coeff) in &other.0 {
+            *self -= (coeff, var);
+        }
+    }
+}
+
+impl<'a, E: ScalarEngine> AddAssign<(E::Fr, &'a LinearCombination<E>)> for LinearCombination<E> {
+    fn add_assign(&mut self, (coeff, other): (E::Fr, &'a LinearCombination<E>)) {
+        for &(var, mut tmp) in &other.0 {
+            tmp.mul_assign(&coeff);
+            *self += (tmp, var);
+        }
+    }
+}
+
+impl<'a, E: ScalarEngine> SubAssign<(E::Fr, &'a LinearCombination<E>)> for LinearCombination<E> {
+    fn sub_assign(&mut self, (coeff, other): (E::Fr, &'a LinearCombination<E>)) {
+        for &(var, mut tmp) in &other.0 {
+            tmp.mul_assign(&coeff);
+            *self -= (tmp, var);
+        }
+    }
+}
+
+impl<E: ScalarEngine> MulAssign<E::Fr> for LinearCombination<E> {
+    fn mul_assign(&mut self, coeff: E::Fr) {
+        for (_, tmp) in &mut self.0 {
+            tmp.mul_assign(&coeff);
+        }
+    }
+}
+
+impl<E: ScalarEngine> Mul<E::Fr> for LinearCombination<E> {
+    type Output = LinearCombination<E>;
+
+    fn mul(mut self, coeff: E::Fr) -> LinearCombination<E> {
+        self *= coeff;
+
+        self
+    }
+}
+
+impl<E: ScalarEngine> Neg for LinearCombination<E> {
+    type Output = LinearCombination<E>;
+
+    fn neg(mut self) -> LinearCombination<E> {
+        for (_, coeff) in &mut self.0 {
+            coeff.negate();
+        }
+
+        self
+    }
+}
+
 /// This is an error that could occur during circuit synthesis contexts,
 /// such as CRS generation, proving or verification.
 #[derive(Debug)]
@@ -319,8 +557,51 @@ pub enum SynthesisError {
     MalformedVerifyingKey,
     /// During CRS generation, we observed an unconstrained auxiliary variable
     UnconstrainedVariable,
-    /// During GPU multiexp/fft, some GPU related error happened
+    /// During GPU multiexp/fft, some GPU related error happened. Its kind,
+    /// e.g. [`GPUError::is_unavailable`](gpu::GPUError::is_unavailable), tells
+    /// whether to retry or to fall back to the CPU.
     GPUError(gpu::GPUError),
+    /// During synthesis, computing the assignment of the variable with the
+    /// given namespace path failed. Only constraint systems that keep track of
+    /// namespaces, like the ones used for testing, report the path.
+    AtPath(String, Box<SynthesisError>),
+    /// During proof generation, the proof was cancelled.
+    Cancelled,
+    /// During proof generation, the deadline of the proof passed.
+    Timeout,
+    /// During proof generation, the prover was estimated to need more than
+    /// the given memory budget, in bytes.
+    MemoryBudgetExceeded(usize),
+    /// The verifying key doesn't have the expected fingerprint, given first
+    /// and followed by the actual one.
+    FingerprintMismatch([u8; 32], [u8; 32]),
+}
+
+impl SynthesisError {
+    /// Attaches the namespace path of the variable whose assignment failed,
+    /// unless the error already has one.
+    pub fn at_path(self, path: String) -> SynthesisError {
+        match self {
+            SynthesisError::AtPath(..) => self,
+            e => SynthesisError::AtPath(path, Box::new(e)),
+        }
+    }
+
+    /// Returns the error without the namespace path.
+    pub fn root_cause(&self) -> &SynthesisError {
+        match *self {
+            SynthesisError::AtPath(_, ref e) => e.root_cause(),
+            ref e => e,
+        }
+    }
+
+    /// Returns the GPU error, also of an error with a namespace path.
+    pub fn gpu_error(&self) -> Option<&gpu::GPUError> {
+        match *self.root_cause() {
+            SynthesisError::GPUError(ref e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 impl From<gpu::GPUError> for SynthesisError {
@@ -349,21 +630,61 @@ impl Error for SynthesisError {
             SynthesisError::MalformedVerifyingKey => "malformed verifying key",
             SynthesisError::UnconstrainedVariable => "auxiliary variable was unconstrained",
             SynthesisError::GPUError(_) => "encountered a GPU error",
+            SynthesisError::AtPath(_, ref e) => e.description(),
+            SynthesisError::Cancelled => "the proof was cancelled",
+            SynthesisError::Timeout => "the deadline of the proof passed",
+            SynthesisError::MemoryBudgetExceeded(_) => {
+                "the proof needs more memory than its budget"
+            }
+            SynthesisError::FingerprintMismatch(..) => {
+                "the verifying key doesn't have the expected fingerprint"
+            }
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            SynthesisError::IoError(ref e) => Some(e),
+            SynthesisError::AtPath(_, ref e) => Some(&**e),
+            SynthesisError::GPUError(ref e) => Some(e),
+            _ => None,
         }
     }
 }
 
 impl fmt::Display for SynthesisError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        if let SynthesisError::IoError(ref e) = *self {
-            write!(f, "I/O error: ")?;
-            e.fmt(f)
-        } else {
-            write!(f, "{}", self.description())
+        match *self {
+            SynthesisError::IoError(ref e) => {
+                write!(f, "I/O error: ")?;
+                e.fmt(f)
+            }
+            SynthesisError::AtPath(ref path, ref e) => write!(f, "{} at `{}`", e, path),
+            SynthesisError::GPUError(ref e) => write!(f, "{}: {}", self.description(), e),
+            SynthesisError::MemoryBudgetExceeded(budget) => write!(
+                f,
+                "the proof needs more memory than its budget of {} bytes",
+                budget
+            ),
+            SynthesisError::FingerprintMismatch(ref expected, ref actual) => {
+                write!(f, "expected a verifying key with the fingerprint ")?;
+                write_hex(f, expected)?;
+                write!(f, ", found one with ")?;
+                write_hex(f, actual)
+            }
+            _ => write!(f, "{}", self.description()),
         }
     }
 }
 
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    for byte in bytes {
+        write!(f, "{:02x}", byte)?;
+    }
+
+    Ok(())
+}
+
 /// Represents a constraint system which can have new variables
 /// allocated and constrains between them formed.
 pub trait ConstraintSystem<E: ScalarEngine>: Sized {
@@ -404,6 +725,61 @@ pub trait ConstraintSystem<E: ScalarEngine>: Sized {
         LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
         LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>;
 
+    /// Allocate two private variables `a` and `b` together with their product `c`, and
+    /// enforce `a` * `b` = `c`. The provided function is used to determine the assignments
+    /// of `a` and `b`. Backends may override this to treat multiplication gates specially.
+    fn multiply<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F,
+    ) -> Result<(Variable, Variable, Variable), SynthesisError>
+    where
+        F: FnOnce() -> Result<(E::Fr, E::Fr), SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let mut cs = self.namespace(annotation);
+
+        let mut values = None;
+        let a = cs.alloc(
+            || "a",
+            || {
+                let (a, b) = f()?;
+                values = Some((a, b));
+                Ok(a)
+            },
+        )?;
+        let b = cs.alloc(
+            || "b",
+            || {
+                values
+                    .map(|(_, b)| b)
+                    .ok_or(SynthesisError::AssignmentMissing)
+            },
+        )?;
+        let c = cs.alloc(
+            || "c",
+            || {
+                let (mut a, b) = values.ok_or(SynthesisError::AssignmentMissing)?;
+                a.mul_assign(&b);
+                Ok(a)
+            },
+        )?;
+        cs.enforce(|| "a*b=c", |lc| lc + a, |lc| lc + b, |lc| lc + c);
+
+        Ok((a, b, c))
+    }
+
+    /// Enforce that the linear combination is zero, with the constraint `lc` * `ONE` = `0`.
+    fn enforce_zero<A, AR, L>(&mut self, annotation: A, lc: L)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        L: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        self.enforce(annotation, lc, |lc| lc + Self::one(), |lc| lc);
+    }
+
     /// Create a new (sub)namespace and enter into it. Not intended
     /// for downstream use; use `namespace` instead.
     fn push_namespace<NR, N>(&mut self, name_fn: N)
@@ -471,6 +847,28 @@ impl<'cs, E: ScalarEngine, CS: ConstraintSystem<E>> ConstraintSystem<E> for Name
         self.0.enforce(annotation, a, b, c)
     }
 
+    fn multiply<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F,
+    ) -> Result<(Variable, Variable, Variable), SynthesisError>
+    where
+        F: FnOnce() -> Result<(E::Fr, E::Fr), SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.0.multiply(annotation, f)
+    }
+
+    fn enforce_zero<A, AR, L>(&mut self, annotation: A, lc: L)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        L: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        self.0.enforce_zero(annotation, lc)
+    }
+
     // Downstream users who use `namespace` will never interact with these
     // functions and they will never be invoked because the namespace is
     // never a root constraint system.
@@ -536,6 +934,28 @@ impl<'cs, E: ScalarEngine, CS: ConstraintSystem<E>> ConstraintSystem<E> for &'cs
         (**self).enforce(annotation, a, b, c)
     }
 
+    fn multiply<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F,
+    ) -> Result<(Variable, Variable, Variable), SynthesisError>
+    where
+        F: FnOnce() -> Result<(E::Fr, E::Fr), SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        (**self).multiply(annotation, f)
+    }
+
+    fn enforce_zero<A, AR, L>(&mut self, annotation: A, lc: L)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        L: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        (**self).enforce_zero(annotation, lc)
+    }
+
     fn push_namespace<NR, N>(&mut self, name_fn: N)
     where
         NR: Into<String>,
@@ -552,3 +972,111 @@ impl<'cs, E: ScalarEngine, CS: ConstraintSystem<E>> ConstraintSystem<E> for &'cs
         (**self).get_root()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::bls::{Bls12, Fr};
+    use ff::PrimeField;
+
+    use super::*;
+
+    #[test]
+    fn test_simplify() {
+        let a = Variable::new_unchecked(Index::Aux(0));
+        let b = Variable::new_unchecked(Index::Input(0));
+        let c = Variable::new_unchecked(Index::Aux(1));
+        let two = Fr::from_str("2").unwrap();
+
+        let lc = LinearCombination::<Bls12>::zero() + a + b + a + (two, c) - b + (Fr::zero(), b);
+        let terms = lc
+            .simplify()
+            .as_ref()
+            .iter()
+            .map(|(var, coeff)| (var.get_unchecked(), *coeff))
+            .collect::<Vec<_>>();
+        assert_eq!(terms, vec![(Index::Aux(0), two), (Index::Aux(1), two)]);
+
+        assert!(LinearCombination::<Bls12>::zero()
+            .simplify()
+            .as_ref()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_operators() {
+        let a = Variable::new_unchecked(Index::Aux(0));
+        let b = Variable::new_unchecked(Index::Input(0));
+        let two = Fr::from_str("2").unwrap();
+        let three = Fr::from_str("3").unwrap();
+        let terms = |lc: &LinearCombination<Bls12>| {
+            lc.iter()
+                .map(|(var, coeff)| (var.get_unchecked(), *coeff))
+                .collect::<Vec<_>>()
+        };
+
+        let mut lc = LinearCombination::<Bls12>::zero();
+        lc += a;
+        lc += (two, b);
+        assert_eq!(lc.len(), 2);
+        assert_eq!(
+            terms(&lc),
+            vec![(Index::Aux(0), Fr::one()), (Index::Input(0), two)]
+        );
+
+        // 3 * (a + 2b) - (a + 2b) = 2a + 4b
+        let mut scaled = lc.clone() * three;
+        scaled -= &lc;
+        let mut four = two;
+        four.double();
+        assert_eq!(
+            terms(&scaled.simplify()),
+            vec![(Index::Aux(0), two), (Index::Input(0), four)]
+        );
+
+        // (a + 2b) + 2 * (a + 2b) - (3a + 6b) = 0
+        let mut zero = lc.clone();
+        zero += (two, &lc);
+        zero -= &(lc.clone() * three);
+        assert!(zero.simplify().is_empty());
+
+        // -(a + 2b) + a = -2b
+        let mut minus_two = two;
+        minus_two.negate();
+        let mut neg = -lc;
+        neg += a;
+        assert_eq!(
+            neg.simplify()
+                .into_iter()
+                .map(|(var, coeff)| (var.get_unchecked(), coeff))
+                .collect::<Vec<_>>(),
+            vec![(Index::Input(0), minus_two)]
+        );
+    }
+
+    #[test]
+    fn test_gpu_errors() {
+        use std::sync::Arc;
+
+        // GPU errors keep their variant, their kind tells them apart.
+        match SynthesisError::from(gpu::GPUError::Disabled) {
+            SynthesisError::GPUError(ref e) => assert!(e.is_unavailable()),
+            ref e => panic!("unexpected error {}", e),
+        }
+        match SynthesisError::from(gpu::GPUError::OutOfMemory("CL_OUT_OF_RESOURCES".into())) {
+            SynthesisError::GPUError(ref e) => assert!(e.is_out_of_memory() && !e.is_busy()),
+            ref e => panic!("unexpected error {}", e),
+        }
+
+        // The error of the lock is the source of the GPU error.
+        let lock = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        let busy = SynthesisError::from(gpu::GPUError::Lock(Arc::new(lock)));
+        assert!(busy.gpu_error().unwrap().is_busy());
+        let gpu = busy.source().unwrap();
+        assert_eq!(gpu.to_string(), "Cannot take the GPU lock: denied");
+        assert_eq!(gpu.source().unwrap().to_string(), "denied");
+
+        let at_path = busy.at_path("a/b".to_string());
+        assert!(at_path.gpu_error().unwrap().source().is_some());
+        assert!(SynthesisError::Cancelled.gpu_error().is_none());
+    }
+}
@@ -0,0 +1,107 @@
+//! Collection of the rank-1 constraints as sparse matrices.
+
+use ff::{Field, ScalarEngine};
+
+use crate::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// Synthesizes a circuit into the matrices `A`, `B` and `C` of the
+/// constraint system `Az ∘ Bz = Cz`, where `z` is the assignment of the
+/// inputs, starting with `ONE`, and the auxiliary variables.
+pub(crate) struct Assembly<E: ScalarEngine> {
+    /// The rows of the matrices, the terms of the linear combinations of
+    /// every constraint.
+    pub matrices: [Vec<Vec<(Index, E::Fr)>>; 3],
+    pub num_inputs: usize,
+    pub num_aux: usize,
+    /// The assignment of the variables, only tracked when proving.
+    pub input_values: Vec<E::Fr>,
+    pub aux_values: Vec<E::Fr>,
+    proving: bool,
+}
+
+impl<E: ScalarEngine> Assembly<E> {
+    pub fn new(proving: bool) -> Assembly<E> {
+        Assembly {
+            matrices: [vec![], vec![], vec![]],
+            num_inputs: 1,
+            num_aux: 0,
+            input_values: vec![E::Fr::one()],
+            aux_values: vec![],
+            proving,
+        }
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.matrices[0].len()
+    }
+}
+
+impl<E: ScalarEngine> ConstraintSystem<E> for Assembly<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        if self.proving {
+            self.aux_values.push(f()?);
+        }
+        self.num_aux += 1;
+
+        Ok(Variable(Index::Aux(self.num_aux - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        if self.proving {
+            self.input_values.push(f()?);
+        }
+        self.num_inputs += 1;
+
+        Ok(Variable(Index::Input(self.num_inputs - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        for (matrix, lc) in self.matrices.iter_mut().zip(vec![
+            a(LinearCombination::zero()),
+            b(LinearCombination::zero()),
+            c(LinearCombination::zero()),
+        ]) {
+            matrix.push(
+                lc.simplify()
+                    .into_iter()
+                    .map(|(var, coeff)| (var.get_unchecked(), coeff))
+                    .collect(),
+            );
+        }
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
@@ -0,0 +1,144 @@
+use crate::bls::Engine;
+use ff::{Field, PrimeField};
+use groupy::CurveAffine;
+
+use super::assembly::Assembly;
+use super::{input_domain_size, position, ProvingKey, UniversalSrs, VerifyingKey};
+
+use crate::multicore::Worker;
+use crate::poly::{commit, coset_evaluations, from_u64, interpolate, root_of_unity};
+use crate::{Circuit, SynthesisError};
+
+/// Derives the parameters of a circuit from a universal SRS, the polynomials
+/// of its matrices, which are committed to in the verifying key.
+///
+/// The SRS needs to support polynomials of degree `max(3|H| - 1, 6|K| - 7)`,
+/// where `|H|` is the number of constraints or variables, whichever is
+/// larger, and `|K|` the number of nonzero entries of the densest matrix,
+/// both rounded up to a power of two.
+pub fn generate_parameters<E, C>(
+    circuit: C,
+    srs: &UniversalSrs<E>,
+) -> Result<ProvingKey<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    let mut assembly = Assembly::<E>::new(false);
+    circuit.synthesize(&mut assembly)?;
+
+    let x_size = input_domain_size(assembly.num_inputs - 1);
+    let h_size = assembly
+        .num_constraints()
+        .max(x_size + assembly.num_aux)
+        .max(2)
+        .next_power_of_two();
+
+    // The nonzero entries, with the variables at their positions in z.
+    let mut matrices = [vec![], vec![], vec![]];
+    for (entries, rows) in matrices.iter_mut().zip(assembly.matrices.iter()) {
+        for (row, terms) in rows.iter().enumerate() {
+            for &(index, coeff) in terms.iter() {
+                entries.push((row, position(index, x_size, h_size), coeff));
+            }
+        }
+    }
+    let k_size = matrices
+        .iter()
+        .map(|entries| entries.len())
+        .max()
+        .unwrap_or(0)
+        .max(2)
+        .next_power_of_two();
+
+    // The quotient of the third sumcheck is computed over a domain of size
+    // 8|K|, the one of the first over a domain of size 4|H|.
+    let log_h = h_size.trailing_zeros();
+    let log_k = k_size.trailing_zeros();
+    if log_k + 3 > E::Fr::S || log_h + 2 > E::Fr::S {
+        return Err(SynthesisError::PolynomialDegreeTooLarge);
+    }
+    // The mask of the first sumcheck has degree 3|H| - 1, the quotient of
+    // the third 6|K| - 7.
+    let max_degree = (3 * h_size - 1).max(6 * k_size - 7);
+    let g1_powers = srs.truncate(max_degree)?.g1_powers;
+
+    let worker = Worker::new();
+
+    // M(X, Y) is the sum of u_H(X, row(κ))·u_H(Y, col(κ))·val(κ) over K,
+    // where u_H(a, a) = |H|/a for the elements of H. Unused entries have a
+    // value of zero.
+    let omega = root_of_unity::<E::Fr>(log_h);
+    let mut omega_powers = Vec::with_capacity(h_size);
+    let mut omega_i = E::Fr::one();
+    for _ in 0..h_size {
+        omega_powers.push(omega_i);
+        omega_i.mul_assign(&omega);
+    }
+    let mut h_squared_inv = from_u64::<E::Fr>(h_size as u64);
+    h_squared_inv.square();
+    let h_squared_inv = h_squared_inv
+        .inverse()
+        .ok_or(SynthesisError::UnexpectedIdentity)?;
+
+    let mut index_polys = [
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+    ];
+    let mut index_commitments = [E::G1Affine::zero(); 9];
+    let mut index_coset = [
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+    ];
+    for (m, entries) in matrices.iter().enumerate() {
+        let mut rows = vec![E::Fr::one(); k_size];
+        let mut cols = vec![E::Fr::one(); k_size];
+        let mut vals = vec![E::Fr::zero(); k_size];
+        for (i, &(row, col, coeff)) in entries.iter().enumerate() {
+            rows[i] = omega_powers[row];
+            cols[i] = omega_powers[col];
+            vals[i] = coeff;
+            vals[i].mul_assign(&rows[i]);
+            vals[i].mul_assign(&cols[i]);
+            vals[i].mul_assign(&h_squared_inv);
+        }
+
+        for (j, evaluations) in vec![rows, cols, vals].into_iter().enumerate() {
+            let poly = interpolate(&worker, evaluations, &mut None)?;
+            index_commitments[3 * m + j] = commit(&worker, &g1_powers, &poly, &mut None)?;
+            index_coset[3 * m + j] = coset_evaluations(&worker, &poly, 8 * k_size, &mut None)?;
+            index_polys[3 * m + j] = poly;
+        }
+    }
+
+    Ok(ProvingKey {
+        vk: VerifyingKey {
+            h_size,
+            k_size,
+            num_inputs: assembly.num_inputs - 1,
+            max_degree,
+            index_commitments,
+            g1: srs.g1_powers[0],
+            g2: srs.g2,
+            g2_x: srs.g2_x,
+        },
+        g1_powers,
+        matrices,
+        index_polys,
+        index_coset,
+    })
+}
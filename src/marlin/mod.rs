@@ -0,0 +1,441 @@
+//! The [Marlin] proving system.
+//!
+//! Like [PLONK], Marlin derives the parameters of a circuit from a
+//! [`UniversalSrs`] instead of a circuit-specific setup. It proves rank-1
+//! constraint systems natively, so circuits written against the
+//! [`ConstraintSystem`] of [Groth16] are proven as they are, without
+//! translating every constraint into gates.
+//!
+//! The constraint system `Az ∘ Bz = Cz` is padded to square matrices over a
+//! multiplicative subgroup `H`. The prover shows that the polynomials of
+//! `Az`, `Bz` and `Az ∘ Bz` are consistent with `z` in a sumcheck over `H`,
+//! whose claimed sum is reduced to the evaluation of the matrices at random
+//! points by a second sumcheck over `H` and a third one over a subgroup `K`,
+//! on which the nonzero entries of the matrices are interpolated.
+//!
+//! [Marlin]: https://eprint.iacr.org/2019/1047
+//! [PLONK]: crate::plonk
+//! [Groth16]: crate::groth16
+//! [`ConstraintSystem`]: crate::ConstraintSystem
+
+use crate::bls::Engine;
+use ff::{Field, PrimeField, PrimeFieldRepr};
+use groupy::{CurveAffine, EncodedPoint};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use crate::Index;
+
+mod assembly;
+mod generator;
+mod prover;
+mod transcript;
+mod verifier;
+
+pub use self::generator::*;
+pub use self::prover::*;
+pub use self::verifier::*;
+pub use crate::srs::UniversalSrs;
+
+/// The size of the subgroup `X` of `H`, whose elements are the positions of
+/// the inputs in `z`, including `ONE`.
+pub(crate) fn input_domain_size(num_inputs: usize) -> usize {
+    (num_inputs + 1).next_power_of_two()
+}
+
+/// The position of a variable in `z`, which is indexed by the elements of
+/// `H`. The inputs are at the elements of `X`, the auxiliary variables at
+/// the remaining ones in order.
+pub(crate) fn position(index: Index, x_size: usize, h_size: usize) -> usize {
+    let stride = h_size / x_size;
+    match index {
+        Index::Input(i) => i * stride,
+        Index::Aux(i) => i / (stride - 1) * stride + i % (stride - 1) + 1,
+    }
+}
+
+fn read_point<P: EncodedPoint, R: Read>(reader: &mut R) -> io::Result<P::Affine> {
+    let mut repr = P::empty();
+    reader.read_exact(repr.as_mut())?;
+
+    repr.into_affine()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_scalar<F: PrimeField, W: Write>(writer: &mut W, scalar: &F) -> io::Result<()> {
+    scalar.into_repr().write_be(writer)
+}
+
+fn read_scalar<F: PrimeField, R: Read>(reader: &mut R) -> io::Result<F> {
+    let mut repr = F::Repr::default();
+    repr.read_be(reader)?;
+
+    F::from_repr(repr).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[derive(Clone)]
+pub struct Proof<E: Engine> {
+    // Commitments to the witness polynomial, to the polynomials of Az and Bz
+    // and to the mask of the first sumcheck.
+    pub w: E::G1Affine,
+    pub z_a: E::G1Affine,
+    pub z_b: E::G1Affine,
+    pub mask: E::G1Affine,
+
+    // Commitments to the polynomials of the three sumchecks. The shifted
+    // commitments bound the degrees of the g polynomials.
+    pub g1: E::G1Affine,
+    pub g1_shifted: E::G1Affine,
+    pub h1: E::G1Affine,
+    pub g2: E::G1Affine,
+    pub g2_shifted: E::G1Affine,
+    pub h2: E::G1Affine,
+    pub g3: E::G1Affine,
+    pub g3_shifted: E::G1Affine,
+    pub h3: E::G1Affine,
+
+    // Opening proofs at the challenges beta1, beta2 and beta3.
+    pub opening_beta1: E::G1Affine,
+    pub opening_beta2: E::G1Affine,
+    pub opening_beta3: E::G1Affine,
+
+    // The claimed sums of the second and third sumcheck.
+    pub sigma2: E::Fr,
+    pub sigma3: E::Fr,
+
+    // Evaluations at beta1.
+    pub w_eval: E::Fr,
+    pub z_a_eval: E::Fr,
+    pub z_b_eval: E::Fr,
+    pub mask_eval: E::Fr,
+    pub g1_eval: E::Fr,
+    pub h1_eval: E::Fr,
+
+    // Evaluations at beta2.
+    pub g2_eval: E::Fr,
+    pub h2_eval: E::Fr,
+
+    // Evaluations at beta3, including those of the row, column and value
+    // polynomials of A, B and C.
+    pub g3_eval: E::Fr,
+    pub h3_eval: E::Fr,
+    pub index_evals: [E::Fr; 9],
+}
+
+impl<E: Engine> PartialEq for Proof<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.commitments() == other.commitments() && self.evaluations() == other.evaluations()
+    }
+}
+
+impl<E: Engine> Proof<E> {
+    fn commitments(&self) -> [E::G1Affine; 16] {
+        [
+            self.w,
+            self.z_a,
+            self.z_b,
+            self.mask,
+            self.g1,
+            self.g1_shifted,
+            self.h1,
+            self.g2,
+            self.g2_shifted,
+            self.h2,
+            self.g3,
+            self.g3_shifted,
+            self.h3,
+            self.opening_beta1,
+            self.opening_beta2,
+            self.opening_beta3,
+        ]
+    }
+
+    fn evaluations(&self) -> Vec<E::Fr> {
+        let mut evaluations = vec![
+            self.sigma2,
+            self.sigma3,
+            self.w_eval,
+            self.z_a_eval,
+            self.z_b_eval,
+            self.mask_eval,
+            self.g1_eval,
+            self.h1_eval,
+            self.g2_eval,
+            self.h2_eval,
+            self.g3_eval,
+            self.h3_eval,
+        ];
+        evaluations.extend_from_slice(&self.index_evals);
+
+        evaluations
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for commitment in self.commitments().iter() {
+            writer.write_all(commitment.into_compressed().as_ref())?;
+        }
+        for evaluation in self.evaluations().iter() {
+            write_scalar(&mut writer, evaluation)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut c = [E::G1Affine::zero(); 16];
+        for commitment in c.iter_mut() {
+            *commitment = read_point::<<E::G1Affine as CurveAffine>::Compressed, _>(&mut reader)?;
+        }
+        let mut e = [E::Fr::zero(); 12];
+        for evaluation in e.iter_mut() {
+            *evaluation = read_scalar(&mut reader)?;
+        }
+        let mut index_evals = [E::Fr::zero(); 9];
+        for evaluation in index_evals.iter_mut() {
+            *evaluation = read_scalar(&mut reader)?;
+        }
+
+        Ok(Proof {
+            w: c[0],
+            z_a: c[1],
+            z_b: c[2],
+            mask: c[3],
+            g1: c[4],
+            g1_shifted: c[5],
+            h1: c[6],
+            g2: c[7],
+            g2_shifted: c[8],
+            h2: c[9],
+            g3: c[10],
+            g3_shifted: c[11],
+            h3: c[12],
+            opening_beta1: c[13],
+            opening_beta2: c[14],
+            opening_beta3: c[15],
+            sigma2: e[0],
+            sigma3: e[1],
+            w_eval: e[2],
+            z_a_eval: e[3],
+            z_b_eval: e[4],
+            mask_eval: e[5],
+            g1_eval: e[6],
+            h1_eval: e[7],
+            g2_eval: e[8],
+            h2_eval: e[9],
+            g3_eval: e[10],
+            h3_eval: e[11],
+            index_evals,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct VerifyingKey<E: Engine> {
+    // Sizes of the subgroups H and K, powers of two.
+    pub h_size: usize,
+    pub k_size: usize,
+
+    // Number of public inputs, not counting the `ONE` input.
+    pub num_inputs: usize,
+
+    // The highest power of the SRS used, which the degree bounds are
+    // relative to.
+    pub max_degree: usize,
+
+    // Commitments to the row, column and value polynomials of A, B and C.
+    pub index_commitments: [E::G1Affine; 9],
+
+    // The generator of G1 and G2 and the secret of the SRS in G2.
+    pub g1: E::G1Affine,
+    pub g2: E::G2Affine,
+    pub g2_x: E::G2Affine,
+}
+
+impl<E: Engine> PartialEq for VerifyingKey<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.h_size == other.h_size
+            && self.k_size == other.k_size
+            && self.num_inputs == other.num_inputs
+            && self.max_degree == other.max_degree
+            && self.index_commitments == other.index_commitments
+            && self.g1 == other.g1
+            && self.g2 == other.g2
+            && self.g2_x == other.g2_x
+    }
+}
+
+impl<E: Engine> VerifyingKey<E> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<BigEndian>(self.h_size as u32)?;
+        writer.write_u32::<BigEndian>(self.k_size as u32)?;
+        writer.write_u32::<BigEndian>(self.num_inputs as u32)?;
+        writer.write_u32::<BigEndian>(self.max_degree as u32)?;
+        for commitment in self.index_commitments.iter().chain(Some(&self.g1)) {
+            writer.write_all(commitment.into_uncompressed().as_ref())?;
+        }
+        writer.write_all(self.g2.into_uncompressed().as_ref())?;
+        writer.write_all(self.g2_x.into_uncompressed().as_ref())?;
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let h_size = reader.read_u32::<BigEndian>()? as usize;
+        let k_size = reader.read_u32::<BigEndian>()? as usize;
+        if !h_size.is_power_of_two() || !k_size.is_power_of_two() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "domain size is not a power of two",
+            ));
+        }
+        let num_inputs = reader.read_u32::<BigEndian>()? as usize;
+        if input_domain_size(num_inputs) > h_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "more inputs than the domain size",
+            ));
+        }
+        let max_degree = reader.read_u32::<BigEndian>()? as usize;
+
+        let mut index_commitments = [E::G1Affine::zero(); 9];
+        for commitment in index_commitments.iter_mut() {
+            *commitment = read_point::<<E::G1Affine as CurveAffine>::Uncompressed, _>(&mut reader)?;
+        }
+        let g1 = read_point::<<E::G1Affine as CurveAffine>::Uncompressed, _>(&mut reader)?;
+        let g2 = read_point::<<E::G2Affine as CurveAffine>::Uncompressed, _>(&mut reader)?;
+        let g2_x = read_point::<<E::G2Affine as CurveAffine>::Uncompressed, _>(&mut reader)?;
+
+        Ok(VerifyingKey {
+            h_size,
+            k_size,
+            num_inputs,
+            max_degree,
+            index_commitments,
+            g1,
+            g2,
+            g2_x,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct ProvingKey<E: Engine> {
+    pub vk: VerifyingKey<E>,
+
+    // Powers of the secret of the SRS in G1 up to the maximal degree.
+    pub(crate) g1_powers: Arc<Vec<E::G1Affine>>,
+
+    // The nonzero entries of A, B and C as row and column in H and value.
+    pub(crate) matrices: [Vec<(usize, usize, E::Fr)>; 3],
+
+    // The row, column and value polynomials of A, B and C in coefficient
+    // form and evaluated over the coset of the subgroup of size 8|K|, which
+    // the quotient of the third sumcheck is computed on.
+    pub(crate) index_polys: [Vec<E::Fr>; 9],
+    pub(crate) index_coset: [Vec<E::Fr>; 9],
+}
+
+#[cfg(test)]
+mod test_with_bls12_381 {
+    use super::*;
+    use crate::{Circuit, ConstraintSystem, SynthesisError};
+
+    use crate::bls::{Bls12, Fr};
+    use crate::testing::test_rng;
+    use ff::Field;
+
+    /// Proves knowledge of `x` with `x^3 + x + 5 = out` for the public `out`.
+    struct CubeCircuit {
+        x: Option<Fr>,
+        out: Option<Fr>,
+    }
+
+    impl Circuit<Bls12> for CubeCircuit {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x_value = self.x;
+            let x = cs.alloc(|| "x", || x_value.ok_or(SynthesisError::AssignmentMissing))?;
+            let x2_value = x_value.map(|mut x| {
+                x.square();
+                x
+            });
+            let x2 = cs.alloc(
+                || "x2",
+                || x2_value.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            cs.enforce(|| "x2 = x * x", |lc| lc + x, |lc| lc + x, |lc| lc + x2);
+            let x3_value = x2_value.map(|mut x2| {
+                x2.mul_assign(&x_value.unwrap());
+                x2
+            });
+            let x3 = cs.alloc(
+                || "x3",
+                || x3_value.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            cs.enforce(|| "x3 = x2 * x", |lc| lc + x2, |lc| lc + x, |lc| lc + x3);
+
+            let out = cs.alloc_input(
+                || "out",
+                || self.out.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            let five = Fr::from_str("5").unwrap();
+            cs.enforce(
+                || "out = x3 + x + 5",
+                |lc| lc + x3 + x + (five, CS::one()),
+                |lc| lc + CS::one(),
+                |lc| lc + out,
+            );
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn cube() {
+        let rng = &mut test_rng();
+
+        let srs = UniversalSrs::<Bls12>::generate_random(64, rng);
+        let pk = generate_parameters(CubeCircuit { x: None, out: None }, &srs).unwrap();
+
+        let x = Fr::from_str("3").unwrap();
+        let out = Fr::from_str("35").unwrap();
+        let proof = create_random_proof(
+            CubeCircuit {
+                x: Some(x),
+                out: Some(out),
+            },
+            &pk,
+            rng,
+        )
+        .unwrap();
+
+        assert!(verify_proof(&pk.vk, &proof, &[out]).unwrap());
+        assert!(!verify_proof(&pk.vk, &proof, &[Fr::from_str("36").unwrap()]).unwrap());
+
+        let mut proof_bytes = vec![];
+        proof.write(&mut proof_bytes).unwrap();
+        assert_eq!(proof_bytes.len(), 16 * 48 + 21 * 32);
+        assert!(proof == Proof::read(&proof_bytes[..]).unwrap());
+
+        let mut vk_bytes = vec![];
+        pk.vk.write(&mut vk_bytes).unwrap();
+        assert!(pk.vk == VerifyingKey::read(&vk_bytes[..]).unwrap());
+
+        // The prover refuses to prove a wrong output.
+        match create_random_proof(
+            CubeCircuit {
+                x: Some(x),
+                out: Some(Fr::from_str("36").unwrap()),
+            },
+            &pk,
+            rng,
+        ) {
+            Err(SynthesisError::Unsatisfiable) => {}
+            _ => panic!("proof of a wrong output"),
+        }
+    }
+}
@@ -0,0 +1,562 @@
+use rand_core::{CryptoRng, RngCore};
+
+use crate::bls::Engine;
+use ff::{Field, PrimeField};
+use log::info;
+
+use super::assembly::Assembly;
+use super::transcript::Transcript;
+use super::{input_domain_size, position, Proof, ProvingKey};
+
+use crate::domain::gpu_fft_supported;
+#[cfg(feature = "gpu")]
+use crate::gpu;
+use crate::multicore::Worker;
+use crate::multiexp::gpu_multiexp_supported;
+use crate::poly::{
+    add_scaled, batch_invert, blind, commit, commit_shifted, coset_evaluations, coset_interpolate,
+    divide_by_linear, divide_by_vanishing, evaluate, evaluations, from_u64, interpolate,
+    lagrange_evaluations, root_of_unity,
+};
+use crate::{Circuit, Index, SynthesisError};
+
+/// Multiplies the polynomial with `X^shift`.
+fn shifted<F: Field>(coeffs: &[F], shift: usize) -> Vec<F> {
+    let mut shifted = vec![F::zero(); shift];
+    shifted.extend_from_slice(coeffs);
+
+    shifted
+}
+
+/// Creates a proof, blinding the polynomials of the witness with random
+/// multiples of the vanishing polynomial of `H` and masking the first
+/// sumcheck with a random polynomial.
+pub fn create_random_proof<E, C, R>(
+    circuit: C,
+    pk: &ProvingKey<E>,
+    rng: &mut R,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+    R: RngCore + CryptoRng,
+{
+    let vk = &pk.vk;
+    let h_size = vk.h_size;
+    let k_size = vk.k_size;
+    let x_size = input_domain_size(vk.num_inputs);
+    let log_h = h_size.trailing_zeros();
+    let log_k = k_size.trailing_zeros();
+
+    let mut assembly = Assembly::<E>::new(true);
+    circuit.synthesize(&mut assembly)?;
+    if assembly.num_inputs != vk.num_inputs + 1 || x_size + assembly.num_aux > h_size {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+
+    #[cfg(feature = "gpu")]
+    let lock = gpu::lock()?;
+
+    let mut fft_kern = gpu_fft_supported::<E>((log_h + 2).max(log_k + 3)).ok();
+    if fft_kern.is_some() {
+        info!("GPU FFT is supported!");
+    } else {
+        info!("GPU FFT is NOT supported!");
+    }
+    let mut multiexp_kern = gpu_multiexp_supported::<E>().ok();
+    if multiexp_kern.is_some() {
+        info!("GPU Multiexp is supported!");
+    } else {
+        info!("GPU Multiexp is NOT supported!");
+    }
+
+    let worker = Worker::new();
+    let mut transcript = Transcript::new(vk, &assembly.input_values[1..]);
+
+    let zero = E::Fr::zero();
+    let one = E::Fr::one();
+    let omega = root_of_unity::<E::Fr>(log_h);
+    let mut omega_powers = Vec::with_capacity(h_size);
+    let mut omega_i = one;
+    for _ in 0..h_size {
+        omega_powers.push(omega_i);
+        omega_i.mul_assign(&omega);
+    }
+    // The degree bounds of g1 and g2, and of g3.
+    let shift_h = vk.max_degree - (h_size - 2);
+    let shift_k = vk.max_degree - (k_size - 2);
+
+    // Round 1: commit to the witness and to Az and Bz, blinded with random
+    // multiples of the vanishing polynomial of H, and to the mask of the
+    // first sumcheck.
+    let mut z = vec![zero; h_size];
+    for (i, value) in assembly.input_values.iter().enumerate() {
+        z[position(Index::Input(i), x_size, h_size)] = *value;
+    }
+    for (i, value) in assembly.aux_values.iter().enumerate() {
+        z[position(Index::Aux(i), x_size, h_size)] = *value;
+    }
+    let mut z_m = vec![vec![zero; h_size]; 2];
+    for (z_m, entries) in z_m.iter_mut().zip(pk.matrices.iter()) {
+        for &(row, col, coeff) in entries.iter() {
+            let mut tmp = z[col];
+            tmp.mul_assign(&coeff);
+            z_m[row].add_assign(&tmp);
+        }
+    }
+
+    // z(X) = w(X)·v_X(X) + x(X), where x(X) interpolates the inputs over X
+    // and v_X(X) = X^|X| - 1 is its vanishing polynomial.
+    let mut x = assembly.input_values.clone();
+    x.resize(x_size, zero);
+    let x_poly = interpolate(&worker, x, &mut fft_kern)?;
+    let x_evaluations = evaluations(&worker, &x_poly, h_size, &mut fft_kern)?;
+
+    let stride = h_size / x_size;
+    let omega_x = omega.pow(&[x_size as u64]);
+    let mut denominators = vec![];
+    let mut power = one;
+    for i in 0..h_size {
+        if i % stride != 0 {
+            let mut tmp = power;
+            tmp.sub_assign(&one);
+            denominators.push(tmp);
+        }
+        power.mul_assign(&omega_x);
+    }
+    batch_invert(&mut denominators)?;
+    let mut w = vec![zero; h_size];
+    for ((i, w), inv) in w
+        .iter_mut()
+        .enumerate()
+        .filter(|(i, _)| i % stride != 0)
+        .zip(denominators)
+    {
+        *w = z[i];
+        w.sub_assign(&x_evaluations[i]);
+        w.mul_assign(&inv);
+    }
+
+    let w_poly = blind(
+        interpolate(&worker, w, &mut fft_kern)?,
+        &[E::Fr::random(rng)],
+    );
+    let mut z_m_polys = vec![];
+    for z_m in z_m {
+        let poly = interpolate(&worker, z_m, &mut fft_kern)?;
+        z_m_polys.push(blind(poly, &[E::Fr::random(rng)]));
+    }
+    let z_b_poly = z_m_polys.pop().unwrap();
+    let z_a_poly = z_m_polys.pop().unwrap();
+
+    // A random polynomial of degree 3|H| - 1 summing to zero over H, which
+    // is |H| times the sum of the coefficients of the multiples of |H|.
+    let mut mask_poly = (0..3 * h_size)
+        .map(|_| E::Fr::random(rng))
+        .collect::<Vec<_>>();
+    let mut sum = mask_poly[h_size];
+    sum.add_assign(&mask_poly[2 * h_size]);
+    sum.negate();
+    mask_poly[0] = sum;
+
+    let mut round1_commitments = vec![];
+    for poly in [&w_poly, &z_a_poly, &z_b_poly, &mask_poly].iter() {
+        let commitment = commit(&worker, &pk.g1_powers, poly, &mut multiexp_kern)?;
+        transcript.append_point(&commitment);
+        round1_commitments.push(commitment);
+    }
+
+    let alpha = transcript.challenge::<E::Fr>();
+    let etas = [
+        transcript.challenge::<E::Fr>(),
+        transcript.challenge::<E::Fr>(),
+        transcript.challenge::<E::Fr>(),
+    ];
+
+    // Round 2: the first sumcheck shows that
+    // u_H(α, X)·(η_A·z_A(X) + η_B·z_B(X) + η_C·z_A(X)·z_B(X)) - t(X)·z(X)
+    // sums to zero over H, where t(X) is the sum of η_M·u_H(α, h)·M(h, X)
+    // over H. For a random α, this implies that z_A, z_B and z_A·z_B are
+    // Az, Bz and Cz.
+    let mut v_h_alpha = alpha.pow(&[h_size as u64]);
+    v_h_alpha.sub_assign(&one);
+    // u_H(α, X) = (X^|H| - α^|H|) / (X - α), which is v_H(α) / (α - h) for
+    // the elements h of H.
+    let mut u_alpha = omega_powers
+        .iter()
+        .map(|h| {
+            let mut tmp = alpha;
+            tmp.sub_assign(h);
+            tmp
+        })
+        .collect::<Vec<_>>();
+    batch_invert(&mut u_alpha)?;
+    for u in u_alpha.iter_mut() {
+        u.mul_assign(&v_h_alpha);
+    }
+    let mut u_poly = vec![one; h_size];
+    for i in (0..h_size - 1).rev() {
+        u_poly[i] = u_poly[i + 1];
+        u_poly[i].mul_assign(&alpha);
+    }
+
+    let mut t = vec![zero; h_size];
+    for (eta, entries) in etas.iter().zip(pk.matrices.iter()) {
+        for &(row, col, coeff) in entries.iter() {
+            let mut tmp = u_alpha[row];
+            tmp.mul_assign(&coeff);
+            tmp.mul_assign(eta);
+            t[col].add_assign(&tmp);
+        }
+    }
+    let t_poly = interpolate(&worker, t, &mut fft_kern)?;
+
+    let mut z_poly = vec![zero; w_poly.len() + x_size];
+    for (i, c) in w_poly.iter().enumerate() {
+        z_poly[i + x_size].add_assign(c);
+        z_poly[i].sub_assign(c);
+    }
+    add_scaled(&mut z_poly, &x_poly, &one);
+
+    // The summand has degree 3|H| - 1.
+    let coset_size = 4 * h_size;
+    let mut q1 = coset_evaluations(&worker, &mask_poly, coset_size, &mut fft_kern)?;
+    let u_coset = coset_evaluations(&worker, &u_poly, coset_size, &mut fft_kern)?;
+    let z_a_coset = coset_evaluations(&worker, &z_a_poly, coset_size, &mut fft_kern)?;
+    let z_b_coset = coset_evaluations(&worker, &z_b_poly, coset_size, &mut fft_kern)?;
+    let t_coset = coset_evaluations(&worker, &t_poly, coset_size, &mut fft_kern)?;
+    let z_coset = coset_evaluations(&worker, &z_poly, coset_size, &mut fft_kern)?;
+    worker.scope(coset_size, |scope, chunk| {
+        let u_coset = &u_coset;
+        let z_a_coset = &z_a_coset;
+        let z_b_coset = &z_b_coset;
+        let t_coset = &t_coset;
+        let z_coset = &z_coset;
+
+        for (i, q1) in q1.chunks_mut(chunk).enumerate() {
+            scope.spawn(move |_scope| {
+                for (j, q) in q1.iter_mut().enumerate() {
+                    let idx = i * chunk + j;
+
+                    let mut lincheck = z_a_coset[idx];
+                    lincheck.mul_assign(&z_b_coset[idx]);
+                    lincheck.mul_assign(&etas[2]);
+                    for &(z_m, eta) in [(z_a_coset[idx], etas[0]), (z_b_coset[idx], etas[1])].iter()
+                    {
+                        let mut tmp = z_m;
+                        tmp.mul_assign(&eta);
+                        lincheck.add_assign(&tmp);
+                    }
+                    lincheck.mul_assign(&u_coset[idx]);
+                    q.add_assign(&lincheck);
+
+                    let mut tmp = t_coset[idx];
+                    tmp.mul_assign(&z_coset[idx]);
+                    q.sub_assign(&tmp);
+                }
+            });
+        }
+    });
+    let q1 = coset_interpolate(&worker, q1, &mut fft_kern)?;
+
+    // The remainder of the division by v_H(X) is X·g1(X) plus the sum over
+    // H divided by |H|, which only vanishes if the constraints are
+    // satisfied.
+    let (h1_poly, remainder) = divide_by_vanishing(&q1, h_size);
+    if !remainder[0].is_zero() {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    let g1_poly = remainder[1..].to_vec();
+
+    let g1 = commit(&worker, &pk.g1_powers, &g1_poly, &mut multiexp_kern)?;
+    let g1_shifted = commit_shifted(
+        &worker,
+        &pk.g1_powers,
+        &g1_poly,
+        shift_h,
+        &mut multiexp_kern,
+    )?;
+    let h1 = commit(&worker, &pk.g1_powers, &h1_poly, &mut multiexp_kern)?;
+    for commitment in [g1, g1_shifted, h1].iter() {
+        transcript.append_point(commitment);
+    }
+
+    let beta1 = transcript.challenge::<E::Fr>();
+
+    // Round 3: the second sumcheck shows that t(β1) is the sum of
+    // u_H(α, X)·Σ η_M·M(X, β1) over H, where M(h, β1) is the sum of the
+    // entries of the row h weighted by the Lagrange polynomials at β1.
+    let lagrange = lagrange_evaluations(h_size, &omega, &beta1, h_size)?;
+    let mut v = vec![zero; h_size];
+    for (eta, entries) in etas.iter().zip(pk.matrices.iter()) {
+        for &(row, col, coeff) in entries.iter() {
+            let mut tmp = lagrange[col];
+            tmp.mul_assign(&coeff);
+            tmp.mul_assign(eta);
+            v[row].add_assign(&tmp);
+        }
+    }
+    let v_poly = interpolate(&worker, v, &mut fft_kern)?;
+    let mut q2 = evaluations(&worker, &u_poly, 2 * h_size, &mut fft_kern)?;
+    for (q, v) in q2
+        .iter_mut()
+        .zip(evaluations(&worker, &v_poly, 2 * h_size, &mut fft_kern)?)
+    {
+        q.mul_assign(&v);
+    }
+    let q2 = interpolate(&worker, q2, &mut fft_kern)?;
+
+    let (h2_poly, remainder) = divide_by_vanishing(&q2, h_size);
+    let mut sigma2 = remainder[0];
+    sigma2.mul_assign(&from_u64(h_size as u64));
+    let g2_poly = remainder[1..].to_vec();
+
+    let g2 = commit(&worker, &pk.g1_powers, &g2_poly, &mut multiexp_kern)?;
+    let g2_shifted = commit_shifted(
+        &worker,
+        &pk.g1_powers,
+        &g2_poly,
+        shift_h,
+        &mut multiexp_kern,
+    )?;
+    let h2 = commit(&worker, &pk.g1_powers, &h2_poly, &mut multiexp_kern)?;
+    for commitment in [g2, g2_shifted, h2].iter() {
+        transcript.append_point(commitment);
+    }
+    transcript.append_scalar(&sigma2);
+
+    let beta2 = transcript.challenge::<E::Fr>();
+
+    // Round 4: the third sumcheck shows that Σ η_M·M(β2, β1) is the sum of
+    // f(X) = Σ η_M·v_H(β2)·v_H(β1)·val_M(X) / ((β2 - row_M(X))(β1 - col_M(X)))
+    // over K. f(X)·b(X) = a(X) over K, where b(X) is the product of the
+    // denominators.
+    let mut v_h_betas = beta1.pow(&[h_size as u64]);
+    v_h_betas.sub_assign(&one);
+    let mut tmp = beta2.pow(&[h_size as u64]);
+    tmp.sub_assign(&one);
+    v_h_betas.mul_assign(&tmp);
+
+    let mut index_evaluations = vec![];
+    for poly in pk.index_polys.iter() {
+        index_evaluations.push(evaluations(&worker, poly, k_size, &mut fft_kern)?);
+    }
+    let mut denominators = vec![zero; 3 * k_size];
+    for (denominators, index) in denominators
+        .chunks_mut(k_size)
+        .zip(index_evaluations.chunks(3))
+    {
+        for (d, (row, col)) in denominators
+            .iter_mut()
+            .zip(index[0].iter().zip(index[1].iter()))
+        {
+            *d = beta2;
+            d.sub_assign(row);
+            let mut tmp = beta1;
+            tmp.sub_assign(col);
+            d.mul_assign(&tmp);
+        }
+    }
+    batch_invert(&mut denominators)?;
+    let mut f = vec![zero; k_size];
+    for ((eta, denominators), index) in etas
+        .iter()
+        .zip(denominators.chunks(k_size))
+        .zip(index_evaluations.chunks(3))
+    {
+        for (f, (d, val)) in f.iter_mut().zip(denominators.iter().zip(index[2].iter())) {
+            let mut tmp = *val;
+            tmp.mul_assign(d);
+            tmp.mul_assign(eta);
+            f.add_assign(&tmp);
+        }
+    }
+    for f in f.iter_mut() {
+        f.mul_assign(&v_h_betas);
+    }
+    let f_poly = interpolate(&worker, f, &mut fft_kern)?;
+
+    // f(X) = X·g3(X) + σ3/|K|
+    let mut sigma3 = f_poly[0];
+    sigma3.mul_assign(&from_u64(k_size as u64));
+    let g3_poly = f_poly[1..].to_vec();
+
+    // h3(X) = (a(X) - b(X)·f(X)) / v_K(X), computed over the coset of the
+    // subgroup of size 8|K|, over which v_K(X) only takes eight values.
+    let coset_size = 8 * k_size;
+    let f_coset = coset_evaluations(&worker, &f_poly, coset_size, &mut fft_kern)?;
+    let coset_omega = root_of_unity::<E::Fr>(log_k + 3);
+    let mut vanishing_inverses = vec![zero; 8];
+    {
+        let shift = E::Fr::multiplicative_generator().pow(&[k_size as u64]);
+        let root = coset_omega.pow(&[k_size as u64]);
+        let mut root_i = one;
+        for v in vanishing_inverses.iter_mut() {
+            *v = shift;
+            v.mul_assign(&root_i);
+            v.sub_assign(&one);
+            root_i.mul_assign(&root);
+        }
+        batch_invert(&mut vanishing_inverses)?;
+    }
+
+    let mut h3 = vec![zero; coset_size];
+    worker.scope(coset_size, |scope, chunk| {
+        let f_coset = &f_coset;
+        let vanishing_inverses = &vanishing_inverses;
+
+        for (i, h3) in h3.chunks_mut(chunk).enumerate() {
+            scope.spawn(move |_scope| {
+                for (j, h) in h3.iter_mut().enumerate() {
+                    let idx = i * chunk + j;
+
+                    let mut factors = [zero; 3];
+                    for (factor, index) in factors.iter_mut().zip(pk.index_coset.chunks(3)) {
+                        *factor = beta2;
+                        factor.sub_assign(&index[0][idx]);
+                        let mut tmp = beta1;
+                        tmp.sub_assign(&index[1][idx]);
+                        factor.mul_assign(&tmp);
+                    }
+
+                    let mut a = zero;
+                    for (m, (eta, index)) in etas.iter().zip(pk.index_coset.chunks(3)).enumerate() {
+                        let mut tmp = index[2][idx];
+                        tmp.mul_assign(eta);
+                        for (_, factor) in factors.iter().enumerate().filter(|&(n, _)| n != m) {
+                            tmp.mul_assign(factor);
+                        }
+                        a.add_assign(&tmp);
+                    }
+                    a.mul_assign(&v_h_betas);
+
+                    let mut b = f_coset[idx];
+                    for factor in factors.iter() {
+                        b.mul_assign(factor);
+                    }
+
+                    *h = a;
+                    h.sub_assign(&b);
+                    h.mul_assign(&vanishing_inverses[idx % 8]);
+                }
+            });
+        }
+    });
+    let mut h3_poly = coset_interpolate(&worker, h3, &mut fft_kern)?;
+    h3_poly.truncate(6 * k_size - 6);
+
+    let g3 = commit(&worker, &pk.g1_powers, &g3_poly, &mut multiexp_kern)?;
+    let g3_shifted = commit_shifted(
+        &worker,
+        &pk.g1_powers,
+        &g3_poly,
+        shift_k,
+        &mut multiexp_kern,
+    )?;
+    let h3 = commit(&worker, &pk.g1_powers, &h3_poly, &mut multiexp_kern)?;
+    for commitment in [g3, g3_shifted, h3].iter() {
+        transcript.append_point(commitment);
+    }
+    transcript.append_scalar(&sigma3);
+
+    let beta3 = transcript.challenge::<E::Fr>();
+
+    // Round 5: evaluate all polynomials and open them, batched with powers
+    // of ξ, where the shifted polynomials follow the ones they are shifted
+    // from.
+    let beta1_polys = [
+        w_poly,
+        z_a_poly,
+        z_b_poly,
+        mask_poly,
+        g1_poly.clone(),
+        shifted(&g1_poly, shift_h),
+        h1_poly,
+    ];
+    let beta2_polys = [g2_poly.clone(), shifted(&g2_poly, shift_h), h2_poly];
+    let mut beta3_polys = vec![g3_poly.clone(), shifted(&g3_poly, shift_k), h3_poly];
+    beta3_polys.extend(pk.index_polys.iter().cloned());
+
+    let w_eval = evaluate(&beta1_polys[0], &beta1);
+    let z_a_eval = evaluate(&beta1_polys[1], &beta1);
+    let z_b_eval = evaluate(&beta1_polys[2], &beta1);
+    let mask_eval = evaluate(&beta1_polys[3], &beta1);
+    let g1_eval = evaluate(&g1_poly, &beta1);
+    let h1_eval = evaluate(&beta1_polys[6], &beta1);
+    let g2_eval = evaluate(&g2_poly, &beta2);
+    let h2_eval = evaluate(&beta2_polys[2], &beta2);
+    let g3_eval = evaluate(&g3_poly, &beta3);
+    let h3_eval = evaluate(&beta3_polys[2], &beta3);
+    let mut index_evals = [zero; 9];
+    for (eval, poly) in index_evals.iter_mut().zip(pk.index_polys.iter()) {
+        *eval = evaluate(poly, &beta3);
+    }
+    for eval in [
+        w_eval, z_a_eval, z_b_eval, mask_eval, g1_eval, h1_eval, g2_eval, h2_eval, g3_eval, h3_eval,
+    ]
+    .iter()
+    .chain(index_evals.iter())
+    {
+        transcript.append_scalar(eval);
+    }
+
+    let xi = transcript.challenge::<E::Fr>();
+
+    let mut openings = vec![];
+    for (polys, point) in [
+        (&beta1_polys[..], beta1),
+        (&beta2_polys[..], beta2),
+        (&beta3_polys[..], beta3),
+    ]
+    .iter()
+    {
+        let mut batched = vec![];
+        let mut xi_i = one;
+        for poly in polys.iter() {
+            add_scaled(&mut batched, poly, &xi_i);
+            xi_i.mul_assign(&xi);
+        }
+        let quotient = divide_by_linear(&batched, point);
+        openings.push(commit(
+            &worker,
+            &pk.g1_powers,
+            &quotient,
+            &mut multiexp_kern,
+        )?);
+    }
+
+    #[cfg(feature = "gpu")]
+    gpu::unlock(lock);
+
+    Ok(Proof {
+        w: round1_commitments[0],
+        z_a: round1_commitments[1],
+        z_b: round1_commitments[2],
+        mask: round1_commitments[3],
+        g1,
+        g1_shifted,
+        h1,
+        g2,
+        g2_shifted,
+        h2,
+        g3,
+        g3_shifted,
+        h3,
+        opening_beta1: openings[0],
+        opening_beta2: openings[1],
+        opening_beta3: openings[2],
+        sigma2,
+        sigma3,
+        w_eval,
+        z_a_eval,
+        z_b_eval,
+        mask_eval,
+        g1_eval,
+        h1_eval,
+        g2_eval,
+        h2_eval,
+        g3_eval,
+        h3_eval,
+        index_evals,
+    })
+}
@@ -0,0 +1,277 @@
+use crate::bls::{Engine, PairingCurveAffine};
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective};
+
+use super::transcript::Transcript;
+use super::{input_domain_size, Proof, VerifyingKey};
+use crate::poly::{from_u64, lagrange_evaluations, root_of_unity};
+use crate::SynthesisError;
+
+pub fn verify_proof<E: Engine>(
+    vk: &VerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs: &[E::Fr],
+) -> Result<bool, SynthesisError> {
+    if public_inputs.len() != vk.num_inputs {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+
+    let h_size = vk.h_size;
+    let k_size = vk.k_size;
+    let x_size = input_domain_size(vk.num_inputs);
+    let zero = E::Fr::zero();
+    let one = E::Fr::one();
+
+    // Recompute the challenges of the prover.
+    let mut transcript = Transcript::new(vk, public_inputs);
+    for commitment in [proof.w, proof.z_a, proof.z_b, proof.mask].iter() {
+        transcript.append_point(commitment);
+    }
+    let alpha = transcript.challenge::<E::Fr>();
+    let etas = [
+        transcript.challenge::<E::Fr>(),
+        transcript.challenge::<E::Fr>(),
+        transcript.challenge::<E::Fr>(),
+    ];
+    for commitment in [proof.g1, proof.g1_shifted, proof.h1].iter() {
+        transcript.append_point(commitment);
+    }
+    let beta1 = transcript.challenge::<E::Fr>();
+    for commitment in [proof.g2, proof.g2_shifted, proof.h2].iter() {
+        transcript.append_point(commitment);
+    }
+    transcript.append_scalar(&proof.sigma2);
+    let beta2 = transcript.challenge::<E::Fr>();
+    for commitment in [proof.g3, proof.g3_shifted, proof.h3].iter() {
+        transcript.append_point(commitment);
+    }
+    transcript.append_scalar(&proof.sigma3);
+    let beta3 = transcript.challenge::<E::Fr>();
+    for eval in [
+        proof.w_eval,
+        proof.z_a_eval,
+        proof.z_b_eval,
+        proof.mask_eval,
+        proof.g1_eval,
+        proof.h1_eval,
+        proof.g2_eval,
+        proof.h2_eval,
+        proof.g3_eval,
+        proof.h3_eval,
+    ]
+    .iter()
+    .chain(proof.index_evals.iter())
+    {
+        transcript.append_scalar(eval);
+    }
+    let xi = transcript.challenge::<E::Fr>();
+    for opening in [
+        proof.opening_beta1,
+        proof.opening_beta2,
+        proof.opening_beta3,
+    ]
+    .iter()
+    {
+        transcript.append_point(opening);
+    }
+    let u = transcript.challenge::<E::Fr>();
+
+    let vanishing = |point: &E::Fr, size: usize| {
+        let mut v = point.pow(&[size as u64]);
+        v.sub_assign(&one);
+        v
+    };
+    let v_h_alpha = vanishing(&alpha, h_size);
+    let v_h_beta1 = vanishing(&beta1, h_size);
+    let v_h_beta2 = vanishing(&beta2, h_size);
+    // u_H(α, β) = (v_H(α) - v_H(β)) / (α - β)
+    let u_h = |beta: &E::Fr, v_h_beta: &E::Fr| {
+        let mut denominator = alpha;
+        denominator.sub_assign(beta);
+        let mut u = v_h_alpha;
+        u.sub_assign(v_h_beta);
+        u.mul_assign(
+            &denominator
+                .inverse()
+                .ok_or(SynthesisError::UnexpectedIdentity)?,
+        );
+        Ok::<_, SynthesisError>(u)
+    };
+
+    // z(β1) = w(β1)·v_X(β1) + x(β1)
+    let omega_x = root_of_unity::<E::Fr>(x_size.trailing_zeros());
+    let lagrange = lagrange_evaluations(x_size, &omega_x, &beta1, vk.num_inputs + 1)?;
+    let mut z_eval = proof.w_eval;
+    z_eval.mul_assign(&vanishing(&beta1, x_size));
+    z_eval.add_assign(&lagrange[0]);
+    for (input, l) in public_inputs.iter().zip(lagrange[1..].iter()) {
+        let mut tmp = *input;
+        tmp.mul_assign(l);
+        z_eval.add_assign(&tmp);
+    }
+
+    // The first sumcheck:
+    // s(β1) + u_H(α, β1)·(η_A·z_A(β1) + η_B·z_B(β1) + η_C·z_A(β1)·z_B(β1)) - σ2·z(β1)
+    //   = h1(β1)·v_H(β1) + β1·g1(β1)
+    let mut lhs = proof.z_a_eval;
+    lhs.mul_assign(&proof.z_b_eval);
+    lhs.mul_assign(&etas[2]);
+    for &(eval, eta) in [(proof.z_a_eval, etas[0]), (proof.z_b_eval, etas[1])].iter() {
+        let mut tmp = eval;
+        tmp.mul_assign(&eta);
+        lhs.add_assign(&tmp);
+    }
+    lhs.mul_assign(&u_h(&beta1, &v_h_beta1)?);
+    lhs.add_assign(&proof.mask_eval);
+    let mut tmp = proof.sigma2;
+    tmp.mul_assign(&z_eval);
+    lhs.sub_assign(&tmp);
+    let mut rhs = proof.h1_eval;
+    rhs.mul_assign(&v_h_beta1);
+    let mut tmp = proof.g1_eval;
+    tmp.mul_assign(&beta1);
+    rhs.add_assign(&tmp);
+    let first_valid = lhs == rhs;
+
+    // The second sumcheck:
+    // u_H(α, β2)·σ3 = h2(β2)·v_H(β2) + β2·g2(β2) + σ2/|H|
+    let mut lhs = proof.sigma3;
+    lhs.mul_assign(&u_h(&beta2, &v_h_beta2)?);
+    let mut rhs = proof.h2_eval;
+    rhs.mul_assign(&v_h_beta2);
+    let mut tmp = proof.g2_eval;
+    tmp.mul_assign(&beta2);
+    rhs.add_assign(&tmp);
+    let mut tmp = proof.sigma2;
+    tmp.mul_assign(
+        &from_u64::<E::Fr>(h_size as u64)
+            .inverse()
+            .ok_or(SynthesisError::UnexpectedIdentity)?,
+    );
+    rhs.add_assign(&tmp);
+    let second_valid = lhs == rhs;
+
+    // The third sumcheck:
+    // a(β3) - b(β3)·(β3·g3(β3) + σ3/|K|) = h3(β3)·v_K(β3)
+    let mut factors = [zero; 3];
+    for (factor, index) in factors.iter_mut().zip(proof.index_evals.chunks(3)) {
+        *factor = beta2;
+        factor.sub_assign(&index[0]);
+        let mut tmp = beta1;
+        tmp.sub_assign(&index[1]);
+        factor.mul_assign(&tmp);
+    }
+    let mut a = zero;
+    for (m, (eta, index)) in etas.iter().zip(proof.index_evals.chunks(3)).enumerate() {
+        let mut tmp = index[2];
+        tmp.mul_assign(eta);
+        for (_, factor) in factors.iter().enumerate().filter(|&(n, _)| n != m) {
+            tmp.mul_assign(factor);
+        }
+        a.add_assign(&tmp);
+    }
+    a.mul_assign(&v_h_beta1);
+    a.mul_assign(&v_h_beta2);
+    let mut f_eval = proof.g3_eval;
+    f_eval.mul_assign(&beta3);
+    let mut tmp = proof.sigma3;
+    tmp.mul_assign(
+        &from_u64::<E::Fr>(k_size as u64)
+            .inverse()
+            .ok_or(SynthesisError::UnexpectedIdentity)?,
+    );
+    f_eval.add_assign(&tmp);
+    for factor in factors.iter() {
+        f_eval.mul_assign(factor);
+    }
+    a.sub_assign(&f_eval);
+    let mut rhs = proof.h3_eval;
+    rhs.mul_assign(&vanishing(&beta3, k_size));
+    let third_valid = a == rhs;
+
+    // The shifted polynomials evaluate to the evaluation of the polynomials
+    // they are shifted from times the shift.
+    let shift_h = (vk.max_degree - (h_size - 2)) as u64;
+    let shift_k = (vk.max_degree - (k_size - 2)) as u64;
+    let shifted = |eval: E::Fr, point: &E::Fr, shift: u64| {
+        let mut tmp = point.pow(&[shift]);
+        tmp.mul_assign(&eval);
+        tmp
+    };
+    let mut beta3_terms = vec![
+        (proof.g3, proof.g3_eval),
+        (proof.g3_shifted, shifted(proof.g3_eval, &beta3, shift_k)),
+        (proof.h3, proof.h3_eval),
+    ];
+    beta3_terms.extend(
+        vk.index_commitments
+            .iter()
+            .cloned()
+            .zip(proof.index_evals.iter().cloned()),
+    );
+    let openings = vec![
+        (
+            beta1,
+            proof.opening_beta1,
+            vec![
+                (proof.w, proof.w_eval),
+                (proof.z_a, proof.z_a_eval),
+                (proof.z_b, proof.z_b_eval),
+                (proof.mask, proof.mask_eval),
+                (proof.g1, proof.g1_eval),
+                (proof.g1_shifted, shifted(proof.g1_eval, &beta1, shift_h)),
+                (proof.h1, proof.h1_eval),
+            ],
+        ),
+        (
+            beta2,
+            proof.opening_beta2,
+            vec![
+                (proof.g2, proof.g2_eval),
+                (proof.g2_shifted, shifted(proof.g2_eval, &beta2, shift_h)),
+                (proof.h2, proof.h2_eval),
+            ],
+        ),
+        (beta3, proof.opening_beta3, beta3_terms),
+    ];
+
+    // The opening proofs W_i of the commitments C_i batched with powers of ξ
+    // to the values v_i at the points β_i are checked with the pairing
+    // equations e(W_i, x) = e(C_i - v_i·1 + β_i·W_i, 1), combined with
+    // powers of u:
+    // e(Σ u^i·W_i, x)·e(-Σ u^i·(C_i - v_i·1 + β_i·W_i), 1) = 1
+    let mut lhs = E::G1::zero();
+    let mut rhs = E::G1::zero();
+    let mut u_i = one;
+    for (point, opening, terms) in openings.iter() {
+        let mut batched = E::G1::zero();
+        let mut value = zero;
+        let mut xi_i = one;
+        for (commitment, eval) in terms.iter() {
+            batched.add_assign(&commitment.mul(xi_i.into_repr()));
+            let mut tmp = *eval;
+            tmp.mul_assign(&xi_i);
+            value.add_assign(&tmp);
+            xi_i.mul_assign(&xi);
+        }
+        batched.sub_assign(&vk.g1.mul(value.into_repr()));
+        batched.add_assign(&opening.mul(point.into_repr()));
+        batched.mul_assign(u_i.into_repr());
+        rhs.add_assign(&batched);
+        lhs.add_assign(&opening.mul(u_i.into_repr()));
+        u_i.mul_assign(&u);
+    }
+    rhs.negate();
+
+    let openings_valid = E::final_exponentiation(&E::miller_loop(
+        [
+            (&lhs.into_affine().prepare(), &vk.g2_x.prepare()),
+            (&rhs.into_affine().prepare(), &vk.g2.prepare()),
+        ]
+        .iter(),
+    ))
+    .unwrap()
+        == E::Fqk::one();
+
+    Ok(first_valid && second_valid && third_valid && openings_valid)
+}
@@ -0,0 +1,167 @@
+//! [Prometheus] metrics of the prover, collected with the `metrics` feature:
+//!
+//! - `bellperson_proofs_started_total`, `bellperson_proofs_completed_total`
+//!   and `bellperson_proofs_failed_total`,
+//! - `bellperson_stage_duration_seconds`, a histogram labelled with the
+//!   `stage` and the `device` it ran on, see
+//!   [`ProvingStage`](crate::groth16::ProvingStage),
+//! - `bellperson_gpu_fallbacks_total`, labelled with the `kernel` that wasn't
+//!   available, so the CPU was used instead,
+//! - `bellperson_gpu_lock_wait_seconds`, a histogram of the time spent
+//!   waiting for the GPU lock, including waits that timed out,
+//! - `bellperson_gpu_priority_check_seconds`, a histogram of the time a proof
+//!   of low priority spent checking whether others wait for the GPU.
+//!
+//! They are collected once `register` added them to a registry. Without the
+//! feature, the hooks of the prover do nothing.
+//!
+//! [Prometheus]: https://prometheus.io
+
+#[cfg(feature = "metrics")]
+use std::time::Duration;
+
+#[cfg(feature = "metrics")]
+use prometheus::{
+    Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry,
+};
+
+#[cfg(feature = "metrics")]
+struct Metrics {
+    proofs_started: IntCounter,
+    proofs_completed: IntCounter,
+    proofs_failed: IntCounter,
+    stage_duration: HistogramVec,
+    gpu_fallbacks: IntCounterVec,
+    gpu_lock_wait: Histogram,
+    gpu_priority_checks: Histogram,
+}
+
+#[cfg(feature = "metrics")]
+impl Metrics {
+    fn new() -> prometheus::Result<Self> {
+        // From 10ms to about 45 minutes, proofs of large circuits take minutes.
+        let buckets = prometheus::exponential_buckets(0.01, 3.0, 12)?;
+
+        Ok(Metrics {
+            proofs_started: IntCounter::new(
+                "bellperson_proofs_started_total",
+                "Number of proofs started",
+            )?,
+            proofs_completed: IntCounter::new(
+                "bellperson_proofs_completed_total",
+                "Number of proofs completed",
+            )?,
+            proofs_failed: IntCounter::new(
+                "bellperson_proofs_failed_total",
+                "Number of proofs that failed or were cancelled",
+            )?,
+            stage_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "bellperson_stage_duration_seconds",
+                    "Duration of the stages of proofs",
+                )
+                .buckets(buckets.clone()),
+                &["stage", "device"],
+            )?,
+            gpu_fallbacks: IntCounterVec::new(
+                Opts::new(
+                    "bellperson_gpu_fallbacks_total",
+                    "Number of times a GPU kernel wasn't available and the CPU was used",
+                ),
+                &["kernel"],
+            )?,
+            gpu_lock_wait: Histogram::with_opts(
+                HistogramOpts::new(
+                    "bellperson_gpu_lock_wait_seconds",
+                    "Time spent waiting for the GPU lock",
+                )
+                .buckets(buckets),
+            )?,
+            gpu_priority_checks: Histogram::with_opts(
+                HistogramOpts::new(
+                    "bellperson_gpu_priority_check_seconds",
+                    "Time a proof spent checking whether provers of higher priority wait for the GPU",
+                )
+                // From 100µs to about 26 seconds, every check reads a directory.
+                .buckets(prometheus::exponential_buckets(0.0001, 4.0, 10)?),
+            )?,
+        })
+    }
+}
+
+#[cfg(feature = "metrics")]
+lazy_static::lazy_static! {
+    static ref METRICS: Metrics = Metrics::new().expect("the metrics are valid");
+}
+
+/// Registers the metrics of the prover with `registry`.
+#[cfg(feature = "metrics")]
+pub fn register(registry: &Registry) -> prometheus::Result<()> {
+    registry.register(Box::new(METRICS.proofs_started.clone()))?;
+    registry.register(Box::new(METRICS.proofs_completed.clone()))?;
+    registry.register(Box::new(METRICS.proofs_failed.clone()))?;
+    registry.register(Box::new(METRICS.stage_duration.clone()))?;
+    registry.register(Box::new(METRICS.gpu_fallbacks.clone()))?;
+    registry.register(Box::new(METRICS.gpu_lock_wait.clone()))?;
+    registry.register(Box::new(METRICS.gpu_priority_checks.clone()))?;
+
+    Ok(())
+}
+
+#[cfg(feature = "metrics")]
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) * 1e-9
+}
+
+pub(crate) fn proof_started() {
+    #[cfg(feature = "metrics")]
+    METRICS.proofs_started.inc();
+}
+
+pub(crate) fn proof_finished(success: bool) {
+    #[cfg(feature = "metrics")]
+    {
+        if success {
+            METRICS.proofs_completed.inc();
+        } else {
+            METRICS.proofs_failed.inc();
+        }
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = success;
+}
+
+pub(crate) fn stage_finished(stage: &str, device: &str, duration: std::time::Duration) {
+    #[cfg(feature = "metrics")]
+    METRICS
+        .stage_duration
+        .with_label_values(&[stage, device])
+        .observe(seconds(duration));
+    #[cfg(not(feature = "metrics"))]
+    let _ = (stage, device, duration);
+}
+
+/// Only called with the `gpu` feature, without it the CPU is no fallback.
+#[cfg_attr(not(feature = "gpu"), allow(dead_code))]
+pub(crate) fn gpu_fallback(kernel: &str) {
+    #[cfg(feature = "metrics")]
+    METRICS.gpu_fallbacks.with_label_values(&[kernel]).inc();
+    #[cfg(not(feature = "metrics"))]
+    let _ = kernel;
+}
+
+#[cfg_attr(not(feature = "gpu"), allow(dead_code))]
+pub(crate) fn gpu_lock_waited(duration: std::time::Duration) {
+    #[cfg(feature = "metrics")]
+    METRICS.gpu_lock_wait.observe(seconds(duration));
+    #[cfg(not(feature = "metrics"))]
+    let _ = duration;
+}
+
+#[cfg_attr(not(feature = "gpu"), allow(dead_code))]
+pub(crate) fn gpu_priority_checked(duration: std::time::Duration) {
+    #[cfg(feature = "metrics")]
+    METRICS.gpu_priority_checks.observe(seconds(duration));
+    #[cfg(not(feature = "metrics"))]
+    let _ = duration;
+}
@@ -1,36 +1,215 @@
 //! An interface for dealing with the kinds of parallel computations involved in
-//! `bellperson`. It's currently just a thin wrapper around [`CpuPool`] and
+//! `bellperson`. It's currently just a thin wrapper around [`ThreadPool`] and
 //! [`crossbeam`] but may be extended in the future to allow for various
-//! parallelism strategies.
+//! parallelism strategies. With the `rayon` feature, all of the work runs on
+//! `rayon` instead.
 //!
-//! [`CpuPool`]: futures_cpupool::CpuPool
+//! [`ThreadPool`]: futures::executor::ThreadPool
 
-#[cfg(feature = "multicore")]
+use std::future::Future;
+use std::mem::{self, MaybeUninit};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use futures::executor;
+
+use crate::SynthesisError;
+
+#[cfg(all(feature = "numa", target_os = "linux"))]
+mod numa;
+#[cfg(all(feature = "numa", target_os = "linux"))]
+pub use self::numa::NumaScope;
+
+/// The result of a computation on a [`Worker`], a standard future that can
+/// either be awaited or waited for on the current thread.
+pub struct WorkerFuture<T> {
+    future: Pin<Box<dyn Future<Output = T> + Send>>,
+}
+
+impl<T> WorkerFuture<T> {
+    pub fn new<F>(future: F) -> Self
+    where
+        F: Future<Output = T> + Send + 'static,
+    {
+        WorkerFuture {
+            future: Box::pin(future),
+        }
+    }
+
+    /// Blocks the current thread until the result is available.
+    pub fn wait(self) -> T {
+        executor::block_on(self.future)
+    }
+}
+
+impl<T> Future for WorkerFuture<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<T> {
+        self.future.as_mut().poll(cx)
+    }
+}
+
+/// Cancels a running computation, e.g. a proof, that checks the token from time
+/// to time. Clones share the same state.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token that additionally expires at `deadline`. There is no clock on
+    /// `wasm32-unknown-unknown`, checking such a token panics there.
+    pub fn with_deadline(deadline: Instant) -> Self {
+        CancellationToken {
+            cancelled: Arc::default(),
+            deadline: Some(deadline),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Fails with [`SynthesisError::Cancelled`] once cancelled and with
+    /// [`SynthesisError::Timeout`] once the deadline has passed.
+    pub fn check(&self) -> Result<(), SynthesisError> {
+        if self.is_cancelled() {
+            return Err(SynthesisError::Cancelled);
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(SynthesisError::Timeout),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Worker {
+    /// A vector of `len` copies of `value`, which are written by the threads
+    /// of the worker. With the `numa` feature, the memory of each chunk ends
+    /// up on the NUMA node of the thread that processes the same chunk later.
+    pub fn scratch<T: Clone + Send + Sync>(&self, len: usize, value: T) -> Vec<T> {
+        let mut scratch: Vec<MaybeUninit<T>> = Vec::with_capacity(len);
+        // Safe, as `MaybeUninit` doesn't need to be initialized.
+        unsafe { scratch.set_len(len) };
+
+        if len > 0 {
+            let value = &value;
+            self.scope(len, |scope, chunk| {
+                for scratch in scratch.chunks_mut(chunk) {
+                    scope.spawn(move |_| {
+                        for v in scratch {
+                            *v = MaybeUninit::new(value.clone());
+                        }
+                    });
+                }
+            });
+        }
+
+        // Safe, as all elements are initialized now.
+        let mut scratch = mem::ManuallyDrop::new(scratch);
+        unsafe { Vec::from_raw_parts(scratch.as_mut_ptr() as *mut T, len, scratch.capacity()) }
+    }
+}
+
+/// Whether `Worker::new` creates a pool of its own every time instead of
+/// sharing the global one, which is the case if `BELLMAN_NO_SHARED_POOL` is set.
+#[cfg(any(feature = "multicore", feature = "rayon"))]
+fn shared_pool_disabled() -> bool {
+    std::env::var_os("BELLMAN_NO_SHARED_POOL").is_some()
+}
+
+#[cfg(any(feature = "multicore", feature = "rayon"))]
+fn log2_floor(num: usize) -> u32 {
+    assert!(num > 0);
+
+    let mut pow = 0;
+
+    while (1 << (pow + 1)) <= num {
+        pow += 1;
+    }
+
+    pow
+}
+
+#[cfg(any(feature = "multicore", feature = "rayon"))]
+#[test]
+fn test_log2_floor() {
+    assert_eq!(log2_floor(1), 0);
+    assert_eq!(log2_floor(2), 1);
+    assert_eq!(log2_floor(3), 1);
+    assert_eq!(log2_floor(4), 2);
+    assert_eq!(log2_floor(5), 2);
+    assert_eq!(log2_floor(6), 2);
+    assert_eq!(log2_floor(7), 2);
+    assert_eq!(log2_floor(8), 3);
+}
+
+#[cfg(all(feature = "multicore", not(feature = "rayon")))]
 mod implementation {
-    use crossbeam::{self, thread::Scope};
-    use futures::{Future, IntoFuture, Poll};
-    use futures_cpupool::{CpuFuture, CpuPool};
+    use super::{log2_floor, shared_pool_disabled, WorkerFuture};
+    #[cfg(not(all(feature = "numa", target_os = "linux")))]
+    use crossbeam::thread::Scope;
+    use futures::channel::oneshot;
+    use futures::executor::ThreadPool;
     use num_cpus;
     use std::env;
 
+    lazy_static::lazy_static! {
+        static ref SHARED_WORKER: Worker = Worker::new_unshared();
+    }
+
     #[derive(Clone)]
     pub struct Worker {
         cpus: usize,
-        pool: CpuPool,
+        pool: ThreadPool,
     }
 
     impl Worker {
-        // We don't expose this outside the library so that
-        // all `Worker` instances have the same number of
-        // CPUs configured.
-        pub(crate) fn new_with_cpus(cpus: usize) -> Worker {
+        /// A worker with a fixed number of threads, independent of the
+        /// number of CPUs and of `BELLMAN_NUM_CPUS`. Passing it to the
+        /// `*_with_worker` functions bounds the CPU usage of a single job.
+        pub fn with_threads(threads: usize) -> Worker {
+            assert!(threads > 0, "a worker needs at least one thread");
+
+            let mut builder = ThreadPool::builder();
+            builder.pool_size(threads);
+            #[cfg(all(feature = "numa", target_os = "linux"))]
+            builder.after_start(super::numa::pin_current_thread);
+
             Worker {
-                cpus,
-                pool: CpuPool::new(cpus),
+                cpus: threads,
+                pool: builder.create().expect("failed to create the thread pool"),
             }
         }
 
+        /// A worker on the pool that is shared by all provers and verifiers of
+        /// the process, it's created on first use.
         pub fn new() -> Worker {
+            if shared_pool_disabled() {
+                Self::new_unshared()
+            } else {
+                SHARED_WORKER.clone()
+            }
+        }
+
+        fn new_unshared() -> Worker {
             let cpus = if let Ok(num) = env::var("BELLMAN_NUM_CPUS") {
                 if let Ok(num) = num.parse() {
                     num
@@ -41,83 +220,186 @@ mod implementation {
                 num_cpus::get()
             };
 
-            Self::new_with_cpus(cpus)
+            Self::with_threads(cpus)
         }
 
         pub fn log_num_cpus(&self) -> u32 {
             log2_floor(self.cpus)
         }
 
-        pub fn compute<F, R>(&self, f: F) -> WorkerFuture<R::Item, R::Error>
+        pub fn compute<F, R>(&self, f: F) -> WorkerFuture<R>
         where
             F: FnOnce() -> R + Send + 'static,
-            R: IntoFuture + 'static,
-            R::Future: Send + 'static,
-            R::Item: Send + 'static,
-            R::Error: Send + 'static,
+            R: Send + 'static,
         {
-            WorkerFuture {
-                future: self.pool.spawn_fn(f),
+            let (sender, receiver) = oneshot::channel();
+            self.pool.spawn_ok(async move {
+                // The receiver might have been dropped already.
+                let _ = sender.send(f());
+            });
+
+            WorkerFuture::new(async move {
+                receiver
+                    .await
+                    .expect("the computation on the worker panicked")
+            })
+        }
+
+        fn chunk_size(&self, elements: usize) -> usize {
+            if elements < self.cpus {
+                1
+            } else {
+                elements / self.cpus
             }
         }
 
+        #[cfg(not(all(feature = "numa", target_os = "linux")))]
         pub fn scope<'a, F, R>(&self, elements: usize, f: F) -> R
         where
             F: FnOnce(&Scope<'a>, usize) -> R,
         {
-            let chunk_size = if elements < self.cpus {
-                1
-            } else {
-                elements / self.cpus
-            };
+            let chunk_size = self.chunk_size(elements);
 
             // TODO: Handle case where threads fail
             crossbeam::scope(|scope| f(scope, chunk_size))
                 .expect("Threads aren't allowed to fail yet")
         }
+
+        /// The spawned threads are pinned to the NUMA nodes round-robin.
+        #[cfg(all(feature = "numa", target_os = "linux"))]
+        pub fn scope<'a, F, R>(&self, elements: usize, f: F) -> R
+        where
+            F: FnOnce(&super::NumaScope<'_, 'a>, usize) -> R,
+        {
+            let chunk_size = self.chunk_size(elements);
+
+            // TODO: Handle case where threads fail
+            crossbeam::scope(|scope| f(&super::NumaScope::new(scope), chunk_size))
+                .expect("Threads aren't allowed to fail yet")
+        }
     }
+}
 
-    pub struct WorkerFuture<T, E> {
-        future: CpuFuture<T, E>,
+/// Runs everything on rayon, so that applications with a rayon pool of their own
+/// don't end up with two sets of threads competing for the CPUs.
+#[cfg(feature = "rayon")]
+mod implementation {
+    use super::{log2_floor, shared_pool_disabled, WorkerFuture};
+    use futures::channel::oneshot;
+    use rayon::{Scope, ThreadPool, ThreadPoolBuilder};
+    use std::env;
+    use std::sync::Arc;
+
+    /// Without a pool of its own, a worker uses the rayon pool of the calling
+    /// thread, which is the global pool unless it is called from within
+    /// another one.
+    lazy_static::lazy_static! {
+        static ref SHARED_WORKER: Worker = Worker::new_unshared();
+    }
+
+    #[derive(Clone)]
+    pub struct Worker {
+        pool: Option<Arc<ThreadPool>>,
     }
 
-    impl<T: Send + 'static, E: Send + 'static> Future for WorkerFuture<T, E> {
-        type Item = T;
-        type Error = E;
+    impl Worker {
+        /// A worker with a dedicated pool of a fixed number of threads,
+        /// independent of the number of CPUs and of `BELLMAN_NUM_CPUS`.
+        /// Passing it to the `*_with_worker` functions bounds the CPU usage
+        /// of a single job.
+        pub fn with_threads(threads: usize) -> Worker {
+            assert!(threads > 0, "a worker needs at least one thread");
 
-        fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-            self.future.poll()
+            Worker {
+                pool: Some(Arc::new(
+                    ThreadPoolBuilder::new()
+                        .num_threads(threads)
+                        .build()
+                        .expect("failed to create the thread pool"),
+                )),
+            }
+        }
+
+        /// A worker that is shared by all provers and verifiers of the
+        /// process. Only with `BELLMAN_NUM_CPUS` it has a pool of its own,
+        /// which is created on first use.
+        pub fn new() -> Worker {
+            if shared_pool_disabled() {
+                Self::new_unshared()
+            } else {
+                SHARED_WORKER.clone()
+            }
         }
-    }
 
-    fn log2_floor(num: usize) -> u32 {
-        assert!(num > 0);
+        fn new_unshared() -> Worker {
+            match env::var("BELLMAN_NUM_CPUS")
+                .ok()
+                .and_then(|num| num.parse().ok())
+            {
+                Some(cpus) => Self::with_threads(cpus),
+                None => Worker { pool: None },
+            }
+        }
 
-        let mut pow = 0;
+        pub fn log_num_cpus(&self) -> u32 {
+            log2_floor(self.num_threads())
+        }
 
-        while (1 << (pow + 1)) <= num {
-            pow += 1;
+        fn num_threads(&self) -> usize {
+            match self.pool {
+                Some(ref pool) => pool.current_num_threads(),
+                None => rayon::current_num_threads(),
+            }
         }
 
-        pow
-    }
+        /// The computation is spawned onto the pool, waiting for its result
+        /// from within a single-threaded pool of the same worker deadlocks.
+        pub fn compute<F, R>(&self, f: F) -> WorkerFuture<R>
+        where
+            F: FnOnce() -> R + Send + 'static,
+            R: Send + 'static,
+        {
+            let (sender, receiver) = oneshot::channel();
+            let job = move || {
+                // The receiver might have been dropped already.
+                let _ = sender.send(f());
+            };
+            match self.pool {
+                Some(ref pool) => pool.spawn(job),
+                None => rayon::spawn(job),
+            }
+
+            WorkerFuture::new(async move {
+                receiver
+                    .await
+                    .expect("the computation on the worker panicked")
+            })
+        }
 
-    #[test]
-    fn test_log2_floor() {
-        assert_eq!(log2_floor(1), 0);
-        assert_eq!(log2_floor(2), 1);
-        assert_eq!(log2_floor(3), 1);
-        assert_eq!(log2_floor(4), 2);
-        assert_eq!(log2_floor(5), 2);
-        assert_eq!(log2_floor(6), 2);
-        assert_eq!(log2_floor(7), 2);
-        assert_eq!(log2_floor(8), 3);
+        pub fn scope<'a, F, R>(&self, elements: usize, f: F) -> R
+        where
+            F: FnOnce(&Scope<'a>, usize) -> R + Send + 'a,
+            R: Send,
+        {
+            let threads = self.num_threads();
+            let chunk_size = if elements < threads {
+                1
+            } else {
+                elements / threads
+            };
+
+            match self.pool {
+                Some(ref pool) => pool.scope(move |scope| f(scope, chunk_size)),
+                None => rayon::scope(move |scope| f(scope, chunk_size)),
+            }
+        }
     }
 }
 
-#[cfg(not(feature = "multicore"))]
+#[cfg(not(any(feature = "multicore", feature = "rayon")))]
 mod implementation {
-    use futures::{future, Future, IntoFuture, Poll};
+    use super::WorkerFuture;
+    use futures::future;
 
     #[derive(Clone)]
     pub struct Worker;
@@ -127,19 +409,22 @@ mod implementation {
             Worker
         }
 
+        /// Everything runs on the calling thread without the `multicore`
+        /// feature, the number of threads is ignored.
+        pub fn with_threads(_threads: usize) -> Worker {
+            Worker
+        }
+
         pub fn log_num_cpus(&self) -> u32 {
             0
         }
 
-        pub fn compute<F, R>(&self, f: F) -> R::Future
+        pub fn compute<F, R>(&self, f: F) -> WorkerFuture<R>
         where
             F: FnOnce() -> R + Send + 'static,
-            R: IntoFuture + 'static,
-            R::Future: Send + 'static,
-            R::Item: Send + 'static,
-            R::Error: Send + 'static,
+            R: Send + 'static,
         {
-            f().into_future()
+            WorkerFuture::new(future::ready(f()))
         }
 
         pub fn scope<F, R>(&self, elements: usize, f: F) -> R
@@ -150,19 +435,6 @@ mod implementation {
         }
     }
 
-    pub struct WorkerFuture<T, E> {
-        future: future::FutureResult<T, E>,
-    }
-
-    impl<T: Send + 'static, E: Send + 'static> Future for WorkerFuture<T, E> {
-        type Item = T;
-        type Error = E;
-
-        fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-            self.future.poll()
-        }
-    }
-
     pub struct DummyScope;
 
     impl DummyScope {
@@ -0,0 +1,96 @@
+//! Pinning of the worker threads to NUMA nodes. The threads are spread over
+//! the nodes round-robin, so that every thread keeps using the memory of its
+//! own node. Scratch memory is allocated on the node of the thread that
+//! touches it first, see [`Worker::scratch`](super::Worker::scratch).
+
+use std::cell::Cell;
+use std::fs;
+use std::mem;
+
+use crossbeam::thread;
+
+lazy_static::lazy_static! {
+    /// The CPUs of each NUMA node, empty if the topology is unknown.
+    static ref NODES: Vec<Vec<usize>> = read_nodes().unwrap_or_default();
+}
+
+fn read_nodes() -> Option<Vec<Vec<usize>>> {
+    let mut nodes = vec![];
+    while let Ok(list) = fs::read_to_string(format!(
+        "/sys/devices/system/node/node{}/cpulist",
+        nodes.len()
+    )) {
+        nodes.push(parse_cpu_list(list.trim())?);
+    }
+
+    Some(nodes)
+}
+
+/// Parses a list of CPUs like `0-3,8-11`.
+fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = vec![];
+    for range in list.split(',').filter(|range| !range.is_empty()) {
+        let mut bounds = range.splitn(2, '-');
+        let first = bounds.next()?.parse().ok()?;
+        let last = match bounds.next() {
+            Some(last) => last.parse().ok()?,
+            None => first,
+        };
+        cpus.extend(first..=last);
+    }
+
+    Some(cpus)
+}
+
+/// Pins the current thread to the CPUs of the node `index` maps to. Nothing
+/// happens on machines with a single node.
+pub(crate) fn pin_current_thread(index: usize) {
+    if NODES.len() < 2 {
+        return;
+    }
+
+    let cpus = &NODES[index % NODES.len()];
+    unsafe {
+        let mut set: libc::cpu_set_t = mem::zeroed();
+        for &cpu in cpus {
+            libc::CPU_SET(cpu, &mut set);
+        }
+        // Pinning is only an optimization, a failure is fine.
+        libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set);
+    }
+}
+
+/// A scope whose threads are pinned to the NUMA nodes one after another.
+pub struct NumaScope<'s, 'a> {
+    inner: &'s thread::Scope<'a>,
+    next: Cell<usize>,
+}
+
+impl<'s, 'a> NumaScope<'s, 'a> {
+    pub(crate) fn new(inner: &'s thread::Scope<'a>) -> Self {
+        NumaScope {
+            inner,
+            next: Cell::new(0),
+        }
+    }
+
+    pub fn spawn<F>(&self, f: F)
+    where
+        F: FnOnce(&thread::Scope<'a>) + Send + 'a,
+    {
+        let index = self.next.get();
+        self.next.set(index + 1);
+        self.inner.spawn(move |scope| {
+            pin_current_thread(index);
+            f(scope)
+        });
+    }
+}
+
+#[test]
+fn test_parse_cpu_list() {
+    assert_eq!(parse_cpu_list("0"), Some(vec![0]));
+    assert_eq!(parse_cpu_list("0-3,8-9"), Some(vec![0, 1, 2, 3, 8, 9]));
+    assert_eq!(parse_cpu_list(""), Some(vec![]));
+    assert_eq!(parse_cpu_list("0-a"), None);
+}
@@ -1,13 +1,19 @@
 use bit_vec::{self, BitVec};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use ff::{Field, PrimeField, PrimeFieldRepr, ScalarEngine};
-use futures::Future;
-use groupy::{CurveAffine, CurveProjective};
-use std::io;
+use groupy::{CurveAffine, CurveProjective, EncodedPoint};
+use std::any::{Any, TypeId};
+use std::cmp;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::iter;
-use std::sync::{Arc, Mutex};
+use std::mem;
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
 
-use super::multicore::Worker;
+use super::multicore::{CancellationToken, Worker, WorkerFuture};
 use super::SynthesisError;
+use crate::bls::{Fq, FqRepr, G1Affine, G1Uncompressed};
 use crate::gpu;
 
 /// An object that builds a source of bases.
@@ -15,17 +21,56 @@ pub trait SourceBuilder<G: CurveAffine>: Send + Sync + 'static + Clone {
     type Source: Source<G>;
 
     fn new(self) -> Self::Source;
-    fn get(self) -> (Arc<Vec<G>>, usize);
+    fn get(self) -> Result<(Arc<Vec<G>>, usize), SynthesisError>;
+
+    /// Whether the sources subtract bases as cheaply as they add them, which
+    /// lets CPU multiexps use signed windows with half as many buckets.
+    fn cheap_negation(&self) -> bool {
+        false
+    }
+
+    /// Whether [`get`](SourceBuilder::get) returns the bases without reading
+    /// or copying them, CPU multiexps then add the bases in batches.
+    fn in_memory(&self) -> bool {
+        false
+    }
+
+    /// Whether bases at infinity are skipped instead of being an error, see
+    /// [`SkipIdentity`].
+    fn skip_identity(&self) -> bool {
+        false
+    }
+
+    /// Precomputed multiples of the bases, with the index of the first base
+    /// of the sources among them. CPU multiexps then look the multiples up
+    /// instead of sorting the bases into buckets.
+    fn fixed_base_tables(&self) -> Option<(Arc<FixedBaseTables<G>>, usize)> {
+        None
+    }
 }
 
 /// A source of bases, like an iterator.
 pub trait Source<G: CurveAffine> {
-    /// Parses the element from the source. Fails if the point is at infinity.
+    /// Parses the element from the source. Fails if the point is at infinity,
+    /// without consuming it.
     fn add_assign_mixed(
         &mut self,
         to: &mut <G as CurveAffine>::Projective,
     ) -> Result<(), SynthesisError>;
 
+    /// Parses the element from the source and subtracts it. Fails if the point
+    /// is at infinity, without consuming it.
+    fn sub_assign_mixed(
+        &mut self,
+        to: &mut <G as CurveAffine>::Projective,
+    ) -> Result<(), SynthesisError> {
+        let mut base = G::Projective::zero();
+        self.add_assign_mixed(&mut base)?;
+        to.sub_assign(&base);
+
+        Ok(())
+    }
+
     /// Skips `amt` elements from the source, avoiding deserialization.
     fn skip(&mut self, amt: usize) -> Result<(), SynthesisError>;
 }
@@ -37,12 +82,160 @@ impl<G: CurveAffine> SourceBuilder<G> for (Arc<Vec<G>>, usize) {
         (self.0.clone(), self.1)
     }
 
-    fn get(self) -> (Arc<Vec<G>>, usize) {
-        (self.0.clone(), self.1)
+    fn get(self) -> Result<(Arc<Vec<G>>, usize), SynthesisError> {
+        Ok((self.0.clone(), self.1))
+    }
+
+    fn cheap_negation(&self) -> bool {
+        true
+    }
+
+    fn in_memory(&self) -> bool {
+        true
     }
 }
 
 impl<G: CurveAffine> Source<G> for (Arc<Vec<G>>, usize) {
+    fn add_assign_mixed(
+        &mut self,
+        to: &mut <G as CurveAffine>::Projective,
+    ) -> Result<(), SynthesisError> {
+        let mut source = (&self.0[..], self.1);
+        source.add_assign_mixed(to)?;
+        self.1 = source.1;
+
+        Ok(())
+    }
+
+    fn sub_assign_mixed(
+        &mut self,
+        to: &mut <G as CurveAffine>::Projective,
+    ) -> Result<(), SynthesisError> {
+        let mut source = (&self.0[..], self.1);
+        source.sub_assign_mixed(to)?;
+        self.1 = source.1;
+
+        Ok(())
+    }
+
+    fn skip(&mut self, amt: usize) -> Result<(), SynthesisError> {
+        let mut source = (&self.0[..], self.1);
+        source.skip(amt)?;
+        self.1 = source.1;
+
+        Ok(())
+    }
+}
+
+/// A range of the bases in a single shared allocation, like the input or the
+/// auxiliary part of a query of Groth16 parameters. Ranges of the same bases
+/// share their allocation, so handing out both parts of a query, or the same
+/// query twice, never copies it. A range is its own source, which fails at the
+/// end of the range instead of reading on into the bases after it.
+pub struct BaseRange<G> {
+    bases: Arc<Vec<G>>,
+    start: usize,
+    end: usize,
+}
+
+impl<G> Clone for BaseRange<G> {
+    fn clone(&self) -> Self {
+        BaseRange {
+            bases: self.bases.clone(),
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+impl<G: CurveAffine> BaseRange<G> {
+    /// A range of all of `bases`.
+    pub fn new(bases: Arc<Vec<G>>) -> Self {
+        let end = bases.len();
+        BaseRange {
+            bases,
+            start: 0,
+            end,
+        }
+    }
+
+    /// Splits the range into the first `mid` bases and the ones after them.
+    pub fn split_at(self, mid: usize) -> (Self, Self) {
+        let mid = cmp::min(self.start.saturating_add(mid), self.end);
+        let first = BaseRange {
+            end: mid,
+            ..self.clone()
+        };
+
+        (first, BaseRange { start: mid, ..self })
+    }
+
+    pub fn as_slice(&self) -> &[G] {
+        &self.bases[self.start..self.end]
+    }
+
+    /// Whether both ranges are of the same allocation.
+    pub fn shares_bases(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.bases, &other.bases)
+    }
+}
+
+/// [`get`](SourceBuilder::get) hands out the allocation with the start of the
+/// range, the end is up to the multiexp's exponents.
+impl<G: CurveAffine> SourceBuilder<G> for BaseRange<G> {
+    type Source = BaseRange<G>;
+
+    fn new(self) -> BaseRange<G> {
+        self
+    }
+
+    fn get(self) -> Result<(Arc<Vec<G>>, usize), SynthesisError> {
+        Ok((self.bases, self.start))
+    }
+
+    fn cheap_negation(&self) -> bool {
+        true
+    }
+
+    fn in_memory(&self) -> bool {
+        true
+    }
+}
+
+impl<G: CurveAffine> Source<G> for BaseRange<G> {
+    fn add_assign_mixed(
+        &mut self,
+        to: &mut <G as CurveAffine>::Projective,
+    ) -> Result<(), SynthesisError> {
+        let mut source = (&self.bases[..self.end], self.start);
+        source.add_assign_mixed(to)?;
+        self.start = source.1;
+
+        Ok(())
+    }
+
+    fn sub_assign_mixed(
+        &mut self,
+        to: &mut <G as CurveAffine>::Projective,
+    ) -> Result<(), SynthesisError> {
+        let mut source = (&self.bases[..self.end], self.start);
+        source.sub_assign_mixed(to)?;
+        self.start = source.1;
+
+        Ok(())
+    }
+
+    fn skip(&mut self, amt: usize) -> Result<(), SynthesisError> {
+        let mut source = (&self.bases[..self.end], self.start);
+        source.skip(amt)?;
+        self.start = source.1;
+
+        Ok(())
+    }
+}
+
+/// A source over borrowed bases, starting at the given index.
+impl<'a, G: CurveAffine> Source<G> for (&'a [G], usize) {
     fn add_assign_mixed(
         &mut self,
         to: &mut <G as CurveAffine>::Projective,
@@ -66,6 +259,31 @@ impl<G: CurveAffine> Source<G> for (Arc<Vec<G>>, usize) {
         Ok(())
     }
 
+    fn sub_assign_mixed(
+        &mut self,
+        to: &mut <G as CurveAffine>::Projective,
+    ) -> Result<(), SynthesisError> {
+        if self.0.len() <= self.1 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "expected more bases from source",
+            )
+            .into());
+        }
+
+        if self.0[self.1].is_zero() {
+            return Err(SynthesisError::UnexpectedIdentity);
+        }
+
+        let mut base = self.0[self.1];
+        base.negate();
+        to.add_assign_mixed(&base);
+
+        self.1 += 1;
+
+        Ok(())
+    }
+
     fn skip(&mut self, amt: usize) -> Result<(), SynthesisError> {
         if self.0.len() <= self.1 {
             return Err(io::Error::new(
@@ -75,235 +293,2334 @@ impl<G: CurveAffine> Source<G> for (Arc<Vec<G>>, usize) {
             .into());
         }
 
-        self.1 += amt;
+        self.1 += amt;
+
+        Ok(())
+    }
+}
+
+/// Wraps a source builder so that bases at infinity are skipped instead of
+/// failing with [`SynthesisError::UnexpectedIdentity`], for multiexps whose
+/// bases may well be the identity, unlike the queries of Groth16 parameters.
+#[derive(Clone)]
+pub struct SkipIdentity<S>(pub S);
+
+impl<G: CurveAffine, S: SourceBuilder<G>> SourceBuilder<G> for SkipIdentity<S> {
+    type Source = SkipIdentity<S::Source>;
+
+    fn new(self) -> SkipIdentity<S::Source> {
+        SkipIdentity(self.0.new())
+    }
+
+    fn get(self) -> Result<(Arc<Vec<G>>, usize), SynthesisError> {
+        self.0.get()
+    }
+
+    fn cheap_negation(&self) -> bool {
+        self.0.cheap_negation()
+    }
+
+    fn in_memory(&self) -> bool {
+        self.0.in_memory()
+    }
+
+    fn skip_identity(&self) -> bool {
+        true
+    }
+
+    fn fixed_base_tables(&self) -> Option<(Arc<FixedBaseTables<G>>, usize)> {
+        self.0.fixed_base_tables()
+    }
+}
+
+impl<G: CurveAffine, S: Source<G>> Source<G> for SkipIdentity<S> {
+    fn add_assign_mixed(
+        &mut self,
+        to: &mut <G as CurveAffine>::Projective,
+    ) -> Result<(), SynthesisError> {
+        match self.0.add_assign_mixed(to) {
+            Err(SynthesisError::UnexpectedIdentity) => self.0.skip(1),
+            result => result,
+        }
+    }
+
+    fn sub_assign_mixed(
+        &mut self,
+        to: &mut <G as CurveAffine>::Projective,
+    ) -> Result<(), SynthesisError> {
+        match self.0.sub_assign_mixed(to) {
+            Err(SynthesisError::UnexpectedIdentity) => self.0.skip(1),
+            result => result,
+        }
+    }
+
+    fn skip(&mut self, amt: usize) -> Result<(), SynthesisError> {
+        self.0.skip(amt)
+    }
+}
+
+/// How many bases a [`StreamSource`] reads and decodes at once.
+const STREAM_CHUNK_SIZE: usize = 1 << 10;
+
+/// Builds [`StreamSource`]s over readers of uncompressed bases, like the
+/// queries in Groth16 parameter files. Every region of a multiexp opens a
+/// reader of its own, so the bases never have to fit into memory at once.
+pub struct StreamSourceBuilder<R, G> {
+    open: Arc<dyn Fn() -> io::Result<R> + Send + Sync>,
+    len: usize,
+    checked: bool,
+    // The bases read by `get`, shared by the clones while they are in use.
+    loaded: Arc<Mutex<Weak<Vec<G>>>>,
+}
+
+impl<R, G> StreamSourceBuilder<R, G>
+where
+    R: Read + Seek + 'static,
+    G: CurveAffine,
+{
+    /// Reads `len` bases from the readers `open` returns, starting at their
+    /// current positions. The bases are checked to be in the subgroup if
+    /// `checked` is set.
+    pub fn new<F>(open: F, len: usize, checked: bool) -> Self
+    where
+        F: Fn() -> io::Result<R> + Send + Sync + 'static,
+    {
+        StreamSourceBuilder {
+            open: Arc::new(open),
+            len,
+            checked,
+            loaded: Arc::new(Mutex::new(Weak::new())),
+        }
+    }
+}
+
+impl<G: CurveAffine> StreamSourceBuilder<File, G> {
+    /// Reads `len` bases starting at byte `offset` of the file at `path`.
+    pub fn from_file<P: AsRef<Path>>(path: P, offset: u64, len: usize, checked: bool) -> Self {
+        let path = path.as_ref().to_path_buf();
+        Self::new(
+            move || {
+                let mut file = File::open(&path)?;
+                file.seek(SeekFrom::Start(offset))?;
+                Ok(file)
+            },
+            len,
+            checked,
+        )
+    }
+}
+
+impl<R, G> Clone for StreamSourceBuilder<R, G> {
+    fn clone(&self) -> Self {
+        StreamSourceBuilder {
+            open: self.open.clone(),
+            len: self.len,
+            checked: self.checked,
+            loaded: self.loaded.clone(),
+        }
+    }
+}
+
+impl<R, G> SourceBuilder<G> for StreamSourceBuilder<R, G>
+where
+    R: Read + Seek + 'static,
+    G: CurveAffine,
+{
+    type Source = StreamSource<R, G>;
+
+    fn new(self) -> StreamSource<R, G> {
+        StreamSource {
+            reader: (self.open)(),
+            remaining: self.len,
+            chunk: Vec::with_capacity(STREAM_CHUNK_SIZE),
+            next: 0,
+            bytes: vec![],
+            checked: self.checked,
+        }
+    }
+
+    /// Reads all bases into memory, which GPU multiexps need, and fails if
+    /// they can't be read. Clones of the builder share them while they are in
+    /// use instead of reading them again.
+    fn get(self) -> Result<(Arc<Vec<G>>, usize), SynthesisError> {
+        let loaded = self.loaded.clone();
+        let mut loaded = loaded
+            .lock()
+            .expect("the bases were read by a panicking thread");
+        if let Some(bases) = loaded.upgrade() {
+            return Ok((bases, 0));
+        }
+
+        let len = self.len;
+        let mut source = SourceBuilder::new(self);
+        let bases = Arc::new(
+            (0..len)
+                .map(|_| source.next_point())
+                .collect::<Result<Vec<_>, _>>()?,
+        );
+        *loaded = Arc::downgrade(&bases);
+
+        Ok((bases, 0))
+    }
+
+    fn cheap_negation(&self) -> bool {
+        true
+    }
+}
+
+/// A source that reads and decodes the bases chunk by chunk.
+pub struct StreamSource<R, G> {
+    reader: io::Result<R>,
+    /// The bases that are left in the reader.
+    remaining: usize,
+    chunk: Vec<G>,
+    next: usize,
+    bytes: Vec<u8>,
+    checked: bool,
+}
+
+impl<R: Read + Seek, G: CurveAffine> StreamSource<R, G> {
+    fn reader(reader: &mut io::Result<R>) -> io::Result<&mut R> {
+        reader
+            .as_mut()
+            .map_err(|e| io::Error::new(e.kind(), e.to_string()))
+    }
+
+    fn read_chunk(&mut self) -> Result<(), SynthesisError> {
+        let n = self.remaining.min(STREAM_CHUNK_SIZE);
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "expected more bases from source",
+            )
+            .into());
+        }
+
+        let size = G::Uncompressed::size();
+        self.bytes.resize(n * size, 0);
+        Self::reader(&mut self.reader)?.read_exact(&mut self.bytes)?;
+
+        self.chunk.clear();
+        for bytes in self.bytes.chunks(size) {
+            let mut repr = G::Uncompressed::empty();
+            repr.as_mut().copy_from_slice(bytes);
+            let base = if self.checked {
+                repr.into_affine()
+            } else {
+                repr.into_affine_unchecked()
+            }
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            self.chunk.push(base);
+        }
+        self.next = 0;
+        self.remaining -= n;
+
+        Ok(())
+    }
+
+    fn next_point(&mut self) -> Result<G, SynthesisError> {
+        if self.next == self.chunk.len() {
+            self.read_chunk()?;
+        }
+        self.next += 1;
+
+        Ok(self.chunk[self.next - 1])
+    }
+
+    fn next_base(&mut self) -> Result<G, SynthesisError> {
+        if self.next == self.chunk.len() {
+            self.read_chunk()?;
+        }
+        if self.chunk[self.next].is_zero() {
+            return Err(SynthesisError::UnexpectedIdentity);
+        }
+
+        self.next_point()
+    }
+}
+
+impl<R: Read + Seek, G: CurveAffine> Source<G> for StreamSource<R, G> {
+    fn add_assign_mixed(
+        &mut self,
+        to: &mut <G as CurveAffine>::Projective,
+    ) -> Result<(), SynthesisError> {
+        to.add_assign_mixed(&self.next_base()?);
+
+        Ok(())
+    }
+
+    fn sub_assign_mixed(
+        &mut self,
+        to: &mut <G as CurveAffine>::Projective,
+    ) -> Result<(), SynthesisError> {
+        let mut base = self.next_base()?;
+        base.negate();
+        to.add_assign_mixed(&base);
+
+        Ok(())
+    }
+
+    fn skip(&mut self, amt: usize) -> Result<(), SynthesisError> {
+        // Skip within the current chunk first, seek past the rest.
+        let in_chunk = (self.chunk.len() - self.next).min(amt);
+        self.next += in_chunk;
+
+        let rest = amt - in_chunk;
+        if rest > 0 {
+            if rest > self.remaining {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "expected more bases from source",
+                )
+                .into());
+            }
+            let bytes = rest * G::Uncompressed::size();
+            Self::reader(&mut self.reader)?.seek(SeekFrom::Current(bytes as i64))?;
+            self.remaining -= rest;
+        }
+
+        Ok(())
+    }
+}
+
+pub trait QueryDensity {
+    /// Returns whether the base exists.
+    type Iter: Iterator<Item = bool>;
+
+    fn iter(self) -> Self::Iter;
+    fn get_query_size(self) -> Option<usize>;
+}
+
+#[derive(Clone)]
+pub struct FullDensity;
+
+impl AsRef<FullDensity> for FullDensity {
+    fn as_ref(&self) -> &FullDensity {
+        self
+    }
+}
+
+impl<'a> QueryDensity for &'a FullDensity {
+    type Iter = iter::Repeat<bool>;
+
+    fn iter(self) -> Self::Iter {
+        iter::repeat(true)
+    }
+
+    fn get_query_size(self) -> Option<usize> {
+        None
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct DensityTracker {
+    bv: BitVec,
+    total_density: usize,
+}
+
+impl<'a> QueryDensity for &'a DensityTracker {
+    type Iter = bit_vec::Iter<'a>;
+
+    fn iter(self) -> Self::Iter {
+        self.bv.iter()
+    }
+
+    fn get_query_size(self) -> Option<usize> {
+        Some(self.bv.len())
+    }
+}
+
+impl DensityTracker {
+    pub fn new() -> DensityTracker {
+        DensityTracker {
+            bv: BitVec::new(),
+            total_density: 0,
+        }
+    }
+
+    pub fn add_element(&mut self) {
+        self.bv.push(false);
+    }
+
+    pub fn inc(&mut self, idx: usize) {
+        if !self.bv.get(idx).unwrap() {
+            self.bv.set(idx, true);
+            self.total_density += 1;
+        }
+    }
+
+    pub fn get_total_density(&self) -> usize {
+        self.total_density
+    }
+
+    /// Removes all elements, keeping the allocated capacity.
+    pub fn clear(&mut self) {
+        self.bv.truncate(0);
+        self.total_density = 0;
+    }
+
+    /// The number of tracked elements.
+    pub fn len(&self) -> usize {
+        self.bv.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bv.is_empty()
+    }
+
+    /// Whether the element with the given index is used.
+    pub fn get(&self, idx: usize) -> bool {
+        self.bv.get(idx).unwrap()
+    }
+
+    /// The flags of the elements, eight per byte with the first element in the
+    /// most significant bit.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.bv.to_bytes()
+    }
+
+    /// A tracker of `len` elements with the flags of [`DensityTracker::to_bytes`].
+    pub fn from_bytes(bytes: &[u8], len: usize) -> DensityTracker {
+        let mut bv = BitVec::from_bytes(bytes);
+        bv.truncate(len);
+        let total_density = bv.iter().filter(|&used| used).count();
+
+        DensityTracker { bv, total_density }
+    }
+
+    /// Appends the elements of a tracker of a separately synthesized part of a
+    /// circuit. For input densities, the first element of both trackers is the
+    /// shared `ONE` input, so they are combined instead of appended.
+    pub fn extend(&mut self, other: &DensityTracker, is_input_density: bool) {
+        if other.bv.is_empty() {
+            return;
+        }
+
+        let mut bits = other.bv.iter();
+        let mut total_density = other.total_density;
+        if is_input_density && !self.bv.is_empty() {
+            let one_is_used = bits.next() == Some(true);
+            if one_is_used {
+                total_density -= 1;
+                self.inc(0);
+            }
+        }
+
+        self.bv.extend(bits);
+        self.total_density += total_density;
+    }
+
+    /// Marks every element as used that is used in `other`, which tracks the
+    /// same elements, e.g. the queries of another part of the same circuit.
+    pub fn union(&mut self, other: &DensityTracker) {
+        assert_eq!(
+            self.bv.len(),
+            other.bv.len(),
+            "different number of elements"
+        );
+
+        for (idx, used) in other.bv.iter().enumerate() {
+            if used {
+                self.inc(idx);
+            }
+        }
+    }
+}
+
+/// The buckets of a single region of a CPU multiexp are kept below this size,
+/// so that they fit into the caches.
+const MAX_BUCKETS_BYTES: usize = 1 << 22;
+
+/// The window size of a CPU multiexp of `num_exps` exponents. Every window is a
+/// region of its own, which sorts all bases into `2^c - 1` buckets and sums
+/// them up. The window size with the fewest additions in total is chosen, as
+/// long as the buckets fit into the caches.
+pub fn window_size<G: CurveAffine>(num_exps: usize) -> u32 {
+    if num_exps < 32 {
+        return 3;
+    }
+
+    let num_bits = <G::Engine as ScalarEngine>::Fr::NUM_BITS as usize;
+    let max_buckets = MAX_BUCKETS_BYTES / mem::size_of::<G::Projective>();
+    let max_c = (mem::size_of::<usize>() * 8) as u32 - 1 - max_buckets.leading_zeros();
+
+    let cost = |c: u32| {
+        let regions = (num_bits + c as usize - 1) / c as usize;
+        regions * (num_exps + (2 << c))
+    };
+    (3..=max_c.max(3)).min_by_key(|&c| cost(c)).unwrap()
+}
+
+/// The number of signed windows of `c` bits for exponents of `num_bits` bits.
+/// With digits from `-2^(c-1)` up to `2^(c-1) - 1`, the windows need to cover
+/// two more bits.
+fn signed_windows(num_bits: usize, c: u32) -> usize {
+    (num_bits + 2 + c as usize - 1) / c as usize
+}
+
+/// The offset that turns the unsigned windows of `exp + offset` into the
+/// signed windows of `exp`: the signed digit of a window is its unsigned one
+/// minus `2^(c-1)`.
+fn signed_offset(num_limbs: usize, num_windows: usize, c: u32) -> Vec<u64> {
+    let mut offset = vec![0u64; num_limbs + 1];
+    for w in 0..num_windows {
+        let bit = w * c as usize + c as usize - 1;
+        offset[bit / 64] |= 1 << (bit % 64);
+    }
+
+    offset
+}
+
+/// The signed digit of the window of `c` bits starting at bit `start`.
+fn signed_digit(limbs: &[u64], offset: &[u64], start: usize, c: u32) -> i64 {
+    // Only the limbs up to the window itself matter.
+    let last = (start + c as usize - 1) / 64;
+    let mut sum = [0u64; 8];
+    let mut carry = false;
+    for (i, sum) in sum.iter_mut().enumerate().take(last + 1) {
+        let limb = limbs.get(i).cloned().unwrap_or(0);
+        let (s, c1) = limb.overflowing_add(offset[i]);
+        let (s, c2) = s.overflowing_add(carry as u64);
+        *sum = s;
+        carry = c1 || c2;
+    }
+
+    window_digit(&sum[..=last], start, c) as i64 - (1 << (c - 1))
+}
+
+/// The coordinates of an affine point, which are read from and written to the
+/// uncompressed encoding of the G1 points of the backend. Other points fall
+/// back to projective buckets, see [`affine_buckets_supported`].
+#[derive(Clone, Copy)]
+struct Affine<F> {
+    x: F,
+    y: F,
+    infinity: bool,
+}
+
+impl<F: Field> Affine<F> {
+    /// The coordinates of `point`, none unless it's a G1 point of the backend.
+    fn of<G: CurveAffine<Base = F>>(point: &G) -> Option<Self> {
+        let point = (point as &dyn Any).downcast_ref::<G1Affine>()?;
+        (&g1_coordinates(point) as &dyn Any)
+            .downcast_ref::<Self>()
+            .cloned()
+    }
+
+    /// The point of these coordinates, none unless `G` is the G1 of the
+    /// backend.
+    fn into_point<G: CurveAffine<Base = F>>(self) -> Option<G> {
+        let affine = (&self as &dyn Any).downcast_ref::<Affine<Fq>>()?;
+        (&g1_point(affine) as &dyn Any).downcast_ref::<G>().cloned()
+    }
+
+    fn zero() -> Self {
+        Affine {
+            x: F::zero(),
+            y: F::one(),
+            infinity: true,
+        }
+    }
+
+    /// The denominator of the slope of `self + other`, none if the sum is
+    /// trivial.
+    fn denominator(&self, other: &Self) -> Option<F> {
+        if self.infinity {
+            None
+        } else if self.x == other.x {
+            if self.y == other.y {
+                let mut den = self.y;
+                den.double();
+                Some(den)
+            } else {
+                None
+            }
+        } else {
+            let mut den = other.x;
+            den.sub_assign(&self.x);
+            Some(den)
+        }
+    }
+
+    /// Adds `other` given the inverse of the denominator of the slope.
+    fn add_assign(&mut self, other: &Self, den_inv: &F) {
+        let mut lambda = if self.x == other.x {
+            // A doubling on `y^2 = x^3 + b`
+            let mut x2 = self.x;
+            x2.square();
+            let mut lambda = x2;
+            lambda.double();
+            lambda.add_assign(&x2);
+            lambda
+        } else {
+            let mut lambda = other.y;
+            lambda.sub_assign(&self.y);
+            lambda
+        };
+        lambda.mul_assign(den_inv);
+
+        let mut x = lambda;
+        x.square();
+        x.sub_assign(&self.x);
+        x.sub_assign(&other.x);
+
+        let mut y = self.x;
+        y.sub_assign(&x);
+        y.mul_assign(&lambda);
+        y.sub_assign(&self.y);
+
+        self.x = x;
+        self.y = y;
+    }
+}
+
+/// How many additions to affine buckets share a single inversion.
+const AFFINE_BATCH_SIZE: usize = 1 << 10;
+
+/// The smallest window for which buckets are kept in affine coordinates, with
+/// fewer buckets the batches are too small.
+const AFFINE_MIN_WINDOW: u32 = 10;
+
+/// Buckets in affine coordinates. The additions to them are collected in
+/// batches whose slopes share a single inversion, with Montgomery's trick. A
+/// base for a bucket that is already part of the batch is added in projective
+/// coordinates instead, so only the sums of the buckets are converted back
+/// from their coordinates.
+struct AffineBuckets<G: CurveAffine> {
+    buckets: Vec<Affine<G::Base>>,
+    conflicts: Vec<G::Projective>,
+    busy: Vec<bool>,
+    batch: Vec<(usize, Affine<G::Base>)>,
+    batch_size: usize,
+    products: Vec<G::Base>,
+}
+
+impl<G: CurveAffine> AffineBuckets<G> {
+    fn new(num_buckets: usize, batch_size: usize) -> Self {
+        AffineBuckets {
+            buckets: vec![Affine::zero(); num_buckets],
+            conflicts: vec![G::Projective::zero(); num_buckets],
+            busy: vec![false; num_buckets],
+            batch: Vec::with_capacity(batch_size),
+            batch_size,
+            products: Vec::with_capacity(batch_size),
+        }
+    }
+
+    /// Adds `base`, whose coordinates are `point`, to `bucket`, subtracts it
+    /// if `negate` is set.
+    fn add(&mut self, bucket: usize, base: &G, point: &Affine<G::Base>, negate: bool) {
+        if self.busy[bucket] {
+            let mut base = *base;
+            if negate {
+                base.negate();
+            }
+            self.conflicts[bucket].add_assign_mixed(&base);
+            return;
+        }
+
+        let mut point = *point;
+        if negate {
+            point.y.negate();
+        }
+        self.busy[bucket] = true;
+        self.batch.push((bucket, point));
+        if self.batch.len() == self.batch_size {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        let mut product = G::Base::one();
+        self.products.clear();
+        for (bucket, point) in &self.batch {
+            self.products.push(product);
+            if let Some(den) = self.buckets[*bucket].denominator(point) {
+                product.mul_assign(&den);
+            }
+        }
+
+        // Points on a curve of prime order never have `y = 0`, so none of the
+        // denominators is zero.
+        let mut inverse = product.inverse().expect("denominators are never zero");
+        for (i, (bucket, point)) in self.batch.iter().enumerate().rev() {
+            let sum = &mut self.buckets[*bucket];
+            if let Some(den) = sum.denominator(point) {
+                let mut den_inv = inverse;
+                den_inv.mul_assign(&self.products[i]);
+                inverse.mul_assign(&den);
+                sum.add_assign(point, &den_inv);
+            } else if sum.infinity {
+                *sum = *point;
+            } else {
+                *sum = Affine::zero();
+            }
+            self.busy[*bucket] = false;
+        }
+        self.batch.clear();
+    }
+
+    fn finish(mut self) -> Vec<G::Projective> {
+        self.flush();
+
+        self.buckets
+            .into_iter()
+            .zip(self.conflicts.into_iter())
+            .map(|(bucket, mut sum)| {
+                let bucket = bucket
+                    .into_point::<G>()
+                    .expect("affine buckets are only used for G1");
+                sum.add_assign_mixed(&bucket);
+                sum
+            })
+            .collect()
+    }
+}
+
+/// Reads the coordinates of a G1 point from its uncompressed encoding, whose
+/// flags are all unset for points other than the one at infinity.
+fn g1_coordinates(point: &G1Affine) -> Affine<Fq> {
+    if point.is_zero() {
+        return Affine::zero();
+    }
+
+    let encoded = point.into_uncompressed();
+    let (x, y) = encoded.as_ref().split_at(encoded.as_ref().len() / 2);
+    let coordinate = |bytes: &[u8]| {
+        let mut repr = FqRepr::default();
+        repr.read_be(bytes)
+            .expect("the coordinates fit into FqRepr");
+        Fq::from_repr(repr).expect("the coordinates are in Fq")
+    };
+
+    Affine {
+        x: coordinate(x),
+        y: coordinate(y),
+        infinity: false,
+    }
+}
+
+/// Writes the coordinates of a G1 point into its uncompressed encoding.
+fn g1_point(affine: &Affine<Fq>) -> G1Affine {
+    if affine.infinity {
+        return G1Affine::zero();
+    }
+
+    let mut encoded = G1Uncompressed::empty();
+    let half = encoded.as_ref().len() / 2;
+    let (x, y) = encoded.as_mut().split_at_mut(half);
+    let write = |coordinate: Fq, bytes: &mut [u8]| {
+        let repr = coordinate.into_repr();
+        repr.write_be(bytes).expect("the coordinates fit");
+    };
+    write(affine.x, x);
+    write(affine.y, y);
+
+    encoded
+        .into_affine_unchecked()
+        .expect("the sums of points are on the curve")
+}
+
+/// Whether `G` is the G1 of the backend, whose coordinates [`Affine`] reads and
+/// writes, and the curve has the form the affine additions assume, checked by
+/// doubling the generator.
+fn affine_buckets_supported<G: CurveAffine>() -> bool {
+    if TypeId::of::<G>() != TypeId::of::<G1Affine>() {
+        return false;
+    }
+
+    let one = G::one();
+    let point = match Affine::of(&one) {
+        Some(point) => point,
+        None => return false,
+    };
+    let mut buckets = AffineBuckets::<G>::new(1, 1);
+    buckets.add(0, &one, &point, false);
+    buckets.add(0, &one, &point, false);
+    let mut two = one.into_projective();
+    two.double();
+
+    buckets.finish() == vec![two]
+}
+
+/// The coordinates of `bases` for affine buckets, converted once for all the
+/// windows of a multiexp. Only called once [`affine_buckets_supported`] holds.
+fn affine_coordinates<G: CurveAffine>(pool: &Worker, bases: &[G]) -> Vec<Affine<G::Base>> {
+    let mut coordinates = vec![Affine::zero(); bases.len()];
+    pool.scope(bases.len(), |scope, chunk| {
+        for (bases, coordinates) in bases.chunks(chunk).zip(coordinates.chunks_mut(chunk)) {
+            scope.spawn(move |_| {
+                for (base, coordinates) in bases.iter().zip(coordinates.iter_mut()) {
+                    *coordinates = Affine::of(base).expect("affine buckets are only used for G1");
+                }
+            });
+        }
+    });
+
+    coordinates
+}
+
+/// The buckets of a region, either summed up with mixed additions from a
+/// source or in affine coordinates straight from the bases in memory and their
+/// coordinates, see [`affine_coordinates`].
+enum Buckets<'b, G: CurveAffine, S> {
+    Projective {
+        source: S,
+        buckets: Vec<G::Projective>,
+    },
+    Affine {
+        bases: &'b [G],
+        coordinates: &'b [Affine<G::Base>],
+        next: usize,
+        skip_identity: bool,
+        buckets: AffineBuckets<G>,
+    },
+}
+
+impl<'b, G: CurveAffine, S: Source<G>> Buckets<'b, G, S> {
+    fn projective(source: S, num_buckets: usize) -> Self {
+        Buckets::Projective {
+            source,
+            buckets: vec![G::Projective::zero(); num_buckets],
+        }
+    }
+
+    fn affine(
+        bases: &'b [G],
+        coordinates: &'b [Affine<G::Base>],
+        num_buckets: usize,
+        skip_identity: bool,
+    ) -> Self {
+        let batch_size = (num_buckets / 8).min(AFFINE_BATCH_SIZE);
+        Buckets::Affine {
+            bases,
+            coordinates,
+            next: 0,
+            skip_identity,
+            buckets: AffineBuckets::new(num_buckets, batch_size),
+        }
+    }
+
+    /// The index of the next base, none if it's at infinity and may be
+    /// skipped.
+    fn next_base(
+        bases: &[G],
+        next: &mut usize,
+        skip_identity: bool,
+    ) -> Result<Option<usize>, SynthesisError> {
+        let index = *next;
+        let base = bases.get(index).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "expected more bases from source",
+            )
+        })?;
+        if base.is_zero() && !skip_identity {
+            return Err(SynthesisError::UnexpectedIdentity);
+        }
+        *next += 1;
+
+        Ok(Some(index).filter(|_| !base.is_zero()))
+    }
+
+    fn skip(&mut self) -> Result<(), SynthesisError> {
+        match self {
+            Buckets::Projective { source, .. } => source.skip(1),
+            Buckets::Affine { next, .. } => {
+                *next += 1;
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds the next base straight to `acc`.
+    fn add_to(&mut self, acc: &mut G::Projective) -> Result<(), SynthesisError> {
+        match self {
+            Buckets::Projective { source, .. } => source.add_assign_mixed(acc),
+            Buckets::Affine {
+                bases,
+                next,
+                skip_identity,
+                ..
+            } => {
+                if let Some(i) = Self::next_base(bases, next, *skip_identity)? {
+                    acc.add_assign_mixed(&bases[i]);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds the next base to the bucket of the nonzero `digit`, subtracts it
+    /// for a negative one.
+    fn add(&mut self, digit: i64) -> Result<(), SynthesisError> {
+        let bucket = (digit.abs() - 1) as usize;
+        match self {
+            Buckets::Projective { source, buckets } => {
+                if digit > 0 {
+                    source.add_assign_mixed(&mut buckets[bucket])
+                } else {
+                    source.sub_assign_mixed(&mut buckets[bucket])
+                }
+            }
+            Buckets::Affine {
+                bases,
+                coordinates,
+                next,
+                skip_identity,
+                buckets,
+            } => {
+                if let Some(i) = Self::next_base(bases, next, *skip_identity)? {
+                    buckets.add(bucket, &bases[i], &coordinates[i], digit < 0);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(self) -> Vec<G::Projective> {
+        match self {
+            Buckets::Projective { buckets, .. } => buckets,
+            Buckets::Affine { buckets, .. } => buckets.finish(),
+        }
+    }
+}
+
+/// How many exponents a CPU multiexp processes between checks for cancellation.
+const CANCEL_CHECK_INTERVAL: usize = 1 << 12;
+
+/// The layout of the windows of a CPU multiexp.
+#[derive(Clone)]
+struct Windows {
+    c: u32,
+    /// Whether the digits are signed, see [`signed_digit`].
+    signed: bool,
+    offset: Vec<u64>,
+    /// The bit the windows end at.
+    end: usize,
+    num_buckets: usize,
+}
+
+impl Windows {
+    fn new<G: CurveAffine>(c: u32, num_limbs: usize, signed: bool) -> Self {
+        let num_bits = <G::Engine as ScalarEngine>::Fr::NUM_BITS as usize;
+        if signed {
+            let num_windows = signed_windows(num_bits, c);
+            Windows {
+                c,
+                signed,
+                offset: signed_offset(num_limbs, num_windows, c),
+                end: num_windows * c as usize,
+                num_buckets: 1 << (c - 1),
+            }
+        } else {
+            Windows {
+                c,
+                signed,
+                offset: vec![],
+                end: num_bits,
+                num_buckets: (1 << c) - 1,
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        (self.end + self.c as usize - 1) / self.c as usize
+    }
+
+    /// The digit of `exp` in the window starting at bit `skip`.
+    fn digit<R: PrimeFieldRepr>(&self, exp: R, skip: u32) -> i64 {
+        if self.signed {
+            signed_digit(exp.as_ref(), &self.offset, skip as usize, self.c)
+        } else {
+            let mut exp = exp;
+            exp.shr(skip);
+            (exp.as_ref()[0] % (1 << self.c)) as i64
+        }
+    }
+}
+
+/// Computes a single region of a CPU multiexp, the window starting at bit
+/// `skip`. Exponents of one are added to the result directly if
+/// `handle_trivial` is set, they are part of the lowest window only.
+#[allow(clippy::too_many_arguments)]
+fn multiexp_region<G, S, I>(
+    mut buckets: Buckets<'_, G, S>,
+    exponents: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    density: I,
+    skip: u32,
+    windows: &Windows,
+    handle_trivial: bool,
+    cancel: Option<&CancellationToken>,
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+where
+    G: CurveAffine,
+    S: Source<G>,
+    I: Iterator<Item = bool>,
+{
+    // Accumulate the result
+    let mut acc = G::Projective::zero();
+
+    let zero = <G::Engine as ScalarEngine>::Fr::zero().into_repr();
+    let one = <G::Engine as ScalarEngine>::Fr::one().into_repr();
+
+    // Sort the bases into buckets
+    for (i, (&exp, density)) in exponents.iter().zip(density).enumerate() {
+        if i % CANCEL_CHECK_INTERVAL == 0 {
+            if let Some(cancel) = cancel {
+                cancel.check()?;
+            }
+        }
+        if density {
+            if exp == zero {
+                buckets.skip()?;
+            } else if exp == one {
+                if handle_trivial {
+                    buckets.add_to(&mut acc)?;
+                } else {
+                    buckets.skip()?;
+                }
+            } else {
+                let digit = windows.digit(exp, skip);
+
+                if digit != 0 {
+                    buckets.add(digit)?;
+                } else {
+                    buckets.skip()?;
+                }
+            }
+        }
+    }
+
+    // Summation by parts
+    // e.g. 3a + 2b + 1c = a +
+    //                    (a) + b +
+    //                    ((a) + b) + c
+    let mut running_sum = G::Projective::zero();
+    for exp in buckets.finish().into_iter().rev() {
+        running_sum.add_assign(&exp);
+        acc.add_assign(&running_sum);
+    }
+
+    Ok(acc)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn multiexp_inner<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    mut skip: u32,
+    c: u32,
+    handle_trivial: bool,
+    cancel: Option<CancellationToken>,
+    coordinates: Option<Arc<Vec<Affine<G::Base>>>>,
+) -> WorkerFuture<Result<<G as CurveAffine>::Projective, SynthesisError>>
+where
+    for<'a> &'a Q: QueryDensity,
+    D: Send + Sync + 'static + Clone + AsRef<Q>,
+    G: CurveAffine,
+    S: SourceBuilder<G>,
+{
+    // Signed windows halve the buckets, the subtraction of a base is as cheap
+    // as its addition for most sources.
+    let num_limbs = exponents
+        .first()
+        .cloned()
+        .unwrap_or_default()
+        .as_ref()
+        .len();
+    let windows = Windows::new::<G>(c, num_limbs, c >= 2 && bases.cheap_negation());
+    let skip_identity = bases.skip_identity();
+
+    // Perform this region of the multiexp
+    let this = {
+        let bases = bases.clone();
+        let exponents = exponents.clone();
+        let density_map = density_map.clone();
+        let cancel = cancel.clone();
+        let windows = windows.clone();
+        let coordinates = coordinates.clone();
+
+        pool.compute(move || {
+            // Create space for the buckets, with a source for the bases or
+            // the bases themselves
+            let in_memory;
+            let buckets = match coordinates {
+                Some(ref coordinates) => {
+                    in_memory = bases.get()?;
+                    Buckets::affine(
+                        in_memory.0.get(in_memory.1..).unwrap_or(&[]),
+                        coordinates,
+                        windows.num_buckets,
+                        skip_identity,
+                    )
+                }
+                None => Buckets::projective(bases.new(), windows.num_buckets),
+            };
+
+            multiexp_region(
+                buckets,
+                &exponents,
+                density_map.as_ref().iter(),
+                skip,
+                &windows,
+                handle_trivial,
+                cancel.as_ref(),
+            )
+        })
+    };
+
+    skip += c;
+
+    if skip as usize >= windows.end {
+        // There isn't another region.
+        this
+    } else {
+        // There's another region more significant. Calculate and join it with
+        // this region recursively.
+        let higher = multiexp_inner(
+            pool,
+            bases,
+            density_map,
+            exponents,
+            skip,
+            c,
+            false,
+            cancel,
+            coordinates,
+        );
+
+        WorkerFuture::new(async move {
+            let this = this.await?;
+            let mut higher = higher.await?;
+
+            for _ in 0..c {
+                higher.double();
+            }
+
+            higher.add_assign(&this);
+
+            Ok(higher)
+        })
+    }
+}
+
+/// Perform multi-exponentiation. The caller is responsible for ensuring the
+/// query size is the same as the number of exponents.
+pub fn multiexp<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    kern: &mut Option<gpu::MultiexpKernel<G::Engine>>,
+) -> WorkerFuture<Result<<G as CurveAffine>::Projective, SynthesisError>>
+where
+    for<'a> &'a Q: QueryDensity,
+    D: Send + Sync + 'static + Clone + AsRef<Q>,
+    G: CurveAffine,
+    G::Engine: crate::bls::Engine,
+    S: SourceBuilder<G>,
+{
+    multiexp_with_cancel(pool, bases, density_map, exponents, kern, None, None)
+}
+
+/// Perform multi-exponentiation like [`multiexp`], with windows of `c` bits
+/// on the CPU instead of the ones chosen by [`window_size`].
+pub fn multiexp_with_window_size<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    kern: &mut Option<gpu::MultiexpKernel<G::Engine>>,
+    c: u32,
+) -> WorkerFuture<Result<<G as CurveAffine>::Projective, SynthesisError>>
+where
+    for<'a> &'a Q: QueryDensity,
+    D: Send + Sync + 'static + Clone + AsRef<Q>,
+    G: CurveAffine,
+    G::Engine: crate::bls::Engine,
+    S: SourceBuilder<G>,
+{
+    multiexp_with_cancel(pool, bases, density_map, exponents, kern, None, Some(c))
+}
+
+/// Perform multi-exponentiation like [`multiexp`], which fails with
+/// [`SynthesisError::Cancelled`] once `cancel` is cancelled. A multiexp on the
+/// GPU can only be cancelled before it starts.
+pub fn multiexp_cancellable<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    kern: &mut Option<gpu::MultiexpKernel<G::Engine>>,
+    cancel: &CancellationToken,
+) -> WorkerFuture<Result<<G as CurveAffine>::Projective, SynthesisError>>
+where
+    for<'a> &'a Q: QueryDensity,
+    D: Send + Sync + 'static + Clone + AsRef<Q>,
+    G: CurveAffine,
+    G::Engine: crate::bls::Engine,
+    S: SourceBuilder<G>,
+{
+    multiexp_with_cancel(
+        pool,
+        bases,
+        density_map,
+        exponents,
+        kern,
+        Some(cancel.clone()),
+        None,
+    )
+}
+
+/// Perform multi-exponentiation, which can be cancelled with `cancel` and
+/// uses windows of `window` bits on the CPU if it's set.
+pub(crate) fn multiexp_with_cancel<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    kern: &mut Option<gpu::MultiexpKernel<G::Engine>>,
+    cancel: Option<CancellationToken>,
+    window: Option<u32>,
+) -> WorkerFuture<Result<<G as CurveAffine>::Projective, SynthesisError>>
+where
+    for<'a> &'a Q: QueryDensity,
+    D: Send + Sync + 'static + Clone + AsRef<Q>,
+    G: CurveAffine,
+    G::Engine: crate::bls::Engine,
+    S: SourceBuilder<G>,
+{
+    if let Some(ref cancel) = cancel {
+        if let Err(e) = cancel.check() {
+            return pool.compute(move || Err(e));
+        }
+    }
+
+    // A few dense bases, e.g. of the inputs in the B query, are neither worth
+    // the transfers to the GPU nor the buckets of a whole window per region.
+    let num_dense = density_map
+        .as_ref()
+        .iter()
+        .take(exponents.len())
+        .filter(|&d| d)
+        .count();
+    let sparse = num_dense <= SPARSE_MAX_EXPS;
+
+    if let Some(ref mut k) = kern {
+        if !sparse {
+            // The GPU takes the exponents of the dense bases only.
+            let exps = dense_exponents(exponents.clone(), density_map.as_ref());
+            let n = exps.len();
+
+            let (bss, skip) = match bases.clone().get() {
+                Ok(bases) => bases,
+                Err(e) => return pool.compute(move || Err(e)),
+            };
+            match k.multiexp(bss, exps, skip, n) {
+                // The kernel gave the GPU up, this and the remaining multiexps run on the CPU.
+                Err(ref e) if e.is_preempted() => {
+                    *kern = None;
+                }
+                result => {
+                    return pool.compute(move || result.map_err(SynthesisError::from));
+                }
+            }
+        }
+    }
+
+    if let Some((tables, start)) = bases.fixed_base_tables() {
+        return fixed_base_multiexp(pool, tables, start, density_map, exponents, cancel);
+    }
+
+    if let Some(query_size) = density_map.as_ref().get_query_size() {
+        // If the density map has a known query size, it should not be
+        // inconsistent with the number of exponents.
+
+        assert!(query_size == exponents.len());
+    }
+
+    if sparse {
+        return pool.compute(move || {
+            sparse_multiexp(bases.new(), &exponents, density_map.as_ref().iter())
+        });
+    }
+
+    let c = window.unwrap_or_else(|| window_size::<G>(exponents.len()));
+
+    // Affine buckets read the coordinates of the bases in memory, converted
+    // once here instead of again in every window.
+    let affine = c >= AFFINE_MIN_WINDOW && bases.in_memory() && affine_buckets_supported::<G>();
+    let coordinates = if affine {
+        let (bss, skip) = match bases.clone().get() {
+            Ok(bases) => bases,
+            Err(e) => return pool.compute(move || Err(e)),
+        };
+        let end = bss.len().min(skip + exponents.len());
+        let bss = bss.get(skip..end).unwrap_or(&[]);
+        Some(Arc::new(affine_coordinates(pool, bss)))
+    } else {
+        None
+    };
+
+    multiexp_inner(
+        pool,
+        bases,
+        density_map,
+        exponents,
+        0,
+        c,
+        true,
+        cancel,
+        coordinates,
+    )
+}
+
+/// Multiexps with at most this many dense bases are computed by
+/// [`sparse_multiexp`].
+const SPARSE_MAX_EXPS: usize = 32;
+
+/// The window size of [`sparse_multiexp`].
+const SPARSE_WINDOW: u32 = 4;
+
+/// Computes a multiexp of a few dense bases with Straus' algorithm: the
+/// multiples of every base within a window are precomputed and the doublings
+/// between the windows are shared by all of them. Unlike the buckets, which
+/// take `2^c` additions per region no matter how few bases there are, it
+/// takes a table of `2^SPARSE_WINDOW - 1` multiples per base.
+fn sparse_multiexp<G, S, I>(
+    mut bases: S,
+    exponents: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    density: I,
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+where
+    G: CurveAffine,
+    S: Source<G>,
+    I: Iterator<Item = bool>,
+{
+    let zero = <G::Engine as ScalarEngine>::Fr::zero().into_repr();
+
+    let mut tables = vec![];
+    let mut exps = vec![];
+    for (&exp, density) in exponents.iter().zip(density) {
+        if !density {
+            continue;
+        }
+        if exp == zero {
+            bases.skip(1)?;
+            continue;
+        }
+
+        let mut base = G::Projective::zero();
+        bases.add_assign_mixed(&mut base)?;
+        let mut table = Vec::with_capacity((1 << SPARSE_WINDOW) - 1);
+        let mut multiple = base;
+        for _ in 1..(1 << SPARSE_WINDOW) {
+            table.push(multiple);
+            multiple.add_assign(&base);
+        }
+        tables.push(table);
+        exps.push(exp);
+    }
+
+    let num_bits = <G::Engine as ScalarEngine>::Fr::NUM_BITS as usize;
+    let num_windows = (num_bits + SPARSE_WINDOW as usize - 1) / SPARSE_WINDOW as usize;
+    let mut acc = G::Projective::zero();
+    for w in (0..num_windows).rev() {
+        for _ in 0..SPARSE_WINDOW {
+            acc.double();
+        }
+        for (table, exp) in tables.iter().zip(exps.iter()) {
+            let digit = window_digit(exp.as_ref(), w * SPARSE_WINDOW as usize, SPARSE_WINDOW);
+            if digit != 0 {
+                acc.add_assign(&table[digit - 1]);
+            }
+        }
+    }
+
+    Ok(acc)
+}
+
+/// Looks up the multiples of the dense bases in `tables`, from the `start`th
+/// base on, with every thread taking a chunk of the exponents.
+fn fixed_base_multiexp<Q, D, G>(
+    pool: &Worker,
+    tables: Arc<FixedBaseTables<G>>,
+    start: usize,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    cancel: Option<CancellationToken>,
+) -> WorkerFuture<Result<<G as CurveAffine>::Projective, SynthesisError>>
+where
+    for<'a> &'a Q: QueryDensity,
+    D: Send + Sync + 'static + Clone + AsRef<Q>,
+    G: CurveAffine,
+{
+    let exps = dense_exponents(exponents, density_map.as_ref());
+    if start + exps.len() > tables.len() {
+        let e = io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "expected more bases from source",
+        );
+        return pool.compute(move || Err(e.into()));
+    }
+
+    let chunk = cmp::max(1, exps.len() >> pool.log_num_cpus());
+    let parts = (0..exps.len())
+        .step_by(chunk)
+        .map(|from| {
+            let tables = tables.clone();
+            let exps = exps.clone();
+            let cancel = cancel.clone();
+            pool.compute(move || {
+                if let Some(ref cancel) = cancel {
+                    cancel.check()?;
+                }
+                let to = cmp::min(from + chunk, exps.len());
+                Ok(tables.multiexp_from(start + from, &exps[from..to]))
+            })
+        })
+        .collect::<Vec<_>>();
+
+    WorkerFuture::new(async move {
+        let mut acc = G::Projective::zero();
+        for part in parts {
+            acc.add_assign(&part.await?);
+        }
+        Ok(acc)
+    })
+}
+
+/// Perform several multi-exponentiations over all of their bases. On the GPU
+/// they share kernel launches, which saves most of the launch and transfer
+/// overhead of small multiexps. On the CPU they run in parallel like separate
+/// calls to [`multiexp`].
+pub fn multiexp_batch<G, S>(
+    pool: &Worker,
+    jobs: Vec<(
+        S,
+        Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    )>,
+    kern: &mut Option<gpu::MultiexpKernel<G::Engine>>,
+) -> Result<Vec<<G as CurveAffine>::Projective>, SynthesisError>
+where
+    G: CurveAffine,
+    G::Engine: crate::bls::Engine,
+    S: SourceBuilder<G>,
+{
+    if let Some(ref mut k) = kern {
+        let jobs = jobs
+            .into_iter()
+            .map(|(bases, exps)| Ok((bases.get()?, exps)))
+            .collect::<Result<Vec<_>, SynthesisError>>()?;
+        let slices = jobs
+            .iter()
+            .map(
+                |((bases, skip), exps)| match bases.get(*skip..*skip + exps.len()) {
+                    Some(bases) => Ok((bases, &exps[..])),
+                    None => Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "expected more bases from source",
+                    )
+                    .into()),
+                },
+            )
+            .collect::<Result<Vec<_>, SynthesisError>>()?;
+        return Ok(k.multiexp_batch(&slices)?);
+    }
+
+    let results = jobs
+        .into_iter()
+        .map(|(bases, exps)| multiexp(pool, bases, FullDensity, exps, &mut None))
+        .collect::<Vec<_>>();
+    results.into_iter().map(|result| result.wait()).collect()
+}
+
+/// Compacts the exponents to the ones of the dense bases, which are the bases
+/// that are actually stored, unless all of them are dense anyway.
+pub(crate) fn dense_exponents<Q, R>(exponents: Arc<Vec<R>>, density_map: &Q) -> Arc<Vec<R>>
+where
+    for<'a> &'a Q: QueryDensity,
+    R: Copy,
+{
+    let n = exponents
+        .iter()
+        .zip(density_map.iter())
+        .filter(|&(_, d)| d)
+        .count();
+    if n == exponents.len() {
+        return exponents;
+    }
+
+    let mut exps = Vec::with_capacity(n);
+    exps.extend(
+        exponents
+            .iter()
+            .zip(density_map.iter())
+            .filter(|&(_, d)| d)
+            .map(|(&e, _)| e),
+    );
+    Arc::new(exps)
+}
+
+/// Perform multi-exponentiation on the CPU over borrowed bases and exponents,
+/// e.g. from a memory map, without wrapping them into an `Arc<Vec<_>>` first.
+/// The windows are computed on scoped threads of `pool`.
+pub fn multiexp_slices<G, B, E>(
+    pool: &Worker,
+    bases: &B,
+    exponents: &E,
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+where
+    G: CurveAffine,
+    B: AsRef<[G]> + ?Sized,
+    E: AsRef<[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]> + ?Sized,
+{
+    let bases = bases.as_ref();
+    let exponents = exponents.as_ref();
+
+    let c = window_size::<G>(exponents.len());
+    let num_limbs = exponents
+        .first()
+        .cloned()
+        .unwrap_or_default()
+        .as_ref()
+        .len();
+    let windows = Windows::new::<G>(c, num_limbs, c >= 2);
+    let coordinates = if c >= AFFINE_MIN_WINDOW && affine_buckets_supported::<G>() {
+        let end = bases.len().min(exponents.len());
+        Some(affine_coordinates(pool, &bases[..end]))
+    } else {
+        None
+    };
+
+    let mut regions = (0..windows.len())
+        .map(|_| Ok(G::Projective::zero()))
+        .collect::<Vec<_>>();
+    pool.scope(regions.len(), |scope, _| {
+        for (i, region) in regions.iter_mut().enumerate() {
+            let windows = &windows;
+            let coordinates = &coordinates;
+            scope.spawn(move |_| {
+                let buckets = match coordinates {
+                    Some(coordinates) => {
+                        Buckets::affine(bases, coordinates, windows.num_buckets, false)
+                    }
+                    None => Buckets::projective((bases, 0usize), windows.num_buckets),
+                };
+                *region = multiexp_region(
+                    buckets,
+                    exponents,
+                    iter::repeat(true),
+                    i as u32 * c,
+                    windows,
+                    i == 0,
+                    None,
+                );
+            });
+        }
+    });
+
+    // Join the regions, starting with the most significant one
+    let mut acc = G::Projective::zero();
+    for region in regions.into_iter().rev() {
+        for _ in 0..c {
+            acc.double();
+        }
+        acc.add_assign(&region?);
+    }
+
+    Ok(acc)
+}
+
+/// Precomputed multiples of fixed bases, for multiexps over the same bases
+/// again and again. For every base and every window `w` of `c` bits, the
+/// table holds `j·2^(c·w)·base` for all `j` from 1 to `2^c - 1`, so that a
+/// multiexp takes a single addition per base and window.
+pub struct FixedBaseTables<G: CurveAffine> {
+    window: u32,
+    num_windows: usize,
+    tables: Vec<Vec<G>>,
+}
+
+impl<G: CurveAffine> FixedBaseTables<G> {
+    /// Builds the tables for windows of `window` bits, which takes
+    /// `2^window - 1` points per base and window.
+    pub fn new(bases: &[G], window: u32) -> Self {
+        assert!(
+            window > 0 && window <= 16,
+            "the window size is out of range"
+        );
+
+        let num_windows = Self::num_windows(window);
+        let tables = bases
+            .iter()
+            .map(|base| Self::table(base, window, num_windows))
+            .collect();
+
+        FixedBaseTables {
+            window,
+            num_windows,
+            tables,
+        }
+    }
+
+    /// Like [`new`](FixedBaseTables::new), with the tables of the bases
+    /// built in parallel.
+    pub fn new_parallel(pool: &Worker, bases: &[G], window: u32) -> Self {
+        assert!(
+            window > 0 && window <= 16,
+            "the window size is out of range"
+        );
+
+        let num_windows = Self::num_windows(window);
+        let mut tables = vec![vec![]; bases.len()];
+        if !bases.is_empty() {
+            pool.scope(bases.len(), |scope, chunk| {
+                for (bases, tables) in bases.chunks(chunk).zip(tables.chunks_mut(chunk)) {
+                    scope.spawn(move |_| {
+                        for (base, table) in bases.iter().zip(tables.iter_mut()) {
+                            *table = Self::table(base, window, num_windows);
+                        }
+                    });
+                }
+            });
+        }
+
+        FixedBaseTables {
+            window,
+            num_windows,
+            tables,
+        }
+    }
+
+    fn num_windows(window: u32) -> usize {
+        let num_bits = <G::Engine as ScalarEngine>::Fr::NUM_BITS as usize;
+        (num_bits + window as usize - 1) / window as usize
+    }
+
+    fn table(base: &G, window: u32, num_windows: usize) -> Vec<G> {
+        let mut table = Vec::with_capacity(num_windows << window);
+        let mut start = base.into_projective();
+        for _ in 0..num_windows {
+            let mut multiple = start;
+            for _ in 1..(1 << window) {
+                table.push(multiple);
+                multiple.add_assign(&start);
+            }
+            // The last multiple is 2^c·start, the start of the next window.
+            start = multiple;
+        }
+        G::Projective::batch_normalization(&mut table);
+        table.into_iter().map(|p| p.into_affine()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// Computes `Σ exponents[i]·bases[i]`, there must not be more exponents
+    /// than bases.
+    pub fn multiexp(
+        &self,
+        exponents: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    ) -> G::Projective {
+        self.multiexp_from(0, exponents)
+    }
+
+    /// Computes `Σ exponents[i]·bases[start + i]`, there must not be more
+    /// exponents than bases from `start` on.
+    pub fn multiexp_from(
+        &self,
+        start: usize,
+        exponents: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    ) -> G::Projective {
+        assert!(start + exponents.len() <= self.tables.len());
+
+        let per_window = (1 << self.window) - 1;
+        let mut acc = G::Projective::zero();
+        for (table, exp) in self.tables[start..].iter().zip(exponents.iter()) {
+            for w in 0..self.num_windows {
+                let digit = window_digit(exp.as_ref(), w * self.window as usize, self.window);
+                if digit != 0 {
+                    acc.add_assign_mixed(&table[w * per_window + digit - 1]);
+                }
+            }
+        }
+
+        acc
+    }
+}
+
+/// A segment of a multiexp that is computed on its own, e.g. by an external
+/// job scheduler on another machine. A multiexp is `split` into jobs, which
+/// are serialized with `write` and computed wherever they are read, their
+/// results are sent back with [`write_partial`] and summed up with
+/// [`combine`].
+pub struct MultiexpJob<G: CurveAffine> {
+    /// The index of the first base within the whole multiexp.
+    pub start: usize,
+    pub bases: Vec<G>,
+    pub exponents: Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>,
+}
+
+impl<G: CurveAffine> MultiexpJob<G> {
+    /// Splits the multiexp of `exponents` with `bases` into jobs of at most
+    /// `job_size` bases each. Panics if the numbers of bases and exponents
+    /// differ.
+    pub fn split(
+        bases: &[G],
+        exponents: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+        job_size: usize,
+    ) -> Vec<Self> {
+        assert_eq!(bases.len(), exponents.len());
+        assert!(job_size > 0);
+
+        bases
+            .chunks(job_size)
+            .zip(exponents.chunks(job_size))
+            .enumerate()
+            .map(|(i, (bases, exponents))| MultiexpJob {
+                start: i * job_size,
+                bases: bases.to_vec(),
+                exponents: exponents.to_vec(),
+            })
+            .collect()
+    }
+
+    /// Computes the job on the CPU.
+    pub fn compute(&self, pool: &Worker) -> Result<G::Projective, SynthesisError> {
+        multiexp_slices(pool, &self.bases[..], &self.exponents[..])
+    }
+
+    /// Writes the job: the start and the number of bases as big-endian
+    /// `u64`s, then the bases uncompressed and the exponents big-endian.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u64::<BigEndian>(self.start as u64)?;
+        writer.write_u64::<BigEndian>(self.bases.len() as u64)?;
+        for base in &self.bases {
+            writer.write_all(base.into_uncompressed().as_ref())?;
+        }
+        for exponent in &self.exponents {
+            exponent.write_be(&mut writer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a job, with `checked` the bases are checked to be in the
+    /// subgroup.
+    pub fn read<R: Read>(mut reader: R, checked: bool) -> io::Result<Self> {
+        let start = reader.read_u64::<BigEndian>()? as usize;
+        let len = reader.read_u64::<BigEndian>()? as usize;
+
+        // The length isn't trusted for allocating everything up front.
+        let mut bases = Vec::with_capacity(len.min(1 << 16));
+        for _ in 0..len {
+            let mut repr = G::Uncompressed::empty();
+            reader.read_exact(repr.as_mut())?;
+            let base = if checked {
+                repr.into_affine()
+            } else {
+                repr.into_affine_unchecked()
+            };
+            bases.push(base.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?);
+        }
+        let mut exponents = Vec::with_capacity(bases.len());
+        for _ in 0..len {
+            let mut exponent = <<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr::default();
+            exponent.read_be(&mut reader)?;
+            exponents.push(exponent);
+        }
+
+        Ok(MultiexpJob {
+            start,
+            bases,
+            exponents,
+        })
+    }
+}
+
+/// Writes the result of a job, uncompressed.
+pub fn write_partial<G: CurveProjective, W: Write>(partial: &G, mut writer: W) -> io::Result<()> {
+    writer.write_all(partial.into_affine().into_uncompressed().as_ref())
+}
+
+/// Reads the result of a job, checking that it's in the subgroup.
+pub fn read_partial<G: CurveAffine, R: Read>(mut reader: R) -> io::Result<G::Projective> {
+    let mut repr = G::Uncompressed::empty();
+    reader.read_exact(repr.as_mut())?;
+    repr.into_affine()
+        .map(|p| p.into_projective())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Sums up the results of the jobs a multiexp was split into, in any order.
+pub fn combine<G: CurveProjective, I: IntoIterator<Item = G>>(partials: I) -> G {
+    partials.into_iter().fold(G::zero(), |mut acc, partial| {
+        acc.add_assign(&partial);
+        acc
+    })
+}
+
+/// Converts many points, e.g. the results of multiexps, to affine ones. Every
+/// chunk of points shares a single field inversion (Montgomery's trick),
+/// instead of one per point like `into_affine` takes, and the chunks are
+/// normalized on the threads of `pool`.
+pub fn batch_normalize<G: CurveProjective>(pool: &Worker, points: &[G]) -> Vec<G::Affine> {
+    let mut affine = vec![G::Affine::zero(); points.len()];
+    if points.is_empty() {
+        return affine;
+    }
+
+    let mut points = points.to_vec();
+    pool.scope(points.len(), |scope, chunk| {
+        for (points, affine) in points.chunks_mut(chunk).zip(affine.chunks_mut(chunk)) {
+            scope.spawn(move |_| {
+                G::batch_normalization(points);
+                for (point, affine) in points.iter().zip(affine.iter_mut()) {
+                    *affine = point.into_affine();
+                }
+            });
+        }
+    });
+
+    affine
+}
+
+/// The `c` bits of the little-endian limbs starting at bit `start`.
+pub(crate) fn window_digit(limbs: &[u64], start: usize, c: u32) -> usize {
+    let limb = start / 64;
+    let shift = start % 64;
+    if limb >= limbs.len() {
+        return 0;
+    }
+
+    let mut digit = limbs[limb] >> shift;
+    if shift + c as usize > 64 && limb + 1 < limbs.len() {
+        digit |= limbs[limb + 1] << (64 - shift);
+    }
+
+    (digit & ((1 << c) - 1)) as usize
+}
+
+#[test]
+fn test_affine_buckets() {
+    use crate::bls::G2Affine;
+    use rand::Rng;
+
+    fn check<G: CurveAffine>() {
+        // Few buckets and repeated points cover conflicts, doublings and
+        // points cancelling each other.
+        let rng = &mut crate::testing::test_rng();
+        let points = (0..8)
+            .map(|_| G::Projective::random(rng).into_affine())
+            .collect::<Vec<_>>();
+        let mut buckets = AffineBuckets::<G>::new(4, 3);
+        let mut expected = vec![G::Projective::zero(); 4];
+        for _ in 0..1000 {
+            let bucket = rng.gen_range(0, 4);
+            let point = points[rng.gen_range(0, points.len())];
+            let negate = rng.gen();
+            buckets.add(bucket, &point, &Affine::of(&point).unwrap(), negate);
+            let mut point = point;
+            if negate {
+                point.negate();
+            }
+            expected[bucket].add_assign_mixed(&point);
+        }
+
+        assert_eq!(buckets.finish(), expected);
+    }
+
+    assert!(affine_buckets_supported::<G1Affine>());
+    assert!(!affine_buckets_supported::<G2Affine>());
+    check::<G1Affine>();
 
-        Ok(())
+    let rng = &mut crate::testing::test_rng();
+    let points = (0..10)
+        .map(|_| crate::bls::G1::random(rng).into_affine())
+        .chain(iter::once(G1Affine::zero()))
+        .collect::<Vec<_>>();
+    let pool = Worker::new();
+    for (point, affine) in points.iter().zip(affine_coordinates(&pool, &points)) {
+        assert_eq!(affine.into_point::<G1Affine>(), Some(*point));
     }
+    assert!(Affine::of(&G2Affine::one()).is_none());
 }
 
-pub trait QueryDensity {
-    /// Returns whether the base exists.
-    type Iter: Iterator<Item = bool>;
+#[test]
+fn test_fixed_base_tables() {
+    use crate::bls::{Fr, G1};
 
-    fn iter(self) -> Self::Iter;
-    fn get_query_size(self) -> Option<usize>;
-}
+    let rng = &mut crate::testing::test_rng();
+    let bases = (0..10)
+        .map(|_| G1::random(rng).into_affine())
+        .collect::<Vec<_>>();
+    let exps = (0..10).map(|_| Fr::random(rng)).collect::<Vec<_>>();
 
-#[derive(Clone)]
-pub struct FullDensity;
+    let mut expected = G1::zero();
+    for (base, exp) in bases.iter().zip(exps.iter()) {
+        expected.add_assign(&base.mul(*exp));
+    }
 
-impl AsRef<FullDensity> for FullDensity {
-    fn as_ref(&self) -> &FullDensity {
-        self
+    let reprs = exps.iter().map(|e| e.into_repr()).collect::<Vec<_>>();
+    for &window in &[1, 4, 7, 8] {
+        let tables = FixedBaseTables::new(&bases, window);
+        assert_eq!(tables.multiexp(&reprs), expected);
+        // Fewer exponents than bases are fine.
+        assert_eq!(tables.multiexp(&reprs[..3]), {
+            let mut acc = G1::zero();
+            for (base, exp) in bases.iter().zip(exps.iter()).take(3) {
+                acc.add_assign(&base.mul(*exp));
+            }
+            acc
+        });
     }
 }
 
-impl<'a> QueryDensity for &'a FullDensity {
-    type Iter = iter::Repeat<bool>;
+#[test]
+fn test_multiexp_slices() {
+    use crate::bls::{Fr, G1};
 
-    fn iter(self) -> Self::Iter {
-        iter::repeat(true)
-    }
+    let rng = &mut crate::testing::test_rng();
+    let bases = (0..256)
+        .map(|_| G1::random(rng).into_affine())
+        .collect::<Vec<_>>();
+    let mut exps = (0..256).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+    exps[0] = Fr::zero();
+    exps[1] = Fr::one();
 
-    fn get_query_size(self) -> Option<usize> {
-        None
+    let mut expected = G1::zero();
+    for (base, exp) in bases.iter().zip(exps.iter()) {
+        expected.add_assign(&base.mul(*exp));
     }
+
+    let pool = Worker::new();
+    let reprs = exps.iter().map(|e| e.into_repr()).collect::<Vec<_>>();
+    assert_eq!(
+        multiexp_slices(&pool, &bases[..], &reprs[..]).unwrap(),
+        expected
+    );
+    assert!(multiexp_slices(&pool, &bases[..10], &reprs[..]).is_err());
 }
 
-pub struct DensityTracker {
-    bv: BitVec,
-    total_density: usize,
+#[test]
+fn test_multiexp_jobs() {
+    use crate::bls::{Fr, G1Affine, G1};
+
+    let rng = &mut crate::testing::test_rng();
+    let bases = (0..100)
+        .map(|_| G1::random(rng).into_affine())
+        .collect::<Vec<_>>();
+    let exps = (0..100)
+        .map(|_| Fr::random(rng).into_repr())
+        .collect::<Vec<_>>();
+
+    let pool = Worker::new();
+    let expected = multiexp_slices(&pool, &bases[..], &exps[..]).unwrap();
+
+    let jobs = MultiexpJob::split(&bases, &exps, 30);
+    assert_eq!(jobs.len(), 4);
+    assert_eq!(jobs[3].start, 90);
+    assert_eq!(jobs[3].bases.len(), 10);
+
+    // Every job and its result make a round trip, as if computed elsewhere.
+    let partials = jobs
+        .iter()
+        .rev()
+        .map(|job| {
+            let mut encoded = vec![];
+            job.write(&mut encoded).unwrap();
+            let job = MultiexpJob::<G1Affine>::read(&encoded[..], true).unwrap();
+
+            let mut encoded = vec![];
+            write_partial(&job.compute(&pool).unwrap(), &mut encoded).unwrap();
+            read_partial::<G1Affine, _>(&encoded[..]).unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(combine(partials), expected);
+    assert_eq!(combine(Vec::<G1>::new()), G1::zero());
 }
 
-impl<'a> QueryDensity for &'a DensityTracker {
-    type Iter = bit_vec::Iter<'a>;
+#[test]
+fn test_multiexp_batch() {
+    use crate::bls::{Fr, G1Affine, G1};
 
-    fn iter(self) -> Self::Iter {
-        self.bv.iter()
-    }
+    let rng = &mut crate::testing::test_rng();
+    let bases = Arc::new(
+        (0..100)
+            .map(|_| G1::random(rng).into_affine())
+            .collect::<Vec<_>>(),
+    );
 
-    fn get_query_size(self) -> Option<usize> {
-        Some(self.bv.len())
-    }
+    let pool = Worker::new();
+    let jobs = [(0, 10), (10, 1), (5, 60), (50, 50)]
+        .iter()
+        .map(|&(skip, n)| {
+            let exps = (0..n)
+                .map(|_| Fr::random(rng).into_repr())
+                .collect::<Vec<_>>();
+            ((bases.clone(), skip), Arc::new(exps))
+        })
+        .collect::<Vec<_>>();
+    let expected = jobs
+        .iter()
+        .map(|((bases, skip), exps)| {
+            let bases = bases.iter().skip(*skip).take(exps.len()).cloned();
+            multiexp_slices(&pool, &bases.collect::<Vec<_>>(), &exps[..]).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let results = multiexp_batch::<G1Affine, _>(&pool, jobs, &mut None).unwrap();
+    assert_eq!(results, expected);
+
+    // A source with fewer bases than exponents is an error.
+    let exps = (0..50)
+        .map(|_| Fr::random(rng).into_repr())
+        .collect::<Vec<_>>();
+    let jobs = vec![((bases, 90), Arc::new(exps))];
+    assert!(multiexp_batch::<G1Affine, _>(&pool, jobs, &mut None).is_err());
 }
 
-impl DensityTracker {
-    pub fn new() -> DensityTracker {
-        DensityTracker {
-            bv: BitVec::new(),
-            total_density: 0,
+#[test]
+fn test_batch_normalize() {
+    use crate::bls::{G1, G2};
+
+    let rng = &mut crate::testing::test_rng();
+    let pool = Worker::new();
+    for &n in &[0, 1, 7, 1000] {
+        let mut points = (0..n).map(|_| G1::random(rng)).collect::<Vec<_>>();
+        if n > 1 {
+            points[n / 2] = G1::zero();
         }
+        let expected = points.iter().map(|p| p.into_affine()).collect::<Vec<_>>();
+        assert_eq!(batch_normalize(&pool, &points), expected);
     }
 
-    pub fn add_element(&mut self) {
-        self.bv.push(false);
+    let points = (0..100).map(|_| G2::random(rng)).collect::<Vec<_>>();
+    let expected = points.iter().map(|p| p.into_affine()).collect::<Vec<_>>();
+    assert_eq!(batch_normalize(&pool, &points), expected);
+}
+
+#[test]
+fn test_stream_source() {
+    use crate::bls::{Fr, G1Affine, G1};
+    use std::io::Cursor;
+
+    const SAMPLES: usize = 3000;
+
+    let rng = &mut crate::testing::test_rng();
+    let bases = Arc::new(
+        (0..SAMPLES)
+            .map(|_| G1::random(rng).into_affine())
+            .collect::<Vec<_>>(),
+    );
+    let exps = Arc::new(
+        (0..SAMPLES)
+            .map(|i| {
+                // Zeros let the source skip bases.
+                if i % 7 < 3 {
+                    Fr::zero().into_repr()
+                } else {
+                    Fr::random(rng).into_repr()
+                }
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    let mut bytes = vec![];
+    for base in bases.iter() {
+        bytes.extend_from_slice(base.into_uncompressed().as_ref());
     }
+    let stream = StreamSourceBuilder::<_, G1Affine>::new(
+        move || Ok(Cursor::new(bytes.clone())),
+        SAMPLES,
+        true,
+    );
 
-    pub fn inc(&mut self, idx: usize) {
-        if !self.bv.get(idx).unwrap() {
-            self.bv.set(idx, true);
-            self.total_density += 1;
+    let pool = Worker::new();
+    let expected = multiexp(&pool, (bases, 0), FullDensity, exps.clone(), &mut None)
+        .wait()
+        .unwrap();
+    let streamed = multiexp(&pool, stream.clone(), FullDensity, exps.clone(), &mut None)
+        .wait()
+        .unwrap();
+
+    assert_eq!(streamed, expected);
+
+    // The clones share the bases they read.
+    let (read, _) = stream.clone().get().unwrap();
+    assert_eq!(read.len(), SAMPLES);
+    assert!(Arc::ptr_eq(&read, &stream.get().unwrap().0));
+
+    let missing = StreamSourceBuilder::<Cursor<Vec<u8>>, G1Affine>::new(
+        || Err(io::Error::new(io::ErrorKind::NotFound, "no bases")),
+        SAMPLES,
+        true,
+    );
+    assert!(missing.get().is_err());
+}
+
+#[test]
+fn test_skip_identity() {
+    use crate::bls::{Fr, G1Affine, G1};
+
+    // Enough exponents for affine buckets
+    const SAMPLES: usize = 1 << 13;
+
+    let rng = &mut crate::testing::test_rng();
+    let mut bases = (0..SAMPLES)
+        .map(|_| G1::random(rng).into_affine())
+        .collect::<Vec<_>>();
+    let exps = Arc::new(
+        (0..SAMPLES)
+            .map(|_| Fr::random(rng).into_repr())
+            .collect::<Vec<_>>(),
+    );
+
+    let pool = Worker::new();
+    for &len in &[100, SAMPLES] {
+        let mut without = exps[..len].to_vec();
+        for &i in &[0, 1, len / 2] {
+            bases[i] = G1Affine::zero();
+            without[i] = Fr::zero().into_repr();
         }
-    }
+        let bases = Arc::new(bases[..len].to_vec());
+        let exps = Arc::new(exps[..len].to_vec());
+
+        let expected = multiexp(
+            &pool,
+            (bases.clone(), 0),
+            FullDensity,
+            Arc::new(without),
+            &mut None,
+        )
+        .wait()
+        .unwrap();
+        let skipped = multiexp(
+            &pool,
+            SkipIdentity((bases.clone(), 0)),
+            FullDensity,
+            exps.clone(),
+            &mut None,
+        )
+        .wait()
+        .unwrap();
+        assert_eq!(skipped, expected);
 
-    pub fn get_total_density(&self) -> usize {
-        self.total_density
+        match multiexp(&pool, (bases, 0), FullDensity, exps, &mut None).wait() {
+            Err(SynthesisError::UnexpectedIdentity) => (),
+            _ => panic!("expected an identity error"),
+        }
     }
 }
 
-fn multiexp_inner<Q, D, G, S>(
-    pool: &Worker,
-    bases: S,
-    density_map: D,
-    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
-    mut skip: u32,
-    c: u32,
-    handle_trivial: bool,
-) -> Box<dyn Future<Item = <G as CurveAffine>::Projective, Error = SynthesisError>>
-where
-    for<'a> &'a Q: QueryDensity,
-    D: Send + Sync + 'static + Clone + AsRef<Q>,
-    G: CurveAffine,
-    S: SourceBuilder<G>,
-{
-    // Perform this region of the multiexp
-    let this = {
-        let bases = bases.clone();
-        let exponents = exponents.clone();
-        let density_map = density_map.clone();
+#[test]
+fn test_base_range() {
+    use crate::bls::{Fr, FrRepr, G1Affine, G1};
 
-        pool.compute(move || {
-            // Accumulate the result
-            let mut acc = G::Projective::zero();
-
-            // Build a source for the bases
-            let mut bases = bases.new();
-
-            // Create space for the buckets
-            let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << c) - 1];
-
-            let zero = <G::Engine as ScalarEngine>::Fr::zero().into_repr();
-            let one = <G::Engine as ScalarEngine>::Fr::one().into_repr();
-
-            // Sort the bases into buckets
-            for (&exp, density) in exponents.iter().zip(density_map.as_ref().iter()) {
-                if density {
-                    if exp == zero {
-                        bases.skip(1)?;
-                    } else if exp == one {
-                        if handle_trivial {
-                            bases.add_assign_mixed(&mut acc)?;
-                        } else {
-                            bases.skip(1)?;
-                        }
-                    } else {
-                        let mut exp = exp;
-                        exp.shr(skip);
-                        let exp = exp.as_ref()[0] % (1 << c);
-
-                        if exp != 0 {
-                            bases.add_assign_mixed(&mut buckets[(exp - 1) as usize])?;
-                        } else {
-                            bases.skip(1)?;
-                        }
-                    }
-                }
-            }
+    let rng = &mut crate::testing::test_rng();
+    let bases = Arc::new(
+        (0..64)
+            .map(|_| G1::random(rng).into_affine())
+            .collect::<Vec<_>>(),
+    );
+    let exps = (0..64)
+        .map(|_| Fr::random(rng).into_repr())
+        .collect::<Vec<_>>();
+
+    let pool = Worker::new();
+    let (inputs, aux) = BaseRange::new(bases.clone()).split_at(40);
+    assert!(inputs.shares_bases(&aux));
+    assert_eq!(inputs.as_slice(), &bases[..40]);
+    assert_eq!(aux.as_slice(), &bases[40..]);
+
+    let run = |bases: BaseRange<G1Affine>, exps: &[FrRepr]| {
+        multiexp(
+            &pool,
+            bases,
+            FullDensity,
+            Arc::new(exps.to_vec()),
+            &mut None,
+        )
+        .wait()
+    };
+    assert_eq!(
+        run(inputs.clone(), &exps[..40]).unwrap(),
+        multiexp_slices(&pool, &bases[..40], &exps[..40]).unwrap()
+    );
+    assert_eq!(
+        run(aux, &exps[40..]).unwrap(),
+        multiexp_slices(&pool, &bases[40..], &exps[40..]).unwrap()
+    );
 
-            // Summation by parts
-            // e.g. 3a + 2b + 1c = a +
-            //                    (a) + b +
-            //                    ((a) + b) + c
-            let mut running_sum = G::Projective::zero();
-            for exp in buckets.into_iter().rev() {
-                running_sum.add_assign(&exp);
-                acc.add_assign(&running_sum);
+    // The inputs end where the auxiliary bases start.
+    assert!(run(inputs, &exps[..41]).is_err());
+}
+
+#[test]
+fn test_signed_digits() {
+    // The signed digits of an exponent sum up to the exponent itself.
+    for &c in &[2, 3, 5, 13] {
+        let num_windows = signed_windows(100, c);
+        let offset = signed_offset(2, num_windows, c);
+        for &x in &[0u128, 1, 42, (1 << 100) - 1, 0x1234_5678_9abc_def0_1234] {
+            let limbs = [x as u64, (x >> 64) as u64];
+            let sum = (0..num_windows).rev().fold(0i128, |sum, w| {
+                (sum << c) + signed_digit(&limbs, &offset, w * c as usize, c) as i128
+            });
+            assert_eq!(sum, x as i128);
+            for w in 0..num_windows {
+                let digit = signed_digit(&limbs, &offset, w * c as usize, c);
+                assert!(digit >= -(1 << (c - 1)) && digit < (1 << (c - 1)));
             }
+        }
+    }
+}
 
-            Ok(acc)
-        })
-    };
+#[test]
+fn test_sparse_multiexp() {
+    use crate::bls::{Fr, G1};
 
-    skip += c;
+    const SAMPLES: usize = 1 << 10;
 
-    if skip >= <G::Engine as ScalarEngine>::Fr::NUM_BITS {
-        // There isn't another region.
-        Box::new(this)
-    } else {
-        // There's another region more significant. Calculate and join it with
-        // this region recursively.
-        Box::new(
-            this.join(multiexp_inner(
-                pool,
-                bases,
-                density_map,
-                exponents,
-                skip,
-                c,
-                false,
-            ))
-            .map(move |(this, mut higher)| {
-                for _ in 0..c {
-                    higher.double();
-                }
+    let rng = &mut crate::testing::test_rng();
+    let bases = Arc::new(
+        (0..SAMPLES)
+            .map(|_| G1::random(rng).into_affine())
+            .collect::<Vec<_>>(),
+    );
+    let mut exps = (0..SAMPLES)
+        .map(|_| Fr::random(rng).into_repr())
+        .collect::<Vec<_>>();
+    exps[7] = Fr::zero().into_repr();
+    exps[10] = Fr::one().into_repr();
+    let exps = Arc::new(exps);
 
-                higher.add_assign(&this);
+    let pool = Worker::new();
+    for &num_dense in &[0, 1, SPARSE_MAX_EXPS, SPARSE_MAX_EXPS + 1] {
+        // The dense bases are the first ones in the source.
+        let mut density = DensityTracker::new();
+        let mut expected = G1::zero();
+        let mut dense = 0;
+        for (i, exp) in exps.iter().enumerate() {
+            density.add_element();
+            if i % 3 == 1 && dense < num_dense {
+                density.inc(i);
+                expected.add_assign(&bases[dense].mul(*exp));
+                dense += 1;
+            }
+        }
 
-                higher
-            }),
-        )
+        let density = Arc::new(density);
+        let result = multiexp(&pool, (bases.clone(), 0), density, exps.clone(), &mut None)
+            .wait()
+            .unwrap();
+        assert_eq!(result, expected);
     }
 }
 
-/// Perform multi-exponentiation. The caller is responsible for ensuring the
-/// query size is the same as the number of exponents.
-pub fn multiexp<Q, D, G, S>(
-    pool: &Worker,
-    bases: S,
-    density_map: D,
-    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
-    kern: &mut Option<gpu::MultiexpKernel<G::Engine>>,
-) -> Box<dyn Future<Item = <G as CurveAffine>::Projective, Error = SynthesisError>>
-where
-    for<'a> &'a Q: QueryDensity,
-    D: Send + Sync + 'static + Clone + AsRef<Q>,
-    G: CurveAffine,
-    G::Engine: paired::Engine,
-    S: SourceBuilder<G>,
-{
-    if let Some(ref mut k) = kern {
-        let mut exps = vec![exponents[0]; exponents.len()];
-        let mut n = 0;
-        for (&e, d) in exponents.iter().zip(density_map.as_ref().iter()) {
-            if d {
-                exps[n] = e;
-                n += 1;
+#[test]
+fn test_density_tracker_composition() {
+    let tracker = |bits: &[bool]| {
+        let mut tracker = DensityTracker::new();
+        for (idx, &used) in bits.iter().enumerate() {
+            tracker.add_element();
+            if used {
+                tracker.inc(idx);
             }
         }
+        tracker
+    };
+    let bits = |tracker: &DensityTracker| {
+        (0..tracker.len())
+            .map(|idx| tracker.get(idx))
+            .collect::<Vec<_>>()
+    };
+
+    let mut aux = tracker(&[true, false]);
+    aux.extend(&tracker(&[false, true, true]), false);
+    assert_eq!(bits(&aux), vec![true, false, false, true, true]);
+    assert_eq!(aux.get_total_density(), 3);
+
+    // The shared `ONE` input is only counted once.
+    let mut inputs = tracker(&[false, true]);
+    inputs.extend(&tracker(&[true, true]), true);
+    assert_eq!(bits(&inputs), vec![true, true, true]);
+    assert_eq!(inputs.get_total_density(), 3);
+
+    let mut empty = DensityTracker::new();
+    assert!(empty.is_empty());
+    empty.extend(&tracker(&[true, false]), true);
+    assert_eq!(bits(&empty), vec![true, false]);
+    assert_eq!(empty.get_total_density(), 1);
+
+    let mut union = tracker(&[true, false, false]);
+    union.union(&tracker(&[true, false, true]));
+    assert_eq!(bits(&union), vec![true, false, true]);
+    assert_eq!(union.get_total_density(), 2);
+}
 
-        let (bss, skip) = bases.get();
-        let result = k.multiexp(bss, Arc::new(exps), skip, n);
+#[test]
+fn test_window_size() {
+    use crate::bls::{Fr, G1Affine, G2Affine, G1};
+
+    assert_eq!(window_size::<G1Affine>(16), 3);
 
-        return Box::new(pool.compute(move || match result {
-            Ok(p) => Ok(p),
-            Err(e) => Err(SynthesisError::from(e)),
-        }));
+    let mut last = 3;
+    for log_n in 5..26 {
+        let c = window_size::<G1Affine>(1 << log_n);
+        assert!(c >= last);
+        last = c;
     }
 
-    let c = if exponents.len() < 32 {
-        3u32
-    } else {
-        (f64::from(exponents.len() as u32)).ln().ceil() as u32
-    };
+    // The buckets of G2 are twice as large.
+    assert!(window_size::<G2Affine>(1 << 25) < window_size::<G1Affine>(1 << 25));
 
-    if let Some(query_size) = density_map.as_ref().get_query_size() {
-        // If the density map has a known query size, it should not be
-        // inconsistent with the number of exponents.
+    assert_eq!(window_size::<G1Affine>(1 << 20), 14);
 
-        assert!(query_size == exponents.len());
+    // Other window sizes only change the speed.
+    let rng = &mut crate::testing::test_rng();
+    let bases = Arc::new(
+        (0..100)
+            .map(|_| G1::random(rng).into_affine())
+            .collect::<Vec<_>>(),
+    );
+    let exps = Arc::new(
+        (0..100)
+            .map(|_| Fr::random(rng).into_repr())
+            .collect::<Vec<_>>(),
+    );
+    let pool = Worker::new();
+    let expected = multiexp(
+        &pool,
+        (bases.clone(), 0),
+        FullDensity,
+        exps.clone(),
+        &mut None,
+    )
+    .wait()
+    .unwrap();
+    for &c in &[2, 5, 9] {
+        let result = multiexp_with_window_size(
+            &pool,
+            (bases.clone(), 0),
+            FullDensity,
+            exps.clone(),
+            &mut None,
+            c,
+        )
+        .wait()
+        .unwrap();
+        assert_eq!(result, expected);
     }
-
-    multiexp_inner(pool, bases, density_map, exponents, 0, c, true)
 }
 
 #[cfg(feature = "pairing")]
@@ -324,12 +2641,12 @@ fn test_with_bls12() {
         acc
     }
 
-    use paired::{bls12_381::Bls12, Engine};
+    use crate::bls::{Bls12, Engine};
     use rand;
 
     const SAMPLES: usize = 1 << 14;
 
-    let rng = &mut rand::thread_rng();
+    let rng = &mut crate::testing::test_rng();
     let v = Arc::new(
         (0..SAMPLES)
             .map(|_| <Bls12 as ScalarEngine>::Fr::random(rng).into_repr())
@@ -345,9 +2662,15 @@ fn test_with_bls12() {
 
     let pool = Worker::new();
 
-    let fast = multiexp(&pool, (g, 0), FullDensity, v).wait().unwrap();
+    let fast = multiexp(&pool, (g.clone(), 0), FullDensity, v.clone(), &mut None)
+        .wait()
+        .unwrap();
 
     assert_eq!(naive, fast);
+
+    let slices = multiexp_slices(&pool, &g[..], &v[..]).unwrap();
+
+    assert_eq!(naive, slices);
 }
 
 lazy_static::lazy_static! {
@@ -357,59 +2680,38 @@ lazy_static::lazy_static! {
 use std::env;
 pub fn gpu_multiexp_supported<E>() -> Result<gpu::MultiexpKernel<E>, SynthesisError>
 where
-    E: paired::Engine,
+    E: crate::bls::Engine,
 {
-    const TEST_SIZE: u32 = 1024;
-    let pool = Worker::new();
-    let rng = &mut rand::thread_rng();
-    let mut kern = Some(gpu::MultiexpKernel::<E>::create()?);
-
     // Checking the correctness of GPU results can be time consuming. User can disable this
-    // feature using BELLMAN_GPU_NO_CHECK flag.
-    if env::var("BELLMAN_GPU_NO_CHECK").is_ok() {
+    // feature using BELLMAN_GPU_NO_CHECK flag. BELLMAN_GPU_SPOT_CHECKS checks parts of every
+    // multiexp instead.
+    gpu_multiexp_kernel(env::var("BELLMAN_GPU_NO_CHECK").is_err())
+}
+
+/// Creates a multiexp kernel. With `self_test`, the kernel is compared against
+/// the CPU the first time on the machine, and isn't used if it computes
+/// something else. The verdict is cached on disk until the devices, their
+/// drivers or the crate change.
+pub fn gpu_multiexp_kernel<E>(self_test: bool) -> Result<gpu::MultiexpKernel<E>, SynthesisError>
+where
+    E: crate::bls::Engine,
+{
+    let mut kern = Some(gpu::MultiexpKernel::<E>::create()?);
+    if !self_test {
         return Ok(kern.unwrap());
     }
 
     let res = {
         let mut supported = GPU_MULTIEXP_SUPPORTED.lock().unwrap();
+        let kernel = format!("multiexp {}", std::any::type_name::<E>());
         if let Some(res) = *supported {
             res
+        } else if let Some(res) = gpu::cached_verdict(&kernel) {
+            *supported = Some(res);
+            res
         } else {
-            let bases_g1 = Arc::new(
-                (0..TEST_SIZE)
-                    .map(|_| E::G1::random(rng).into_affine())
-                    .collect::<Vec<_>>(),
-            );
-            let bases_g2 = Arc::new(
-                (0..TEST_SIZE)
-                    .map(|_| E::G2::random(rng).into_affine())
-                    .collect::<Vec<_>>(),
-            );
-            let exps = Arc::new(
-                (0..TEST_SIZE)
-                    .map(|_| E::Fr::random(rng).into_repr())
-                    .collect::<Vec<_>>(),
-            );
-            let gpu_g1 = multiexp(
-                &pool,
-                (bases_g1.clone(), 0),
-                FullDensity,
-                exps.clone(),
-                &mut kern,
-            )
-            .wait()?;
-            let cpu_g1 =
-                multiexp(&pool, (bases_g1, 0), FullDensity, exps.clone(), &mut None).wait()?;
-            let gpu_g2 = multiexp(
-                &pool,
-                (bases_g2.clone(), 0),
-                FullDensity,
-                exps.clone(),
-                &mut kern,
-            )
-            .wait()?;
-            let cpu_g2 = multiexp(&pool, (bases_g2, 0), FullDensity, exps, &mut None).wait()?;
-            let res = cpu_g1 == gpu_g1 && cpu_g2 == gpu_g2;
+            let res = gpu_multiexp_self_test(&mut kern)?;
+            gpu::cache_verdict(&kernel, res);
             *supported = Some(res);
             res
         }
@@ -417,16 +2719,64 @@ where
     if res {
         Ok(kern.unwrap())
     } else {
-        Err(SynthesisError::from(gpu::GPUError {
-            msg: "GPU Multiexp not supported!".to_string(),
-        }))
+        Err(SynthesisError::from(gpu::GPUError::WrongResults(
+            "GPU multiexp results differ from the CPU, see gpu::diagnostics()".to_string(),
+        )))
     }
 }
 
+/// Compares multiexps of random points in G1 and G2 on the GPU against the
+/// CPU.
+pub(crate) fn gpu_multiexp_self_test<E>(
+    kern: &mut Option<gpu::MultiexpKernel<E>>,
+) -> Result<bool, SynthesisError>
+where
+    E: crate::bls::Engine,
+{
+    const TEST_SIZE: u32 = 1024;
+    let pool = Worker::new();
+    let rng = &mut rand::thread_rng();
+    let bases_g1 = Arc::new(
+        (0..TEST_SIZE)
+            .map(|_| E::G1::random(rng).into_affine())
+            .collect::<Vec<_>>(),
+    );
+    let bases_g2 = Arc::new(
+        (0..TEST_SIZE)
+            .map(|_| E::G2::random(rng).into_affine())
+            .collect::<Vec<_>>(),
+    );
+    let exps = Arc::new(
+        (0..TEST_SIZE)
+            .map(|_| E::Fr::random(rng).into_repr())
+            .collect::<Vec<_>>(),
+    );
+    let gpu_g1 = multiexp(
+        &pool,
+        (bases_g1.clone(), 0),
+        FullDensity,
+        exps.clone(),
+        kern,
+    )
+    .wait()?;
+    let cpu_g1 = multiexp(&pool, (bases_g1, 0), FullDensity, exps.clone(), &mut None).wait()?;
+    let gpu_g2 = multiexp(
+        &pool,
+        (bases_g2.clone(), 0),
+        FullDensity,
+        exps.clone(),
+        kern,
+    )
+    .wait()?;
+    let cpu_g2 = multiexp(&pool, (bases_g2, 0), FullDensity, exps, &mut None).wait()?;
+
+    Ok(cpu_g1 == gpu_g1 && cpu_g2 == gpu_g2)
+}
+
 #[cfg(feature = "gpu-test")]
 #[test]
 pub fn gpu_multiexp_consistency() {
-    use paired::bls12_381::Bls12;
+    use crate::bls::Bls12;
     use std::time::Instant;
 
     const CHUNK_SIZE: usize = 1048576;
@@ -438,10 +2788,10 @@ pub fn gpu_multiexp_consistency() {
     }
     let pool = Worker::new();
 
-    let rng = &mut rand::thread_rng();
+    let rng = &mut crate::testing::test_rng();
 
     let mut bases = (0..(1 << 10))
-        .map(|_| <Bls12 as paired::Engine>::G1::random(rng).into_affine())
+        .map(|_| <Bls12 as crate::bls::Engine>::G1::random(rng).into_affine())
         .collect::<Vec<_>>();
     for _ in 10..START_LOG_D {
         bases = [bases.clone(), bases.clone()].concat();
@@ -482,3 +2832,54 @@ pub fn gpu_multiexp_consistency() {
         bases = [bases.clone(), bases.clone()].concat();
     }
 }
+
+#[cfg(feature = "gpu-test")]
+#[test]
+pub fn gpu_multiexp_batch_consistency() {
+    use crate::bls::{Bls12, Fr, G1Affine, G2Affine};
+    use std::time::Instant;
+
+    fn check<G: CurveAffine<Engine = Bls12>>(kern: &mut Option<gpu::MultiexpKernel<Bls12>>) {
+        let rng = &mut crate::testing::test_rng();
+        let pool = Worker::new();
+        let bases = Arc::new(
+            (0..(1 << 12))
+                .map(|_| G::Projective::random(rng).into_affine())
+                .collect::<Vec<_>>(),
+        );
+        // Many small multiexps, like the ones of a batch of small proofs.
+        let jobs = (0..64)
+            .map(|i| {
+                let exps = (0..(1 << (i % 10)))
+                    .map(|_| Fr::random(rng).into_repr())
+                    .collect::<Vec<_>>();
+                ((bases.clone(), i), Arc::new(exps))
+            })
+            .collect::<Vec<_>>();
+
+        let now = Instant::now();
+        let gpu = multiexp_batch::<G, _>(&pool, jobs.clone(), kern).unwrap();
+        println!(
+            "Batch of {} multiexps took {:?}.",
+            jobs.len(),
+            now.elapsed()
+        );
+
+        let cpu = multiexp_batch::<G, _>(&pool, jobs, &mut None).unwrap();
+        assert_eq!(cpu, gpu);
+
+        // A source with fewer bases than exponents is an error.
+        let exps = (0..64)
+            .map(|_| Fr::random(rng).into_repr())
+            .collect::<Vec<_>>();
+        let jobs = vec![((bases.clone(), bases.len() - 1), Arc::new(exps))];
+        assert!(multiexp_batch::<G, _>(&pool, jobs, kern).is_err());
+    }
+
+    let mut kern = gpu::MultiexpKernel::<Bls12>::create().ok();
+    if kern.is_none() {
+        panic!("Cannot initialize kernel!");
+    }
+    check::<G1Affine>(&mut kern);
+    check::<G2Affine>(&mut kern);
+}
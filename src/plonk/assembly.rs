@@ -0,0 +1,235 @@
+//! Translation of rank-1 constraint systems into PLONK gates.
+
+use ff::{Field, ScalarEngine};
+
+use crate::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// The variable that is always zero, used for unused wires.
+pub(crate) const ZERO: usize = 0;
+
+/// A gate enforcing `q_m·a·b + q_l·a + q_r·b + q_o·c + q_c = 0` for the values
+/// of the variables connected to its wires `a`, `b` and `c`.
+#[derive(Clone)]
+pub(crate) struct Gate<E: ScalarEngine> {
+    pub wires: [usize; 3],
+    pub q_m: E::Fr,
+    pub q_l: E::Fr,
+    pub q_r: E::Fr,
+    pub q_o: E::Fr,
+    pub q_c: E::Fr,
+}
+
+impl<E: ScalarEngine> Gate<E> {
+    /// A gate that is satisfied by any assignment.
+    pub fn empty() -> Gate<E> {
+        Gate {
+            wires: [ZERO; 3],
+            q_m: E::Fr::zero(),
+            q_l: E::Fr::zero(),
+            q_r: E::Fr::zero(),
+            q_o: E::Fr::zero(),
+            q_c: E::Fr::zero(),
+        }
+    }
+}
+
+/// Synthesizes a circuit into PLONK gates.
+///
+/// Every public input gets a gate of its own, which come first, so that the
+/// public inputs can be added to the gate constraints of the first rows. The
+/// `ONE` input isn't a variable, but a constant of the gates. Linear
+/// combinations with more than one variable are reduced to a single variable
+/// by a chain of addition gates.
+pub(crate) struct Assembly<E: ScalarEngine> {
+    pub input_gates: Vec<Gate<E>>,
+    pub gates: Vec<Gate<E>>,
+    pub num_variables: usize,
+    /// The variables of the inputs, the `ONE` input maps to the zero variable.
+    inputs: Vec<usize>,
+    aux: Vec<usize>,
+    /// The assignment of the variables, only tracked when proving.
+    pub values: Option<Vec<E::Fr>>,
+    pub input_values: Vec<E::Fr>,
+}
+
+impl<E: ScalarEngine> Assembly<E> {
+    pub fn new(proving: bool) -> Assembly<E> {
+        Assembly {
+            input_gates: vec![],
+            gates: vec![],
+            num_variables: 1,
+            inputs: vec![ZERO],
+            aux: vec![],
+            values: if proving {
+                Some(vec![E::Fr::zero()])
+            } else {
+                None
+            },
+            input_values: vec![],
+        }
+    }
+
+    /// The gates, starting with the ones of the public inputs.
+    pub fn all_gates(&self) -> impl Iterator<Item = &Gate<E>> {
+        self.input_gates.iter().chain(self.gates.iter())
+    }
+
+    pub fn num_gates(&self) -> usize {
+        self.input_gates.len() + self.gates.len()
+    }
+
+    fn new_variable(&mut self, value: Option<E::Fr>) -> usize {
+        if let (Some(values), Some(value)) = (self.values.as_mut(), value) {
+            values.push(value);
+        }
+        self.num_variables += 1;
+
+        self.num_variables - 1
+    }
+
+    /// Adds a gate for `l·a + r·b` and returns the variable of the result.
+    fn add(&mut self, (l, a): (E::Fr, usize), (r, b): (E::Fr, usize)) -> usize {
+        let value = self.values.as_ref().map(|values| {
+            let mut value = values[a];
+            value.mul_assign(&l);
+            let mut tmp = values[b];
+            tmp.mul_assign(&r);
+            value.add_assign(&tmp);
+            value
+        });
+        let out = self.new_variable(value);
+
+        let mut q_o = E::Fr::one();
+        q_o.negate();
+        self.gates.push(Gate {
+            wires: [a, b, out],
+            q_l: l,
+            q_r: r,
+            q_o,
+            ..Gate::empty()
+        });
+
+        out
+    }
+
+    /// Reduces the linear combination to `coeff·var + constant`.
+    fn reduce(&mut self, lc: LinearCombination<E>) -> (E::Fr, usize, E::Fr) {
+        let mut constant = E::Fr::zero();
+        let mut term = None;
+        for (var, coeff) in lc.simplify() {
+            let var = match var.get_unchecked() {
+                Index::Input(0) => {
+                    constant.add_assign(&coeff);
+                    continue;
+                }
+                Index::Input(i) => self.inputs[i],
+                Index::Aux(i) => self.aux[i],
+            };
+
+            term = Some(match term {
+                None => (coeff, var),
+                Some(acc) => (E::Fr::one(), self.add(acc, (coeff, var))),
+            });
+        }
+        let (coeff, var) = term.unwrap_or((E::Fr::zero(), ZERO));
+
+        (coeff, var, constant)
+    }
+}
+
+impl<E: ScalarEngine> ConstraintSystem<E> for Assembly<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let value = match self.values {
+            Some(_) => Some(f()?),
+            None => None,
+        };
+        let var = self.new_variable(value);
+        self.aux.push(var);
+
+        Ok(Variable(Index::Aux(self.aux.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let value = match self.values {
+            Some(_) => Some(f()?),
+            None => None,
+        };
+        let var = self.new_variable(value);
+        self.inputs.push(var);
+        if let Some(value) = value {
+            self.input_values.push(value);
+        }
+
+        // The public input polynomial subtracts the value again.
+        self.input_gates.push(Gate {
+            wires: [var, ZERO, ZERO],
+            q_l: E::Fr::one(),
+            ..Gate::empty()
+        });
+
+        Ok(Variable(Index::Input(self.inputs.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        let (a_coeff, a, a_const) = self.reduce(a(LinearCombination::zero()));
+        let (b_coeff, b, b_const) = self.reduce(b(LinearCombination::zero()));
+        let (c_coeff, c, c_const) = self.reduce(c(LinearCombination::zero()));
+
+        // (a_coeff·a + a_const)·(b_coeff·b + b_const) - (c_coeff·c + c_const) = 0
+        let mut q_m = a_coeff;
+        q_m.mul_assign(&b_coeff);
+        let mut q_l = a_coeff;
+        q_l.mul_assign(&b_const);
+        let mut q_r = a_const;
+        q_r.mul_assign(&b_coeff);
+        let mut q_o = c_coeff;
+        q_o.negate();
+        let mut q_c = a_const;
+        q_c.mul_assign(&b_const);
+        q_c.sub_assign(&c_const);
+
+        self.gates.push(Gate {
+            wires: [a, b, c],
+            q_m,
+            q_l,
+            q_r,
+            q_o,
+            q_c,
+        });
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
@@ -0,0 +1,137 @@
+use crate::bls::Engine;
+use ff::{Field, PrimeField};
+use groupy::CurveAffine;
+
+use super::assembly::{Assembly, Gate};
+use super::{coset_shifts, ProvingKey, UniversalSrs, VerifyingKey};
+
+use crate::multicore::Worker;
+use crate::poly::{commit, coset_evaluations, interpolate, root_of_unity};
+use crate::{Circuit, SynthesisError};
+
+/// Derives the parameters of a circuit from a universal SRS, which needs to
+/// support polynomials of degree `n + 5`, where `n` is the number of gates
+/// of the circuit rounded up to a power of two.
+pub fn generate_parameters<E, C>(
+    circuit: C,
+    srs: &UniversalSrs<E>,
+) -> Result<ProvingKey<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    let mut assembly = Assembly::<E>::new(false);
+    circuit.synthesize(&mut assembly)?;
+
+    let mut n = 4;
+    let mut log_n = 2;
+    while n < assembly.num_gates() {
+        n *= 2;
+        log_n += 1;
+    }
+    // The quotient polynomial is computed over a domain of size 8n.
+    if log_n + 3 > E::Fr::S {
+        return Err(SynthesisError::PolynomialDegreeTooLarge);
+    }
+    // The quotient polynomial is split into polynomials of degree n + 5 at
+    // most.
+    let srs = srs.truncate(n + 5)?;
+
+    let mut gates = assembly.all_gates().cloned().collect::<Vec<_>>();
+    gates.resize(n, Gate::empty());
+
+    let worker = Worker::new();
+
+    // The selector polynomials.
+    let mut selectors = [vec![], vec![], vec![], vec![], vec![]];
+    for (selector, evaluations) in selectors.iter_mut().zip(
+        [
+            gates.iter().map(|g| g.q_m).collect::<Vec<_>>(),
+            gates.iter().map(|g| g.q_l).collect(),
+            gates.iter().map(|g| g.q_r).collect(),
+            gates.iter().map(|g| g.q_o).collect(),
+            gates.iter().map(|g| g.q_c).collect(),
+        ]
+        .iter(),
+    ) {
+        *selector = interpolate(&worker, evaluations.clone(), &mut None)?;
+    }
+
+    // Wire `col` of gate `row` is labeled with k_col * omega^row, where k_0
+    // is one. The permutation polynomials map every label to the label of
+    // the next wire in the cycle of wires connected to the same variable.
+    let omega = root_of_unity::<E::Fr>(log_n);
+    let (k1, k2) = coset_shifts::<E::Fr>();
+    let mut labels = vec![vec![E::Fr::zero(); n]; 3];
+    for (col, k) in [E::Fr::one(), k1, k2].iter().enumerate() {
+        let mut label = *k;
+        for l in labels[col].iter_mut() {
+            *l = label;
+            label.mul_assign(&omega);
+        }
+    }
+
+    let mut cycles = vec![vec![]; assembly.num_variables];
+    for (row, gate) in gates.iter().enumerate() {
+        for (col, &var) in gate.wires.iter().enumerate() {
+            cycles[var].push((col, row));
+        }
+    }
+
+    let mut sigma_evaluations = vec![vec![E::Fr::zero(); n]; 3];
+    for cycle in cycles.iter() {
+        for (i, &(col, row)) in cycle.iter().enumerate() {
+            let (next_col, next_row) = cycle[(i + 1) % cycle.len()];
+            sigma_evaluations[col][row] = labels[next_col][next_row];
+        }
+    }
+
+    let mut sigmas = [vec![], vec![], vec![]];
+    for (sigma, evaluations) in sigmas.iter_mut().zip(sigma_evaluations) {
+        *sigma = interpolate(&worker, evaluations, &mut None)?;
+    }
+
+    let mut l1 = vec![E::Fr::zero(); n];
+    l1[0] = E::Fr::one();
+    let l1 = interpolate(&worker, l1, &mut None)?;
+
+    let mut selector_commitments = [E::G1Affine::zero(); 5];
+    let mut selectors_coset = [vec![], vec![], vec![], vec![], vec![]];
+    for ((commitment, coset), selector) in selector_commitments
+        .iter_mut()
+        .zip(selectors_coset.iter_mut())
+        .zip(selectors.iter())
+    {
+        *commitment = commit(&worker, &srs.g1_powers, selector, &mut None)?;
+        *coset = coset_evaluations(&worker, selector, 8 * n, &mut None)?;
+    }
+
+    let mut sigma_commitments = [E::G1Affine::zero(); 3];
+    let mut sigmas_coset = [vec![], vec![], vec![]];
+    for ((commitment, coset), sigma) in sigma_commitments
+        .iter_mut()
+        .zip(sigmas_coset.iter_mut())
+        .zip(sigmas.iter())
+    {
+        *commitment = commit(&worker, &srs.g1_powers, sigma, &mut None)?;
+        *coset = coset_evaluations(&worker, sigma, 8 * n, &mut None)?;
+    }
+
+    Ok(ProvingKey {
+        vk: VerifyingKey {
+            n,
+            num_inputs: assembly.input_gates.len(),
+            selector_commitments,
+            sigma_commitments,
+            g1: srs.g1_powers[0],
+            g2: srs.g2,
+            g2_x: srs.g2_x,
+        },
+        g1_powers: srs.g1_powers,
+        selectors,
+        sigmas,
+        selectors_coset,
+        sigmas_coset,
+        l1_coset: coset_evaluations(&worker, &l1, 8 * n, &mut None)?,
+    })
+}
@@ -0,0 +1,380 @@
+//! The [PLONK] proving system.
+//!
+//! In contrast to [Groth16], the parameters of a circuit are derived from a
+//! [`UniversalSrs`], which only depends on the size of the circuits it
+//! supports. Circuits are written against the same [`ConstraintSystem`] as
+//! for Groth16, every rank-1 constraint is translated into PLONK gates.
+//!
+//! [PLONK]: https://eprint.iacr.org/2019/953
+//! [Groth16]: crate::groth16
+//! [`ConstraintSystem`]: crate::ConstraintSystem
+
+use crate::bls::Engine;
+use ff::{Field, PrimeField, PrimeFieldRepr};
+use groupy::{CurveAffine, EncodedPoint};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+mod assembly;
+mod generator;
+mod prover;
+mod transcript;
+mod verifier;
+
+pub use self::generator::*;
+pub use self::prover::*;
+pub use self::verifier::*;
+pub use crate::srs::UniversalSrs;
+
+/// The shifts `k1` and `k2` of the cosets of the evaluation domain that
+/// label the `b` and `c` wires in the permutation argument.
+pub(crate) fn coset_shifts<F: PrimeField>() -> (F, F) {
+    let k1 = F::multiplicative_generator();
+    let mut k2 = k1;
+    k2.square();
+
+    (k1, k2)
+}
+
+fn read_point<P: EncodedPoint, R: Read>(reader: &mut R) -> io::Result<P::Affine> {
+    let mut repr = P::empty();
+    reader.read_exact(repr.as_mut())?;
+
+    repr.into_affine()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_scalar<F: PrimeField, W: Write>(writer: &mut W, scalar: &F) -> io::Result<()> {
+    scalar.into_repr().write_be(writer)
+}
+
+fn read_scalar<F: PrimeField, R: Read>(reader: &mut R) -> io::Result<F> {
+    let mut repr = F::Repr::default();
+    repr.read_be(reader)?;
+
+    F::from_repr(repr).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[derive(Clone)]
+pub struct Proof<E: Engine> {
+    // Commitments to the wire polynomials.
+    pub a: E::G1Affine,
+    pub b: E::G1Affine,
+    pub c: E::G1Affine,
+
+    // Commitment to the permutation polynomial.
+    pub z: E::G1Affine,
+
+    // Commitments to the low, middle and high parts of the quotient
+    // polynomial.
+    pub t_lo: E::G1Affine,
+    pub t_mid: E::G1Affine,
+    pub t_hi: E::G1Affine,
+
+    // Opening proofs at the challenge zeta and at zeta times the generator
+    // of the evaluation domain.
+    pub w_zeta: E::G1Affine,
+    pub w_zeta_omega: E::G1Affine,
+
+    // Evaluations at zeta of the wire polynomials, the first two permutation
+    // polynomials and the linearization polynomial.
+    pub a_eval: E::Fr,
+    pub b_eval: E::Fr,
+    pub c_eval: E::Fr,
+    pub sigma1_eval: E::Fr,
+    pub sigma2_eval: E::Fr,
+    pub r_eval: E::Fr,
+
+    // Evaluation of the permutation polynomial at zeta times the generator.
+    pub z_omega_eval: E::Fr,
+}
+
+impl<E: Engine> PartialEq for Proof<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.commitments() == other.commitments() && self.evaluations() == other.evaluations()
+    }
+}
+
+impl<E: Engine> Proof<E> {
+    fn commitments(&self) -> [E::G1Affine; 9] {
+        [
+            self.a,
+            self.b,
+            self.c,
+            self.z,
+            self.t_lo,
+            self.t_mid,
+            self.t_hi,
+            self.w_zeta,
+            self.w_zeta_omega,
+        ]
+    }
+
+    fn evaluations(&self) -> [E::Fr; 7] {
+        [
+            self.a_eval,
+            self.b_eval,
+            self.c_eval,
+            self.sigma1_eval,
+            self.sigma2_eval,
+            self.r_eval,
+            self.z_omega_eval,
+        ]
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for commitment in self.commitments().iter() {
+            writer.write_all(commitment.into_compressed().as_ref())?;
+        }
+        for evaluation in self.evaluations().iter() {
+            write_scalar(&mut writer, evaluation)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut commitments = [E::G1Affine::zero(); 9];
+        for commitment in commitments.iter_mut() {
+            *commitment = read_point::<<E::G1Affine as CurveAffine>::Compressed, _>(&mut reader)?;
+        }
+        let mut evaluations = [E::Fr::zero(); 7];
+        for evaluation in evaluations.iter_mut() {
+            *evaluation = read_scalar(&mut reader)?;
+        }
+
+        Ok(Proof {
+            a: commitments[0],
+            b: commitments[1],
+            c: commitments[2],
+            z: commitments[3],
+            t_lo: commitments[4],
+            t_mid: commitments[5],
+            t_hi: commitments[6],
+            w_zeta: commitments[7],
+            w_zeta_omega: commitments[8],
+            a_eval: evaluations[0],
+            b_eval: evaluations[1],
+            c_eval: evaluations[2],
+            sigma1_eval: evaluations[3],
+            sigma2_eval: evaluations[4],
+            r_eval: evaluations[5],
+            z_omega_eval: evaluations[6],
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct VerifyingKey<E: Engine> {
+    // Size of the evaluation domain, the number of gates rounded up to a
+    // power of two.
+    pub n: usize,
+
+    // Number of public inputs, not counting the `ONE` input.
+    pub num_inputs: usize,
+
+    // Commitments to the selector polynomials q_M, q_L, q_R, q_O and q_C.
+    pub selector_commitments: [E::G1Affine; 5],
+
+    // Commitments to the permutation polynomials of the a, b and c wires.
+    pub sigma_commitments: [E::G1Affine; 3],
+
+    // The generator of G1 and G2 and the secret of the SRS in G2.
+    pub g1: E::G1Affine,
+    pub g2: E::G2Affine,
+    pub g2_x: E::G2Affine,
+}
+
+impl<E: Engine> PartialEq for VerifyingKey<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.n == other.n
+            && self.num_inputs == other.num_inputs
+            && self.selector_commitments == other.selector_commitments
+            && self.sigma_commitments == other.sigma_commitments
+            && self.g1 == other.g1
+            && self.g2 == other.g2
+            && self.g2_x == other.g2_x
+    }
+}
+
+impl<E: Engine> VerifyingKey<E> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<BigEndian>(self.n as u32)?;
+        writer.write_u32::<BigEndian>(self.num_inputs as u32)?;
+        for commitment in self
+            .selector_commitments
+            .iter()
+            .chain(self.sigma_commitments.iter())
+            .chain(Some(&self.g1))
+        {
+            writer.write_all(commitment.into_uncompressed().as_ref())?;
+        }
+        writer.write_all(self.g2.into_uncompressed().as_ref())?;
+        writer.write_all(self.g2_x.into_uncompressed().as_ref())?;
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let n = reader.read_u32::<BigEndian>()? as usize;
+        if !n.is_power_of_two() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "domain size is not a power of two",
+            ));
+        }
+        let num_inputs = reader.read_u32::<BigEndian>()? as usize;
+
+        let mut selector_commitments = [E::G1Affine::zero(); 5];
+        for commitment in selector_commitments.iter_mut() {
+            *commitment = read_point::<<E::G1Affine as CurveAffine>::Uncompressed, _>(&mut reader)?;
+        }
+        let mut sigma_commitments = [E::G1Affine::zero(); 3];
+        for commitment in sigma_commitments.iter_mut() {
+            *commitment = read_point::<<E::G1Affine as CurveAffine>::Uncompressed, _>(&mut reader)?;
+        }
+        let g1 = read_point::<<E::G1Affine as CurveAffine>::Uncompressed, _>(&mut reader)?;
+        let g2 = read_point::<<E::G2Affine as CurveAffine>::Uncompressed, _>(&mut reader)?;
+        let g2_x = read_point::<<E::G2Affine as CurveAffine>::Uncompressed, _>(&mut reader)?;
+
+        Ok(VerifyingKey {
+            n,
+            num_inputs,
+            selector_commitments,
+            sigma_commitments,
+            g1,
+            g2,
+            g2_x,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct ProvingKey<E: Engine> {
+    pub vk: VerifyingKey<E>,
+
+    // Powers of the secret of the SRS in G1, as many as needed to commit to
+    // the polynomials of the proof.
+    pub(crate) g1_powers: Arc<Vec<E::G1Affine>>,
+
+    // The selector polynomials q_M, q_L, q_R, q_O and q_C and the permutation
+    // polynomials in coefficient form.
+    pub(crate) selectors: [Vec<E::Fr>; 5],
+    pub(crate) sigmas: [Vec<E::Fr>; 3],
+
+    // The same polynomials and the first Lagrange basis polynomial evaluated
+    // over the coset of the domain of size 8n, which the quotient polynomial
+    // is computed on.
+    pub(crate) selectors_coset: [Vec<E::Fr>; 5],
+    pub(crate) sigmas_coset: [Vec<E::Fr>; 3],
+    pub(crate) l1_coset: Vec<E::Fr>,
+}
+
+#[cfg(test)]
+mod test_with_bls12_381 {
+    use super::*;
+    use crate::{Circuit, ConstraintSystem, SynthesisError};
+
+    use crate::bls::{Bls12, Fr};
+    use crate::testing::test_rng;
+    use ff::Field;
+
+    /// Proves knowledge of `x` with `x^3 + x + 5 = out` for the public `out`.
+    struct CubeCircuit {
+        x: Option<Fr>,
+        out: Option<Fr>,
+    }
+
+    impl Circuit<Bls12> for CubeCircuit {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x_value = self.x;
+            let x = cs.alloc(|| "x", || x_value.ok_or(SynthesisError::AssignmentMissing))?;
+            let x2_value = x_value.map(|mut x| {
+                x.square();
+                x
+            });
+            let x2 = cs.alloc(
+                || "x2",
+                || x2_value.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            cs.enforce(|| "x2 = x * x", |lc| lc + x, |lc| lc + x, |lc| lc + x2);
+            let x3_value = x2_value.map(|mut x2| {
+                x2.mul_assign(&x_value.unwrap());
+                x2
+            });
+            let x3 = cs.alloc(
+                || "x3",
+                || x3_value.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            cs.enforce(|| "x3 = x2 * x", |lc| lc + x2, |lc| lc + x, |lc| lc + x3);
+
+            let out = cs.alloc_input(
+                || "out",
+                || self.out.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            let five = Fr::from_str("5").unwrap();
+            cs.enforce(
+                || "out = x3 + x + 5",
+                |lc| lc + x3 + x + (five, CS::one()),
+                |lc| lc + CS::one(),
+                |lc| lc + out,
+            );
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn cube() {
+        let rng = &mut test_rng();
+
+        let srs = UniversalSrs::<Bls12>::generate_random(32, rng);
+        let pk = generate_parameters(CubeCircuit { x: None, out: None }, &srs).unwrap();
+
+        let x = Fr::from_str("3").unwrap();
+        let out = Fr::from_str("35").unwrap();
+        let proof = create_random_proof(
+            CubeCircuit {
+                x: Some(x),
+                out: Some(out),
+            },
+            &pk,
+            rng,
+        )
+        .unwrap();
+
+        assert!(verify_proof(&pk.vk, &proof, &[out]).unwrap());
+        assert!(!verify_proof(&pk.vk, &proof, &[Fr::from_str("36").unwrap()]).unwrap());
+
+        let mut proof_bytes = vec![];
+        proof.write(&mut proof_bytes).unwrap();
+        assert_eq!(proof_bytes.len(), 9 * 48 + 7 * 32);
+        assert!(proof == Proof::read(&proof_bytes[..]).unwrap());
+
+        let mut vk_bytes = vec![];
+        pk.vk.write(&mut vk_bytes).unwrap();
+        assert!(pk.vk == VerifyingKey::read(&vk_bytes[..]).unwrap());
+
+        let mut srs_bytes = vec![];
+        srs.write(&mut srs_bytes).unwrap();
+        assert!(srs == UniversalSrs::read(&srs_bytes[..], true).unwrap());
+
+        // The prover refuses to prove a wrong output.
+        match create_random_proof(
+            CubeCircuit {
+                x: Some(x),
+                out: Some(Fr::from_str("36").unwrap()),
+            },
+            &pk,
+            rng,
+        ) {
+            Err(SynthesisError::Unsatisfiable) => {}
+            _ => panic!("proof of a wrong output"),
+        }
+    }
+}
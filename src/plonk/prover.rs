@@ -0,0 +1,416 @@
+use rand_core::{CryptoRng, RngCore};
+
+use crate::bls::Engine;
+use ff::{Field, PrimeField};
+use log::info;
+
+use super::assembly::Assembly;
+use super::transcript::Transcript;
+use super::{coset_shifts, Proof, ProvingKey};
+
+use crate::domain::gpu_fft_supported;
+#[cfg(feature = "gpu")]
+use crate::gpu;
+use crate::multicore::Worker;
+use crate::multiexp::gpu_multiexp_supported;
+use crate::poly::{
+    add_scaled, batch_invert, blind, commit, coset_evaluations, coset_interpolate,
+    divide_by_linear, evaluate, evaluations, interpolate, lagrange_evaluations, root_of_unity,
+};
+use crate::{Circuit, SynthesisError};
+
+/// Creates a proof, blinding the polynomials with random scalars.
+pub fn create_random_proof<E, C, R>(
+    circuit: C,
+    pk: &ProvingKey<E>,
+    rng: &mut R,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+    R: RngCore + CryptoRng,
+{
+    let mut blinding = [E::Fr::zero(); 9];
+    for b in blinding.iter_mut() {
+        *b = E::Fr::random(rng);
+    }
+
+    create_proof::<E, C>(circuit, pk, blinding)
+}
+
+/// Creates a proof with the given blinding scalars: two for each wire
+/// polynomial and three for the permutation polynomial.
+pub fn create_proof<E, C>(
+    circuit: C,
+    pk: &ProvingKey<E>,
+    blinding: [E::Fr; 9],
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    let vk = &pk.vk;
+    let n = vk.n;
+    let mut log_n = 0;
+    while (1 << log_n) < n {
+        log_n += 1;
+    }
+
+    let mut assembly = Assembly::<E>::new(true);
+    circuit.synthesize(&mut assembly)?;
+    if assembly.num_gates() > n || assembly.input_gates.len() != vk.num_inputs {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    let values = assembly
+        .values
+        .as_ref()
+        .ok_or(SynthesisError::AssignmentMissing)?;
+
+    #[cfg(feature = "gpu")]
+    let lock = gpu::lock()?;
+
+    let mut fft_kern = gpu_fft_supported::<E>(log_n + 3).ok();
+    if fft_kern.is_some() {
+        info!("GPU FFT is supported!");
+    } else {
+        info!("GPU FFT is NOT supported!");
+    }
+    let mut multiexp_kern = gpu_multiexp_supported::<E>().ok();
+    if multiexp_kern.is_some() {
+        info!("GPU Multiexp is supported!");
+    } else {
+        info!("GPU Multiexp is NOT supported!");
+    }
+
+    let worker = Worker::new();
+    let mut transcript = Transcript::new(vk, &assembly.input_values);
+
+    let zero = E::Fr::zero();
+    let one = E::Fr::one();
+    let omega = root_of_unity::<E::Fr>(log_n);
+    let (k1, k2) = coset_shifts::<E::Fr>();
+
+    // Round 1: commit to the wire polynomials.
+    let mut wires = vec![vec![zero; n]; 3];
+    for (row, gate) in assembly.all_gates().enumerate() {
+        for (col, &var) in gate.wires.iter().enumerate() {
+            wires[col][row] = values[var];
+        }
+    }
+
+    let mut wire_polys = vec![];
+    let mut wire_commitments = vec![];
+    for (col, evaluations) in wires.iter().enumerate() {
+        let poly = interpolate(&worker, evaluations.clone(), &mut fft_kern)?;
+        let poly = blind(poly, &blinding[2 * col..2 * col + 2]);
+        let commitment = commit(&worker, &pk.g1_powers, &poly, &mut multiexp_kern)?;
+        transcript.append_point(&commitment);
+        wire_polys.push(poly);
+        wire_commitments.push(commitment);
+    }
+
+    let beta = transcript.challenge::<E::Fr>();
+    let gamma = transcript.challenge::<E::Fr>();
+
+    // Round 2: commit to the permutation polynomial, which accumulates the
+    // ratio of the wires labeled by their positions and by the permutation.
+    let mut sigmas = vec![];
+    for sigma in pk.sigmas.iter() {
+        sigmas.push(evaluations(&worker, sigma, n, &mut fft_kern)?);
+    }
+
+    let mut numerators = vec![one; n];
+    let mut denominators = vec![one; n];
+    let mut omega_i = one;
+    for (row, (numerator, denominator)) in numerators
+        .iter_mut()
+        .zip(denominators.iter_mut())
+        .enumerate()
+    {
+        for (col, k) in [one, k1, k2].iter().enumerate() {
+            let mut label = *k;
+            label.mul_assign(&omega_i);
+            label.mul_assign(&beta);
+            label.add_assign(&wires[col][row]);
+            label.add_assign(&gamma);
+            numerator.mul_assign(&label);
+
+            let mut label = sigmas[col][row];
+            label.mul_assign(&beta);
+            label.add_assign(&wires[col][row]);
+            label.add_assign(&gamma);
+            denominator.mul_assign(&label);
+        }
+        omega_i.mul_assign(&omega);
+    }
+    batch_invert(&mut denominators)?;
+
+    let mut z = vec![one; n];
+    let mut acc = one;
+    for ((z, numerator), denominator) in z.iter_mut().zip(numerators).zip(denominators) {
+        *z = acc;
+        acc.mul_assign(&numerator);
+        acc.mul_assign(&denominator);
+    }
+    // The accumulator only wraps around if the copy constraints hold.
+    if acc != one {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    let z_poly = blind(interpolate(&worker, z, &mut fft_kern)?, &blinding[6..9]);
+    let z_commitment = commit(&worker, &pk.g1_powers, &z_poly, &mut multiexp_kern)?;
+    transcript.append_point(&z_commitment);
+
+    let alpha = transcript.challenge::<E::Fr>();
+
+    // Round 3: commit to the quotient polynomial, computed over the coset
+    // of the domain of size 8n.
+    let coset_size = 8 * n;
+    let mut wires_coset = vec![];
+    for poly in wire_polys.iter() {
+        wires_coset.push(coset_evaluations(&worker, poly, coset_size, &mut fft_kern)?);
+    }
+    let z_coset = coset_evaluations(&worker, &z_poly, coset_size, &mut fft_kern)?;
+    let z_omega_coset = {
+        let mut shifted = z_poly.clone();
+        let mut omega_i = one;
+        for c in shifted.iter_mut() {
+            c.mul_assign(&omega_i);
+            omega_i.mul_assign(&omega);
+        }
+        coset_evaluations(&worker, &shifted, coset_size, &mut fft_kern)?
+    };
+    let public_inputs_coset = {
+        let mut evaluations = vec![zero; n];
+        for (e, input) in evaluations.iter_mut().zip(assembly.input_values.iter()) {
+            *e = *input;
+            e.negate();
+        }
+        let poly = interpolate(&worker, evaluations, &mut fft_kern)?;
+        coset_evaluations(&worker, &poly, coset_size, &mut fft_kern)?
+    };
+
+    // X^n - 1 only takes eight different values over the coset.
+    let coset_omega = root_of_unity::<E::Fr>(log_n + 3);
+    let mut vanishing_inverses = vec![zero; 8];
+    {
+        let shift = E::Fr::multiplicative_generator().pow(&[n as u64]);
+        let root = coset_omega.pow(&[n as u64]);
+        let mut root_i = one;
+        for v in vanishing_inverses.iter_mut() {
+            *v = shift;
+            v.mul_assign(&root_i);
+            v.sub_assign(&one);
+            root_i.mul_assign(&root);
+        }
+        batch_invert(&mut vanishing_inverses)?;
+    }
+
+    let mut alpha2 = alpha;
+    alpha2.square();
+
+    let mut quotient = vec![zero; coset_size];
+    worker.scope(coset_size, |scope, chunk| {
+        let wires_coset = &wires_coset;
+        let z_coset = &z_coset;
+        let z_omega_coset = &z_omega_coset;
+        let public_inputs_coset = &public_inputs_coset;
+        let vanishing_inverses = &vanishing_inverses;
+
+        for (i, quotient) in quotient.chunks_mut(chunk).enumerate() {
+            scope.spawn(move |_scope| {
+                let mut x = E::Fr::multiplicative_generator();
+                x.mul_assign(&coset_omega.pow(&[(i * chunk) as u64]));
+
+                for (j, t) in quotient.iter_mut().enumerate() {
+                    let idx = i * chunk + j;
+                    let a = wires_coset[0][idx];
+                    let b = wires_coset[1][idx];
+                    let c = wires_coset[2][idx];
+                    let q = |s: usize| pk.selectors_coset[s][idx];
+
+                    // a·b·q_M + a·q_L + b·q_R + c·q_O + PI + q_C
+                    let mut gate = a;
+                    gate.mul_assign(&b);
+                    gate.mul_assign(&q(0));
+                    for &(w, s) in [(a, 1), (b, 2), (c, 3)].iter() {
+                        let mut tmp = w;
+                        tmp.mul_assign(&q(s));
+                        gate.add_assign(&tmp);
+                    }
+                    gate.add_assign(&public_inputs_coset[idx]);
+                    gate.add_assign(&q(4));
+
+                    // α·((a + βX + γ)(b + βk1X + γ)(c + βk2X + γ)·z(X)
+                    //   - (a + βSσ1 + γ)(b + βSσ2 + γ)(c + βSσ3 + γ)·z(ωX))
+                    let mut labeled = z_coset[idx];
+                    let mut permuted = z_omega_coset[idx];
+                    for (col, (w, k)) in [(a, one), (b, k1), (c, k2)].iter().enumerate() {
+                        let mut tmp = *k;
+                        tmp.mul_assign(&x);
+                        tmp.mul_assign(&beta);
+                        tmp.add_assign(w);
+                        tmp.add_assign(&gamma);
+                        labeled.mul_assign(&tmp);
+
+                        let mut tmp = pk.sigmas_coset[col][idx];
+                        tmp.mul_assign(&beta);
+                        tmp.add_assign(w);
+                        tmp.add_assign(&gamma);
+                        permuted.mul_assign(&tmp);
+                    }
+                    labeled.sub_assign(&permuted);
+                    labeled.mul_assign(&alpha);
+
+                    // α²·(z(X) - 1)·L_1(X)
+                    let mut first = z_coset[idx];
+                    first.sub_assign(&one);
+                    first.mul_assign(&pk.l1_coset[idx]);
+                    first.mul_assign(&alpha2);
+
+                    *t = gate;
+                    t.add_assign(&labeled);
+                    t.add_assign(&first);
+                    t.mul_assign(&vanishing_inverses[idx % 8]);
+
+                    x.mul_assign(&coset_omega);
+                }
+            });
+        }
+    });
+    let mut quotient = coset_interpolate(&worker, quotient, &mut fft_kern)?;
+
+    // The quotient is only a polynomial of degree 3n + 5 if all gates are
+    // satisfied.
+    if quotient[3 * n + 6..].iter().any(|c| !c.is_zero()) {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    quotient.truncate(3 * n + 6);
+    let t_hi = quotient.split_off(2 * n);
+    let t_mid = quotient.split_off(n);
+    let t_lo = quotient;
+
+    let mut quotient_commitments = vec![];
+    for poly in [&t_lo, &t_mid, &t_hi].iter() {
+        let commitment = commit(&worker, &pk.g1_powers, poly, &mut multiexp_kern)?;
+        transcript.append_point(&commitment);
+        quotient_commitments.push(commitment);
+    }
+
+    let zeta = transcript.challenge::<E::Fr>();
+
+    // Round 4: evaluate at zeta, using the linearization polynomial for the
+    // terms that include the selectors, z and Sσ3.
+    let mut zeta_omega = zeta;
+    zeta_omega.mul_assign(&omega);
+
+    let a_eval = evaluate(&wire_polys[0], &zeta);
+    let b_eval = evaluate(&wire_polys[1], &zeta);
+    let c_eval = evaluate(&wire_polys[2], &zeta);
+    let sigma1_eval = evaluate(&pk.sigmas[0], &zeta);
+    let sigma2_eval = evaluate(&pk.sigmas[1], &zeta);
+    let z_omega_eval = evaluate(&z_poly, &zeta_omega);
+    for eval in [
+        a_eval,
+        b_eval,
+        c_eval,
+        sigma1_eval,
+        sigma2_eval,
+        z_omega_eval,
+    ]
+    .iter()
+    {
+        transcript.append_scalar(eval);
+    }
+
+    let l1_eval = lagrange_evaluations(n, &omega, &zeta, 1)?[0];
+
+    let mut r = vec![];
+    let mut ab = a_eval;
+    ab.mul_assign(&b_eval);
+    add_scaled(&mut r, &pk.selectors[0], &ab);
+    add_scaled(&mut r, &pk.selectors[1], &a_eval);
+    add_scaled(&mut r, &pk.selectors[2], &b_eval);
+    add_scaled(&mut r, &pk.selectors[3], &c_eval);
+    add_scaled(&mut r, &pk.selectors[4], &one);
+
+    let mut z_scalar = alpha;
+    for (eval, k) in [(a_eval, one), (b_eval, k1), (c_eval, k2)].iter() {
+        let mut tmp = *k;
+        tmp.mul_assign(&zeta);
+        tmp.mul_assign(&beta);
+        tmp.add_assign(eval);
+        tmp.add_assign(&gamma);
+        z_scalar.mul_assign(&tmp);
+    }
+    let mut tmp = l1_eval;
+    tmp.mul_assign(&alpha2);
+    z_scalar.add_assign(&tmp);
+    add_scaled(&mut r, &z_poly, &z_scalar);
+
+    let mut sigma3_scalar = alpha;
+    sigma3_scalar.mul_assign(&beta);
+    sigma3_scalar.mul_assign(&z_omega_eval);
+    for eval in [(a_eval, sigma1_eval), (b_eval, sigma2_eval)].iter() {
+        let mut tmp = eval.1;
+        tmp.mul_assign(&beta);
+        tmp.add_assign(&eval.0);
+        tmp.add_assign(&gamma);
+        sigma3_scalar.mul_assign(&tmp);
+    }
+    sigma3_scalar.negate();
+    add_scaled(&mut r, &pk.sigmas[2], &sigma3_scalar);
+
+    let r_eval = evaluate(&r, &zeta);
+    transcript.append_scalar(&r_eval);
+
+    let v = transcript.challenge::<E::Fr>();
+
+    // Round 5: commit to the opening proofs.
+    let mut opened = t_lo;
+    let zeta_n = zeta.pow(&[n as u64]);
+    add_scaled(&mut opened, &t_mid, &zeta_n);
+    let mut zeta_2n = zeta_n;
+    zeta_2n.square();
+    add_scaled(&mut opened, &t_hi, &zeta_2n);
+    let mut v_i = one;
+    for poly in [
+        &r,
+        &wire_polys[0],
+        &wire_polys[1],
+        &wire_polys[2],
+        &pk.sigmas[0],
+        &pk.sigmas[1],
+    ]
+    .iter()
+    {
+        v_i.mul_assign(&v);
+        add_scaled(&mut opened, poly, &v_i);
+    }
+
+    let w_zeta = divide_by_linear(&opened, &zeta);
+    let w_zeta = commit(&worker, &pk.g1_powers, &w_zeta, &mut multiexp_kern)?;
+    let w_zeta_omega = divide_by_linear(&z_poly, &zeta_omega);
+    let w_zeta_omega = commit(&worker, &pk.g1_powers, &w_zeta_omega, &mut multiexp_kern)?;
+
+    #[cfg(feature = "gpu")]
+    gpu::unlock(lock);
+
+    Ok(Proof {
+        a: wire_commitments[0],
+        b: wire_commitments[1],
+        c: wire_commitments[2],
+        z: z_commitment,
+        t_lo: quotient_commitments[0],
+        t_mid: quotient_commitments[1],
+        t_hi: quotient_commitments[2],
+        w_zeta,
+        w_zeta_omega,
+        a_eval,
+        b_eval,
+        c_eval,
+        sigma1_eval,
+        sigma2_eval,
+        r_eval,
+        z_omega_eval,
+    })
+}
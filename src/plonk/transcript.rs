@@ -0,0 +1,77 @@
+//! The Fiat-Shamir transcript deriving the verifier challenges.
+
+use crate::bls::Engine;
+use blake2s_simd::{Params, State};
+use byteorder::{BigEndian, ByteOrder};
+use ff::PrimeField;
+use groupy::CurveAffine;
+
+use super::{write_scalar, VerifyingKey};
+use crate::poly::from_u64;
+
+/// Personalization of the BLAKE2s hash of the transcript.
+const TRANSCRIPT_PERSONALIZATION: &[u8; 8] = b"Plonk_FS";
+
+/// Hashes everything the prover sent so far. The challenges are derived from
+/// the hash, which is then absorbed as well, so that consecutive challenges
+/// differ.
+pub(crate) struct Transcript {
+    state: State,
+}
+
+impl Transcript {
+    /// Starts a transcript for a proof of the circuit of `vk` with the given
+    /// public inputs.
+    pub fn new<E: Engine>(vk: &VerifyingKey<E>, public_inputs: &[E::Fr]) -> Transcript {
+        let mut transcript = Transcript {
+            state: Params::new()
+                .hash_length(32)
+                .personal(TRANSCRIPT_PERSONALIZATION)
+                .to_state(),
+        };
+
+        let mut sizes = [0; 8];
+        BigEndian::write_u32(&mut sizes[..4], vk.n as u32);
+        BigEndian::write_u32(&mut sizes[4..], vk.num_inputs as u32);
+        transcript.state.update(&sizes);
+        for commitment in vk
+            .selector_commitments
+            .iter()
+            .chain(vk.sigma_commitments.iter())
+        {
+            transcript.append_point(commitment);
+        }
+        for input in public_inputs {
+            transcript.append_scalar(input);
+        }
+
+        transcript
+    }
+
+    pub fn append_point<G: CurveAffine>(&mut self, point: &G) {
+        self.state.update(point.into_compressed().as_ref());
+    }
+
+    pub fn append_scalar<F: PrimeField>(&mut self, scalar: &F) {
+        let mut bytes = vec![];
+        write_scalar(&mut bytes, scalar).expect("writing to a vector never fails");
+        self.state.update(&bytes);
+    }
+
+    pub fn challenge<F: PrimeField>(&mut self) -> F {
+        let hash = self.state.clone().finalize();
+        self.state.update(hash.as_bytes());
+
+        // Interpret the hash as a big-endian integer, reduced modulo the
+        // characteristic of the field.
+        let mut shift = from_u64::<F>(1 << 32);
+        shift.square();
+        let mut challenge = F::zero();
+        for word in hash.as_bytes().chunks(8) {
+            challenge.mul_assign(&shift);
+            challenge.add_assign(&from_u64(BigEndian::read_u64(word)));
+        }
+
+        challenge
+    }
+}
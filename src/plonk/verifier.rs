@@ -0,0 +1,215 @@
+use crate::bls::{Engine, PairingCurveAffine};
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective};
+
+use super::transcript::Transcript;
+use super::{coset_shifts, Proof, VerifyingKey};
+use crate::poly::{lagrange_evaluations, root_of_unity};
+use crate::SynthesisError;
+
+pub fn verify_proof<E: Engine>(
+    vk: &VerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs: &[E::Fr],
+) -> Result<bool, SynthesisError> {
+    if public_inputs.len() != vk.num_inputs {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+
+    let n = vk.n;
+    let mut log_n = 0;
+    while (1 << log_n) < n {
+        log_n += 1;
+    }
+    let omega = root_of_unity::<E::Fr>(log_n);
+    let (k1, k2) = coset_shifts::<E::Fr>();
+    let one = E::Fr::one();
+
+    // Recompute the challenges of the prover.
+    let mut transcript = Transcript::new(vk, public_inputs);
+    for commitment in [proof.a, proof.b, proof.c].iter() {
+        transcript.append_point(commitment);
+    }
+    let beta = transcript.challenge::<E::Fr>();
+    let gamma = transcript.challenge::<E::Fr>();
+    transcript.append_point(&proof.z);
+    let alpha = transcript.challenge::<E::Fr>();
+    for commitment in [proof.t_lo, proof.t_mid, proof.t_hi].iter() {
+        transcript.append_point(commitment);
+    }
+    let zeta = transcript.challenge::<E::Fr>();
+    for eval in [
+        proof.a_eval,
+        proof.b_eval,
+        proof.c_eval,
+        proof.sigma1_eval,
+        proof.sigma2_eval,
+        proof.z_omega_eval,
+    ]
+    .iter()
+    {
+        transcript.append_scalar(eval);
+    }
+    transcript.append_scalar(&proof.r_eval);
+    let v = transcript.challenge::<E::Fr>();
+    transcript.append_point(&proof.w_zeta);
+    transcript.append_point(&proof.w_zeta_omega);
+    let u = transcript.challenge::<E::Fr>();
+
+    let mut alpha2 = alpha;
+    alpha2.square();
+    let zeta_n = zeta.pow(&[n as u64]);
+    let mut vanishing = zeta_n;
+    vanishing.sub_assign(&one);
+
+    let lagrange = lagrange_evaluations(n, &omega, &zeta, vk.num_inputs.max(1))?;
+    let l1_eval = lagrange[0];
+    let mut public_inputs_eval = E::Fr::zero();
+    for (input, l) in public_inputs.iter().zip(lagrange.iter()) {
+        let mut tmp = *input;
+        tmp.mul_assign(l);
+        public_inputs_eval.sub_assign(&tmp);
+    }
+
+    // (a + β·sσ1 + γ)(b + β·sσ2 + γ) at zeta.
+    let mut permuted = one;
+    for &(eval, sigma_eval) in [
+        (proof.a_eval, proof.sigma1_eval),
+        (proof.b_eval, proof.sigma2_eval),
+    ]
+    .iter()
+    {
+        let mut tmp = sigma_eval;
+        tmp.mul_assign(&beta);
+        tmp.add_assign(&eval);
+        tmp.add_assign(&gamma);
+        permuted.mul_assign(&tmp);
+    }
+
+    // The evaluation of the quotient polynomial at zeta:
+    // (r + PI - α·(a + β·sσ1 + γ)(b + β·sσ2 + γ)(c + γ)·z(ωζ) - α²·L_1) / (ζ^n - 1)
+    let mut t_eval = proof.r_eval;
+    t_eval.add_assign(&public_inputs_eval);
+    let mut tmp = proof.c_eval;
+    tmp.add_assign(&gamma);
+    tmp.mul_assign(&permuted);
+    tmp.mul_assign(&proof.z_omega_eval);
+    tmp.mul_assign(&alpha);
+    t_eval.sub_assign(&tmp);
+    let mut tmp = l1_eval;
+    tmp.mul_assign(&alpha2);
+    t_eval.sub_assign(&tmp);
+    t_eval.mul_assign(
+        &vanishing
+            .inverse()
+            .ok_or(SynthesisError::UnexpectedIdentity)?,
+    );
+
+    // The scalars of the commitments that are combined into the commitment
+    // of the batched opening at zeta.
+    let mut z_scalar = alpha;
+    for &(eval, k) in [(proof.a_eval, one), (proof.b_eval, k1), (proof.c_eval, k2)].iter() {
+        let mut tmp = k;
+        tmp.mul_assign(&zeta);
+        tmp.mul_assign(&beta);
+        tmp.add_assign(&eval);
+        tmp.add_assign(&gamma);
+        z_scalar.mul_assign(&tmp);
+    }
+    let mut tmp = l1_eval;
+    tmp.mul_assign(&alpha2);
+    z_scalar.add_assign(&tmp);
+    z_scalar.mul_assign(&v);
+    z_scalar.add_assign(&u);
+
+    let mut sigma3_scalar = permuted;
+    sigma3_scalar.mul_assign(&alpha);
+    sigma3_scalar.mul_assign(&beta);
+    sigma3_scalar.mul_assign(&proof.z_omega_eval);
+    sigma3_scalar.mul_assign(&v);
+    sigma3_scalar.negate();
+
+    let mut ab = proof.a_eval;
+    ab.mul_assign(&proof.b_eval);
+    let mut v_powers = vec![one];
+    for _ in 0..6 {
+        let mut next = *v_powers.last().unwrap();
+        next.mul_assign(&v);
+        v_powers.push(next);
+    }
+    let scaled = |mut value: E::Fr, power: usize| {
+        value.mul_assign(&v_powers[power]);
+        value
+    };
+
+    let mut zeta_2n = zeta_n;
+    zeta_2n.square();
+    let mut u_zeta_omega = zeta;
+    u_zeta_omega.mul_assign(&omega);
+    u_zeta_omega.mul_assign(&u);
+
+    // The batched evaluation at zeta and zeta times omega.
+    let mut eval = t_eval;
+    for (i, e) in [
+        proof.r_eval,
+        proof.a_eval,
+        proof.b_eval,
+        proof.c_eval,
+        proof.sigma1_eval,
+        proof.sigma2_eval,
+    ]
+    .iter()
+    .enumerate()
+    {
+        eval.add_assign(&scaled(*e, i + 1));
+    }
+    let mut tmp = proof.z_omega_eval;
+    tmp.mul_assign(&u);
+    eval.add_assign(&tmp);
+    eval.negate();
+
+    let mut rhs = E::G1::zero();
+    for (point, scalar) in [
+        (proof.t_lo, one),
+        (proof.t_mid, zeta_n),
+        (proof.t_hi, zeta_2n),
+        (vk.selector_commitments[0], scaled(ab, 1)),
+        (vk.selector_commitments[1], scaled(proof.a_eval, 1)),
+        (vk.selector_commitments[2], scaled(proof.b_eval, 1)),
+        (vk.selector_commitments[3], scaled(proof.c_eval, 1)),
+        (vk.selector_commitments[4], v),
+        (proof.z, z_scalar),
+        (vk.sigma_commitments[2], sigma3_scalar),
+        (proof.a, v_powers[2]),
+        (proof.b, v_powers[3]),
+        (proof.c, v_powers[4]),
+        (vk.sigma_commitments[0], v_powers[5]),
+        (vk.sigma_commitments[1], v_powers[6]),
+        (proof.w_zeta, zeta),
+        (proof.w_zeta_omega, u_zeta_omega),
+        (vk.g1, eval),
+    ]
+    .iter()
+    {
+        rhs.add_assign(&point.mul(scalar.into_repr()));
+    }
+
+    let mut lhs = proof.w_zeta_omega.mul(u.into_repr());
+    lhs.add_assign_mixed(&proof.w_zeta);
+
+    // The opening proofs are checked with the pairing equation
+    // e(W_ζ + u·W_ζω, x) = e(ζ·W_ζ + uζω·W_ζω + F - E, 1)
+    // ... which we rearrange to use a single final exponentiation:
+    // e(W_ζ + u·W_ζω, x)·e(-(ζ·W_ζ + uζω·W_ζω + F - E), 1) = 1
+    rhs.negate();
+
+    Ok(E::final_exponentiation(&E::miller_loop(
+        [
+            (&lhs.into_affine().prepare(), &vk.g2_x.prepare()),
+            (&rhs.into_affine().prepare(), &vk.g2.prepare()),
+        ]
+        .iter(),
+    ))
+    .unwrap()
+        == E::Fqk::one())
+}
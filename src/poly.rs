@@ -0,0 +1,539 @@
+//! Polynomials in coefficient form, their evaluations over the domains of
+//! [`domain`] and their commitments.
+//!
+//! [`Polynomial`] bundles the arithmetic, the free functions work on plain
+//! slices of coefficients. The FFTs and multiexponentiations run on the GPU
+//! if a kernel is passed in, e.g. one from [`gpu_fft_supported`] or
+//! [`gpu_multiexp_supported`].
+//!
+//! [`domain`]: crate::domain
+//! [`Polynomial`]: crate::poly::Polynomial
+//! [`gpu_fft_supported`]: crate::domain::gpu_fft_supported
+//! [`gpu_multiexp_supported`]: crate::multiexp::gpu_multiexp_supported
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::bls::Engine;
+use ff::{Field, PrimeField};
+use groupy::CurveProjective;
+
+use crate::domain::{EvaluationDomain, Scalar};
+use crate::gpu;
+use crate::multicore::Worker;
+use crate::multiexp::{multiexp, FullDensity};
+use crate::srs::UniversalSrs;
+use crate::SynthesisError;
+
+/// A polynomial over the scalar field of `E`, stored as its coefficients from
+/// the constant one on.
+pub struct Polynomial<E: Engine> {
+    coeffs: Vec<E::Fr>,
+}
+
+impl<E: Engine> Clone for Polynomial<E> {
+    fn clone(&self) -> Self {
+        Polynomial {
+            coeffs: self.coeffs.clone(),
+        }
+    }
+}
+
+impl<E: Engine> PartialEq for Polynomial<E> {
+    /// Polynomials are equal if their coefficients are, regardless of
+    /// trailing zeros.
+    fn eq(&self, other: &Self) -> bool {
+        let zero = E::Fr::zero();
+        let len = self.coeffs.len().max(other.coeffs.len());
+        (0..len)
+            .all(|i| self.coeffs.get(i).unwrap_or(&zero) == other.coeffs.get(i).unwrap_or(&zero))
+    }
+}
+
+impl<E: Engine> fmt::Debug for Polynomial<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Polynomial").field(&self.coeffs).finish()
+    }
+}
+
+impl<E: Engine> Polynomial<E> {
+    pub fn new(coeffs: Vec<E::Fr>) -> Self {
+        Polynomial { coeffs }
+    }
+
+    pub fn zero() -> Self {
+        Polynomial { coeffs: vec![] }
+    }
+
+    pub fn coeffs(&self) -> &[E::Fr] {
+        &self.coeffs
+    }
+
+    pub fn into_coeffs(self) -> Vec<E::Fr> {
+        self.coeffs
+    }
+
+    /// The degree of the polynomial, `None` for the zero polynomial.
+    pub fn degree(&self) -> Option<usize> {
+        self.coeffs.iter().rposition(|c| !c.is_zero())
+    }
+
+    pub fn evaluate(&self, point: &E::Fr) -> E::Fr {
+        evaluate(&self.coeffs, point)
+    }
+
+    /// Interpolates the polynomial with the given evaluations over the
+    /// domain of the `evaluations.len()`-th roots of unity, whose size has to
+    /// be a power of two.
+    pub fn interpolate(
+        worker: &Worker,
+        evaluations: Vec<E::Fr>,
+        kern: &mut Option<gpu::FFTKernel<E>>,
+    ) -> Result<Self, SynthesisError> {
+        Ok(Polynomial::new(interpolate(worker, evaluations, kern)?))
+    }
+
+    /// Evaluates the polynomial over the domain of the `size`-th roots of
+    /// unity.
+    pub fn evaluations(
+        &self,
+        worker: &Worker,
+        size: usize,
+        kern: &mut Option<gpu::FFTKernel<E>>,
+    ) -> Result<Vec<E::Fr>, SynthesisError> {
+        evaluations(worker, &self.coeffs, size, kern)
+    }
+
+    /// Evaluates the polynomial over the coset of the domain of the `size`-th
+    /// roots of unity that is shifted by the multiplicative generator.
+    pub fn coset_evaluations(
+        &self,
+        worker: &Worker,
+        size: usize,
+        kern: &mut Option<gpu::FFTKernel<E>>,
+    ) -> Result<Vec<E::Fr>, SynthesisError> {
+        coset_evaluations(worker, &self.coeffs, size, kern)
+    }
+
+    /// Interpolates the polynomial with the given evaluations over the coset
+    /// of [`coset_evaluations`].
+    ///
+    /// [`coset_evaluations`]: Polynomial::coset_evaluations
+    pub fn coset_interpolate(
+        worker: &Worker,
+        evaluations: Vec<E::Fr>,
+        kern: &mut Option<gpu::FFTKernel<E>>,
+    ) -> Result<Self, SynthesisError> {
+        Ok(Polynomial::new(coset_interpolate(
+            worker,
+            evaluations,
+            kern,
+        )?))
+    }
+
+    pub fn add_assign(&mut self, other: &Self) {
+        add_scaled(&mut self.coeffs, &other.coeffs, &E::Fr::one());
+    }
+
+    pub fn sub_assign(&mut self, other: &Self) {
+        let mut minus_one = E::Fr::one();
+        minus_one.negate();
+        add_scaled(&mut self.coeffs, &other.coeffs, &minus_one);
+    }
+
+    /// Adds `scalar` times `other`.
+    pub fn add_scaled(&mut self, other: &Self, scalar: &E::Fr) {
+        add_scaled(&mut self.coeffs, &other.coeffs, scalar);
+    }
+
+    pub fn scale(&mut self, scalar: &E::Fr) {
+        for c in self.coeffs.iter_mut() {
+            c.mul_assign(scalar);
+        }
+    }
+
+    /// Multiplies the polynomials by multiplying their evaluations over a
+    /// domain large enough for the product.
+    pub fn mul(
+        &self,
+        worker: &Worker,
+        other: &Self,
+        kern: &mut Option<gpu::FFTKernel<E>>,
+    ) -> Result<Self, SynthesisError> {
+        if self.coeffs.is_empty() || other.coeffs.is_empty() {
+            return Ok(Polynomial::zero());
+        }
+
+        let len = self.coeffs.len() + other.coeffs.len() - 1;
+        let size = len.next_power_of_two();
+        let mut product = self.evaluations(worker, size, kern)?;
+        let other = other.evaluations(worker, size, kern)?;
+        for (p, o) in product.iter_mut().zip(other.iter()) {
+            p.mul_assign(o);
+        }
+
+        let mut coeffs = interpolate(worker, product, kern)?;
+        coeffs.truncate(len);
+
+        Ok(Polynomial::new(coeffs))
+    }
+
+    /// Divides the polynomial by `divisor`, returning the quotient and the
+    /// remainder. Fails if `divisor` is the zero polynomial.
+    pub fn div_rem(&self, divisor: &Self) -> Result<(Self, Self), SynthesisError> {
+        let divisor_degree = divisor.degree().ok_or(SynthesisError::DivisionByZero)?;
+        let lead_inv = divisor.coeffs[divisor_degree]
+            .inverse()
+            .ok_or(SynthesisError::DivisionByZero)?;
+
+        let mut remainder = self.coeffs.clone();
+        let len = self.degree().map(|d| d + 1).unwrap_or(0);
+        remainder.truncate(len);
+        if len <= divisor_degree {
+            return Ok((Polynomial::zero(), Polynomial::new(remainder)));
+        }
+
+        let mut quotient = vec![E::Fr::zero(); len - divisor_degree];
+        for i in (0..quotient.len()).rev() {
+            let mut factor = remainder[i + divisor_degree];
+            factor.mul_assign(&lead_inv);
+            for (r, d) in remainder[i..]
+                .iter_mut()
+                .zip(divisor.coeffs[..=divisor_degree].iter())
+            {
+                let mut tmp = *d;
+                tmp.mul_assign(&factor);
+                r.sub_assign(&tmp);
+            }
+            quotient[i] = factor;
+        }
+        remainder.truncate(divisor_degree);
+
+        Ok((Polynomial::new(quotient), Polynomial::new(remainder)))
+    }
+
+    /// Divides the polynomial by `X - point`, dropping the remainder, which
+    /// is the evaluation at `point`.
+    pub fn divide_by_linear(&self, point: &E::Fr) -> Self {
+        Polynomial::new(divide_by_linear(&self.coeffs, point))
+    }
+
+    /// Divides the polynomial by `X^n - 1`, the vanishing polynomial of the
+    /// domain of size `n`, returning the quotient and the remainder.
+    pub fn divide_by_vanishing(&self, n: usize) -> (Self, Self) {
+        let (quotient, remainder) = divide_by_vanishing(&self.coeffs, n);
+
+        (Polynomial::new(quotient), Polynomial::new(remainder))
+    }
+
+    /// Commits to the polynomial with the powers of the secret of the SRS,
+    /// a KZG commitment.
+    pub fn commit(
+        &self,
+        worker: &Worker,
+        srs: &UniversalSrs<E>,
+        kern: &mut Option<gpu::MultiexpKernel<E>>,
+    ) -> Result<E::G1Affine, SynthesisError> {
+        commit(worker, &srs.g1_powers, &self.coeffs, kern)
+    }
+}
+
+/// Returns a primitive `2^log_n`-th root of unity.
+pub fn root_of_unity<F: PrimeField>(log_n: u32) -> F {
+    let mut omega = F::root_of_unity();
+    for _ in log_n..F::S {
+        omega.square();
+    }
+
+    omega
+}
+
+/// Converts a small integer into a field element.
+pub fn from_u64<F: PrimeField>(value: u64) -> F {
+    F::from_repr(F::Repr::from(value)).expect("small integers are in the field")
+}
+
+/// Interpolates the polynomial with the given evaluations over the domain of
+/// the `evaluations.len()`-th roots of unity.
+pub fn interpolate<E: Engine>(
+    worker: &Worker,
+    evaluations: Vec<E::Fr>,
+    kern: &mut Option<gpu::FFTKernel<E>>,
+) -> Result<Vec<E::Fr>, SynthesisError> {
+    let mut domain = EvaluationDomain::from_coeffs(evaluations.into_iter().map(Scalar).collect())?;
+    domain.ifft(worker, kern)?;
+
+    Ok(domain.into_coeffs().into_iter().map(|s| s.0).collect())
+}
+
+/// Evaluates the polynomial over the domain of the `size`-th roots of unity.
+pub fn evaluations<E: Engine>(
+    worker: &Worker,
+    coeffs: &[E::Fr],
+    size: usize,
+    kern: &mut Option<gpu::FFTKernel<E>>,
+) -> Result<Vec<E::Fr>, SynthesisError> {
+    let mut domain = padded_domain::<E>(coeffs, size)?;
+    domain.fft(worker, kern)?;
+
+    Ok(domain.into_coeffs().into_iter().map(|s| s.0).collect())
+}
+
+/// Evaluates the polynomial over the coset of the `size`-th roots of unity
+/// that is shifted by the multiplicative generator.
+pub fn coset_evaluations<E: Engine>(
+    worker: &Worker,
+    coeffs: &[E::Fr],
+    size: usize,
+    kern: &mut Option<gpu::FFTKernel<E>>,
+) -> Result<Vec<E::Fr>, SynthesisError> {
+    let mut domain = padded_domain::<E>(coeffs, size)?;
+    domain.coset_fft(worker, kern)?;
+
+    Ok(domain.into_coeffs().into_iter().map(|s| s.0).collect())
+}
+
+/// Interpolates the polynomial with the given evaluations over the coset of
+/// [`coset_evaluations`].
+pub fn coset_interpolate<E: Engine>(
+    worker: &Worker,
+    evaluations: Vec<E::Fr>,
+    kern: &mut Option<gpu::FFTKernel<E>>,
+) -> Result<Vec<E::Fr>, SynthesisError> {
+    let mut domain = EvaluationDomain::from_coeffs(evaluations.into_iter().map(Scalar).collect())?;
+    domain.icoset_fft(worker, kern)?;
+
+    Ok(domain.into_coeffs().into_iter().map(|s| s.0).collect())
+}
+
+fn padded_domain<E: Engine>(
+    coeffs: &[E::Fr],
+    size: usize,
+) -> Result<EvaluationDomain<E, Scalar<E>>, SynthesisError> {
+    if coeffs.len() > size {
+        return Err(SynthesisError::PolynomialDegreeTooLarge);
+    }
+
+    let mut padded = vec![Scalar(E::Fr::zero()); size];
+    for (p, c) in padded.iter_mut().zip(coeffs.iter()) {
+        p.0 = *c;
+    }
+
+    EvaluationDomain::from_coeffs(padded)
+}
+
+/// Evaluates the polynomial at `point`.
+pub fn evaluate<F: Field>(coeffs: &[F], point: &F) -> F {
+    let mut acc = F::zero();
+    for c in coeffs.iter().rev() {
+        acc.mul_assign(point);
+        acc.add_assign(c);
+    }
+
+    acc
+}
+
+/// Divides the polynomial by `X - point`, dropping the remainder, which is
+/// the evaluation at `point`.
+pub fn divide_by_linear<F: Field>(coeffs: &[F], point: &F) -> Vec<F> {
+    let mut quotient = vec![F::zero(); coeffs.len().saturating_sub(1)];
+    let mut acc = F::zero();
+    for (i, c) in coeffs.iter().enumerate().skip(1).rev() {
+        acc.mul_assign(point);
+        acc.add_assign(c);
+        quotient[i - 1] = acc;
+    }
+
+    quotient
+}
+
+/// Adds `blinding(X) * (X^n - 1)` to a polynomial of degree smaller than `n`,
+/// which doesn't change its evaluations over the domain.
+pub fn blind<F: Field>(mut coeffs: Vec<F>, blinding: &[F]) -> Vec<F> {
+    let n = coeffs.len();
+    coeffs.resize(n + blinding.len(), F::zero());
+    for (i, b) in blinding.iter().enumerate() {
+        coeffs[n + i].add_assign(b);
+        coeffs[i].sub_assign(b);
+    }
+
+    coeffs
+}
+
+/// Adds `scalar` times `other` to `poly`.
+pub fn add_scaled<F: Field>(poly: &mut Vec<F>, other: &[F], scalar: &F) {
+    if poly.len() < other.len() {
+        poly.resize(other.len(), F::zero());
+    }
+
+    for (p, o) in poly.iter_mut().zip(other.iter()) {
+        let mut tmp = *o;
+        tmp.mul_assign(scalar);
+        p.add_assign(&tmp);
+    }
+}
+
+/// Inverts all elements with a single inversion. Fails if any is zero.
+pub fn batch_invert<F: Field>(values: &mut [F]) -> Result<(), SynthesisError> {
+    let mut products = Vec::with_capacity(values.len());
+    let mut acc = F::one();
+    for v in values.iter() {
+        products.push(acc);
+        acc.mul_assign(v);
+    }
+
+    let mut inv = acc.inverse().ok_or(SynthesisError::UnexpectedIdentity)?;
+    for (v, p) in values.iter_mut().zip(products).rev() {
+        let mut tmp = inv;
+        tmp.mul_assign(&p);
+        inv.mul_assign(v);
+        *v = tmp;
+    }
+
+    Ok(())
+}
+
+/// Evaluates the Lagrange basis polynomials `L_0, ..., L_{count - 1}` of the
+/// domain of size `n` with generator `omega` at `point`. Fails if `point` is
+/// within the domain.
+pub fn lagrange_evaluations<F: PrimeField>(
+    n: usize,
+    omega: &F,
+    point: &F,
+    count: usize,
+) -> Result<Vec<F>, SynthesisError> {
+    // L_i(X) = omega^i (X^n - 1) / (n (X - omega^i))
+    let mut z = point.pow(&[n as u64]);
+    z.sub_assign(&F::one());
+
+    let mut omega_i = F::one();
+    let mut denominators = Vec::with_capacity(count);
+    let mut numerators = Vec::with_capacity(count);
+    for _ in 0..count {
+        let mut denominator = *point;
+        denominator.sub_assign(&omega_i);
+        denominator.mul_assign(&from_u64(n as u64));
+        denominators.push(denominator);
+
+        let mut numerator = z;
+        numerator.mul_assign(&omega_i);
+        numerators.push(numerator);
+
+        omega_i.mul_assign(omega);
+    }
+    batch_invert(&mut denominators)?;
+
+    Ok(numerators
+        .into_iter()
+        .zip(denominators)
+        .map(|(mut n, d)| {
+            n.mul_assign(&d);
+            n
+        })
+        .collect())
+}
+
+/// Divides the polynomial by `X^n - 1`, returning the quotient and the
+/// remainder.
+pub fn divide_by_vanishing<F: Field>(coeffs: &[F], n: usize) -> (Vec<F>, Vec<F>) {
+    if coeffs.len() <= n {
+        return (vec![], coeffs.to_vec());
+    }
+
+    // c_k = q_(k-n) - q_k + r_k, where r_k is zero from n on.
+    let mut quotient = coeffs[n..].to_vec();
+    for i in (0..quotient.len().saturating_sub(n)).rev() {
+        let tmp = quotient[i + n];
+        quotient[i].add_assign(&tmp);
+    }
+    let mut remainder = coeffs[..n].to_vec();
+    for (r, q) in remainder.iter_mut().zip(quotient.iter()) {
+        r.add_assign(q);
+    }
+
+    (quotient, remainder)
+}
+
+/// Commits to the polynomial with the powers of the secret of the SRS.
+pub fn commit<E: Engine>(
+    worker: &Worker,
+    powers: &Arc<Vec<E::G1Affine>>,
+    coeffs: &[E::Fr],
+    kern: &mut Option<gpu::MultiexpKernel<E>>,
+) -> Result<E::G1Affine, SynthesisError> {
+    commit_shifted(worker, powers, coeffs, 0, kern)
+}
+
+/// Commits to the polynomial multiplied by `X^shift`. Committing to it with
+/// the largest power of the SRS as the highest coefficient proves that the
+/// polynomial has at most the degree of that power minus the shift.
+pub fn commit_shifted<E: Engine>(
+    worker: &Worker,
+    powers: &Arc<Vec<E::G1Affine>>,
+    coeffs: &[E::Fr],
+    shift: usize,
+    kern: &mut Option<gpu::MultiexpKernel<E>>,
+) -> Result<E::G1Affine, SynthesisError> {
+    if shift + coeffs.len() > powers.len() {
+        return Err(SynthesisError::PolynomialDegreeTooLarge);
+    }
+
+    let exponents = Arc::new(coeffs.iter().map(|c| c.into_repr()).collect::<Vec<_>>());
+    let commitment = multiexp(
+        worker,
+        (powers.clone(), shift),
+        FullDensity,
+        exponents,
+        kern,
+    )
+    .wait()?;
+
+    Ok(commitment.into_affine())
+}
+
+#[test]
+fn test_polynomial_arithmetic() {
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut crate::testing::test_rng();
+    let worker = Worker::new();
+    let random = |rng: &mut rand::rngs::ThreadRng, len: usize| {
+        Polynomial::<Bls12>::new((0..len).map(|_| Fr::random(rng)).collect())
+    };
+
+    for &(len_a, len_b) in [(1, 1), (3, 5), (8, 8), (17, 4)].iter() {
+        let a = random(rng, len_a);
+        let b = random(rng, len_b);
+        let point = Fr::random(rng);
+
+        // The product evaluates to the product of the evaluations.
+        let product = a.mul(&worker, &b, &mut None).unwrap();
+        assert_eq!(product.degree(), Some(len_a + len_b - 2));
+        let mut expected = a.evaluate(&point);
+        expected.mul_assign(&b.evaluate(&point));
+        assert_eq!(product.evaluate(&point), expected);
+
+        // Dividing the product plus a small remainder recovers both.
+        let mut dividend = product.clone();
+        let remainder = random(rng, len_b - 1);
+        dividend.add_assign(&remainder);
+        let (quotient, rem) = dividend.div_rem(&b).unwrap();
+        assert_eq!(quotient, a);
+        assert_eq!(rem, remainder);
+
+        // Interpolation inverts evaluation.
+        let size = len_a.next_power_of_two();
+        let evaluations = a.evaluations(&worker, size, &mut None).unwrap();
+        assert_eq!(
+            Polynomial::interpolate(&worker, evaluations, &mut None).unwrap(),
+            a
+        );
+        let evaluations = a.coset_evaluations(&worker, size, &mut None).unwrap();
+        assert_eq!(
+            Polynomial::coset_interpolate(&worker, evaluations, &mut None).unwrap(),
+            a
+        );
+    }
+
+    assert!(random(rng, 3).div_rem(&Polynomial::zero()).is_err());
+}
@@ -0,0 +1,336 @@
+//! Translation of rank-1 constraint systems into Sonic's constraint system.
+
+use ff::{Field, ScalarEngine};
+
+use super::poly::Laurent;
+use crate::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// A wire of the multiplication gate with the given (zero-based) index.
+#[derive(Copy, Clone)]
+pub(crate) enum Wire {
+    A(usize),
+    B(usize),
+    C(usize),
+}
+
+/// A linear constraint `Σ coeff·wire = k`.
+pub(crate) struct LinearConstraint<E: ScalarEngine> {
+    pub terms: Vec<(Wire, E::Fr)>,
+    pub k: E::Fr,
+}
+
+/// Synthesizes a circuit into multiplication gates `a_i·b_i = c_i` and
+/// linear constraints over their wires.
+///
+/// Allocated variables fill the `a` and `b` wires of otherwise unconstrained
+/// gates. Every rank-1 constraint gets a gate of its own, whose wires are
+/// linked to the linear combinations by three linear constraints. Public
+/// inputs are linear constraints whose `k` is the value of the input.
+pub(crate) struct Assembly<E: ScalarEngine> {
+    pub num_gates: usize,
+    pub constraints: Vec<LinearConstraint<E>>,
+    /// The constraints that fix the public inputs, in the order of the inputs.
+    pub input_constraints: Vec<usize>,
+    /// The gate with a free `b` wire for the next allocation.
+    pending: Option<usize>,
+    inputs: Vec<Wire>,
+    aux: Vec<Wire>,
+    /// The assignment of the `a`, `b` and `c` wires, only tracked when
+    /// proving.
+    pub values: Option<[Vec<E::Fr>; 3]>,
+    pub input_values: Vec<E::Fr>,
+}
+
+impl<E: ScalarEngine> Assembly<E> {
+    pub fn new(proving: bool) -> Assembly<E> {
+        Assembly {
+            num_gates: 0,
+            constraints: vec![],
+            input_constraints: vec![],
+            pending: None,
+            // The `ONE` input is a constant, not a wire.
+            inputs: vec![Wire::A(0)],
+            aux: vec![],
+            values: if proving {
+                Some([vec![], vec![], vec![]])
+            } else {
+                None
+            },
+            input_values: vec![],
+        }
+    }
+
+    fn new_gate(&mut self) -> usize {
+        if let Some(values) = self.values.as_mut() {
+            for v in values.iter_mut() {
+                v.push(E::Fr::zero());
+            }
+        }
+        self.num_gates += 1;
+
+        self.num_gates - 1
+    }
+
+    fn value(&self, wire: Wire) -> Option<E::Fr> {
+        self.values.as_ref().map(|values| match wire {
+            Wire::A(i) => values[0][i],
+            Wire::B(i) => values[1][i],
+            Wire::C(i) => values[2][i],
+        })
+    }
+
+    fn set_value(&mut self, wire: Wire, value: E::Fr) {
+        if let Some(values) = self.values.as_mut() {
+            match wire {
+                Wire::A(i) => values[0][i] = value,
+                Wire::B(i) => values[1][i] = value,
+                Wire::C(i) => values[2][i] = value,
+            }
+        }
+    }
+
+    fn alloc_wire(&mut self, value: Option<E::Fr>) -> Wire {
+        match self.pending.take() {
+            Some(gate) => {
+                if let Some(value) = value {
+                    let mut product = self.value(Wire::A(gate)).unwrap();
+                    product.mul_assign(&value);
+                    self.set_value(Wire::B(gate), value);
+                    self.set_value(Wire::C(gate), product);
+                }
+                Wire::B(gate)
+            }
+            None => {
+                let gate = self.new_gate();
+                if let Some(value) = value {
+                    self.set_value(Wire::A(gate), value);
+                }
+                self.pending = Some(gate);
+                Wire::A(gate)
+            }
+        }
+    }
+
+    /// Links the linear combination to the wire, returning the value of the
+    /// linear combination when proving.
+    fn link(&mut self, lc: LinearCombination<E>, wire: Wire) -> Option<E::Fr> {
+        let mut value = self.values.as_ref().map(|_| E::Fr::zero());
+        let mut terms = vec![];
+        let mut k = E::Fr::zero();
+        for (var, coeff) in lc.simplify() {
+            let term = match var.get_unchecked() {
+                Index::Input(0) => {
+                    k.sub_assign(&coeff);
+                    if let Some(value) = value.as_mut() {
+                        value.add_assign(&coeff);
+                    }
+                    continue;
+                }
+                Index::Input(i) => self.inputs[i],
+                Index::Aux(i) => self.aux[i],
+            };
+            if let (Some(value), Some(mut tmp)) = (value.as_mut(), self.value(term)) {
+                tmp.mul_assign(&coeff);
+                value.add_assign(&tmp);
+            }
+            terms.push((term, coeff));
+        }
+
+        let mut minus_one = E::Fr::one();
+        minus_one.negate();
+        terms.push((wire, minus_one));
+        self.constraints.push(LinearConstraint { terms, k });
+
+        value
+    }
+
+    /// Evaluates `s(X, y)` in `X`, the polynomial encoding the linear
+    /// constraints and the multiplication gates.
+    pub fn s_poly(&self, y: &E::Fr) -> Result<Laurent<E::Fr>, SynthesisError> {
+        let n = self.num_gates as isize;
+        let mut s = Laurent::zero(-n, 2 * n);
+
+        // The coefficient of X^i and X^-i is a polynomial in Y, which
+        // starts at the power Y^(q + n) for linear constraint q.
+        let y_inv = y.inverse().ok_or(SynthesisError::UnexpectedIdentity)?;
+        let mut y_q = y.pow(&[self.num_gates as u64 + 1]);
+        for constraint in &self.constraints {
+            for &(wire, coeff) in &constraint.terms {
+                let mut tmp = coeff;
+                tmp.mul_assign(&y_q);
+                s.coeff_mut(exponent(wire, n)).add_assign(&tmp);
+            }
+            y_q.mul_assign(y);
+        }
+
+        // -Y^i - Y^-i for the c wires of the multiplication gates.
+        let mut y_i = *y;
+        let mut y_inv_i = y_inv;
+        for i in 1..=n {
+            let c = s.coeff_mut(i + n);
+            c.sub_assign(&y_i);
+            c.sub_assign(&y_inv_i);
+            y_i.mul_assign(y);
+            y_inv_i.mul_assign(&y_inv);
+        }
+
+        Ok(s)
+    }
+
+    /// Evaluates `s(z, y)` directly, without computing `s(X, y)`.
+    pub fn s_eval(&self, z: &E::Fr, y: &E::Fr) -> Result<E::Fr, SynthesisError> {
+        let n = self.num_gates;
+        let z_inv = z.inverse().ok_or(SynthesisError::UnexpectedIdentity)?;
+        let y_inv = y.inverse().ok_or(SynthesisError::UnexpectedIdentity)?;
+        let pow = |base: &E::Fr, inv: &E::Fr, exp: isize| {
+            if exp < 0 {
+                inv.pow(&[(-exp) as u64])
+            } else {
+                base.pow(&[exp as u64])
+            }
+        };
+
+        let mut s = E::Fr::zero();
+        let mut y_q = y.pow(&[n as u64 + 1]);
+        for constraint in &self.constraints {
+            let mut acc = E::Fr::zero();
+            for &(wire, coeff) in &constraint.terms {
+                let mut tmp = pow(z, &z_inv, exponent(wire, n as isize));
+                tmp.mul_assign(&coeff);
+                acc.add_assign(&tmp);
+            }
+            acc.mul_assign(&y_q);
+            s.add_assign(&acc);
+            y_q.mul_assign(y);
+        }
+
+        let mut z_i = z.pow(&[n as u64 + 1]);
+        let mut y_i = *y;
+        let mut y_inv_i = y_inv;
+        for _ in 0..n {
+            let mut tmp = y_i;
+            tmp.add_assign(&y_inv_i);
+            tmp.mul_assign(&z_i);
+            s.sub_assign(&tmp);
+            z_i.mul_assign(z);
+            y_i.mul_assign(y);
+            y_inv_i.mul_assign(&y_inv);
+        }
+
+        Ok(s)
+    }
+
+    /// Evaluates `k(y)`, the polynomial of the constants of the linear
+    /// constraints, for the given public inputs.
+    pub fn k_eval(&self, y: &E::Fr, public_inputs: &[E::Fr]) -> E::Fr {
+        let mut ks = self.constraints.iter().map(|c| c.k).collect::<Vec<_>>();
+        for (&q, input) in self.input_constraints.iter().zip(public_inputs) {
+            ks[q].add_assign(input);
+        }
+
+        let mut k = E::Fr::zero();
+        let mut y_q = y.pow(&[self.num_gates as u64 + 1]);
+        for mut tmp in ks {
+            tmp.mul_assign(&y_q);
+            k.add_assign(&tmp);
+            y_q.mul_assign(y);
+        }
+
+        k
+    }
+}
+
+/// The exponent of `X` of the wire in `s(X, Y)`.
+fn exponent(wire: Wire, n: isize) -> isize {
+    match wire {
+        Wire::A(i) => -(i as isize) - 1,
+        Wire::B(i) => i as isize + 1,
+        Wire::C(i) => i as isize + 1 + n,
+    }
+}
+
+impl<E: ScalarEngine> ConstraintSystem<E> for Assembly<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let value = match self.values {
+            Some(_) => Some(f()?),
+            None => None,
+        };
+        let wire = self.alloc_wire(value);
+        self.aux.push(wire);
+
+        Ok(Variable(Index::Aux(self.aux.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let value = match self.values {
+            Some(_) => Some(f()?),
+            None => None,
+        };
+        let wire = self.alloc_wire(value);
+        self.inputs.push(wire);
+        if let Some(value) = value {
+            self.input_values.push(value);
+        }
+
+        // The constant of the constraint is the value of the input, which
+        // is only added when the constants are evaluated.
+        self.input_constraints.push(self.constraints.len());
+        self.constraints.push(LinearConstraint {
+            terms: vec![(wire, E::Fr::one())],
+            k: E::Fr::zero(),
+        });
+
+        Ok(Variable(Index::Input(self.inputs.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        let gate = self.new_gate();
+        for (wire, lc) in [Wire::A(gate), Wire::B(gate), Wire::C(gate)]
+            .iter()
+            .zip(vec![
+                a(LinearCombination::zero()),
+                b(LinearCombination::zero()),
+                c(LinearCombination::zero()),
+            ])
+        {
+            if let Some(value) = self.link(lc, *wire) {
+                self.set_value(*wire, value);
+            }
+        }
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
@@ -0,0 +1,220 @@
+//! The [Sonic] proving system.
+//!
+//! Like [PLONK], Sonic needs no setup for a particular circuit: proofs of
+//! any small enough circuit are created and verified with the same [`Srs`],
+//! which can moreover be updated by anyone. Circuits are written against the
+//! same [`ConstraintSystem`] as for [Groth16]. The verifier synthesizes the
+//! circuit as well, there are no circuit-specific keys.
+//!
+//! This is the basic protocol without helpers, verification takes time
+//! linear in the size of the circuit.
+//!
+//! [Sonic]: https://eprint.iacr.org/2019/099
+//! [PLONK]: https://eprint.iacr.org/2019/953
+//! [Groth16]: crate::groth16
+//! [`ConstraintSystem`]: crate::ConstraintSystem
+
+use crate::bls::Engine;
+use ff::{Field, PrimeField, PrimeFieldRepr};
+use groupy::{CurveAffine, EncodedPoint};
+
+use std::io::{self, Read, Write};
+
+mod assembly;
+mod poly;
+mod prover;
+mod srs;
+mod transcript;
+mod verifier;
+
+pub use self::prover::*;
+pub use self::srs::*;
+pub use self::verifier::*;
+
+fn read_point<P: EncodedPoint, R: Read>(reader: &mut R) -> io::Result<P::Affine> {
+    let mut repr = P::empty();
+    reader.read_exact(repr.as_mut())?;
+
+    repr.into_affine()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_scalar<F: PrimeField, W: Write>(writer: &mut W, scalar: &F) -> io::Result<()> {
+    scalar.into_repr().write_be(writer)
+}
+
+fn read_scalar<F: PrimeField, R: Read>(reader: &mut R) -> io::Result<F> {
+    let mut repr = F::Repr::default();
+    repr.read_be(reader)?;
+
+    F::from_repr(repr).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[derive(Clone)]
+pub struct Proof<E: Engine> {
+    // Commitments to the wire polynomial r(X, 1) and to t(X, y).
+    pub r: E::G1Affine,
+    pub t: E::G1Affine,
+
+    // Evaluations of the wire polynomial at z and yz.
+    pub rz: E::Fr,
+    pub rzy: E::Fr,
+
+    // Opening proofs of t(X, y) batched with r(X, 1) at z and of r(X, 1) at
+    // yz.
+    pub z_opening: E::G1Affine,
+    pub zy_opening: E::G1Affine,
+}
+
+impl<E: Engine> PartialEq for Proof<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.commitments() == other.commitments() && self.evaluations() == other.evaluations()
+    }
+}
+
+impl<E: Engine> Proof<E> {
+    fn commitments(&self) -> [E::G1Affine; 4] {
+        [self.r, self.t, self.z_opening, self.zy_opening]
+    }
+
+    fn evaluations(&self) -> [E::Fr; 2] {
+        [self.rz, self.rzy]
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for commitment in self.commitments().iter() {
+            writer.write_all(commitment.into_compressed().as_ref())?;
+        }
+        for evaluation in self.evaluations().iter() {
+            write_scalar(&mut writer, evaluation)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut commitments = [E::G1Affine::zero(); 4];
+        for commitment in commitments.iter_mut() {
+            *commitment = read_point::<<E::G1Affine as CurveAffine>::Compressed, _>(&mut reader)?;
+        }
+        let mut evaluations = [E::Fr::zero(); 2];
+        for evaluation in evaluations.iter_mut() {
+            *evaluation = read_scalar(&mut reader)?;
+        }
+
+        Ok(Proof {
+            r: commitments[0],
+            t: commitments[1],
+            z_opening: commitments[2],
+            zy_opening: commitments[3],
+            rz: evaluations[0],
+            rzy: evaluations[1],
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_with_bls12_381 {
+    use super::*;
+    use crate::{Circuit, ConstraintSystem, SynthesisError};
+
+    use crate::bls::{Bls12, Fr};
+    use crate::testing::test_rng;
+    use ff::Field;
+
+    /// Proves knowledge of `x` with `x^3 + x + 5 = out` for the public `out`.
+    #[derive(Clone)]
+    struct CubeCircuit {
+        x: Option<Fr>,
+        out: Option<Fr>,
+    }
+
+    impl Circuit<Bls12> for CubeCircuit {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x_value = self.x;
+            let x = cs.alloc(|| "x", || x_value.ok_or(SynthesisError::AssignmentMissing))?;
+            let x2_value = x_value.map(|mut x| {
+                x.square();
+                x
+            });
+            let x2 = cs.alloc(
+                || "x2",
+                || x2_value.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            cs.enforce(|| "x2 = x * x", |lc| lc + x, |lc| lc + x, |lc| lc + x2);
+            let x3_value = x2_value.map(|mut x2| {
+                x2.mul_assign(&x_value.unwrap());
+                x2
+            });
+            let x3 = cs.alloc(
+                || "x3",
+                || x3_value.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            cs.enforce(|| "x3 = x2 * x", |lc| lc + x2, |lc| lc + x, |lc| lc + x3);
+
+            let out = cs.alloc_input(
+                || "out",
+                || self.out.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            let five = Fr::from_str("5").unwrap();
+            cs.enforce(
+                || "out = x3 + x + 5",
+                |lc| lc + x3 + x + (five, CS::one()),
+                |lc| lc + CS::one(),
+                |lc| lc + out,
+            );
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn cube() {
+        let rng = &mut test_rng();
+
+        let mut srs = Srs::<Bls12>::generate_random(64, rng);
+        // Updating the SRS keeps it working.
+        srs.update(Fr::random(rng), Fr::random(rng));
+
+        let blank = CubeCircuit { x: None, out: None };
+        let x = Fr::from_str("3").unwrap();
+        let out = Fr::from_str("35").unwrap();
+        let proof = create_random_proof(
+            CubeCircuit {
+                x: Some(x),
+                out: Some(out),
+            },
+            &srs,
+            rng,
+        )
+        .unwrap();
+
+        assert!(verify_proof(blank.clone(), &proof, &[out], &srs).unwrap());
+        assert!(!verify_proof(blank, &proof, &[Fr::from_str("36").unwrap()], &srs).unwrap());
+
+        let mut proof_bytes = vec![];
+        proof.write(&mut proof_bytes).unwrap();
+        assert_eq!(proof_bytes.len(), 4 * 48 + 2 * 32);
+        assert!(proof == Proof::read(&proof_bytes[..]).unwrap());
+
+        let mut srs_bytes = vec![];
+        srs.write(&mut srs_bytes).unwrap();
+        assert!(srs == Srs::read(&srs_bytes[..], true).unwrap());
+
+        // The prover refuses to prove a wrong output.
+        match create_random_proof(
+            CubeCircuit {
+                x: Some(x),
+                out: Some(Fr::from_str("36").unwrap()),
+            },
+            &srs,
+            rng,
+        ) {
+            Err(SynthesisError::Unsatisfiable) => {}
+            _ => panic!("proof of a wrong output"),
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Laurent polynomials and their commitments.
+
+use std::sync::Arc;
+
+use crate::bls::Engine;
+use ff::{Field, PrimeField};
+use groupy::CurveProjective;
+
+use super::Srs;
+use crate::domain::{EvaluationDomain, Scalar};
+use crate::gpu;
+use crate::multicore::Worker;
+use crate::multiexp::{multiexp, FullDensity};
+use crate::SynthesisError;
+
+/// A polynomial with negative powers, the coefficients of the powers of `X`
+/// starting at `X^min_degree`.
+#[derive(Clone)]
+pub(crate) struct Laurent<F: Field> {
+    pub min_degree: isize,
+    pub coeffs: Vec<F>,
+}
+
+impl<F: Field> Laurent<F> {
+    /// The zero polynomial with coefficients from `X^min_degree` up to
+    /// `X^max_degree`.
+    pub fn zero(min_degree: isize, max_degree: isize) -> Laurent<F> {
+        Laurent {
+            min_degree,
+            coeffs: vec![F::zero(); (max_degree - min_degree + 1) as usize],
+        }
+    }
+
+    pub fn max_degree(&self) -> isize {
+        self.min_degree + self.coeffs.len() as isize - 1
+    }
+
+    pub fn coeff_mut(&mut self, degree: isize) -> &mut F {
+        &mut self.coeffs[(degree - self.min_degree) as usize]
+    }
+
+    /// Multiplies every coefficient with `point` to the power of its degree,
+    /// turning `p(X)` into `p(point·X)`.
+    pub fn scale(&mut self, point: &F) -> Result<(), SynthesisError> {
+        let mut power = power(point, self.min_degree)?;
+        for c in self.coeffs.iter_mut() {
+            c.mul_assign(&power);
+            power.mul_assign(point);
+        }
+
+        Ok(())
+    }
+
+    /// Adds `scalar` times `other`, which must not have more coefficients.
+    pub fn add_scaled(&mut self, other: &Laurent<F>, scalar: &F) {
+        assert!(other.min_degree >= self.min_degree && other.max_degree() <= self.max_degree());
+        for (i, o) in other.coeffs.iter().enumerate() {
+            let mut tmp = *o;
+            tmp.mul_assign(scalar);
+            self.coeff_mut(other.min_degree + i as isize)
+                .add_assign(&tmp);
+        }
+    }
+
+    pub fn evaluate(&self, point: &F) -> Result<F, SynthesisError> {
+        let mut acc = F::zero();
+        for c in self.coeffs.iter().rev() {
+            acc.mul_assign(point);
+            acc.add_assign(c);
+        }
+        acc.mul_assign(&power(point, self.min_degree)?);
+
+        Ok(acc)
+    }
+
+    /// Divides `p(X) - p(point)` by `X - point`. The polynomial must have a
+    /// constant coefficient.
+    pub fn divide_by_linear(&self, point: &F) -> Result<Laurent<F>, SynthesisError> {
+        assert!(self.min_degree <= 0 && self.max_degree() >= 0);
+
+        // f(X) - f(point) = (X - point)·q(X), computed from the highest
+        // coefficient down.
+        let value = self.evaluate(point)?;
+        let mut quotient = vec![F::zero(); self.coeffs.len() - 1];
+        let mut acc = F::zero();
+        for (i, c) in self.coeffs.iter().enumerate().skip(1).rev() {
+            acc.mul_assign(point);
+            acc.add_assign(c);
+            if self.min_degree + i as isize == 0 {
+                acc.sub_assign(&value);
+            }
+            quotient[i - 1] = acc;
+        }
+
+        Ok(Laurent {
+            min_degree: self.min_degree,
+            coeffs: quotient,
+        })
+    }
+}
+
+/// `point` to the power of `exp`, which may be negative.
+pub(crate) fn power<F: Field>(point: &F, exp: isize) -> Result<F, SynthesisError> {
+    if exp < 0 {
+        Ok(point
+            .inverse()
+            .ok_or(SynthesisError::UnexpectedIdentity)?
+            .pow(&[(-exp) as u64]))
+    } else {
+        Ok(point.pow(&[exp as u64]))
+    }
+}
+
+/// Multiplies two polynomials with FFTs.
+pub(crate) fn multiply<E: Engine>(
+    worker: &Worker,
+    a: &Laurent<E::Fr>,
+    b: &Laurent<E::Fr>,
+    kern: &mut Option<gpu::FFTKernel<E>>,
+) -> Result<Laurent<E::Fr>, SynthesisError> {
+    let len = a.coeffs.len() + b.coeffs.len() - 1;
+    let padded = |p: &Laurent<E::Fr>| {
+        let mut coeffs = p.coeffs.iter().map(|c| Scalar(*c)).collect::<Vec<_>>();
+        coeffs.resize(len, Scalar(E::Fr::zero()));
+        EvaluationDomain::from_coeffs(coeffs)
+    };
+    let mut a_domain = padded(a)?;
+    let mut b_domain = padded(b)?;
+
+    a_domain.fft(worker, kern)?;
+    b_domain.fft(worker, kern)?;
+    a_domain.mul_assign(worker, &b_domain, kern)?;
+    a_domain.ifft(worker, kern)?;
+
+    let mut coeffs = a_domain
+        .into_coeffs()
+        .into_iter()
+        .map(|s| s.0)
+        .collect::<Vec<_>>();
+    coeffs.truncate(len);
+
+    Ok(Laurent {
+        min_degree: a.min_degree + b.min_degree,
+        coeffs,
+    })
+}
+
+/// Computes `Σ coeff·g^(x^degree)` with `negative[-degree - offset]` and
+/// `positive[degree - offset]` as the bases of the negative and the positive
+/// degrees. With an offset of one, the constant coefficient lacks a base and
+/// has to be zero.
+fn multiexp_laurent<E: Engine>(
+    worker: &Worker,
+    negative: &Arc<Vec<E::G1Affine>>,
+    positive: &Arc<Vec<E::G1Affine>>,
+    offset: isize,
+    poly: &Laurent<E::Fr>,
+    kern: &mut Option<gpu::MultiexpKernel<E>>,
+) -> Result<E::G1, SynthesisError> {
+    let coeff = |degree: isize| {
+        if degree < poly.min_degree || degree > poly.max_degree() {
+            E::Fr::zero()
+        } else {
+            poly.coeffs[(degree - poly.min_degree) as usize]
+        }
+    };
+    let negative_exps = (poly.min_degree..0)
+        .rev()
+        .map(|degree| coeff(degree).into_repr())
+        .collect::<Vec<_>>();
+    let first_positive = offset.max(poly.min_degree);
+    let positive_exps = (first_positive..=poly.max_degree())
+        .map(|degree| coeff(degree).into_repr())
+        .collect::<Vec<_>>();
+
+    let mut acc = E::G1::zero();
+    for (bases, start, exps) in vec![
+        (negative, (1 - offset) as usize, negative_exps),
+        (positive, (first_positive - offset) as usize, positive_exps),
+    ] {
+        if exps.is_empty() {
+            continue;
+        }
+        if start + exps.len() > bases.len() {
+            return Err(SynthesisError::PolynomialDegreeTooLarge);
+        }
+        let source = (bases.clone(), start);
+        acc.add_assign(&multiexp(worker, source, FullDensity, Arc::new(exps), kern).wait()?);
+    }
+
+    Ok(acc)
+}
+
+/// Commits to a polynomial with degree `max` at most, `g^(α·x^(d - max)·p(x))`.
+/// The SRS lacks `g^α`, so that the coefficient of `X^(max - d)` has to be
+/// zero.
+pub(crate) fn commit<E: Engine>(
+    worker: &Worker,
+    srs: &Srs<E>,
+    max: usize,
+    poly: &Laurent<E::Fr>,
+    kern: &mut Option<gpu::MultiexpKernel<E>>,
+) -> Result<E::G1Affine, SynthesisError> {
+    if max > srs.d || poly.max_degree() > max as isize {
+        return Err(SynthesisError::PolynomialDegreeTooLarge);
+    }
+
+    let mut shifted = poly.clone();
+    shifted.min_degree += (srs.d - max) as isize;
+    if shifted.min_degree <= 0 && shifted.max_degree() >= 0 && !shifted.coeff_mut(0).is_zero() {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+
+    // The bases of the shifted powers start at x^-1 and x^1.
+    let commitment = multiexp_laurent(
+        worker,
+        &srs.g_negative_x_alpha,
+        &srs.g_positive_x_alpha,
+        1,
+        &shifted,
+        kern,
+    )?;
+
+    Ok(commitment.into_affine())
+}
+
+/// Opens the polynomial at `point`, returning `g^((p(x) - p(point)) / (x - point))`.
+pub(crate) fn open<E: Engine>(
+    worker: &Worker,
+    srs: &Srs<E>,
+    poly: &Laurent<E::Fr>,
+    point: &E::Fr,
+    kern: &mut Option<gpu::MultiexpKernel<E>>,
+) -> Result<E::G1Affine, SynthesisError> {
+    let quotient = poly.divide_by_linear(point)?;
+    let opening = multiexp_laurent(
+        worker,
+        &srs.g_negative_x,
+        &srs.g_positive_x,
+        0,
+        &quotient,
+        kern,
+    )?;
+
+    Ok(opening.into_affine())
+}
@@ -0,0 +1,151 @@
+use rand_core::{CryptoRng, RngCore};
+
+use crate::bls::Engine;
+use ff::Field;
+use log::info;
+
+use super::assembly::Assembly;
+use super::poly::{commit, multiply, open, Laurent};
+use super::transcript::Transcript;
+use super::{Proof, Srs};
+
+use crate::domain::gpu_fft_supported;
+#[cfg(feature = "gpu")]
+use crate::gpu;
+use crate::multicore::Worker;
+use crate::multiexp::gpu_multiexp_supported;
+use crate::{Circuit, SynthesisError};
+
+/// Creates a proof, blinding the wire polynomial with random scalars.
+pub fn create_random_proof<E, C, R>(
+    circuit: C,
+    srs: &Srs<E>,
+    rng: &mut R,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+    R: RngCore + CryptoRng,
+{
+    let mut blinding = [E::Fr::zero(); 4];
+    for b in blinding.iter_mut() {
+        *b = E::Fr::random(rng);
+    }
+
+    create_proof::<E, C>(circuit, srs, blinding)
+}
+
+/// Creates a proof with the given blinding scalars, the coefficients of the
+/// lowest powers of the wire polynomial, which hide its four evaluations.
+pub fn create_proof<E, C>(
+    circuit: C,
+    srs: &Srs<E>,
+    blinding: [E::Fr; 4],
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    let mut assembly = Assembly::<E>::new(true);
+    circuit.synthesize(&mut assembly)?;
+    let n = assembly.num_gates;
+    if 4 * n + 8 > srs.d {
+        return Err(SynthesisError::PolynomialDegreeTooLarge);
+    }
+    let values = assembly
+        .values
+        .as_ref()
+        .ok_or(SynthesisError::AssignmentMissing)?;
+
+    #[cfg(feature = "gpu")]
+    let lock = gpu::lock()?;
+
+    // The product of the wire polynomial and the one shifted by y has
+    // 7n + 9 coefficients.
+    let mut log_d = 0;
+    while (1 << log_d) < 7 * n + 9 {
+        log_d += 1;
+    }
+    let mut fft_kern = gpu_fft_supported::<E>(log_d).ok();
+    if fft_kern.is_some() {
+        info!("GPU FFT is supported!");
+    } else {
+        info!("GPU FFT is NOT supported!");
+    }
+    let mut multiexp_kern = gpu_multiexp_supported::<E>().ok();
+    if multiexp_kern.is_some() {
+        info!("GPU Multiexp is supported!");
+    } else {
+        info!("GPU Multiexp is NOT supported!");
+    }
+
+    let worker = Worker::new();
+    let mut transcript = Transcript::new(&assembly.input_values);
+
+    // Commit to r(X, 1) = Σ a_i X^i + b_i X^-i + c_i X^(-i-n), blinded with
+    // the coefficients of X^(-2n-1) to X^(-2n-4).
+    let n = n as isize;
+    let mut r = Laurent::zero(-2 * n - 4, n);
+    for (i, ((a, b), c)) in values[0]
+        .iter()
+        .zip(values[1].iter())
+        .zip(values[2].iter())
+        .enumerate()
+    {
+        let i = i as isize + 1;
+        *r.coeff_mut(i) = *a;
+        *r.coeff_mut(-i) = *b;
+        *r.coeff_mut(-i - n) = *c;
+    }
+    for (j, b) in blinding.iter().enumerate() {
+        *r.coeff_mut(-2 * n - 1 - j as isize) = *b;
+    }
+    let r_commitment = commit(&worker, srs, n as usize, &r, &mut multiexp_kern)?;
+    transcript.append_point(&r_commitment);
+
+    let y = transcript.challenge::<E::Fr>();
+
+    // Commit to t(X, y) = r(X, 1)·(r(X, y) + s(X, y)) - k(y), whose constant
+    // term vanishes if the constraints are satisfied.
+    let mut r_y = r.clone();
+    r_y.scale(&y)?;
+    let mut r_prime = Laurent::zero(-2 * n - 4, 2 * n);
+    r_prime.add_scaled(&r_y, &E::Fr::one());
+    r_prime.add_scaled(&assembly.s_poly(&y)?, &E::Fr::one());
+    let mut t = multiply(&worker, &r, &r_prime, &mut fft_kern)?;
+    let constant = t.coeff_mut(0);
+    constant.sub_assign(&assembly.k_eval(&y, &assembly.input_values));
+    if !constant.is_zero() {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    let t_commitment = commit(&worker, srs, srs.d, &t, &mut multiexp_kern)?;
+    transcript.append_point(&t_commitment);
+
+    let z = transcript.challenge::<E::Fr>();
+
+    // Open r(X, 1) at z batched with t(X, y), and at yz, which is r(z, y).
+    let rz = r.evaluate(&z)?;
+    let rzy = r_y.evaluate(&z)?;
+    transcript.append_scalar(&rz);
+    transcript.append_scalar(&rzy);
+
+    let c = transcript.challenge::<E::Fr>();
+
+    t.add_scaled(&r, &c);
+    let z_opening = open(&worker, srs, &t, &z, &mut multiexp_kern)?;
+    let mut yz = y;
+    yz.mul_assign(&z);
+    let zy_opening = open(&worker, srs, &r, &yz, &mut multiexp_kern)?;
+
+    #[cfg(feature = "gpu")]
+    gpu::unlock(lock);
+
+    Ok(Proof {
+        r: r_commitment,
+        t: t_commitment,
+        rz,
+        rzy,
+        z_opening,
+        zy_opening,
+    })
+}
@@ -0,0 +1,234 @@
+use rand_core::RngCore;
+
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use crate::bls::Engine;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective, EncodedPoint};
+
+use crate::multicore::Worker;
+
+/// The structured reference string of Sonic, the positive and negative powers
+/// of a secret `x` up to `d`, with and without a second secret `α`.
+///
+/// It is universal, the same SRS is used for any circuit with at most
+/// `(d - 8) / 4` multiplication gates, and updatable: anyone can mix further
+/// secrets into it with [`Srs::update`], after which it is sound as long as
+/// one of the contributors discarded their secrets.
+#[derive(Clone)]
+pub struct Srs<E: Engine> {
+    pub d: usize,
+
+    // x^-i and x^i in G1 for i between 0 and d inclusive.
+    pub g_negative_x: Arc<Vec<E::G1Affine>>,
+    pub g_positive_x: Arc<Vec<E::G1Affine>>,
+
+    // α·x^-i and α·x^i in G1 for i between 1 and d inclusive. Without α·x^0,
+    // which would allow to commit to polynomials of a higher degree.
+    pub g_negative_x_alpha: Arc<Vec<E::G1Affine>>,
+    pub g_positive_x_alpha: Arc<Vec<E::G1Affine>>,
+
+    // x^-i, x^i, α·x^-i and α·x^i in G2 for i between 0 and d inclusive.
+    pub h_negative_x: Vec<E::G2Affine>,
+    pub h_positive_x: Vec<E::G2Affine>,
+    pub h_negative_x_alpha: Vec<E::G2Affine>,
+    pub h_positive_x_alpha: Vec<E::G2Affine>,
+}
+
+impl<E: Engine> PartialEq for Srs<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.d == other.d
+            && self.g_negative_x == other.g_negative_x
+            && self.g_positive_x == other.g_positive_x
+            && self.g_negative_x_alpha == other.g_negative_x_alpha
+            && self.g_positive_x_alpha == other.g_positive_x_alpha
+            && self.h_negative_x == other.h_negative_x
+            && self.h_positive_x == other.h_positive_x
+            && self.h_negative_x_alpha == other.h_negative_x_alpha
+            && self.h_positive_x_alpha == other.h_positive_x_alpha
+    }
+}
+
+impl<E: Engine> Srs<E> {
+    /// An SRS with both secrets set to one, which [`Srs::update`] turns into
+    /// a real one.
+    pub fn dummy(d: usize) -> Srs<E> {
+        let g1 = E::G1Affine::one();
+        let g2 = E::G2Affine::one();
+
+        Srs {
+            d,
+            g_negative_x: Arc::new(vec![g1; d + 1]),
+            g_positive_x: Arc::new(vec![g1; d + 1]),
+            g_negative_x_alpha: Arc::new(vec![g1; d]),
+            g_positive_x_alpha: Arc::new(vec![g1; d]),
+            h_negative_x: vec![g2; d + 1],
+            h_positive_x: vec![g2; d + 1],
+            h_negative_x_alpha: vec![g2; d + 1],
+            h_positive_x_alpha: vec![g2; d + 1],
+        }
+    }
+
+    /// Generates an SRS from random secrets. Anyone who knows the secrets can
+    /// create proofs of false statements, so production deployments update
+    /// the SRS in a multiparty computation instead.
+    pub fn generate_random<R: RngCore>(d: usize, rng: &mut R) -> Srs<E> {
+        Srs::new(d, E::Fr::random(rng), E::Fr::random(rng))
+    }
+
+    /// Generates an SRS from the given secrets.
+    pub fn new(d: usize, x: E::Fr, alpha: E::Fr) -> Srs<E> {
+        let mut srs = Srs::dummy(d);
+        srs.update(x, alpha);
+
+        srs
+    }
+
+    /// Multiplies the secrets of the SRS with `x` and `alpha`.
+    ///
+    /// # Panics
+    ///
+    /// If either of them is zero.
+    pub fn update(&mut self, x: E::Fr, alpha: E::Fr) {
+        let worker = Worker::new();
+        let one = E::Fr::one();
+        let x_inv = x.inverse().expect("x must not be zero");
+        assert!(!alpha.is_zero(), "alpha must not be zero");
+        let mut alpha_x = alpha;
+        alpha_x.mul_assign(&x);
+        let mut alpha_x_inv = alpha;
+        alpha_x_inv.mul_assign(&x_inv);
+
+        scale(&worker, Arc::make_mut(&mut self.g_negative_x), one, x_inv);
+        scale(&worker, Arc::make_mut(&mut self.g_positive_x), one, x);
+        scale(
+            &worker,
+            Arc::make_mut(&mut self.g_negative_x_alpha),
+            alpha_x_inv,
+            x_inv,
+        );
+        scale(
+            &worker,
+            Arc::make_mut(&mut self.g_positive_x_alpha),
+            alpha_x,
+            x,
+        );
+        scale(&worker, &mut self.h_negative_x, one, x_inv);
+        scale(&worker, &mut self.h_positive_x, one, x);
+        scale(&worker, &mut self.h_negative_x_alpha, alpha, x_inv);
+        scale(&worker, &mut self.h_positive_x_alpha, alpha, x);
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<BigEndian>(self.d as u32)?;
+        for points in [
+            &self.g_negative_x,
+            &self.g_positive_x,
+            &self.g_negative_x_alpha,
+            &self.g_positive_x_alpha,
+        ]
+        .iter()
+        {
+            for g in points.iter() {
+                writer.write_all(g.into_uncompressed().as_ref())?;
+            }
+        }
+        for points in [
+            &self.h_negative_x,
+            &self.h_positive_x,
+            &self.h_negative_x_alpha,
+            &self.h_positive_x_alpha,
+        ]
+        .iter()
+        {
+            for h in points.iter() {
+                writer.write_all(h.into_uncompressed().as_ref())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R, checked: bool) -> io::Result<Self> {
+        fn read_points<G: CurveAffine, R: Read>(
+            reader: &mut R,
+            len: usize,
+            checked: bool,
+        ) -> io::Result<Vec<G>> {
+            let mut points = Vec::with_capacity(len);
+            for _ in 0..len {
+                let mut repr = G::Uncompressed::empty();
+                reader.read_exact(repr.as_mut())?;
+
+                let point = if checked {
+                    repr.into_affine()
+                } else {
+                    repr.into_affine_unchecked()
+                }
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                if point.is_zero() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "point at infinity",
+                    ));
+                }
+                points.push(point);
+            }
+
+            Ok(points)
+        }
+
+        let d = reader.read_u32::<BigEndian>()? as usize;
+        let g_negative_x = read_points(&mut reader, d + 1, checked)?;
+        let g_positive_x = read_points(&mut reader, d + 1, checked)?;
+        let g_negative_x_alpha = read_points(&mut reader, d, checked)?;
+        let g_positive_x_alpha = read_points(&mut reader, d, checked)?;
+        let h_negative_x = read_points(&mut reader, d + 1, checked)?;
+        let h_positive_x = read_points(&mut reader, d + 1, checked)?;
+        let h_negative_x_alpha = read_points(&mut reader, d + 1, checked)?;
+        let h_positive_x_alpha = read_points(&mut reader, d + 1, checked)?;
+
+        Ok(Srs {
+            d,
+            g_negative_x: Arc::new(g_negative_x),
+            g_positive_x: Arc::new(g_positive_x),
+            g_negative_x_alpha: Arc::new(g_negative_x_alpha),
+            g_positive_x_alpha: Arc::new(g_positive_x_alpha),
+            h_negative_x,
+            h_positive_x,
+            h_negative_x_alpha,
+            h_positive_x_alpha,
+        })
+    }
+}
+
+/// Multiplies the `i`-th point with `first·step^i`.
+fn scale<G: CurveAffine>(worker: &Worker, points: &mut [G], first: G::Scalar, step: G::Scalar) {
+    let mut projective = points
+        .iter()
+        .map(|p| p.into_projective())
+        .collect::<Vec<_>>();
+
+    worker.scope(projective.len(), |scope, chunk| {
+        for (i, projective) in projective.chunks_mut(chunk).enumerate() {
+            scope.spawn(move |_scope| {
+                let mut current = step.pow(&[(i * chunk) as u64]);
+                current.mul_assign(&first);
+
+                for p in projective.iter_mut() {
+                    p.mul_assign(current.into_repr());
+                    current.mul_assign(&step);
+                }
+
+                // Batch normalize
+                G::Projective::batch_normalization(projective);
+            });
+        }
+    });
+
+    for (p, q) in points.iter_mut().zip(projective) {
+        *p = q.into_affine();
+    }
+}
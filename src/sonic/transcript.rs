@@ -0,0 +1,67 @@
+//! The Fiat-Shamir transcript deriving the verifier challenges.
+
+use blake2s_simd::{Params, State};
+use byteorder::{BigEndian, ByteOrder};
+use ff::PrimeField;
+use groupy::CurveAffine;
+
+use super::write_scalar;
+use crate::poly::from_u64;
+
+/// Personalization of the BLAKE2s hash of the transcript.
+const TRANSCRIPT_PERSONALIZATION: &[u8; 8] = b"Sonic_FS";
+
+/// Hashes everything the prover sent so far. The challenges are derived from
+/// the hash, which is then absorbed as well, so that consecutive challenges
+/// differ.
+pub(crate) struct Transcript {
+    state: State,
+}
+
+impl Transcript {
+    /// Starts a transcript for a proof with the given public inputs.
+    pub fn new<F: PrimeField>(public_inputs: &[F]) -> Transcript {
+        let mut transcript = Transcript {
+            state: Params::new()
+                .hash_length(32)
+                .personal(TRANSCRIPT_PERSONALIZATION)
+                .to_state(),
+        };
+
+        let mut len = [0; 4];
+        BigEndian::write_u32(&mut len, public_inputs.len() as u32);
+        transcript.state.update(&len);
+        for input in public_inputs {
+            transcript.append_scalar(input);
+        }
+
+        transcript
+    }
+
+    pub fn append_point<G: CurveAffine>(&mut self, point: &G) {
+        self.state.update(point.into_compressed().as_ref());
+    }
+
+    pub fn append_scalar<F: PrimeField>(&mut self, scalar: &F) {
+        let mut bytes = vec![];
+        write_scalar(&mut bytes, scalar).expect("writing to a vector never fails");
+        self.state.update(&bytes);
+    }
+
+    pub fn challenge<F: PrimeField>(&mut self) -> F {
+        let hash = self.state.clone().finalize();
+        self.state.update(hash.as_bytes());
+
+        // Interpret the hash as a big-endian integer, reduced modulo the
+        // characteristic of the field.
+        let mut shift = from_u64::<F>(1 << 32);
+        shift.square();
+        let mut challenge = F::zero();
+        for word in hash.as_bytes().chunks(8) {
+            challenge.mul_assign(&shift);
+            challenge.add_assign(&from_u64(BigEndian::read_u64(word)));
+        }
+
+        challenge
+    }
+}
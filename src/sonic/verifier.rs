@@ -0,0 +1,109 @@
+use crate::bls::{Engine, PairingCurveAffine};
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective};
+
+use super::assembly::Assembly;
+use super::transcript::Transcript;
+use super::{Proof, Srs};
+use crate::{Circuit, SynthesisError};
+
+/// Verifies a proof of the circuit, which is synthesized without assignments
+/// to evaluate `s(z, y)`.
+pub fn verify_proof<E, C>(
+    circuit: C,
+    proof: &Proof<E>,
+    public_inputs: &[E::Fr],
+    srs: &Srs<E>,
+) -> Result<bool, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    let mut assembly = Assembly::<E>::new(false);
+    circuit.synthesize(&mut assembly)?;
+    if public_inputs.len() != assembly.input_constraints.len() {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+    let n = assembly.num_gates;
+    if 4 * n + 8 > srs.d {
+        return Err(SynthesisError::PolynomialDegreeTooLarge);
+    }
+
+    // Recompute the challenges of the prover.
+    let mut transcript = Transcript::new(public_inputs);
+    transcript.append_point(&proof.r);
+    let y = transcript.challenge::<E::Fr>();
+    transcript.append_point(&proof.t);
+    let z = transcript.challenge::<E::Fr>();
+    transcript.append_scalar(&proof.rz);
+    transcript.append_scalar(&proof.rzy);
+    let c = transcript.challenge::<E::Fr>();
+
+    // t(z, y) = r(z, 1)·(r(z, y) + s(z, y)) - k(y)
+    let mut t = proof.rzy;
+    t.add_assign(&assembly.s_eval(&z, &y)?);
+    t.mul_assign(&proof.rz);
+    t.sub_assign(&assembly.k_eval(&y, public_inputs));
+
+    let g = srs.g_positive_x[0];
+    let h = srs.h_positive_x[0];
+    let h_alpha = srs.h_positive_x_alpha[0].prepare();
+    let h_alpha_x = srs.h_positive_x_alpha[1].prepare();
+    // Undoes the shift of the commitment to r(X, 1) by x^(d - n).
+    let h_shift = srs.h_negative_x[srs.d - n].prepare();
+
+    // An opening W of p at point with the value v is checked with the
+    // pairing equation
+    // e(W, α·x) = e(p - v·1 + point·W, α)
+    // ... which we rearrange to use a single final exponentiation:
+    // e(W, α·x)·e(v·1 - point·W, α)·e(-p, α) = 1
+    // The commitments are to α·p(x) already, so the last pairing is with 1
+    // or the inverse of the shift.
+
+    // The opening of t(X, y) + c·r(X, 1) at z.
+    let mut value = proof.rz;
+    value.mul_assign(&c);
+    value.add_assign(&t);
+    let mut lhs = g.mul(value.into_repr());
+    let mut neg_z = z;
+    neg_z.negate();
+    lhs.add_assign(&proof.z_opening.mul(neg_z.into_repr()));
+    let mut neg_t = proof.t;
+    neg_t.negate();
+    let mut neg_c_r = proof.r.mul(c.into_repr());
+    neg_c_r.negate();
+
+    let z_valid = E::final_exponentiation(&E::miller_loop(
+        [
+            (&proof.z_opening.prepare(), &h_alpha_x),
+            (&lhs.into_affine().prepare(), &h_alpha),
+            (&neg_t.prepare(), &h.prepare()),
+            (&neg_c_r.into_affine().prepare(), &h_shift),
+        ]
+        .iter(),
+    ))
+    .unwrap()
+        == E::Fqk::one();
+
+    // The opening of r(X, 1) at yz.
+    let mut neg_yz = y;
+    neg_yz.mul_assign(&z);
+    neg_yz.negate();
+    let mut lhs = g.mul(proof.rzy.into_repr());
+    lhs.add_assign(&proof.zy_opening.mul(neg_yz.into_repr()));
+    let mut neg_r = proof.r;
+    neg_r.negate();
+
+    let zy_valid = E::final_exponentiation(&E::miller_loop(
+        [
+            (&proof.zy_opening.prepare(), &h_alpha_x),
+            (&lhs.into_affine().prepare(), &h_alpha),
+            (&neg_r.prepare(), &h_shift),
+        ]
+        .iter(),
+    ))
+    .unwrap()
+        == E::Fqk::one();
+
+    Ok(z_valid && zy_valid)
+}
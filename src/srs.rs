@@ -0,0 +1,284 @@
+//! Universal structured reference strings, the powers of a secret in G1 and
+//! G2 which polynomial commitments and the universal-setup backends are built
+//! on.
+
+use rand_core::RngCore;
+
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use crate::bls::{Engine, PairingCurveAffine};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective, EncodedPoint, Wnaf};
+
+use crate::multicore::Worker;
+use crate::multiexp::{multiexp, FullDensity};
+use crate::SynthesisError;
+
+/// A universal structured reference string, the powers of a secret `x` in
+/// G1 and `x` in G2.
+///
+/// Unlike the Groth16 parameters, it doesn't depend on a circuit: the
+/// parameters of any circuit with small enough polynomials can be derived
+/// from it, with the `generate_parameters` of the PLONK or the Marlin
+/// backend.
+#[derive(Clone)]
+pub struct UniversalSrs<E: Engine> {
+    /// `x^i` in G1 for `i` between 0 and the maximal degree inclusive. Never
+    /// contains points at infinity.
+    pub g1_powers: Arc<Vec<E::G1Affine>>,
+
+    /// The generator of G2.
+    pub g2: E::G2Affine,
+
+    /// `x` in G2.
+    pub g2_x: E::G2Affine,
+}
+
+impl<E: Engine> PartialEq for UniversalSrs<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.g1_powers == other.g1_powers && self.g2 == other.g2 && self.g2_x == other.g2_x
+    }
+}
+
+impl<E: Engine> UniversalSrs<E> {
+    /// Generates an SRS from a random secret. Anyone who knows the secret can
+    /// create proofs of false statements, so production deployments use an
+    /// SRS from a multiparty computation instead.
+    pub fn generate_random<R: RngCore>(max_degree: usize, rng: &mut R) -> UniversalSrs<E> {
+        UniversalSrs::generate(max_degree, E::Fr::random(rng))
+    }
+
+    /// Generates an SRS from the given secret.
+    pub fn generate(max_degree: usize, x: E::Fr) -> UniversalSrs<E> {
+        let worker = Worker::new();
+
+        let mut g1_wnaf = Wnaf::new();
+        let g1_wnaf = g1_wnaf.base(E::G1::one(), max_degree + 1);
+
+        let mut g1_powers = vec![E::G1::zero(); max_degree + 1];
+        worker.scope(g1_powers.len(), |scope, chunk| {
+            for (i, g1_powers) in g1_powers.chunks_mut(chunk).enumerate() {
+                let mut g1_wnaf = g1_wnaf.shared();
+
+                scope.spawn(move |_scope| {
+                    let mut current_x_power = x.pow(&[(i * chunk) as u64]);
+
+                    for p in g1_powers.iter_mut() {
+                        *p = g1_wnaf.scalar(current_x_power.into_repr());
+                        current_x_power.mul_assign(&x);
+                    }
+
+                    // Batch normalize
+                    E::G1::batch_normalization(g1_powers);
+                });
+            }
+        });
+
+        UniversalSrs {
+            g1_powers: Arc::new(g1_powers.into_iter().map(|p| p.into_affine()).collect()),
+            g2: E::G2Affine::one(),
+            g2_x: E::G2Affine::one().mul(x.into_repr()).into_affine(),
+        }
+    }
+
+    /// The maximal degree of the polynomials that can be committed to.
+    pub fn max_degree(&self) -> usize {
+        self.g1_powers.len() - 1
+    }
+
+    /// Returns the SRS for polynomials of at most the given degree, which
+    /// keeps only the powers needed to commit to them.
+    pub fn truncate(&self, max_degree: usize) -> Result<UniversalSrs<E>, SynthesisError> {
+        if max_degree > self.max_degree() {
+            return Err(SynthesisError::PolynomialDegreeTooLarge);
+        }
+
+        Ok(UniversalSrs {
+            g1_powers: Arc::new(self.g1_powers[..=max_degree].to_vec()),
+            g2: self.g2,
+            g2_x: self.g2_x,
+        })
+    }
+
+    /// Checks that the SRS consists of consecutive powers of the same secret,
+    /// which an SRS from an untrusted source, e.g. read unchecked or
+    /// truncated from a larger one, might not.
+    ///
+    /// All pairs of consecutive powers are checked at once, as
+    /// `e(Σ r_i·x^(i+1), 1) = e(Σ r_i·x^i, x)` with random `r_i`.
+    pub fn verify<R: RngCore>(&self, rng: &mut R) -> Result<bool, SynthesisError> {
+        if self.g1_powers.iter().any(|g| g.is_zero()) || self.g2.is_zero() || self.g2_x.is_zero() {
+            return Ok(false);
+        }
+        if self.g1_powers.len() < 2 {
+            return Ok(true);
+        }
+
+        let worker = Worker::new();
+        let exponents = Arc::new(
+            (1..self.g1_powers.len())
+                .map(|_| E::Fr::random(rng).into_repr())
+                .collect::<Vec<_>>(),
+        );
+        let shifted = multiexp(
+            &worker,
+            (self.g1_powers.clone(), 1),
+            FullDensity,
+            exponents.clone(),
+            &mut None,
+        )
+        .wait()?;
+        let mut powers = multiexp(
+            &worker,
+            (self.g1_powers.clone(), 0),
+            FullDensity,
+            exponents,
+            &mut None,
+        )
+        .wait()?;
+        powers.negate();
+
+        Ok(E::final_exponentiation(&E::miller_loop(
+            [
+                (&shifted.into_affine().prepare(), &self.g2.prepare()),
+                (&powers.into_affine().prepare(), &self.g2_x.prepare()),
+            ]
+            .iter(),
+        ))
+        .unwrap()
+            == E::Fqk::one())
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<BigEndian>(self.g1_powers.len() as u32)?;
+        for g in &self.g1_powers[..] {
+            writer.write_all(g.into_uncompressed().as_ref())?;
+        }
+        writer.write_all(self.g2.into_uncompressed().as_ref())?;
+        writer.write_all(self.g2_x.into_uncompressed().as_ref())?;
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: R, checked: bool) -> io::Result<Self> {
+        Self::read_truncated(reader, usize::max_value(), checked)
+    }
+
+    /// Reads an SRS, keeping only the powers up to the given degree, so that
+    /// the parameters of small circuits can be derived from a large SRS
+    /// without holding all of it in memory.
+    pub fn read_truncated<R: Read>(
+        mut reader: R,
+        max_degree: usize,
+        checked: bool,
+    ) -> io::Result<Self> {
+        let read_g1 = |reader: &mut R| -> io::Result<E::G1Affine> {
+            let mut repr = <E::G1Affine as CurveAffine>::Uncompressed::empty();
+            reader.read_exact(repr.as_mut())?;
+
+            if checked {
+                repr.into_affine()
+            } else {
+                repr.into_affine_unchecked()
+            }
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            .and_then(|e| {
+                if e.is_zero() {
+                    Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "point at infinity",
+                    ))
+                } else {
+                    Ok(e)
+                }
+            })
+        };
+
+        let read_g2 = |reader: &mut R| -> io::Result<E::G2Affine> {
+            let mut repr = <E::G2Affine as CurveAffine>::Uncompressed::empty();
+            reader.read_exact(repr.as_mut())?;
+
+            if checked {
+                repr.into_affine()
+            } else {
+                repr.into_affine_unchecked()
+            }
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            .and_then(|e| {
+                if e.is_zero() {
+                    Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "point at infinity",
+                    ))
+                } else {
+                    Ok(e)
+                }
+            })
+        };
+
+        let len = reader.read_u32::<BigEndian>()? as usize;
+        if len == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "no powers in the SRS",
+            ));
+        }
+        let kept = len.min(max_degree.saturating_add(1));
+        let mut g1_powers = Vec::with_capacity(kept);
+        for _ in 0..kept {
+            g1_powers.push(read_g1(&mut reader)?);
+        }
+        let skipped = ((len - kept) * <E::G1Affine as CurveAffine>::Uncompressed::size()) as u64;
+        if io::copy(&mut (&mut reader).take(skipped), &mut io::sink())? != skipped {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "too few powers in the SRS",
+            ));
+        }
+        let g2 = read_g2(&mut reader)?;
+        let g2_x = read_g2(&mut reader)?;
+
+        Ok(UniversalSrs {
+            g1_powers: Arc::new(g1_powers),
+            g2,
+            g2_x,
+        })
+    }
+}
+
+#[test]
+fn test_srs_verify_and_truncate() {
+    use crate::bls::Bls12;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let srs = UniversalSrs::<Bls12>::generate_random(16, &mut rng);
+    assert!(srs.verify(&mut rng).unwrap());
+
+    let truncated = srs.truncate(8).unwrap();
+    assert_eq!(truncated.max_degree(), 8);
+    assert!(truncated.verify(&mut rng).unwrap());
+    assert!(srs.truncate(17).is_err());
+
+    let mut bytes = vec![];
+    srs.write(&mut bytes).unwrap();
+    assert!(srs == UniversalSrs::read(&bytes[..], true).unwrap());
+    assert!(truncated == UniversalSrs::read_truncated(&bytes[..], 8, true).unwrap());
+    assert!(UniversalSrs::<Bls12>::read_truncated(&bytes[..bytes.len() - 1], 8, true).is_err());
+
+    // Swapping two powers breaks the consistency of the SRS.
+    let mut g1_powers = srs.g1_powers.to_vec();
+    g1_powers.swap(3, 4);
+    let broken = UniversalSrs::<Bls12> {
+        g1_powers: Arc::new(g1_powers),
+        ..srs
+    };
+    assert!(!broken.verify(&mut rng).unwrap());
+}
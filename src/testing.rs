@@ -0,0 +1,15 @@
+//! Helpers for reproducible tests of circuits and of code built on top of the
+//! crate, with the `testing` feature: a seeded RNG, small fixed Groth16
+//! parameters and property-based testing of gadgets with [proptest].
+//!
+//! [proptest]: https://docs.rs/proptest
+
+#[cfg(feature = "groth16")]
+pub mod fixtures;
+#[cfg(feature = "testing")]
+mod properties;
+mod rng;
+
+#[cfg(feature = "testing")]
+pub use self::properties::*;
+pub use self::rng::{test_rng, XorShiftRng};
@@ -0,0 +1,56 @@
+//! Groth16 parameters and proofs from a fixed toxic waste and fixed blinding
+//! factors, which are the same in every run and quick to create for small
+//! circuits. The toxic waste is known to everyone, so they are good for tests
+//! only. The reference circuits proven this way are the test vectors in
+//! [`circuits::vectors`](crate::circuits::vectors).
+
+use ff::PrimeField;
+use groupy::CurveProjective;
+
+use crate::bls::{Bls12, Engine, Fr};
+use crate::groth16::{
+    create_proof, create_proof_with_opts, generate_parameters, ParameterSource, Parameters, Proof,
+    ProverOptions,
+};
+use crate::{Circuit, SynthesisError};
+
+/// The parameters of the circuit.
+pub fn parameters<C: Circuit<Bls12>>(circuit: C) -> Result<Parameters<Bls12>, SynthesisError> {
+    generate_parameters(
+        circuit,
+        <Bls12 as Engine>::G1::one(),
+        <Bls12 as Engine>::G2::one(),
+        fr("48577"),
+        fr("22580"),
+        fr("53332"),
+        fr("5481"),
+        fr("3673"),
+    )
+}
+
+/// A proof of the circuit, which is the same for the same parameters and
+/// assignment.
+pub fn proof<C, P>(circuit: C, params: P) -> Result<Proof<Bls12>, SynthesisError>
+where
+    C: Circuit<Bls12>,
+    P: ParameterSource<Bls12>,
+{
+    create_proof(circuit, params, fr("27134"), fr("17146"))
+}
+
+/// A proof like [`proof`], created with `opts`, e.g. on the CPU only.
+pub fn proof_with_opts<C, P>(
+    circuit: C,
+    params: P,
+    opts: &ProverOptions,
+) -> Result<Proof<Bls12>, SynthesisError>
+where
+    C: Circuit<Bls12>,
+    P: ParameterSource<Bls12>,
+{
+    create_proof_with_opts(circuit, params, fr("27134"), fr("17146"), opts)
+}
+
+fn fr(s: &str) -> Fr {
+    Fr::from_str(s).unwrap()
+}
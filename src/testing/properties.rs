@@ -0,0 +1,314 @@
+//! Property-based testing of gadgets and constraint systems with [proptest].
+//!
+//! The strategies generate field elements, linear combinations and random
+//! satisfiable circuits. [`assert_gadget`] checks that a gadget is complete,
+//! i.e. that its constraints are satisfied and its result matches a native
+//! implementation, and [`assert_pinned`] that it's sound, i.e. that its
+//! constraints admit no other value for a variable.
+//!
+//! ```
+//! use bellperson::bls::{Bls12, Fr};
+//! use bellperson::gadgets::test::TestConstraintSystem;
+//! use bellperson::gadgets::num::AllocatedNum;
+//! use bellperson::testing::{assert_gadget, assert_pinned, field_element};
+//! use bellperson::ConstraintSystem;
+//! use ff::Field;
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn square(x in field_element::<Fr>(), other in field_element::<Fr>()) {
+//!         let mut cs = assert_gadget(
+//!             &x,
+//!             |cs: &mut TestConstraintSystem<Bls12>, x| {
+//!                 let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(*x))?;
+//!                 Ok(x.square(cs.namespace(|| "square"))?.get_value())
+//!             },
+//!             |x| {
+//!                 let mut square = *x;
+//!                 square.square();
+//!                 Some(square)
+//!             },
+//!         );
+//!         assert_pinned(&mut cs, "square/squared num", other);
+//!     }
+//! }
+//! ```
+//!
+//! [proptest]: https://docs.rs/proptest
+
+use std::fmt;
+
+use ff::{Field, ScalarEngine};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::gadgets::test::TestConstraintSystem;
+use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// Field elements, a quarter of them the edge cases zero, one and minus one.
+pub fn field_element<F: Field>() -> impl Strategy<Value = F> {
+    let mut minus_one = F::one();
+    minus_one.negate();
+
+    prop_oneof![
+        1 => Just(F::zero()),
+        1 => Just(F::one()),
+        1 => Just(minus_one),
+        9 => any::<[u8; 32]>().prop_map(|seed| F::random(&mut StdRng::from_seed(seed))),
+    ]
+}
+
+/// Linear combinations of up to `max_terms` terms, over the first
+/// `num_inputs` inputs, starting with the constant one, and the first
+/// `num_aux` auxiliary variables. A variable can occur in several terms.
+pub fn linear_combination<E: ScalarEngine>(
+    num_inputs: usize,
+    num_aux: usize,
+    max_terms: usize,
+) -> impl Strategy<Value = LinearCombination<E>> {
+    assert!(num_inputs + num_aux > 0, "there are no variables");
+
+    let variable = (0..num_inputs + num_aux).prop_map(move |i| {
+        if i < num_inputs {
+            Variable::new_unchecked(Index::Input(i))
+        } else {
+            Variable::new_unchecked(Index::Aux(i - num_inputs))
+        }
+    });
+
+    vec((variable, field_element::<E::Fr>()), 0..=max_terms).prop_map(|terms| {
+        terms
+            .into_iter()
+            .fold(LinearCombination::zero(), |lc, (var, coeff)| {
+                lc + (coeff, var)
+            })
+    })
+}
+
+/// A satisfiable circuit of random constraints `a * b = c`: `a` and `b` are
+/// linear combinations of the variables before, `c` is a new auxiliary
+/// variable assigned their product.
+///
+/// The variables of the linear combinations are indices into the assignment:
+/// the inputs start with the constant one, and the auxiliary variables are
+/// followed by the products.
+#[derive(Clone)]
+pub struct RandomCircuit<E: ScalarEngine> {
+    pub inputs: Vec<E::Fr>,
+    pub aux: Vec<E::Fr>,
+    pub constraints: Vec<(LinearCombination<E>, LinearCombination<E>)>,
+}
+
+impl<E: ScalarEngine> fmt::Debug for RandomCircuit<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RandomCircuit")
+            .field("inputs", &self.inputs)
+            .field("aux", &self.aux)
+            .field("constraints", &self.constraints)
+            .finish()
+    }
+}
+
+/// Circuits with up to the given numbers of public inputs, auxiliary variables
+/// besides the products, and constraints.
+pub fn random_circuit<E: ScalarEngine>(
+    max_inputs: usize,
+    max_aux: usize,
+    max_constraints: usize,
+) -> impl Strategy<Value = RandomCircuit<E>> {
+    (0..=max_inputs, 1..=max_aux.max(1), 0..=max_constraints).prop_flat_map(
+        |(num_inputs, num_aux, num_constraints)| {
+            let constraints = (0..num_constraints)
+                .map(|i| {
+                    (
+                        linear_combination::<E>(num_inputs + 1, num_aux + i, 4),
+                        linear_combination::<E>(num_inputs + 1, num_aux + i, 4),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            (
+                vec(field_element::<E::Fr>(), num_inputs),
+                vec(field_element::<E::Fr>(), num_aux),
+                constraints,
+            )
+                .prop_map(|(inputs, aux, constraints)| RandomCircuit {
+                    inputs,
+                    aux,
+                    constraints,
+                })
+        },
+    )
+}
+
+impl<E: ScalarEngine> RandomCircuit<E> {
+    /// The whole assignment: the inputs with the constant one first, and the
+    /// auxiliary variables including the products.
+    pub fn assignment(&self) -> (Vec<E::Fr>, Vec<E::Fr>) {
+        let mut inputs = vec![E::Fr::one()];
+        inputs.extend_from_slice(&self.inputs);
+        let mut aux = self.aux.clone();
+
+        for (a, b) in &self.constraints {
+            let mut c = eval(a, &inputs, &aux);
+            c.mul_assign(&eval(b, &inputs, &aux));
+            aux.push(c);
+        }
+
+        (inputs, aux)
+    }
+}
+
+impl<E: ScalarEngine> Circuit<E> for RandomCircuit<E> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let (inputs, aux) = self.assignment();
+
+        // The indices of the linear combinations are mapped to the variables
+        // of `cs`, which may be numbered differently.
+        let mut input_vars = vec![CS::one()];
+        for (i, value) in inputs.iter().enumerate().skip(1) {
+            input_vars.push(cs.alloc_input(|| format!("input {}", i), || Ok(*value))?);
+        }
+        let mut aux_vars = vec![];
+        for (i, value) in aux[..self.aux.len()].iter().enumerate() {
+            aux_vars.push(cs.alloc(|| format!("aux {}", i), || Ok(*value))?);
+        }
+
+        let map = |lc: &LinearCombination<E>, aux_vars: &[Variable]| {
+            lc.iter()
+                .fold(LinearCombination::<E>::zero(), |acc, &(var, coeff)| {
+                    let var = match var.get_unchecked() {
+                        Index::Input(i) => input_vars[i],
+                        Index::Aux(i) => aux_vars[i],
+                    };
+                    acc + (coeff, var)
+                })
+        };
+        for (i, (a, b)) in self.constraints.iter().enumerate() {
+            let a = map(a, &aux_vars);
+            let b = map(b, &aux_vars);
+            let value = aux[self.aux.len() + i];
+            let c = cs.alloc(|| format!("product {}", i), || Ok(value))?;
+            cs.enforce(|| format!("constraint {}", i), |_| a, |_| b, |lc| lc + c);
+            aux_vars.push(c);
+        }
+
+        Ok(())
+    }
+}
+
+fn eval<E: ScalarEngine>(lc: &LinearCombination<E>, inputs: &[E::Fr], aux: &[E::Fr]) -> E::Fr {
+    let mut acc = E::Fr::zero();
+    for &(var, coeff) in lc.iter() {
+        let mut term = match var.get_unchecked() {
+            Index::Input(i) => inputs[i],
+            Index::Aux(i) => aux[i],
+        };
+        term.mul_assign(&coeff);
+        acc.add_assign(&term);
+    }
+
+    acc
+}
+
+/// Synthesizes `gadget` on `input` and checks it against `native`, an
+/// implementation of the same computation outside of a circuit: the
+/// constraints have to be satisfied, and the values the gadget returns have
+/// to be the ones `native` returns. Returns the constraint system for further
+/// checks, e.g. with [`assert_pinned`].
+pub fn assert_gadget<E, T, O, G, N>(input: &T, gadget: G, native: N) -> TestConstraintSystem<E>
+where
+    E: ScalarEngine,
+    O: PartialEq + fmt::Debug,
+    G: FnOnce(&mut TestConstraintSystem<E>, &T) -> Result<O, SynthesisError>,
+    N: FnOnce(&T) -> O,
+{
+    let mut cs = TestConstraintSystem::new();
+    let output = gadget(&mut cs, input).expect("synthesizing the gadget failed");
+    if let Some(explanation) = cs.explain_unsatisfied() {
+        panic!("the constraints are unsatisfied: {}", explanation);
+    }
+    assert_eq!(
+        output,
+        native(input),
+        "the gadget computed something else than the native implementation"
+    );
+
+    cs
+}
+
+/// Checks that the constraints admit no other value for the variable at
+/// `path` than its current one: assigned `other` instead, they have to be
+/// unsatisfied. The variable is reset afterwards.
+pub fn assert_pinned<E: ScalarEngine>(cs: &mut TestConstraintSystem<E>, path: &str, other: E::Fr) {
+    let value = cs.get(path);
+    if other == value {
+        return;
+    }
+
+    cs.set(path, other);
+    let satisfied = cs.is_satisfied();
+    cs.set(path, value);
+    assert!(
+        !satisfied,
+        "the constraints admit {} for `{}` besides {}",
+        other, path, value
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::num::AllocatedNum;
+
+    proptest! {
+        #[test]
+        fn test_mul(a in field_element::<Fr>(), b in field_element::<Fr>(), other in field_element::<Fr>()) {
+            let mut cs = assert_gadget(
+                &(a, b),
+                |cs: &mut TestConstraintSystem<Bls12>, (a, b)| {
+                    let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(*a))?;
+                    let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(*b))?;
+                    Ok(a.mul(cs.namespace(|| "mul"), &b)?.get_value())
+                },
+                |(a, b)| {
+                    let mut product = *a;
+                    product.mul_assign(b);
+                    Some(product)
+                },
+            );
+            assert_pinned(&mut cs, "mul/product num", other);
+        }
+
+        #[test]
+        fn test_xor(a in any::<bool>(), b in any::<bool>()) {
+            let mut cs = assert_gadget(
+                &(a, b),
+                |cs: &mut TestConstraintSystem<Bls12>, (a, b)| {
+                    let a = AllocatedBit::alloc(cs.namespace(|| "a"), Some(*a))?;
+                    let b = AllocatedBit::alloc(cs.namespace(|| "b"), Some(*b))?;
+                    let xor = Boolean::xor(cs.namespace(|| "xor"), &a.into(), &b.into())?;
+                    Ok(xor.get_value())
+                },
+                |(a, b)| Some(a ^ b),
+            );
+            let flipped = if a ^ b { Fr::zero() } else { Fr::one() };
+            assert_pinned(&mut cs, "xor/xor result", flipped);
+        }
+
+        #[test]
+        fn test_random_circuit(circuit in random_circuit::<Bls12>(3, 3, 8)) {
+            let (inputs, _) = circuit.assignment();
+            let mut cs = TestConstraintSystem::new();
+            circuit.synthesize(&mut cs).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert!(cs.verify(&inputs[1..]));
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use rand_core::{impls, le, Error, RngCore, SeedableRng};
+
+use crate::InsecureRng;
+
+/// The xorshift128 generator, which gives the same numbers as the
+/// `XorShiftRng` of the `rand_xorshift` crate for the same seed. It's fast and
+/// reproducible, but not cryptographically secure.
+#[derive(Clone, Debug)]
+pub struct XorShiftRng {
+    x: u32,
+    y: u32,
+    z: u32,
+    w: u32,
+}
+
+impl RngCore for XorShiftRng {
+    fn next_u32(&mut self) -> u32 {
+        let t = self.x ^ (self.x << 11);
+        self.x = self.y;
+        self.y = self.z;
+        self.z = self.w;
+        self.w = self.w ^ (self.w >> 19) ^ (t ^ (t >> 8));
+        self.w
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_u32(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for XorShiftRng {
+    type Seed = [u8; 16];
+
+    fn from_seed(seed: [u8; 16]) -> Self {
+        let mut state = [0u32; 4];
+        le::read_u32_into(&seed, &mut state);
+
+        // The state must not be all zeros.
+        if state.iter().all(|&x| x == 0) {
+            state = [0x0BAD_5EED; 4];
+        }
+
+        XorShiftRng {
+            x: state[0],
+            y: state[1],
+            z: state[2],
+            w: state[3],
+        }
+    }
+}
+
+/// The seeded RNG of the crate's tests, the same in every call. It's marked as
+/// cryptographically secure, so that it can be passed to the provers, which
+/// is only acceptable in tests, see [`InsecureRng`].
+pub fn test_rng() -> InsecureRng<XorShiftRng> {
+    InsecureRng(XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xorshift() {
+        // The same numbers as `rand_xorshift`, also for the all-zero seed.
+        for seed in &[[0; 16], [7; 16]] {
+            let mut rng = XorShiftRng::from_seed(*seed);
+            let mut reference = rand_xorshift::XorShiftRng::from_seed(*seed);
+            for _ in 0..100 {
+                assert_eq!(rng.next_u64(), reference.next_u64());
+            }
+        }
+
+        let mut a = test_rng();
+        let mut b = test_rng();
+        assert_eq!(a.next_u64(), b.next_u64());
+    }
+}
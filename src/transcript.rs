@@ -0,0 +1,132 @@
+//! A Fiat-Shamir transcript for protocols built on the crate, which turns
+//! everything a prover sent into challenges that the verifier derives the
+//! same way.
+//!
+//! Every transcript starts with the name of its protocol, and every message
+//! is appended with a label, both length-prefixed, so that neither the same
+//! messages in different protocols nor different messages that happen to
+//! concatenate to the same bytes give the same challenges. Points are
+//! appended in their compressed encoding and scalars in their canonical
+//! big-endian encoding.
+//!
+//! ```
+//! use bellperson::bls::{Bls12, Engine, Fr};
+//! use bellperson::transcript::Transcript;
+//! use groupy::CurveAffine;
+//!
+//! let mut transcript = Transcript::new(b"example protocol");
+//! transcript.append_point(b"commitment", &<Bls12 as Engine>::G1Affine::one());
+//! let challenge: Fr = transcript.challenge_scalar(b"challenge");
+//! ```
+
+use blake2s_simd::{Params, State};
+use byteorder::{BigEndian, ByteOrder};
+use ff::{Field, PrimeField, PrimeFieldRepr};
+use groupy::CurveAffine;
+
+use crate::poly::from_u64;
+
+/// Personalization of the BLAKE2s hash of the transcript.
+const TRANSCRIPT_PERSONALIZATION: &[u8; 8] = b"BellTrns";
+
+/// Hashes the messages appended so far. Every challenge is appended as well,
+/// so that consecutive challenges differ.
+#[derive(Clone)]
+pub struct Transcript {
+    state: State,
+}
+
+impl Transcript {
+    /// Starts a transcript of the protocol called `protocol`.
+    pub fn new(protocol: &[u8]) -> Transcript {
+        let mut transcript = Transcript {
+            state: Params::new()
+                .hash_length(32)
+                .personal(TRANSCRIPT_PERSONALIZATION)
+                .to_state(),
+        };
+        transcript.append_bytes(protocol);
+
+        transcript
+    }
+
+    pub fn append_message(&mut self, label: &[u8], message: &[u8]) {
+        self.append_bytes(label);
+        self.append_bytes(message);
+    }
+
+    pub fn append_point<G: CurveAffine>(&mut self, label: &[u8], point: &G) {
+        self.append_message(label, point.into_compressed().as_ref());
+    }
+
+    pub fn append_scalar<F: PrimeField>(&mut self, label: &[u8], scalar: &F) {
+        let mut bytes = vec![];
+        scalar
+            .into_repr()
+            .write_be(&mut bytes)
+            .expect("writing to a vector never fails");
+        self.append_message(label, &bytes);
+    }
+
+    /// Derives a challenge from everything appended so far. It's reduced from
+    /// 512 bits, so it's uniform in the field up to a negligible bias.
+    pub fn challenge_scalar<F: PrimeField>(&mut self, label: &[u8]) -> F {
+        self.append_bytes(label);
+        let high = self.state.clone().update(&[0]).finalize();
+        let low = self.state.clone().update(&[1]).finalize();
+        self.state.update(high.as_bytes());
+        self.state.update(low.as_bytes());
+
+        // Interpret the hashes as a big-endian integer, reduced modulo the
+        // characteristic of the field.
+        let mut shift = from_u64::<F>(1 << 32);
+        shift.square();
+        let mut challenge = F::zero();
+        for word in high.as_bytes().chunks(8).chain(low.as_bytes().chunks(8)) {
+            challenge.mul_assign(&shift);
+            challenge.add_assign(&from_u64(BigEndian::read_u64(word)));
+        }
+
+        challenge
+    }
+
+    fn append_bytes(&mut self, bytes: &[u8]) {
+        let mut len = [0; 8];
+        BigEndian::write_u64(&mut len, bytes.len() as u64);
+        self.state.update(&len);
+        self.state.update(bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::{Bls12, Engine, Fr};
+
+    #[test]
+    fn test_transcript() {
+        let challenge = |protocol: &[u8], messages: &[(&[u8], &[u8])]| -> Fr {
+            let mut transcript = Transcript::new(protocol);
+            for (label, message) in messages {
+                transcript.append_message(label, message);
+            }
+            transcript.challenge_scalar(b"c")
+        };
+
+        // The same transcript gives the same challenge.
+        let expected = challenge(b"p", &[(b"a", b"bc")]);
+        assert_eq!(challenge(b"p", &[(b"a", b"bc")]), expected);
+        // Other protocols, labels or splits of the same bytes don't.
+        assert!(challenge(b"q", &[(b"a", b"bc")]) != expected);
+        assert!(challenge(b"p", &[(b"ab", b"c")]) != expected);
+        assert!(challenge(b"p", &[(b"a", b"b"), (b"", b"c")]) != expected);
+
+        // Consecutive challenges differ.
+        let mut transcript = Transcript::new(b"p");
+        transcript.append_point(b"g", &<Bls12 as Engine>::G1Affine::one());
+        transcript.append_scalar(b"s", &Fr::one());
+        let first: Fr = transcript.challenge_scalar(b"c");
+        let second: Fr = transcript.challenge_scalar(b"c");
+        assert!(first != second);
+    }
+}
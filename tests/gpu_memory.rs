@@ -0,0 +1,119 @@
+//! Checks how much memory the FFTs of the prover take on the GPU. The
+//! allocations of the whole process are counted, so this is a test binary of
+//! its own.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bellperson::bls::{Bls12, Fr};
+use bellperson::groth16::{
+    create_random_proof_with_opts, estimate_resources, generate_random_parameters,
+    prepare_verifying_key, verify_proof, ProverOptions, ProvingStage,
+};
+use bellperson::{Circuit, ConstraintSystem, SynthesisError};
+use ff::{Field, PrimeField};
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, keeping track of the bytes allocated at the moment
+/// and of their peak.
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            let mut peak = PEAK.load(Ordering::SeqCst);
+            while allocated > peak {
+                match PEAK.compare_exchange(peak, allocated, Ordering::SeqCst, Ordering::SeqCst) {
+                    Ok(_) => break,
+                    Err(current) => peak = current,
+                }
+            }
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Squares a number again and again.
+struct Squarings(usize);
+
+impl Circuit<Bls12> for Squarings {
+    fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mut value = Fr::from_str("3").unwrap();
+        let mut var = cs.alloc(|| "x0", || Ok(value))?;
+        for i in 0..self.0 {
+            value.square();
+            let next = cs.alloc(|| format!("x{}", i + 1), || Ok(value))?;
+            cs.enforce(
+                || format!("square {}", i),
+                |lc| lc + var,
+                |lc| lc + var,
+                |lc| lc + next,
+            );
+            var = next;
+        }
+        let out = cs.alloc_input(|| "out", || Ok(value))?;
+        cs.enforce(|| "out", |lc| lc + var, |lc| lc + CS::one(), |lc| lc + out);
+        Ok(())
+    }
+}
+
+#[test]
+fn gpu_fft_peak_memory() {
+    const SQUARINGS: usize = 1 << 16;
+    let mut out = Fr::from_str("3").unwrap();
+    for _ in 0..SQUARINGS {
+        out.square();
+    }
+
+    let rng = &mut rand::thread_rng();
+    let params = generate_random_parameters(Squarings(SQUARINGS), rng).unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+    let domain_bytes = estimate_resources(&params).fft_size * mem::size_of::<Fr>();
+
+    // The peak from the end of synthesis to the last FFT of an evaluation.
+    let synthesized = Cell::new(0);
+    let fft_peak = Cell::new(None);
+    let sink = |stage: ProvingStage, _: f64| match stage {
+        ProvingStage::Synthesis => {
+            let allocated = ALLOCATED.load(Ordering::SeqCst);
+            PEAK.store(allocated, Ordering::SeqCst);
+            synthesized.set(allocated);
+        }
+        ProvingStage::Fft("coset fft c") => {
+            fft_peak.set(Some(PEAK.load(Ordering::SeqCst) - synthesized.get()));
+        }
+        _ => {}
+    };
+    let opts = ProverOptions {
+        gpu: true,
+        cpu_fallback: false,
+        progress: Some(&sink),
+        ..Default::default()
+    };
+    let proof = create_random_proof_with_opts(Squarings(SQUARINGS), &params, rng, &opts).unwrap();
+    assert!(verify_proof(&pvk, &proof, &[out]).unwrap());
+
+    // The evaluations are already in memory after synthesis. Expanding them
+    // into their domains takes at most two domains more, as no more than two
+    // of them are expanded at the same time.
+    let fft_peak = fft_peak.get().expect("the FFTs weren't reported");
+    println!(
+        "The FFTs took {} bytes on top of synthesis, a domain has {} bytes.",
+        fft_peak, domain_bytes
+    );
+    assert!(fft_peak <= 2 * domain_bytes);
+}
@@ -5,11 +5,11 @@ use rand::thread_rng;
 use std::time::{Duration, Instant};
 
 // Bring in some tools for using pairing-friendly curves
+use bellperson::bls::Engine;
 use ff::{Field, ScalarEngine};
-use paired::Engine;
 
 // We're going to use the BLS12-381 pairing-friendly elliptic curve.
-use paired::bls12_381::Bls12;
+use bellperson::bls::Bls12;
 
 // We'll use these interfaces to construct our circuit.
 use bellperson::{Circuit, ConstraintSystem, SynthesisError};